@@ -0,0 +1,19 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct AttributePolicy {
+    pub preserve_data_attributes: bool,
+    pub allowed_classes: Vec<String>,
+}
+
+impl From<AttributePolicy> for wysiwyg::AttributePolicy {
+    fn from(policy: AttributePolicy) -> Self {
+        Self {
+            preserve_data_attributes: policy.preserve_data_attributes,
+            allowed_classes: policy.allowed_classes,
+        }
+    }
+}
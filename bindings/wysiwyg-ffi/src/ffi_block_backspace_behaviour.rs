@@ -0,0 +1,23 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum BlockBackspaceBehaviour {
+    MergeIntoPrevious,
+    ExitBlock,
+    NoOp,
+}
+
+impl From<BlockBackspaceBehaviour> for wysiwyg::BlockBackspaceBehaviour {
+    fn from(behaviour: BlockBackspaceBehaviour) -> Self {
+        match behaviour {
+            BlockBackspaceBehaviour::MergeIntoPrevious => {
+                Self::MergeIntoPrevious
+            }
+            BlockBackspaceBehaviour::ExitBlock => Self::ExitBlock,
+            BlockBackspaceBehaviour::NoOp => Self::NoOp,
+        }
+    }
+}
@@ -4,22 +4,36 @@
 // Please see LICENSE in the repository root for full details.
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::vec;
 
-use widestring::Utf16String;
-
+use crate::ffi_attribute_policy::AttributePolicy;
+use crate::ffi_block_backspace_behaviour::BlockBackspaceBehaviour;
+use crate::ffi_composer_model_config::ComposerModelConfig;
 use crate::ffi_composer_state::ComposerState;
 use crate::ffi_composer_update::ComposerUpdate;
+use crate::ffi_content_length::ContentLength;
+use crate::ffi_decoration::{Decoration, DecorationKind};
 use crate::ffi_dom_creation_error::DomCreationError;
+use crate::ffi_formatting_tags::FormattingTags;
+use crate::ffi_history::HistoryEntry;
 use crate::ffi_link_actions::LinkAction;
+use crate::ffi_markdown_escaping::MarkdownEscaping;
+use crate::ffi_markdown_mentions::MarkdownMentions;
 use crate::ffi_mentions_state::MentionsState;
+use crate::ffi_pattern_key::PatternKey;
+use crate::ffi_placeholder_policy::PlaceholderPolicy;
+use crate::ffi_range_query::RangeQuery;
+use crate::ffi_selection_granularity::{Direction, Granularity};
+use crate::ffi_whitespace_policy::WhitespacePolicy;
+use crate::ffi_word::Word;
 use crate::into_ffi::IntoFfi;
+use crate::model_string::ModelString;
 use crate::{ActionState, ComposerAction, SuggestionPattern};
 
 #[derive(Default, uniffi::Object)]
 pub struct ComposerModel {
-    inner: Mutex<wysiwyg::ComposerModel<Utf16String>>,
+    inner: Mutex<wysiwyg::ComposerModel<ModelString>>,
 }
 
 impl ComposerModel {
@@ -28,6 +42,24 @@ impl ComposerModel {
             inner: Mutex::new(wysiwyg::ComposerModel::new()),
         }
     }
+
+    pub fn with_config(config: ComposerModelConfig) -> Self {
+        Self {
+            inner: Mutex::new(wysiwyg::ComposerModel::with_config(
+                config.into(),
+            )),
+        }
+    }
+
+    /// See [wysiwyg::ComposerModel::from_example_format].
+    #[cfg(all(feature = "example-format", not(feature = "utf8")))]
+    pub fn from_example_format(text: &str) -> Self {
+        Self {
+            inner: Mutex::new(wysiwyg::ComposerModel::from_example_format(
+                text,
+            )),
+        }
+    }
 }
 
 #[uniffi::export]
@@ -36,7 +68,7 @@ impl ComposerModel {
         self: &Arc<Self>,
         html: String,
     ) -> Result<Arc<ComposerUpdate>, DomCreationError> {
-        let html = Utf16String::from_str(&html);
+        let html = ModelString::from(html.as_str());
         let update = self.inner.lock().unwrap().set_content_from_html(&html)?;
         Ok(Arc::new(ComposerUpdate::from(update)))
     }
@@ -45,7 +77,7 @@ impl ComposerModel {
         self: &Arc<Self>,
         markdown: String,
     ) -> Result<Arc<ComposerUpdate>, DomCreationError> {
-        let markdown = Utf16String::from_str(&markdown);
+        let markdown = ModelString::from(markdown.as_str());
         let update = self
             .inner
             .lock()
@@ -54,6 +86,20 @@ impl ComposerModel {
         Ok(Arc::new(ComposerUpdate::from(update)))
     }
 
+    pub fn set_content_from_event_content(
+        self: &Arc<Self>,
+        json: String,
+    ) -> Result<Arc<ComposerUpdate>, DomCreationError> {
+        let content: wysiwyg::MessageEventContent = serde_json::from_str(&json)
+            .map_err(|_| DomCreationError::EventContentParseError)?;
+        let update = self
+            .inner
+            .lock()
+            .unwrap()
+            .set_content_from_event_content(&content)?;
+        Ok(Arc::new(ComposerUpdate::from(update)))
+    }
+
     pub fn set_custom_suggestion_patterns(
         self: &Arc<Self>,
         custom_suggestion_patterns: Vec<String>,
@@ -64,6 +110,157 @@ impl ComposerModel {
             .set_custom_suggestion_patterns(custom_suggestion_patterns)
     }
 
+    /// Make `trigger` start a suggestion reported with `key`, or stop it
+    /// starting one if `key` is None. Does nothing unless `trigger` is a
+    /// single character.
+    pub fn set_suggestion_trigger(
+        self: &Arc<Self>,
+        trigger: String,
+        key: Option<PatternKey>,
+    ) {
+        let mut chars = trigger.chars();
+        if let (Some(trigger), None) = (chars.next(), chars.next()) {
+            self.inner
+                .lock()
+                .unwrap()
+                .set_suggestion_trigger(trigger, key.map(Into::into))
+        }
+    }
+
+    pub fn set_max_parse_depth(self: &Arc<Self>, max_parse_depth: u32) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_max_parse_depth(max_parse_depth as usize)
+    }
+
+    pub fn set_block_backspace_behaviour(
+        self: &Arc<Self>,
+        behaviour: BlockBackspaceBehaviour,
+    ) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_block_backspace_behaviour(behaviour.into())
+    }
+
+    pub fn set_attribute_policy(self: &Arc<Self>, policy: AttributePolicy) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_attribute_policy(policy.into())
+    }
+
+    pub fn set_formatting_tags(self: &Arc<Self>, tags: FormattingTags) {
+        self.inner.lock().unwrap().set_formatting_tags(tags.into())
+    }
+
+    pub fn set_strip_message_styles(self: &Arc<Self>, strip: bool) {
+        self.inner.lock().unwrap().set_strip_message_styles(strip)
+    }
+
+    pub fn set_isolate_message_bidi(self: &Arc<Self>, isolate: bool) {
+        self.inner.lock().unwrap().set_isolate_message_bidi(isolate)
+    }
+
+    pub fn set_whitespace_policy(self: &Arc<Self>, policy: WhitespacePolicy) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_whitespace_policy(policy.into())
+    }
+
+    pub fn set_placeholder_policy(self: &Arc<Self>, policy: PlaceholderPolicy) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_placeholder_policy(policy.into())
+    }
+
+    pub fn get_config(self: &Arc<Self>) -> ComposerModelConfig {
+        ComposerModelConfig::from(self.inner.lock().unwrap().config())
+    }
+
+    pub fn set_config(self: &Arc<Self>, config: ComposerModelConfig) {
+        self.inner.lock().unwrap().set_config(config.into())
+    }
+
+    pub fn get_parse_warnings(self: &Arc<Self>) -> Vec<String> {
+        self.inner.lock().unwrap().parse_warnings().to_vec()
+    }
+
+    pub fn set_at_room_detection(self: &Arc<Self>, detect_at_room: bool) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_at_room_detection(detect_at_room)
+    }
+
+    pub fn set_at_room_detection_in_quotes(
+        self: &Arc<Self>,
+        detect_at_room_in_quotes: bool,
+    ) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_at_room_detection_in_quotes(detect_at_room_in_quotes)
+    }
+
+    pub fn set_can_notify_room(self: &Arc<Self>, can_notify_room: bool) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_can_notify_room(can_notify_room)
+    }
+
+    pub fn set_markdown_escaping(self: &Arc<Self>, escaping: MarkdownEscaping) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_markdown_escaping(escaping.into())
+    }
+
+    pub fn set_markdown_mentions(self: &Arc<Self>, mentions: MarkdownMentions) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_markdown_mentions(mentions.into())
+    }
+
+    pub fn remove_decoration(self: &Arc<Self>, id: u64) -> bool {
+        self.inner.lock().unwrap().remove_decoration(id)
+    }
+
+    pub fn remove_decorations_of_kind(self: &Arc<Self>, kind: DecorationKind) {
+        self.inner
+            .lock()
+            .unwrap()
+            .remove_decorations_of_kind(kind.into())
+    }
+
+    /// How long the content is, in total and in each top-level block, both
+    /// in code units, i.e. UTF-16 code units or with the `utf8` feature
+    /// bytes, and in graphemes.
+    pub fn content_length(self: &Arc<Self>) -> ContentLength {
+        self.inner.lock().unwrap().content_length().into()
+    }
+
+    pub fn get_word_at_cursor(self: &Arc<Self>) -> Option<Word> {
+        self.inner
+            .lock()
+            .unwrap()
+            .get_word_at_cursor()
+            .map(Word::from)
+    }
+
+    pub fn get_content_as_html_with_decorations(self: &Arc<Self>) -> String {
+        self.inner
+            .lock()
+            .unwrap()
+            .get_content_as_html_with_decorations()
+            .to_string()
+    }
+
     pub fn get_content_as_html(self: &Arc<Self>) -> String {
         self.inner.lock().unwrap().get_content_as_html().to_string()
     }
@@ -104,20 +301,22 @@ impl ComposerModel {
         Arc::new(ComposerUpdate::from(self.inner.lock().unwrap().clear()))
     }
 
-    pub fn select(
+    pub fn extend_selection(
         self: &Arc<Self>,
-        start_utf16_codeunit: u32,
-        end_utf16_codeunit: u32,
+        direction: Direction,
+        granularity: Granularity,
     ) -> Arc<ComposerUpdate> {
-        let start = wysiwyg::Location::from(
-            usize::try_from(start_utf16_codeunit).unwrap(),
-        );
-        let end = wysiwyg::Location::from(
-            usize::try_from(end_utf16_codeunit).unwrap(),
-        );
+        Arc::new(ComposerUpdate::from(
+            self.inner
+                .lock()
+                .unwrap()
+                .extend_selection(direction.into(), granularity.into()),
+        ))
+    }
 
+    pub fn clear_composing_region(self: &Arc<Self>) -> Arc<ComposerUpdate> {
         Arc::new(ComposerUpdate::from(
-            self.inner.lock().unwrap().select(start, end),
+            self.inner.lock().unwrap().clear_composing_region(),
         ))
     }
 
@@ -129,7 +328,16 @@ impl ComposerModel {
             self.inner
                 .lock()
                 .unwrap()
-                .replace_text(Utf16String::from_str(&new_text)),
+                .replace_text(ModelString::from(new_text.as_str())),
+        ))
+    }
+
+    pub fn paste_text(self: &Arc<Self>, text: String) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner
+                .lock()
+                .unwrap()
+                .paste_text(ModelString::from(text.as_str())),
         ))
     }
 
@@ -143,7 +351,7 @@ impl ComposerModel {
         let end = usize::try_from(end).unwrap();
         Arc::new(ComposerUpdate::from(
             self.inner.lock().unwrap().replace_text_in(
-                Utf16String::from_str(&new_text),
+                ModelString::from(new_text.as_str()),
                 start,
                 end,
             ),
@@ -158,7 +366,7 @@ impl ComposerModel {
     ) -> Arc<ComposerUpdate> {
         Arc::new(ComposerUpdate::from(
             self.inner.lock().unwrap().replace_text_suggestion(
-                Utf16String::from_str(&new_text),
+                ModelString::from(new_text.as_str()),
                 wysiwyg::SuggestionPattern::from(suggestion),
                 append_space,
             ),
@@ -213,6 +421,16 @@ impl ComposerModel {
         ))
     }
 
+    pub fn apply_style(self: &Arc<Self>, name: String) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().apply_style(&name),
+        ))
+    }
+
+    pub fn style_is_applied(self: &Arc<Self>, name: String) -> bool {
+        self.inner.lock().unwrap().style_is_applied(&name)
+    }
+
     pub fn code_block(self: &Arc<Self>) -> Arc<ComposerUpdate> {
         Arc::new(ComposerUpdate::from(
             self.inner.lock().unwrap().code_block(),
@@ -223,6 +441,25 @@ impl ComposerModel {
         Arc::new(ComposerUpdate::from(self.inner.lock().unwrap().quote()))
     }
 
+    /// Move the block at the handle, given as the index of each node on
+    /// the path to it from the root, to be child `target_index` of its
+    /// parent.
+    pub fn move_block(
+        self: &Arc<Self>,
+        handle: Vec<u32>,
+        target_index: u32,
+    ) -> Arc<ComposerUpdate> {
+        let handle = wysiwyg::DomHandle::from_raw(
+            handle.into_iter().map(|i| i as usize).collect(),
+        );
+        Arc::new(ComposerUpdate::from(
+            self.inner
+                .lock()
+                .unwrap()
+                .move_block(&handle, target_index as usize),
+        ))
+    }
+
     pub fn ordered_list(self: &Arc<Self>) -> Arc<ComposerUpdate> {
         Arc::new(ComposerUpdate::from(
             self.inner.lock().unwrap().ordered_list(),
@@ -243,18 +480,131 @@ impl ComposerModel {
         Arc::new(ComposerUpdate::from(self.inner.lock().unwrap().redo()))
     }
 
+    pub fn undo_steps(self: &Arc<Self>, steps: u32) -> Arc<ComposerUpdate> {
+        let steps = usize::try_from(steps).unwrap();
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().undo_steps(steps),
+        ))
+    }
+
+    pub fn redo_steps(self: &Arc<Self>, steps: u32) -> Arc<ComposerUpdate> {
+        let steps = usize::try_from(steps).unwrap();
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().redo_steps(steps),
+        ))
+    }
+
+    pub fn history(self: &Arc<Self>) -> Vec<HistoryEntry> {
+        self.inner
+            .lock()
+            .unwrap()
+            .history()
+            .into_iter()
+            .map(HistoryEntry::from)
+            .collect()
+    }
+
+    pub fn create_snapshot(self: &Arc<Self>) -> u64 {
+        self.inner.lock().unwrap().create_snapshot()
+    }
+
+    pub fn restore_snapshot(self: &Arc<Self>, id: u64) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().restore_snapshot(id),
+        ))
+    }
+
+    pub fn discard_snapshot(self: &Arc<Self>, id: u64) -> bool {
+        self.inner.lock().unwrap().discard_snapshot(id)
+    }
+
+    /// Put back the content as it was before the operation which left it
+    /// invalid, from the latest valid undo step. Can be called after catching a panic in another method, which
+    /// leaves the model's lock poisoned, to recover the user's draft.
+    pub fn reset_to_last_good(self: &Arc<Self>) -> Arc<ComposerUpdate> {
+        let mut inner =
+            self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let update = inner.reset_to_last_good();
+        drop(inner);
+        self.inner.clear_poison();
+        Arc::new(ComposerUpdate::from(update))
+    }
+
+    pub fn insert_template(
+        self: &Arc<Self>,
+        html: String,
+    ) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner
+                .lock()
+                .unwrap()
+                .insert_template(ModelString::from(html.as_str())),
+        ))
+    }
+
+    pub fn next_placeholder(self: &Arc<Self>) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().next_placeholder(),
+        ))
+    }
+
+    pub fn fill_placeholder(
+        self: &Arc<Self>,
+        text: String,
+    ) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner
+                .lock()
+                .unwrap()
+                .fill_placeholder(ModelString::from(text.as_str())),
+        ))
+    }
+
+    pub fn operation_log_json(self: &Arc<Self>) -> String {
+        serde_json::to_string(self.inner.lock().unwrap().operation_log())
+            .unwrap()
+    }
+
+    pub fn clear_operation_log(self: &Arc<Self>) {
+        self.inner.lock().unwrap().clear_operation_log()
+    }
+
+    pub fn apply_operations_json(
+        self: &Arc<Self>,
+        json: String,
+    ) -> Result<Arc<ComposerUpdate>, DomCreationError> {
+        let operations = serde_json::from_str(&json)
+            .map_err(|_| DomCreationError::OperationsParseError)?;
+        let update = self.inner.lock().unwrap().apply_operations(operations)?;
+        Ok(Arc::new(ComposerUpdate::from(update)))
+    }
+
+    pub fn run_command_sequence_json(
+        self: &Arc<Self>,
+        json: String,
+    ) -> Result<Arc<ComposerUpdate>, DomCreationError> {
+        let operations = serde_json::from_str(&json)
+            .map_err(|_| DomCreationError::OperationsParseError)?;
+        let update = self
+            .inner
+            .lock()
+            .unwrap()
+            .run_command_sequence(operations)?;
+        Ok(Arc::new(ComposerUpdate::from(update)))
+    }
+
     pub fn set_link(
         self: &Arc<Self>,
         url: String,
         attributes: Vec<Attribute>,
     ) -> Arc<ComposerUpdate> {
-        let url = Utf16String::from_str(&url);
+        let url = ModelString::from(url.as_str());
         let attrs = attributes
             .iter()
             .map(|attr| {
                 (
-                    Utf16String::from_str(&attr.key),
-                    Utf16String::from_str(&attr.value),
+                    ModelString::from(attr.key.as_str()),
+                    ModelString::from(attr.value.as_str()),
                 )
             })
             .collect();
@@ -269,14 +619,14 @@ impl ComposerModel {
         text: String,
         attributes: Vec<Attribute>,
     ) -> Arc<ComposerUpdate> {
-        let url = Utf16String::from_str(&url);
-        let text = Utf16String::from_str(&html_escape::encode_safe(&text));
+        let url = ModelString::from(url.as_str());
+        let text = ModelString::from(&*html_escape::encode_safe(&text));
         let attrs = attributes
             .iter()
             .map(|attr| {
                 (
-                    Utf16String::from_str(&attr.key),
-                    Utf16String::from_str(&attr.value),
+                    ModelString::from(attr.key.as_str()),
+                    ModelString::from(attr.value.as_str()),
                 )
             })
             .collect();
@@ -302,14 +652,53 @@ impl ComposerModel {
         text: String,
         _attributes: Vec<Attribute>, // TODO remove attributes
     ) -> Arc<ComposerUpdate> {
-        let url = Utf16String::from_str(&url);
-        let text = Utf16String::from_str(&html_escape::encode_safe(&text));
+        let url = ModelString::from(url.as_str());
+        let text = ModelString::from(&*html_escape::encode_safe(&text));
         let attrs = vec![];
         Arc::new(ComposerUpdate::from(
             self.inner.lock().unwrap().insert_mention(url, text, attrs),
         ))
     }
 
+    /// Changes the text shown for the mentions between start and end, e.g.
+    /// when the display name of a mentioned user changes
+    pub fn update_mention_text(
+        self: &Arc<Self>,
+        start: u32,
+        end: u32,
+        text: String,
+    ) -> Arc<ComposerUpdate> {
+        let start = wysiwyg::Location::from(usize::try_from(start).unwrap());
+        let end = wysiwyg::Location::from(usize::try_from(end).unwrap());
+        let text = ModelString::from(&*html_escape::encode_safe(&text));
+        Arc::new(ComposerUpdate::from(
+            self.inner
+                .lock()
+                .unwrap()
+                .update_mention_text(start, end, text),
+        ))
+    }
+
+    /// Turns each of the given texts found in the content into a mention of
+    /// the URI it maps to, e.g. when restoring a draft saved as plain text
+    pub fn convert_plain_mentions(
+        self: &Arc<Self>,
+        mentions: HashMap<String, String>,
+    ) -> Arc<ComposerUpdate> {
+        let mentions = mentions
+            .iter()
+            .map(|(text, url)| {
+                (
+                    ModelString::from(text.as_str()),
+                    ModelString::from(url.as_str()),
+                )
+            })
+            .collect();
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().convert_plain_mentions(mentions),
+        ))
+    }
+
     /// Creates an at-room mention node and inserts it into the composer, replacing the
     /// text content defined by the suggestion
     pub fn insert_at_room_mention_at_suggestion(
@@ -335,8 +724,8 @@ impl ComposerModel {
         suggestion: SuggestionPattern,
         _attributes: Vec<Attribute>, // TODO remove attributes
     ) -> Arc<ComposerUpdate> {
-        let url = Utf16String::from_str(&url);
-        let text = Utf16String::from_str(&html_escape::encode_safe(&text));
+        let url = ModelString::from(url.as_str());
+        let text = ModelString::from(&*html_escape::encode_safe(&text));
         let suggestion = wysiwyg::SuggestionPattern::from(suggestion);
         let attrs = vec![];
         Arc::new(ComposerUpdate::from(
@@ -361,12 +750,41 @@ impl ComposerModel {
         Arc::new(ComposerUpdate::from(self.inner.lock().unwrap().unindent()))
     }
 
-    pub fn to_example_format(self: &Arc<Self>) -> String {
-        self.inner.lock().unwrap().to_example_format()
+    pub fn split_list_at_cursor(self: &Arc<Self>) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().split_list_at_cursor(),
+        ))
+    }
+
+    pub fn join_with_previous_list(self: &Arc<Self>) -> Arc<ComposerUpdate> {
+        Arc::new(ComposerUpdate::from(
+            self.inner.lock().unwrap().join_with_previous_list(),
+        ))
+    }
+
+    /// The structure of the content as a tree, leaving out the nodes more
+    /// than `max_depth` levels below the root if it is given.
+    pub fn to_tree(self: &Arc<Self>, max_depth: Option<u32>) -> String {
+        let inner = self.inner.lock().unwrap();
+        match max_depth {
+            Some(depth) => inner.to_tree_with_max_depth(depth as usize),
+            None => inner.to_tree(),
+        }
+        .to_string()
+    }
+
+    /// The tree with every text node replaced by its length and no URLs,
+    /// followed by the selection, to attach to bug reports.
+    pub fn to_tree_redacted(self: &Arc<Self>) -> String {
+        self.inner.lock().unwrap().to_tree_redacted().to_string()
     }
 
-    pub fn to_tree(self: &Arc<Self>) -> String {
-        self.inner.lock().unwrap().to_tree().to_string()
+    /// A hash of the content, ignoring the selection, which is the same
+    /// whenever the content is, e.g. to skip saving unchanged drafts. It
+    /// can only be compared with hashes from the same build of the app, so
+    /// it must not be saved along with the draft.
+    pub fn content_hash(self: &Arc<Self>) -> u64 {
+        self.inner.lock().unwrap().content_hash()
     }
 
     pub fn get_current_dom_state(self: &Arc<Self>) -> ComposerState {
@@ -399,6 +817,198 @@ impl ComposerModel {
     }
 }
 
+/// Exports the methods which take offsets, with parameters named for the
+/// unit of the offsets, see [crate::model_string].
+macro_rules! export_offset_methods {
+    ($start:ident, $end:ident, $offset:ident, $length:ident) => {
+        #[uniffi::export]
+        impl ComposerModel {
+            pub fn add_decoration(
+                self: &Arc<Self>,
+                kind: DecorationKind,
+                $start: u32,
+                $end: u32,
+            ) -> u64 {
+                let start =
+                    wysiwyg::Location::from(usize::try_from($start).unwrap());
+                let end =
+                    wysiwyg::Location::from(usize::try_from($end).unwrap());
+                self.inner.lock().unwrap().add_decoration(
+                    kind.into(),
+                    start,
+                    end,
+                )
+            }
+
+            pub fn decorations_in(
+                self: &Arc<Self>,
+                $start: u32,
+                $end: u32,
+            ) -> Vec<Decoration> {
+                let start =
+                    wysiwyg::Location::from(usize::try_from($start).unwrap());
+                let end =
+                    wysiwyg::Location::from(usize::try_from($end).unwrap());
+                self.inner
+                    .lock()
+                    .unwrap()
+                    .decorations_in(start, end)
+                    .into_iter()
+                    .map(Decoration::from)
+                    .collect()
+            }
+
+            pub fn words_in_range(
+                self: &Arc<Self>,
+                $start: u32,
+                $end: u32,
+            ) -> Vec<Word> {
+                let start =
+                    wysiwyg::Location::from(usize::try_from($start).unwrap());
+                let end =
+                    wysiwyg::Location::from(usize::try_from($end).unwrap());
+                self.inner
+                    .lock()
+                    .unwrap()
+                    .words_in_range(start, end)
+                    .map(Word::from)
+                    .collect()
+            }
+
+            /// What the content between the given offsets is made of, without
+            /// reading the whole document.
+            pub fn query_range(
+                self: &Arc<Self>,
+                $start: u32,
+                $end: u32,
+            ) -> RangeQuery {
+                let start =
+                    wysiwyg::Location::from(usize::try_from($start).unwrap());
+                let end =
+                    wysiwyg::Location::from(usize::try_from($end).unwrap());
+                self.inner.lock().unwrap().query_range(start, end).into()
+            }
+
+            pub fn select(
+                self: &Arc<Self>,
+                $start: u32,
+                $end: u32,
+            ) -> Arc<ComposerUpdate> {
+                let start =
+                    wysiwyg::Location::from(usize::try_from($start).unwrap());
+                let end =
+                    wysiwyg::Location::from(usize::try_from($end).unwrap());
+
+                Arc::new(ComposerUpdate::from(
+                    self.inner.lock().unwrap().select(start, end),
+                ))
+            }
+
+            pub fn set_composing_region(
+                self: &Arc<Self>,
+                $start: u32,
+                $end: u32,
+            ) -> Arc<ComposerUpdate> {
+                let start =
+                    wysiwyg::Location::from(usize::try_from($start).unwrap());
+                let end =
+                    wysiwyg::Location::from(usize::try_from($end).unwrap());
+                Arc::new(ComposerUpdate::from(
+                    self.inner.lock().unwrap().set_composing_region(start, end),
+                ))
+            }
+
+            pub fn previous_grapheme_boundary(
+                self: &Arc<Self>,
+                $offset: u32,
+            ) -> u32 {
+                let location =
+                    wysiwyg::Location::from(usize::try_from($offset).unwrap());
+                let boundary: usize = self
+                    .inner
+                    .lock()
+                    .unwrap()
+                    .previous_grapheme_boundary(location)
+                    .into();
+                u32::try_from(boundary).unwrap()
+            }
+
+            pub fn next_grapheme_boundary(
+                self: &Arc<Self>,
+                $offset: u32,
+            ) -> u32 {
+                let location =
+                    wysiwyg::Location::from(usize::try_from($offset).unwrap());
+                let boundary: usize = self
+                    .inner
+                    .lock()
+                    .unwrap()
+                    .next_grapheme_boundary(location)
+                    .into();
+                u32::try_from(boundary).unwrap()
+            }
+
+            pub fn truncate_to(
+                self: &Arc<Self>,
+                $length: u32,
+            ) -> Arc<ComposerUpdate> {
+                let length = usize::try_from($length).unwrap();
+                Arc::new(ComposerUpdate::from(
+                    self.inner.lock().unwrap().truncate_to(length),
+                ))
+            }
+
+            pub fn remove_links_in(
+                self: &Arc<Self>,
+                $start: u32,
+                $end: u32,
+            ) -> Arc<ComposerUpdate> {
+                let start =
+                    wysiwyg::Location::from(usize::try_from($start).unwrap());
+                let end =
+                    wysiwyg::Location::from(usize::try_from($end).unwrap());
+                Arc::new(ComposerUpdate::from(
+                    self.inner.lock().unwrap().remove_links_in(start, end),
+                ))
+            }
+
+            pub fn remove_link_at(
+                self: &Arc<Self>,
+                $offset: u32,
+            ) -> Arc<ComposerUpdate> {
+                let offset =
+                    wysiwyg::Location::from(usize::try_from($offset).unwrap());
+                Arc::new(ComposerUpdate::from(
+                    self.inner.lock().unwrap().remove_link_at(offset),
+                ))
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "utf8"))]
+export_offset_methods!(
+    start_utf16_codeunit,
+    end_utf16_codeunit,
+    utf16_codeunit,
+    length_utf16_codeunits
+);
+
+#[cfg(feature = "utf8")]
+export_offset_methods!(start_offset, end_offset, offset, length);
+
+// The example format is only written for UTF-16 models
+#[cfg(not(feature = "utf8"))]
+#[uniffi::export]
+impl ComposerModel {
+    /// The content and selection of the model in the example format, e.g.
+    /// `a{b}|c`, so that UI tests can assert on both at once. See
+    /// [wysiwyg::ComposerModel::to_example_format].
+    pub fn to_example_format(self: &Arc<Self>) -> String {
+        self.inner.lock().unwrap().to_example_format()
+    }
+}
+
 #[derive(uniffi::Record)]
 pub struct Attribute {
     pub key: String,
@@ -0,0 +1,203 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::collections::HashMap;
+
+use crate::{ComposerAction, PatternKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum ListExitRule {
+    OnEmptyItem,
+    Never,
+}
+
+impl From<ListExitRule> for wysiwyg::ListExitRule {
+    fn from(rule: ListExitRule) -> Self {
+        match rule {
+            ListExitRule::OnEmptyItem => Self::OnEmptyItem,
+            ListExitRule::Never => Self::Never,
+        }
+    }
+}
+
+impl From<wysiwyg::ListExitRule> for ListExitRule {
+    fn from(rule: wysiwyg::ListExitRule) -> Self {
+        match rule {
+            wysiwyg::ListExitRule::OnEmptyItem => Self::OnEmptyItem,
+            wysiwyg::ListExitRule::Never => Self::Never,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum InlineCodeExitRule {
+    OnEnter,
+    Never,
+}
+
+impl From<InlineCodeExitRule> for wysiwyg::InlineCodeExitRule {
+    fn from(rule: InlineCodeExitRule) -> Self {
+        match rule {
+            InlineCodeExitRule::OnEnter => Self::OnEnter,
+            InlineCodeExitRule::Never => Self::Never,
+        }
+    }
+}
+
+impl From<wysiwyg::InlineCodeExitRule> for InlineCodeExitRule {
+    fn from(rule: wysiwyg::InlineCodeExitRule) -> Self {
+        match rule {
+            wysiwyg::InlineCodeExitRule::OnEnter => Self::OnEnter,
+            wysiwyg::InlineCodeExitRule::Never => Self::Never,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ComposerModelConfig {
+    pub list_exit_rule: ListExitRule,
+    pub inline_code_exit_rule: InlineCodeExitRule,
+    pub auto_link: bool,
+    pub link_pasted_urls: bool,
+    pub at_room_detection: bool,
+    pub at_room_detection_in_quotes: bool,
+    pub can_notify_room: bool,
+    pub max_length: Option<u32>,
+    pub max_paste_nodes: Option<u32>,
+    pub max_paste_length: Option<u32>,
+    pub max_paste_list_depth: Option<u32>,
+    pub markdown_shortcuts: bool,
+    pub allowed_link_schemes: Option<Vec<String>>,
+    pub disallowed_actions: Vec<ComposerAction>,
+    pub lenient_parsing: bool,
+    pub plain_text_lists: bool,
+    pub range_updates: bool,
+    pub block_ids: bool,
+    pub menu_state_deltas: bool,
+    pub normalize_typed_nbsp: bool,
+    pub strip_invisible_characters: bool,
+    pub record_operations: bool,
+    /// The formatting actions of each named style, by name
+    pub styles: HashMap<String, Vec<ComposerAction>>,
+    /// The key reported by each character which starts a suggestion, by
+    /// the character. Keys which aren't a single character are ignored.
+    pub suggestion_triggers: HashMap<String, PatternKey>,
+}
+
+impl From<ComposerModelConfig> for wysiwyg::ComposerModelConfig {
+    fn from(config: ComposerModelConfig) -> Self {
+        let model_config = Self::default()
+            .with_list_exit_rule(config.list_exit_rule.into())
+            .with_inline_code_exit_rule(config.inline_code_exit_rule.into())
+            .with_auto_link(config.auto_link)
+            .with_link_pasted_urls(config.link_pasted_urls)
+            .with_at_room_detection(config.at_room_detection)
+            .with_at_room_detection_in_quotes(
+                config.at_room_detection_in_quotes,
+            )
+            .with_can_notify_room(config.can_notify_room)
+            .with_max_length(config.max_length.map(|l| l as usize))
+            .with_max_paste_nodes(config.max_paste_nodes.map(|l| l as usize))
+            .with_max_paste_length(config.max_paste_length.map(|l| l as usize))
+            .with_max_paste_list_depth(
+                config.max_paste_list_depth.map(|l| l as usize),
+            )
+            .with_markdown_shortcuts(config.markdown_shortcuts)
+            .with_allowed_link_schemes(config.allowed_link_schemes)
+            .with_disallowed_actions(
+                config.disallowed_actions.iter().map(Into::into).collect(),
+            )
+            .with_lenient_parsing(config.lenient_parsing)
+            .with_plain_text_lists(config.plain_text_lists)
+            .with_range_updates(config.range_updates)
+            .with_block_ids(config.block_ids)
+            .with_menu_state_deltas(config.menu_state_deltas)
+            .with_normalize_typed_nbsp(config.normalize_typed_nbsp)
+            .with_strip_invisible_characters(config.strip_invisible_characters)
+            .with_record_operations(config.record_operations)
+            .with_suggestion_triggers(
+                config
+                    .suggestion_triggers
+                    .into_iter()
+                    .filter_map(|(trigger, key)| {
+                        let mut chars = trigger.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(trigger), None) => {
+                                Some((trigger, key.into()))
+                            }
+                            _ => None,
+                        }
+                    })
+                    .collect(),
+            );
+        config.styles.iter().fold(
+            model_config,
+            |model_config, (name, actions)| {
+                model_config.with_style(
+                    name.clone(),
+                    actions
+                        .iter()
+                        .filter_map(|action| {
+                            wysiwyg::InlineFormatType::from_action(
+                                &action.into(),
+                            )
+                        })
+                        .collect(),
+                )
+            },
+        )
+    }
+}
+
+impl From<&wysiwyg::ComposerModelConfig> for ComposerModelConfig {
+    fn from(config: &wysiwyg::ComposerModelConfig) -> Self {
+        Self {
+            list_exit_rule: config.list_exit_rule().into(),
+            inline_code_exit_rule: config.inline_code_exit_rule().into(),
+            auto_link: config.auto_link(),
+            link_pasted_urls: config.link_pasted_urls(),
+            at_room_detection: config.at_room_detection(),
+            at_room_detection_in_quotes: config.at_room_detection_in_quotes(),
+            can_notify_room: config.can_notify_room(),
+            max_length: config.max_length().map(|l| l as u32),
+            max_paste_nodes: config.max_paste_nodes().map(|l| l as u32),
+            max_paste_length: config.max_paste_length().map(|l| l as u32),
+            max_paste_list_depth: config
+                .max_paste_list_depth()
+                .map(|l| l as u32),
+            markdown_shortcuts: config.markdown_shortcuts(),
+            allowed_link_schemes: config
+                .allowed_link_schemes()
+                .map(|schemes| schemes.to_vec()),
+            disallowed_actions: config
+                .disallowed_actions()
+                .iter()
+                .map(Into::into)
+                .collect(),
+            lenient_parsing: config.lenient_parsing(),
+            plain_text_lists: config.plain_text_lists(),
+            range_updates: config.range_updates(),
+            block_ids: config.block_ids(),
+            menu_state_deltas: config.menu_state_deltas(),
+            normalize_typed_nbsp: config.normalize_typed_nbsp(),
+            strip_invisible_characters: config.strip_invisible_characters(),
+            record_operations: config.record_operations(),
+            styles: config
+                .styles()
+                .iter()
+                .map(|(name, formats)| {
+                    let actions =
+                        formats.iter().map(|f| (&f.action()).into()).collect();
+                    (name.clone(), actions)
+                })
+                .collect(),
+            suggestion_triggers: config
+                .suggestion_triggers()
+                .iter()
+                .map(|(trigger, key)| (trigger.to_string(), key.clone().into()))
+                .collect(),
+        }
+    }
+}
@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use widestring::Utf16String;
+use crate::model_string::{html_code_units, ModelString};
 use wysiwyg::ToHtml;
 
 #[derive(uniffi::Record)]
@@ -13,12 +13,12 @@ pub struct ComposerState {
     pub end: u32,
 }
 
-impl From<wysiwyg::ComposerState<Utf16String>> for ComposerState {
-    fn from(state: wysiwyg::ComposerState<Utf16String>) -> Self {
+impl From<wysiwyg::ComposerState<ModelString>> for ComposerState {
+    fn from(state: wysiwyg::ComposerState<ModelString>) -> Self {
         let start_utf16_codeunit: usize = state.start.into();
         let end_utf16_codeunit: usize = state.end.into();
         Self {
-            html: state.dom.to_html().into_vec(),
+            html: html_code_units(state.dom.to_html()),
             start: u32::try_from(start_utf16_codeunit).unwrap(),
             end: u32::try_from(end_utf16_codeunit).unwrap(),
         }
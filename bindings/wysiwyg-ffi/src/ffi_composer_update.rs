@@ -3,8 +3,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use widestring::Utf16String;
+use crate::model_string::ModelString;
 
+use crate::ffi_deleted_mention::DeletedMention;
 use crate::ffi_link_actions::LinkActionUpdate;
 use crate::ffi_menu_state::MenuState;
 use crate::ffi_text_update::TextUpdate;
@@ -12,11 +13,11 @@ use crate::MenuAction;
 
 #[derive(uniffi::Object)]
 pub struct ComposerUpdate {
-    inner: wysiwyg::ComposerUpdate<Utf16String>,
+    inner: wysiwyg::ComposerUpdate<ModelString>,
 }
 
 impl ComposerUpdate {
-    pub fn from(inner: wysiwyg::ComposerUpdate<Utf16String>) -> Self {
+    pub fn from(inner: wysiwyg::ComposerUpdate<ModelString>) -> Self {
         Self { inner }
     }
 }
@@ -38,6 +39,188 @@ impl ComposerUpdate {
     pub fn link_action(&self) -> LinkActionUpdate {
         LinkActionUpdate::from(self.inner.link_action.clone())
     }
+
+    /// The mentions removed by this update, e.g. by backspace or delete.
+    pub fn deleted_mentions(&self) -> Vec<DeletedMention> {
+        self.inner
+            .deleted_mentions
+            .iter()
+            .cloned()
+            .map(DeletedMention::from)
+            .collect()
+    }
+
+    /// The indices of the top-level blocks this update changed, so that
+    /// only those need re-rendering. The replacement HTML in the text update
+    /// always holds the whole content.
+    pub fn changed_blocks(&self) -> ChangedBlocks {
+        let indices = |handles: &[wysiwyg::DomHandle]| {
+            handles
+                .iter()
+                .map(|h| u32::try_from(h.index_in_parent()).unwrap())
+                .collect()
+        };
+        let changed = &self.inner.changed_handles;
+        ChangedBlocks {
+            added: indices(&changed.added),
+            removed: indices(&changed.removed),
+            modified: indices(&changed.modified),
+        }
+    }
+
+    /// Which of the configured limits on pasted content this update's
+    /// paste went over, if it went over any, so that only part of it was
+    /// pasted.
+    pub fn paste_truncation(&self) -> Option<PasteTruncation> {
+        self.inner.paste_truncation.map(PasteTruncation::from)
+    }
+
+    /// The invisible characters removed from this update's paste, if any
+    /// were and the configuration asks for them to be stripped.
+    pub fn stripped_characters(&self) -> Option<StrippedCharacters> {
+        self.inner.stripped_characters.map(StrippedCharacters::from)
+    }
+
+    /// How long this update took to make, if the `timing` feature is
+    /// enabled and the update changed the content.
+    pub fn timing(&self) -> Option<UpdateTiming> {
+        self.inner.timing.map(UpdateTiming::from)
+    }
+
+    /// True if this update changed the content, so that it is worth saving,
+    /// rather than only e.g. moving the selection or updating the menu.
+    pub fn content_changed(&self) -> bool {
+        self.inner.content_changed()
+    }
+}
+
+/// Exports the methods which take offsets, with parameters named for the
+/// unit of the offsets, see [crate::model_string].
+macro_rules! export_offset_methods {
+    ($start:ident, $end:ident, $offset:ident) => {
+        #[uniffi::export]
+        impl ComposerUpdate {
+            /// Map an offset in the content before this update to the
+            /// equivalent offset in the new content.
+            pub fn map_offset(&self, $offset: u32) -> u32 {
+                let offset =
+                    self.inner.offset_mapping.map_offset($offset as usize);
+                u32::try_from(offset).unwrap()
+            }
+
+            /// Map a range in the content before this update to the
+            /// equivalent range in the new content, or None if the text
+            /// inside it changed.
+            pub fn map_range(
+                &self,
+                $start: u32,
+                $end: u32,
+            ) -> Option<MappedRange> {
+                self.inner
+                    .offset_mapping
+                    .map_range($start as usize, $end as usize)
+                    .map(|(start, end)| MappedRange::new(start, end))
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "utf8"))]
+export_offset_methods!(
+    start_utf16_codeunit,
+    end_utf16_codeunit,
+    utf16_codeunit
+);
+
+#[cfg(feature = "utf8")]
+export_offset_methods!(start_offset, end_offset, offset);
+
+/// Indices of top-level blocks: `added` and `modified` ones in the new
+/// content, `removed` ones in the content before the update.
+#[derive(uniffi::Record)]
+pub struct ChangedBlocks {
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
+    pub modified: Vec<u32>,
+}
+
+#[derive(uniffi::Record)]
+pub struct MappedRange {
+    #[cfg(not(feature = "utf8"))]
+    pub start_utf16_codeunit: u32,
+    #[cfg(not(feature = "utf8"))]
+    pub end_utf16_codeunit: u32,
+    #[cfg(feature = "utf8")]
+    pub start_offset: u32,
+    #[cfg(feature = "utf8")]
+    pub end_offset: u32,
+}
+
+impl MappedRange {
+    fn new(start: usize, end: usize) -> Self {
+        let start = u32::try_from(start).unwrap();
+        let end = u32::try_from(end).unwrap();
+        Self {
+            #[cfg(not(feature = "utf8"))]
+            start_utf16_codeunit: start,
+            #[cfg(not(feature = "utf8"))]
+            end_utf16_codeunit: end,
+            #[cfg(feature = "utf8")]
+            start_offset: start,
+            #[cfg(feature = "utf8")]
+            end_offset: end,
+        }
+    }
+}
+
+#[derive(uniffi::Record)]
+pub struct PasteTruncation {
+    pub too_many_nodes: bool,
+    pub too_long: bool,
+    pub lists_too_deep: bool,
+}
+
+impl From<wysiwyg::PasteTruncation> for PasteTruncation {
+    fn from(truncation: wysiwyg::PasteTruncation) -> Self {
+        Self {
+            too_many_nodes: truncation.too_many_nodes,
+            too_long: truncation.too_long,
+            lists_too_deep: truncation.lists_too_deep,
+        }
+    }
+}
+
+#[derive(uniffi::Record)]
+pub struct StrippedCharacters {
+    pub soft_hyphens: u32,
+    pub zero_width: u32,
+    pub directional_marks: u32,
+}
+
+impl From<wysiwyg::StrippedCharacters> for StrippedCharacters {
+    fn from(stripped: wysiwyg::StrippedCharacters) -> Self {
+        Self {
+            soft_hyphens: u32::try_from(stripped.soft_hyphens).unwrap(),
+            zero_width: u32::try_from(stripped.zero_width).unwrap(),
+            directional_marks: u32::try_from(stripped.directional_marks)
+                .unwrap(),
+        }
+    }
+}
+
+#[derive(uniffi::Record)]
+pub struct UpdateTiming {
+    pub operation_micros: u64,
+    pub serialization_micros: u64,
+}
+
+impl From<wysiwyg::UpdateTiming> for UpdateTiming {
+    fn from(timing: wysiwyg::UpdateTiming) -> Self {
+        Self {
+            operation_micros: timing.operation_micros,
+            serialization_micros: timing.serialization_micros,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,42 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Record)]
+pub struct TextLength {
+    #[cfg(not(feature = "utf8"))]
+    pub utf16_code_units: u32,
+    #[cfg(feature = "utf8")]
+    pub code_units: u32,
+    /// The characters users see, e.g. for a character counter
+    pub graphemes: u32,
+}
+
+impl From<wysiwyg::TextLength> for TextLength {
+    fn from(length: wysiwyg::TextLength) -> Self {
+        Self {
+            #[cfg(not(feature = "utf8"))]
+            utf16_code_units: u32::try_from(length.code_units).unwrap(),
+            #[cfg(feature = "utf8")]
+            code_units: u32::try_from(length.code_units).unwrap(),
+            graphemes: u32::try_from(length.graphemes).unwrap(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ContentLength {
+    pub total: TextLength,
+    /// The length of each top-level block, by its index
+    pub blocks: Vec<TextLength>,
+}
+
+impl From<wysiwyg::ContentLength> for ContentLength {
+    fn from(length: wysiwyg::ContentLength) -> Self {
+        Self {
+            total: length.total.into(),
+            blocks: length.blocks.into_iter().map(Into::into).collect(),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum DecorationKind {
+    SpellingError,
+    GrammarSuggestion,
+    SearchHighlight,
+    Placeholder,
+}
+
+impl From<DecorationKind> for wysiwyg::DecorationKind {
+    fn from(kind: DecorationKind) -> Self {
+        match kind {
+            DecorationKind::SpellingError => Self::SpellingError,
+            DecorationKind::GrammarSuggestion => Self::GrammarSuggestion,
+            DecorationKind::SearchHighlight => Self::SearchHighlight,
+            DecorationKind::Placeholder => Self::Placeholder,
+        }
+    }
+}
+
+impl From<wysiwyg::DecorationKind> for DecorationKind {
+    fn from(kind: wysiwyg::DecorationKind) -> Self {
+        match kind {
+            wysiwyg::DecorationKind::SpellingError => Self::SpellingError,
+            wysiwyg::DecorationKind::GrammarSuggestion => {
+                Self::GrammarSuggestion
+            }
+            wysiwyg::DecorationKind::SearchHighlight => Self::SearchHighlight,
+            wysiwyg::DecorationKind::Placeholder => Self::Placeholder,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Decoration {
+    pub id: u64,
+    pub kind: DecorationKind,
+    #[cfg(not(feature = "utf8"))]
+    pub start_utf16_codeunit: u32,
+    #[cfg(not(feature = "utf8"))]
+    pub end_utf16_codeunit: u32,
+    #[cfg(feature = "utf8")]
+    pub start_offset: u32,
+    #[cfg(feature = "utf8")]
+    pub end_offset: u32,
+}
+
+impl From<&wysiwyg::Decoration> for Decoration {
+    fn from(decoration: &wysiwyg::Decoration) -> Self {
+        let start: usize = decoration.start.into();
+        let end: usize = decoration.end.into();
+        Self {
+            id: decoration.id,
+            kind: decoration.kind.into(),
+            #[cfg(not(feature = "utf8"))]
+            start_utf16_codeunit: u32::try_from(start).unwrap(),
+            #[cfg(not(feature = "utf8"))]
+            end_utf16_codeunit: u32::try_from(end).unwrap(),
+            #[cfg(feature = "utf8")]
+            start_offset: u32::try_from(start).unwrap(),
+            #[cfg(feature = "utf8")]
+            end_offset: u32::try_from(end).unwrap(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum DeletedMentionKind {
+    User,
+    RoomId,
+    RoomAlias,
+    AtRoom,
+}
+
+impl From<wysiwyg::DeletedMentionKind> for DeletedMentionKind {
+    fn from(kind: wysiwyg::DeletedMentionKind) -> Self {
+        match kind {
+            wysiwyg::DeletedMentionKind::User => Self::User,
+            wysiwyg::DeletedMentionKind::RoomId => Self::RoomId,
+            wysiwyg::DeletedMentionKind::RoomAlias => Self::RoomAlias,
+            wysiwyg::DeletedMentionKind::AtRoom => Self::AtRoom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct DeletedMention {
+    pub kind: DeletedMentionKind,
+    pub uri: Option<String>,
+    pub mx_id: Option<String>,
+    pub display_text: String,
+    #[cfg(not(feature = "utf8"))]
+    pub utf16_codeunit: u32,
+    #[cfg(feature = "utf8")]
+    pub offset: u32,
+}
+
+impl From<wysiwyg::DeletedMention> for DeletedMention {
+    fn from(mention: wysiwyg::DeletedMention) -> Self {
+        let location: usize = mention.location.into();
+        Self {
+            kind: mention.kind.into(),
+            uri: mention.uri,
+            mx_id: mention.mx_id,
+            display_text: mention.display_text,
+            #[cfg(not(feature = "utf8"))]
+            utf16_codeunit: u32::try_from(location).unwrap(),
+            #[cfg(feature = "utf8")]
+            offset: u32::try_from(location).unwrap(),
+        }
+    }
+}
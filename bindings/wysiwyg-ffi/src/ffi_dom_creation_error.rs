@@ -10,6 +10,8 @@ use std::{error::Error, fmt::Display};
 pub enum DomCreationError {
     HtmlParseError,
     MarkdownParseError,
+    OperationsParseError,
+    EventContentParseError,
 }
 
 impl Display for DomCreationError {
@@ -21,6 +23,12 @@ impl Display for DomCreationError {
             DomCreationError::MarkdownParseError => {
                 "could not create dom from markdown"
             }
+            DomCreationError::OperationsParseError => {
+                "could not parse operations"
+            }
+            DomCreationError::EventContentParseError => {
+                "could not parse event content"
+            }
         })
     }
 }
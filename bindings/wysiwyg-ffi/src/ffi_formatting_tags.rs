@@ -0,0 +1,21 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum FormattingTags {
+    AsCreated,
+    Semantic,
+    Presentational,
+}
+
+impl From<FormattingTags> for wysiwyg::FormattingTags {
+    fn from(tags: FormattingTags) -> Self {
+        match tags {
+            FormattingTags::AsCreated => Self::AsCreated,
+            FormattingTags::Semantic => Self::Semantic,
+            FormattingTags::Presentational => Self::Presentational,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum HistoryEntryKind {
+    Typing,
+    Deletion,
+    NewLine,
+    Formatting,
+    List,
+    Indent,
+    Link,
+    Mention,
+    Paste,
+    Move,
+}
+
+impl From<wysiwyg::HistoryEntryKind> for HistoryEntryKind {
+    fn from(kind: wysiwyg::HistoryEntryKind) -> Self {
+        match kind {
+            wysiwyg::HistoryEntryKind::Typing => Self::Typing,
+            wysiwyg::HistoryEntryKind::Deletion => Self::Deletion,
+            wysiwyg::HistoryEntryKind::NewLine => Self::NewLine,
+            wysiwyg::HistoryEntryKind::Formatting => Self::Formatting,
+            wysiwyg::HistoryEntryKind::List => Self::List,
+            wysiwyg::HistoryEntryKind::Indent => Self::Indent,
+            wysiwyg::HistoryEntryKind::Link => Self::Link,
+            wysiwyg::HistoryEntryKind::Mention => Self::Mention,
+            wysiwyg::HistoryEntryKind::Paste => Self::Paste,
+            wysiwyg::HistoryEntryKind::Move => Self::Move,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct HistoryEntry {
+    pub kind: HistoryEntryKind,
+    pub length_delta: i64,
+    pub timestamp_millis: u64,
+    pub is_undone: bool,
+}
+
+impl From<wysiwyg::HistoryEntry> for HistoryEntry {
+    fn from(entry: wysiwyg::HistoryEntry) -> Self {
+        Self {
+            kind: entry.kind.into(),
+            length_delta: i64::try_from(entry.length_delta).unwrap(),
+            timestamp_millis: entry.timestamp_millis,
+            is_undone: entry.is_undone,
+        }
+    }
+}
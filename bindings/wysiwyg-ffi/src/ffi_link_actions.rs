@@ -3,23 +3,34 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use widestring::Utf16String;
+use crate::model_string::ModelString;
+use crate::Attribute;
 
 #[derive(uniffi::Enum)]
 pub enum LinkAction {
     CreateWithText,
     Create,
-    Edit { url: String },
+    Edit {
+        url: String,
+        attributes: Vec<Attribute>,
+    },
     Disabled,
 }
 
-impl From<wysiwyg::LinkAction<Utf16String>> for LinkAction {
-    fn from(inner: wysiwyg::LinkAction<Utf16String>) -> Self {
+impl From<wysiwyg::LinkAction<ModelString>> for LinkAction {
+    fn from(inner: wysiwyg::LinkAction<ModelString>) -> Self {
         match inner {
             wysiwyg::LinkAction::CreateWithText => Self::CreateWithText,
             wysiwyg::LinkAction::Create => Self::Create,
-            wysiwyg::LinkAction::Edit(url) => Self::Edit {
+            wysiwyg::LinkAction::Edit(url, attributes) => Self::Edit {
                 url: url.to_string(),
+                attributes: attributes
+                    .into_iter()
+                    .map(|(key, value)| Attribute {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
             },
             wysiwyg::LinkAction::Disabled => Self::Disabled,
         }
@@ -32,8 +43,8 @@ pub enum LinkActionUpdate {
     Update { link_action: LinkAction },
 }
 
-impl From<wysiwyg::LinkActionUpdate<Utf16String>> for LinkActionUpdate {
-    fn from(inner: wysiwyg::LinkActionUpdate<Utf16String>) -> Self {
+impl From<wysiwyg::LinkActionUpdate<ModelString>> for LinkActionUpdate {
+    fn from(inner: wysiwyg::LinkActionUpdate<ModelString>) -> Self {
         match inner {
             wysiwyg::LinkActionUpdate::Keep => Self::Keep,
             wysiwyg::LinkActionUpdate::Update(action) => Self::Update {
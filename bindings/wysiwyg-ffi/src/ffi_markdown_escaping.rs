@@ -0,0 +1,25 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Record)]
+pub struct MarkdownEscaping {
+    pub underscores: bool,
+    pub asterisks: bool,
+    pub hashes: bool,
+    pub angle_brackets: bool,
+    pub autolink_bare_urls: bool,
+}
+
+impl From<MarkdownEscaping> for wysiwyg::MarkdownEscaping {
+    fn from(escaping: MarkdownEscaping) -> Self {
+        Self {
+            underscores: escaping.underscores,
+            asterisks: escaping.asterisks,
+            hashes: escaping.hashes,
+            angle_brackets: escaping.angle_brackets,
+            autolink_bare_urls: escaping.autolink_bare_urls,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum MarkdownMentions {
+    Link,
+    MxId,
+    DisplayText,
+}
+
+impl From<MarkdownMentions> for wysiwyg::MarkdownMentions {
+    fn from(mentions: MarkdownMentions) -> Self {
+        match mentions {
+            MarkdownMentions::Link => Self::Link,
+            MarkdownMentions::MxId => Self::MxId,
+            MarkdownMentions::DisplayText => Self::DisplayText,
+        }
+    }
+}
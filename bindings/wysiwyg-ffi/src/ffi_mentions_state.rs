@@ -9,6 +9,8 @@ pub struct MentionsState {
     pub room_ids: Vec<String>,
     pub room_aliases: Vec<String>,
     pub has_at_room_mention: bool,
+    #[uniffi(default = false)]
+    pub has_at_room_text: bool,
 }
 
 impl From<wysiwyg::MentionsState> for MentionsState {
@@ -18,6 +20,7 @@ impl From<wysiwyg::MentionsState> for MentionsState {
             room_ids: value.room_ids.into_iter().collect(),
             room_aliases: value.room_aliases.into_iter().collect(),
             has_at_room_mention: value.has_at_room_mention,
+            has_at_room_text: value.has_at_room_text,
         }
     }
 }
@@ -13,6 +13,11 @@ pub enum MenuAction {
     Suggestion {
         suggestion_pattern: SuggestionPattern,
     },
+    UrlDetected {
+        url: String,
+        start: u32,
+        end: u32,
+    },
 }
 
 impl MenuAction {
@@ -27,6 +32,13 @@ impl MenuAction {
                     ),
                 }
             }
+            wysiwyg::MenuAction::UrlDetected { url, start, end } => {
+                Self::UrlDetected {
+                    url,
+                    start: u32::try_from(start).unwrap(),
+                    end: u32::try_from(end).unwrap(),
+                }
+            }
         }
     }
 }
@@ -0,0 +1,19 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum PlaceholderPolicy {
+    EmptyBlocks,
+    Never,
+}
+
+impl From<PlaceholderPolicy> for wysiwyg::PlaceholderPolicy {
+    fn from(policy: PlaceholderPolicy) -> Self {
+        match policy {
+            PlaceholderPolicy::EmptyBlocks => Self::EmptyBlocks,
+            PlaceholderPolicy::Never => Self::Never,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::ComposerAction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum RangeLeafKind {
+    Text,
+    LineBreak,
+    Mention,
+}
+
+impl From<wysiwyg::RangeLeafKind> for RangeLeafKind {
+    fn from(kind: wysiwyg::RangeLeafKind) -> Self {
+        match kind {
+            wysiwyg::RangeLeafKind::Text => Self::Text,
+            wysiwyg::RangeLeafKind::LineBreak => Self::LineBreak,
+            wysiwyg::RangeLeafKind::Mention => Self::Mention,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum RangeBlockKind {
+    Paragraph,
+    OrderedList,
+    UnorderedList,
+    ListItem,
+    CodeBlock,
+    Quote,
+}
+
+impl From<wysiwyg::RangeBlockKind> for RangeBlockKind {
+    fn from(kind: wysiwyg::RangeBlockKind) -> Self {
+        match kind {
+            wysiwyg::RangeBlockKind::Paragraph => Self::Paragraph,
+            wysiwyg::RangeBlockKind::OrderedList => Self::OrderedList,
+            wysiwyg::RangeBlockKind::UnorderedList => Self::UnorderedList,
+            wysiwyg::RangeBlockKind::ListItem => Self::ListItem,
+            wysiwyg::RangeBlockKind::CodeBlock => Self::CodeBlock,
+            wysiwyg::RangeBlockKind::Quote => Self::Quote,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct RangeQuery {
+    pub leaf_kinds: Vec<RangeLeafKind>,
+    pub block_kinds: Vec<RangeBlockKind>,
+    /// The formatting actions of the inline formats applied to any of the
+    /// range
+    pub formats: Vec<ComposerAction>,
+    pub has_link: bool,
+}
+
+impl From<wysiwyg::RangeQuery> for RangeQuery {
+    fn from(query: wysiwyg::RangeQuery) -> Self {
+        Self {
+            leaf_kinds: query.leaf_kinds.into_iter().map(Into::into).collect(),
+            block_kinds: query
+                .block_kinds
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            formats: query
+                .formats
+                .iter()
+                .map(|format| ComposerAction::from(&format.action()))
+                .collect(),
+            has_link: query.has_link,
+        }
+    }
+}
@@ -3,47 +3,107 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use widestring::Utf16String;
+use crate::model_string::{html_code_units, ModelString};
 
 #[derive(uniffi::Enum)]
 pub enum TextUpdate {
     Keep,
     ReplaceAll {
         replacement_html: Vec<u16>,
+        #[cfg(not(feature = "utf8"))]
         start_utf16_codeunit: u32,
+        #[cfg(not(feature = "utf8"))]
         end_utf16_codeunit: u32,
+        #[cfg(feature = "utf8")]
+        start_offset: u32,
+        #[cfg(feature = "utf8")]
+        end_offset: u32,
+    },
+    ReplaceRange {
+        first_node: u32,
+        removed_nodes: u32,
+        replacement_html: Vec<u16>,
+        #[cfg(not(feature = "utf8"))]
+        start_utf16_codeunit: u32,
+        #[cfg(not(feature = "utf8"))]
+        end_utf16_codeunit: u32,
+        #[cfg(feature = "utf8")]
+        start_offset: u32,
+        #[cfg(feature = "utf8")]
+        end_offset: u32,
     },
     Select {
+        #[cfg(not(feature = "utf8"))]
         start_utf16_codeunit: u32,
+        #[cfg(not(feature = "utf8"))]
         end_utf16_codeunit: u32,
+        #[cfg(feature = "utf8")]
+        start_offset: u32,
+        #[cfg(feature = "utf8")]
+        end_offset: u32,
     },
 }
 
 impl TextUpdate {
-    pub fn from(inner: wysiwyg::TextUpdate<Utf16String>) -> Self {
+    pub fn from(inner: wysiwyg::TextUpdate<ModelString>) -> Self {
         match inner {
             wysiwyg::TextUpdate::Keep => Self::Keep,
             wysiwyg::TextUpdate::ReplaceAll(replace_all) => {
-                let start_utf16_codeunit: usize = replace_all.start.into();
-                let end_utf16_codeunit: usize = replace_all.end.into();
+                let (start, end) = offsets(replace_all.start, replace_all.end);
                 Self::ReplaceAll {
-                    replacement_html: replace_all.replacement_html.into_vec(),
-                    start_utf16_codeunit: u32::try_from(start_utf16_codeunit)
+                    replacement_html: html_code_units(
+                        replace_all.replacement_html,
+                    ),
+                    #[cfg(not(feature = "utf8"))]
+                    start_utf16_codeunit: start,
+                    #[cfg(not(feature = "utf8"))]
+                    end_utf16_codeunit: end,
+                    #[cfg(feature = "utf8")]
+                    start_offset: start,
+                    #[cfg(feature = "utf8")]
+                    end_offset: end,
+                }
+            }
+            wysiwyg::TextUpdate::ReplaceRange(replace_range) => {
+                let (start, end) =
+                    offsets(replace_range.start, replace_range.end);
+                Self::ReplaceRange {
+                    first_node: u32::try_from(replace_range.first_node)
                         .unwrap(),
-                    end_utf16_codeunit: u32::try_from(end_utf16_codeunit)
+                    removed_nodes: u32::try_from(replace_range.removed_nodes)
                         .unwrap(),
+                    replacement_html: html_code_units(
+                        replace_range.replacement_html,
+                    ),
+                    #[cfg(not(feature = "utf8"))]
+                    start_utf16_codeunit: start,
+                    #[cfg(not(feature = "utf8"))]
+                    end_utf16_codeunit: end,
+                    #[cfg(feature = "utf8")]
+                    start_offset: start,
+                    #[cfg(feature = "utf8")]
+                    end_offset: end,
                 }
             }
             wysiwyg::TextUpdate::Select(selection) => {
-                let start_utf16_codeunit: usize = selection.start.into();
-                let end_utf16_codeunit: usize = selection.end.into();
+                let (start, end) = offsets(selection.start, selection.end);
                 Self::Select {
-                    start_utf16_codeunit: u32::try_from(start_utf16_codeunit)
-                        .unwrap(),
-                    end_utf16_codeunit: u32::try_from(end_utf16_codeunit)
-                        .unwrap(),
+                    #[cfg(not(feature = "utf8"))]
+                    start_utf16_codeunit: start,
+                    #[cfg(not(feature = "utf8"))]
+                    end_utf16_codeunit: end,
+                    #[cfg(feature = "utf8")]
+                    start_offset: start,
+                    #[cfg(feature = "utf8")]
+                    end_offset: end,
                 }
             }
         }
     }
 }
+
+fn offsets(start: wysiwyg::Location, end: wysiwyg::Location) -> (u32, u32) {
+    let start: usize = start.into();
+    let end: usize = end.into();
+    (u32::try_from(start).unwrap(), u32::try_from(end).unwrap())
+}
@@ -0,0 +1,19 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum WhitespacePolicy {
+    Collapse,
+    Preserve,
+}
+
+impl From<WhitespacePolicy> for wysiwyg::WhitespacePolicy {
+    fn from(policy: WhitespacePolicy) -> Self {
+        match policy {
+            WhitespacePolicy::Collapse => Self::Collapse,
+            WhitespacePolicy::Preserve => Self::Preserve,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::model_string::ModelString;
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Word {
+    pub text: String,
+    #[cfg(not(feature = "utf8"))]
+    pub start_utf16_codeunit: u32,
+    #[cfg(not(feature = "utf8"))]
+    pub end_utf16_codeunit: u32,
+    #[cfg(feature = "utf8")]
+    pub start_offset: u32,
+    #[cfg(feature = "utf8")]
+    pub end_offset: u32,
+}
+
+impl From<wysiwyg::Word<ModelString>> for Word {
+    fn from(word: wysiwyg::Word<ModelString>) -> Self {
+        let start: usize = word.start.into();
+        let end: usize = word.end.into();
+        Self {
+            text: word.text.to_string(),
+            #[cfg(not(feature = "utf8"))]
+            start_utf16_codeunit: u32::try_from(start).unwrap(),
+            #[cfg(not(feature = "utf8"))]
+            end_utf16_codeunit: u32::try_from(end).unwrap(),
+            #[cfg(feature = "utf8")]
+            start_offset: u32::try_from(start).unwrap(),
+            #[cfg(feature = "utf8")]
+            end_offset: u32::try_from(end).unwrap(),
+        }
+    }
+}
@@ -7,44 +7,107 @@
 uniffi_macros::include_scaffolding!("wysiwyg_composer");
 
 mod ffi_action_state;
+mod ffi_attribute_policy;
+mod ffi_block_backspace_behaviour;
 mod ffi_composer_action;
 mod ffi_composer_model;
+mod ffi_composer_model_config;
 mod ffi_composer_state;
 mod ffi_composer_update;
+mod ffi_content_length;
+mod ffi_decoration;
+mod ffi_deleted_mention;
 mod ffi_dom_creation_error;
+mod ffi_formatting_tags;
+mod ffi_history;
 mod ffi_link_actions;
+mod ffi_markdown_escaping;
+mod ffi_markdown_mentions;
 mod ffi_mention_detector;
 mod ffi_mentions_state;
 mod ffi_menu_action;
 mod ffi_menu_state;
 mod ffi_pattern_key;
+mod ffi_placeholder_policy;
+mod ffi_range_query;
+mod ffi_selection_granularity;
 mod ffi_suggestion_pattern;
 mod ffi_text_update;
+mod ffi_whitespace_policy;
+mod ffi_word;
 mod into_ffi;
+mod model_string;
 
 use std::sync::Arc;
 
 pub use crate::ffi_action_state::ActionState;
+pub use crate::ffi_attribute_policy::AttributePolicy;
+pub use crate::ffi_block_backspace_behaviour::BlockBackspaceBehaviour;
 pub use crate::ffi_composer_action::ComposerAction;
 pub use crate::ffi_composer_model::Attribute;
 pub use crate::ffi_composer_model::ComposerModel;
+pub use crate::ffi_composer_model_config::ComposerModelConfig;
+pub use crate::ffi_composer_model_config::InlineCodeExitRule;
+pub use crate::ffi_composer_model_config::ListExitRule;
 pub use crate::ffi_composer_state::ComposerState;
+pub use crate::ffi_composer_update::ChangedBlocks;
 pub use crate::ffi_composer_update::ComposerUpdate;
+pub use crate::ffi_composer_update::MappedRange;
+pub use crate::ffi_composer_update::PasteTruncation;
+pub use crate::ffi_composer_update::StrippedCharacters;
+pub use crate::ffi_composer_update::UpdateTiming;
+pub use crate::ffi_content_length::ContentLength;
+pub use crate::ffi_content_length::TextLength;
+pub use crate::ffi_decoration::Decoration;
+pub use crate::ffi_decoration::DecorationKind;
+pub use crate::ffi_deleted_mention::DeletedMention;
+pub use crate::ffi_deleted_mention::DeletedMentionKind;
 pub use crate::ffi_dom_creation_error::DomCreationError;
+pub use crate::ffi_formatting_tags::FormattingTags;
+pub use crate::ffi_history::HistoryEntry;
+pub use crate::ffi_history::HistoryEntryKind;
 pub use crate::ffi_link_actions::LinkAction;
+pub use crate::ffi_markdown_escaping::MarkdownEscaping;
+pub use crate::ffi_markdown_mentions::MarkdownMentions;
 use crate::ffi_mention_detector::MentionDetector;
 pub use crate::ffi_mentions_state::MentionsState;
 pub use crate::ffi_menu_action::MenuAction;
 pub use crate::ffi_menu_state::MenuState;
 pub use crate::ffi_pattern_key::PatternKey;
+pub use crate::ffi_placeholder_policy::PlaceholderPolicy;
+pub use crate::ffi_range_query::RangeBlockKind;
+pub use crate::ffi_range_query::RangeLeafKind;
+pub use crate::ffi_range_query::RangeQuery;
+pub use crate::ffi_selection_granularity::Direction;
+pub use crate::ffi_selection_granularity::Granularity;
 pub use crate::ffi_suggestion_pattern::SuggestionPattern;
 pub use crate::ffi_text_update::TextUpdate;
+pub use crate::ffi_whitespace_policy::WhitespacePolicy;
+pub use crate::ffi_word::Word;
 
 #[uniffi::export]
 pub fn new_composer_model() -> Arc<ComposerModel> {
     Arc::new(ComposerModel::new())
 }
 
+#[uniffi::export]
+pub fn new_composer_model_with_config(
+    config: ComposerModelConfig,
+) -> Arc<ComposerModel> {
+    Arc::new(ComposerModel::with_config(config))
+}
+
+/// A model with the content and selection described in the example format,
+/// e.g. `a{b}|c`, for setting up UI tests. Panics if the text is not valid
+/// example format.
+#[cfg(all(feature = "example-format", not(feature = "utf8")))]
+#[uniffi::export]
+pub fn new_composer_model_from_example_format(
+    text: String,
+) -> Arc<ComposerModel> {
+    Arc::new(ComposerModel::from_example_format(&text))
+}
+
 #[uniffi::export]
 pub fn new_mention_detector() -> Arc<MentionDetector> {
     Arc::new(MentionDetector::new())
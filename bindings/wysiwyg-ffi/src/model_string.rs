@@ -0,0 +1,29 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! The string type the wrapped model is built on. By default this is
+//! UTF-16, so that offsets match the string indices of Kotlin and Swift.
+//! With the `utf8` feature it is UTF-8 and every offset passed through the
+//! bindings is a byte offset. The fields and parameters named after UTF-16
+//! code units, e.g. `start_utf16_codeunit`, are then named `start_offset`
+//! and so on instead.
+
+#[cfg(not(feature = "utf8"))]
+pub(crate) type ModelString = widestring::Utf16String;
+
+#[cfg(feature = "utf8")]
+pub(crate) type ModelString = String;
+
+/// The HTML of the model as the UTF-16 code units the bindings return,
+/// whichever string type the model is built on.
+#[cfg(not(feature = "utf8"))]
+pub(crate) fn html_code_units(html: ModelString) -> Vec<u16> {
+    html.into_vec()
+}
+
+#[cfg(feature = "utf8")]
+pub(crate) fn html_code_units(html: ModelString) -> Vec<u16> {
+    html.encode_utf16().collect()
+}
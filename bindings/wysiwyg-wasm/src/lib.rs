@@ -5,8 +5,11 @@
 // Please see LICENSE in the repository root for full details.
 
 use std::{
+    cell::OnceCell,
     collections::{HashMap, VecDeque},
     fmt::Display,
+    mem,
+    rc::Rc,
 };
 
 use wasm_bindgen::prelude::*;
@@ -24,6 +27,15 @@ pub fn new_composer_model() -> ComposerModel {
     }
 }
 
+#[wasm_bindgen]
+pub fn new_composer_model_with_config(
+    config: &ComposerModelConfig,
+) -> ComposerModel {
+    ComposerModel {
+        inner: wysiwyg::ComposerModel::with_config(config.into()),
+    }
+}
+
 #[wasm_bindgen]
 pub fn new_composer_model_from_html(
     html: &str,
@@ -122,10 +134,79 @@ impl ComposerModel {
         self.inner.to_example_format()
     }
 
+    /// The structure of the content as a tree, leaving out the nodes more
+    /// than `max_depth` levels below the root if it is given.
+    pub fn to_tree(&self, max_depth: Option<u32>) -> String {
+        match max_depth {
+            Some(depth) => self.inner.to_tree_with_max_depth(depth as usize),
+            None => self.inner.to_tree(),
+        }
+        .to_string()
+    }
+
+    /// The tree with every text node replaced by its length and no URLs,
+    /// followed by the selection, to attach to bug reports.
+    pub fn to_tree_redacted(&self) -> String {
+        self.inner.to_tree_redacted().to_string()
+    }
+
+    /// A hash of the content, ignoring the selection, which is the same
+    /// whenever the content is, e.g. to skip saving unchanged drafts. It
+    /// can only be compared with hashes from the same build of the app, so
+    /// it must not be saved along with the draft.
+    pub fn content_hash(&self) -> u64 {
+        self.inner.content_hash()
+    }
+
     pub fn get_content_as_html(&self) -> String {
         self.inner.get_content_as_html().to_string()
     }
 
+    /// The words a spellchecker should check in the given range, as an
+    /// array of Word objects.
+    pub fn words_in_range(
+        &self,
+        start_utf16_codeunit: u32,
+        end_utf16_codeunit: u32,
+    ) -> js_sys::Array {
+        self.inner
+            .words_in_range(
+                wysiwyg::Location::from(start_utf16_codeunit as usize),
+                wysiwyg::Location::from(end_utf16_codeunit as usize),
+            )
+            .map(|w| JsValue::from(Word::from(w)))
+            .collect()
+    }
+
+    pub fn get_word_at_cursor(&self) -> Option<Word> {
+        self.inner.get_word_at_cursor().map(Word::from)
+    }
+
+    /// What the content between the given offsets is made of, without
+    /// reading the whole document.
+    pub fn query_range(
+        &self,
+        start_utf16_codeunit: u32,
+        end_utf16_codeunit: u32,
+    ) -> RangeQuery {
+        RangeQuery::from(self.inner.query_range(
+            wysiwyg::Location::from(start_utf16_codeunit as usize),
+            wysiwyg::Location::from(end_utf16_codeunit as usize),
+        ))
+    }
+
+    /// How long the content is, in total and in each top-level block, both
+    /// in UTF-16 code units and in graphemes.
+    pub fn content_length(&self) -> ContentLength {
+        ContentLength::from(self.inner.content_length())
+    }
+
+    pub fn get_content_as_html_with_decorations(&self) -> String {
+        self.inner
+            .get_content_as_html_with_decorations()
+            .to_string()
+    }
+
     pub fn get_content_as_message_html(&self) -> String {
         self.inner.get_content_as_message_html().to_string()
     }
@@ -145,6 +226,7 @@ impl ComposerModel {
     pub fn document(&self) -> DomHandle {
         DomHandle {
             inner: self.inner.state.dom.document().handle(),
+            generation: self.inner.generation(),
         }
     }
 
@@ -167,6 +249,52 @@ impl ComposerModel {
         ))
     }
 
+    pub fn extend_selection(
+        &mut self,
+        direction: Direction,
+        granularity: Granularity,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(
+            self.inner
+                .extend_selection(direction.into(), granularity.into()),
+        )
+    }
+
+    pub fn set_composing_region(
+        &mut self,
+        start_utf16_codeunit: u32,
+        end_utf16_codeunit: u32,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.set_composing_region(
+            wysiwyg::Location::from(
+                usize::try_from(start_utf16_codeunit).unwrap(),
+            ),
+            wysiwyg::Location::from(
+                usize::try_from(end_utf16_codeunit).unwrap(),
+            ),
+        ))
+    }
+
+    pub fn clear_composing_region(&mut self) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.clear_composing_region())
+    }
+
+    pub fn previous_grapheme_boundary(&self, utf16_codeunit: u32) -> u32 {
+        let location =
+            wysiwyg::Location::from(usize::try_from(utf16_codeunit).unwrap());
+        let boundary: usize =
+            self.inner.previous_grapheme_boundary(location).into();
+        u32::try_from(boundary).unwrap()
+    }
+
+    pub fn next_grapheme_boundary(&self, utf16_codeunit: u32) -> u32 {
+        let location =
+            wysiwyg::Location::from(usize::try_from(utf16_codeunit).unwrap());
+        let boundary: usize =
+            self.inner.next_grapheme_boundary(location).into();
+        u32::try_from(boundary).unwrap()
+    }
+
     pub fn selection_start(&self) -> u32 {
         let ret: usize = self.inner.state.start.into();
         ret as u32
@@ -187,6 +315,10 @@ impl ComposerModel {
         )
     }
 
+    pub fn paste_text(&mut self, text: &str) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.paste_text(Utf16String::from_str(text)))
+    }
+
     pub fn replace_html(
         &mut self,
         new_html: &str,
@@ -198,6 +330,21 @@ impl ComposerModel {
         ))
     }
 
+    /// Start pasting HTML in chunks, so that a large paste doesn't block
+    /// the main thread: call `feed_html_paste` with each chunk, yielding in
+    /// between if needed, then `finish_html_paste` to insert it.
+    pub fn start_html_paste(&mut self, external_source: HtmlSource) {
+        self.inner.start_html_paste(external_source.into());
+    }
+
+    pub fn feed_html_paste(&mut self, chunk: &str) {
+        self.inner.feed_html_paste(Utf16String::from_str(chunk));
+    }
+
+    pub fn finish_html_paste(&mut self) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.finish_html_paste())
+    }
+
     pub fn replace_text_suggestion(
         &mut self,
         new_text: &str,
@@ -231,6 +378,18 @@ impl ComposerModel {
         Ok(ComposerUpdate::from(markdown))
     }
 
+    /// Replace the content with that of an `m.room.message` event, given
+    /// as JSON.
+    pub fn set_content_from_event_content(
+        &mut self,
+        json: &str,
+    ) -> Result<ComposerUpdate, DomCreationError> {
+        let content: wysiwyg::MessageEventContent = serde_json::from_str(json)
+            .map_err(|_| DomCreationError::EventContentParseError)?;
+        let update = self.inner.set_content_from_event_content(&content)?;
+        Ok(ComposerUpdate::from(update))
+    }
+
     pub fn clear(&mut self) -> ComposerUpdate {
         ComposerUpdate::from(self.inner.clear())
     }
@@ -251,6 +410,16 @@ impl ComposerModel {
         ComposerUpdate::from(self.inner.delete())
     }
 
+    pub fn truncate_to(
+        &mut self,
+        length_utf16_codeunits: u32,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(
+            self.inner
+                .truncate_to(usize::try_from(length_utf16_codeunits).unwrap()),
+        )
+    }
+
     pub fn delete_word(&mut self) -> ComposerUpdate {
         ComposerUpdate::from(self.inner.delete_word())
     }
@@ -275,10 +444,34 @@ impl ComposerModel {
         ComposerUpdate::from(self.inner.quote())
     }
 
+    /// Move the block at the handle, given as the index of each node on
+    /// the path to it from the root, to be child `target_index` of its
+    /// parent.
+    pub fn move_block(
+        &mut self,
+        handle: Vec<u32>,
+        target_index: u32,
+    ) -> ComposerUpdate {
+        let handle = wysiwyg::DomHandle::from_raw(
+            handle.into_iter().map(|i| i as usize).collect(),
+        );
+        ComposerUpdate::from(
+            self.inner.move_block(&handle, target_index as usize),
+        )
+    }
+
     pub fn inline_code(&mut self) -> ComposerUpdate {
         ComposerUpdate::from(self.inner.inline_code())
     }
 
+    pub fn apply_style(&mut self, name: &str) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.apply_style(name))
+    }
+
+    pub fn style_is_applied(&self, name: &str) -> bool {
+        self.inner.style_is_applied(name)
+    }
+
     pub fn code_block(&mut self) -> ComposerUpdate {
         ComposerUpdate::from(self.inner.code_block())
     }
@@ -291,6 +484,86 @@ impl ComposerModel {
         ComposerUpdate::from(self.inner.redo())
     }
 
+    pub fn undo_steps(&mut self, steps: usize) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.undo_steps(steps))
+    }
+
+    pub fn redo_steps(&mut self, steps: usize) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.redo_steps(steps))
+    }
+
+    /// Every step in the undo history, oldest first, as an array of
+    /// HistoryEntry objects.
+    pub fn history(&self) -> js_sys::Array {
+        self.inner
+            .history()
+            .into_iter()
+            .map(|e| JsValue::from(HistoryEntry::from(e)))
+            .collect()
+    }
+
+    pub fn create_snapshot(&mut self) -> u64 {
+        self.inner.create_snapshot()
+    }
+
+    pub fn restore_snapshot(&mut self, id: u64) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.restore_snapshot(id))
+    }
+
+    pub fn discard_snapshot(&mut self, id: u64) -> bool {
+        self.inner.discard_snapshot(id)
+    }
+
+    /// Put back the content as it was before the operation which left it
+    /// invalid, from the latest valid undo step, e.g. after catching an error thrown by another method.
+    pub fn reset_to_last_good(&mut self) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.reset_to_last_good())
+    }
+
+    pub fn insert_template(&mut self, html: &str) -> ComposerUpdate {
+        ComposerUpdate::from(
+            self.inner.insert_template(Utf16String::from_str(html)),
+        )
+    }
+
+    pub fn next_placeholder(&mut self) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.next_placeholder())
+    }
+
+    pub fn fill_placeholder(&mut self, text: &str) -> ComposerUpdate {
+        ComposerUpdate::from(
+            self.inner.fill_placeholder(Utf16String::from_str(text)),
+        )
+    }
+
+    pub fn operation_log_json(&self) -> String {
+        serde_json::to_string(self.inner.operation_log()).unwrap()
+    }
+
+    pub fn clear_operation_log(&mut self) {
+        self.inner.clear_operation_log();
+    }
+
+    pub fn apply_operations_json(
+        &mut self,
+        json: &str,
+    ) -> Result<ComposerUpdate, DomCreationError> {
+        let operations = serde_json::from_str(json)
+            .map_err(|_| DomCreationError::OperationsParseError)?;
+        let update = self.inner.apply_operations(operations)?;
+        Ok(ComposerUpdate::from(update))
+    }
+
+    pub fn run_command_sequence_json(
+        &mut self,
+        json: &str,
+    ) -> Result<ComposerUpdate, DomCreationError> {
+        let operations = serde_json::from_str(json)
+            .map_err(|_| DomCreationError::OperationsParseError)?;
+        let update = self.inner.run_command_sequence(operations)?;
+        Ok(ComposerUpdate::from(update))
+    }
+
     pub fn ordered_list(&mut self) -> ComposerUpdate {
         ComposerUpdate::from(self.inner.ordered_list())
     }
@@ -307,6 +580,14 @@ impl ComposerModel {
         ComposerUpdate::from(self.inner.unindent())
     }
 
+    pub fn split_list_at_cursor(&mut self) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.split_list_at_cursor())
+    }
+
+    pub fn join_with_previous_list(&mut self) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.join_with_previous_list())
+    }
+
     pub fn get_link_action(&self) -> LinkAction {
         self.inner.get_link_action().into()
     }
@@ -322,110 +603,1079 @@ impl ComposerModel {
         )
     }
 
-    pub fn set_link_with_text(
-        &mut self,
-        url: &str,
-        text: &str,
-        attributes: js_sys::Map,
-    ) -> ComposerUpdate {
-        ComposerUpdate::from(self.inner.set_link_with_text(
-            Utf16String::from_str(url),
-            Utf16String::from_str(&html_escape::encode_safe(&text)),
-            attributes.into_vec(),
-        ))
-    }
+    pub fn set_link_with_text(
+        &mut self,
+        url: &str,
+        text: &str,
+        attributes: js_sys::Map,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.set_link_with_text(
+            Utf16String::from_str(url),
+            Utf16String::from_str(&html_escape::encode_safe(&text)),
+            attributes.into_vec(),
+        ))
+    }
+
+    pub fn set_custom_suggestion_patterns(
+        &mut self,
+        custom_suggestion_patterns: js_sys::Array,
+    ) {
+        self.inner.set_custom_suggestion_patterns(
+            custom_suggestion_patterns.into_vec(),
+        );
+    }
+
+    /// Make `trigger` start a suggestion reported with `key`, or stop it
+    /// starting one if `key` is undefined. Does nothing unless `trigger` is
+    /// a single character.
+    pub fn set_suggestion_trigger(
+        &mut self,
+        trigger: &str,
+        key: Option<PatternKey>,
+    ) {
+        let mut chars = trigger.chars();
+        if let (Some(trigger), None) = (chars.next(), chars.next()) {
+            self.inner
+                .set_suggestion_trigger(trigger, key.map(Into::into));
+        }
+    }
+
+    pub fn set_max_parse_depth(&mut self, max_parse_depth: usize) {
+        self.inner.set_max_parse_depth(max_parse_depth);
+    }
+
+    pub fn set_block_backspace_behaviour(
+        &mut self,
+        behaviour: BlockBackspaceBehaviour,
+    ) {
+        self.inner.set_block_backspace_behaviour(behaviour.into());
+    }
+
+    pub fn set_attribute_policy(
+        &mut self,
+        preserve_data_attributes: bool,
+        allowed_classes: js_sys::Array,
+    ) {
+        self.inner.set_attribute_policy(wysiwyg::AttributePolicy {
+            preserve_data_attributes,
+            allowed_classes: allowed_classes.into_vec(),
+        });
+    }
+
+    pub fn set_formatting_tags(&mut self, tags: FormattingTags) {
+        self.inner.set_formatting_tags(tags.into());
+    }
+
+    pub fn set_strip_message_styles(&mut self, strip: bool) {
+        self.inner.set_strip_message_styles(strip);
+    }
+
+    pub fn set_isolate_message_bidi(&mut self, isolate: bool) {
+        self.inner.set_isolate_message_bidi(isolate);
+    }
+
+    pub fn set_whitespace_policy(&mut self, policy: WhitespacePolicy) {
+        self.inner.set_whitespace_policy(policy.into());
+    }
+
+    pub fn set_placeholder_policy(&mut self, policy: PlaceholderPolicy) {
+        self.inner.set_placeholder_policy(policy.into());
+    }
+
+    pub fn get_config(&self) -> ComposerModelConfig {
+        ComposerModelConfig::from(self.inner.config())
+    }
+
+    pub fn set_config(&mut self, config: &ComposerModelConfig) {
+        self.inner.set_config(config.into());
+    }
+
+    pub fn get_parse_warnings(&self) -> js_sys::Array {
+        self.inner
+            .parse_warnings()
+            .iter()
+            .map(|warning| JsValue::from_str(warning))
+            .collect()
+    }
+
+    pub fn set_at_room_detection(&mut self, detect_at_room: bool) {
+        self.inner.set_at_room_detection(detect_at_room);
+    }
+
+    pub fn set_at_room_detection_in_quotes(
+        &mut self,
+        detect_at_room_in_quotes: bool,
+    ) {
+        self.inner
+            .set_at_room_detection_in_quotes(detect_at_room_in_quotes);
+    }
+
+    pub fn set_can_notify_room(&mut self, can_notify_room: bool) {
+        self.inner.set_can_notify_room(can_notify_room);
+    }
+
+    pub fn set_markdown_escaping(
+        &mut self,
+        underscores: bool,
+        asterisks: bool,
+        hashes: bool,
+        angle_brackets: bool,
+        autolink_bare_urls: bool,
+    ) {
+        self.inner.set_markdown_escaping(wysiwyg::MarkdownEscaping {
+            underscores,
+            asterisks,
+            hashes,
+            angle_brackets,
+            autolink_bare_urls,
+        });
+    }
+
+    pub fn set_markdown_mentions(&mut self, mentions: MarkdownMentions) {
+        self.inner.set_markdown_mentions(mentions.into());
+    }
+
+    pub fn add_decoration(
+        &mut self,
+        kind: DecorationKind,
+        start_utf16_codeunit: u32,
+        end_utf16_codeunit: u32,
+    ) -> u64 {
+        self.inner.add_decoration(
+            kind.into(),
+            wysiwyg::Location::from(start_utf16_codeunit as usize),
+            wysiwyg::Location::from(end_utf16_codeunit as usize),
+        )
+    }
+
+    pub fn remove_decoration(&mut self, id: u64) -> bool {
+        self.inner.remove_decoration(id)
+    }
+
+    pub fn remove_decorations_of_kind(&mut self, kind: DecorationKind) {
+        self.inner.remove_decorations_of_kind(kind.into());
+    }
+
+    /// The decorations overlapping the given range, as an array of
+    /// Decoration objects.
+    pub fn decorations_in(
+        &self,
+        start_utf16_codeunit: u32,
+        end_utf16_codeunit: u32,
+    ) -> js_sys::Array {
+        self.inner
+            .decorations_in(
+                wysiwyg::Location::from(start_utf16_codeunit as usize),
+                wysiwyg::Location::from(end_utf16_codeunit as usize),
+            )
+            .into_iter()
+            .map(|d| JsValue::from(Decoration::from(d)))
+            .collect()
+    }
+
+    /// Creates an at-room mention node and inserts it into the composer at the current selection
+    pub fn insert_at_room_mention(
+        &mut self,
+        attributes: js_sys::Map,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(
+            self.inner.insert_at_room_mention(attributes.into_vec()),
+        )
+    }
+
+    /// Creates a mention node and inserts it into the composer at the current selection
+    pub fn insert_mention(
+        &mut self,
+        url: &str,
+        text: &str,
+        attributes: js_sys::Map,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.insert_mention(
+            Utf16String::from_str(url),
+            Utf16String::from_str(&html_escape::encode_safe(&text)),
+            attributes.into_vec(),
+        ))
+    }
+
+    /// Changes the text shown for the mentions between start and end, e.g.
+    /// when the display name of a mentioned user changes
+    pub fn update_mention_text(
+        &mut self,
+        start: u32,
+        end: u32,
+        text: &str,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.update_mention_text(
+            wysiwyg::Location::from(usize::try_from(start).unwrap()),
+            wysiwyg::Location::from(usize::try_from(end).unwrap()),
+            Utf16String::from_str(&html_escape::encode_safe(&text)),
+        ))
+    }
+
+    /// Turns each of the keys of `mentions` found in the content into a
+    /// mention of the URI it maps to, e.g. when restoring a draft saved as
+    /// plain text
+    pub fn convert_plain_mentions(
+        &mut self,
+        mentions: js_sys::Map,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(
+            self.inner.convert_plain_mentions(mentions.into_vec()),
+        )
+    }
+
+    /// Creates an at-room mention node and inserts it into the composer, replacing the
+    /// text content defined by the suggestion
+    pub fn insert_at_room_mention_at_suggestion(
+        &mut self,
+        suggestion: &SuggestionPattern,
+        attributes: js_sys::Map,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.insert_at_room_mention_at_suggestion(
+            wysiwyg::SuggestionPattern::from(suggestion.clone()),
+            attributes.into_vec(),
+        ))
+    }
+
+    /// Creates a mention node and inserts it into the composer, replacing the
+    /// text content defined by the suggestion
+    pub fn insert_mention_at_suggestion(
+        &mut self,
+        url: &str,
+        text: &str,
+        suggestion: &SuggestionPattern,
+        attributes: js_sys::Map,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.insert_mention_at_suggestion(
+            Utf16String::from_str(url),
+            Utf16String::from_str(&html_escape::encode_safe(&text)),
+            wysiwyg::SuggestionPattern::from(suggestion.clone()),
+            attributes.into_vec(),
+        ))
+    }
+
+    pub fn remove_links(&mut self) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.remove_links())
+    }
+
+    pub fn remove_links_in(
+        &mut self,
+        start_utf16_codeunit: u32,
+        end_utf16_codeunit: u32,
+    ) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.remove_links_in(
+            wysiwyg::Location::from(
+                usize::try_from(start_utf16_codeunit).unwrap(),
+            ),
+            wysiwyg::Location::from(
+                usize::try_from(end_utf16_codeunit).unwrap(),
+            ),
+        ))
+    }
+
+    pub fn remove_link_at(&mut self, utf16_codeunit: u32) -> ComposerUpdate {
+        ComposerUpdate::from(self.inner.remove_link_at(
+            wysiwyg::Location::from(usize::try_from(utf16_codeunit).unwrap()),
+        ))
+    }
+}
+
+#[wasm_bindgen]
+pub struct ComposerUpdate {
+    inner: wysiwyg::ComposerUpdate<Utf16String>,
+    /// Taken out of [Self::inner], so that it can be shared with the
+    /// [TextUpdate]s handed to JS without copying the replacement HTML.
+    text_update: Rc<SharedTextUpdate>,
+}
+
+impl ComposerUpdate {
+    fn from(mut inner: wysiwyg::ComposerUpdate<Utf16String>) -> Self {
+        let text_update =
+            mem::replace(&mut inner.text_update, wysiwyg::TextUpdate::Keep);
+        Self {
+            inner,
+            text_update: Rc::new(SharedTextUpdate {
+                inner: text_update,
+                replacement_html: OnceCell::new(),
+            }),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ComposerUpdate {
+    pub fn text_update(&self) -> TextUpdate {
+        TextUpdate {
+            shared: Rc::clone(&self.text_update),
+        }
+    }
+
+    pub fn menu_state(&self) -> MenuState {
+        MenuState::from(self.inner.menu_state.clone())
+    }
+
+    pub fn menu_action(&self) -> MenuAction {
+        MenuAction::from(self.inner.menu_action.clone())
+    }
+
+    /// The mentions removed by this update, as an array of DeletedMention
+    /// objects.
+    pub fn deleted_mentions(&self) -> js_sys::Array {
+        self.inner
+            .deleted_mentions
+            .iter()
+            .cloned()
+            .map(|m| JsValue::from(DeletedMention::from(m)))
+            .collect()
+    }
+
+    /// The indices of the top-level blocks in the new content which this
+    /// update added, so that only changed blocks need re-rendering.
+    pub fn added_blocks(&self) -> Vec<u32> {
+        block_indices(&self.inner.changed_handles.added)
+    }
+
+    /// The indices of the top-level blocks in the previous content which
+    /// this update removed.
+    pub fn removed_blocks(&self) -> Vec<u32> {
+        block_indices(&self.inner.changed_handles.removed)
+    }
+
+    /// The indices of the top-level blocks in the new content which this
+    /// update modified.
+    pub fn modified_blocks(&self) -> Vec<u32> {
+        block_indices(&self.inner.changed_handles.modified)
+    }
+
+    /// Map an offset in the content before this update to the equivalent
+    /// offset in the new content.
+    pub fn map_offset(&self, utf16_codeunit: u32) -> u32 {
+        let offset = self
+            .inner
+            .offset_mapping
+            .map_offset(utf16_codeunit as usize);
+        u32::try_from(offset).unwrap()
+    }
+
+    /// Map a range in the content before this update to the equivalent
+    /// range in the new content, or undefined if the text inside it changed.
+    pub fn map_range(
+        &self,
+        start_utf16_codeunit: u32,
+        end_utf16_codeunit: u32,
+    ) -> Option<MappedRange> {
+        self.inner
+            .offset_mapping
+            .map_range(
+                start_utf16_codeunit as usize,
+                end_utf16_codeunit as usize,
+            )
+            .map(|(start, end)| MappedRange {
+                start_utf16_codeunit: u32::try_from(start).unwrap(),
+                end_utf16_codeunit: u32::try_from(end).unwrap(),
+            })
+    }
+
+    /// Which of the configured limits on pasted content this update's
+    /// paste went over, or undefined if it went over none.
+    pub fn paste_truncation(&self) -> Option<PasteTruncation> {
+        self.inner.paste_truncation.map(PasteTruncation::from)
+    }
+
+    /// The invisible characters removed from this update's paste, or
+    /// undefined if none were or the configuration doesn't ask for them to
+    /// be stripped.
+    pub fn stripped_characters(&self) -> Option<StrippedCharacters> {
+        self.inner.stripped_characters.map(StrippedCharacters::from)
+    }
+
+    /// How long this update took to make, or undefined if the `timing`
+    /// feature is disabled or the update didn't change the content.
+    pub fn timing(&self) -> Option<UpdateTiming> {
+        self.inner.timing.map(UpdateTiming::from)
+    }
+
+    /// True if this update changed the content, so that it is worth saving,
+    /// rather than only e.g. moving the selection or updating the menu.
+    pub fn content_changed(&self) -> bool {
+        self.inner.content_changed()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum BlockBackspaceBehaviour {
+    MergeIntoPrevious,
+    ExitBlock,
+    NoOp,
+}
+
+impl From<BlockBackspaceBehaviour> for wysiwyg::BlockBackspaceBehaviour {
+    fn from(behaviour: BlockBackspaceBehaviour) -> Self {
+        match behaviour {
+            BlockBackspaceBehaviour::MergeIntoPrevious => {
+                Self::MergeIntoPrevious
+            }
+            BlockBackspaceBehaviour::ExitBlock => Self::ExitBlock,
+            BlockBackspaceBehaviour::NoOp => Self::NoOp,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum FormattingTags {
+    AsCreated,
+    Semantic,
+    Presentational,
+}
+
+impl From<FormattingTags> for wysiwyg::FormattingTags {
+    fn from(tags: FormattingTags) -> Self {
+        match tags {
+            FormattingTags::AsCreated => Self::AsCreated,
+            FormattingTags::Semantic => Self::Semantic,
+            FormattingTags::Presentational => Self::Presentational,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum ListExitRule {
+    OnEmptyItem,
+    Never,
+}
+
+impl From<ListExitRule> for wysiwyg::ListExitRule {
+    fn from(rule: ListExitRule) -> Self {
+        match rule {
+            ListExitRule::OnEmptyItem => Self::OnEmptyItem,
+            ListExitRule::Never => Self::Never,
+        }
+    }
+}
+
+impl From<wysiwyg::ListExitRule> for ListExitRule {
+    fn from(rule: wysiwyg::ListExitRule) -> Self {
+        match rule {
+            wysiwyg::ListExitRule::OnEmptyItem => Self::OnEmptyItem,
+            wysiwyg::ListExitRule::Never => Self::Never,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum InlineCodeExitRule {
+    OnEnter,
+    Never,
+}
+
+impl From<InlineCodeExitRule> for wysiwyg::InlineCodeExitRule {
+    fn from(rule: InlineCodeExitRule) -> Self {
+        match rule {
+            InlineCodeExitRule::OnEnter => Self::OnEnter,
+            InlineCodeExitRule::Never => Self::Never,
+        }
+    }
+}
+
+impl From<wysiwyg::InlineCodeExitRule> for InlineCodeExitRule {
+    fn from(rule: wysiwyg::InlineCodeExitRule) -> Self {
+        match rule {
+            wysiwyg::InlineCodeExitRule::OnEnter => Self::OnEnter,
+            wysiwyg::InlineCodeExitRule::Never => Self::Never,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct ComposerModelConfig {
+    pub list_exit_rule: ListExitRule,
+    pub inline_code_exit_rule: InlineCodeExitRule,
+    pub auto_link: bool,
+    pub link_pasted_urls: bool,
+    pub at_room_detection: bool,
+    pub at_room_detection_in_quotes: bool,
+    pub can_notify_room: bool,
+    pub max_length: Option<u32>,
+    pub max_paste_nodes: Option<u32>,
+    pub max_paste_length: Option<u32>,
+    pub max_paste_list_depth: Option<u32>,
+    pub markdown_shortcuts: bool,
+    pub allowed_link_schemes: Option<js_sys::Array>,
+    /// The names of the disallowed actions, as in `action_states`
+    pub disallowed_actions: js_sys::Array,
+    pub lenient_parsing: bool,
+    pub plain_text_lists: bool,
+    pub range_updates: bool,
+    pub block_ids: bool,
+    pub menu_state_deltas: bool,
+    pub normalize_typed_nbsp: bool,
+    pub strip_invisible_characters: bool,
+    pub record_operations: bool,
+    /// The names of the formatting actions of each named style, by name
+    pub styles: js_sys::Map,
+    /// The `PatternKey` reported by each character which starts a
+    /// suggestion, by the character
+    pub suggestion_triggers: js_sys::Map,
+}
+
+#[wasm_bindgen]
+impl ComposerModelConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::from(&wysiwyg::ComposerModelConfig::default())
+    }
+}
+
+impl Default for ComposerModelConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&ComposerModelConfig> for wysiwyg::ComposerModelConfig {
+    fn from(config: &ComposerModelConfig) -> Self {
+        let model_config = Self::default()
+            .with_list_exit_rule(config.list_exit_rule.into())
+            .with_inline_code_exit_rule(config.inline_code_exit_rule.into())
+            .with_auto_link(config.auto_link)
+            .with_link_pasted_urls(config.link_pasted_urls)
+            .with_at_room_detection(config.at_room_detection)
+            .with_at_room_detection_in_quotes(
+                config.at_room_detection_in_quotes,
+            )
+            .with_can_notify_room(config.can_notify_room)
+            .with_max_length(config.max_length.map(|l| l as usize))
+            .with_max_paste_nodes(config.max_paste_nodes.map(|l| l as usize))
+            .with_max_paste_length(config.max_paste_length.map(|l| l as usize))
+            .with_max_paste_list_depth(
+                config.max_paste_list_depth.map(|l| l as usize),
+            )
+            .with_markdown_shortcuts(config.markdown_shortcuts)
+            .with_allowed_link_schemes(
+                config.allowed_link_schemes.clone().map(|s| s.into_vec()),
+            )
+            .with_disallowed_actions(
+                config
+                    .disallowed_actions
+                    .clone()
+                    .into_vec()
+                    .iter()
+                    .filter_map(|name| name.parse().ok())
+                    .collect(),
+            )
+            .with_lenient_parsing(config.lenient_parsing)
+            .with_plain_text_lists(config.plain_text_lists)
+            .with_range_updates(config.range_updates)
+            .with_block_ids(config.block_ids)
+            .with_menu_state_deltas(config.menu_state_deltas)
+            .with_normalize_typed_nbsp(config.normalize_typed_nbsp)
+            .with_strip_invisible_characters(config.strip_invisible_characters)
+            .with_record_operations(config.record_operations);
+        let mut styles = Vec::new();
+        config.styles.for_each(&mut |actions, name| {
+            if let Some(name) = name.as_string() {
+                let formats = js_sys::Array::from(&actions)
+                    .iter()
+                    .filter_map(|action| action.as_string()?.parse().ok())
+                    .filter_map(|action| {
+                        wysiwyg::InlineFormatType::from_action(&action)
+                    })
+                    .collect();
+                styles.push((name, formats));
+            }
+        });
+        let mut triggers = HashMap::new();
+        config.suggestion_triggers.for_each(&mut |key, trigger| {
+            let trigger = trigger.as_string().unwrap_or_default();
+            let mut chars = trigger.chars();
+            if let (Some(trigger), None, Some(key)) =
+                (chars.next(), chars.next(), pattern_key_from_js(&key))
+            {
+                triggers.insert(trigger, key);
+            }
+        });
+        styles
+            .into_iter()
+            .fold(model_config, |model_config, (name, formats)| {
+                model_config.with_style(name, formats)
+            })
+            .with_suggestion_triggers(triggers)
+    }
+}
+
+/// Read a [PatternKey], or an object with the same fields, from JS.
+fn pattern_key_from_js(value: &JsValue) -> Option<wysiwyg::PatternKey> {
+    let field = |name: &str| js_sys::Reflect::get(value, &name.into()).ok();
+    let key_type = field("key_type")?.as_f64()?;
+    let key_type = [
+        PatternKeyType::At,
+        PatternKeyType::Hash,
+        PatternKeyType::Slash,
+        PatternKeyType::Custom,
+        PatternKeyType::Colon,
+    ]
+    .into_iter()
+    .find(|t| t.clone() as u32 as f64 == key_type)?;
+    let custom_key_value = field("custom_key_value")?.as_string();
+    if matches!(key_type, PatternKeyType::Custom) && custom_key_value.is_none()
+    {
+        return None;
+    }
+    Some(
+        PatternKey {
+            key_type,
+            custom_key_value,
+        }
+        .into(),
+    )
+}
+
+impl From<&wysiwyg::ComposerModelConfig> for ComposerModelConfig {
+    fn from(config: &wysiwyg::ComposerModelConfig) -> Self {
+        Self {
+            list_exit_rule: config.list_exit_rule().into(),
+            inline_code_exit_rule: config.inline_code_exit_rule().into(),
+            auto_link: config.auto_link(),
+            link_pasted_urls: config.link_pasted_urls(),
+            at_room_detection: config.at_room_detection(),
+            at_room_detection_in_quotes: config.at_room_detection_in_quotes(),
+            can_notify_room: config.can_notify_room(),
+            max_length: config.max_length().map(|l| l as u32),
+            max_paste_nodes: config.max_paste_nodes().map(|l| l as u32),
+            max_paste_length: config.max_paste_length().map(|l| l as u32),
+            max_paste_list_depth: config
+                .max_paste_list_depth()
+                .map(|l| l as u32),
+            markdown_shortcuts: config.markdown_shortcuts(),
+            allowed_link_schemes: config.allowed_link_schemes().map(|s| {
+                s.iter().map(|scheme| JsValue::from_str(scheme)).collect()
+            }),
+            disallowed_actions: config
+                .disallowed_actions()
+                .iter()
+                .map(|action| JsValue::from_str(action.as_ref()))
+                .collect(),
+            lenient_parsing: config.lenient_parsing(),
+            plain_text_lists: config.plain_text_lists(),
+            range_updates: config.range_updates(),
+            block_ids: config.block_ids(),
+            menu_state_deltas: config.menu_state_deltas(),
+            normalize_typed_nbsp: config.normalize_typed_nbsp(),
+            strip_invisible_characters: config.strip_invisible_characters(),
+            record_operations: config.record_operations(),
+            styles: config.styles().iter().fold(
+                js_sys::Map::new(),
+                |styles, (name, formats)| {
+                    let actions: js_sys::Array = formats
+                        .iter()
+                        .map(|f| JsValue::from_str(f.action().as_ref()))
+                        .collect();
+                    styles.set(&JsValue::from_str(name), &actions);
+                    styles
+                },
+            ),
+            suggestion_triggers: config.suggestion_triggers().iter().fold(
+                js_sys::Map::new(),
+                |triggers, (trigger, key)| {
+                    triggers.set(
+                        &JsValue::from_str(&trigger.to_string()),
+                        &PatternKey::from(key.clone()).into(),
+                    );
+                    triggers
+                },
+            ),
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum WhitespacePolicy {
+    Collapse,
+    Preserve,
+}
+
+impl From<WhitespacePolicy> for wysiwyg::WhitespacePolicy {
+    fn from(policy: WhitespacePolicy) -> Self {
+        match policy {
+            WhitespacePolicy::Collapse => Self::Collapse,
+            WhitespacePolicy::Preserve => Self::Preserve,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum PlaceholderPolicy {
+    EmptyBlocks,
+    Never,
+}
+
+impl From<PlaceholderPolicy> for wysiwyg::PlaceholderPolicy {
+    fn from(policy: PlaceholderPolicy) -> Self {
+        match policy {
+            PlaceholderPolicy::EmptyBlocks => Self::EmptyBlocks,
+            PlaceholderPolicy::Never => Self::Never,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum MarkdownMentions {
+    Link,
+    MxId,
+    DisplayText,
+}
+
+impl From<MarkdownMentions> for wysiwyg::MarkdownMentions {
+    fn from(mentions: MarkdownMentions) -> Self {
+        match mentions {
+            MarkdownMentions::Link => Self::Link,
+            MarkdownMentions::MxId => Self::MxId,
+            MarkdownMentions::DisplayText => Self::DisplayText,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum Direction {
+    Forwards,
+    Backwards,
+}
+
+impl From<Direction> for wysiwyg::Direction {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Forwards => Self::Forwards,
+            Direction::Backwards => Self::Backwards,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum Granularity {
+    Character,
+    Word,
+    Block,
+    Document,
+}
+
+impl From<Granularity> for wysiwyg::Granularity {
+    fn from(granularity: Granularity) -> Self {
+        match granularity {
+            Granularity::Character => Self::Character,
+            Granularity::Word => Self::Word,
+            Granularity::Block => Self::Block,
+            Granularity::Document => Self::Document,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum HistoryEntryKind {
+    Typing,
+    Deletion,
+    NewLine,
+    Formatting,
+    List,
+    Indent,
+    Link,
+    Mention,
+    Paste,
+    Move,
+}
+
+impl From<wysiwyg::HistoryEntryKind> for HistoryEntryKind {
+    fn from(kind: wysiwyg::HistoryEntryKind) -> Self {
+        match kind {
+            wysiwyg::HistoryEntryKind::Typing => Self::Typing,
+            wysiwyg::HistoryEntryKind::Deletion => Self::Deletion,
+            wysiwyg::HistoryEntryKind::NewLine => Self::NewLine,
+            wysiwyg::HistoryEntryKind::Formatting => Self::Formatting,
+            wysiwyg::HistoryEntryKind::List => Self::List,
+            wysiwyg::HistoryEntryKind::Indent => Self::Indent,
+            wysiwyg::HistoryEntryKind::Link => Self::Link,
+            wysiwyg::HistoryEntryKind::Mention => Self::Mention,
+            wysiwyg::HistoryEntryKind::Paste => Self::Paste,
+            wysiwyg::HistoryEntryKind::Move => Self::Move,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct HistoryEntry {
+    pub kind: HistoryEntryKind,
+    pub length_delta: isize,
+    pub timestamp_millis: f64,
+    pub is_undone: bool,
+}
+
+impl From<wysiwyg::HistoryEntry> for HistoryEntry {
+    fn from(entry: wysiwyg::HistoryEntry) -> Self {
+        Self {
+            kind: entry.kind.into(),
+            length_delta: entry.length_delta,
+            timestamp_millis: entry.timestamp_millis as f64,
+            is_undone: entry.is_undone,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum DeletedMentionKind {
+    User,
+    RoomId,
+    RoomAlias,
+    AtRoom,
+}
+
+impl From<wysiwyg::DeletedMentionKind> for DeletedMentionKind {
+    fn from(kind: wysiwyg::DeletedMentionKind) -> Self {
+        match kind {
+            wysiwyg::DeletedMentionKind::User => Self::User,
+            wysiwyg::DeletedMentionKind::RoomId => Self::RoomId,
+            wysiwyg::DeletedMentionKind::RoomAlias => Self::RoomAlias,
+            wysiwyg::DeletedMentionKind::AtRoom => Self::AtRoom,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct DeletedMention {
+    pub kind: DeletedMentionKind,
+    pub uri: Option<String>,
+    pub mx_id: Option<String>,
+    pub display_text: String,
+    pub utf16_codeunit: u32,
+}
 
-    pub fn set_custom_suggestion_patterns(
-        &mut self,
-        custom_suggestion_patterns: js_sys::Array,
-    ) {
-        self.inner.set_custom_suggestion_patterns(
-            custom_suggestion_patterns.into_vec(),
-        );
+impl From<wysiwyg::DeletedMention> for DeletedMention {
+    fn from(mention: wysiwyg::DeletedMention) -> Self {
+        let location: usize = mention.location.into();
+        Self {
+            kind: mention.kind.into(),
+            uri: mention.uri,
+            mx_id: mention.mx_id,
+            display_text: mention.display_text,
+            utf16_codeunit: u32::try_from(location).unwrap(),
+        }
     }
+}
 
-    /// Creates an at-room mention node and inserts it into the composer at the current selection
-    pub fn insert_at_room_mention(
-        &mut self,
-        attributes: js_sys::Map,
-    ) -> ComposerUpdate {
-        ComposerUpdate::from(
-            self.inner.insert_at_room_mention(attributes.into_vec()),
-        )
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct MappedRange {
+    pub start_utf16_codeunit: u32,
+    pub end_utf16_codeunit: u32,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct PasteTruncation {
+    pub too_many_nodes: bool,
+    pub too_long: bool,
+    pub lists_too_deep: bool,
+}
+
+impl From<wysiwyg::PasteTruncation> for PasteTruncation {
+    fn from(truncation: wysiwyg::PasteTruncation) -> Self {
+        Self {
+            too_many_nodes: truncation.too_many_nodes,
+            too_long: truncation.too_long,
+            lists_too_deep: truncation.lists_too_deep,
+        }
     }
+}
 
-    /// Creates a mention node and inserts it into the composer at the current selection
-    pub fn insert_mention(
-        &mut self,
-        url: &str,
-        text: &str,
-        attributes: js_sys::Map,
-    ) -> ComposerUpdate {
-        ComposerUpdate::from(self.inner.insert_mention(
-            Utf16String::from_str(url),
-            Utf16String::from_str(&html_escape::encode_safe(&text)),
-            attributes.into_vec(),
-        ))
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct StrippedCharacters {
+    pub soft_hyphens: u32,
+    pub zero_width: u32,
+    pub directional_marks: u32,
+}
+
+impl From<wysiwyg::StrippedCharacters> for StrippedCharacters {
+    fn from(stripped: wysiwyg::StrippedCharacters) -> Self {
+        Self {
+            soft_hyphens: u32::try_from(stripped.soft_hyphens).unwrap(),
+            zero_width: u32::try_from(stripped.zero_width).unwrap(),
+            directional_marks: u32::try_from(stripped.directional_marks)
+                .unwrap(),
+        }
     }
+}
 
-    /// Creates an at-room mention node and inserts it into the composer, replacing the
-    /// text content defined by the suggestion
-    pub fn insert_at_room_mention_at_suggestion(
-        &mut self,
-        suggestion: &SuggestionPattern,
-        attributes: js_sys::Map,
-    ) -> ComposerUpdate {
-        ComposerUpdate::from(self.inner.insert_at_room_mention_at_suggestion(
-            wysiwyg::SuggestionPattern::from(suggestion.clone()),
-            attributes.into_vec(),
-        ))
+/// Microseconds, capped so that they fit in a JS number.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct UpdateTiming {
+    pub operation_micros: u32,
+    pub serialization_micros: u32,
+}
+
+impl From<wysiwyg::UpdateTiming> for UpdateTiming {
+    fn from(timing: wysiwyg::UpdateTiming) -> Self {
+        let micros = |m: u64| u32::try_from(m).unwrap_or(u32::MAX);
+        Self {
+            operation_micros: micros(timing.operation_micros),
+            serialization_micros: micros(timing.serialization_micros),
+        }
     }
+}
 
-    /// Creates a mention node and inserts it into the composer, replacing the
-    /// text content defined by the suggestion
-    pub fn insert_mention_at_suggestion(
-        &mut self,
-        url: &str,
-        text: &str,
-        suggestion: &SuggestionPattern,
-        attributes: js_sys::Map,
-    ) -> ComposerUpdate {
-        ComposerUpdate::from(self.inner.insert_mention_at_suggestion(
-            Utf16String::from_str(url),
-            Utf16String::from_str(&html_escape::encode_safe(&text)),
-            wysiwyg::SuggestionPattern::from(suggestion.clone()),
-            attributes.into_vec(),
-        ))
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum DecorationKind {
+    SpellingError,
+    GrammarSuggestion,
+    SearchHighlight,
+    Placeholder,
+}
+
+impl From<DecorationKind> for wysiwyg::DecorationKind {
+    fn from(kind: DecorationKind) -> Self {
+        match kind {
+            DecorationKind::SpellingError => Self::SpellingError,
+            DecorationKind::GrammarSuggestion => Self::GrammarSuggestion,
+            DecorationKind::SearchHighlight => Self::SearchHighlight,
+            DecorationKind::Placeholder => Self::Placeholder,
+        }
     }
+}
 
-    pub fn remove_links(&mut self) -> ComposerUpdate {
-        ComposerUpdate::from(self.inner.remove_links())
+impl From<wysiwyg::DecorationKind> for DecorationKind {
+    fn from(kind: wysiwyg::DecorationKind) -> Self {
+        match kind {
+            wysiwyg::DecorationKind::SpellingError => Self::SpellingError,
+            wysiwyg::DecorationKind::GrammarSuggestion => {
+                Self::GrammarSuggestion
+            }
+            wysiwyg::DecorationKind::SearchHighlight => Self::SearchHighlight,
+            wysiwyg::DecorationKind::Placeholder => Self::Placeholder,
+        }
     }
 }
 
 #[wasm_bindgen]
-pub struct ComposerUpdate {
-    inner: wysiwyg::ComposerUpdate<Utf16String>,
+#[derive(Clone, Copy)]
+pub struct Decoration {
+    pub id: u64,
+    pub kind: DecorationKind,
+    pub start_utf16_codeunit: u32,
+    pub end_utf16_codeunit: u32,
 }
 
-impl ComposerUpdate {
-    fn from(inner: wysiwyg::ComposerUpdate<Utf16String>) -> Self {
-        Self { inner }
+impl From<&wysiwyg::Decoration> for Decoration {
+    fn from(decoration: &wysiwyg::Decoration) -> Self {
+        let start: usize = decoration.start.into();
+        let end: usize = decoration.end.into();
+        Self {
+            id: decoration.id,
+            kind: decoration.kind.into(),
+            start_utf16_codeunit: u32::try_from(start).unwrap(),
+            end_utf16_codeunit: u32::try_from(end).unwrap(),
+        }
     }
 }
 
-#[wasm_bindgen]
-impl ComposerUpdate {
-    pub fn text_update(&self) -> TextUpdate {
-        TextUpdate::from(self.inner.text_update.clone())
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct Word {
+    pub text: String,
+    pub start_utf16_codeunit: u32,
+    pub end_utf16_codeunit: u32,
+}
+
+impl From<wysiwyg::Word<Utf16String>> for Word {
+    fn from(word: wysiwyg::Word<Utf16String>) -> Self {
+        let start: usize = word.start.into();
+        let end: usize = word.end.into();
+        Self {
+            text: word.text.to_string(),
+            start_utf16_codeunit: u32::try_from(start).unwrap(),
+            end_utf16_codeunit: u32::try_from(end).unwrap(),
+        }
     }
+}
 
-    pub fn menu_state(&self) -> MenuState {
-        MenuState::from(self.inner.menu_state.clone())
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct ContentLength {
+    pub utf16_code_units: u32,
+    /// The characters users see, e.g. for a character counter
+    pub graphemes: u32,
+    /// The length of each top-level block, by its index
+    pub block_utf16_code_units: Vec<u32>,
+    pub block_graphemes: Vec<u32>,
+}
+
+impl From<wysiwyg::ContentLength> for ContentLength {
+    fn from(length: wysiwyg::ContentLength) -> Self {
+        let to_u32 = |n: usize| u32::try_from(n).unwrap();
+        Self {
+            utf16_code_units: to_u32(length.total.code_units),
+            graphemes: to_u32(length.total.graphemes),
+            block_utf16_code_units: length
+                .blocks
+                .iter()
+                .map(|block| to_u32(block.code_units))
+                .collect(),
+            block_graphemes: length
+                .blocks
+                .iter()
+                .map(|block| to_u32(block.graphemes))
+                .collect(),
+        }
     }
+}
 
-    pub fn menu_action(&self) -> MenuAction {
-        MenuAction::from(self.inner.menu_action.clone())
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct RangeQuery {
+    /// The kinds of leaf node in the range, e.g. "Text" or "Mention"
+    pub leaf_kinds: js_sys::Array,
+    /// The kinds of block the range is in, e.g. "Paragraph" or "ListItem",
+    /// outermost first
+    pub block_kinds: js_sys::Array,
+    /// The names of the formatting actions of the inline formats applied to
+    /// any of the range, as in `action_states`
+    pub formats: js_sys::Array,
+    pub has_link: bool,
+}
+
+impl From<wysiwyg::RangeQuery> for RangeQuery {
+    fn from(query: wysiwyg::RangeQuery) -> Self {
+        Self {
+            leaf_kinds: query
+                .leaf_kinds
+                .iter()
+                .map(|kind| JsValue::from_str(kind.as_ref()))
+                .collect(),
+            block_kinds: query
+                .block_kinds
+                .iter()
+                .map(|kind| JsValue::from_str(kind.as_ref()))
+                .collect(),
+            formats: query
+                .formats
+                .iter()
+                .map(|format| JsValue::from_str(format.action().as_ref()))
+                .collect(),
+            has_link: query.has_link,
+        }
     }
 }
 
@@ -434,6 +1684,8 @@ impl ComposerUpdate {
 pub enum DomCreationError {
     HtmlParseError,
     MarkdownParseError,
+    OperationsParseError,
+    EventContentParseError,
 }
 
 impl Display for DomCreationError {
@@ -445,6 +1697,12 @@ impl Display for DomCreationError {
             DomCreationError::MarkdownParseError => {
                 "could not create dom from markdown"
             }
+            DomCreationError::OperationsParseError => {
+                "could not parse operations"
+            }
+            DomCreationError::EventContentParseError => {
+                "could not parse event content"
+            }
         })
     }
 }
@@ -468,54 +1726,66 @@ impl From<DomCreationError> for wysiwyg::DomCreationError {
     }
 }
 
-#[wasm_bindgen(getter_with_clone)]
+fn block_indices(handles: &[wysiwyg::DomHandle]) -> Vec<u32> {
+    handles
+        .iter()
+        .map(|h| u32::try_from(h.index_in_parent()).unwrap())
+        .collect()
+}
+
+/// The text update of a [ComposerUpdate], shared by the objects giving JS
+/// access to it. Its replacement HTML is only converted to a string the
+/// first time it is read, as most updates are only checked for their menu
+/// state.
+struct SharedTextUpdate {
+    inner: wysiwyg::TextUpdate<Utf16String>,
+    replacement_html: OnceCell<String>,
+}
+
+#[wasm_bindgen]
 pub struct TextUpdate {
-    pub keep: Option<Keep>,
-    pub replace_all: Option<ReplaceAll>,
-    pub select: Option<Selection>,
+    shared: Rc<SharedTextUpdate>,
 }
 
+#[wasm_bindgen]
 impl TextUpdate {
-    pub fn from(inner: wysiwyg::TextUpdate<Utf16String>) -> Self {
-        match inner {
-            wysiwyg::TextUpdate::Keep => Self {
-                keep: Some(Keep),
-                replace_all: None,
-                select: None,
+    #[wasm_bindgen(getter)]
+    pub fn keep(&self) -> Option<Keep> {
+        matches!(self.shared.inner, wysiwyg::TextUpdate::Keep).then_some(Keep)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn replace_all(&self) -> Option<ReplaceAll> {
+        matches!(self.shared.inner, wysiwyg::TextUpdate::ReplaceAll(_)).then(
+            || ReplaceAll {
+                shared: Rc::clone(&self.shared),
             },
-            wysiwyg::TextUpdate::ReplaceAll(r) => {
-                let start_utf16_codeunit: usize = r.start.into();
-                let end_utf16_codeunit: usize = r.end.into();
-                Self {
-                    keep: None,
-                    replace_all: Some(ReplaceAll {
-                        replacement_html: r.replacement_html.to_string(),
-                        start_utf16_codeunit: u32::try_from(
-                            start_utf16_codeunit,
-                        )
-                        .unwrap(),
-                        end_utf16_codeunit: u32::try_from(end_utf16_codeunit)
-                            .unwrap(),
-                    }),
-                    select: None,
-                }
-            }
+        )
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn replace_range(&self) -> Option<ReplaceRange> {
+        matches!(self.shared.inner, wysiwyg::TextUpdate::ReplaceRange(_)).then(
+            || ReplaceRange {
+                shared: Rc::clone(&self.shared),
+            },
+        )
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn select(&self) -> Option<Selection> {
+        match &self.shared.inner {
             wysiwyg::TextUpdate::Select(s) => {
                 let start_utf16_codeunit: usize = s.start.into();
                 let end_utf16_codeunit: usize = s.end.into();
-                Self {
-                    keep: None,
-                    replace_all: None,
-                    select: Some(Selection {
-                        start_utf16_codeunit: u32::try_from(
-                            start_utf16_codeunit,
-                        )
+                Some(Selection {
+                    start_utf16_codeunit: u32::try_from(start_utf16_codeunit)
                         .unwrap(),
-                        end_utf16_codeunit: u32::try_from(end_utf16_codeunit)
-                            .unwrap(),
-                    }),
-                }
+                    end_utf16_codeunit: u32::try_from(end_utf16_codeunit)
+                        .unwrap(),
+                })
             }
+            _ => None,
         }
     }
 }
@@ -525,11 +1795,91 @@ impl TextUpdate {
 pub struct Keep;
 
 #[derive(Clone)]
-#[wasm_bindgen(getter_with_clone)]
+#[wasm_bindgen]
 pub struct ReplaceAll {
-    pub replacement_html: String,
-    pub start_utf16_codeunit: u32,
-    pub end_utf16_codeunit: u32,
+    shared: Rc<SharedTextUpdate>,
+}
+
+impl ReplaceAll {
+    fn inner(&self) -> &wysiwyg::ReplaceAll<Utf16String> {
+        match &self.shared.inner {
+            wysiwyg::TextUpdate::ReplaceAll(r) => r,
+            _ => unreachable!("ReplaceAll is only created for ReplaceAll"),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ReplaceAll {
+    #[wasm_bindgen(getter)]
+    pub fn replacement_html(&self) -> String {
+        self.shared
+            .replacement_html
+            .get_or_init(|| self.inner().replacement_html.to_string())
+            .clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn start_utf16_codeunit(&self) -> u32 {
+        let start: usize = self.inner().start.into();
+        u32::try_from(start).unwrap()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn end_utf16_codeunit(&self) -> u32 {
+        let end: usize = self.inner().end.into();
+        u32::try_from(end).unwrap()
+    }
+}
+
+/// Replaces `removed_nodes` top-level nodes of the content, from
+/// `first_node`, with the nodes of the replacement HTML.
+#[derive(Clone)]
+#[wasm_bindgen]
+pub struct ReplaceRange {
+    shared: Rc<SharedTextUpdate>,
+}
+
+impl ReplaceRange {
+    fn inner(&self) -> &wysiwyg::ReplaceRange<Utf16String> {
+        match &self.shared.inner {
+            wysiwyg::TextUpdate::ReplaceRange(r) => r,
+            _ => unreachable!("ReplaceRange is only created for ReplaceRange"),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ReplaceRange {
+    #[wasm_bindgen(getter)]
+    pub fn first_node(&self) -> u32 {
+        u32::try_from(self.inner().first_node).unwrap()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn removed_nodes(&self) -> u32 {
+        u32::try_from(self.inner().removed_nodes).unwrap()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn replacement_html(&self) -> String {
+        self.shared
+            .replacement_html
+            .get_or_init(|| self.inner().replacement_html.to_string())
+            .clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn start_utf16_codeunit(&self) -> u32 {
+        let start: usize = self.inner().start.into();
+        u32::try_from(start).unwrap()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn end_utf16_codeunit(&self) -> u32 {
+        let end: usize = self.inner().end.into();
+        u32::try_from(end).unwrap()
+    }
 }
 
 #[derive(Clone)]
@@ -613,6 +1963,19 @@ impl MenuAction {
             _ => None,
         }
     }
+
+    pub fn url_detected(&self) -> Option<MenuActionUrlDetected> {
+        match &self.inner {
+            wysiwyg::MenuAction::UrlDetected { url, start, end } => {
+                Some(MenuActionUrlDetected {
+                    url: url.clone(),
+                    start: u32::try_from(*start).unwrap(),
+                    end: u32::try_from(*end).unwrap(),
+                })
+            }
+            _ => None,
+        }
+    }
 }
 
 #[wasm_bindgen(getter_with_clone)]
@@ -621,6 +1984,14 @@ pub struct MenuActionSuggestion {
     pub suggestion_pattern: SuggestionPattern,
 }
 
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone)]
+pub struct MenuActionUrlDetected {
+    pub url: String,
+    pub start: u32,
+    pub end: u32,
+}
+
 #[wasm_bindgen]
 #[derive(Clone)]
 pub enum ComposerAction {
@@ -803,70 +2174,120 @@ impl FromIterator<DomHandle> for DomChildren {
 /// Refers to a node in the composer model.
 pub struct DomHandle {
     inner: wysiwyg::DomHandle,
+    /// The generation of the model when we were created.
+    generation: u64,
+}
+
+impl DomHandle {
+    fn lookup<'a>(
+        &self,
+        model: &'a ComposerModel,
+    ) -> Result<&'a wysiwyg::DomNode<Utf16String>, DomHandleError> {
+        Ok(model
+            .inner
+            .lookup_node_at_generation(&self.inner, self.generation)?)
+    }
 }
 
 #[wasm_bindgen]
 impl DomHandle {
     /// Returns "container", "line_break", "text" or "zwsp" depending on the type of
     /// node we refer to.
-    /// Panics if we are not a valid reference (because the model has changed
-    /// since we were created, or because you passed in a different model
-    /// from the one that created us.)
-    pub fn node_type(&self, model: &ComposerModel) -> String {
-        let node = model.inner.state.dom.lookup_node(&self.inner);
-        String::from(match node {
+    /// Fails with DomHandleError.StaleHandle if we are not a valid reference
+    /// (because the model has changed since we were created, or because you
+    /// passed in a different model from the one that created us.)
+    pub fn node_type(
+        &self,
+        model: &ComposerModel,
+    ) -> Result<String, DomHandleError> {
+        let node = self.lookup(model)?;
+        Ok(String::from(match node {
             wysiwyg::DomNode::Container(_) => "container",
             wysiwyg::DomNode::LineBreak(_) => "line_break",
             wysiwyg::DomNode::Mention(_) => "mention",
             wysiwyg::DomNode::Text(_) => "text",
-        })
+        }))
     }
 
     /// Returns a list of our children nodes, or an empty list if we refer
     /// to a text or line break node.
-    /// Panics if we are not a valid reference (because the model has changed
-    /// since we were created, or because you passed in a different model
-    /// from the one that created us.)
-    pub fn children(&self, model: &ComposerModel) -> DomChildren {
-        let node = model.inner.state.dom.lookup_node(&self.inner);
-        match node {
+    /// Fails with DomHandleError.StaleHandle if we are not a valid reference
+    /// (because the model has changed since we were created, or because you
+    /// passed in a different model from the one that created us.)
+    pub fn children(
+        &self,
+        model: &ComposerModel,
+    ) -> Result<DomChildren, DomHandleError> {
+        let node = self.lookup(model)?;
+        Ok(match node {
             wysiwyg::DomNode::Container(node) => node
                 .children()
                 .iter()
                 .map(|child| DomHandle {
                     inner: child.handle(),
+                    generation: self.generation,
                 })
                 .collect(),
             _ => DomChildren::new(),
-        }
+        })
     }
 
     /// Returns the text of this node, or an empty string if this is a
     /// container or line break.
-    /// Panics if we are not a valid reference (because the model has changed
-    /// since we were created, or because you passed in a different model
-    /// from the one that created us.)
-    pub fn text(&self, model: &ComposerModel) -> String {
-        let node = model.inner.state.dom.lookup_node(&self.inner);
-        match node {
+    /// Fails with DomHandleError.StaleHandle if we are not a valid reference
+    /// (because the model has changed since we were created, or because you
+    /// passed in a different model from the one that created us.)
+    pub fn text(
+        &self,
+        model: &ComposerModel,
+    ) -> Result<String, DomHandleError> {
+        let node = self.lookup(model)?;
+        Ok(match node {
             wysiwyg::DomNode::Container(_) => String::from(""),
             wysiwyg::DomNode::LineBreak(_) => String::from(""),
             wysiwyg::DomNode::Mention(node) => node.display_text().to_string(),
             wysiwyg::DomNode::Text(node) => node.data().to_string(),
-        }
+        })
     }
 
     /// Returns our tagname, or "-text-"/"-zwsp-" if we are a text/zwsp node.
-    /// Panics if we are not a valid reference (because the model has changed
-    /// since we were created, or because you passed in a different model
-    /// from the one that created us.)
-    pub fn tag(&self, model: &ComposerModel) -> String {
-        let node = model.inner.state.dom.lookup_node(&self.inner);
-        match node {
+    /// Fails with DomHandleError.StaleHandle if we are not a valid reference
+    /// (because the model has changed since we were created, or because you
+    /// passed in a different model from the one that created us.)
+    pub fn tag(&self, model: &ComposerModel) -> Result<String, DomHandleError> {
+        let node = self.lookup(model)?;
+        Ok(match node {
             wysiwyg::DomNode::Container(node) => node.name().to_string(),
             wysiwyg::DomNode::LineBreak(node) => node.name().to_string(),
             wysiwyg::DomNode::Mention(node) => node.name().to_string(),
             wysiwyg::DomNode::Text(_) => String::from("-text-"),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+#[wasm_bindgen]
+pub enum DomHandleError {
+    StaleHandle,
+    InvalidHandle,
+}
+
+impl Display for DomHandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DomHandleError::StaleHandle => {
+                "the model has changed since this handle was created"
+            }
+            DomHandleError::InvalidHandle => "this handle refers to no node",
+        })
+    }
+}
+
+impl From<wysiwyg::HandleLookupError> for DomHandleError {
+    fn from(e: wysiwyg::HandleLookupError) -> Self {
+        match e {
+            wysiwyg::HandleLookupError::StaleHandle { .. } => Self::StaleHandle,
+            wysiwyg::HandleLookupError::InvalidHandle(_) => Self::InvalidHandle,
         }
     }
 }
@@ -883,6 +2304,9 @@ pub struct Create;
 #[wasm_bindgen(getter_with_clone)]
 pub struct Edit {
     pub url: String,
+    /// The attributes of the link other than `href`, to pass back to
+    /// `set_link` so that they are kept
+    pub attributes: js_sys::Map,
 }
 
 #[derive(Clone)]
@@ -912,12 +2336,19 @@ impl From<wysiwyg::LinkAction<Utf16String>> for LinkAction {
                 edit_link: None,
                 disabled: None,
             },
-            wysiwyg::LinkAction::Edit(url) => {
+            wysiwyg::LinkAction::Edit(url, attrs) => {
                 let url = url.to_string();
+                let attributes = js_sys::Map::new();
+                for (name, value) in attrs {
+                    attributes.set(
+                        &name.to_string().into(),
+                        &value.to_string().into(),
+                    );
+                }
                 Self {
                     create_with_text: None,
                     create: None,
-                    edit_link: Some(Edit { url }),
+                    edit_link: Some(Edit { url, attributes }),
                     disabled: None,
                 }
             }
@@ -958,9 +2389,11 @@ mod test {
         let mut model = ComposerModel::new();
         model.replace_text("foo");
 
-        assert_eq!(model.document().node_type(&model), "container");
+        assert_eq!(model.document().node_type(&model).unwrap(), "container");
         assert_eq!(
-            model.document().children(&model).inner[0].node_type(&model),
+            model.document().children(&model).unwrap().inner[0]
+                .node_type(&model)
+                .unwrap(),
             "text"
         );
     }
@@ -974,23 +2407,24 @@ mod test {
         model.select(2, 3);
         model.italic();
 
-        let children = model.document().children(&model).inner;
-        let grandchildren = children[1].children(&model).inner;
-        let great_grandchildren = grandchildren[1].children(&model).inner;
-
-        assert_eq!(children[0].node_type(&model), "text");
-        assert_eq!(children[0].text(&model), "0");
-        assert_eq!(children[1].node_type(&model), "container");
-        assert_eq!(children[1].tag(&model), "strong");
-        assert_eq!(grandchildren[0].node_type(&model), "text");
-        assert_eq!(grandchildren[0].text(&model), "1");
-        assert_eq!(grandchildren[1].node_type(&model), "container");
-        assert_eq!(grandchildren[1].tag(&model), "em");
-        assert_eq!(great_grandchildren[0].node_type(&model), "text");
-        assert_eq!(great_grandchildren[0].text(&model), "2");
-        assert_eq!(grandchildren[2].node_type(&model), "text");
-        assert_eq!(grandchildren[2].text(&model), "3");
-        assert_eq!(children[2].node_type(&model), "text");
-        assert_eq!(children[2].text(&model), "4");
+        let children = model.document().children(&model).unwrap().inner;
+        let grandchildren = children[1].children(&model).unwrap().inner;
+        let great_grandchildren =
+            grandchildren[1].children(&model).unwrap().inner;
+
+        assert_eq!(children[0].node_type(&model).unwrap(), "text");
+        assert_eq!(children[0].text(&model).unwrap(), "0");
+        assert_eq!(children[1].node_type(&model).unwrap(), "container");
+        assert_eq!(children[1].tag(&model).unwrap(), "strong");
+        assert_eq!(grandchildren[0].node_type(&model).unwrap(), "text");
+        assert_eq!(grandchildren[0].text(&model).unwrap(), "1");
+        assert_eq!(grandchildren[1].node_type(&model).unwrap(), "container");
+        assert_eq!(grandchildren[1].tag(&model).unwrap(), "em");
+        assert_eq!(great_grandchildren[0].node_type(&model).unwrap(), "text");
+        assert_eq!(great_grandchildren[0].text(&model).unwrap(), "2");
+        assert_eq!(grandchildren[2].node_type(&model).unwrap(), "text");
+        assert_eq!(grandchildren[2].text(&model).unwrap(), "3");
+        assert_eq!(children[2].node_type(&model).unwrap(), "text");
+        assert_eq!(children[2].text(&model).unwrap(), "4");
     }
 }
@@ -0,0 +1,198 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Benchmarks of the Dom algorithms behind common editing operations. Run
+//! with `cargo bench -p wysiwyg`, optionally followed by `-- <filter>`.
+//!
+//! With `CHECK_PERFORMANCE_BUDGET` set, each operation is also timed
+//! against a budget once the benchmarks have run, and any which go over are
+//! reported. The budgets are generous enough to absorb noise, so going over
+//! one on a developer machine points at an algorithmic regression rather
+//! than a small slowdown.
+
+use std::env;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use criterion::{BatchSize, Criterion};
+use widestring::Utf16String;
+use wysiwyg::{ComposerModel, HtmlSource, Location, ToHtml};
+
+type Model = ComposerModel<Utf16String>;
+
+const GOOGLE_DOC_HTML_PASTEBOARD: &str =
+    include_str!("../fixtures/google_doc_pasteboard.html");
+
+/// How many times each operation is timed when checking its budget. The
+/// fastest run is compared with the budget.
+const BUDGET_RUNS: usize = 10;
+
+struct Scenario {
+    name: &'static str,
+    /// The longest one run of the operation may take.
+    budget: Duration,
+    model: Model,
+    run: fn(&mut Model),
+}
+
+/// About 100KB of HTML mixing formatting, links, mentions and lists.
+fn large_document() -> Model {
+    let paragraph = "<p>Some <strong>bold</strong> and <em>italic</em> \
+        text with a <a href=\"https://element.io\">link</a>, a \
+        <a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a> \
+        mention, an &amp; and <code>inline  code</code>.</p>\
+        <ul><li>An item</li><li>Another  item </li></ul>";
+    let mut html = String::new();
+    while html.len() < 100_000 {
+        html.push_str(paragraph);
+    }
+    model_from_html(&html)
+}
+
+/// Lists and quotes nested as deep as parsing allows, each level with
+/// formatted text, repeated to make a sizeable document.
+fn deep_document() -> Model {
+    fn nested(depth: usize) -> String {
+        if depth == 0 {
+            return "<p>leaf <b>bold <i>italic <u>underlined</u></i></b></p>"
+                .to_owned();
+        }
+        let inner = nested(depth - 1);
+        if depth.is_multiple_of(2) {
+            format!("<ul><li><p>item {depth}</p>{inner}</li><li>next</li></ul>")
+        } else {
+            format!("<blockquote><p>quote {depth}</p>{inner}</blockquote>")
+        }
+    }
+    model_from_html(&nested(40).repeat(20))
+}
+
+fn model_from_html(html: &str) -> Model {
+    let mut model = Model::new();
+    model
+        .set_content_from_html(&Utf16String::from_str(html))
+        .unwrap();
+    model
+}
+
+fn scenarios() -> Vec<Scenario> {
+    let large = large_document();
+    let len = large.state.dom.text_len();
+
+    let mut typing = large.clone();
+    typing.select(Location::from(len / 2), Location::from(len / 2));
+    let mut selected = large.clone();
+    selected.select(Location::from(0), Location::from(len));
+    let mut deep = deep_document();
+    let deep_len = deep.state.dom.text_len();
+    deep.select(Location::from(deep_len / 2), Location::from(deep_len / 2));
+
+    vec![
+        Scenario {
+            name: "replace_text in a 100KB document",
+            budget: Duration::from_millis(100),
+            model: typing,
+            run: |model| {
+                model.replace_text(Utf16String::from_str("a"));
+            },
+        },
+        Scenario {
+            name: "bold over a 100KB selection",
+            budget: Duration::from_secs(1),
+            model: selected,
+            run: |model| {
+                model.bold();
+            },
+        },
+        Scenario {
+            name: "paste from Google Docs",
+            budget: Duration::from_millis(100),
+            model: Model::new(),
+            run: |model| {
+                model.replace_html(
+                    Utf16String::from_str(GOOGLE_DOC_HTML_PASTEBOARD),
+                    HtmlSource::GoogleDoc,
+                );
+            },
+        },
+        Scenario {
+            name: "move the cursor in a deep tree",
+            budget: Duration::from_millis(5),
+            model: deep.clone(),
+            run: |model| {
+                let next = Location::from(model.state.dom.text_len() / 2 + 1);
+                model.select(next, next);
+            },
+        },
+        Scenario {
+            name: "to_html of a deep tree",
+            budget: Duration::from_millis(5),
+            model: deep,
+            run: |model| {
+                black_box(model.state.dom.to_html());
+            },
+        },
+        Scenario {
+            name: "to_html of a 100KB document",
+            budget: Duration::from_millis(20),
+            model: large,
+            run: |model| {
+                black_box(model.state.dom.to_html());
+            },
+        },
+    ]
+}
+
+fn bench(criterion: &mut Criterion, scenario: &Scenario) {
+    criterion.bench_function(scenario.name, |b| {
+        b.iter_batched_ref(
+            || scenario.model.clone(),
+            scenario.run,
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+/// The fastest of [BUDGET_RUNS] runs of the scenario's operation.
+fn fastest_run(scenario: &Scenario) -> Duration {
+    (0..BUDGET_RUNS)
+        .map(|_| {
+            let mut model = scenario.model.clone();
+            let start = Instant::now();
+            (scenario.run)(&mut model);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn check_budget(scenarios: &[Scenario]) {
+    for scenario in scenarios {
+        let time = fastest_run(scenario);
+        println!(
+            "{}: {time:?} (budget {:?}){}",
+            scenario.name,
+            scenario.budget,
+            if time <= scenario.budget {
+                ""
+            } else {
+                " OVER BUDGET"
+            }
+        );
+    }
+}
+
+fn main() {
+    let scenarios = scenarios();
+    let mut criterion = Criterion::default().configure_from_args();
+    for scenario in &scenarios {
+        bench(&mut criterion, scenario);
+    }
+    criterion.final_summary();
+
+    if env::var_os("CHECK_PERFORMANCE_BUDGET").is_some() {
+        check_budget(&scenarios);
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Parse arbitrary input as HTML from every source, as pasted or loaded
+//! content reaches the parsers through the bindings. Parsing may fail, but
+//! must not panic, and what it parses must satisfy the invariants of the
+//! Dom and serialize to HTML which parses again.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use widestring::Utf16String;
+use wysiwyg::{
+    parse, parse_from_source_with_options, AtRoomDetection, AttributePolicy,
+    HtmlSource, ToHtml, ToMarkdown, ToRawText, ToTree, WhitespacePolicy,
+    DEFAULT_MAX_PARSE_DEPTH,
+};
+
+const SOURCES: [HtmlSource; 3] = [
+    HtmlSource::Matrix,
+    HtmlSource::GoogleDoc,
+    HtmlSource::UnknownExternal,
+];
+
+fuzz_target!(|data: &[u8]| {
+    let html = String::from_utf8_lossy(data);
+
+    if let Ok(dom) = parse::<Utf16String>(&html) {
+        dom.explicitly_assert_invariants();
+        check_serializes(&dom);
+    }
+
+    for source in SOURCES {
+        for lenient in [false, true] {
+            let parsed = parse_from_source_with_options::<Utf16String>(
+                &html,
+                source,
+                DEFAULT_MAX_PARSE_DEPTH,
+                &AttributePolicy::default(),
+                WhitespacePolicy::default(),
+                AtRoomDetection::default(),
+                lenient,
+            );
+            if let Ok((dom, _warnings)) = parsed {
+                dom.explicitly_assert_invariants();
+                check_serializes(&dom);
+            }
+        }
+    }
+});
+
+/// Serialize `dom` in every format, and check that its HTML parses again.
+fn check_serializes<D>(dom: &D)
+where
+    D: ToHtml<Utf16String>
+        + ToMarkdown<Utf16String>
+        + ToRawText<Utf16String>
+        + ToTree<Utf16String>,
+{
+    let html = dom.to_html().to_string();
+    let _ = dom.to_markdown();
+    let _ = dom.to_raw_text();
+    let _ = dom.to_tree();
+    if let Err(err) = parse::<Utf16String>(&html) {
+        panic!("Serialized HTML `{html}` does not parse again: {err:?}");
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::ChangedHandles;
+
+/// An id for each top-level node of the content, which stays the same while
+/// the node is edited, written as `data-block-id` when
+/// [crate::ComposerModelConfig::with_block_ids] is set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct BlockIds {
+    ids: Vec<u64>,
+    next: u64,
+}
+
+impl BlockIds {
+    /// Ids for content with `count` top-level nodes.
+    pub(crate) fn new(count: usize) -> Self {
+        let mut block_ids = Self::default();
+        block_ids.ids = block_ids.take_ids(count);
+        block_ids
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<u64> {
+        self.ids.get(index).copied()
+    }
+
+    /// Follow the changes made by an update: modified nodes keep the id of
+    /// the node they replace, added nodes get new ids and the ids of
+    /// removed nodes are dropped.
+    pub(crate) fn apply(&mut self, changes: &ChangedHandles) {
+        let Some(first) = changes.first_index() else {
+            return;
+        };
+        let start = (first + changes.modified.len()).min(self.ids.len());
+        let end = (start + changes.removed.len()).min(self.ids.len());
+        let added = self.take_ids(changes.added.len());
+        self.ids.splice(start..end, added);
+    }
+
+    fn take_ids(&mut self, count: usize) -> Vec<u64> {
+        let ids = (self.next..).take(count).collect();
+        self.next += count as u64;
+        ids
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlockIds;
+    use crate::ChangedHandles;
+
+    fn ids(block_ids: &BlockIds) -> Vec<u64> {
+        (0..).map_while(|i| block_ids.get(i)).collect()
+    }
+
+    #[test]
+    fn modified_nodes_keep_their_ids() {
+        let mut block_ids = BlockIds::new(3);
+        block_ids.apply(&ChangedHandles::between(&[1, 2, 3], &[1, 4, 3]));
+        assert_eq!(ids(&block_ids), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn added_nodes_get_new_ids() {
+        let mut block_ids = BlockIds::new(2);
+        block_ids.apply(&ChangedHandles::between(&[1, 3], &[1, 2, 2, 3]));
+        assert_eq!(ids(&block_ids), vec![0, 2, 3, 1]);
+    }
+
+    #[test]
+    fn ids_of_removed_nodes_are_dropped() {
+        let mut block_ids = BlockIds::new(4);
+        block_ids.apply(&ChangedHandles::between(&[1, 2, 2, 3], &[4, 3]));
+        assert_eq!(ids(&block_ids), vec![0, 3]);
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::DomHandle;
+
+/// The top-level nodes of the content which were added, removed or
+/// modified by an update, so that clients can re-render only those blocks.
+/// The replacement HTML of the update always holds the whole content, and
+/// can be used instead.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChangedHandles {
+    /// Nodes in the new content which take the place of no previous node
+    pub added: Vec<DomHandle>,
+    /// Nodes in the previous content which are no longer there
+    pub removed: Vec<DomHandle>,
+    /// Nodes in the new content which replace a different previous node
+    /// in the same place
+    pub modified: Vec<DomHandle>,
+}
+
+impl ChangedHandles {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+    }
+
+    /// The index of the first top-level node changed, if any.
+    pub(crate) fn first_index(&self) -> Option<usize> {
+        [&self.modified, &self.added, &self.removed]
+            .into_iter()
+            .find_map(|handles| handles.first())
+            .map(|handle| handle.raw()[0])
+    }
+
+    /// Compare the hashes of the top-level nodes before and after an
+    /// update. Nodes which are the same at the start and end of the content
+    /// are unchanged, and each node left in between is modified if there is
+    /// a node in its place in the old content, and added or removed
+    /// otherwise.
+    pub(crate) fn between(old: &[u64], new: &[u64]) -> Self {
+        let (prefix, suffix) = common_ends(old, new);
+        let old_end = old.len() - suffix;
+        let new_end = new.len() - suffix;
+        let modified_end = prefix + (old_end - prefix).min(new_end - prefix);
+        Self {
+            added: top_level_handles(modified_end..new_end),
+            removed: top_level_handles(modified_end..old_end),
+            modified: top_level_handles(prefix..modified_end),
+        }
+    }
+}
+
+/// How many hashes are the same at the start of `old` and `new`, and how
+/// many of the rest are the same at their end.
+pub(crate) fn common_ends(old: &[u64], new: &[u64]) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(o, n)| o == n).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count();
+    (prefix, suffix)
+}
+
+fn top_level_handles(indices: std::ops::Range<usize>) -> Vec<DomHandle> {
+    indices.map(|i| DomHandle::from_raw(vec![i])).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChangedHandles;
+    use crate::tests::testutils_composer_model::cm;
+    use crate::DomHandle;
+
+    fn handles(indices: &[usize]) -> Vec<DomHandle> {
+        indices
+            .iter()
+            .map(|i| DomHandle::from_raw(vec![*i]))
+            .collect()
+    }
+
+    #[test]
+    fn nothing_changes_between_the_same_hashes() {
+        assert!(ChangedHandles::between(&[1, 2, 3], &[1, 2, 3]).is_empty());
+    }
+
+    #[test]
+    fn nodes_in_the_same_place_are_modified() {
+        let changes = ChangedHandles::between(&[1, 2, 3], &[1, 4, 3]);
+        assert_eq!(changes.modified, handles(&[1]));
+        assert_eq!(changes.added, vec![]);
+        assert_eq!(changes.removed, vec![]);
+    }
+
+    #[test]
+    fn extra_nodes_are_added_or_removed() {
+        let added = ChangedHandles::between(&[1, 3], &[1, 2, 2, 3]);
+        assert_eq!(added.added, handles(&[1, 2]));
+        assert_eq!(added.modified, vec![]);
+
+        let removed = ChangedHandles::between(&[1, 2, 2, 3], &[4, 3]);
+        assert_eq!(removed.modified, handles(&[0]));
+        assert_eq!(removed.removed, handles(&[1, 2]));
+        assert_eq!(removed.added, vec![]);
+    }
+
+    #[test]
+    fn typing_modifies_only_the_paragraph_typed_in() {
+        let mut model = cm("<p>a</p><p>b|</p><p>c</p>");
+        let changes = model.replace_text("d".into()).changed_handles;
+        assert_eq!(changes.modified, handles(&[1]));
+        assert!(changes.added.is_empty() && changes.removed.is_empty());
+    }
+
+    #[test]
+    fn formatting_reports_the_paragraph_as_modified() {
+        let mut model = cm("<p>a</p><p>{b}|</p>");
+        assert_eq!(model.bold().changed_handles.modified, handles(&[1]));
+    }
+
+    #[test]
+    fn splitting_and_joining_paragraphs_adds_and_removes_one() {
+        let mut model = cm("<p>a</p><p>b|c</p><p>d</p>");
+        let split = model.enter().changed_handles;
+        assert_eq!(split.modified, handles(&[1]));
+        assert_eq!(split.added, handles(&[2]));
+
+        let joined = model.backspace().changed_handles;
+        assert_eq!(joined.modified, handles(&[1]));
+        assert_eq!(joined.removed, handles(&[2]));
+    }
+
+    #[test]
+    fn replacing_nothing_with_nothing_changes_nothing() {
+        let mut model = cm("<p>a|</p><p>b</p>");
+        model.select(0.into(), 0.into());
+        assert!(model.replace_text("".into()).changed_handles.is_empty());
+    }
+}
@@ -4,9 +4,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use strum_macros::{AsRefStr, EnumIter};
+use strum_macros::{AsRefStr, EnumIter, EnumString};
 
-#[derive(AsRefStr, Debug, Clone, EnumIter, Eq, Hash, PartialEq)]
+#[derive(AsRefStr, Debug, Clone, EnumIter, EnumString, Eq, Hash, PartialEq)]
 pub enum ComposerAction {
     Bold,
     Italic,
@@ -6,20 +6,30 @@
 
 pub mod base;
 pub mod code_block;
+pub mod content_length;
+pub mod decorations;
 pub mod delete_text;
 pub mod example_format;
+pub mod extend_selection;
 pub mod format;
 mod format_inline_code;
+pub mod generation;
 pub mod hyperlinks;
 pub mod lists;
 pub mod mentions;
 pub mod menu_action;
 pub mod menu_state;
+pub mod move_block;
 pub mod new_lines;
+pub mod operation_log;
 pub mod quotes;
+pub mod range_query;
 pub mod replace_html;
 pub mod replace_text;
 pub mod selection;
+pub mod snapshots;
+pub mod templates;
 pub mod undo_redo;
+pub mod words;
 
-pub use base::ComposerModel;
+pub use base::{ComposerModel, Utf16ComposerModel, Utf8ComposerModel};
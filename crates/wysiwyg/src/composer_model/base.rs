@@ -5,20 +5,73 @@
 // Please see LICENSE in the repository root for full details.
 
 use crate::action_state::ActionState;
-use crate::composer_model::menu_state::MenuStateComputeType;
+use crate::block_ids::BlockIds;
+use crate::composer_model::menu_state::{
+    MenuStateComputeType, ReversedActionsCache,
+};
+use crate::composer_model::replace_html::PendingPaste;
+use crate::composer_model::snapshots::Snapshots;
+use crate::composer_model::undo_redo::HistoryRecord;
 use crate::composer_state::ComposerState;
+use crate::decoration::Decorations;
+use crate::deleted_mention::DeletedMention;
 use crate::dom::parser::markdown::markdown_html_parser::MarkdownHTMLParser;
-use crate::dom::parser::parse;
+use crate::dom::parser::{parse, parse_from_source_with_options};
+use crate::dom::to_html::ToHtmlState;
+use crate::dom::to_markdown::MarkdownOptions;
 use crate::dom::to_plain_text::ToPlainText;
-use crate::dom::{Dom, DomCreationError, UnicodeString};
+use crate::dom::unicode_string::UnicodeStringExt;
+use crate::dom::{
+    AtRoomDetection, Dom, DomCreationError, FormattingTags, HtmlSource,
+    MarkdownEscaping, MarkdownMentions, PlaceholderPolicy, UnicodeString,
+    WhitespacePolicy,
+};
 use crate::link_action::LinkActionUpdate;
+use crate::offset_mapping::OffsetMapping;
+use crate::tracked_content::TrackedContent;
+use crate::update_timing::Stopwatch;
 use crate::{
-    ComposerAction, ComposerUpdate, DomHandle, Location, ToHtml, ToMarkdown,
-    ToTree,
+    AttributePolicy, BlockBackspaceBehaviour, ChangedHandles, ComposerAction,
+    ComposerModelConfig, ComposerOperation, ComposerUpdate, DomHandle, DomNode,
+    Location, MessageEventContent, PatternKey, ReplaceRange, TextUpdate,
+    ToHtml, ToMarkdown, ToTree, UpdateTiming,
 };
 use std::collections::{HashMap, HashSet};
+use widestring::Utf16String;
+
+/// The content as of an update, which the next one is compared with, see
+/// [ComposerModel::update_baseline].
+pub(crate) struct UpdateBaseline<S: UnicodeString> {
+    content: TrackedContent<S>,
+    block_ids: BlockIds,
+    decorations: Decorations,
+}
 
-#[derive(Clone, Default)]
+/// A document being edited, with its selection and history.
+///
+/// Offsets into the text, such as [Location]s, are counted in code units of
+/// `S`: UTF-16 code units for a [Utf16ComposerModel], which match string
+/// indices on Android, iOS and the web, and bytes for a [Utf8ComposerModel].
+///
+/// A model is `Send` and `Sync` when `S` and its code units are both `Send`
+/// and `Sync`, as for [Utf16ComposerModel] and [Utf8ComposerModel], so it
+/// can be moved into or shared between threads, e.g. by async server
+/// handlers which pre-render or transform message HTML. Both need `Sync` of
+/// `S`, as the content tracked for updates is shared between the model and
+/// its clones. A model takes no locks itself: reading goes through `&self`
+/// and editing through `&mut self`, so a model shared between threads for
+/// editing needs to be wrapped in whichever lock the application already
+/// uses. It keeps these caches:
+/// - the text length and content hash of each container, which are
+///   memoized through `&self` in atomics
+/// - the actions reversed at each node, used to compute the menu state,
+///   which are only updated through `&mut self`
+/// - the content as of the last update, kept per top-level node behind an
+///   `Arc` and never modified once made
+///
+/// Parsing keeps no state between calls, so any number of threads can parse
+/// at the same time.
+#[derive(Clone)]
 pub struct ComposerModel<S>
 where
     S: UnicodeString,
@@ -32,37 +85,93 @@ where
     /// States after the current one that may be restored by calling redo()
     pub(crate) next_states: Vec<ComposerState<S>>,
 
+    /// The kind and time of each step in previous_states
+    pub(crate) previous_records: Vec<HistoryRecord>,
+
+    /// The kind and time of each step in next_states
+    pub(crate) next_records: Vec<HistoryRecord>,
+
     /// The states of the buttons for each action e.g. bold, undo
     pub(crate) action_states: HashMap<ComposerAction, ActionState>,
 
     /// Suggestion patterns provided by the client at runtime
     pub(crate) custom_suggestion_patterns: HashSet<String>,
+
+    /// The text being composed by an input method, which no suggestion is
+    /// reported for, see [Self::set_composing_region]
+    pub(crate) composing_region: Option<(Location, Location)>,
+
+    /// Behavioural options, see [Self::with_config]
+    pub(crate) config: ComposerModelConfig,
+
+    /// The problems found when HTML was last parsed, see
+    /// [Self::parse_warnings]
+    pub(crate) parse_warnings: Vec<String>,
+
+    /// The content as of the last update, used to report how the next one
+    /// changed it
+    pub(crate) tracked_content: TrackedContent<S>,
+
+    /// The content as of the last update before the current operation,
+    /// once the operation has made an update, so that an operation which
+    /// makes several reports the changes of all of them in the last
+    pub(crate) operation_start: Option<TrackedContent<S>>,
+
+    /// An id for each top-level node as of the last update, see
+    /// [ComposerModelConfig::with_block_ids]
+    pub(crate) block_ids: BlockIds,
+
+    /// Ranges attached by the client, e.g. to mark spelling errors
+    pub(crate) decorations: Decorations,
+
+    /// Restore points created by the client, see [Self::create_snapshot]
+    pub(crate) snapshots: Snapshots<S>,
+
+    /// The operations made on this model since its content was last set,
+    /// see [Self::operation_log]
+    pub(crate) operation_log: Vec<ComposerOperation>,
+
+    /// Started at the beginning of the last operation, see [UpdateTiming]
+    pub(crate) operation_stopwatch: Stopwatch,
+
+    /// HTML being pasted in chunks, see [Self::start_html_paste]
+    pub(crate) pending_paste: Option<PendingPaste<S>>,
+
+    /// The state the undo history starts from, which
+    /// [Self::reset_to_last_good] falls back to when no undo step is valid
+    pub(crate) history_start_state: ComposerState<S>,
+
+    /// The actions reversed at each place the selection has been, until
+    /// the content changes
+    pub(crate) reversed_actions_cache: ReversedActionsCache,
+}
+
+impl<S> Default for ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    fn default() -> Self {
+        Self::from_state(ComposerState::default())
+    }
 }
 
+/// A model with offsets in UTF-16 code units, as used by the platform
+/// bindings.
+pub type Utf16ComposerModel = ComposerModel<Utf16String>;
+
+/// A model with offsets in UTF-8 bytes, matching Rust string indices.
+pub type Utf8ComposerModel = ComposerModel<String>;
+
 impl<S> ComposerModel<S>
 where
     S: UnicodeString,
 {
     pub fn new() -> Self {
-        let mut instance = Self {
-            state: ComposerState::default(),
-            previous_states: Vec::new(),
-            next_states: Vec::new(),
-            action_states: HashMap::new(), // TODO: Calculate state based on ComposerState
-            custom_suggestion_patterns: HashSet::new(),
-        };
-        instance.compute_menu_state(MenuStateComputeType::AlwaysUpdate);
-        instance
+        Self::with_config(ComposerModelConfig::default())
     }
 
     pub fn from_state(state: ComposerState<S>) -> Self {
-        Self {
-            state,
-            previous_states: Vec::new(),
-            next_states: Vec::new(),
-            action_states: HashMap::new(), // TODO: Calculate state based on ComposerState
-            custom_suggestion_patterns: HashSet::new(),
-        }
+        Self::from_state_and_config(state, ComposerModelConfig::default())
     }
 
     /// Create a UTF-16 model from an HTML string, or panic if HTML parsing
@@ -72,21 +181,75 @@ where
         start_codeunit: usize,
         end_codeunit: usize,
     ) -> Self {
-        let mut model = Self {
-            state: ComposerState {
-                dom: parse(html).expect("HTML parsing failed"),
+        let mut dom = parse(html).expect("HTML parsing failed");
+        Self::post_process_dom(&mut dom);
+        let mut model = Self::from_state_and_config(
+            ComposerState {
+                dom,
                 start: Location::from(start_codeunit),
                 end: Location::from(end_codeunit),
                 toggled_format_types: Vec::new(),
             },
+            ComposerModelConfig::default(),
+        );
+        model.compute_menu_state(MenuStateComputeType::AlwaysUpdate);
+        model
+    }
+
+    /// Create an empty model with the given behavioural options.
+    pub fn with_config(config: ComposerModelConfig) -> Self {
+        let mut model =
+            Self::from_state_and_config(ComposerState::default(), config);
+        model.compute_menu_state(MenuStateComputeType::AlwaysUpdate);
+        model
+    }
+
+    fn from_state_and_config(
+        state: ComposerState<S>,
+        config: ComposerModelConfig,
+    ) -> Self {
+        Self {
+            tracked_content: TrackedContent::of(&state.dom),
+            operation_start: None,
+            block_ids: BlockIds::new(state.dom.children().len()),
+            history_start_state: state.clone(),
+            state,
             previous_states: Vec::new(),
             next_states: Vec::new(),
+            previous_records: Vec::new(),
+            next_records: Vec::new(),
             action_states: HashMap::new(), // TODO: Calculate state based on ComposerState
             custom_suggestion_patterns: HashSet::new(),
-        };
-        model.compute_menu_state(MenuStateComputeType::AlwaysUpdate);
-        Self::post_process_dom(&mut model.state.dom);
-        model
+            composing_region: None,
+            config,
+            parse_warnings: Vec::new(),
+            decorations: Decorations::default(),
+            snapshots: Snapshots::default(),
+            operation_log: Vec::new(),
+            operation_stopwatch: Stopwatch::start(),
+            pending_paste: None,
+            reversed_actions_cache: ReversedActionsCache::default(),
+        }
+    }
+
+    /// The behavioural options of the model, see [ComposerModelConfig].
+    pub fn config(&self) -> &ComposerModelConfig {
+        &self.config
+    }
+
+    /// Replace the behavioural options of the model. Content which is
+    /// already there is left as it is, the options apply to the next
+    /// edits and to the content written from then on.
+    pub fn set_config(&mut self, config: ComposerModelConfig) {
+        self.config = config;
+    }
+
+    /// The unsupported elements which were skipped when HTML was last set
+    /// with [Self::set_content_from_html] or
+    /// [Self::set_content_from_markdown], if
+    /// [ComposerModelConfig::with_lenient_parsing] is set.
+    pub fn parse_warnings(&self) -> &[String] {
+        &self.parse_warnings
     }
 
     /// Replace the entire content of the model with given HTML string.
@@ -96,15 +259,37 @@ where
         &mut self,
         html: &S,
     ) -> Result<ComposerUpdate<S>, DomCreationError> {
-        let dom = parse(&html.to_string())
-            .map_err(DomCreationError::HtmlParseError)?;
+        let _span = self.enter_operation_span("set_content_from_html");
+        let update = self.do_set_content_from_html(html)?;
+        self.restart_operation_log(ComposerOperation::SetContentFromHtml {
+            html: html.to_string(),
+        });
+        Ok(update)
+    }
+
+    fn do_set_content_from_html(
+        &mut self,
+        html: &S,
+    ) -> Result<ComposerUpdate<S>, DomCreationError> {
+        let (mut dom, warnings) = parse_from_source_with_options(
+            &html.to_string(),
+            HtmlSource::Matrix,
+            self.config.max_parse_depth,
+            &self.config.attribute_policy,
+            self.config.whitespace_policy,
+            self.at_room_detection(),
+            self.config.lenient_parsing,
+        )
+        .map_err(DomCreationError::HtmlParseError)?;
+        self.parse_warnings = warnings;
+        dom.remove_disallowed_formatting(&self.config.disallowed_actions);
 
         self.state.dom = dom;
-        self.previous_states.clear();
-        self.next_states.clear();
+        self.clear_history();
         Self::post_process_dom(&mut self.state.dom);
         self.state.start = Location::from(self.state.dom.text_len());
         self.state.end = self.state.start;
+        self.history_start_state = self.state.clone();
         Ok(self.create_update_replace_all_with_menu_state())
     }
 
@@ -117,10 +302,27 @@ where
         &mut self,
         markdown: &S,
     ) -> Result<ComposerUpdate<S>, DomCreationError> {
+        let _span = self.enter_operation_span("set_content_from_markdown");
         let html = MarkdownHTMLParser::to_html(markdown)
             .map_err(DomCreationError::MarkdownParseError)?;
 
-        self.set_content_from_html(&html)
+        let update = self.do_set_content_from_html(&html)?;
+        self.restart_operation_log(ComposerOperation::SetContentFromMarkdown {
+            markdown: markdown.to_string(),
+        });
+        Ok(update)
+    }
+
+    /// Replace the entire content of the model with the content of an
+    /// `m.room.message` event, e.g. to edit the message: its HTML formatted
+    /// body if it has one, or else its plain text body. For an edit, its
+    /// new content is used. As with [Self::set_content_from_html], undo and
+    /// redo are disabled until further updates.
+    pub fn set_content_from_event_content(
+        &mut self,
+        content: &MessageEventContent,
+    ) -> Result<ComposerUpdate<S>, DomCreationError> {
+        self.set_content_from_html(&S::from(content.to_html().as_str()))
     }
 
     pub fn set_custom_suggestion_patterns(
@@ -131,6 +333,128 @@ where
             HashSet::from_iter(custom_suggestion_patterns)
     }
 
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_suggestion_trigger].
+    pub fn set_suggestion_trigger(
+        &mut self,
+        trigger: char,
+        key: Option<PatternKey>,
+    ) {
+        match key {
+            Some(key) => self.config.suggestion_triggers.insert(trigger, key),
+            None => self.config.suggestion_triggers.remove(&trigger),
+        };
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_max_parse_depth].
+    pub fn set_max_parse_depth(&mut self, max_parse_depth: usize) {
+        self.config.max_parse_depth = max_parse_depth;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_block_backspace_behaviour].
+    pub fn set_block_backspace_behaviour(
+        &mut self,
+        behaviour: BlockBackspaceBehaviour,
+    ) {
+        self.config.block_backspace_behaviour = behaviour;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_attribute_policy].
+    pub fn set_attribute_policy(&mut self, attribute_policy: AttributePolicy) {
+        self.config.attribute_policy = attribute_policy;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_formatting_tags].
+    pub fn set_formatting_tags(&mut self, formatting_tags: FormattingTags) {
+        self.config.formatting_tags = formatting_tags;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_strip_message_styles].
+    pub fn set_strip_message_styles(&mut self, strip_message_styles: bool) {
+        self.config.strip_message_styles = strip_message_styles;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_isolate_message_bidi].
+    pub fn set_isolate_message_bidi(&mut self, isolate_message_bidi: bool) {
+        self.config.isolate_message_bidi = isolate_message_bidi;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_whitespace_policy].
+    pub fn set_whitespace_policy(
+        &mut self,
+        whitespace_policy: WhitespacePolicy,
+    ) {
+        self.config.whitespace_policy = whitespace_policy;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_placeholder_policy].
+    pub fn set_placeholder_policy(
+        &mut self,
+        placeholder_policy: PlaceholderPolicy,
+    ) {
+        self.config.placeholder_policy = placeholder_policy;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_at_room_detection].
+    pub fn set_at_room_detection(&mut self, detect_at_room: bool) {
+        self.config.at_room_detection = detect_at_room;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_at_room_detection_in_quotes].
+    pub fn set_at_room_detection_in_quotes(
+        &mut self,
+        detect_at_room_in_quotes: bool,
+    ) {
+        self.config.at_room_detection_in_quotes = detect_at_room_in_quotes;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_can_notify_room].
+    pub fn set_can_notify_room(&mut self, can_notify_room: bool) {
+        self.config.can_notify_room = can_notify_room;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_record_operations].
+    pub fn set_record_operations(&mut self, record_operations: bool) {
+        self.config.record_operations = record_operations;
+    }
+
+    /// Where parsed HTML may contain @room mentions, as set by
+    /// [Self::set_at_room_detection], [Self::set_at_room_detection_in_quotes]
+    /// and [Self::set_can_notify_room].
+    pub(crate) fn at_room_detection(&self) -> AtRoomDetection {
+        self.config.at_room_detection_scope()
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_markdown_escaping].
+    pub fn set_markdown_escaping(
+        &mut self,
+        markdown_escaping: MarkdownEscaping,
+    ) {
+        self.config.markdown_escaping = markdown_escaping;
+    }
+
+    /// Set the option of the config of this model, see
+    /// [ComposerModelConfig::with_markdown_mentions].
+    pub fn set_markdown_mentions(
+        &mut self,
+        markdown_mentions: MarkdownMentions,
+    ) {
+        self.config.markdown_mentions = markdown_mentions;
+    }
+
     pub fn action_states(&self) -> &HashMap<ComposerAction, ActionState> {
         &self.action_states
     }
@@ -140,6 +464,12 @@ where
         self.action_states.get(&action) == Some(&ActionState::Enabled)
     }
 
+    /// Whether the configuration doesn't allow this action, see
+    /// [ComposerModelConfig::with_disallowed_actions].
+    pub(crate) fn action_is_disallowed(&self, action: ComposerAction) -> bool {
+        !self.config.allows_action(&action)
+    }
+
     pub(crate) fn action_is_reversed(&self, action: ComposerAction) -> bool {
         self.action_states.get(&action) == Some(&ActionState::Reversed)
     }
@@ -171,14 +501,26 @@ where
         #[cfg(any(test, feature = "assert-invariants"))]
         self.state.dom.assert_transaction_not_in_progress();
 
-        ComposerUpdate::replace_all(
-            self.state.dom.to_html(),
+        let (offset_mapping, deleted_mentions, changed_handles) =
+            self.track_content_changes();
+        let serialization = Stopwatch::start();
+        let mut html = S::default();
+        self.write_editor_html(&mut html, &self.to_html_state());
+        let timing =
+            UpdateTiming::measure(&self.operation_stopwatch, &serialization);
+        let mut update = ComposerUpdate::replace_all(
+            html,
             self.state.start,
             self.state.end,
             self.compute_menu_state(MenuStateComputeType::KeepIfUnchanged),
             self.compute_menu_action(),
             LinkActionUpdate::Update(self.get_link_action()),
-        )
+            offset_mapping,
+        );
+        update.deleted_mentions = deleted_mentions;
+        update.changed_handles = changed_handles;
+        update.timing = timing;
+        update
     }
 
     pub(crate) fn create_update_replace_all_with_menu_state(
@@ -187,34 +529,232 @@ where
         #[cfg(any(test, feature = "assert-invariants"))]
         self.state.dom.assert_transaction_not_in_progress();
 
-        ComposerUpdate::replace_all(
-            self.state.dom.to_html(),
+        let (offset_mapping, deleted_mentions, changed_handles) =
+            self.track_content_changes();
+        let serialization = Stopwatch::start();
+        let mut html = S::default();
+        self.write_editor_html(&mut html, &self.to_html_state());
+        let timing =
+            UpdateTiming::measure(&self.operation_stopwatch, &serialization);
+        let mut update = ComposerUpdate::replace_all(
+            html,
             self.state.start,
             self.state.end,
             self.compute_menu_state(MenuStateComputeType::AlwaysUpdate),
             self.compute_menu_action(),
             LinkActionUpdate::Update(self.get_link_action()),
+            offset_mapping,
+        );
+        update.deleted_mentions = deleted_mentions;
+        update.changed_handles = changed_handles;
+        update.timing = timing;
+        update
+    }
+
+    /// Like [Self::create_update_replace_all], but with the HTML of only the
+    /// top-level nodes which changed since the last update. The nodes
+    /// either side of them are replaced too, as the HTML of a node can
+    /// depend on its neighbours, e.g. for spaces at the edge of text.
+    pub(crate) fn create_update_replace_range(&mut self) -> ComposerUpdate<S> {
+        #[cfg(any(test, feature = "assert-invariants"))]
+        self.state.dom.assert_transaction_not_in_progress();
+
+        let (offset_mapping, deleted_mentions, changed_handles) =
+            self.track_content_changes();
+        let first = changed_handles.first_index().unwrap_or(0);
+        let modified = changed_handles.modified.len();
+        let new_end = first + modified + changed_handles.added.len();
+        let old_end = first + modified + changed_handles.removed.len();
+
+        let first_node = first.saturating_sub(1);
+        // The nodes after the change are the same before and after it
+        let after =
+            usize::from(new_end < self.state.dom.document().children().len());
+        let serialization = Stopwatch::start();
+        let mut replacement_html = S::default();
+        self.fmt_top_level_html(
+            &mut replacement_html,
+            first_node..new_end + after,
+            &self.to_html_state(),
+        );
+        let timing =
+            UpdateTiming::measure(&self.operation_stopwatch, &serialization);
+
+        let mut update = ComposerUpdate::update_selection(
+            self.state.start,
+            self.state.end,
+            self.compute_menu_state(MenuStateComputeType::KeepIfUnchanged),
+            self.compute_menu_action(),
+            LinkActionUpdate::Update(self.get_link_action()),
+        );
+        update.text_update = TextUpdate::ReplaceRange(ReplaceRange {
+            first_node,
+            removed_nodes: old_end + after - first_node,
+            replacement_html,
+            start: self.state.start,
+            end: self.state.end,
+        });
+        update.offset_mapping = offset_mapping;
+        update.deleted_mentions = deleted_mentions;
+        update.changed_handles = changed_handles;
+        update.timing = timing;
+        update
+    }
+
+    /// Work out how offsets moved since the last update, which mentions it
+    /// removed and which top-level nodes it changed, move the decorations
+    /// to match, and remember the current content for next time. If the
+    /// current operation already made an update, the changes since the
+    /// start of the operation are returned instead, as the client only
+    /// sees the last update of an operation.
+    fn track_content_changes(
+        &mut self,
+    ) -> (OffsetMapping, Vec<DeletedMention>, ChangedHandles) {
+        let content = self.tracked_content.updated_for(&self.state.dom);
+        let changes = content.changes_since(&self.tracked_content);
+        self.block_ids.apply(&changes.changed_handles);
+        if !changes.offset_mapping.is_identity() {
+            self.decorations.apply(&changes.offset_mapping);
+        }
+        let previous = std::mem::replace(&mut self.tracked_content, content);
+        let changes = match &self.operation_start {
+            Some(start) => self.tracked_content.changes_since(start),
+            None => {
+                self.operation_start = Some(previous);
+                changes
+            }
+        };
+        (
+            changes.offset_mapping,
+            changes.deleted_mentions,
+            changes.changed_handles,
         )
     }
 
+    /// What the next update will be compared with, so that several
+    /// operations can be reported as one change, see
+    /// [Self::report_changes_since].
+    pub(crate) fn update_baseline(&self) -> UpdateBaseline<S> {
+        UpdateBaseline {
+            content: self.tracked_content.clone(),
+            block_ids: self.block_ids.clone(),
+            decorations: self.decorations.clone(),
+        }
+    }
+
+    /// Put back the baseline, along with the decorations and block ids
+    /// which followed it, after operations which were undone.
+    pub(crate) fn restore_update_baseline(
+        &mut self,
+        baseline: UpdateBaseline<S>,
+    ) {
+        self.tracked_content = baseline.content;
+        self.block_ids = baseline.block_ids;
+        self.decorations = baseline.decorations;
+    }
+
+    /// Report in `update`, which must have just been created, how offsets
+    /// moved, which mentions were removed and which top-level nodes changed
+    /// since `baseline` was taken, rather than since the last update.
+    pub(crate) fn report_changes_since(
+        &self,
+        baseline: &UpdateBaseline<S>,
+        update: &mut ComposerUpdate<S>,
+    ) {
+        let changes = self.tracked_content.changes_since(&baseline.content);
+        update.changed_handles = changes.changed_handles;
+        update.offset_mapping = changes.offset_mapping;
+        update.deleted_mentions = changes.deleted_mentions;
+    }
+
     pub fn get_selection(&self) -> (Location, Location) {
         (self.state.start, self.state.end)
     }
 
     pub fn get_content_as_html(&self) -> S {
-        self.state.dom.to_html()
+        let mut html = S::default();
+        self.write_editor_html(&mut html, &self.to_html_state());
+        html
     }
 
     pub fn get_content_as_message_html(&self) -> S {
-        self.state.dom.to_message_html()
+        self.state
+            .dom
+            .to_html_with_state(&self.to_html_state(), true)
+    }
+
+    /// Like [Self::get_content_as_html], but replaces the content of `buf`
+    /// instead of allocating a new string, so that callers serializing
+    /// after every change can reuse it.
+    pub fn write_content_as_html(&self, buf: &mut S) {
+        self.write_editor_html(buf, &self.to_html_state())
+    }
+
+    /// Replace the content of `buf` with the HTML of the content for the
+    /// editor, rather than for a message.
+    fn write_editor_html(&self, buf: &mut S, state: &ToHtmlState) {
+        if !self.config.block_ids {
+            return self.state.dom.write_html(buf, state, false);
+        }
+        buf.clear();
+        buf.reserve(self.state.dom.html_len_hint());
+        let count = self.state.dom.document().children().len();
+        self.fmt_top_level_html(buf, 0..count, state);
+    }
+
+    /// Write the HTML of the top-level nodes in `nodes`, giving each block
+    /// its `data-block-id` if [ComposerModelConfig::with_block_ids] is set.
+    fn fmt_top_level_html(
+        &self,
+        buf: &mut S,
+        nodes: std::ops::Range<usize>,
+        state: &ToHtmlState,
+    ) {
+        let document = self.state.dom.document();
+        for i in nodes {
+            let mut state = document.updated_state(state, i);
+            if self.config.block_ids {
+                state.block_id = self.block_ids.get(i);
+            }
+            document.children()[i].fmt_html(buf, None, &state, false);
+        }
+    }
+
+    /// The state to start serializing the content with, as configured by
+    /// [Self::set_formatting_tags], [Self::set_whitespace_policy],
+    /// [Self::set_placeholder_policy], [Self::set_strip_message_styles] and
+    /// [Self::set_isolate_message_bidi].
+    pub(crate) fn to_html_state(&self) -> ToHtmlState {
+        ToHtmlState {
+            formatting_tags: self.config.formatting_tags,
+            whitespace_policy: self.config.whitespace_policy,
+            placeholder_policy: self.config.placeholder_policy,
+            strip_message_styles: self.config.strip_message_styles,
+            isolate_message_bidi: self.config.isolate_message_bidi,
+            ..Default::default()
+        }
     }
 
     pub fn get_content_as_markdown(&self) -> S {
-        self.state.dom.to_markdown().unwrap()
+        self.state
+            .dom
+            .to_markdown_with_options(&self.markdown_options(), false)
+            .unwrap()
     }
 
     pub fn get_content_as_message_markdown(&self) -> S {
-        self.state.dom.to_message_markdown().unwrap()
+        self.state
+            .dom
+            .to_markdown_with_options(&self.markdown_options(), true)
+            .unwrap()
+    }
+
+    /// The options to write the content as Markdown with, as configured by
+    /// [Self::set_markdown_escaping] and [Self::set_markdown_mentions].
+    fn markdown_options(&self) -> MarkdownOptions {
+        let mut options = MarkdownOptions::from(&self.config.markdown_escaping);
+        options.insert(self.config.markdown_mentions.into());
+        options
     }
 
     pub fn get_content_as_plain_text(&self) -> S {
@@ -229,8 +769,45 @@ where
         self.state.dom.to_tree()
     }
 
+    /// The tree of the content, like [ComposerModel::to_tree], but leaving
+    /// out the children of containers `max_depth` levels below the root, so
+    /// that the structure can be included in a bug report without all of
+    /// the text inside it.
+    pub fn to_tree_with_max_depth(&self, max_depth: usize) -> S {
+        let mut dom = self.state.dom.clone();
+        let handles: Vec<DomHandle> = dom
+            .iter_containers()
+            .filter(|c| c.handle().raw().len() == max_depth)
+            .map(|c| c.handle())
+            .collect();
+        for handle in handles {
+            if let DomNode::Container(container) = dom.lookup_node_mut(&handle)
+            {
+                container.remove_children();
+            }
+        }
+        dom.to_tree()
+    }
+
+    /// The tree of the content with every text node replaced by its length
+    /// and no URLs, followed by the selection, so that it can be attached to
+    /// bug reports without giving away what the user wrote.
+    pub fn to_tree_redacted(&self) -> S {
+        let mut tree = self.state.dom.to_tree_redacted();
+        tree.push(
+            format!(
+                "selection: {}..{}\n",
+                usize::from(self.state.start),
+                usize::from(self.state.end)
+            )
+            .as_str(),
+        );
+        tree
+    }
+
     pub fn clear(&mut self) -> ComposerUpdate<S> {
-        self.set_content_from_html(&"".into())
+        let _span = self.start_operation(ComposerOperation::Clear);
+        self.do_set_content_from_html(&"".into())
             .expect("empty content")
     }
 }
@@ -255,6 +832,26 @@ mod test {
         assert_eq!(model.state.dom.to_string(), "foo <b>bar</b>")
     }
 
+    #[test]
+    fn tree_with_max_depth_leaves_out_deeper_nodes() {
+        let model = cm("<ul><li>a<b>b|</b></li></ul><p>c</p>");
+        assert_eq!(
+            model.to_tree_with_max_depth(2),
+            "\n├>ul\n│ └>li\n└>p\n  └>\"c\"\n"
+        );
+        assert_eq!(model.to_tree_with_max_depth(0), "\n");
+        assert_eq!(model.to_tree_with_max_depth(10), model.to_tree());
+    }
+
+    #[test]
+    fn redacted_tree_includes_the_selection() {
+        let model = cm("<p>a{bc</p><p>d}|e</p>");
+        assert_eq!(
+            model.to_tree_redacted(),
+            "\n├>p\n│ └>text (3)\n└>p\n  └>text (2)\nselection: 1..5\n"
+        );
+    }
+
     #[test]
     fn action_states_are_reported() {
         let mut model = ComposerModel::new();
@@ -267,6 +864,18 @@ mod test {
         assert!(model.action_is_disabled(ComposerAction::Redo));
     }
 
+    #[test]
+    fn write_content_as_html_replaces_the_content_of_the_buffer() {
+        let model = cm("a <b>b</b> &lt;c&gt;  <a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>|");
+        let mut buf = utf16("previous content");
+        model.write_content_as_html(&mut buf);
+        assert_eq!(buf, model.get_content_as_html());
+        assert_eq!(
+            buf,
+            "a <b>b</b> &lt;c&gt;\u{A0}\u{A0}<a data-mention-type=\"user\" href=\"https://matrix.to/#/@alice:matrix.org\" contenteditable=\"false\">Alice</a>"
+        );
+    }
+
     #[test]
     fn set_content_from_html_with_complex_html_has_proper_selection() {
         let mut model = cm("|");
@@ -293,4 +902,31 @@ mod test {
             <p>Some <code>inline</code> code|</p>"
         );
     }
+
+    #[test]
+    fn models_can_be_used_from_several_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Utf16ComposerModel>();
+        assert_send_sync::<Utf8ComposerModel>();
+        assert_send_sync::<ComposerUpdate<Utf16String>>();
+
+        let model = cm("<p>a</p><p>b|</p>");
+        let htmls: Vec<String> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|i| {
+                    let model = &model;
+                    scope.spawn(move || {
+                        let mut copy = model.clone();
+                        copy.replace_text(utf16(&i.to_string()));
+                        let html = copy.get_content_as_html().to_string();
+                        let parsed: Dom<Utf16String> = parse(&html).unwrap();
+                        parsed.to_html().to_string()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(htmls[3], "<p>a</p><p>b3</p>");
+        assert_eq!(tx(&model), "<p>a</p><p>b|</p>");
+    }
 }
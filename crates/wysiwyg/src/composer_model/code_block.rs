@@ -7,13 +7,20 @@
 use crate::dom::nodes::dom_node::DomNodeKind::*;
 use crate::dom::nodes::{ContainerNode, ContainerNodeKind, DomNode};
 use crate::dom::{DomHandle, DomLocation, Range};
-use crate::{ComposerAction, ComposerModel, ComposerUpdate, UnicodeString};
+use crate::{
+    ComposerAction, ComposerModel, ComposerOperation, ComposerUpdate,
+    UnicodeString,
+};
 
 impl<S> ComposerModel<S>
 where
     S: UnicodeString,
 {
     pub fn code_block(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::CodeBlock);
+        if self.action_is_disallowed(ComposerAction::CodeBlock) {
+            return ComposerUpdate::keep();
+        }
         if self.action_is_reversed(ComposerAction::CodeBlock) {
             self.remove_code_block()
         } else {
@@ -163,7 +170,7 @@ where
         handle
     }
 
-    fn remove_code_block(&mut self) -> ComposerUpdate<S> {
+    pub(crate) fn remove_code_block(&mut self) -> ComposerUpdate<S> {
         let (s, e) = self.safe_selection();
         let range = self.state.dom.find_range(s, e);
         let Some(block_location) =
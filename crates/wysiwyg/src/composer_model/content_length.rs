@@ -0,0 +1,140 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{ComposerModel, DomNode, UnicodeString};
+
+/// How long some content is, both in the code units of the model's string
+/// type, UTF-16 in the platform bindings, and in graphemes, the characters
+/// users see. An emoji such as 👩‍💻 is one grapheme but five UTF-16 code
+/// units. Mentions and line breaks count as one of each, as they do for
+/// the cursor.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TextLength {
+    /// The length in code units, which the maximum length of the
+    /// configuration is checked against
+    pub code_units: usize,
+    /// The length in graphemes, for showing to users, e.g. in a character
+    /// counter
+    pub graphemes: usize,
+}
+
+/// The length of the whole content and of each of its blocks, see
+/// [ComposerModel::content_length].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ContentLength {
+    /// The length of all of the content, including the boundaries between
+    /// blocks, which count as a code unit and a grapheme each
+    pub total: TextLength,
+    /// The length of each top-level node of the content, in the order of
+    /// the indices of [crate::ChangedHandles]
+    pub blocks: Vec<TextLength>,
+}
+
+impl TextLength {
+    fn of<S: UnicodeString>(node: &DomNode<S>) -> Self {
+        let mut text = String::new();
+        push_visible_text(node, &mut text);
+        Self {
+            code_units: node.text_len(),
+            graphemes: text.graphemes(true).count(),
+        }
+    }
+}
+
+/// Write text with the graphemes of the node to `text`, with mentions, line
+/// breaks and the boundaries between blocks as one character each, as they
+/// are in [DomNode::text_len].
+fn push_visible_text<S: UnicodeString>(node: &DomNode<S>, text: &mut String) {
+    match node {
+        DomNode::Container(container) => {
+            let mut is_first_block = true;
+            for child in container.children() {
+                if child.is_block_node() {
+                    if !is_first_block {
+                        text.push('\n');
+                    }
+                    is_first_block = false;
+                }
+                push_visible_text(child, text);
+            }
+        }
+        DomNode::Text(node) => text.push_str(&node.data().to_string()),
+        DomNode::LineBreak(_) => text.push('\n'),
+        DomNode::Mention(_) => text.push('\u{FFFC}'),
+    }
+}
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// How long the content is, in total and in each of its blocks, both in
+    /// code units and in graphemes, so that clients can show a character
+    /// count which matches what users see while the maximum length is still
+    /// checked in code units.
+    pub fn content_length(&self) -> ContentLength {
+        let dom = &self.state.dom;
+        ContentLength {
+            total: TextLength::of(dom.document_node()),
+            blocks: dom
+                .document()
+                .children()
+                .iter()
+                .map(TextLength::of)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tests::testutils_composer_model::cm;
+
+    use super::{ContentLength, TextLength};
+
+    fn length(code_units: usize, graphemes: usize) -> TextLength {
+        TextLength {
+            code_units,
+            graphemes,
+        }
+    }
+
+    #[test]
+    fn empty_content_has_no_length() {
+        assert_eq!(cm("|").content_length(), ContentLength::default());
+    }
+
+    #[test]
+    fn emoji_are_one_grapheme_each() {
+        let content_length = cm("a👩\u{200D}💻🇫🇷é|").content_length();
+        assert_eq!(content_length.total, length(11, 4));
+    }
+
+    #[test]
+    fn combining_characters_join_the_letter_before_them() {
+        let content_length = cm("e\u{301}|").content_length();
+        assert_eq!(content_length.total, length(2, 1));
+    }
+
+    #[test]
+    fn blocks_are_measured_separately() {
+        let content_length =
+            cm("<p>a😀</p><ul><li>b</li><li>c|</li></ul>").content_length();
+        assert_eq!(content_length.total, length(7, 6));
+        assert_eq!(content_length.blocks, vec![length(3, 2), length(3, 3)]);
+    }
+
+    #[test]
+    fn mentions_and_line_breaks_are_one_grapheme_each() {
+        let content_length = cm(
+            "<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>\
+            <br />b|",
+        )
+        .content_length();
+        assert_eq!(content_length.total, length(3, 3));
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::dom::nodes::ContainerNode;
+use crate::dom::unicode_string::UnicodeStr;
+use crate::dom::DomLocation;
+use crate::{
+    ComposerModel, Decoration, DecorationKind, DomNode, Location, ToHtml,
+    UnicodeString,
+};
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// Attach a decoration to the content between start and end. It will be
+    /// moved as the content is edited, and removed if the text it covers is
+    /// changed. Returns an id which can be passed to
+    /// [Self::remove_decoration].
+    pub fn add_decoration(
+        &mut self,
+        kind: DecorationKind,
+        start: Location,
+        end: Location,
+    ) -> u64 {
+        self.decorations.add(kind, start, end)
+    }
+
+    /// Remove the decoration with the given id. Returns false if it was not
+    /// found, e.g. because its text was already changed.
+    pub fn remove_decoration(&mut self, id: u64) -> bool {
+        let len = self.decorations.items.len();
+        self.decorations.items.retain(|d| d.id != id);
+        self.decorations.items.len() != len
+    }
+
+    /// Remove all decorations of the given kind, e.g. when a search is
+    /// cleared.
+    pub fn remove_decorations_of_kind(&mut self, kind: DecorationKind) {
+        self.decorations.items.retain(|d| d.kind != kind);
+    }
+
+    /// All the decorations currently attached, in the order they were added.
+    pub fn decorations(&self) -> &[Decoration] {
+        &self.decorations.items
+    }
+
+    /// The decorations overlapping the content between start and end.
+    pub fn decorations_in(
+        &self,
+        start: Location,
+        end: Location,
+    ) -> Vec<&Decoration> {
+        let (start, end) = (start.min(end), start.max(end));
+        self.decorations
+            .items
+            .iter()
+            .filter(|d| d.overlaps(start.into(), end.into()))
+            .collect()
+    }
+
+    /// The content as HTML, with the decorated text wrapped in
+    /// `<span data-decoration="...">` elements. Where decorations overlap,
+    /// the attribute lists all of their kinds, separated by spaces.
+    pub fn get_content_as_html_with_decorations(&self) -> S {
+        if self.decorations.items.is_empty() {
+            return self.get_content_as_html();
+        }
+        let mut dom = self.state.dom.clone();
+        let text_locations: Vec<DomLocation> = dom
+            .find_range(0, dom.text_len())
+            .locations
+            .into_iter()
+            .filter(|l| dom.lookup_node(&l.node_handle).is_text_node())
+            .collect();
+
+        // Work backwards so that splitting a node doesn't move the ones we
+        // still have to visit.
+        for location in text_locations.iter().rev() {
+            let node_start = location.position;
+            let node_end = location.position + location.length;
+            let DomNode::Text(text_node) =
+                dom.lookup_node(&location.node_handle)
+            else {
+                continue;
+            };
+            let data = text_node.data();
+
+            // Decorations come from the client, so can reach past the text
+            // or start or end inside a character, e.g. between the halves
+            // of a surrogate pair. They are cut to the text, and ends
+            // inside a character are moved back to its start.
+            let boundaries = char_boundaries(data, node_start);
+            let snap = |offset: usize| {
+                let offset = offset.clamp(node_start, node_end);
+                boundaries[boundaries.partition_point(|b| *b <= offset) - 1]
+            };
+            let decorations: Vec<Decoration> = self
+                .decorations
+                .items
+                .iter()
+                .map(|d| Decoration {
+                    start: Location::from(snap(d.start.into())),
+                    end: Location::from(snap(d.end.into())),
+                    ..*d
+                })
+                .filter(|d| d.start < d.end)
+                .collect();
+            if decorations.is_empty() {
+                continue;
+            }
+            let mut cuts = vec![node_start, node_end];
+            for d in &decorations {
+                cuts.push(d.start.into());
+                cuts.push(d.end.into());
+            }
+            cuts.sort_unstable();
+            cuts.dedup();
+
+            let mut nodes = Vec::new();
+            for cut in cuts.windows(2) {
+                let (start, end) = (cut[0], cut[1]);
+                let text = DomNode::new_text(S::from(
+                    data[start - node_start..end - node_start].to_string(),
+                ));
+                let kinds = decoration_kinds_covering(&decorations, start, end);
+                if kinds.is_empty() {
+                    nodes.push(text);
+                } else {
+                    nodes.push(DomNode::Container(ContainerNode::new_span(
+                        vec![text],
+                        Some(vec![("data-decoration".into(), kinds.into())]),
+                    )));
+                }
+            }
+            dom.replace(&location.node_handle, nodes);
+        }
+        dom.to_html_with_state(&self.to_html_state(), false)
+    }
+}
+
+/// The kinds of the decorations overlapping start to end, separated by
+/// spaces.
+fn decoration_kinds_covering(
+    decorations: &[Decoration],
+    start: usize,
+    end: usize,
+) -> String {
+    let mut kinds: Vec<&str> = decorations
+        .iter()
+        .filter(|d| d.overlaps(start, end))
+        .map(|d| d.kind.as_str())
+        .collect();
+    kinds.sort_unstable();
+    kinds.dedup();
+    kinds.join(" ")
+}
+
+/// The offsets at which each character of `data` starts, followed by the
+/// one at which it ends, counting from `start`.
+fn char_boundaries<S>(data: &S, start: usize) -> Vec<usize>
+where
+    S: UnicodeStr + ?Sized,
+{
+    let mut boundaries = vec![start];
+    let mut offset = start;
+    for c in data.chars() {
+        offset += data.char_len(&c);
+        boundaries.push(offset);
+    }
+    boundaries
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::{DecorationKind, Location};
+
+    #[test]
+    fn decorations_follow_edits_before_them() {
+        let mut model = cm("|abc def");
+        let id = model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(4),
+            Location::from(7),
+        );
+        model.replace_text(Utf16String::from("xy "));
+        let decoration = model.decorations()[0];
+        assert_eq!(decoration.id, id);
+        assert_eq!(decoration.start, 7);
+        assert_eq!(decoration.end, 10);
+    }
+
+    #[test]
+    fn decorations_are_dropped_when_their_text_changes() {
+        let mut model = cm("abc de|f");
+        model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(4),
+            Location::from(7),
+        );
+        model.replace_text(Utf16String::from("x"));
+        assert!(model.decorations().is_empty());
+    }
+
+    #[test]
+    fn typing_after_a_decoration_does_not_extend_it() {
+        let mut model = cm("abc def|");
+        model.add_decoration(
+            DecorationKind::SearchHighlight,
+            Location::from(4),
+            Location::from(7),
+        );
+        model.replace_text(Utf16String::from("g"));
+        assert_eq!(model.decorations()[0].end, 7);
+    }
+
+    #[test]
+    fn decorations_can_be_removed() {
+        let mut model = cm("abc def|");
+        let spelling = model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(0),
+            Location::from(3),
+        );
+        model.add_decoration(
+            DecorationKind::SearchHighlight,
+            Location::from(4),
+            Location::from(7),
+        );
+        model.add_decoration(
+            DecorationKind::SearchHighlight,
+            Location::from(0),
+            Location::from(1),
+        );
+        assert!(model.remove_decoration(spelling));
+        assert!(!model.remove_decoration(spelling));
+        assert_eq!(model.decorations().len(), 2);
+        model.remove_decorations_of_kind(DecorationKind::SearchHighlight);
+        assert!(model.decorations().is_empty());
+    }
+
+    #[test]
+    fn decorations_in_returns_overlapping_decorations() {
+        let mut model = cm("abc def|");
+        model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(0),
+            Location::from(3),
+        );
+        let grammar = model.add_decoration(
+            DecorationKind::GrammarSuggestion,
+            Location::from(4),
+            Location::from(7),
+        );
+        let found = model.decorations_in(Location::from(3), Location::from(5));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, grammar);
+    }
+
+    #[test]
+    fn decorations_are_not_part_of_the_content() {
+        let mut model = cm("abc def|");
+        model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(0),
+            Location::from(3),
+        );
+        assert_eq!(tx(&model), "abc def|");
+        assert_eq!(model.get_content_as_message_html(), "abc def");
+    }
+
+    #[test]
+    fn html_with_decorations_wraps_decorated_text_in_spans() {
+        let mut model = cm("<b>abc</b> def|");
+        model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(1),
+            Location::from(3),
+        );
+        model.add_decoration(
+            DecorationKind::SearchHighlight,
+            Location::from(4),
+            Location::from(7),
+        );
+        model.add_decoration(
+            DecorationKind::GrammarSuggestion,
+            Location::from(5),
+            Location::from(6),
+        );
+        assert_eq!(
+            model.get_content_as_html_with_decorations(),
+            "<b>a<span data-decoration=\"spelling-error\">bc</span></b> \
+            <span data-decoration=\"search-highlight\">d</span>\
+            <span data-decoration=\"grammar-suggestion search-highlight\">e</span>\
+            <span data-decoration=\"search-highlight\">f</span>"
+        );
+        assert_eq!(model.get_content_as_html(), "<b>abc</b> def");
+    }
+
+    #[test]
+    fn html_with_decorations_ignores_the_part_past_the_end() {
+        let mut model = cm("abc|");
+        model.add_decoration(
+            DecorationKind::SearchHighlight,
+            Location::from(2),
+            Location::from(10),
+        );
+        model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(5),
+            Location::from(8),
+        );
+        assert_eq!(
+            model.get_content_as_html_with_decorations(),
+            "ab<span data-decoration=\"search-highlight\">c</span>"
+        );
+    }
+
+    #[test]
+    fn html_with_decorations_moves_ends_inside_a_character_to_its_start() {
+        let mut model = cm("a😀b|");
+        model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(2),
+            Location::from(4),
+        );
+        assert_eq!(
+            model.get_content_as_html_with_decorations(),
+            "a<span data-decoration=\"spelling-error\">😀b</span>"
+        );
+        let mut model = cm("a😀b|");
+        model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(2),
+            Location::from(2),
+        );
+        assert_eq!(model.get_content_as_html_with_decorations(), "a😀b");
+    }
+}
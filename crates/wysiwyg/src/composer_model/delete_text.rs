@@ -4,19 +4,44 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use crate::dom::nodes::dom_node::DomNodeKind::{Link, ListItem};
+use crate::composer_model::extend_selection::{units_in, UnitKind};
+use crate::dom::nodes::dom_node::DomNodeKind;
+use crate::dom::nodes::dom_node::DomNodeKind::{
+    CodeBlock, Link, ListItem, Quote,
+};
 use crate::dom::nodes::text_node::CharType;
 use crate::dom::nodes::{DomNode, TextNode};
 use crate::dom::unicode_string::UnicodeStrExt;
 use crate::dom::{DomHandle, DomLocation, Range};
-use crate::{ComposerModel, ComposerUpdate, Location, UnicodeString};
-
-#[derive(PartialEq, Eq, Debug)]
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, HistoryEntryKind,
+    Location, UnicodeString,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Direction {
     Forwards,
     Backwards,
 }
 
+/// What backspace does when the cursor is at the start of a code block or
+/// quote, see [ComposerModel::set_block_backspace_behaviour].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BlockBackspaceBehaviour {
+    /// Move the first line of the block to the end of the block before it
+    #[default]
+    MergeIntoPrevious,
+    /// Turn the whole block back into ordinary paragraphs
+    ExitBlock,
+    /// Leave the content as it is
+    NoOp,
+}
+
 impl Direction {
     pub fn increment(&self, index: usize) -> usize {
         match self {
@@ -37,7 +62,28 @@ where
     S: UnicodeString,
 {
     pub fn backspace(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::Backspace);
+        self.push_history_and_backspace()
+    }
+
+    fn push_history_and_backspace(&mut self) -> ComposerUpdate<S> {
+        if let Some(kind) = self.block_starting_at_cursor() {
+            match self.config.block_backspace_behaviour {
+                BlockBackspaceBehaviour::MergeIntoPrevious => {}
+                BlockBackspaceBehaviour::ExitBlock => {
+                    self.push_state_to_history(HistoryEntryKind::Deletion);
+                    return if kind == CodeBlock {
+                        self.remove_code_block()
+                    } else {
+                        self.remove_quote()
+                    };
+                }
+                BlockBackspaceBehaviour::NoOp => {
+                    return ComposerUpdate::keep();
+                }
+            }
+        }
+        self.push_state_to_history(HistoryEntryKind::Deletion);
         self.handle_non_editable_selection(&Direction::Backwards);
 
         let (s, e) = self.safe_selection();
@@ -52,6 +98,25 @@ where
         }
     }
 
+    /// The kind of the innermost code block or quote which starts exactly at
+    /// the cursor, if any.
+    fn block_starting_at_cursor(&self) -> Option<DomNodeKind> {
+        let (s, e) = self.safe_selection();
+        if s != e {
+            return None;
+        }
+        self.state
+            .dom
+            .find_range(s, e)
+            .locations
+            .into_iter()
+            .filter(|l| {
+                matches!(l.kind, CodeBlock | Quote) && l.start_offset == 0
+            })
+            .max_by_key(|l| l.node_handle.depth())
+            .map(|l| l.kind)
+    }
+
     /// Deletes the current selection, will return a keep in case where
     /// we don't have a selection
     fn delete_selection(&mut self) -> ComposerUpdate<S> {
@@ -60,7 +125,7 @@ where
         }
 
         let (s, e) = self.safe_selection();
-        self.delete_in(s, e)
+        self.do_delete_in(s, e)
     }
 
     /// Allows deletion between two positions, regardless of argument order
@@ -72,15 +137,54 @@ where
         let (s, _) = self.safe_selection();
 
         if s < position {
-            self.delete_in(s, position)
+            self.do_delete_in(s, position)
         } else {
-            self.delete_in(position, s)
+            self.do_delete_in(position, s)
         }
     }
 
     /// Deletes text in an arbitrary start..end range.
     pub fn delete_in(&mut self, start: usize, end: usize) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span =
+            self.start_operation(ComposerOperation::DeleteIn { start, end });
+        self.do_delete_in(start, end)
+    }
+
+    /// Cut the content down to at most `length` code units, e.g. for a
+    /// preview or to enforce a limit before sending. Graphemes and mentions
+    /// are never split, but left out whole if they don't fit, and a line
+    /// break or the gap before a block which would end the content is left
+    /// out too. The selection is moved inside what is left.
+    pub fn truncate_to(&mut self, length: usize) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::TruncateTo { length });
+        let text_len = self.state.dom.text_len();
+        if length >= text_len {
+            return ComposerUpdate::keep();
+        }
+        let units = units_in(self.state.dom.document());
+        let cut = units
+            .iter()
+            .take_while(|unit| unit.end <= length)
+            .filter(|unit| {
+                !matches!(
+                    unit.kind,
+                    UnitKind::LineBreak | UnitKind::BlockBoundary
+                )
+            })
+            .last()
+            .map_or(0, |unit| unit.end);
+        let (s, e) = (self.state.start, self.state.end);
+        self.push_state_to_history(HistoryEntryKind::Deletion);
+        self.do_replace_text_in(S::default(), cut, text_len);
+        let cut = Location::from(cut);
+        self.state.start = s.min(cut);
+        self.state.end = e.min(cut);
+        self.create_update_replace_all()
+    }
+
+    fn do_delete_in(&mut self, start: usize, end: usize) -> ComposerUpdate<S> {
+        self.push_state_to_history(HistoryEntryKind::Deletion);
         self.state.end = Location::from(start);
         self.do_replace_text_in(S::default(), start, end)
     }
@@ -126,7 +230,7 @@ where
                     .lookup_container(&link.node_handle)
                     .is_immutable_link()
                 {
-                    self.select(
+                    self.do_select(
                         Location::from(link.position),
                         Location::from(link.position + link.length),
                     );
@@ -137,7 +241,8 @@ where
 
     /// Deletes the character after the current cursor position.
     pub fn delete(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::Delete);
+        self.push_state_to_history(HistoryEntryKind::Deletion);
         self.do_delete()
     }
 
@@ -166,11 +271,13 @@ where
 
     /// Remove a single word when user does ctrl/opt + delete
     pub fn delete_word(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::DeleteWord);
         self.remove_word_in_direction(Direction::Forwards)
     }
 
     /// Remove a single word when user does ctrl/opt + backspace
     pub fn backspace_word(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::BackspaceWord);
         self.remove_word_in_direction(Direction::Backwards)
     }
 
@@ -212,7 +319,7 @@ where
             // backspace and delete be equivalent, as well as the do_* functions
             return match direction {
                 Direction::Forwards => self.do_delete(),
-                Direction::Backwards => self.backspace(),
+                Direction::Backwards => self.push_history_and_backspace(),
             };
         }
         match self.state.dom.lookup_node_mut(&location.node_handle) {
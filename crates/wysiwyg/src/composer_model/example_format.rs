@@ -9,13 +9,20 @@ use std::ops::Not;
 
 use widestring::{Utf16Str, Utf16String};
 
-use crate::char::CharExt;
+use crate::block_ids::BlockIds;
 use crate::composer_model::menu_state::MenuStateComputeType;
 use crate::dom::nodes::{ContainerNode, LineBreakNode, MentionNode, TextNode};
-use crate::dom::parser::parse;
+use crate::dom::parser::{
+    parse_from_source_with_options, DEFAULT_MAX_PARSE_DEPTH,
+};
 use crate::dom::to_html::ToHtmlState;
 use crate::dom::unicode_string::{UnicodeStr, UnicodeStrExt};
-use crate::dom::{Dom, DomLocation};
+use crate::dom::{
+    AtRoomDetection, AttributePolicy, Dom, DomLocation, HtmlSource,
+    WhitespacePolicy,
+};
+use crate::nbsp::nbsp_to_entity;
+use crate::tracked_content::TrackedContent;
 use crate::{
     ComposerModel, DomHandle, DomNode, Location, ToHtml, UnicodeString,
 };
@@ -54,6 +61,13 @@ impl ComposerModel<Utf16String> {
     /// followed by a text node containing `CC`, with a selection starting and
     /// ending at 3.
     ///
+    /// Attributes are kept as they are written, so features which store
+    /// them can be described too, e.g. `<ol start="3">` for the start of a
+    /// list, `<pre><code class="language-rust">` for the language of a code
+    /// block, `data-*` attributes, or the `style` of a mention. Every class
+    /// and `data-*` attribute is kept, whatever the model's
+    /// [crate::AttributePolicy].
+    ///
     /// ## Examples
     ///
     /// ```
@@ -68,7 +82,17 @@ impl ComposerModel<Utf16String> {
     /// ```
     pub fn from_example_format(text: &str) -> Self {
         let mut model = ComposerModel::new();
-        model.state.dom = parse(text).unwrap();
+        let (dom, _) = parse_from_source_with_options(
+            text,
+            HtmlSource::Matrix,
+            DEFAULT_MAX_PARSE_DEPTH,
+            &example_attribute_policy(),
+            WhitespacePolicy::default(),
+            AtRoomDetection::default(),
+            false,
+        )
+        .unwrap();
+        model.state.dom = dom;
 
         let mut offset = 0;
         let (start, end, curs) = Self::find_selection_in(
@@ -137,6 +161,9 @@ impl ComposerModel<Utf16String> {
             .dom
             .wrap_inline_nodes_into_paragraphs_if_needed(&DomHandle::root());
         model.state.dom.explicitly_assert_invariants();
+        model.tracked_content = TrackedContent::of(&model.state.dom);
+        model.block_ids = BlockIds::new(model.tracked_content.len());
+        model.history_start_state = model.state.clone();
 
         model
     }
@@ -267,7 +294,16 @@ impl ComposerModel<Utf16String> {
         }
 
         // Replace characters with visible ones
-        html.replace(char::nbsp(), "&nbsp;")
+        nbsp_to_entity(&html)
+    }
+}
+
+/// Keeps every class and `data-*` attribute, so that examples can describe
+/// them.
+fn example_attribute_policy() -> AttributePolicy {
+    AttributePolicy {
+        preserve_data_attributes: true,
+        allowed_classes: vec!["*".to_owned()],
     }
 }
 
@@ -941,6 +977,25 @@ mod test {
         .roundtrips();
     }
 
+    #[test]
+    fn attributes_roundtrip() {
+        assert_that!("<ol start=\"3\"><li>a|</li></ol>").roundtrips();
+        assert_that!("<pre><code class=\"language-rust\">a|</code></pre>")
+            .roundtrips();
+        assert_that!("<p data-id=\"1\">a|</p>").roundtrips();
+        assert_that!(
+            "<a style=\"color: red\" data-mention-type=\"user\" \
+            href=\"https://matrix.to/#/@a:b.org\" \
+            contenteditable=\"false\">Alice</a>|"
+        )
+        .roundtrips();
+        assert_that!(
+            "<a class=\"pill\" data-mention-type=\"at-room\" href=\"#\" \
+            contenteditable=\"false\">@room</a>|"
+        )
+        .roundtrips();
+    }
+
     trait Roundtrips<T> {
         fn roundtrips(&self);
     }
@@ -0,0 +1,393 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::composer_model::delete_text::Direction;
+use crate::dom::nodes::text_node::{get_char_type, CharType};
+use crate::dom::nodes::{ContainerNode, TextNode};
+use crate::dom::unicode_string::UnicodeStrExt;
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, DomNode, Location,
+    UnicodeString,
+};
+
+/// How far [ComposerModel::extend_selection] moves the selection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Granularity {
+    Character,
+    Word,
+    Block,
+    Document,
+}
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// Move the end of the selection which is not anchored (the `end`
+    /// passed to [Self::select]) as Shift+Arrow keys do, keeping the other
+    /// end where it is.
+    ///
+    /// Characters are whole graphemes and mentions are a single character.
+    /// Words follow the same rules as [Self::backspace_word]: whitespace is
+    /// skipped, then a run of punctuation or of other characters is taken.
+    /// Blocks move to the start or end of the paragraph, list item etc.,
+    /// or into the next one if already there.
+    pub fn extend_selection(
+        &mut self,
+        direction: Direction,
+        granularity: Granularity,
+    ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::ExtendSelection {
+            direction,
+            granularity,
+        });
+        let focus = usize::from(self.state.end).min(self.state.dom.text_len());
+        let units = units_in(self.state.dom.document());
+        let new_focus = match granularity {
+            Granularity::Character => step(&units, focus, &direction, |_| true),
+            Granularity::Word => move_by_word(&units, focus, &direction),
+            Granularity::Block => move_by_block(&units, focus, &direction),
+            Granularity::Document => match direction {
+                Direction::Forwards => self.state.dom.text_len(),
+                Direction::Backwards => 0,
+            },
+        };
+        self.do_select(self.state.start, Location::from(new_focus))
+    }
+
+    /// The closest position before `location` that the cursor can be put
+    /// at, for moving it as the Left arrow key does, without splitting a
+    /// grapheme such as an emoji ZWJ sequence. A mention, a line break and
+    /// the gap between two blocks each count as one grapheme. `location`
+    /// itself if it is at the start of the content.
+    pub fn previous_grapheme_boundary(&self, location: Location) -> Location {
+        let position = usize::from(location).min(self.state.dom.text_len());
+        let units = units_in(self.state.dom.document());
+        let boundary = units
+            .iter()
+            .rev()
+            .find(|unit| unit.start < position)
+            .map_or(position, |unit| unit.start);
+        Location::from(boundary)
+    }
+
+    /// The closest position after `location` that the cursor can be put
+    /// at, as for [Self::previous_grapheme_boundary] but moving forwards.
+    /// `location` itself, clamped to the length of the content, if it is
+    /// at the end.
+    pub fn next_grapheme_boundary(&self, location: Location) -> Location {
+        let position = usize::from(location).min(self.state.dom.text_len());
+        let units = units_in(self.state.dom.document());
+        let boundary = units
+            .iter()
+            .find(|unit| unit.end > position)
+            .map_or(position, |unit| unit.end);
+        Location::from(boundary)
+    }
+}
+
+/// The smallest pieces of content the cursor can move over.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum UnitKind {
+    Char(CharType),
+    Mention,
+    LineBreak,
+    /// The gap between two blocks
+    BlockBoundary,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Unit {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) kind: UnitKind,
+}
+
+impl Unit {
+    fn is_break(&self) -> bool {
+        matches!(self.kind, UnitKind::LineBreak | UnitKind::BlockBoundary)
+    }
+}
+
+/// Every grapheme, mention, line break and block boundary in the
+/// container, in order.
+pub(crate) fn units_in<S>(container: &ContainerNode<S>) -> Vec<Unit>
+where
+    S: UnicodeString,
+{
+    let mut units = Vec::new();
+    collect_units(container, &mut 0, &mut units);
+    units
+}
+
+fn collect_units<S>(
+    container: &ContainerNode<S>,
+    offset: &mut usize,
+    units: &mut Vec<Unit>,
+) where
+    S: UnicodeString,
+{
+    let children = container.children();
+    for (i, child) in children.iter().enumerate() {
+        match child {
+            DomNode::Container(c) => {
+                collect_units(c, offset, units);
+                if c.is_block_node() && i + 1 < children.len() {
+                    push_unit(units, offset, 1, UnitKind::BlockBoundary);
+                }
+            }
+            DomNode::Text(t) => collect_graphemes(t, offset, units),
+            DomNode::LineBreak(_) => {
+                push_unit(units, offset, 1, UnitKind::LineBreak)
+            }
+            DomNode::Mention(_) => {
+                push_unit(units, offset, child.text_len(), UnitKind::Mention)
+            }
+        }
+    }
+}
+
+fn collect_graphemes<S>(
+    text: &TextNode<S>,
+    offset: &mut usize,
+    units: &mut Vec<Unit>,
+) where
+    S: UnicodeString,
+{
+    let data = text.data().to_string();
+    for grapheme in data.graphemes(true) {
+        let len = S::from(grapheme).len();
+        let first = grapheme.chars().next().unwrap_or(' ');
+        push_unit(units, offset, len, UnitKind::Char(get_char_type(first)));
+    }
+}
+
+fn push_unit(
+    units: &mut Vec<Unit>,
+    offset: &mut usize,
+    len: usize,
+    kind: UnitKind,
+) {
+    units.push(Unit {
+        start: *offset,
+        end: *offset + len,
+        kind,
+    });
+    *offset += len;
+}
+
+/// The unit next to position in the given direction, if any. The units are
+/// in order and don't overlap, so they are searched by halves.
+pub(crate) fn unit_next_to(
+    units: &[Unit],
+    position: usize,
+    direction: &Direction,
+) -> Option<Unit> {
+    match direction {
+        Direction::Forwards => {
+            let i = units.partition_point(|u| u.start < position);
+            units.get(i).copied()
+        }
+        Direction::Backwards => {
+            let i = units.partition_point(|u| u.end <= position);
+            i.checked_sub(1).map(|i| units[i])
+        }
+    }
+}
+
+/// Move over the unit next to position if it matches, returning the new
+/// position.
+fn step(
+    units: &[Unit],
+    position: usize,
+    direction: &Direction,
+    matches: impl Fn(&Unit) -> bool,
+) -> usize {
+    match unit_next_to(units, position, direction) {
+        Some(unit) if matches(&unit) => match direction {
+            Direction::Forwards => unit.end,
+            Direction::Backwards => unit.start,
+        },
+        _ => position,
+    }
+}
+
+/// Move over units next to position for as long as they match, returning
+/// the new position.
+pub(crate) fn skip_while(
+    units: &[Unit],
+    position: usize,
+    direction: &Direction,
+    matches: impl Fn(&Unit) -> bool,
+) -> usize {
+    let mut position = position;
+    loop {
+        let next = step(units, position, direction, &matches);
+        if next == position {
+            return position;
+        }
+        position = next;
+    }
+}
+
+fn move_by_word(
+    units: &[Unit],
+    position: usize,
+    direction: &Direction,
+) -> usize {
+    let Some(first) = unit_next_to(units, position, direction) else {
+        return position;
+    };
+    if first.is_break() || first.kind == UnitKind::Mention {
+        return step(units, position, direction, |_| true);
+    }
+    let position = skip_while(units, position, direction, |u| {
+        u.kind == UnitKind::Char(CharType::Whitespace)
+    });
+    match unit_next_to(units, position, direction) {
+        Some(word) if !word.is_break() && word.kind != UnitKind::Mention => {
+            skip_while(units, position, direction, |u| u.kind == word.kind)
+        }
+        _ => position,
+    }
+}
+
+fn move_by_block(
+    units: &[Unit],
+    position: usize,
+    direction: &Direction,
+) -> usize {
+    // Cross into the next block if we are already at the edge of this one
+    let position = step(units, position, direction, |u| {
+        u.kind == UnitKind::BlockBoundary
+    });
+    skip_while(units, position, direction, |u| {
+        u.kind != UnitKind::BlockBoundary
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::{Direction, Granularity, TextUpdate};
+
+    use Direction::{Backwards, Forwards};
+    use Granularity::{Block, Character, Document, Word};
+
+    fn extended(html: &str, moves: &[(Direction, Granularity)]) -> String {
+        let mut model = cm(html);
+        for (direction, granularity) in moves {
+            let update = model.extend_selection(*direction, *granularity);
+            assert!(!matches!(update.text_update, TextUpdate::ReplaceAll(_)));
+        }
+        tx(&model)
+    }
+
+    #[test]
+    fn extending_by_character_keeps_graphemes_together() {
+        assert_eq!(extended("a|👍🏽b", &[(Forwards, Character)]), "a{👍🏽}|b");
+        assert_eq!(extended("a👍🏽|b", &[(Backwards, Character)]), "a|{👍🏽}b");
+    }
+
+    #[test]
+    fn extending_by_character_moves_over_mentions() {
+        assert_eq!(
+            extended(
+                "a|<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>b",
+                &[(Forwards, Character)],
+            ),
+            "a{<a data-mention-type=\"user\" href=\"https://matrix.to/#/@alice:matrix.org\" contenteditable=\"false\">Alice</a>}|b"
+        );
+    }
+
+    #[test]
+    fn extending_by_word_matches_backspace_word() {
+        assert_eq!(extended("|abc def", &[(Forwards, Word)]), "{abc}| def");
+        assert_eq!(
+            extended("|abc def", &[(Forwards, Word), (Forwards, Word)]),
+            "{abc def}|"
+        );
+        assert_eq!(
+            extended("abc...def|", &[(Backwards, Word), (Backwards, Word)]),
+            "abc|{...def}"
+        );
+    }
+
+    #[test]
+    fn extending_by_word_stops_at_line_breaks() {
+        let mut model = cm("abc|<br />def");
+        model.extend_selection(Forwards, Word);
+        assert_eq!(model.get_selection(), (3.into(), 4.into()));
+        model.extend_selection(Forwards, Word);
+        assert_eq!(model.get_selection(), (3.into(), 7.into()));
+    }
+
+    #[test]
+    fn extending_by_block_moves_to_the_edges_of_blocks() {
+        assert_eq!(
+            extended("<p>a|bc</p><p>def</p>", &[(Forwards, Block)]),
+            "<p>a{bc}|</p><p>def</p>"
+        );
+        assert_eq!(
+            extended(
+                "<p>a|bc</p><p>def</p>",
+                &[(Forwards, Block), (Forwards, Block)]
+            ),
+            "<p>a{bc</p><p>def}|</p>"
+        );
+        assert_eq!(
+            extended("<p>abc</p><p>de|f</p>", &[(Backwards, Block)]),
+            "<p>abc</p><p>|{de}f</p>"
+        );
+    }
+
+    #[test]
+    fn extending_by_document_moves_to_either_end() {
+        assert_eq!(
+            extended("<p>abc</p><p>d|ef</p>", &[(Backwards, Document)]),
+            "<p>|{abc</p><p>d}ef</p>"
+        );
+        assert_eq!(
+            extended("<p>abc</p><p>d|ef</p>", &[(Forwards, Document)]),
+            "<p>abc</p><p>d{ef}|</p>"
+        );
+    }
+
+    #[test]
+    fn extending_past_the_anchor_reverses_the_selection() {
+        assert_eq!(
+            extended(
+                "ab{c}|d",
+                &[(Backwards, Character), (Backwards, Character)]
+            ),
+            "a|{b}cd"
+        );
+    }
+
+    #[test]
+    fn grapheme_boundaries_skip_over_zwj_sequences() {
+        let model = cm("a👨‍👩‍👧b|");
+        assert_eq!(model.next_grapheme_boundary(1.into()), 9);
+        assert_eq!(model.previous_grapheme_boundary(9.into()), 1);
+        assert_eq!(model.next_grapheme_boundary(5.into()), 9);
+        assert_eq!(model.previous_grapheme_boundary(5.into()), 1);
+    }
+
+    #[test]
+    fn grapheme_boundaries_stop_at_the_ends_of_the_content() {
+        let model = cm("<p>a</p><p>b|</p>");
+        assert_eq!(model.previous_grapheme_boundary(0.into()), 0);
+        assert_eq!(model.next_grapheme_boundary(3.into()), 3);
+        assert_eq!(model.next_grapheme_boundary(10.into()), 3);
+        assert_eq!(model.next_grapheme_boundary(1.into()), 2);
+    }
+}
@@ -12,7 +12,8 @@ use crate::dom::nodes::{ContainerNodeKind, DomNode};
 use crate::dom::unicode_string::UnicodeStrExt;
 use crate::dom::{Dom, DomHandle, DomLocation, Range};
 use crate::{
-    ComposerModel, ComposerUpdate, InlineFormatType, MenuAction, UnicodeString,
+    ComposerAction, ComposerModel, ComposerOperation, ComposerUpdate,
+    HistoryEntryKind, InlineFormatType, MenuAction, UnicodeString,
 };
 
 #[derive(Eq, PartialEq, Debug)]
@@ -26,27 +27,47 @@ where
     S: UnicodeString,
 {
     pub fn bold(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::Bold);
+        if self.action_is_disallowed(ComposerAction::Bold) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Formatting);
         self.format_or_unformat(InlineFormatType::Bold)
     }
 
     pub fn italic(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::Italic);
+        if self.action_is_disallowed(ComposerAction::Italic) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Formatting);
         self.format_or_unformat(InlineFormatType::Italic)
     }
 
     pub fn strike_through(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::StrikeThrough);
+        if self.action_is_disallowed(ComposerAction::StrikeThrough) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Formatting);
         self.format_or_unformat(InlineFormatType::StrikeThrough)
     }
 
     pub fn underline(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::Underline);
+        if self.action_is_disallowed(ComposerAction::Underline) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Formatting);
         self.format_or_unformat(InlineFormatType::Underline)
     }
 
     pub fn inline_code(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::InlineCode);
+        if self.action_is_disallowed(ComposerAction::InlineCode) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Formatting);
         let format_type = InlineFormatType::InlineCode;
         if self.action_is_reversed(format_type.action()) {
             self.unformat(format_type)
@@ -55,6 +76,90 @@ where
         }
     }
 
+    /// Turn each of the formats on, or off, over the selection in one step
+    /// with a single undo entry, e.g. to apply a preset of several formats.
+    /// Formats which are already on or off as asked, or whose actions are
+    /// disallowed, are left as they are.
+    pub fn apply_formats(
+        &mut self,
+        formats: &[InlineFormatType],
+        enabled: bool,
+    ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::ApplyFormats {
+            formats: formats.to_vec(),
+            enabled,
+        });
+        let formats: Vec<&InlineFormatType> = formats
+            .iter()
+            .filter(|f| !self.action_is_disallowed(f.action()))
+            .collect();
+        if formats.is_empty() {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Formatting);
+
+        let (s, e) = self.safe_selection();
+        for format in formats {
+            // Check each time, as applying one format can change another,
+            // e.g. inline code replaces the formatting inside it
+            if self.format_is_applied(s, e, format) == enabled {
+                continue;
+            }
+            if s == e {
+                self.toggle_zero_length_format(format);
+            } else if enabled {
+                self.format_range(s, e, format);
+            } else {
+                self.unformat_range(s, e, format);
+            }
+        }
+
+        if s == e {
+            ComposerUpdate::update_menu_state(
+                self.compute_menu_state(MenuStateComputeType::KeepIfUnchanged),
+                MenuAction::Keep,
+            )
+        } else {
+            self.create_update_replace_all()
+        }
+    }
+
+    /// Turn the formats of the named style, defined with
+    /// [crate::ComposerModelConfig::with_style], on over the selection in
+    /// one step, or off if they all apply already. Does nothing if there is
+    /// no style with the name.
+    pub fn apply_style(&mut self, name: &str) -> ComposerUpdate<S> {
+        let Some(formats) = self.config.style(name).map(<[_]>::to_vec) else {
+            return ComposerUpdate::keep();
+        };
+        let enabled = !self.style_is_applied(name);
+        self.apply_formats(&formats, enabled)
+    }
+
+    /// True if every format of the named style applies to the whole
+    /// selection, or to text typed at the cursor, e.g. to show the style as
+    /// active in a toolbar.
+    pub fn style_is_applied(&self, name: &str) -> bool {
+        let Some(formats) = self.config.style(name) else {
+            return false;
+        };
+        let (s, e) = self.safe_selection();
+        formats.iter().all(|f| self.format_is_applied(s, e, f))
+    }
+
+    /// True if the format applies to the whole selection, or to text typed
+    /// at the cursor.
+    fn format_is_applied(
+        &self,
+        start: usize,
+        end: usize,
+        format: &InlineFormatType,
+    ) -> bool {
+        let range = self.state.dom.find_range(start, end);
+        self.compute_reversed_actions_from_range(&range)
+            .contains(&format.action())
+    }
+
     /// Finds the closest structure node ancestor for each leaf node handle and groups it with other
     /// leaves that share it as the common closest structure node ancestor. If none is found,
     /// the root/document node is used instead.
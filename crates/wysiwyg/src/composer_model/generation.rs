@@ -0,0 +1,222 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{ComposerModel, DomHandle, DomNode, UnicodeString};
+
+/// Shared by all Doms, so that a generation also identifies which model
+/// handed out a handle.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// The generation of a Dom's content, see [crate::dom::Dom::generation].
+/// Cloning it hands out a new generation, so handles given out by a model
+/// are not valid on its clones.
+#[derive(Debug)]
+pub(crate) struct Generation(u64);
+
+impl Generation {
+    pub(crate) fn new() -> Self {
+        Self(NEXT_GENERATION.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub(crate) fn get(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Clone for Generation {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl Default for Generation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returned when a handle can't be looked up with
+/// [ComposerModel::lookup_node_at_generation].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HandleLookupError {
+    /// The handle was handed out by a different model, or by this model
+    /// before its content last changed.
+    StaleHandle {
+        handle_generation: u64,
+        model_generation: u64,
+    },
+    /// The handle is of the current generation but refers to no node, e.g.
+    /// because it was made up rather than handed out by the model.
+    InvalidHandle(DomHandle),
+}
+
+impl fmt::Display for HandleLookupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::StaleHandle {
+                handle_generation,
+                model_generation,
+            } => write!(
+                f,
+                "stale handle: it was created at generation \
+                {handle_generation} but the model is at generation \
+                {model_generation}"
+            ),
+            Self::InvalidHandle(handle) => {
+                write!(f, "invalid handle: no node at {:?}", handle.raw())
+            }
+        }
+    }
+}
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// An identifier for the current content of this model. It changes
+    /// whenever the content is replaced or edited, and is never shared with
+    /// another model, not even a clone of this one, so handles can be
+    /// stamped with it when they are given out and checked with
+    /// [Self::lookup_node_at_generation].
+    pub fn generation(&self) -> u64 {
+        self.state.dom.generation()
+    }
+
+    /// A hash of the content as of the last update, ignoring the selection.
+    /// Unlike [Self::generation], it is the same whenever the content is,
+    /// e.g. after undoing back to it, so clients can tell whether a draft
+    /// changed since it was saved without comparing its HTML. It is made
+    /// from the hashes of the top-level nodes which each update works out
+    /// anyway, so it is cheap to call after every update. The hash uses
+    /// std's [DefaultHasher], whose algorithm isn't guaranteed to stay the
+    /// same between Rust releases, so it can only be compared with hashes
+    /// from the same process and build, and must not be persisted.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.tracked_content.hashes().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Find the node at the given handle, provided the model has not changed
+    /// since `generation` was read from [Self::generation] and the handle
+    /// refers to a node.
+    pub fn lookup_node_at_generation(
+        &self,
+        handle: &DomHandle,
+        generation: u64,
+    ) -> Result<&DomNode<S>, HandleLookupError> {
+        if generation != self.generation() {
+            return Err(HandleLookupError::StaleHandle {
+                handle_generation: generation,
+                model_generation: self.generation(),
+            });
+        }
+        if !self.state.dom.contains(handle) {
+            return Err(HandleLookupError::InvalidHandle(handle.clone()));
+        }
+        Ok(self.state.dom.lookup_node(handle))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::cm;
+    use crate::{DomHandle, Location};
+
+    use super::HandleLookupError;
+
+    #[test]
+    fn content_hash_changes_with_the_content_only() {
+        let mut model = cm("abc|");
+        let hash = model.content_hash();
+        model.select(Location::from(0), Location::from(1));
+        assert_eq!(model.content_hash(), hash);
+
+        model.replace_text(Utf16String::from("d"));
+        assert_ne!(model.content_hash(), hash);
+        model.undo();
+        assert_eq!(model.content_hash(), hash);
+        assert_eq!(cm("|abc").content_hash(), hash);
+        assert_ne!(cm("<b>abc|</b>").content_hash(), hash);
+    }
+
+    #[test]
+    fn handle_is_valid_until_the_content_changes() {
+        let mut model = cm("abc|");
+        let generation = model.generation();
+        let handle = DomHandle::from_raw(vec![0]);
+        assert!(model.lookup_node_at_generation(&handle, generation).is_ok());
+
+        model.replace_text(Utf16String::from("d"));
+        assert_eq!(
+            model.lookup_node_at_generation(&handle, generation),
+            Err(HandleLookupError::StaleHandle {
+                handle_generation: generation,
+                model_generation: model.generation(),
+            })
+        );
+    }
+
+    #[test]
+    fn changing_the_selection_does_not_invalidate_handles() {
+        let mut model = cm("abc|");
+        let generation = model.generation();
+        model.select(Location::from(0), Location::from(1));
+        let handle = DomHandle::from_raw(vec![0]);
+        assert!(model.lookup_node_at_generation(&handle, generation).is_ok());
+    }
+
+    #[test]
+    fn undo_invalidates_handles() {
+        let mut model = cm("abc|");
+        model.replace_text(Utf16String::from("d"));
+        let generation = model.generation();
+        model.undo();
+        let handle = DomHandle::from_raw(vec![0]);
+        assert!(model
+            .lookup_node_at_generation(&handle, generation)
+            .is_err());
+    }
+
+    #[test]
+    fn handle_from_another_model_is_stale() {
+        let model = cm("abc|");
+        let other = cm("abc|");
+        let handle = DomHandle::from_raw(vec![0]);
+        assert!(model
+            .lookup_node_at_generation(&handle, other.generation())
+            .is_err());
+    }
+
+    #[test]
+    fn handle_from_the_model_a_clone_was_made_from_is_stale() {
+        let model = cm("abc|");
+        let clone = model.clone();
+        let handle = DomHandle::from_raw(vec![0]);
+        assert!(clone
+            .lookup_node_at_generation(&handle, model.generation())
+            .is_err());
+        assert!(model
+            .lookup_node_at_generation(&handle, clone.generation())
+            .is_err());
+    }
+
+    #[test]
+    fn handle_to_no_node_is_invalid() {
+        let model = cm("abc|");
+        let handle = DomHandle::from_raw(vec![3]);
+        assert_eq!(
+            model.lookup_node_at_generation(&handle, model.generation()),
+            Err(HandleLookupError::InvalidHandle(handle))
+        );
+    }
+}
@@ -6,6 +6,7 @@
 
 use std::cmp::{max, min};
 
+use crate::composer_operation::attributes_to_strings;
 use crate::dom::nodes::dom_node::DomNodeKind;
 use crate::dom::nodes::dom_node::DomNodeKind::{Link, List};
 use crate::dom::nodes::ContainerNodeKind;
@@ -13,7 +14,8 @@ use crate::dom::nodes::DomNode;
 use crate::dom::unicode_string::UnicodeStrExt;
 use crate::dom::Range;
 use crate::{
-    ComposerModel, ComposerUpdate, DomHandle, LinkAction, UnicodeString,
+    ComposerAction, ComposerModel, ComposerOperation, ComposerUpdate,
+    DomHandle, HistoryEntryKind, LinkAction, Location, UnicodeString,
 };
 use email_address::*;
 use url::{ParseError, Url};
@@ -45,7 +47,14 @@ where
                 LinkAction::Disabled
             } else {
                 // Otherwise we edit the first link of the selection.
-                LinkAction::Edit(first_link.get_link_url().unwrap())
+                let attributes = first_link
+                    .attributes()
+                    .into_iter()
+                    .flatten()
+                    .filter(|(name, _)| *name != "href".into())
+                    .cloned()
+                    .collect();
+                LinkAction::Edit(first_link.get_link_url().unwrap(), attributes)
             }
         } else if s == e || self.is_blank_selection(range) {
             LinkAction::CreateWithText
@@ -78,7 +87,8 @@ where
                 | DomNodeKind::CodeBlock
                 | DomNodeKind::Quote
                 | DomNodeKind::Generic
-                | DomNodeKind::Paragraph => {
+                | DomNodeKind::Paragraph
+                | DomNodeKind::Span => {
                     unreachable!("Inside leaf iterator and found a non-leaf")
                 }
             }
@@ -92,8 +102,18 @@ where
         text: S,
         attributes: Vec<(S, S)>,
     ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::SetLinkWithText {
+            url: url.to_string(),
+            text: text.to_string(),
+            attributes: attributes_to_strings(&attributes),
+        });
+        if self.action_is_disallowed(ComposerAction::Link)
+            || !self.link_is_allowed(&url)
+        {
+            return ComposerUpdate::keep();
+        }
         let (s, _) = self.safe_selection();
-        self.push_state_to_history();
+        self.push_state_to_history(HistoryEntryKind::Link);
         self.do_replace_text(text.clone());
         let e = s + text.len();
         let range = self.state.dom.find_range(s, e);
@@ -105,7 +125,16 @@ where
         url: S,
         attributes: Vec<(S, S)>,
     ) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::SetLink {
+            url: url.to_string(),
+            attributes: attributes_to_strings(&attributes),
+        });
+        if self.action_is_disallowed(ComposerAction::Link)
+            || !self.link_is_allowed(&url)
+        {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Link);
         let (s, e) = self.safe_selection();
 
         let range = self.state.dom.find_range(s, e);
@@ -113,7 +142,7 @@ where
         self.set_link_in_range(url, range, attributes)
     }
 
-    fn set_link_in_range(
+    pub(crate) fn set_link_in_range(
         &mut self,
         mut url: S,
         range: Range,
@@ -215,7 +244,15 @@ where
         self.create_update_replace_all()
     }
 
-    fn add_http_scheme(&mut self, url: &mut S) {
+    /// Whether the configuration allows links to be set with this URL,
+    /// once any missing scheme is added to it.
+    fn link_is_allowed(&self, url: &S) -> bool {
+        let mut url = url.clone();
+        self.add_http_scheme(&mut url);
+        self.config.allows_link_url(&url.to_string())
+    }
+
+    fn add_http_scheme(&self, url: &mut S) {
         let string = url.to_string();
         let str = string.as_str();
 
@@ -297,15 +334,47 @@ where
     }
 
     pub fn remove_links(&mut self) -> ComposerUpdate<S> {
-        let mut has_found_link = false;
+        let _span = self.start_operation(ComposerOperation::RemoveLinks);
         let (s, e) = self.safe_selection();
+        self.do_remove_links_in(s, e)
+    }
+
+    /// Remove the links in the range between the offsets, leaving their
+    /// text, without moving the selection, e.g. for a context menu on a
+    /// link which isn't selected.
+    pub fn remove_links_in(
+        &mut self,
+        start: Location,
+        end: Location,
+    ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::RemoveLinksIn {
+            start: start.into(),
+            end: end.into(),
+        });
+        let len = self.state.dom.text_len();
+        let (s, e) = (usize::from(start), usize::from(end));
+        self.do_remove_links_in(min(min(s, e), len), min(max(s, e), len))
+    }
+
+    /// Remove the link at the offset, leaving its text, without moving the
+    /// selection, e.g. for the link under a long press.
+    pub fn remove_link_at(&mut self, offset: Location) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::RemoveLinkAt {
+            offset: offset.into(),
+        });
+        let offset = min(usize::from(offset), self.state.dom.text_len());
+        self.do_remove_links_in(offset, offset)
+    }
+
+    fn do_remove_links_in(&mut self, s: usize, e: usize) -> ComposerUpdate<S> {
+        let mut has_found_link = false;
         let range = self.state.dom.find_range(s, e);
         let iter = range.locations.into_iter().rev();
         for loc in iter {
             if loc.kind == DomNodeKind::Link {
                 if !has_found_link {
                     has_found_link = true;
-                    self.push_state_to_history();
+                    self.push_state_to_history(HistoryEntryKind::Link);
                 }
                 self.state
                     .dom
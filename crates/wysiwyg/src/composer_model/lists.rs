@@ -6,27 +6,42 @@
 
 use crate::dom::nodes::dom_node::DomNodeKind;
 use crate::dom::nodes::dom_node::DomNodeKind::Paragraph;
-use crate::dom::nodes::DomNode;
+use crate::dom::nodes::{ContainerNodeKind, DomNode};
 use crate::dom::range::DomLocationPosition;
 use crate::dom::range::DomLocationPosition::Before;
 use crate::dom::{DomHandle, DomLocation, Range};
-use crate::{ComposerModel, ComposerUpdate, ListType, UnicodeString};
+use crate::{
+    ComposerAction, ComposerModel, ComposerOperation, ComposerUpdate,
+    HistoryEntryKind, ListType, UnicodeString,
+};
 
 impl<S> ComposerModel<S>
 where
     S: UnicodeString,
 {
     pub fn ordered_list(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::OrderedList);
+        if self.action_is_disallowed(ComposerAction::OrderedList) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::List);
         self.toggle_list(ListType::Ordered)
     }
 
     pub fn unordered_list(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::UnorderedList);
+        if self.action_is_disallowed(ComposerAction::UnorderedList) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::List);
         self.toggle_list(ListType::Unordered)
     }
 
     pub fn indent(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::Indent);
+        if self.action_is_disallowed(ComposerAction::Indent) {
+            return ComposerUpdate::keep();
+        }
         // push_state_to_history is called if we can indent
         let (s, e) = self.safe_selection();
         let range = self.state.dom.find_range(s, e);
@@ -35,7 +50,7 @@ where
         if !top_most_locations.is_empty()
             && self.can_indent(&top_most_locations)
         {
-            self.push_state_to_history();
+            self.push_state_to_history(HistoryEntryKind::Indent);
             self.indent_locations(&top_most_locations);
             self.create_update_replace_all()
         } else {
@@ -44,13 +59,17 @@ where
     }
 
     pub fn unindent(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::Unindent);
+        if self.action_is_disallowed(ComposerAction::Unindent) {
+            return ComposerUpdate::keep();
+        }
         // push_state_to_history is called if we can unindent
         let (s, e) = self.safe_selection();
         let range = self.state.dom.find_range(s, e);
         let top_most_locations =
             self.find_top_most_list_item_locations(&range.locations);
         if self.can_unindent(&top_most_locations) {
-            self.push_state_to_history();
+            self.push_state_to_history(HistoryEntryKind::Indent);
             self.unindent_locations(&top_most_locations);
             self.create_update_replace_all()
         } else {
@@ -58,6 +77,85 @@ where
         }
     }
 
+    /// Split the list containing the cursor in two, so that the item with
+    /// the cursor starts a new list of the same type, e.g. to restart the
+    /// numbering of an ordered list. Does nothing if the cursor isn't in a
+    /// list, or is in its first item.
+    pub fn split_list_at_cursor(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::SplitListAtCursor);
+        let Some(item_handle) = self.list_item_at_cursor() else {
+            return ComposerUpdate::keep();
+        };
+        let index = item_handle.index_in_parent();
+        if index == 0 {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::List);
+
+        let list_handle = item_handle.parent_handle();
+        let DomNode::Container(list) =
+            self.state.dom.lookup_node_mut(&list_handle)
+        else {
+            unreachable!("Lists are containers");
+        };
+        let ContainerNodeKind::List(list_type) = list.kind().clone() else {
+            unreachable!("The parent of a list item must be a list");
+        };
+        let items = (index..list.children().len())
+            .map(|_| list.remove_child(index))
+            .collect();
+        self.state.dom.insert_at(
+            &list_handle.next_sibling(),
+            DomNode::new_list(list_type, items),
+        );
+        self.create_update_replace_all()
+    }
+
+    /// Move the items of the list containing the cursor to the end of the
+    /// list right before it, which they then take the type of. Does nothing
+    /// if the cursor isn't in a list, or the list doesn't follow another.
+    pub fn join_with_previous_list(&mut self) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::JoinWithPreviousList);
+        let Some(item_handle) = self.list_item_at_cursor() else {
+            return ComposerUpdate::keep();
+        };
+        let list_handle = item_handle.parent_handle();
+        if list_handle.index_in_parent() == 0
+            || !self
+                .state
+                .dom
+                .lookup_node(&list_handle.prev_sibling())
+                .is_list()
+        {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::List);
+
+        let DomNode::Container(mut list) = self.state.dom.remove(&list_handle)
+        else {
+            unreachable!("Lists are containers");
+        };
+        let DomNode::Container(previous_list) =
+            self.state.dom.lookup_node_mut(&list_handle.prev_sibling())
+        else {
+            unreachable!("Lists are containers");
+        };
+        previous_list.append_children(list.remove_children());
+        self.create_update_replace_all()
+    }
+
+    /// The handle of the deepest list item containing the start of the
+    /// selection, if any.
+    fn list_item_at_cursor(&self) -> Option<DomHandle> {
+        let (s, _) = self.safe_selection();
+        let range = self.state.dom.find_range(s, s);
+        let block_location = range.deepest_block_node(None)?;
+        self.state
+            .dom
+            .find_ancestor_list_item_or_self(&block_location.node_handle)
+    }
+
     pub fn can_indent(&self, locations: &[DomLocation]) -> bool {
         let list_item_locations: Vec<&DomLocation> = locations
             .iter()
@@ -170,7 +268,10 @@ where
         self.create_update_replace_all()
     }
 
-    fn toggle_list(&mut self, list_type: ListType) -> ComposerUpdate<S> {
+    pub(crate) fn toggle_list(
+        &mut self,
+        list_type: ListType,
+    ) -> ComposerUpdate<S> {
         let (s, e) = self.safe_selection();
         let range = self.state.dom.find_extended_range(s, e);
         self.toggle_list_range(list_type, range)
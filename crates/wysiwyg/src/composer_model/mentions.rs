@@ -4,13 +4,21 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
+use std::cmp::Reverse;
+
 use crate::{
+    composer_operation::attributes_to_strings,
     dom::{
-        nodes::{MentionNode, MentionNodeKind},
+        nodes::{
+            dom_node::DomNodeKind, mention_node::AT_ROOM, MentionNode,
+            MentionNodeKind,
+        },
+        unicode_string::UnicodeStrExt,
         DomLocation,
     },
-    ComposerModel, ComposerUpdate, DomNode, Location, MentionsState,
-    SuggestionPattern, UnicodeString,
+    ComposerModel, ComposerOperation, ComposerUpdate, DomHandle, DomNode,
+    HistoryEntryKind, Location, MentionsState, SuggestionPattern,
+    UnicodeString,
 };
 
 impl<S> ComposerModel<S>
@@ -48,6 +56,11 @@ where
                 },
             }
         }
+        mentions_state.has_at_room_text = self
+            .state
+            .dom
+            .iter_text()
+            .any(|text| text.data().to_string().contains(AT_ROOM));
         mentions_state
     }
 
@@ -60,12 +73,20 @@ where
         suggestion: SuggestionPattern,
         attributes: Vec<(S, S)>,
     ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(
+            ComposerOperation::InsertMentionAtSuggestion {
+                url: url.to_string(),
+                text: text.to_string(),
+                suggestion: suggestion.clone(),
+                attributes: attributes_to_strings(&attributes),
+            },
+        );
         if self.range_contains_link_or_code_leaves() {
             return ComposerUpdate::keep();
         }
 
         if let Ok(mention_node) = DomNode::new_mention(url, text, attributes) {
-            self.push_state_to_history();
+            self.push_state_to_history(HistoryEntryKind::Mention);
             self.do_replace_text_in(
                 S::default(),
                 suggestion.start,
@@ -87,12 +108,17 @@ where
         text: S,
         attributes: Vec<(S, S)>,
     ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::InsertMention {
+            url: url.to_string(),
+            text: text.to_string(),
+            attributes: attributes_to_strings(&attributes),
+        });
         if self.range_contains_link_or_code_leaves() {
             return ComposerUpdate::keep();
         }
 
         if let Ok(mention_node) = DomNode::new_mention(url, text, attributes) {
-            self.push_state_to_history();
+            self.push_state_to_history(HistoryEntryKind::Mention);
             if self.has_selection() {
                 self.do_replace_text(S::default());
             }
@@ -109,11 +135,17 @@ where
         suggestion: SuggestionPattern,
         attributes: Vec<(S, S)>,
     ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(
+            ComposerOperation::InsertAtRoomMentionAtSuggestion {
+                suggestion: suggestion.clone(),
+                attributes: attributes_to_strings(&attributes),
+            },
+        );
         if self.range_contains_link_or_code_leaves() {
             return ComposerUpdate::keep();
         }
 
-        self.push_state_to_history();
+        self.push_state_to_history(HistoryEntryKind::Mention);
         self.do_replace_text_in(S::default(), suggestion.start, suggestion.end);
         self.state.start = Location::from(suggestion.start);
         self.state.end = self.state.start;
@@ -128,11 +160,15 @@ where
         &mut self,
         attributes: Vec<(S, S)>,
     ) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::InsertAtRoomMention {
+                attributes: attributes_to_strings(&attributes),
+            });
         if self.range_contains_link_or_code_leaves() {
             return ComposerUpdate::keep();
         }
 
-        self.push_state_to_history();
+        self.push_state_to_history(HistoryEntryKind::Mention);
         if self.has_selection() {
             self.do_replace_text(S::default());
         }
@@ -141,6 +177,141 @@ where
         self.do_insert_mention(mention_node)
     }
 
+    /// Change the text shown for the mentions of users and rooms between
+    /// start and end, e.g. to show a new display name when a user's profile
+    /// changes while a draft mentioning them is open. Their URIs and
+    /// attributes are kept, and at-room mentions are left as they are.
+    ///
+    /// As this refreshes the content rather than editing it, it does not
+    /// add a step to the undo history.
+    pub fn update_mention_text(
+        &mut self,
+        start: Location,
+        end: Location,
+        text: S,
+    ) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::UpdateMentionText {
+                start: start.into(),
+                end: end.into(),
+                text: text.to_string(),
+            });
+        let (start, end) = (start.min(end), start.max(end));
+        let handles: Vec<DomHandle> = self
+            .state
+            .dom
+            .find_range(start.into(), end.into())
+            .locations
+            .into_iter()
+            .filter(|l| {
+                l.kind == DomNodeKind::Mention && l.start_offset < l.end_offset
+            })
+            .map(|l| l.node_handle)
+            .collect();
+
+        let mut updated = false;
+        for handle in handles {
+            if let DomNode::Mention(mention) =
+                self.state.dom.lookup_node_mut(&handle)
+            {
+                updated |= mention.set_display_text(text.clone());
+            }
+        }
+
+        if updated {
+            self.create_update_replace_all()
+        } else {
+            ComposerUpdate::keep()
+        }
+    }
+
+    /// Turn each of the given texts which appears as a word of the content
+    /// into a mention of the URI it is paired with, e.g. `@alice` or
+    /// `@alice:matrix.org` when restoring a draft saved as plain text.
+    /// Text in links and code is left as it is, and where several of the
+    /// texts start at the same place the longest is used. All the mentions
+    /// are added in one step of the undo history.
+    pub fn convert_plain_mentions(
+        &mut self,
+        mentions: Vec<(S, S)>,
+    ) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::ConvertPlainMentions {
+                mentions: attributes_to_strings(&mentions),
+            });
+        let mut mentions: Vec<(String, S)> = mentions
+            .into_iter()
+            .filter(|(text, url)| {
+                !text.is_empty()
+                    && DomNode::new_mention(url.clone(), text.clone(), vec![])
+                        .is_ok()
+            })
+            .map(|(text, url)| (text.to_string(), url))
+            .collect();
+        mentions.sort_by_key(|(text, _)| Reverse(text.len()));
+        let texts: Vec<&str> =
+            mentions.iter().map(|(t, _)| t.as_str()).collect();
+
+        let dom = &self.state.dom;
+        let mut matches = Vec::new();
+        for location in dom.find_range(0, dom.text_len()).locations {
+            if location.kind != DomNodeKind::Text
+                || self.is_in_link_or_code(&location.node_handle)
+            {
+                continue;
+            }
+            let DomNode::Text(node) = dom.lookup_node(&location.node_handle)
+            else {
+                continue;
+            };
+            let data = node.data().to_string();
+            for (byte_start, byte_end, i) in find_words(&data, &texts) {
+                let start =
+                    location.position + S::from(&data[..byte_start]).len();
+                let end = start + S::from(&data[byte_start..byte_end]).len();
+                matches.push((start, end, i));
+            }
+        }
+        if matches.is_empty() {
+            return ComposerUpdate::keep();
+        }
+        matches.sort();
+
+        self.push_state_to_history(HistoryEntryKind::Mention);
+        for &(start, end, i) in matches.iter().rev() {
+            // Mentions write their text as it is into HTML, so it is escaped
+            // as the bindings do for inserted mentions
+            let (text, url) = &mentions[i];
+            let text = S::from(&*html_escape::encode_safe(text));
+            if let Ok(mention) = DomNode::new_mention(url.clone(), text, vec![])
+            {
+                self.state.dom.replace_text_in(S::default(), start, end);
+                let range = self.state.dom.find_range(start, start);
+                self.state
+                    .dom
+                    .insert_node_at_cursor(&range, DomNode::Mention(mention));
+            }
+        }
+
+        // Each mention takes the place of its text, so the selection moves
+        // back by the length of the text before it, less one for the mention
+        let moved = |location: Location| {
+            let location = usize::from(location);
+            let mut new_location = location;
+            for &(start, end, _) in &matches {
+                if end <= location {
+                    new_location -= end - start - 1;
+                } else if start < location {
+                    new_location -= location - start - 1;
+                }
+            }
+            Location::from(new_location)
+        };
+        self.state.start = moved(self.state.start);
+        self.state.end = moved(self.state.end);
+        self.create_update_replace_all()
+    }
+
     /// Inserts the node at the cursor position. It adds a trailing space when the inserted
     /// mention is the last node in it's parent.
     fn do_insert_mention(
@@ -169,6 +340,20 @@ where
         }
     }
 
+    /// Whether the node is inside a link or code, where text is not turned
+    /// into mentions.
+    fn is_in_link_or_code(&self, handle: &DomHandle) -> bool {
+        let mut handle = handle.clone();
+        while handle.has_parent() {
+            handle = handle.parent_handle();
+            let kind = self.state.dom.lookup_node(&handle).kind();
+            if kind.is_link_kind() || kind.is_code_kind() {
+                return true;
+            }
+        }
+        false
+    }
+
     /// We should not insert a mention if the uri is invalid or the range contains link
     /// or code leaves. See issue https://github.com/matrix-org/matrix-rich-text-editor/issues/702.
     fn range_contains_link_or_code_leaves(&self) -> bool {
@@ -180,3 +365,30 @@ where
         })
     }
 }
+
+/// The byte ranges of the places in `text` where one of `words` appears
+/// with no letters, digits or underscores either side of it, and the index
+/// of the word found there. Where several of the words match at the same
+/// place the first is used, so longer words should come first.
+fn find_words(text: &str, words: &[&str]) -> Vec<(usize, usize, usize)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut found = Vec::new();
+    let mut next_start = 0;
+    for (start, _) in text.char_indices() {
+        if start < next_start
+            || text[..start].chars().next_back().is_some_and(is_word_char)
+        {
+            continue;
+        }
+        let rest = &text[start..];
+        let matched = words.iter().position(|word| {
+            rest.starts_with(word)
+                && !rest[word.len()..].chars().next().is_some_and(is_word_char)
+        });
+        if let Some(i) = matched {
+            next_start = start + words[i].len();
+            found.push((start, next_start, i));
+        }
+    }
+    found
+}
@@ -4,20 +4,65 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+use url::Url;
 
 use crate::{
     dom::{
-        unicode_string::{UnicodeStr, UnicodeStringExt},
+        unicode_string::{UnicodeStr, UnicodeStrExt, UnicodeStringExt},
         Range,
     },
-    ComposerModel, MenuAction, PatternKey, SuggestionPattern, UnicodeString,
+    ComposerModel, ComposerOperation, ComposerUpdate, Location, MenuAction,
+    PatternKey, SuggestionPattern, UnicodeString,
 };
 
+/// Punctuation which ends a sentence or clause, and so isn't part of a
+/// suggestion or URL it comes right after.
+const TRAILING_PUNCTUATION: [char; 7] = ['.', ',', ';', ':', '!', '?', ')'];
+
 impl<S> ComposerModel<S>
 where
     S: UnicodeString,
 {
+    /// Mark the text between `start` and `end` as being composed by an
+    /// input method, until [Self::clear_composing_region] is called. No
+    /// suggestion is reported for text overlapping it, as the text isn't
+    /// final yet, e.g. the candidates shown while typing Japanese. The
+    /// region is not moved when the content changes, so clients should
+    /// set it again whenever the composition changes.
+    pub fn set_composing_region(
+        &mut self,
+        start: Location,
+        end: Location,
+    ) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::SetComposingRegion {
+                start: start.into(),
+                end: end.into(),
+            });
+        self.composing_region = Some((start.min(end), start.max(end)));
+        self.create_update_update_selection()
+    }
+
+    /// Finish composing, so that suggestions are reported for the text
+    /// which was being composed.
+    pub fn clear_composing_region(&mut self) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::ClearComposingRegion);
+        if self.composing_region.take().is_none() {
+            return ComposerUpdate::keep();
+        }
+        self.create_update_update_selection()
+    }
+
+    /// Whether any of the text between `start` and `end` is being composed.
+    fn is_composing_in(&self, start: usize, end: usize) -> bool {
+        self.composing_region.is_some_and(|(s, e)| {
+            usize::from(s) < end && start < usize::from(e)
+        })
+    }
+
     /// Compute the menu action for current composer model state.
     pub(crate) fn compute_menu_action(&self) -> MenuAction {
         let (s, e) = self.safe_selection();
@@ -31,18 +76,24 @@ where
             return MenuAction::None;
         }
         let (raw_text, start, end) = self.extended_text(range);
+        let (raw_text, end) = Self::without_punctuation_after(raw_text, end, e);
 
         if let Some((key, text)) = Self::pattern_for_text(
-            raw_text,
+            raw_text.clone(),
             start,
             &self.custom_suggestion_patterns,
-        ) {
+            &self.config.suggestion_triggers,
+        )
+        .filter(|_| !self.is_composing_in(start, end))
+        {
             MenuAction::Suggestion(SuggestionPattern {
                 key,
                 text,
                 start,
                 end,
             })
+        } else if let Some((url, end)) = Self::url_for_text(&raw_text, start) {
+            MenuAction::UrlDetected { url, start, end }
         } else {
             MenuAction::None
         }
@@ -73,19 +124,62 @@ where
             )
     }
 
+    /// Leave out the punctuation at the end of the text the cursor is in
+    /// when it comes after the cursor, e.g. the comma in `@al|, bye`, as it
+    /// isn't part of what is being typed. Returns the text and the location
+    /// where it now ends.
+    fn without_punctuation_after(
+        mut text: S,
+        mut end: usize,
+        cursor: usize,
+    ) -> (S, usize) {
+        while end > cursor {
+            match text.pop_last() {
+                Some(c) if TRAILING_PUNCTUATION.contains(&c) => {
+                    end -= text.char_len(&c);
+                }
+                Some(c) => {
+                    text.push(c);
+                    break;
+                }
+                None => break,
+            }
+        }
+        (text, end)
+    }
+
+    /// The http(s) URL the text is made of, if any, leaving out punctuation
+    /// after it such as the full stop ending a sentence. Returns the URL and
+    /// the location where it ends.
+    fn url_for_text(
+        text: &S,
+        start_location: usize,
+    ) -> Option<(String, usize)> {
+        let text = text.to_string();
+        let url = text.trim_end_matches(TRAILING_PUNCTUATION);
+        let parsed = Url::parse(url).ok()?;
+        if !matches!(parsed.scheme(), "http" | "https") || !parsed.has_host() {
+            return None;
+        }
+        let end = start_location + S::from(url).len();
+        Some((url.to_owned(), end))
+    }
+
     /// Compute at/hash/slash pattern for a given text.
     /// Return pattern key and associated text, if it exists.
     fn pattern_for_text(
         mut text: S,
         start_location: usize,
         custom_suggestion_patterns: &HashSet<String>,
+        suggestion_triggers: &HashMap<char, PatternKey>,
     ) -> Option<(PatternKey, String)> {
-        let key = PatternKey::from_string_and_suggestions(
+        let (key, is_triggered) = PatternKey::from_string_and_suggestions(
             text.to_string(),
             custom_suggestion_patterns,
+            suggestion_triggers,
         )?;
 
-        if key.is_static_pattern() {
+        if is_triggered {
             text.pop_first();
         }
 
@@ -26,6 +26,27 @@ pub(crate) enum MenuStateComputeType {
     KeepIfUnchanged,
 }
 
+/// The actions reversed by each node the selection has been in and its
+/// ancestors, kept between menu state computations until the Dom changes,
+/// so that moving the selection within deeply nested content doesn't walk
+/// all its ancestors again.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ReversedActionsCache {
+    /// The [Dom::generation](crate::dom::Dom) these were worked out for
+    generation: u64,
+    by_handle: HashMap<DomHandle, HashSet<ComposerAction>>,
+}
+
+impl ReversedActionsCache {
+    /// Forget everything worked out for other generations of the Dom.
+    fn invalidate_unless(&mut self, generation: u64) {
+        if self.generation != generation {
+            self.generation = generation;
+            self.by_handle.clear();
+        }
+    }
+}
+
 impl<S> ComposerModel<S>
 where
     S: UnicodeString,
@@ -37,26 +58,37 @@ where
         let (s, e) = self.safe_selection();
         let range = self.state.dom.find_range(s, e);
 
-        let action_states = self.compute_action_states(&range);
+        let mut cache = std::mem::take(&mut self.reversed_actions_cache);
+        cache.invalidate_unless(self.state.dom.generation());
+        let action_states = self.compute_action_states(&range, &mut cache);
+        self.reversed_actions_cache = cache;
 
         if action_states == self.action_states
             && matches!(compute_type, MenuStateComputeType::KeepIfUnchanged)
         {
             MenuState::Keep
         } else {
-            self.action_states = action_states.clone();
-            MenuState::Update(MenuStateUpdate { action_states })
+            let update = if self.config.menu_state_deltas() {
+                MenuStateUpdate::between(&self.action_states, &action_states)
+            } else {
+                MenuStateUpdate {
+                    action_states: action_states.clone(),
+                }
+            };
+            self.action_states = action_states;
+            MenuState::Update(update)
         }
     }
 
     fn compute_action_states(
         &self,
         range: &Range,
+        cache: &mut ReversedActionsCache,
     ) -> HashMap<ComposerAction, ActionState> {
         let mut action_states = HashMap::new();
 
-        let reversed = self.compute_reversed_actions_from_range(range);
-        let disabled = self.compute_disabled_actions();
+        let reversed = self.reversed_actions_from_range(range, cache);
+        let disabled = self.compute_disabled_actions(range);
 
         for action in ComposerAction::iter() {
             let state = if disabled.contains(&action) {
@@ -72,9 +104,19 @@ where
         action_states
     }
 
-    fn compute_reversed_actions_from_range(
+    pub(crate) fn compute_reversed_actions_from_range(
+        &self,
+        range: &Range,
+    ) -> HashSet<ComposerAction> {
+        let mut cache = ReversedActionsCache::default();
+        cache.invalidate_unless(self.state.dom.generation());
+        self.reversed_actions_from_range(range, &mut cache)
+    }
+
+    fn reversed_actions_from_range(
         &self,
         range: &Range,
+        cache: &mut ReversedActionsCache,
     ) -> HashSet<ComposerAction> {
         let toggled_format_actions = self
             .state
@@ -84,24 +126,26 @@ where
             .collect();
 
         let reversed_actions = if let Some(first_leaf) = range.leaves().next() {
+            // Init with reversed_actions from the first leave.
+            let first =
+                self.compute_reversed_actions(&first_leaf.node_handle, cache);
             range
                 .leaves()
                 // do not need locations after the cursor for next logic
                 .filter(|loc| !(loc.relative_position() == After))
-                .map(|loc| self.compute_reversed_actions(&loc.node_handle))
-                .fold(
-                    // Init with reversed_actions from the first leave.
-                    self.compute_reversed_actions(&first_leaf.node_handle),
-                    // And intersect with the reversed_actions of all subsequent leaves.
-                    |i, set| i.intersection(&set).cloned().collect(),
-                )
+                // And intersect with the reversed_actions of all subsequent leaves.
+                .fold(first, |i, loc| {
+                    let set =
+                        self.compute_reversed_actions(&loc.node_handle, cache);
+                    i.intersection(&set).cloned().collect()
+                })
         } else if self.state.dom.document().children().is_empty() {
             HashSet::new()
         } else if let Some(container_loc) = range.deepest_container_node(None) {
             if container_loc.node_handle.is_root() {
                 HashSet::new()
             } else {
-                self.compute_reversed_actions(&container_loc.node_handle)
+                self.compute_reversed_actions(&container_loc.node_handle, cache)
             }
         } else {
             HashSet::new()
@@ -113,30 +157,32 @@ where
             .collect()
     }
 
+    /// The actions reversed by the node at `handle` and its ancestors,
+    /// worked out from those of its parent.
     fn compute_reversed_actions(
         &self,
         handle: &DomHandle,
+        cache: &mut ReversedActionsCache,
     ) -> HashSet<ComposerAction> {
-        fn has_list_in(set: &HashSet<ComposerAction>) -> bool {
-            set.contains(&OrderedList) || set.contains(&UnorderedList)
+        if let Some(set) = cache.by_handle.get(handle) {
+            return set.clone();
         }
-
-        handle.with_ancestors().iter().rev().fold(
-            HashSet::new(),
-            |mut set, handle| {
-                if let Some(action) = self.reversed_action_for_handle(handle) {
-                    match action {
-                        // If there is multiple list types in the hierarchy we
-                        // only keep the deepest list type.
-                        OrderedList | UnorderedList if has_list_in(&set) => {}
-                        _ => {
-                            set.insert(action);
-                        }
-                    }
-                }
-                set
-            },
-        )
+        let mut set = if handle.is_root() {
+            HashSet::new()
+        } else {
+            self.compute_reversed_actions(&handle.parent_handle(), cache)
+        };
+        if let Some(action) = self.reversed_action_for_handle(handle) {
+            // If there is multiple list types in the hierarchy we only keep
+            // the deepest list type.
+            if matches!(action, OrderedList | UnorderedList) {
+                set.remove(&OrderedList);
+                set.remove(&UnorderedList);
+            }
+            set.insert(action);
+        }
+        cache.by_handle.insert(handle.clone(), set.clone());
+        set
     }
 
     fn reversed_action_for_handle(
@@ -177,7 +223,10 @@ where
         }
     }
 
-    fn compute_disabled_actions(&self) -> HashSet<ComposerAction> {
+    fn compute_disabled_actions(
+        &self,
+        range: &Range,
+    ) -> HashSet<ComposerAction> {
         let mut disabled_actions = HashSet::new();
         if self.previous_states.is_empty() {
             disabled_actions.insert(ComposerAction::Undo);
@@ -186,11 +235,10 @@ where
             disabled_actions.insert(ComposerAction::Redo);
         }
 
-        let (s, e) = self.safe_selection();
-        let range = self.state.dom.find_range(s, e);
         disabled_actions.extend(
             self.compute_disabled_actions_for_locations(&range.locations),
         );
+        disabled_actions.extend(self.config.disallowed_actions.iter().cloned());
         disabled_actions
     }
 
@@ -0,0 +1,172 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, DomHandle,
+    HistoryEntryKind, Location, UnicodeString,
+};
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// Move the top-level block or list item at the handle, with everything
+    /// inside it, so that it becomes child `target_index` of its parent,
+    /// e.g. to reorder the items of a list by dragging them. Lists left
+    /// next to another list of the same type are joined, and the selection
+    /// stays on the same content. Does nothing if the handle isn't a
+    /// top-level block or list item, or the index is out of range.
+    pub fn move_block(
+        &mut self,
+        handle: &DomHandle,
+        target_index: usize,
+    ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::MoveBlock {
+            handle: handle.raw().clone(),
+            target_index,
+        });
+        if !self.can_move_block(handle, target_index) {
+            return ComposerUpdate::keep();
+        }
+        self.push_state_to_history(HistoryEntryKind::Move);
+
+        let old_location = self.state.dom.location_for_node(handle);
+        let node = self.state.dom.remove(handle);
+        let parent_handle = handle.parent_handle();
+        let new_handle = parent_handle.child_handle(target_index);
+        self.state.dom.insert_at(&new_handle, node);
+        let new_position =
+            self.state.dom.location_for_node(&new_handle).position;
+
+        // Offsets inside the block move with it, the others shift to make
+        // room for it
+        let (start, end, length) = (
+            old_location.position,
+            old_location.position + old_location.length,
+            old_location.length,
+        );
+        let move_offset = |offset: usize| {
+            if (start..end).contains(&offset) {
+                offset - start + new_position
+            } else {
+                let offset = if offset >= end {
+                    offset - length
+                } else {
+                    offset
+                };
+                if offset >= new_position {
+                    offset + length
+                } else {
+                    offset
+                }
+            }
+        };
+        self.state.start = Location::from(move_offset(self.state.start.into()));
+        self.state.end = Location::from(move_offset(self.state.end.into()));
+
+        if parent_handle.is_root() {
+            self.join_adjacent_lists();
+        }
+        self.create_update_replace_all()
+    }
+
+    /// True if the handle is of a list item, or a block at the top level,
+    /// which isn't already at the target index.
+    fn can_move_block(&self, handle: &DomHandle, target_index: usize) -> bool {
+        let dom = &self.state.dom;
+        if handle.is_root() || !dom.contains(handle) {
+            return false;
+        }
+        let node = dom.lookup_node(handle);
+        let parent_handle = handle.parent_handle();
+        let is_movable = node.is_list_item()
+            || (parent_handle.is_root() && node.is_block_node());
+        is_movable
+            && target_index != handle.index_in_parent()
+            && target_index
+                < dom.lookup_container(&parent_handle).children().len()
+    }
+
+    /// Join each pair of lists of the same type next to each other at the
+    /// top level.
+    fn join_adjacent_lists(&mut self) {
+        let root = DomHandle::root();
+        let count = self.state.dom.document().children().len();
+        for i in (1..count).rev() {
+            let handle = root.child_handle(i - 1);
+            let next_handle = root.child_handle(i);
+            let node = self.state.dom.lookup_node(&handle);
+            let next = self.state.dom.lookup_node(&next_handle);
+            if node.is_list() && node.can_push(next) {
+                let mut next = self.state.dom.remove(&next_handle);
+                self.state.dom.lookup_node_mut(&handle).push(&mut next);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::DomHandle;
+
+    fn handle(raw: &[usize]) -> DomHandle {
+        DomHandle::from_raw(raw.to_vec())
+    }
+
+    #[test]
+    fn top_level_blocks_are_moved() {
+        let mut model = cm("<p>a</p><p>b</p><p>c|</p>");
+        model.move_block(&handle(&[2]), 0);
+        assert_eq!(tx(&model), "<p>c|</p><p>a</p><p>b</p>");
+        model.move_block(&handle(&[0]), 2);
+        assert_eq!(tx(&model), "<p>a</p><p>b</p><p>c|</p>");
+    }
+
+    #[test]
+    fn the_selection_stays_on_the_same_content() {
+        let mut model = cm("<p>ab</p><p>c{d}|</p><p>ef</p>");
+        model.move_block(&handle(&[0]), 2);
+        assert_eq!(tx(&model), "<p>c{d}|</p><p>ef</p><p>ab</p>");
+        model.move_block(&handle(&[0]), 1);
+        assert_eq!(tx(&model), "<p>ef</p><p>c{d}|</p><p>ab</p>");
+    }
+
+    #[test]
+    fn list_items_are_moved_with_their_nested_lists() {
+        let mut model =
+            cm("<ol><li><p>a</p><ul><li>b</li></ul></li><li>c|</li></ol>");
+        model.move_block(&handle(&[0, 0]), 1);
+        assert_eq!(
+            tx(&model),
+            "<ol><li>c|</li><li><p>a</p><ul><li>b</li></ul></li></ol>"
+        );
+    }
+
+    #[test]
+    fn lists_of_the_same_type_are_joined() {
+        let mut model = cm("<ul><li>a</li></ul><p>b|</p><ul><li>c</li></ul>");
+        model.move_block(&handle(&[1]), 0);
+        assert_eq!(tx(&model), "<p>b|</p><ul><li>a</li><li>c</li></ul>");
+    }
+
+    #[test]
+    fn a_move_is_undone_in_one_step() {
+        let mut model = cm("<p>a</p><p>b|</p>");
+        model.move_block(&handle(&[1]), 0);
+        model.undo();
+        assert_eq!(tx(&model), "<p>a</p><p>b|</p>");
+    }
+
+    #[test]
+    fn only_blocks_and_list_items_can_be_moved() {
+        let mut model = cm("<p>a<b>b|</b></p><p>c</p>");
+        model.move_block(&handle(&[0, 1]), 0);
+        model.move_block(&handle(&[0]), 2);
+        model.move_block(&handle(&[3]), 0);
+        assert_eq!(tx(&model), "<p>a<b>b|</b></p><p>c</p>");
+        assert!(model.history().is_empty());
+    }
+}
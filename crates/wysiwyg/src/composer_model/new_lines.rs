@@ -5,10 +5,14 @@
 
 use crate::dom::nodes::dom_node::DomNodeKind;
 use crate::dom::nodes::dom_node::DomNodeKind::{
-    Generic, Link, List, ListItem, Paragraph,
+    Formatting, Generic, Link, List, ListItem, Paragraph,
 };
 use crate::dom::{Dom, DomLocation};
-use crate::{ComposerModel, ComposerUpdate, DomNode, UnicodeString};
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, DomNode,
+    HistoryEntryKind, InlineCodeExitRule, InlineFormatType, ListExitRule,
+    UnicodeString,
+};
 
 impl<S> ComposerModel<S>
 where
@@ -16,7 +20,8 @@ where
 {
     /// Adds a new line break by creating a paragraph.
     pub fn enter(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::Enter);
+        self.push_state_to_history(HistoryEntryKind::NewLine);
         self.do_enter()
     }
 
@@ -33,7 +38,7 @@ where
                 DomNode::new_paragraph(Vec::new()),
             ]);
             self.state.advance_selection();
-            return self.create_update_replace_all();
+            return self.create_update_after_enter();
         }
 
         // If the selection covered several characters, remove them first
@@ -73,7 +78,7 @@ where
             } else {
                 self.state.dom.insert_at(&block_handle, paragraph);
             }
-            return self.create_update_replace_all();
+            return self.create_update_after_enter();
         }
 
         let first_leaf = range.leaves().next();
@@ -105,7 +110,9 @@ where
                     .dom
                     .lookup_node(&block_location.node_handle)
                     .has_no_text();
-                if list_item_has_no_text {
+                if list_item_has_no_text
+                    && self.config.list_exit_rule == ListExitRule::OnEmptyItem
+                {
                     let list_handle =
                         block_location.node_handle.parent_handle();
                     // Remove the current list item
@@ -161,6 +168,11 @@ where
                         &block_location.node_handle,
                         DomNode::new_list_item(Vec::new()),
                     );
+                    // An empty item only stays in the list if the list is
+                    // never left, then the cursor moves to the new item.
+                    if list_item_has_no_text {
+                        self.state.advance_selection();
+                    }
                 } else {
                     let first_leaf = first_leaf.unwrap();
                     let mut sub_tree = self.state.dom.split_sub_tree_from(
@@ -168,7 +180,10 @@ where
                         first_leaf.start_offset,
                         block_location.node_handle.depth(),
                     );
-                    pre_process_sub_tree(&mut sub_tree);
+                    pre_process_sub_tree(
+                        &mut sub_tree,
+                        self.config.inline_code_exit_rule,
+                    );
                     let children = sub_tree.document_mut().remove_children();
                     self.state.dom.insert_at(
                         &block_location.node_handle.next_sibling(),
@@ -185,9 +200,33 @@ where
                 block_location.kind
             ),
         }
+        self.create_update_after_enter()
+    }
+
+    fn create_update_after_enter(&mut self) -> ComposerUpdate<S> {
+        if self.config.inline_code_exit_rule == InlineCodeExitRule::OnEnter {
+            self.exit_inline_code();
+        }
         self.create_update_replace_all()
     }
 
+    /// Make the text typed at the cursor plain rather than inline code, by
+    /// toggling inline code off if the cursor is inside it.
+    fn exit_inline_code(&mut self) {
+        let inline_code = InlineFormatType::InlineCode;
+        self.state
+            .toggled_format_types
+            .retain(|f| *f != inline_code);
+        let (s, e) = self.safe_selection();
+        let range = self.state.dom.find_range(s, e);
+        if self
+            .compute_reversed_actions_from_range(&range)
+            .contains(&inline_code.action())
+        {
+            self.state.toggled_format_types.push(inline_code);
+        }
+    }
+
     fn do_new_line_in_paragraph(
         &mut self,
         first_leaf: Option<&DomLocation>,
@@ -215,7 +254,10 @@ where
                 usize::MAX,
                 block_node_handle.depth(),
             );
-            pre_process_sub_tree(&mut sub_tree);
+            pre_process_sub_tree(
+                &mut sub_tree,
+                self.config.inline_code_exit_rule,
+            );
             let sub_tree_container = sub_tree.document_mut();
 
             let cur_block_node_was_removed =
@@ -290,7 +332,7 @@ where
             0,
             ancestor_block_location.node_handle.depth(),
         );
-        pre_process_sub_tree(&mut sub_tree);
+        pre_process_sub_tree(&mut sub_tree, self.config.inline_code_exit_rule);
         let sub_tree_container = &sub_tree.document();
 
         let block_node_was_removed = !self.state.dom.contains(block_handle);
@@ -323,11 +365,20 @@ where
     }
 }
 
-fn pre_process_sub_tree<S: UnicodeString>(sub_tree: &mut Dom<S>) {
+fn pre_process_sub_tree<S: UnicodeString>(
+    sub_tree: &mut Dom<S>,
+    inline_code_exit_rule: InlineCodeExitRule,
+) {
     // Links are a special case, if they we split them at their last index, they'll generate empty
     // link nodes in the sub_tree, but we don't want that as links shouldn't grow when we add text
     // to its last index
     sub_tree.remove_nodes_matching(&|n| n.kind() == Link && n.is_empty());
+    // The same goes for inline code, unless the new line should stay in it
+    if inline_code_exit_rule == InlineCodeExitRule::OnEnter {
+        sub_tree.remove_nodes_matching(&|n| {
+            n.kind() == Formatting(InlineFormatType::InlineCode) && n.is_empty()
+        });
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,557 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::composer_model::snapshots::Snapshot;
+use crate::instrumentation::{enter_span, EnteredSpan};
+use crate::update_timing::Stopwatch;
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, DomCreationError,
+    DomHandle, Location, UnicodeString,
+};
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// Every operation which changed the content or selection of this
+    /// model, in the order they were made. Applying them to a new model
+    /// with [Self::apply_operations] recreates this one, e.g. to recover a
+    /// draft after a crash or to reproduce a bug.
+    ///
+    /// Operations are only logged if
+    /// [crate::ComposerModelConfig::with_record_operations] is set. Setting
+    /// the content from HTML or Markdown starts the log again from that
+    /// operation. Snapshots taken before then can't be restored by
+    /// replaying the log.
+    pub fn operation_log(&self) -> &[ComposerOperation] {
+        &self.operation_log
+    }
+
+    /// Empty the operation log, e.g. once it has been saved. The operations
+    /// logged after this only recreate this model when applied to a model
+    /// which has its current content.
+    pub fn clear_operation_log(&mut self) {
+        self.operation_log.clear();
+    }
+
+    /// Apply each of the operations in turn, as if the corresponding
+    /// methods had been called. Stops at the first one which fails.
+    pub fn apply_operations(
+        &mut self,
+        operations: Vec<ComposerOperation>,
+    ) -> Result<ComposerUpdate<S>, DomCreationError> {
+        // The update reports the changes of all the operations together
+        let baseline = self.update_baseline();
+        for operation in operations {
+            self.apply_operation(operation)?;
+        }
+        let mut update = self.create_update_replace_all_with_menu_state();
+        self.report_changes_since(&baseline, &mut update);
+        Ok(update)
+    }
+
+    /// Apply the operations as one change, e.g. to insert a template for a
+    /// slash command. Undo reverts the whole sequence in a single step. If
+    /// any of the operations fails, the content, selection and undo history
+    /// are put back as they were before the sequence started.
+    pub fn run_command_sequence(
+        &mut self,
+        operations: Vec<ComposerOperation>,
+    ) -> Result<ComposerUpdate<S>, DomCreationError> {
+        let _span = self.enter_operation_span("run_command_sequence");
+        let before = Snapshot::of(self);
+        // The client only sees the update for the whole sequence, so it is
+        // compared with the content from before the sequence
+        let baseline = self.update_baseline();
+        // Log the sequence as a whole, so replaying it is also one step
+        let log = std::mem::take(&mut self.operation_log);
+        let result = operations.iter().cloned().try_for_each(|operation| {
+            self.apply_operation(operation).map(drop)
+        });
+        self.operation_log = log;
+        match result {
+            Ok(()) => {
+                self.merge_history_since(before);
+                self.log_operation(ComposerOperation::RunCommandSequence {
+                    operations,
+                });
+                let mut update =
+                    self.create_update_replace_all_with_menu_state();
+                self.report_changes_since(&baseline, &mut update);
+                Ok(update)
+            }
+            Err(e) => {
+                before.restore(self);
+                self.restore_update_baseline(baseline);
+                Err(e)
+            }
+        }
+    }
+
+    pub(crate) fn apply_operation(
+        &mut self,
+        operation: ComposerOperation,
+    ) -> Result<ComposerUpdate<S>, DomCreationError> {
+        use ComposerOperation::*;
+
+        let update = match operation {
+            SetContentFromHtml { html } => {
+                return self.set_content_from_html(&S::from(html))
+            }
+            SetContentFromMarkdown { markdown } => {
+                return self.set_content_from_markdown(&S::from(markdown))
+            }
+            Clear => self.clear(),
+            Select { start, end } => {
+                self.select(Location::from(start), Location::from(end))
+            }
+            ExtendSelection {
+                direction,
+                granularity,
+            } => self.extend_selection(direction, granularity),
+            SetComposingRegion { start, end } => self.set_composing_region(
+                Location::from(start),
+                Location::from(end),
+            ),
+            ClearComposingRegion => self.clear_composing_region(),
+            ReplaceText { text } => self.replace_text(S::from(text)),
+            PasteText { text } => self.paste_text(S::from(text)),
+            ReplaceTextIn { text, start, end } => {
+                self.replace_text_in(S::from(text), start, end)
+            }
+            ReplaceTextSuggestion {
+                text,
+                suggestion,
+                append_space,
+            } => self.replace_text_suggestion(
+                S::from(text),
+                suggestion,
+                append_space,
+            ),
+            ReplaceHtml { html, source } => {
+                self.replace_html(S::from(html), source)
+            }
+            Backspace => self.backspace(),
+            Delete => self.delete(),
+            DeleteIn { start, end } => self.delete_in(start, end),
+            TruncateTo { length } => self.truncate_to(length),
+            BackspaceWord => self.backspace_word(),
+            DeleteWord => self.delete_word(),
+            Enter => self.enter(),
+            #[allow(deprecated)]
+            AddLineBreak => self.add_line_break(),
+            Bold => self.bold(),
+            Italic => self.italic(),
+            StrikeThrough => self.strike_through(),
+            Underline => self.underline(),
+            InlineCode => self.inline_code(),
+            ApplyFormats { formats, enabled } => {
+                self.apply_formats(&formats, enabled)
+            }
+            OrderedList => self.ordered_list(),
+            UnorderedList => self.unordered_list(),
+            Indent => self.indent(),
+            Unindent => self.unindent(),
+            SplitListAtCursor => self.split_list_at_cursor(),
+            JoinWithPreviousList => self.join_with_previous_list(),
+            CodeBlock => self.code_block(),
+            Quote => self.quote(),
+            MoveBlock {
+                handle,
+                target_index,
+            } => self.move_block(&DomHandle::from_raw(handle), target_index),
+            SetLink { url, attributes } => {
+                self.set_link(S::from(url), to_attributes(attributes))
+            }
+            SetLinkWithText {
+                url,
+                text,
+                attributes,
+            } => self.set_link_with_text(
+                S::from(url),
+                S::from(text),
+                to_attributes(attributes),
+            ),
+            RemoveLinks => self.remove_links(),
+            RemoveLinksIn { start, end } => {
+                self.remove_links_in(Location::from(start), Location::from(end))
+            }
+            RemoveLinkAt { offset } => {
+                self.remove_link_at(Location::from(offset))
+            }
+            InsertMention {
+                url,
+                text,
+                attributes,
+            } => self.insert_mention(
+                S::from(url),
+                S::from(text),
+                to_attributes(attributes),
+            ),
+            InsertMentionAtSuggestion {
+                url,
+                text,
+                suggestion,
+                attributes,
+            } => self.insert_mention_at_suggestion(
+                S::from(url),
+                S::from(text),
+                suggestion,
+                to_attributes(attributes),
+            ),
+            InsertAtRoomMention { attributes } => {
+                self.insert_at_room_mention(to_attributes(attributes))
+            }
+            InsertAtRoomMentionAtSuggestion {
+                suggestion,
+                attributes,
+            } => self.insert_at_room_mention_at_suggestion(
+                suggestion,
+                to_attributes(attributes),
+            ),
+            UpdateMentionText { start, end, text } => self.update_mention_text(
+                Location::from(start),
+                Location::from(end),
+                S::from(text),
+            ),
+            ConvertPlainMentions { mentions } => {
+                self.convert_plain_mentions(to_attributes(mentions))
+            }
+            Undo { steps } => self.undo_steps(steps),
+            Redo { steps } => self.redo_steps(steps),
+            CreateSnapshot => {
+                self.create_snapshot();
+                ComposerUpdate::keep()
+            }
+            RestoreSnapshot { id } => self.restore_snapshot(id),
+            DiscardSnapshot { id } => {
+                self.discard_snapshot(id);
+                ComposerUpdate::keep()
+            }
+            ResetToLastGood => self.reset_to_last_good(),
+            InsertTemplate { html } => self.insert_template(S::from(html)),
+            NextPlaceholder => self.next_placeholder(),
+            FillPlaceholder { text } => self.fill_placeholder(S::from(text)),
+            RunCommandSequence { operations } => {
+                return self.run_command_sequence(operations)
+            }
+        };
+        Ok(update)
+    }
+
+    /// Log an operation which is about to be made, and enter a span which
+    /// lasts until the returned guard is dropped at the end of it.
+    pub(crate) fn start_operation(
+        &mut self,
+        operation: ComposerOperation,
+    ) -> EnteredSpan {
+        let span = self.enter_operation_span((&operation).into());
+        self.log_operation(operation);
+        span
+    }
+
+    /// Enter a span for an operation named as in [ComposerOperation], for
+    /// operations which are only logged once they have succeeded.
+    pub(crate) fn enter_operation_span(
+        &mut self,
+        #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+        name: &'static str,
+    ) -> EnteredSpan {
+        self.operation_stopwatch = Stopwatch::start();
+        self.operation_start = None;
+        enter_span!(
+            "composer_operation",
+            operation = name,
+            text_len = self.state.dom.text_len(),
+        )
+    }
+
+    pub(crate) fn log_operation(&mut self, operation: ComposerOperation) {
+        if self.config.record_operations {
+            self.operation_log.push(operation);
+        }
+    }
+
+    /// Start the log again from an operation which replaced the whole
+    /// content, as replaying it doesn't need anything logged before.
+    pub(crate) fn restart_operation_log(
+        &mut self,
+        operation: ComposerOperation,
+    ) {
+        self.operation_log.clear();
+        self.log_operation(operation);
+    }
+}
+
+fn to_attributes<S>(attributes: Vec<(String, String)>) -> Vec<(S, S)>
+where
+    S: UnicodeString,
+{
+    attributes
+        .into_iter()
+        .map(|(name, value)| (S::from(name), S::from(value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::{
+        ComposerModel, ComposerModelConfig, ComposerOperation, DecorationKind,
+        Location,
+    };
+
+    fn recording(text: &str) -> ComposerModel<Utf16String> {
+        let mut model = cm(text);
+        model.set_record_operations(true);
+        model
+    }
+
+    fn replay(
+        model: &ComposerModel<Utf16String>,
+    ) -> ComposerModel<Utf16String> {
+        let mut replayed = ComposerModel::with_config(
+            ComposerModelConfig::default().with_record_operations(true),
+        );
+        replayed
+            .apply_operations(model.operation_log().to_vec())
+            .unwrap();
+        replayed
+    }
+
+    #[test]
+    fn each_operation_is_logged_once() {
+        let mut model = recording("|");
+        model.replace_text(Utf16String::from("abc"));
+        model.select(Location::from(0), Location::from(3));
+        model.bold();
+        model.backspace_word();
+        model.undo();
+        assert_eq!(
+            model.operation_log(),
+            &[
+                ComposerOperation::ReplaceText { text: "abc".into() },
+                ComposerOperation::Select { start: 0, end: 3 },
+                ComposerOperation::Bold,
+                ComposerOperation::BackspaceWord,
+                ComposerOperation::Undo { steps: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn replaying_the_log_recreates_the_model() {
+        let mut model = recording("|");
+        model.set_content_from_html(&"<p>one</p>".into()).unwrap();
+        model.enter();
+        model.replace_text(Utf16String::from("two "));
+        model.ordered_list();
+        model.insert_mention(
+            "https://matrix.to/#/@alice:matrix.org".into(),
+            "Alice".into(),
+            vec![],
+        );
+        model.select(Location::from(0), Location::from(3));
+        model.italic();
+        model.set_link("https://example.org".into(), vec![]);
+        model.undo();
+        model.redo();
+
+        let replayed = replay(&model);
+        assert_eq!(tx(&replayed), tx(&model));
+        assert_eq!(replayed.operation_log(), model.operation_log());
+        assert_eq!(replayed.history().len(), model.history().len());
+    }
+
+    #[test]
+    fn applying_operations_reports_their_changes_as_one() {
+        let mut model = cm("|");
+        model.insert_mention(
+            "https://matrix.to/#/@alice:matrix.org".into(),
+            "Alice".into(),
+            vec![],
+        );
+        let update = model
+            .apply_operations(vec![
+                ComposerOperation::Backspace,
+                ComposerOperation::Backspace,
+                ComposerOperation::ReplaceText { text: "ab".into() },
+            ])
+            .unwrap();
+        assert_eq!(tx(&model), "ab|");
+        assert!(!update.offset_mapping.is_identity());
+        assert_eq!(update.deleted_mentions.len(), 1);
+    }
+
+    #[test]
+    fn replaying_restores_snapshots() {
+        let mut model = recording("|");
+        model.replace_text(Utf16String::from("abc"));
+        let snapshot = model.create_snapshot();
+        model.replace_text(Utf16String::from("def"));
+        model.restore_snapshot(snapshot);
+        assert_eq!(tx(&replay(&model)), "abc|");
+    }
+
+    #[test]
+    fn a_command_sequence_is_undone_in_one_step() {
+        let mut model = cm("abc|");
+        model
+            .run_command_sequence(vec![
+                ComposerOperation::Enter,
+                ComposerOperation::CodeBlock,
+                ComposerOperation::ReplaceText {
+                    text: "fn main() {}".into(),
+                },
+            ])
+            .unwrap();
+        assert_eq!(
+            tx(&model),
+            "<p>abc</p><pre><code>fn main() {}|</code></pre>"
+        );
+        assert_eq!(model.history().len(), 1);
+
+        model.undo();
+        assert_eq!(tx(&model), "abc|");
+        model.redo();
+        assert_eq!(
+            tx(&model),
+            "<p>abc</p><pre><code>fn main() {}|</code></pre>"
+        );
+    }
+
+    #[test]
+    fn a_failing_command_sequence_changes_nothing() {
+        let mut model = cm("abc|");
+        model.replace_text(Utf16String::from("d"));
+        let result = model.run_command_sequence(vec![
+            ComposerOperation::ReplaceText { text: "e".into() },
+            ComposerOperation::SetContentFromHtml {
+                html: "<strong>".into(),
+            },
+        ]);
+        assert!(result.is_err());
+        assert_eq!(tx(&model), "abcd|");
+        assert_eq!(model.history().len(), 1);
+    }
+
+    #[test]
+    fn a_command_sequence_reports_its_changes_as_one() {
+        let mut model = cm("|");
+        model.insert_mention(
+            "https://matrix.to/#/@alice:matrix.org".into(),
+            "Alice".into(),
+            vec![],
+        );
+        let update = model
+            .run_command_sequence(vec![
+                ComposerOperation::Select { start: 0, end: 2 },
+                ComposerOperation::Backspace,
+                ComposerOperation::ReplaceText { text: "ab".into() },
+            ])
+            .unwrap();
+        assert_eq!(tx(&model), "ab|");
+        assert_eq!(update.offset_mapping.map_offset(2), 2);
+        assert!(!update.offset_mapping.is_identity());
+        assert_eq!(update.deleted_mentions.len(), 1);
+        assert_eq!(update.changed_handles.first_index(), Some(0));
+    }
+
+    #[test]
+    fn a_failing_command_sequence_leaves_the_next_update_consistent() {
+        let mut model = cm("abc|");
+        let decoration = model.add_decoration(
+            DecorationKind::SpellingError,
+            Location::from(0),
+            Location::from(3),
+        );
+        let result = model.run_command_sequence(vec![
+            ComposerOperation::Select { start: 0, end: 0 },
+            ComposerOperation::ReplaceText { text: "xy".into() },
+            ComposerOperation::SetContentFromHtml {
+                html: "<strong>".into(),
+            },
+        ]);
+        assert!(result.is_err());
+        assert_eq!(model.decorations()[0].id, decoration);
+        assert_eq!(model.decorations()[0].start, Location::from(0));
+
+        let update = model.replace_text(Utf16String::from("d"));
+        assert_eq!(tx(&model), "abcd|");
+        assert_eq!(update.offset_mapping.map_offset(3), 3);
+        assert!(update.deleted_mentions.is_empty());
+    }
+
+    #[test]
+    fn a_command_sequence_is_logged_as_one_operation() {
+        let mut model = recording("|");
+        let operations = vec![
+            ComposerOperation::ReplaceText { text: "a".into() },
+            ComposerOperation::Bold,
+        ];
+        model.run_command_sequence(operations.clone()).unwrap();
+        assert_eq!(
+            model.operation_log(),
+            &[ComposerOperation::RunCommandSequence { operations }]
+        );
+        assert_eq!(replay(&model).history().len(), 1);
+    }
+
+    #[test]
+    fn operations_are_not_logged_by_default() {
+        let mut model = cm("|");
+        model.replace_text(Utf16String::from("abc"));
+        model.set_content_from_html(&"<p>one</p>".into()).unwrap();
+        assert!(model.operation_log().is_empty());
+    }
+
+    #[test]
+    fn setting_the_content_starts_the_log_again() {
+        let mut model = recording("|");
+        model.replace_text(Utf16String::from("abc"));
+        model.set_content_from_html(&"<p>one</p>".into()).unwrap();
+        model.replace_text(Utf16String::from("two"));
+        assert_eq!(
+            model.operation_log(),
+            &[
+                ComposerOperation::SetContentFromHtml {
+                    html: "<p>one</p>".into(),
+                },
+                ComposerOperation::ReplaceText { text: "two".into() },
+            ]
+        );
+        assert_eq!(tx(&replay(&model)), tx(&model));
+    }
+
+    #[test]
+    fn a_failing_command_sequence_keeps_the_log() {
+        let mut model = recording("|");
+        model.replace_text(Utf16String::from("abc"));
+        let result = model.run_command_sequence(vec![
+            ComposerOperation::SetContentFromHtml {
+                html: "<p>one</p>".into(),
+            },
+            ComposerOperation::SetContentFromHtml {
+                html: "<strong>".into(),
+            },
+        ]);
+        assert!(result.is_err());
+        assert_eq!(
+            model.operation_log(),
+            &[ComposerOperation::ReplaceText { text: "abc".into() }]
+        );
+    }
+
+    #[test]
+    fn clearing_the_log_empties_it() {
+        let mut model = recording("|");
+        model.replace_text(Utf16String::from("abc"));
+        model.clear_operation_log();
+        model.bold();
+        assert_eq!(model.operation_log(), &[ComposerOperation::Bold]);
+    }
+}
@@ -7,7 +7,8 @@
 use crate::dom::nodes::dom_node::DomNodeKind::{Generic, ListItem, Quote};
 use crate::dom::DomLocation;
 use crate::{
-    ComposerAction, ComposerModel, ComposerUpdate, DomNode, UnicodeString,
+    ComposerAction, ComposerModel, ComposerOperation, ComposerUpdate, DomNode,
+    UnicodeString,
 };
 
 impl<S> ComposerModel<S>
@@ -15,6 +16,10 @@ where
     S: UnicodeString,
 {
     pub fn quote(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::Quote);
+        if self.action_is_disallowed(ComposerAction::Quote) {
+            return ComposerUpdate::keep();
+        }
         if self.action_is_reversed(ComposerAction::Quote) {
             self.remove_quote()
         } else {
@@ -22,7 +27,7 @@ where
         }
     }
 
-    fn add_quote(&mut self) -> ComposerUpdate<S> {
+    pub(crate) fn add_quote(&mut self) -> ComposerUpdate<S> {
         let (s, e) = self.safe_selection();
         let Some(wrap_result) =
             self.state.dom.find_nodes_to_wrap_in_block(s, e)
@@ -121,7 +126,7 @@ where
         self.create_update_replace_all()
     }
 
-    fn remove_quote(&mut self) -> ComposerUpdate<S> {
+    pub(crate) fn remove_quote(&mut self) -> ComposerUpdate<S> {
         let (s, e) = self.safe_selection();
         let range = self.state.dom.find_range(s, e);
         let Some(quote_location) =
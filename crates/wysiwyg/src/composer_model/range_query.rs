@@ -0,0 +1,206 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use strum_macros::AsRefStr;
+
+use crate::dom::nodes::{ContainerNode, ContainerNodeKind};
+use crate::{
+    ComposerModel, DomHandle, DomNode, InlineFormatType, ListType, Location,
+    UnicodeString,
+};
+
+/// What some of the content is made of, for inspecting a range without
+/// reading the whole document, see [ComposerModel::query_range].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RangeQuery {
+    /// The kinds of leaf node in the range, in the order they first appear
+    pub leaf_kinds: Vec<RangeLeafKind>,
+    /// The kinds of block the range is in, outermost first
+    pub block_kinds: Vec<RangeBlockKind>,
+    /// The inline formats applied to any of the range
+    pub formats: Vec<InlineFormatType>,
+    /// Whether any of the range is in a link
+    pub has_link: bool,
+}
+
+#[derive(AsRefStr, Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RangeLeafKind {
+    Text,
+    LineBreak,
+    Mention,
+}
+
+#[derive(AsRefStr, Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RangeBlockKind {
+    Paragraph,
+    OrderedList,
+    UnorderedList,
+    ListItem,
+    CodeBlock,
+    Quote,
+}
+
+impl RangeQuery {
+    fn add_leaf<S: UnicodeString>(&mut self, node: &DomNode<S>) {
+        let kind = match node {
+            DomNode::Text(_) => RangeLeafKind::Text,
+            DomNode::LineBreak(_) => RangeLeafKind::LineBreak,
+            DomNode::Mention(_) => RangeLeafKind::Mention,
+            DomNode::Container(_) => return,
+        };
+        push_new(&mut self.leaf_kinds, kind);
+    }
+
+    fn add_container<S: UnicodeString>(
+        &mut self,
+        container: &ContainerNode<S>,
+    ) {
+        let block_kind = match container.kind() {
+            ContainerNodeKind::Formatting(format) => {
+                push_new(&mut self.formats, format.clone());
+                None
+            }
+            ContainerNodeKind::Link(_) => {
+                self.has_link = true;
+                None
+            }
+            ContainerNodeKind::Paragraph => Some(RangeBlockKind::Paragraph),
+            ContainerNodeKind::List(ListType::Ordered) => {
+                Some(RangeBlockKind::OrderedList)
+            }
+            ContainerNodeKind::List(ListType::Unordered) => {
+                Some(RangeBlockKind::UnorderedList)
+            }
+            ContainerNodeKind::ListItem => Some(RangeBlockKind::ListItem),
+            ContainerNodeKind::CodeBlock => Some(RangeBlockKind::CodeBlock),
+            ContainerNodeKind::Quote => Some(RangeBlockKind::Quote),
+            ContainerNodeKind::Generic | ContainerNodeKind::Span => None,
+        };
+        if let Some(block_kind) = block_kind {
+            push_new(&mut self.block_kinds, block_kind);
+        }
+    }
+}
+
+fn push_new<T: PartialEq>(items: &mut Vec<T>, item: T) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// What the content between start and end is made of. Leaves which only
+    /// touch the edge of a selection are left out, while if start and end
+    /// are equal this is what is on either side of that position.
+    pub fn query_range(&self, start: Location, end: Location) -> RangeQuery {
+        let dom = &self.state.dom;
+        let len = dom.text_len();
+        let start = usize::from(start).min(len);
+        let end = usize::from(end).min(len);
+        let range = dom.find_range(start, end);
+
+        let mut query = RangeQuery::default();
+        let mut handles: Vec<DomHandle> = dom
+            .iter_leaves_in(&range)
+            .map(|leaf| {
+                query.add_leaf(leaf.node);
+                leaf.handle().clone()
+            })
+            .collect();
+        if handles.is_empty() {
+            // e.g. the cursor is in an empty paragraph
+            handles.extend(
+                range
+                    .deepest_container_node(None)
+                    .map(|location| location.node_handle.clone()),
+            );
+        }
+        for handle in handles {
+            for node in dom.nodes_on_path_to(&handle) {
+                if let DomNode::Container(container) = node {
+                    query.add_container(container);
+                }
+            }
+        }
+        query
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tests::testutils_composer_model::cm;
+    use crate::{InlineFormatType, Location};
+
+    use super::{RangeBlockKind, RangeLeafKind, RangeQuery};
+
+    fn query(html: &str, start: usize, end: usize) -> RangeQuery {
+        cm(html).query_range(Location::from(start), Location::from(end))
+    }
+
+    #[test]
+    fn query_a_selection_across_blocks() {
+        assert_eq!(
+            query("<p><b>ab</b></p><ol><li><i>cd</i></li></ol>|", 1, 4),
+            RangeQuery {
+                leaf_kinds: vec![RangeLeafKind::Text],
+                block_kinds: vec![
+                    RangeBlockKind::Paragraph,
+                    RangeBlockKind::OrderedList,
+                    RangeBlockKind::ListItem,
+                ],
+                formats: vec![InlineFormatType::Bold, InlineFormatType::Italic],
+                has_link: false,
+            }
+        );
+    }
+
+    #[test]
+    fn query_leaves_out_what_only_touches_a_selection() {
+        let query = query("<b>ab</b>cd<a href=\"https://x.org\">ef</a>|", 2, 4);
+        assert_eq!(query.formats, Vec::new());
+        assert!(!query.has_link);
+    }
+
+    #[test]
+    fn query_at_a_cursor_reports_both_sides() {
+        let query = query("<b>ab</b>cd|", 2, 2);
+        assert_eq!(query.leaf_kinds, vec![RangeLeafKind::Text]);
+        assert_eq!(query.formats, vec![InlineFormatType::Bold]);
+    }
+
+    #[test]
+    fn query_links_and_mentions() {
+        let query = query(
+            "<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>, \
+            <a href=\"https://x.org\">x</a>|",
+            0,
+            4,
+        );
+        assert_eq!(
+            query.leaf_kinds,
+            vec![RangeLeafKind::Mention, RangeLeafKind::Text]
+        );
+        assert!(query.has_link);
+    }
+
+    #[test]
+    fn query_in_an_empty_block() {
+        let query = query("<blockquote><p>|</p></blockquote>", 0, 0);
+        assert_eq!(query.leaf_kinds, Vec::new());
+        assert_eq!(
+            query.block_kinds,
+            vec![RangeBlockKind::Quote, RangeBlockKind::Paragraph]
+        );
+    }
+
+    #[test]
+    fn query_past_the_end_of_the_content() {
+        let query = query("<code>ab|</code>", 1, 10);
+        assert_eq!(query.formats, vec![InlineFormatType::InlineCode]);
+    }
+}
@@ -4,12 +4,17 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use regex::Regex;
-
 use crate::dom::html_source::HtmlSource;
 use crate::dom::nodes::ContainerNode;
-use crate::dom::parser::parse_from_source;
-use crate::{ComposerModel, ComposerUpdate, DomNode, Location, UnicodeString}; // Import the trait for to_tree
+use crate::dom::parser::{
+    parse_from_source_with_options, post_process_joined_fragments, HtmlChunker,
+};
+use crate::invisible_characters::strip_nodes;
+use crate::paste_truncation::PasteLimiter;
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, DomNode,
+    HistoryEntryKind, Location, UnicodeString,
+}; // Import the trait for to_tree
 
 impl<S> ComposerModel<S>
 where
@@ -23,32 +28,150 @@ where
         new_html: S,
         external_source: HtmlSource,
     ) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::ReplaceHtml {
+            html: new_html.to_string(),
+            source: external_source,
+        });
+        self.push_state_to_history(HistoryEntryKind::Paste);
+        let nodes =
+            self.parse_pasted_html(&new_html.to_string(), external_source);
+        self.paste_nodes(nodes)
+    }
+
+    /// Start pasting HTML which will arrive in chunks, e.g. a paste too
+    /// large to parse at once without blocking the UI. Each chunk passed to
+    /// [Self::feed_html_paste] is parsed as far as it is complete, so
+    /// clients can yield and show progress between chunks, and
+    /// [Self::finish_html_paste] inserts the content at the selection as
+    /// [Self::replace_html] would. Any paste which wasn't finished is
+    /// abandoned.
+    pub fn start_html_paste(&mut self, external_source: HtmlSource) {
+        // Google Docs wraps everything in a `<b>` which is stripped before
+        // parsing, so its HTML is parsed in one piece.
+        let chunker = if external_source == HtmlSource::GoogleDoc {
+            HtmlChunker::unsplittable()
+        } else {
+            HtmlChunker::default()
+        };
+        self.pending_paste = Some(PendingPaste {
+            source: external_source,
+            chunker,
+            html: String::new(),
+            nodes: Vec::new(),
+        });
+    }
+
+    /// Add the next chunk of the HTML being pasted, parsing any part of it
+    /// which is now complete. Does nothing if no paste was started.
+    pub fn feed_html_paste(&mut self, chunk: S) {
+        let Some(mut paste) = self.pending_paste.take() else {
+            return;
+        };
+        let chunk = chunk.to_string();
+        if let Some(piece) = paste.chunker.push(&chunk) {
+            paste
+                .nodes
+                .extend(self.parse_pasted_html(&piece, paste.source));
+        }
+        paste.html.push_str(&chunk);
+        self.pending_paste = Some(paste);
+    }
+
+    /// Parse the rest of the HTML being pasted and insert all of it at the
+    /// selection, as one undoable paste.
+    pub fn finish_html_paste(&mut self) -> ComposerUpdate<S> {
+        let Some(paste) = self.pending_paste.take() else {
+            return ComposerUpdate::keep();
+        };
+        let mut nodes = paste.nodes;
+        nodes.extend(
+            self.parse_pasted_html(&paste.chunker.finish(), paste.source),
+        );
+        let nodes = post_process_joined_fragments(nodes);
+        let _span = self.start_operation(ComposerOperation::ReplaceHtml {
+            html: paste.html,
+            source: paste.source,
+        });
+        self.push_state_to_history(HistoryEntryKind::Paste);
+        self.paste_nodes(nodes)
+    }
+
+    /// Replace the selection with the pasted nodes, without invisible
+    /// characters if the configuration asks for that, and cut down to the
+    /// limits on pasted content in the configuration.
+    fn paste_nodes(&mut self, nodes: Vec<DomNode<S>>) -> ComposerUpdate<S> {
         if self.has_selection() {
             self.do_replace_text(S::default());
         }
+        let (nodes, stripped_characters) =
+            if self.config.strip_invisible_characters {
+                strip_nodes(nodes)
+            } else {
+                (nodes, None)
+            };
+        let (nodes, paste_truncation) =
+            PasteLimiter::new(&self.config).limit(nodes);
+        self.insert_pasted_nodes(nodes);
+        let mut update = self.create_update_replace_all();
+        update.paste_truncation = paste_truncation;
+        update.stripped_characters = stripped_characters;
+        update
+    }
+
+    /// Internal: insert html at the cursor without modifying the undo/redo
+    /// state.
+    pub(crate) fn do_replace_html(
+        &mut self,
+        new_html: S,
+        external_source: HtmlSource,
+    ) {
+        if self.has_selection() {
+            self.do_replace_text(S::default());
+        }
+        let nodes =
+            self.parse_pasted_html(&new_html.to_string(), external_source);
+        self.insert_pasted_nodes(nodes);
+    }
+
+    /// Parse pasted html into the top-level nodes to insert.
+    fn parse_pasted_html(
+        &self,
+        html: &str,
+        external_source: HtmlSource,
+    ) -> Vec<DomNode<S>> {
         // Remove meta tags from the HTML which caused errors in html5ever
-        let meta_regex = Regex::new(r"<meta[^>]*>").unwrap();
-        let mut cleaned_html = meta_regex
-            .replace_all(&new_html.to_string(), "")
-            .to_string();
+        let mut cleaned_html = remove_meta_tags(html);
 
         if external_source == HtmlSource::GoogleDoc {
             // Strip outer b tag that google docs adds
-            let b_regex = Regex::new(r"<b[^>]*>(.*)<\/b>").unwrap();
-            cleaned_html = b_regex.replace(&cleaned_html, "$1").to_string();
+            cleaned_html = strip_outer_b_tag(&cleaned_html);
         }
 
-        let result =
-            parse_from_source(&cleaned_html.to_string(), external_source);
+        let result = parse_from_source_with_options(
+            &cleaned_html.to_string(),
+            external_source,
+            self.config.max_parse_depth,
+            &self.config.attribute_policy,
+            self.config.whitespace_policy,
+            self.at_room_detection(),
+            false,
+        );
+
+        let (mut dom, _) = result.unwrap();
+        dom.remove_disallowed_formatting(&self.config.disallowed_actions);
+        dom.into_document_node()
+            .into_container()
+            .unwrap()
+            .take_children()
+    }
 
-        let doc_node = result.unwrap().into_document_node();
+    /// Insert parsed nodes at the cursor, leaving the cursor after them.
+    fn insert_pasted_nodes(&mut self, new_children: Vec<DomNode<S>>) {
         let (start, end) = self.safe_selection();
         let range = self.state.dom.find_range(start, end);
 
         // We should only have 1 dom node, so add the children under a paragraph to take advantage of the exisitng
         // insert_node_at_cursor api and then delete the paragraph node promoting it's the children up a level.
-        let new_children = doc_node.into_container().unwrap().take_children();
         let child_count = new_children.len();
         let p = DomNode::Container(ContainerNode::new_paragraph(new_children));
 
@@ -59,25 +182,113 @@ where
         );
 
         // Track the index of the last inserted node for placing the cursor
-        let last_index = handle.index_in_parent() + child_count - 1;
+        let Some(last_index) =
+            (handle.index_in_parent() + child_count).checked_sub(1)
+        else {
+            return;
+        };
         let last_handle = handle.parent_handle().child_handle(last_index);
         let location = self.state.dom.location_for_node(&last_handle);
 
         self.state.start =
             Location::from(location.position + location.length - 1);
         self.state.end = self.state.start;
-        // add a trailing space in cases when we do not have a next sibling
-        self.create_update_replace_all()
     }
 }
 
+/// HTML being pasted in chunks, see [ComposerModel::start_html_paste].
+#[derive(Clone)]
+pub(crate) struct PendingPaste<S>
+where
+    S: UnicodeString,
+{
+    source: HtmlSource,
+    chunker: HtmlChunker,
+    /// All of the HTML so far, for the operation log
+    html: String,
+    /// The top-level nodes parsed so far
+    nodes: Vec<DomNode<S>>,
+}
+
+/// Remove every `<meta ...>` tag from the HTML.
+fn remove_meta_tags(html: &str) -> String {
+    let mut cleaned = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<meta") {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        cleaned.push_str(&rest[..start]);
+        rest = &rest[start + len + 1..];
+    }
+    cleaned.push_str(rest);
+    cleaned
+}
+
+/// Replace the first `<b ...>` tag with a closing `</b>` later on the same
+/// line by its content, up to the last closing tag on that line.
+fn strip_outer_b_tag(html: &str) -> String {
+    let mut from = 0;
+    while let Some(offset) = html[from..].find("<b") {
+        let start = from + offset;
+        let Some(tag_len) = html[start..].find('>') else {
+            break;
+        };
+        let content_start = start + tag_len + 1;
+        let line_end = html[content_start..]
+            .find('\n')
+            .map_or(html.len(), |i| content_start + i);
+        if let Some(len) = html[content_start..line_end].rfind("</b>") {
+            let content_end = content_start + len;
+            return format!(
+                "{}{}{}",
+                &html[..start],
+                &html[content_start..content_end],
+                &html[content_end + "</b>".len()..]
+            );
+        }
+        from = start + 1;
+    }
+    html.to_owned()
+}
+
 #[cfg(test)]
 mod test {
     use crate::dom::html_source::HtmlSource;
     use crate::dom::parser::{
-        GOOGLE_DOC_HTML_PASTEBOARD, MS_DOC_HTML_PASTEBOARD,
+        EMAIL_HTML_PASTEBOARD, GOOGLE_DOC_CHECKLIST_HTML_PASTEBOARD,
+        GOOGLE_DOC_HEADINGS_HTML_PASTEBOARD, GOOGLE_DOC_HTML_PASTEBOARD,
+        MS_DOC_HTML_PASTEBOARD,
     };
-    use crate::tests::testutils_composer_model::cm;
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::{
+        ComposerModel, ComposerModelConfig, ComposerUpdate, PasteTruncation,
+    };
+    use widestring::Utf16String;
+
+    use super::{remove_meta_tags, strip_outer_b_tag};
+
+    #[test]
+    fn meta_tags_are_removed_wherever_they_are() {
+        assert_eq!(
+            remove_meta_tags("<meta a='1'>x<meta>y<meta b=\"2\">"),
+            "xy"
+        );
+        assert_eq!(remove_meta_tags("x<meta"), "x<meta");
+    }
+
+    #[test]
+    fn the_first_b_tag_closed_on_its_line_is_stripped() {
+        assert_eq!(
+            strip_outer_b_tag("<b id='x'><b>a</b> b</b>c"),
+            "<b>a</b> bc"
+        );
+        assert_eq!(
+            strip_outer_b_tag("<br><b>a\n</b><b>b</b>"),
+            "<br><b>a\n</b>b"
+        );
+        assert_eq!(strip_outer_b_tag("<p>a</p>"), "<p>a</p>");
+    }
 
     #[test]
     fn test_replace_html_strips_meta_tags_google_docs() {
@@ -99,6 +310,28 @@ mod test {
         assert_eq!(html_str, "<ol><li><p><i>Italic</i></p></li><li><p><b>Bold</b></p></li><li><p>Unformatted</p></li><li><p><del>Strikethrough</del></p></li><li><p><u>Underlined</u></p></li><li><p><a style=\"text-decoration:none;\" href=\"http://matrix.org\"><u>Linked</u></a></p><ul><li><p>Nested</p></li></ul></li></ol>");
     }
 
+    #[test]
+    fn test_replace_html_google_docs_headings_and_checklist() {
+        let mut model = cm("|");
+        let html = format!(
+            r#"<meta charset="utf-8"><b style="font-weight:normal;" id="docs-internal-guid-bec65465-7fff-9422-b4bc-8e35d97b3ccb">{}{}</b>"#,
+            GOOGLE_DOC_HEADINGS_HTML_PASTEBOARD,
+            GOOGLE_DOC_CHECKLIST_HTML_PASTEBOARD
+        );
+
+        let _ = model.replace_html(html.into(), HtmlSource::GoogleDoc);
+
+        assert_eq!(
+            model.get_content_as_html(),
+            "<p><strong>Title</strong></p>\
+            <p><strong>Heading</strong></p>\
+            <p><strong>Subheading</strong></p>\
+            <p>Body text</p>\
+            <ul><li><p>☑ <del>Done</del></p></li>\
+            <li><p>☐ To <b>do</b></p></li></ul>"
+        );
+    }
+
     #[test]
     fn test_replace_html_strips_only_meta_tags_ms_docs() {
         let mut model = cm("|");
@@ -112,7 +345,24 @@ mod test {
         let html = model.get_content_as_html();
         let html_str = html.to_string();
         assert!(!html_str.contains("<meta"));
-        assert_eq!(html_str, "<ol start=\"1\"><li><p><i>Italic</i></p></li><li><p><b>Bold</b></p></li><li><p>Unformatted</p></li><li><p><del>Strikethrough</del></p></li><li><p><u>Underlined</u></p></li><li><p><a class=\"Hyperlink SCXW204127278 BCX0\" target=\"_blank\" rel=\"noreferrer noopener\" style=\"-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; text-decoration: none; color: inherit;\" href=\"https://matrix.org/\"><u>Linked</u></a></p></li></ol><ul><li><p>Nested</p></li></ul>");
+        assert_eq!(html_str, "<ol start=\"1\"><li><p><i>Italic</i></p></li><li><p><b>Bold</b></p></li><li><p>Unformatted</p></li><li><p><del>Strikethrough</del></p></li><li><p><u>Underlined</u></p></li><li><p><a class=\"Hyperlink SCXW204127278 BCX0\" rel=\"noreferrer noopener\" style=\"-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; text-decoration: none; color: inherit;\" target=\"_blank\" href=\"https://matrix.org/\"><u>Linked</u></a></p></li></ol><ul><li><p>Nested</p></li></ul>");
+    }
+
+    #[test]
+    fn test_replace_html_flattens_email_layout_tables() {
+        let mut model = cm("|");
+
+        let _ = model.replace_html(
+            EMAIL_HTML_PASTEBOARD.into(),
+            HtmlSource::UnknownExternal,
+        );
+
+        assert_eq!(
+            model.get_content_as_message_html(),
+            "<b>Monthly update</b><br />Hello all,<br /><br />\
+            Read the <a href=\"https://matrix.org/blog\">blog</a>.<br />\
+            Sent from <i>Outlook</i><br />"
+        );
     }
 
     #[test]
@@ -202,6 +452,198 @@ mod test {
         let html_str = html.to_string();
         assert_eq!(html_str, "<p>hello</p><p>list item</p>");
     }
+
+    fn paste_in_chunks(
+        model: &mut ComposerModel<Utf16String>,
+        chunks: &[&str],
+        source: HtmlSource,
+    ) {
+        model.start_html_paste(source);
+        for chunk in chunks {
+            model.feed_html_paste((*chunk).into());
+        }
+        let _ = model.finish_html_paste();
+    }
+
+    #[test]
+    fn pasting_in_chunks_matches_replace_html() {
+        let chunks = [
+            "<p>a</p>\n<p>b<b",
+            "r>c</p><ul><li>d</li></ul>e <i>f",
+            "</i><blockquote><p>g</p></bloc",
+            "kquote>",
+        ];
+        let mut chunked = cm("Hello{world}|test");
+        paste_in_chunks(&mut chunked, &chunks, HtmlSource::UnknownExternal);
+        let mut whole = cm("Hello{world}|test");
+        let _ = whole
+            .replace_html(chunks.concat().into(), HtmlSource::UnknownExternal);
+        assert_eq!(chunked.get_content_as_html(), whole.get_content_as_html());
+        assert_eq!(chunked.safe_selection(), whole.safe_selection());
+    }
+
+    #[test]
+    fn lists_split_across_chunks_are_joined_as_in_replace_html() {
+        for chunks in [
+            ["<ul><li>c</li></ul>", "<ul><li>d</li></ul>"],
+            ["<ol start=\"3\"><li>c</li></ol>", "<ol><li>d</li></ol>"],
+        ] {
+            let mut chunked = cm("|");
+            paste_in_chunks(&mut chunked, &chunks, HtmlSource::Matrix);
+            let mut whole = cm("|");
+            let _ =
+                whole.replace_html(chunks.concat().into(), HtmlSource::Matrix);
+            assert_eq!(
+                chunked.get_content_as_html(),
+                whole.get_content_as_html()
+            );
+            assert_eq!(chunked.safe_selection(), whole.safe_selection());
+        }
+    }
+
+    #[test]
+    fn pasting_google_docs_html_in_chunks_strips_the_outer_b_tag() {
+        let mut model = cm("|");
+        paste_in_chunks(
+            &mut model,
+            &["<meta charset='utf-8'><b id=\"x\"><p>a</p>", "<p>b</p></b>"],
+            HtmlSource::GoogleDoc,
+        );
+        assert_eq!(model.get_content_as_html(), "<p>a</p><p>b</p>");
+    }
+
+    #[test]
+    fn a_chunked_paste_is_undone_at_once() {
+        let mut model = cm("a|");
+        paste_in_chunks(
+            &mut model,
+            &["<p>b</p>", "<p>c</p>"],
+            HtmlSource::Matrix,
+        );
+        model.undo();
+        assert_eq!(tx(&model), "a|");
+    }
+
+    #[test]
+    fn feeding_or_finishing_without_starting_a_paste_does_nothing() {
+        let mut model = cm("a|");
+        model.feed_html_paste("<p>b</p>".into());
+        let update = model.finish_html_paste();
+        assert_eq!(update, ComposerUpdate::keep());
+        assert_eq!(tx(&model), "a|");
+    }
+
+    fn with_config(
+        text: &str,
+        config: ComposerModelConfig,
+    ) -> ComposerModel<Utf16String> {
+        let mut model = cm(text);
+        model.set_config(config);
+        model
+    }
+
+    #[test]
+    fn pastes_within_the_limits_are_not_truncated() {
+        let mut model = with_config(
+            "|",
+            ComposerModelConfig::default()
+                .with_max_paste_nodes(Some(5))
+                .with_max_paste_length(Some(3))
+                .with_max_paste_list_depth(Some(1)),
+        );
+        let update = model.replace_html(
+            "<ul><li>a<b>bc</b></li></ul>".into(),
+            HtmlSource::Matrix,
+        );
+        assert_eq!(update.paste_truncation, None);
+        assert_eq!(tx(&model), "<ul><li>a<b>bc|</b></li></ul>");
+    }
+
+    #[test]
+    fn nodes_past_the_maximum_are_left_out() {
+        let mut model = with_config(
+            "|",
+            ComposerModelConfig::default().with_max_paste_nodes(Some(6)),
+        );
+        let update = model.replace_html(
+            "<p>a</p><p><b>b</b></p><p>c</p>".into(),
+            HtmlSource::Matrix,
+        );
+        assert_eq!(
+            update.paste_truncation,
+            Some(PasteTruncation {
+                too_many_nodes: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(tx(&model), "<p>a</p><p><b>b|</b></p>");
+    }
+
+    #[test]
+    fn text_past_the_maximum_length_is_left_out() {
+        let mut model = with_config(
+            "|",
+            ComposerModelConfig::default().with_max_paste_length(Some(4)),
+        );
+        let update = model.replace_html(
+            "<p>ab<i>c😀d</i></p><p>e</p>".into(),
+            HtmlSource::Matrix,
+        );
+        assert_eq!(
+            update.paste_truncation,
+            Some(PasteTruncation {
+                too_long: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(tx(&model), "<p>ab<i>c|</i></p>");
+    }
+
+    #[test]
+    fn lists_nested_too_deeply_are_left_out() {
+        let mut model = with_config(
+            "|",
+            ComposerModelConfig::default().with_max_paste_list_depth(Some(1)),
+        );
+        let update = model.replace_html(
+            "<ul><li><p>a</p><ol><li>b</li></ol></li><li>c</li></ul><p>d</p>"
+                .into(),
+            HtmlSource::Matrix,
+        );
+        assert_eq!(
+            update.paste_truncation,
+            Some(PasteTruncation {
+                lists_too_deep: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(tx(&model), "<ul><li><p>a</p></li><li>c</li></ul><p>d|</p>");
+    }
+
+    #[test]
+    fn a_paste_cut_down_to_nothing_pastes_nothing() {
+        let mut model = with_config(
+            "|",
+            ComposerModelConfig::default().with_max_paste_nodes(Some(0)),
+        );
+        let update = model.replace_html("<p>a</p>".into(), HtmlSource::Matrix);
+        assert!(update.paste_truncation.is_some_and(|t| t.too_many_nodes));
+        assert_eq!(tx(&model), "|");
+    }
+
+    #[test]
+    fn chunked_pastes_are_truncated_too() {
+        let mut model = with_config(
+            "a|",
+            ComposerModelConfig::default().with_max_paste_length(Some(2)),
+        );
+        model.start_html_paste(HtmlSource::Matrix);
+        model.feed_html_paste("<p>bc</p>".into());
+        model.feed_html_paste("<p>de</p>".into());
+        let update = model.finish_html_paste();
+        assert!(update.paste_truncation.is_some_and(|t| t.too_long));
+        assert_eq!(tx(&model), "<p>a</p><p>bc|</p>");
+    }
 }
 
 #[cfg(all(test, target_arch = "wasm32"))]
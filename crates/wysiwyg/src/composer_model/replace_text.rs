@@ -6,13 +6,17 @@
 
 use crate::dom::nodes::dom_node::DomNodeKind;
 use crate::dom::nodes::DomNode;
-use crate::dom::unicode_string::UnicodeStrExt;
+use crate::dom::unicode_string::{UnicodeStr, UnicodeStrExt};
 use crate::dom::{DomLocation, Range};
+use crate::invisible_characters::strip_text;
+use crate::nbsp::{is_placeholder, nbsp_to_space, NBSP};
 use crate::{
-    ComposerModel, ComposerUpdate, DomHandle, Location, SuggestionPattern,
-    UnicodeString,
+    ComposerAction, ComposerModel, ComposerOperation, ComposerUpdate,
+    DomHandle, HistoryEntryKind, InlineFormatType, ListType, Location,
+    MenuAction, SuggestionPattern, ToRawText, UnicodeString,
 };
 use std::cmp::min;
+use url::Url;
 
 impl<S> ComposerModel<S>
 where
@@ -22,8 +26,64 @@ where
     /// Treats its input as plain text, so any HTML code will show up in
     /// the document (i.e. it will be escaped).
     pub fn replace_text(&mut self, new_text: S) -> ComposerUpdate<S> {
-        self.push_state_to_history();
-        self.do_replace_text(new_text)
+        let _span = self.start_operation(ComposerOperation::ReplaceText {
+            text: new_text.to_string(),
+        });
+        self.do_replace_typed_text(new_text)
+    }
+
+    /// Paste plain text at the selection. If the configuration asks for
+    /// pasted URLs to become links, pasting a URL makes the selected text a
+    /// link to it, or inserts the URL as a link if nothing is selected.
+    /// Anything else is inserted as if by [Self::replace_text]. Invisible
+    /// characters are removed first if the configuration asks for that.
+    pub fn paste_text(&mut self, text: S) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::PasteText {
+            text: text.to_string(),
+        });
+        if !self.config.strip_invisible_characters {
+            return self.do_paste_text(text);
+        }
+        let (text, stripped_characters) = strip_text(&text.to_string());
+        let mut update = self.do_paste_text(S::from(text.as_str()));
+        update.stripped_characters = stripped_characters;
+        update
+    }
+
+    fn do_paste_text(&mut self, text: S) -> ComposerUpdate<S> {
+        let Some(url) = self.pasted_url(&text) else {
+            return self.do_replace_typed_text(text);
+        };
+        self.push_state_to_history(HistoryEntryKind::Link);
+        let (s, mut e) = self.safe_selection();
+        if s == e {
+            self.do_replace_text(url.clone());
+            e = s + url.len();
+        }
+        let range = self.state.dom.find_range(s, e);
+        self.set_link_in_range(url, range, Vec::new())
+    }
+
+    fn do_replace_typed_text(&mut self, new_text: S) -> ComposerUpdate<S> {
+        self.push_state_to_history(HistoryEntryKind::Typing);
+        let new_text = self.normalize_typed_nbsp(new_text);
+        if let Some(update) = self.apply_markdown_shortcut(&new_text) {
+            return update;
+        }
+        if let Some(update) = self.insert_plain_text_lists(&new_text) {
+            return update;
+        }
+        let link = self.url_to_auto_link(&new_text);
+        let (s, e) = self.safe_selection();
+        let new_text = self.truncate_to_max_length(new_text, s, e);
+        let update = self.do_replace_text_in(new_text, s, e);
+        match link {
+            Some((url, start, end)) => {
+                let range = self.state.dom.find_range(start, end);
+                self.set_link_in_range(url, range, Vec::new())
+            }
+            None => update,
+        }
     }
 
     /// Replaces text in the an arbitrary start..end range with new_text.
@@ -33,7 +93,13 @@ where
         start: usize,
         end: usize,
     ) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::ReplaceTextIn {
+            text: new_text.to_string(),
+            start,
+            end,
+        });
+        self.push_state_to_history(HistoryEntryKind::Typing);
+        let new_text = self.truncate_to_max_length(new_text, start, end);
         self.do_replace_text_in(new_text, start, end)
     }
 
@@ -43,7 +109,13 @@ where
         suggestion: SuggestionPattern,
         append_space: bool,
     ) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span =
+            self.start_operation(ComposerOperation::ReplaceTextSuggestion {
+                text: new_text.to_string(),
+                suggestion: suggestion.clone(),
+                append_space,
+            });
+        self.push_state_to_history(HistoryEntryKind::Typing);
         let replace_suggestion_update =
             self.do_replace_text_in(new_text, suggestion.start, suggestion.end);
         if append_space {
@@ -55,7 +127,8 @@ where
 
     #[deprecated(since = "0.20.0")]
     pub fn add_line_break(&mut self) -> ComposerUpdate<S> {
-        self.push_state_to_history();
+        let _span = self.start_operation(ComposerOperation::AddLineBreak);
+        self.push_state_to_history(HistoryEntryKind::NewLine);
         self.do_add_line_break()
     }
 
@@ -182,6 +255,224 @@ where
         self.state.end = self.state.start;
     }
 
+    /// Shorten text replacing start..end so that the content is no longer
+    /// than the configured maximum length.
+    fn truncate_to_max_length(
+        &self,
+        mut text: S,
+        start: usize,
+        end: usize,
+    ) -> S {
+        if let Some(max_length) = self.config.max_length {
+            let kept_len = self.state.dom.text_len() - (end - start);
+            let allowed = max_length.saturating_sub(kept_len);
+            while text.len() > allowed {
+                text.pop_last();
+            }
+        }
+        text
+    }
+
+    /// The typed text with its non-breaking spaces replaced by spaces, if the
+    /// configuration asks for that and the text isn't being typed into code.
+    fn normalize_typed_nbsp(&self, new_text: S) -> S {
+        let text = new_text.to_string();
+        if !self.config.normalize_typed_nbsp
+            || !text.contains(NBSP)
+            || self.is_typing_code()
+        {
+            return new_text;
+        }
+        S::from(nbsp_to_space(&text).as_str())
+    }
+
+    /// Whether text typed at the selection becomes code, either because it
+    /// is in a code block or inline code, or because inline code was
+    /// toggled at the cursor.
+    fn is_typing_code(&self) -> bool {
+        let (s, e) = self.safe_selection();
+        let range = self.state.dom.find_range(s, e);
+        let kind = |kind| range.locations.iter().any(|l| l.kind == kind);
+        let toggled_inline_code = self
+            .state
+            .toggled_format_types
+            .contains(&InlineFormatType::InlineCode);
+        kind(DomNodeKind::CodeBlock)
+            || (kind(DomNodeKind::Formatting(InlineFormatType::InlineCode))
+                ^ toggled_inline_code)
+    }
+
+    /// The URL, and where it is, to turn into a link if the configuration
+    /// asks for typed URLs to become links and whitespace is being typed
+    /// right after one.
+    fn url_to_auto_link(&self, new_text: &S) -> Option<(S, usize, usize)> {
+        let (s, e) = self.safe_selection();
+        if !self.config.auto_link
+            || self.action_is_disallowed(ComposerAction::Link)
+            || s != e
+            || !new_text.chars().next().is_some_and(char::is_whitespace)
+        {
+            return None;
+        }
+        let MenuAction::UrlDetected { url, start, end } =
+            self.compute_menu_action()
+        else {
+            return None;
+        };
+        (end == s && self.config.allows_link_url(&url))
+            .then(|| (S::from(url.as_str()), start, end))
+    }
+
+    /// The URL to make a link to when the text is pasted, if the
+    /// configuration asks for pasted URLs to become links and the text,
+    /// apart from whitespace around it, is an http(s) URL which links are
+    /// allowed to and which fits in the maximum length.
+    fn pasted_url(&self, text: &S) -> Option<S> {
+        if !self.config.link_pasted_urls
+            || self.action_is_disallowed(ComposerAction::Link)
+        {
+            return None;
+        }
+        let text = text.to_string();
+        let url = text.trim();
+        let parsed = Url::parse(url).ok()?;
+        if !matches!(parsed.scheme(), "http" | "https")
+            || !parsed.has_host()
+            || url.contains(char::is_whitespace)
+            || !self.config.allows_link_url(url)
+        {
+            return None;
+        }
+        let (s, e) = self.safe_selection();
+        let range = self.state.dom.find_range(s, e);
+        if range.locations.iter().any(|l| l.kind.is_code_kind()) {
+            return None;
+        }
+        let url = S::from(url);
+        let fits = s != e
+            || self.truncate_to_max_length(url.clone(), s, e).len()
+                == url.len();
+        fits.then_some(url)
+    }
+
+    /// If the configuration asks for Markdown shortcuts and a space is being
+    /// typed after one at the start of a paragraph outside any list or
+    /// quote, replace the shortcut with the list or quote it stands for.
+    fn apply_markdown_shortcut(
+        &mut self,
+        new_text: &S,
+    ) -> Option<ComposerUpdate<S>> {
+        let (s, e) = self.safe_selection();
+        if !self.config.markdown_shortcuts
+            || s != e
+            || new_text != &S::from(" ")
+        {
+            return None;
+        }
+        let range = self.state.dom.find_range(s, e);
+        let block = range.deepest_block_node(None)?;
+        if !matches!(block.kind, DomNodeKind::Paragraph | DomNodeKind::Generic)
+            || block.node_handle.depth() > 1
+        {
+            return None;
+        }
+        let block_text = self
+            .state
+            .dom
+            .lookup_node(&block.node_handle)
+            .to_raw_text()
+            .to_string();
+        let (prefix, action) =
+            BLOCK_SHORTCUTS.into_iter().find(|(prefix, _)| {
+                block.start_offset == prefix.len()
+                    && block_text.starts_with(prefix)
+            })?;
+        if self.action_is_disallowed(action.clone()) {
+            return None;
+        }
+        self.do_replace_text_in(S::default(), s - prefix.len(), s);
+        Some(match action {
+            ComposerAction::Quote => self.add_quote(),
+            ComposerAction::OrderedList => self.toggle_list(ListType::Ordered),
+            _ => self.toggle_list(ListType::Unordered),
+        })
+    }
+
+    /// If the configuration asks for lists in plain text and text with
+    /// several lines, some of which start with a Markdown shortcut for a
+    /// list, is being inserted into a paragraph outside any list or quote,
+    /// insert it as paragraphs and lists instead.
+    fn insert_plain_text_lists(
+        &mut self,
+        new_text: &S,
+    ) -> Option<ComposerUpdate<S>> {
+        let (s, e) = self.safe_selection();
+        let text = new_text.to_string();
+        if !self.config.plain_text_lists
+            || !text.contains('\n')
+            || self.truncate_to_max_length(new_text.clone(), s, e) != *new_text
+        {
+            return None;
+        }
+        let range = self.state.dom.find_range(s, e);
+        let in_top_level_paragraph =
+            range.deepest_block_node(None).is_none_or(|block| {
+                matches!(
+                    block.kind,
+                    DomNodeKind::Paragraph | DomNodeKind::Generic
+                ) && block.node_handle.depth() <= 1
+            });
+        if !in_top_level_paragraph {
+            return None;
+        }
+        // Insert the lines without their shortcuts, then make each run of
+        // items a list, as typing the shortcuts would
+        let mut lines = Vec::new();
+        let mut inserted = String::new();
+        for line in text.strip_suffix('\n').unwrap_or(&text).split('\n') {
+            let (list_type, line) = match list_item_shortcut(line) {
+                Some((list_type, item))
+                    if !self.action_is_disallowed(list_type.action()) =>
+                {
+                    (Some(list_type), item)
+                }
+                _ => (None, line),
+            };
+            if !lines.is_empty() {
+                inserted.push('\n');
+            }
+            inserted.push_str(line);
+            lines.push((list_type, S::from(line).len()));
+        }
+        if lines.iter().all(|(list_type, _)| list_type.is_none()) {
+            return None;
+        }
+        self.do_replace_text_in(S::from(inserted), s, e);
+        let cursor = self.state.end;
+        let mut line_start = s;
+        let mut run_start = s;
+        for (i, (list_type, len)) in lines.iter().enumerate() {
+            let line_end = line_start + len;
+            let run_continues = lines
+                .get(i + 1)
+                .is_some_and(|(next_type, _)| next_type == list_type);
+            if let Some(list_type) =
+                list_type.as_ref().filter(|_| !run_continues)
+            {
+                self.state.start = Location::from(run_start);
+                self.state.end = Location::from(line_end);
+                self.toggle_list(list_type.clone());
+            }
+            line_start = line_end + 1;
+            if !run_continues {
+                run_start = line_start;
+            }
+        }
+        self.state.start = cursor;
+        self.state.end = cursor;
+        Some(self.create_update_replace_all())
+    }
+
     pub(crate) fn do_replace_text(&mut self, new_text: S) -> ComposerUpdate<S> {
         let (s, e) = self.safe_selection();
         self.do_replace_text_in(new_text, s, e)
@@ -205,7 +496,7 @@ where
             let mut slices = text_string.split('\n').peekable();
             while let Some(slice) = slices.next() {
                 let (s, e) = self.safe_selection();
-                if !slice.is_empty() && slice != "\u{A0}" {
+                if !slice.is_empty() && !is_placeholder(slice) {
                     self.do_replace_text_in(S::from(slice), s, e);
                 }
                 if slices.peek().is_some() {
@@ -233,6 +524,34 @@ where
     }
 }
 
+/// The Markdown shortcuts for blocks, and the action each stands for.
+const BLOCK_SHORTCUTS: [(&str, ComposerAction); 4] = [
+    ("-", ComposerAction::UnorderedList),
+    ("*", ComposerAction::UnorderedList),
+    ("1.", ComposerAction::OrderedList),
+    (">", ComposerAction::Quote),
+];
+
+/// The kind of list a line of plain text is an item of, and the text of
+/// the item, if the line starts with a Markdown shortcut for a list
+/// followed by a space. Items of numbered lists may have any number, as
+/// the lines after the first of a pasted list will.
+fn list_item_shortcut(line: &str) -> Option<(ListType, &str)> {
+    let (prefix, item) = line.split_once(' ')?;
+    let numbered = prefix.strip_suffix('.').is_some_and(|n| {
+        !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())
+    });
+    if numbered {
+        Some((ListType::Ordered, item))
+    } else if BLOCK_SHORTCUTS.iter().any(|(shortcut, action)| {
+        *shortcut == prefix && *action == ComposerAction::UnorderedList
+    }) {
+        Some((ListType::Unordered, item))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -245,8 +564,8 @@ mod test {
     use crate::tests::testutils_composer_model::cm;
     use crate::tests::testutils_conversion::utf16;
     use crate::{
-        ComposerAction, ComposerUpdate, LinkAction, Location, MenuAction,
-        MenuState,
+        ComposerAction, ComposerUpdate, DomHandle, LinkAction, Location,
+        MenuAction, MenuState, OffsetMapping,
     };
     use strum::IntoEnumIterator;
 
@@ -254,19 +573,26 @@ mod test {
     fn composer_update_contains_escaped_html() {
         let mut model = cm("|");
         let update = model.replace_text(Utf16String::from_str("<"));
-        assert_eq!(
-            update,
-            ComposerUpdate::replace_all(
-                utf16("&lt;"),
-                Location::from(1),
-                Location::from(1),
-                MenuState::Update(MenuStateUpdate {
-                    action_states: indent_unindent_redo_disabled()
-                }),
-                MenuAction::None,
-                LinkActionUpdate::Update(LinkAction::CreateWithText),
-            ),
+        let mut expected = ComposerUpdate::replace_all(
+            utf16("&lt;"),
+            Location::from(1),
+            Location::from(1),
+            MenuState::Update(MenuStateUpdate {
+                action_states: indent_unindent_redo_disabled(),
+            }),
+            MenuAction::None,
+            LinkActionUpdate::Update(LinkAction::CreateWithText),
+            OffsetMapping {
+                start: 0,
+                old_end: 0,
+                new_end: 1,
+            },
         );
+        expected.changed_handles.added =
+            vec![DomHandle::root().child_handle(0)];
+        // The timing varies from run to run
+        expected.timing = update.timing;
+        assert_eq!(update, expected);
     }
 
     fn indent_unindent_redo_disabled() -> HashMap<ComposerAction, ActionState> {
@@ -4,7 +4,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use crate::{ComposerModel, ComposerUpdate, Location, UnicodeString};
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, Location, UnicodeString,
+};
 
 impl<S> ComposerModel<S>
 where
@@ -16,6 +18,18 @@ where
         &mut self,
         start: Location,
         end: Location,
+    ) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::Select {
+            start: start.into(),
+            end: end.into(),
+        });
+        self.do_select(start, end)
+    }
+
+    pub(crate) fn do_select(
+        &mut self,
+        start: Location,
+        end: Location,
     ) -> ComposerUpdate<S> {
         if self.state.start == start && self.state.end == end {
             return ComposerUpdate::keep();
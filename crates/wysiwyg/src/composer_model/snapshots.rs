@@ -0,0 +1,260 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::collections::HashMap;
+
+use crate::composer_model::undo_redo::HistoryRecord;
+use crate::{
+    ComposerModel, ComposerOperation, ComposerState, ComposerUpdate,
+    UnicodeString,
+};
+
+/// Identifies a snapshot created by [ComposerModel::create_snapshot].
+pub type SnapshotId = u64;
+
+/// The content, selection and undo history of a model at some point.
+#[derive(Clone)]
+pub(crate) struct Snapshot<S>
+where
+    S: UnicodeString,
+{
+    state: ComposerState<S>,
+    previous_states: Vec<ComposerState<S>>,
+    next_states: Vec<ComposerState<S>>,
+    previous_records: Vec<HistoryRecord>,
+    next_records: Vec<HistoryRecord>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Snapshots<S>
+where
+    S: UnicodeString,
+{
+    snapshots: HashMap<SnapshotId, Snapshot<S>>,
+    next_id: SnapshotId,
+}
+
+impl<S> Default for Snapshots<S>
+where
+    S: UnicodeString,
+{
+    fn default() -> Self {
+        Self {
+            snapshots: HashMap::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<S> Snapshot<S>
+where
+    S: UnicodeString,
+{
+    pub(crate) fn of(model: &ComposerModel<S>) -> Self {
+        Self {
+            state: model.state.clone(),
+            previous_states: model.previous_states.clone(),
+            next_states: model.next_states.clone(),
+            previous_records: model.previous_records.clone(),
+            next_records: model.next_records.clone(),
+        }
+    }
+
+    pub(crate) fn restore(self, model: &mut ComposerModel<S>) {
+        model.state = self.state;
+        model.previous_states = self.previous_states;
+        model.next_states = self.next_states;
+        model.previous_records = self.previous_records;
+        model.next_records = self.next_records;
+    }
+
+    /// How many steps there were to undo when the snapshot was taken
+    pub(crate) fn undo_depth(&self) -> usize {
+        self.previous_records.len()
+    }
+}
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// Remember the current content, selection and undo history, so that
+    /// they can be put back with [Self::restore_snapshot]. Snapshots are
+    /// not part of the undo history, so they can be used to preview a
+    /// change and then revert it without the user seeing either step.
+    pub fn create_snapshot(&mut self) -> SnapshotId {
+        let _span = self.start_operation(ComposerOperation::CreateSnapshot);
+        let id = self.snapshots.next_id;
+        self.snapshots.next_id += 1;
+        self.snapshots.snapshots.insert(id, Snapshot::of(self));
+        id
+    }
+
+    /// Put back the content, selection and undo history as they were when
+    /// the snapshot was created. The snapshot is kept, so it can be
+    /// restored again, until it is discarded. Does nothing if there is no
+    /// snapshot with this id.
+    pub fn restore_snapshot(&mut self, id: SnapshotId) -> ComposerUpdate<S> {
+        let _span =
+            self.start_operation(ComposerOperation::RestoreSnapshot { id });
+        let Some(snapshot) = self.snapshots.snapshots.get(&id).cloned() else {
+            return ComposerUpdate::keep();
+        };
+        snapshot.restore(self);
+        self.create_update_replace_all_with_menu_state()
+    }
+
+    /// Forget a snapshot. Returns false if there was no snapshot with this
+    /// id.
+    pub fn discard_snapshot(&mut self, id: SnapshotId) -> bool {
+        let _span =
+            self.start_operation(ComposerOperation::DiscardSnapshot { id });
+        self.snapshots.snapshots.remove(&id).is_some()
+    }
+
+    /// Put back the content and selection as they were before the
+    /// operation which left them invalid, e.g. when a client has caught a
+    /// panic in one of the model's methods and would otherwise lose the
+    /// user's draft. They are taken from the latest undo step which is
+    /// valid, so nothing is kept for this while editing, and the steps
+    /// after it are dropped. Any paste in progress is abandoned.
+    pub fn reset_to_last_good(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::ResetToLastGood);
+        self.pending_paste = None;
+        while !is_good(&self.state) {
+            let Some(state) = self.previous_states.pop() else {
+                self.state = self.history_start_state.clone();
+                break;
+            };
+            self.previous_records.pop();
+            self.state = state;
+        }
+        self.create_update_replace_all_with_menu_state()
+    }
+}
+
+/// Whether the content of `state` is valid and its selection is inside it.
+fn is_good<S: UnicodeString>(state: &ComposerState<S>) -> bool {
+    let text_len = state.dom.text_len();
+    usize::from(state.start) <= text_len
+        && usize::from(state.end) <= text_len
+        && state.dom.satisfies_invariants()
+}
+
+#[cfg(test)]
+mod test {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use widestring::Utf16String;
+
+    use crate::dom::Dom;
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::{
+        ActionState, ComposerAction, DomNode, HistoryEntryKind, TextUpdate,
+    };
+
+    #[test]
+    fn restoring_a_snapshot_reverts_a_preview() {
+        let mut model = cm("abc|");
+        model.replace_text(Utf16String::from("d"));
+        let snapshot = model.create_snapshot();
+        model.select(0.into(), 4.into());
+        model.bold();
+        assert_eq!(tx(&model), "<strong>{abcd}|</strong>");
+
+        let update = model.restore_snapshot(snapshot);
+        assert!(matches!(update.text_update, TextUpdate::ReplaceAll(_)));
+        assert_eq!(tx(&model), "abcd|");
+    }
+
+    #[test]
+    fn restoring_a_snapshot_does_not_touch_the_undo_history() {
+        let mut model = cm("abc|");
+        model.replace_text(Utf16String::from("d"));
+        let snapshot = model.create_snapshot();
+        model.replace_text(Utf16String::from("e"));
+        model.restore_snapshot(snapshot);
+
+        assert_eq!(
+            model.action_states().get(&ComposerAction::Redo),
+            Some(&ActionState::Disabled)
+        );
+        model.undo();
+        assert_eq!(tx(&model), "abc|");
+        model.redo();
+        assert_eq!(tx(&model), "abcd|");
+    }
+
+    #[test]
+    fn a_snapshot_can_be_restored_until_it_is_discarded() {
+        let mut model = cm("abc|");
+        let snapshot = model.create_snapshot();
+        model.replace_text(Utf16String::from("d"));
+        model.restore_snapshot(snapshot);
+        model.replace_text(Utf16String::from("e"));
+        model.restore_snapshot(snapshot);
+        assert_eq!(tx(&model), "abc|");
+
+        assert!(model.discard_snapshot(snapshot));
+        assert!(!model.discard_snapshot(snapshot));
+        model.replace_text(Utf16String::from("f"));
+        let update = model.restore_snapshot(snapshot);
+        assert!(matches!(update.text_update, TextUpdate::Keep));
+        assert_eq!(tx(&model), "abcf|");
+    }
+
+    #[test]
+    fn resetting_after_a_panic_restores_the_last_good_content() {
+        let mut model = cm("abc|");
+        model.replace_text(Utf16String::from("d"));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            // Half way through an operation which then panics
+            model.push_state_to_history(HistoryEntryKind::Typing);
+            model.state.dom.append_at_end_of_document(DomNode::new_text(
+                Utf16String::new(),
+            ));
+            model.state.start = 10.into();
+            panic!("Something went wrong");
+        }));
+        assert!(result.is_err());
+
+        let update = model.reset_to_last_good();
+        assert!(matches!(update.text_update, TextUpdate::ReplaceAll(_)));
+        assert_eq!(tx(&model), "abcd|");
+        model.undo();
+        assert_eq!(tx(&model), "abc|");
+    }
+
+    #[test]
+    fn invalid_undo_steps_are_skipped() {
+        let mut model = cm("abc|");
+        model.replace_text(Utf16String::from("d"));
+        model.push_state_to_history(HistoryEntryKind::Typing);
+        model.state.dom = Dom::new(vec![DomNode::new_text(Utf16String::new())]);
+        model.push_state_to_history(HistoryEntryKind::Typing);
+        model.state.dom = Dom::new(vec![DomNode::new_text("xy".into())]);
+        model.state.end = 5.into();
+
+        model.reset_to_last_good();
+        assert_eq!(tx(&model), "abcd|");
+        model.undo();
+        assert_eq!(tx(&model), "abc|");
+    }
+
+    #[test]
+    fn resetting_without_a_valid_undo_step_restores_the_initial_content() {
+        let mut model = cm("abc|");
+        model.state.end = 5.into();
+        model.reset_to_last_good();
+        assert_eq!(tx(&model), "abc|");
+    }
+
+    #[test]
+    fn resetting_a_model_in_a_good_state_keeps_its_content() {
+        let mut model = cm("<b>a{b</b>c}|");
+        model.reset_to_last_good();
+        assert_eq!(tx(&model), "<b>a{b</b>c}|");
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::dom::html_source::HtmlSource;
+use crate::dom::unicode_string::UnicodeStrExt;
+use crate::dom::DomLocation;
+use crate::{
+    ComposerModel, ComposerOperation, ComposerUpdate, Decoration,
+    DecorationKind, DomNode, HistoryEntryKind, Location, UnicodeString,
+};
+
+// Private use characters marking where each placeholder starts and ends
+// while the template is parsed and inserted.
+const PLACEHOLDER_START: char = '\u{E000}';
+const PLACEHOLDER_END: char = '\u{E001}';
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// Insert a template at the cursor, like [Self::replace_html]. Each
+    /// `{{label}}` in the template becomes a placeholder showing its label,
+    /// which is tracked as a [DecorationKind::Placeholder] decoration. The
+    /// first placeholder is selected, so typing replaces it.
+    pub fn insert_template(&mut self, html: S) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::InsertTemplate {
+            html: html.to_string(),
+        });
+        self.push_state_to_history(HistoryEntryKind::Paste);
+        let marked = mark_placeholders(&html.to_string());
+        self.do_replace_html(S::from(marked), HtmlSource::Matrix);
+
+        let placeholders = self.remove_placeholder_markers();
+        if let Some(&(start, end)) = placeholders.first() {
+            self.state.toggled_format_types.clear();
+            self.state.start = Location::from(start);
+            self.state.end = Location::from(end);
+        }
+        let update = self.create_update_replace_all();
+        for (start, end) in placeholders {
+            self.decorations.add(
+                DecorationKind::Placeholder,
+                Location::from(start),
+                Location::from(end),
+            );
+        }
+        update
+    }
+
+    /// Select the first placeholder after the selection, or the first one
+    /// in the content if there are none after it. Keeps the selection if
+    /// there are no placeholders left.
+    pub fn next_placeholder(&mut self) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::NextPlaceholder);
+        match self.placeholder_after_selection() {
+            Some(placeholder) => {
+                self.do_select(placeholder.start, placeholder.end)
+            }
+            None => ComposerUpdate::keep(),
+        }
+    }
+
+    /// Replace the selected placeholder (or the next one, if none is
+    /// selected) with text, then select the placeholder after it. Keeps
+    /// the content if there are no placeholders left.
+    pub fn fill_placeholder(&mut self, text: S) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::FillPlaceholder {
+            text: text.to_string(),
+        });
+        let (s, e) = self.safe_selection();
+        let Some(placeholder) = self
+            .placeholders()
+            .into_iter()
+            .find(|p| usize::from(p.start) <= s && e <= usize::from(p.end))
+            .or_else(|| self.placeholder_after_selection())
+        else {
+            return ComposerUpdate::keep();
+        };
+        let (start, end) = (placeholder.start.into(), placeholder.end.into());
+        let next = self
+            .placeholders()
+            .into_iter()
+            .find(|p| usize::from(p.start) >= end);
+
+        self.push_state_to_history(HistoryEntryKind::Typing);
+        let len = text.len();
+        self.state.start = placeholder.start;
+        self.state.end = placeholder.end;
+        self.do_replace_text_in(text, start, end);
+        if let Some(next) = next {
+            // Everything after the placeholder moved by the change in length
+            let moved = |location: Location| {
+                Location::from(usize::from(location) + len - (end - start))
+            };
+            self.state.start = moved(next.start);
+            self.state.end = moved(next.end);
+        }
+        self.create_update_replace_all()
+    }
+
+    fn placeholders(&self) -> Vec<Decoration> {
+        let mut placeholders: Vec<Decoration> = self
+            .decorations
+            .items
+            .iter()
+            .filter(|d| d.kind == DecorationKind::Placeholder)
+            .copied()
+            .collect();
+        placeholders.sort_by_key(|d| d.start);
+        placeholders
+    }
+
+    fn placeholder_after_selection(&self) -> Option<Decoration> {
+        let (_, e) = self.safe_selection();
+        let placeholders = self.placeholders();
+        placeholders
+            .iter()
+            .find(|p| usize::from(p.start) >= e)
+            .or(placeholders.first())
+            .copied()
+    }
+
+    /// Remove the markers around each placeholder from the text, returning
+    /// where the placeholders are once they have gone.
+    fn remove_placeholder_markers(&mut self) -> Vec<(usize, usize)> {
+        let dom = &mut self.state.dom;
+        let text_locations: Vec<DomLocation> = dom
+            .find_range(0, dom.text_len())
+            .locations
+            .into_iter()
+            .filter(|l| dom.lookup_node(&l.node_handle).is_text_node())
+            .collect();
+
+        let mut placeholders = Vec::new();
+        let mut start = None;
+        let mut removed = 0;
+        for location in text_locations {
+            let DomNode::Text(text_node) =
+                dom.lookup_node_mut(&location.node_handle)
+            else {
+                continue;
+            };
+            let data = text_node.data().to_string();
+            if !data.contains([PLACEHOLDER_START, PLACEHOLDER_END]) {
+                continue;
+            }
+            let mut offset = location.position - removed;
+            for c in data.chars() {
+                // Offsets count code units of S, so measure each char,
+                // including the markers
+                let len = S::from(c.to_string()).len();
+                match c {
+                    PLACEHOLDER_START => {
+                        start = Some(offset);
+                        removed += len;
+                    }
+                    PLACEHOLDER_END => {
+                        if let Some(start) = start.take() {
+                            placeholders.push((start, offset));
+                        }
+                        removed += len;
+                    }
+                    _ => offset += len,
+                }
+            }
+            text_node.set_data(S::from(
+                data.replace([PLACEHOLDER_START, PLACEHOLDER_END], ""),
+            ));
+        }
+        placeholders
+    }
+}
+
+/// Replace each `{{label}}` in the template by its label, between
+/// [PLACEHOLDER_START] and [PLACEHOLDER_END].
+fn mark_placeholders(template: &str) -> String {
+    let mut marked = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        let label_start = open + "{{".len();
+        let label_end = rest[label_start..]
+            .find(['{', '}'])
+            .map_or(rest.len(), |i| label_start + i);
+        if label_end > label_start && rest[label_end..].starts_with("}}") {
+            marked.push_str(&rest[..open]);
+            marked.push(PLACEHOLDER_START);
+            marked.push_str(&rest[label_start..label_end]);
+            marked.push(PLACEHOLDER_END);
+            rest = &rest[label_end + "}}".len()..];
+        } else {
+            marked.push_str(&rest[..=open]);
+            rest = &rest[open + 1..];
+        }
+    }
+    marked.push_str(rest);
+    marked
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::{DecorationKind, TextUpdate};
+
+    use super::{mark_placeholders, PLACEHOLDER_END, PLACEHOLDER_START};
+
+    fn placeholder_ranges(
+        model: &crate::ComposerModel<Utf16String>,
+    ) -> Vec<(usize, usize)> {
+        model
+            .decorations()
+            .iter()
+            .filter(|d| d.kind == DecorationKind::Placeholder)
+            .map(|d| (d.start.into(), d.end.into()))
+            .collect()
+    }
+
+    #[test]
+    fn inserting_a_template_selects_the_first_placeholder() {
+        let mut model = cm("|");
+        model.insert_template(Utf16String::from(
+            "Bug: {{title}} seen in <strong>{{version}}</strong>",
+        ));
+        assert_eq!(
+            tx(&model),
+            "Bug: {title}| seen in <strong>version</strong>"
+        );
+        assert_eq!(placeholder_ranges(&model), vec![(5, 10), (19, 26)]);
+    }
+
+    #[test]
+    fn placeholders_are_found_in_code_units_of_the_model() {
+        let mut model = crate::ComposerModel::<String>::new();
+        model.insert_template(String::from("{{é}} and <strong>{{b}}</strong>"));
+        // The markers are three bytes each in UTF-8
+        assert_eq!(
+            model
+                .decorations()
+                .iter()
+                .map(|d| (d.start.into(), d.end.into()))
+                .collect::<Vec<(usize, usize)>>(),
+            vec![(0, 2), (7, 8)]
+        );
+    }
+
+    #[test]
+    fn next_placeholder_moves_through_the_placeholders_and_wraps() {
+        let mut model = cm("|");
+        model.insert_template(Utf16String::from("{{a}} and {{b}}"));
+        model.next_placeholder();
+        assert_eq!(tx(&model), "a and {b}|");
+        model.next_placeholder();
+        assert_eq!(tx(&model), "{a}| and b");
+    }
+
+    #[test]
+    fn filling_a_placeholder_selects_the_next_one() {
+        let mut model = cm("|");
+        model.insert_template(Utf16String::from("{{name}}: {{reason}}"));
+        model.fill_placeholder(Utf16String::from("Alice"));
+        assert_eq!(tx(&model), "Alice: {reason}|");
+        model.fill_placeholder(Utf16String::from("holiday"));
+        assert_eq!(tx(&model), "Alice: holiday|");
+        assert!(placeholder_ranges(&model).is_empty());
+
+        let update = model.fill_placeholder(Utf16String::from("more"));
+        assert!(matches!(update.text_update, TextUpdate::Keep));
+    }
+
+    #[test]
+    fn typing_over_a_placeholder_removes_it() {
+        let mut model = cm("|");
+        model.insert_template(Utf16String::from("<p>{{a}}</p><p>{{b}}</p>"));
+        model.replace_text(Utf16String::from("x"));
+        assert_eq!(tx(&model), "<p>x|</p><p>b</p>");
+        assert_eq!(placeholder_ranges(&model), vec![(2, 3)]);
+    }
+
+    #[test]
+    fn undo_removes_the_whole_template() {
+        let mut model = cm("abc|");
+        model.insert_template(Utf16String::from("{{a}} and {{b}}"));
+        model.undo();
+        assert_eq!(tx(&model), "abc|");
+    }
+
+    #[test]
+    fn only_labels_between_double_braces_are_marked() {
+        assert_eq!(
+            mark_placeholders("{{{a}} {{}} {{b {{c}}}"),
+            format!(
+                "{{{PLACEHOLDER_START}a{PLACEHOLDER_END} {{{{}}}} \
+                {{{{b {PLACEHOLDER_START}c{PLACEHOLDER_END}}}"
+            )
+        );
+    }
+}
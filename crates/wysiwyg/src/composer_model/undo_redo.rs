@@ -4,36 +4,287 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use crate::{ComposerModel, ComposerUpdate, UnicodeString};
+use crate::composer_model::snapshots::Snapshot;
+use crate::{
+    ComposerAction, ComposerModel, ComposerOperation, ComposerState,
+    ComposerUpdate, UnicodeString,
+};
+
+/// The kind of operation which created an undo step.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HistoryEntryKind {
+    Typing,
+    Deletion,
+    NewLine,
+    Formatting,
+    List,
+    Indent,
+    Link,
+    Mention,
+    Paste,
+    Move,
+}
+
+/// A summary of one undo step, for showing an undo history menu.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HistoryEntry {
+    pub kind: HistoryEntryKind,
+    /// How many code units the step added to the content (or removed, if
+    /// negative)
+    pub length_delta: isize,
+    /// When the step was made, in milliseconds since the Unix epoch, see
+    /// [crate::ComposerModelConfig::with_clock]
+    pub timestamp_millis: u64,
+    /// True if the step has been undone, and can be redone
+    pub is_undone: bool,
+}
+
+/// What we remember about each state in the undo and redo stacks, apart
+/// from the state itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct HistoryRecord {
+    kind: HistoryEntryKind,
+    timestamp_millis: u64,
+}
 
 impl<S> ComposerModel<S>
 where
     S: UnicodeString,
 {
     pub fn undo(&mut self) -> ComposerUpdate<S> {
-        if let Some(prev) = self.previous_states.pop() {
-            self.next_states.push(self.state.clone());
-            self.state = prev;
-            self.create_update_replace_all()
-        } else {
-            ComposerUpdate::keep()
-        }
+        self.undo_steps(1)
     }
 
     pub fn redo(&mut self) -> ComposerUpdate<S> {
-        if let Some(next) = self.next_states.pop() {
-            self.previous_states.push(self.state.clone());
-            self.state = next;
-            self.create_update_replace_all()
+        self.redo_steps(1)
+    }
+
+    /// Undo up to `steps` steps at once, stopping early if there are no
+    /// more to undo.
+    pub fn undo_steps(&mut self, steps: usize) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::Undo { steps });
+        if self.action_is_disallowed(ComposerAction::Undo) {
+            return ComposerUpdate::keep();
+        }
+        let mut changed = false;
+        for _ in 0..steps {
+            let Some(prev) = self.previous_states.pop() else {
+                break;
+            };
+            self.next_states
+                .push(std::mem::replace(&mut self.state, prev));
+            if let Some(record) = self.previous_records.pop() {
+                self.next_records.push(record);
+            }
+            changed = true;
+        }
+        if !changed {
+            ComposerUpdate::keep()
+        } else if self.config.range_updates {
+            self.create_update_replace_range()
         } else {
+            self.create_update_replace_all()
+        }
+    }
+
+    /// Redo up to `steps` steps at once, stopping early if there are no
+    /// more to redo.
+    pub fn redo_steps(&mut self, steps: usize) -> ComposerUpdate<S> {
+        let _span = self.start_operation(ComposerOperation::Redo { steps });
+        if self.action_is_disallowed(ComposerAction::Redo) {
+            return ComposerUpdate::keep();
+        }
+        let mut changed = false;
+        for _ in 0..steps {
+            let Some(next) = self.next_states.pop() else {
+                break;
+            };
+            self.previous_states
+                .push(std::mem::replace(&mut self.state, next));
+            if let Some(record) = self.next_records.pop() {
+                self.previous_records.push(record);
+            }
+            changed = true;
+        }
+        if !changed {
             ComposerUpdate::keep()
+        } else if self.config.range_updates {
+            self.create_update_replace_range()
+        } else {
+            self.create_update_replace_all()
         }
     }
 
-    pub(crate) fn push_state_to_history(&mut self) {
+    /// Every step in the undo history, oldest first. Steps which have been
+    /// undone come last, with `is_undone` set.
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        // All the states in order, with the current one in the middle
+        let states: Vec<&ComposerState<S>> = self
+            .previous_states
+            .iter()
+            .chain(std::iter::once(&self.state))
+            .chain(self.next_states.iter().rev())
+            .collect();
+        let records = self
+            .previous_records
+            .iter()
+            .chain(self.next_records.iter().rev());
+        let undone_from = self.previous_records.len();
+        records
+            .enumerate()
+            .map(|(i, record)| {
+                let before = states[i].dom.text_len() as isize;
+                let after = states[i + 1].dom.text_len() as isize;
+                HistoryEntry {
+                    kind: record.kind,
+                    length_delta: after - before,
+                    timestamp_millis: record.timestamp_millis,
+                    is_undone: i >= undone_from,
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn push_state_to_history(&mut self, kind: HistoryEntryKind) {
         // Clear future events as they're no longer valid
         self.next_states.clear();
+        self.next_records.clear();
         // Store a copy of the current state in the previous_states
         self.previous_states.push(self.state.clone());
+        self.previous_records.push(HistoryRecord {
+            kind,
+            timestamp_millis: self.config.clock().unwrap_or(now_millis)(),
+        });
+    }
+
+    /// Replace the undo steps made since `before` was taken with one step,
+    /// of the same kind as the first of them, so that undo reverts them all
+    /// at once.
+    pub(crate) fn merge_history_since(&mut self, before: Snapshot<S>) {
+        let kind = self
+            .previous_records
+            .get(before.undo_depth())
+            .map(|record| record.kind);
+        let state = self.state.clone();
+        before.restore(self);
+        if let Some(kind) = kind {
+            self.push_state_to_history(kind);
+        }
+        self.state = state;
+    }
+
+    pub(crate) fn clear_history(&mut self) {
+        self.previous_states.clear();
+        self.next_states.clear();
+        self.previous_records.clear();
+        self.next_records.clear();
+    }
+}
+
+fn now_millis() -> u64 {
+    cfg_if::cfg_if! {
+        if #[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))] {
+            js::now() as u64
+        } else if #[cfg(all(target_arch = "wasm32", target_os = "unknown"))] {
+            // There is no clock to read without JS, and std::time panics
+            0
+        } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0)
+        }
+    }
+}
+
+#[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))]
+mod js {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        // std::time is not available in the browser
+        #[wasm_bindgen(js_namespace = Date)]
+        pub fn now() -> f64;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::{cm, tx};
+    use crate::{ComposerModelConfig, HistoryEntryKind, TextUpdate};
+
+    #[test]
+    fn undo_steps_undoes_several_steps_at_once() {
+        let mut model = cm("|");
+        model.replace_text(Utf16String::from("a"));
+        model.replace_text(Utf16String::from("b"));
+        model.replace_text(Utf16String::from("c"));
+        model.undo_steps(2);
+        assert_eq!(tx(&model), "a|");
+        model.redo_steps(5);
+        assert_eq!(tx(&model), "abc|");
+    }
+
+    #[test]
+    fn undo_steps_with_nothing_to_undo_keeps() {
+        let mut model = cm("abc|");
+        let update = model.undo_steps(3);
+        assert!(matches!(update.text_update, TextUpdate::Keep));
+        let update = model.redo_steps(0);
+        assert!(matches!(update.text_update, TextUpdate::Keep));
+    }
+
+    #[test]
+    fn history_summarises_each_step() {
+        let mut model = cm("|");
+        model.replace_text(Utf16String::from("abc"));
+        model.select(0.into(), 3.into());
+        model.bold();
+        model.backspace();
+        model.undo();
+
+        let history = model.history();
+        let summary: Vec<(HistoryEntryKind, isize, bool)> = history
+            .iter()
+            .map(|e| (e.kind, e.length_delta, e.is_undone))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (HistoryEntryKind::Typing, 3, false),
+                (HistoryEntryKind::Formatting, 0, false),
+                (HistoryEntryKind::Deletion, -3, true),
+            ]
+        );
+        assert!(history.iter().all(|e| e.timestamp_millis > 0));
+    }
+
+    #[test]
+    fn history_is_timed_by_the_configured_clock() {
+        let mut model = cm("|");
+        model.set_config(ComposerModelConfig::default().with_clock(|| 42));
+        model.replace_text(Utf16String::from("a"));
+        model.enter();
+        let timestamps: Vec<u64> =
+            model.history().iter().map(|e| e.timestamp_millis).collect();
+        assert_eq!(timestamps, vec![42, 42]);
+    }
+
+    #[test]
+    fn new_steps_drop_undone_history() {
+        let mut model = cm("|");
+        model.replace_text(Utf16String::from("a"));
+        model.replace_text(Utf16String::from("b"));
+        model.undo();
+        model.enter();
+        let kinds: Vec<HistoryEntryKind> =
+            model.history().iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![HistoryEntryKind::Typing, HistoryEntryKind::NewLine]
+        );
     }
 }
@@ -0,0 +1,452 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::composer_model::delete_text::Direction;
+use crate::composer_model::extend_selection::{
+    skip_while, unit_next_to, units_in, UnitKind,
+};
+use crate::dom::nodes::dom_node::DomNodeKind;
+use crate::dom::nodes::text_node::CharType;
+use crate::dom::nodes::{ContainerNode, ContainerNodeKind, TextNode};
+use crate::dom::unicode_string::UnicodeStr;
+use crate::{
+    ComposerModel, DomNode, InlineFormatType, Location, ToRawText,
+    UnicodeString,
+};
+
+/// A word in the content, as a spellchecker should see it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Word<S>
+where
+    S: UnicodeString,
+{
+    pub text: S,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl<S> ComposerModel<S>
+where
+    S: UnicodeString,
+{
+    /// The words overlapping the content between start and end, in
+    /// document order. If start and end are equal, this is the word
+    /// touching that position, if any.
+    ///
+    /// Text which is not prose is left out: code blocks, inline code, the
+    /// text of links which just show their URL, and mentions. Words never
+    /// continue across line breaks, mentions or block boundaries, but do
+    /// continue across formatting, so `"sp<b>ell</b>"` is a single word.
+    ///
+    /// Only the top-level blocks touching the range are read, so this stays
+    /// cheap for a small range in a long document.
+    pub fn words_in_range(
+        &self,
+        start: Location,
+        end: Location,
+    ) -> impl Iterator<Item = Word<S>> {
+        let (start, end) = (start.min(end), start.max(end));
+        let mut collector = WordCollector::default();
+        // Words never continue across blocks, so only the top-level blocks
+        // touching the range are read, or runs of top-level inline nodes,
+        // which a word can continue across.
+        let children = self.state.dom.document().children();
+        let mut first = 0;
+        let mut offset = 0;
+        while first < children.len() {
+            let mut last = first;
+            let mut len = children[first].text_len();
+            if !children[first].is_block_node() {
+                while children
+                    .get(last + 1)
+                    .is_some_and(|next| !next.is_block_node())
+                {
+                    last += 1;
+                    len += children[last].text_len();
+                }
+            }
+            if offset <= end.into() && start <= Location::from(offset + len) {
+                collector.offset = offset;
+                for (child, next) in
+                    children[first..=last].iter().zip(first + 1..)
+                {
+                    collector.visit_node(child, next < children.len());
+                }
+                collector.finish_word();
+            }
+            offset += len;
+            if children[last].is_block_node() && last + 1 < children.len() {
+                offset += 1;
+            }
+            first = last + 1;
+        }
+        collector.words.into_iter().filter(move |word| {
+            if start == end {
+                word.start <= start && start <= word.end
+            } else {
+                word.start < end && start < word.end
+            }
+        })
+    }
+
+    /// The word touching the cursor, e.g. to offer completions for it.
+    ///
+    /// Words are split as [Self::backspace_word] splits them: a run of
+    /// punctuation or of other characters, which stops at whitespace, line
+    /// breaks, mentions and block boundaries. If the cursor is between two
+    /// runs, the one before it is used. Returns None if there is a
+    /// selection, the cursor is not touching a word, or it is in code.
+    pub fn get_word_at_cursor(&self) -> Option<Word<S>> {
+        let (s, e) = self.safe_selection();
+        if s != e {
+            return None;
+        }
+        let dom = &self.state.dom;
+        let in_code = dom.find_range(s, e).locations.iter().any(|l| {
+            matches!(
+                l.kind,
+                DomNodeKind::CodeBlock
+                    | DomNodeKind::Formatting(InlineFormatType::InlineCode)
+            )
+        });
+        if in_code {
+            return None;
+        }
+
+        let units = units_in(dom.document());
+        let kind = [Direction::Backwards, Direction::Forwards]
+            .iter()
+            .filter_map(|direction| unit_next_to(&units, s, direction))
+            .map(|unit| unit.kind)
+            .find(|kind| {
+                matches!(kind, UnitKind::Char(t) if *t != CharType::Whitespace)
+            })?;
+        let start =
+            skip_while(&units, s, &Direction::Backwards, |u| u.kind == kind);
+        let end =
+            skip_while(&units, s, &Direction::Forwards, |u| u.kind == kind);
+
+        let mut text = String::new();
+        for location in dom.find_range(start, end).locations {
+            if let DomNode::Text(node) = dom.lookup_node(&location.node_handle)
+            {
+                text.push_str(
+                    &node.data()[location.start_offset..location.end_offset]
+                        .to_string(),
+                );
+            }
+        }
+        Some(Word {
+            text: S::from(text),
+            start: Location::from(start),
+            end: Location::from(end),
+        })
+    }
+}
+
+struct WordCollector<S>
+where
+    S: UnicodeString,
+{
+    words: Vec<Word<S>>,
+    offset: usize,
+    current: Option<PartialWord>,
+}
+
+/// The word being collected. Apostrophes are only part of a word if they
+/// are followed by more of it, so the text and end position are only
+/// committed after a word character.
+struct PartialWord {
+    text: String,
+    start: usize,
+    committed_len: usize,
+    committed_end: usize,
+}
+
+impl<S> Default for WordCollector<S>
+where
+    S: UnicodeString,
+{
+    fn default() -> Self {
+        Self {
+            words: Vec::new(),
+            offset: 0,
+            current: None,
+        }
+    }
+}
+
+impl<S> WordCollector<S>
+where
+    S: UnicodeString,
+{
+    fn visit_container(&mut self, container: &ContainerNode<S>) {
+        let children = container.children();
+        for (i, child) in children.iter().enumerate() {
+            self.visit_node(child, i + 1 < children.len());
+        }
+    }
+
+    fn visit_node(&mut self, node: &DomNode<S>, has_next_sibling: bool) {
+        match node {
+            DomNode::Container(c) => {
+                if c.is_block_node() {
+                    self.finish_word();
+                }
+                if is_skipped(c) {
+                    self.finish_word();
+                    self.offset += c.text_len();
+                } else {
+                    self.visit_container(c);
+                }
+                if c.is_block_node() {
+                    self.finish_word();
+                    if has_next_sibling {
+                        self.offset += 1;
+                    }
+                }
+            }
+            DomNode::Text(t) => self.visit_text(t),
+            DomNode::LineBreak(_) | DomNode::Mention(_) => {
+                self.finish_word();
+                self.offset += node.text_len();
+            }
+        }
+    }
+
+    fn visit_text(&mut self, text: &TextNode<S>) {
+        let data = text.data();
+        for c in data.chars() {
+            let len = data.char_len(&c);
+            if c.is_alphanumeric() {
+                let word = self.current.get_or_insert(PartialWord {
+                    text: String::new(),
+                    start: self.offset,
+                    committed_len: 0,
+                    committed_end: self.offset,
+                });
+                word.text.push(c);
+                word.committed_len = word.text.len();
+                word.committed_end = self.offset + len;
+            } else if is_apostrophe(c) && self.current.is_some() {
+                if let Some(word) = self.current.as_mut() {
+                    word.text.push(c);
+                }
+            } else {
+                self.finish_word();
+            }
+            self.offset += len;
+        }
+    }
+
+    fn finish_word(&mut self) {
+        if let Some(mut word) = self.current.take() {
+            word.text.truncate(word.committed_len);
+            self.words.push(Word {
+                text: S::from(word.text),
+                start: Location::from(word.start),
+                end: Location::from(word.committed_end),
+            });
+        }
+    }
+}
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '\u{2019}'
+}
+
+/// Whether the text inside this container should be hidden from a
+/// spellchecker.
+fn is_skipped<S>(container: &ContainerNode<S>) -> bool
+where
+    S: UnicodeString,
+{
+    match container.kind() {
+        ContainerNodeKind::CodeBlock => true,
+        ContainerNodeKind::Formatting(InlineFormatType::InlineCode) => true,
+        ContainerNodeKind::Link(url) => container.to_raw_text() == *url,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::cm;
+    use crate::{ComposerModel, Location};
+
+    fn words(
+        model: &ComposerModel<Utf16String>,
+    ) -> Vec<(String, usize, usize)> {
+        let len = model.state.dom.text_len();
+        model
+            .words_in_range(Location::from(0), Location::from(len))
+            .map(|w| (w.text.to_string(), w.start.into(), w.end.into()))
+            .collect()
+    }
+
+    #[test]
+    fn words_are_split_on_whitespace_and_punctuation() {
+        let model = cm("Hello, wrld! Don't|");
+        assert_eq!(
+            words(&model),
+            vec![
+                ("Hello".into(), 0, 5),
+                ("wrld".into(), 7, 11),
+                ("Don't".into(), 13, 18),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_apostrophes_are_not_part_of_a_word() {
+        let model = cm("'quoted' dogs' |");
+        assert_eq!(
+            words(&model),
+            vec![("quoted".into(), 1, 7), ("dogs".into(), 9, 13)]
+        );
+    }
+
+    #[test]
+    fn words_continue_across_formatting() {
+        let model = cm("sp<b>ell</b>ing wrd|");
+        assert_eq!(
+            words(&model),
+            vec![("spelling".into(), 0, 8), ("wrd".into(), 9, 12)]
+        );
+    }
+
+    #[test]
+    fn words_stop_at_line_breaks_and_blocks() {
+        let model = cm("<p>one</p><p>two<br />three|</p>");
+        assert_eq!(
+            words(&model),
+            vec![
+                ("one".into(), 0, 3),
+                ("two".into(), 4, 7),
+                ("three".into(), 8, 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_and_url_links_are_skipped() {
+        let model = cm("a <code>fn</code> \
+            <a href=\"https://x.org\">https://x.org</a> \
+            <a href=\"https://x.org\">lnk</a> b|");
+        assert_eq!(
+            words(&model),
+            vec![
+                ("a".into(), 0, 1),
+                ("lnk".into(), 19, 22),
+                ("b".into(), 23, 24)
+            ]
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_skipped() {
+        let model = cm("<pre><code>let x</code></pre><p>abc|</p>");
+        assert_eq!(words(&model), vec![("abc".into(), 6, 9)]);
+    }
+
+    #[test]
+    fn mentions_are_skipped_and_split_words() {
+        let model = cm(
+            "a<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>b|",
+        );
+        assert_eq!(words(&model), vec![("a".into(), 0, 1), ("b".into(), 2, 3)]);
+    }
+
+    #[test]
+    fn only_words_in_the_range_are_returned() {
+        let model = cm("one two three|");
+        let found: Vec<String> = model
+            .words_in_range(Location::from(5), Location::from(9))
+            .map(|w| w.text.to_string())
+            .collect();
+        assert_eq!(found, vec!["two", "three"]);
+        let found: Vec<String> = model
+            .words_in_range(Location::from(7), Location::from(7))
+            .map(|w| w.text.to_string())
+            .collect();
+        assert_eq!(found, vec!["two"]);
+        let found: Vec<String> = model
+            .words_in_range(Location::from(3), Location::from(4))
+            .map(|w| w.text.to_string())
+            .collect();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn words_in_range_reads_only_the_blocks_touching_it() {
+        let model = cm("<p>one two</p><p>three <b>fo</b>ur</p><p>five|</p>");
+        let found: Vec<(String, usize, usize)> = model
+            .words_in_range(Location::from(10), Location::from(13))
+            .map(|w| (w.text.to_string(), w.start.into(), w.end.into()))
+            .collect();
+        assert_eq!(found, vec![("three".to_owned(), 8, 13)]);
+        let found: Vec<String> = model
+            .words_in_range(Location::from(7), Location::from(7))
+            .map(|w| w.text.to_string())
+            .collect();
+        assert_eq!(found, vec!["two"]);
+        let found: Vec<String> = model
+            .words_in_range(Location::from(15), Location::from(20))
+            .map(|w| w.text.to_string())
+            .collect();
+        assert_eq!(found, vec!["four", "five"]);
+    }
+
+    fn word_at_cursor(html: &str) -> Option<(String, usize, usize)> {
+        cm(html)
+            .get_word_at_cursor()
+            .map(|w| (w.text.to_string(), w.start.into(), w.end.into()))
+    }
+
+    #[test]
+    fn word_at_cursor_covers_the_run_around_the_cursor() {
+        assert_eq!(word_at_cursor("one tw|o"), Some(("two".into(), 4, 7)));
+        assert_eq!(
+            word_at_cursor("one <b>t</b>w|o"),
+            Some(("two".into(), 4, 7))
+        );
+        assert_eq!(word_at_cursor("one |two"), Some(("two".into(), 4, 7)));
+        assert_eq!(word_at_cursor("one | two"), None);
+    }
+
+    #[test]
+    fn word_at_cursor_prefers_the_run_before_the_cursor() {
+        assert_eq!(
+            word_at_cursor("say :smile|:"),
+            Some(("smile".into(), 5, 10))
+        );
+        assert_eq!(word_at_cursor("say :|smile"), Some((":".into(), 4, 5)));
+    }
+
+    #[test]
+    fn word_at_cursor_stops_at_mentions() {
+        assert_eq!(
+            word_at_cursor(
+                "<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>ab|"
+            ),
+            Some(("ab".into(), 1, 3))
+        );
+    }
+
+    #[test]
+    fn there_is_no_word_at_cursor_in_code_or_with_a_selection() {
+        assert_eq!(word_at_cursor("a <code>fn|</code>"), None);
+        assert_eq!(word_at_cursor("<pre><code>let x|</code></pre>"), None);
+        assert_eq!(word_at_cursor("{abc}|"), None);
+    }
+
+    #[test]
+    fn offsets_are_in_code_units() {
+        let model = cm("😀 héllo|");
+        assert_eq!(words(&model), vec![("héllo".into(), 3, 8)]);
+    }
+}
@@ -0,0 +1,709 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::collections::{HashMap, HashSet};
+
+use url::Url;
+
+use crate::dom::parser::DEFAULT_MAX_PARSE_DEPTH;
+use crate::dom::{
+    AtRoomDetection, FormattingTags, MarkdownEscaping, MarkdownMentions,
+    PlaceholderPolicy, WhitespacePolicy,
+};
+use crate::{
+    AttributePolicy, BlockBackspaceBehaviour, ComposerAction, InlineFormatType,
+    PatternKey,
+};
+
+/// What pressing enter in an empty list item does.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ListExitRule {
+    /// The item is removed and the cursor leaves the list, or moves to the
+    /// parent list if the list is nested
+    #[default]
+    OnEmptyItem,
+    /// Another item is added, so the list can only be left by toggling it
+    /// off
+    Never,
+}
+
+/// What pressing enter with the cursor in inline code does.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum InlineCodeExitRule {
+    /// The new line starts outside of the inline code, so any text typed
+    /// there is not code
+    #[default]
+    OnEnter,
+    /// The new line starts in inline code too, as the formatting carries
+    /// over like any other
+    Never,
+}
+
+/// The behavioural options of a [crate::ComposerModel], passed to
+/// [crate::ComposerModel::with_config] or
+/// [crate::ComposerModel::set_config].
+///
+/// ```
+/// use wysiwyg::{ComposerModelConfig, ListExitRule};
+///
+/// let config = ComposerModelConfig::default()
+///     .with_list_exit_rule(ListExitRule::Never)
+///     .with_max_length(Some(500));
+/// assert_eq!(config.max_length(), Some(500));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ComposerModelConfig {
+    pub(crate) list_exit_rule: ListExitRule,
+    pub(crate) inline_code_exit_rule: InlineCodeExitRule,
+    pub(crate) auto_link: bool,
+    pub(crate) link_pasted_urls: bool,
+    pub(crate) at_room_detection: bool,
+    pub(crate) at_room_detection_in_quotes: bool,
+    pub(crate) can_notify_room: bool,
+    pub(crate) max_length: Option<usize>,
+    pub(crate) max_paste_nodes: Option<usize>,
+    pub(crate) max_paste_length: Option<usize>,
+    pub(crate) max_paste_list_depth: Option<usize>,
+    pub(crate) markdown_shortcuts: bool,
+    pub(crate) allowed_link_schemes: Option<Vec<String>>,
+    pub(crate) disallowed_actions: HashSet<ComposerAction>,
+    pub(crate) lenient_parsing: bool,
+    pub(crate) plain_text_lists: bool,
+    pub(crate) range_updates: bool,
+    pub(crate) block_ids: bool,
+    pub(crate) menu_state_deltas: bool,
+    pub(crate) normalize_typed_nbsp: bool,
+    pub(crate) strip_invisible_characters: bool,
+    pub(crate) record_operations: bool,
+    pub(crate) clock: Option<Clock>,
+    pub(crate) styles: HashMap<String, Vec<InlineFormatType>>,
+    pub(crate) suggestion_triggers: HashMap<char, PatternKey>,
+    pub(crate) max_parse_depth: usize,
+    pub(crate) block_backspace_behaviour: BlockBackspaceBehaviour,
+    pub(crate) attribute_policy: AttributePolicy,
+    pub(crate) formatting_tags: FormattingTags,
+    pub(crate) strip_message_styles: bool,
+    pub(crate) isolate_message_bidi: bool,
+    pub(crate) whitespace_policy: WhitespacePolicy,
+    pub(crate) placeholder_policy: PlaceholderPolicy,
+    pub(crate) markdown_escaping: MarkdownEscaping,
+    pub(crate) markdown_mentions: MarkdownMentions,
+}
+
+/// The function set by [ComposerModelConfig::with_clock].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Clock(pub(crate) fn() -> u64);
+
+impl PartialEq for Clock {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::fn_addr_eq(self.0, other.0)
+    }
+}
+
+impl Eq for Clock {}
+
+impl Default for ComposerModelConfig {
+    fn default() -> Self {
+        Self {
+            list_exit_rule: ListExitRule::default(),
+            inline_code_exit_rule: InlineCodeExitRule::default(),
+            auto_link: false,
+            link_pasted_urls: false,
+            at_room_detection: true,
+            at_room_detection_in_quotes: true,
+            can_notify_room: true,
+            max_length: None,
+            max_paste_nodes: None,
+            max_paste_length: None,
+            max_paste_list_depth: None,
+            markdown_shortcuts: false,
+            allowed_link_schemes: None,
+            disallowed_actions: HashSet::new(),
+            lenient_parsing: false,
+            plain_text_lists: false,
+            range_updates: false,
+            block_ids: false,
+            menu_state_deltas: false,
+            normalize_typed_nbsp: false,
+            strip_invisible_characters: false,
+            record_operations: false,
+            clock: None,
+            styles: HashMap::new(),
+            suggestion_triggers: PatternKey::default_triggers(),
+            max_parse_depth: DEFAULT_MAX_PARSE_DEPTH,
+            block_backspace_behaviour: BlockBackspaceBehaviour::default(),
+            attribute_policy: AttributePolicy::default(),
+            formatting_tags: FormattingTags::default(),
+            strip_message_styles: false,
+            isolate_message_bidi: false,
+            whitespace_policy: WhitespacePolicy::default(),
+            placeholder_policy: PlaceholderPolicy::default(),
+            markdown_escaping: MarkdownEscaping::default(),
+            markdown_mentions: MarkdownMentions::default(),
+        }
+    }
+}
+
+impl ComposerModelConfig {
+    /// Set what pressing enter in an empty list item does.
+    pub fn with_list_exit_rule(mut self, list_exit_rule: ListExitRule) -> Self {
+        self.list_exit_rule = list_exit_rule;
+        self
+    }
+
+    /// Set what pressing enter with the cursor in inline code does.
+    pub fn with_inline_code_exit_rule(
+        mut self,
+        inline_code_exit_rule: InlineCodeExitRule,
+    ) -> Self {
+        self.inline_code_exit_rule = inline_code_exit_rule;
+        self
+    }
+
+    /// Set whether typing whitespace right after an http(s) URL turns it
+    /// into a link.
+    pub fn with_auto_link(mut self, auto_link: bool) -> Self {
+        self.auto_link = auto_link;
+        self
+    }
+
+    /// Set whether pasting a URL with [crate::ComposerModel::paste_text]
+    /// makes the selected text a link to it, or inserts it as a link if
+    /// nothing is selected.
+    pub fn with_link_pasted_urls(mut self, link_pasted_urls: bool) -> Self {
+        self.link_pasted_urls = link_pasted_urls;
+        self
+    }
+
+    /// Set whether `@room` in parsed HTML or Markdown becomes an @room
+    /// mention, along with links written for @room mentions. When false,
+    /// they are kept as text and links, so that content can't notify a
+    /// whole room, e.g. in a direct message. Mentions added by
+    /// [crate::ComposerModel::insert_at_room_mention] are not affected.
+    pub fn with_at_room_detection(mut self, at_room_detection: bool) -> Self {
+        self.at_room_detection = at_room_detection;
+        self
+    }
+
+    /// Set whether `@room` inside quotes becomes an @room mention when HTML
+    /// is parsed. When false, quoting a message which notified the room
+    /// doesn't notify it again.
+    pub fn with_at_room_detection_in_quotes(
+        mut self,
+        at_room_detection_in_quotes: bool,
+    ) -> Self {
+        self.at_room_detection_in_quotes = at_room_detection_in_quotes;
+        self
+    }
+
+    /// Set whether the user may notify the whole room. When they can't,
+    /// `@room` is never turned into an @room mention when HTML is parsed.
+    pub fn with_can_notify_room(mut self, can_notify_room: bool) -> Self {
+        self.can_notify_room = can_notify_room;
+        self
+    }
+
+    /// Set the length, in code units, which typed text can't make the
+    /// content longer than, or None for no limit.
+    pub fn with_max_length(mut self, max_length: Option<usize>) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Set the number of nodes, e.g. paragraphs, text and formatting, which
+    /// pasted HTML is cut down to, or None for no limit.
+    pub fn with_max_paste_nodes(
+        mut self,
+        max_paste_nodes: Option<usize>,
+    ) -> Self {
+        self.max_paste_nodes = max_paste_nodes;
+        self
+    }
+
+    /// Set the length, in code units, which the text of pasted HTML is cut
+    /// down to, or None for no limit.
+    pub fn with_max_paste_length(
+        mut self,
+        max_paste_length: Option<usize>,
+    ) -> Self {
+        self.max_paste_length = max_paste_length;
+        self
+    }
+
+    /// Set how deeply lists in pasted HTML may be nested, leaving out any
+    /// nested deeper, or None for no limit. Lists which aren't inside
+    /// another list are at depth 1.
+    pub fn with_max_paste_list_depth(
+        mut self,
+        max_paste_list_depth: Option<usize>,
+    ) -> Self {
+        self.max_paste_list_depth = max_paste_list_depth;
+        self
+    }
+
+    /// Set whether typing a space after `-` or `*` at the start of a
+    /// paragraph makes a bulleted list, after `1.` a numbered list, and
+    /// after `>` a quote.
+    pub fn with_markdown_shortcuts(mut self, markdown_shortcuts: bool) -> Self {
+        self.markdown_shortcuts = markdown_shortcuts;
+        self
+    }
+
+    /// Set the schemes, e.g. `https` or `mailto`, which links may be set
+    /// with, or None to allow any scheme.
+    pub fn with_allowed_link_schemes(
+        mut self,
+        allowed_link_schemes: Option<Vec<String>>,
+    ) -> Self {
+        self.allowed_link_schemes = allowed_link_schemes;
+        self
+    }
+
+    /// Set the actions which this deployment doesn't allow, e.g.
+    /// [ComposerAction::Underline]. They are reported as
+    /// [crate::ActionState::Disabled], calling them does nothing, and the
+    /// formatting, links or blocks they would create are replaced by their
+    /// content when HTML is parsed.
+    pub fn with_disallowed_actions(
+        mut self,
+        disallowed_actions: HashSet<ComposerAction>,
+    ) -> Self {
+        self.disallowed_actions = disallowed_actions;
+        self
+    }
+
+    /// Set whether unsupported elements in Matrix HTML passed to
+    /// [crate::ComposerModel::set_content_from_html] are skipped, keeping
+    /// their content, instead of failing. What was skipped is reported by
+    /// [crate::ComposerModel::parse_warnings].
+    pub fn with_lenient_parsing(mut self, lenient_parsing: bool) -> Self {
+        self.lenient_parsing = lenient_parsing;
+        self
+    }
+
+    /// Set whether lines of inserted plain text which start with `-`, `*`
+    /// or a number and `.`, followed by a space, become list items, as if
+    /// they were typed with Markdown shortcuts.
+    pub fn with_plain_text_lists(mut self, plain_text_lists: bool) -> Self {
+        self.plain_text_lists = plain_text_lists;
+        self
+    }
+
+    /// Set whether undo and redo report only the top-level nodes they
+    /// changed, as a [crate::TextUpdate::ReplaceRange], instead of
+    /// replacing all of the content, which is cheaper for clients showing
+    /// long documents.
+    pub fn with_range_updates(mut self, range_updates: bool) -> Self {
+        self.range_updates = range_updates;
+        self
+    }
+
+    /// Set whether each top-level block in the HTML of updates and of
+    /// [crate::ComposerModel::get_content_as_html] has a `data-block-id`
+    /// attribute, which stays the same while the block is edited, so that
+    /// clients can key the elements they render blocks with.
+    pub fn with_block_ids(mut self, block_ids: bool) -> Self {
+        self.block_ids = block_ids;
+        self
+    }
+
+    /// Set whether each [crate::MenuState::Update] holds only the actions
+    /// whose state changed since the previous update, as
+    /// [crate::MenuStateUpdate::between] finds, rather than every action.
+    /// Clients then merge each update into the states they already have,
+    /// starting from [crate::ComposerModel::action_states].
+    pub fn with_menu_state_deltas(mut self, menu_state_deltas: bool) -> Self {
+        self.menu_state_deltas = menu_state_deltas;
+        self
+    }
+
+    /// Set whether non-breaking spaces in typed text, e.g. those some
+    /// keyboards insert after punctuation, become plain spaces. Text typed
+    /// into code is kept as it was typed.
+    pub fn with_normalize_typed_nbsp(
+        mut self,
+        normalize_typed_nbsp: bool,
+    ) -> Self {
+        self.normalize_typed_nbsp = normalize_typed_nbsp;
+        self
+    }
+
+    /// Set whether invisible characters which split words and mentions
+    /// apart, e.g. soft hyphens, zero-width spaces and directional marks,
+    /// are removed from pasted text and HTML. What was removed is reported
+    /// by [crate::ComposerUpdate::stripped_characters].
+    pub fn with_strip_invisible_characters(
+        mut self,
+        strip_invisible_characters: bool,
+    ) -> Self {
+        self.strip_invisible_characters = strip_invisible_characters;
+        self
+    }
+
+    /// Set whether the model keeps a log of the operations made on it, see
+    /// [crate::ComposerModel::operation_log]. Setting the content from HTML
+    /// or Markdown starts the log again, so it doesn't grow for as long as
+    /// the model is kept.
+    pub fn with_record_operations(mut self, record_operations: bool) -> Self {
+        self.record_operations = record_operations;
+        self
+    }
+
+    /// Set the function which gives the current time, in milliseconds since
+    /// the Unix epoch, for [crate::HistoryEntry::timestamp_millis]. By
+    /// default the system clock is used, or `Date.now()` in the browser
+    /// with the `js` feature. Builds for `wasm32-unknown-unknown` without
+    /// it have no clock to read, so their timestamps are 0 unless one is
+    /// set here.
+    pub fn with_clock(mut self, clock: fn() -> u64) -> Self {
+        self.clock = Some(Clock(clock));
+        self
+    }
+
+    /// Define a named style, e.g. `warning`, as the formats which
+    /// [crate::ComposerModel::apply_style] turns on or off together,
+    /// replacing any style already defined with the name.
+    pub fn with_style(
+        mut self,
+        name: impl Into<String>,
+        formats: Vec<InlineFormatType>,
+    ) -> Self {
+        self.styles.insert(name.into(), formats);
+        self
+    }
+
+    /// Make `trigger` start a suggestion reported with `key`, in place of
+    /// any key it had before, or stop it starting one if `key` is None.
+    /// By default, `@`, `#`, `/` and `:` start suggestions with the keys
+    /// [PatternKey::At], [PatternKey::Hash], [PatternKey::Slash] and
+    /// [PatternKey::Colon]. A trigger added with a [PatternKey::Custom] key
+    /// reports that key, e.g. `Custom("!")` for room pings.
+    pub fn with_suggestion_trigger(
+        mut self,
+        trigger: char,
+        key: Option<PatternKey>,
+    ) -> Self {
+        match key {
+            Some(key) => self.suggestion_triggers.insert(trigger, key),
+            None => self.suggestion_triggers.remove(&trigger),
+        };
+        self
+    }
+
+    /// Replace all the characters which start a suggestion, and the key
+    /// each reports, see [Self::with_suggestion_trigger].
+    pub fn with_suggestion_triggers(
+        mut self,
+        suggestion_triggers: HashMap<char, PatternKey>,
+    ) -> Self {
+        self.suggestion_triggers = suggestion_triggers;
+        self
+    }
+
+    /// Set how deeply elements may be nested in parsed HTML before their
+    /// content is flattened to plain text.
+    pub fn with_max_parse_depth(mut self, max_parse_depth: usize) -> Self {
+        self.max_parse_depth = max_parse_depth;
+        self
+    }
+
+    /// Set what backspace does when the cursor is at the start of a code
+    /// block or quote.
+    pub fn with_block_backspace_behaviour(
+        mut self,
+        block_backspace_behaviour: BlockBackspaceBehaviour,
+    ) -> Self {
+        self.block_backspace_behaviour = block_backspace_behaviour;
+        self
+    }
+
+    /// Set which attributes are kept from parsed HTML, so that they are
+    /// still there in [crate::ComposerModel::get_content_as_html] after
+    /// editing.
+    pub fn with_attribute_policy(
+        mut self,
+        attribute_policy: AttributePolicy,
+    ) -> Self {
+        self.attribute_policy = attribute_policy;
+        self
+    }
+
+    /// Set which tags bold and italic text are written with in HTML,
+    /// whichever tags they were parsed from.
+    pub fn with_formatting_tags(
+        mut self,
+        formatting_tags: FormattingTags,
+    ) -> Self {
+        self.formatting_tags = formatting_tags;
+        self
+    }
+
+    /// Set whether message HTML leaves out the `style` attributes kept from
+    /// parsed HTML, so that messages only carry their formatting as tags.
+    pub fn with_strip_message_styles(
+        mut self,
+        strip_message_styles: bool,
+    ) -> Self {
+        self.strip_message_styles = strip_message_styles;
+        self
+    }
+
+    /// Set whether message HTML wraps the text of links and mentions in
+    /// Unicode bidi isolates, so that right-to-left display names don't
+    /// reorder the text around them. Parsing HTML always removes them again.
+    pub fn with_isolate_message_bidi(
+        mut self,
+        isolate_message_bidi: bool,
+    ) -> Self {
+        self.isolate_message_bidi = isolate_message_bidi;
+        self
+    }
+
+    /// Set whether runs of spaces, indentation and newlines outside code
+    /// blocks are kept when HTML is parsed, and written so that they render.
+    pub fn with_whitespace_policy(
+        mut self,
+        whitespace_policy: WhitespacePolicy,
+    ) -> Self {
+        self.whitespace_policy = whitespace_policy;
+        self
+    }
+
+    /// Set which empty blocks get a placeholder `&nbsp;` in the HTML for
+    /// the editor, so that they are shown. Placeholders are never written
+    /// in message HTML.
+    pub fn with_placeholder_policy(
+        mut self,
+        placeholder_policy: PlaceholderPolicy,
+    ) -> Self {
+        self.placeholder_policy = placeholder_policy;
+        self
+    }
+
+    /// Set which characters of text are escaped when writing Markdown.
+    pub fn with_markdown_escaping(
+        mut self,
+        markdown_escaping: MarkdownEscaping,
+    ) -> Self {
+        self.markdown_escaping = markdown_escaping;
+        self
+    }
+
+    /// Set how mentions are written in Markdown for a message.
+    pub fn with_markdown_mentions(
+        mut self,
+        markdown_mentions: MarkdownMentions,
+    ) -> Self {
+        self.markdown_mentions = markdown_mentions;
+        self
+    }
+
+    pub fn list_exit_rule(&self) -> ListExitRule {
+        self.list_exit_rule
+    }
+
+    pub fn inline_code_exit_rule(&self) -> InlineCodeExitRule {
+        self.inline_code_exit_rule
+    }
+
+    pub fn auto_link(&self) -> bool {
+        self.auto_link
+    }
+
+    pub fn link_pasted_urls(&self) -> bool {
+        self.link_pasted_urls
+    }
+
+    pub fn at_room_detection(&self) -> bool {
+        self.at_room_detection
+    }
+
+    pub fn at_room_detection_in_quotes(&self) -> bool {
+        self.at_room_detection_in_quotes
+    }
+
+    pub fn can_notify_room(&self) -> bool {
+        self.can_notify_room
+    }
+
+    pub fn max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    pub fn max_paste_nodes(&self) -> Option<usize> {
+        self.max_paste_nodes
+    }
+
+    pub fn max_paste_length(&self) -> Option<usize> {
+        self.max_paste_length
+    }
+
+    pub fn max_paste_list_depth(&self) -> Option<usize> {
+        self.max_paste_list_depth
+    }
+
+    pub fn markdown_shortcuts(&self) -> bool {
+        self.markdown_shortcuts
+    }
+
+    pub fn allowed_link_schemes(&self) -> Option<&[String]> {
+        self.allowed_link_schemes.as_deref()
+    }
+
+    pub fn disallowed_actions(&self) -> &HashSet<ComposerAction> {
+        &self.disallowed_actions
+    }
+
+    pub fn lenient_parsing(&self) -> bool {
+        self.lenient_parsing
+    }
+
+    pub fn plain_text_lists(&self) -> bool {
+        self.plain_text_lists
+    }
+
+    pub fn range_updates(&self) -> bool {
+        self.range_updates
+    }
+
+    pub fn block_ids(&self) -> bool {
+        self.block_ids
+    }
+
+    pub fn menu_state_deltas(&self) -> bool {
+        self.menu_state_deltas
+    }
+
+    pub fn normalize_typed_nbsp(&self) -> bool {
+        self.normalize_typed_nbsp
+    }
+
+    pub fn strip_invisible_characters(&self) -> bool {
+        self.strip_invisible_characters
+    }
+
+    pub fn record_operations(&self) -> bool {
+        self.record_operations
+    }
+
+    pub fn clock(&self) -> Option<fn() -> u64> {
+        self.clock.map(|clock| clock.0)
+    }
+
+    pub fn styles(&self) -> &HashMap<String, Vec<InlineFormatType>> {
+        &self.styles
+    }
+
+    /// The formats of the named style, or None if it isn't defined.
+    pub fn style(&self, name: &str) -> Option<&[InlineFormatType]> {
+        self.styles.get(name).map(Vec::as_slice)
+    }
+
+    pub fn suggestion_triggers(&self) -> &HashMap<char, PatternKey> {
+        &self.suggestion_triggers
+    }
+
+    pub fn max_parse_depth(&self) -> usize {
+        self.max_parse_depth
+    }
+
+    pub fn block_backspace_behaviour(&self) -> BlockBackspaceBehaviour {
+        self.block_backspace_behaviour
+    }
+
+    pub fn attribute_policy(&self) -> &AttributePolicy {
+        &self.attribute_policy
+    }
+
+    pub fn formatting_tags(&self) -> FormattingTags {
+        self.formatting_tags
+    }
+
+    pub fn strip_message_styles(&self) -> bool {
+        self.strip_message_styles
+    }
+
+    pub fn isolate_message_bidi(&self) -> bool {
+        self.isolate_message_bidi
+    }
+
+    pub fn whitespace_policy(&self) -> WhitespacePolicy {
+        self.whitespace_policy
+    }
+
+    pub fn placeholder_policy(&self) -> PlaceholderPolicy {
+        self.placeholder_policy
+    }
+
+    pub fn markdown_escaping(&self) -> MarkdownEscaping {
+        self.markdown_escaping
+    }
+
+    pub fn markdown_mentions(&self) -> MarkdownMentions {
+        self.markdown_mentions
+    }
+
+    pub(crate) fn allows_action(&self, action: &ComposerAction) -> bool {
+        !self.disallowed_actions.contains(action)
+    }
+
+    /// Where parsed HTML may contain @room mentions.
+    pub(crate) fn at_room_detection_scope(&self) -> AtRoomDetection {
+        if !self.at_room_detection || !self.can_notify_room {
+            AtRoomDetection::Off
+        } else if !self.at_room_detection_in_quotes {
+            AtRoomDetection::OutsideQuotes
+        } else {
+            AtRoomDetection::Everywhere
+        }
+    }
+
+    /// Whether a link may be set with this URL. URLs without a scheme are
+    /// checked once a scheme has been added to them.
+    pub(crate) fn allows_link_url(&self, url: &str) -> bool {
+        let Some(schemes) = &self.allowed_link_schemes else {
+            return true;
+        };
+        Url::parse(url).is_ok_and(|url| {
+            schemes.iter().any(|s| s.eq_ignore_ascii_case(url.scheme()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn at_room_detection_scope_follows_the_flags() {
+        let config = ComposerModelConfig::default();
+        assert_eq!(
+            config.at_room_detection_scope(),
+            AtRoomDetection::Everywhere
+        );
+        let config = config.with_at_room_detection_in_quotes(false);
+        assert_eq!(
+            config.at_room_detection_scope(),
+            AtRoomDetection::OutsideQuotes
+        );
+        let config = config.with_can_notify_room(false);
+        assert_eq!(config.at_room_detection_scope(), AtRoomDetection::Off);
+    }
+
+    #[test]
+    fn any_link_scheme_is_allowed_by_default() {
+        let config = ComposerModelConfig::default();
+        assert!(config.allows_link_url("javascript:alert(1)"));
+    }
+
+    #[test]
+    fn only_listed_link_schemes_are_allowed() {
+        let config = ComposerModelConfig::default()
+            .with_allowed_link_schemes(Some(vec!["https".into()]));
+        assert!(config.allows_link_url("HTTPS://matrix.org"));
+        assert!(!config.allows_link_url("ftp://matrix.org"));
+        assert!(!config.allows_link_url("not a url"));
+    }
+}
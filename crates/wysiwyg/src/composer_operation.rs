@@ -0,0 +1,181 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::dom::HtmlSource;
+use crate::{
+    Direction, Granularity, InlineFormatType, SuggestionPattern, UnicodeString,
+};
+use strum_macros::IntoStaticStr;
+
+/// One call to a [crate::ComposerModel] method which changes its content
+/// or selection, with its arguments. A model records these as they are
+/// made, and applying the same operations to a fresh model gives the same
+/// result, see [crate::ComposerModel::apply_operations].
+///
+/// Text is held as [String] whatever the model's string type, so that
+/// operations can be stored and shared between platforms. With the `serde`
+/// feature enabled, operations can be serialized.
+#[derive(Clone, Debug, PartialEq, Eq, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum ComposerOperation {
+    SetContentFromHtml {
+        html: String,
+    },
+    SetContentFromMarkdown {
+        markdown: String,
+    },
+    Clear,
+    Select {
+        start: usize,
+        end: usize,
+    },
+    ExtendSelection {
+        direction: Direction,
+        granularity: Granularity,
+    },
+    SetComposingRegion {
+        start: usize,
+        end: usize,
+    },
+    ClearComposingRegion,
+    ReplaceText {
+        text: String,
+    },
+    PasteText {
+        text: String,
+    },
+    ReplaceTextIn {
+        text: String,
+        start: usize,
+        end: usize,
+    },
+    ReplaceTextSuggestion {
+        text: String,
+        suggestion: SuggestionPattern,
+        append_space: bool,
+    },
+    ReplaceHtml {
+        html: String,
+        source: HtmlSource,
+    },
+    Backspace,
+    Delete,
+    DeleteIn {
+        start: usize,
+        end: usize,
+    },
+    TruncateTo {
+        length: usize,
+    },
+    BackspaceWord,
+    DeleteWord,
+    Enter,
+    AddLineBreak,
+    Bold,
+    Italic,
+    StrikeThrough,
+    Underline,
+    InlineCode,
+    ApplyFormats {
+        formats: Vec<InlineFormatType>,
+        enabled: bool,
+    },
+    OrderedList,
+    UnorderedList,
+    Indent,
+    Unindent,
+    SplitListAtCursor,
+    JoinWithPreviousList,
+    CodeBlock,
+    Quote,
+    MoveBlock {
+        handle: Vec<usize>,
+        target_index: usize,
+    },
+    SetLink {
+        url: String,
+        attributes: Vec<(String, String)>,
+    },
+    SetLinkWithText {
+        url: String,
+        text: String,
+        attributes: Vec<(String, String)>,
+    },
+    RemoveLinks,
+    RemoveLinksIn {
+        start: usize,
+        end: usize,
+    },
+    RemoveLinkAt {
+        offset: usize,
+    },
+    InsertMention {
+        url: String,
+        text: String,
+        attributes: Vec<(String, String)>,
+    },
+    InsertMentionAtSuggestion {
+        url: String,
+        text: String,
+        suggestion: SuggestionPattern,
+        attributes: Vec<(String, String)>,
+    },
+    InsertAtRoomMention {
+        attributes: Vec<(String, String)>,
+    },
+    InsertAtRoomMentionAtSuggestion {
+        suggestion: SuggestionPattern,
+        attributes: Vec<(String, String)>,
+    },
+    UpdateMentionText {
+        start: usize,
+        end: usize,
+        text: String,
+    },
+    ConvertPlainMentions {
+        mentions: Vec<(String, String)>,
+    },
+    Undo {
+        steps: usize,
+    },
+    Redo {
+        steps: usize,
+    },
+    CreateSnapshot,
+    RestoreSnapshot {
+        id: u64,
+    },
+    DiscardSnapshot {
+        id: u64,
+    },
+    ResetToLastGood,
+    InsertTemplate {
+        html: String,
+    },
+    NextPlaceholder,
+    FillPlaceholder {
+        text: String,
+    },
+    RunCommandSequence {
+        operations: Vec<ComposerOperation>,
+    },
+}
+
+pub(crate) fn attributes_to_strings<S>(
+    attributes: &[(S, S)],
+) -> Vec<(String, String)>
+where
+    S: UnicodeString,
+{
+    attributes
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
@@ -7,7 +7,9 @@
 use crate::dom::UnicodeString;
 use crate::link_action::LinkActionUpdate;
 use crate::{
-    Location, MenuAction, MenuState, ReplaceAll, Selection, TextUpdate,
+    ChangedHandles, DeletedMention, Location, MenuAction, MenuState,
+    OffsetMapping, PasteTruncation, ReplaceAll, Selection, StrippedCharacters,
+    TextUpdate, UpdateTiming,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -19,6 +21,22 @@ where
     pub menu_state: MenuState,
     pub menu_action: MenuAction,
     pub link_action: LinkActionUpdate<S>,
+    /// How offsets in the previous content map to the new content
+    pub offset_mapping: OffsetMapping,
+    /// The mentions removed by this update
+    pub deleted_mentions: Vec<DeletedMention>,
+    /// The top-level nodes added, removed or modified by this update
+    pub changed_handles: ChangedHandles,
+    /// How pasted content was cut down to the configured limits, if this
+    /// update pasted content which went over them
+    pub paste_truncation: Option<PasteTruncation>,
+    /// The invisible characters removed from pasted content, if this update
+    /// pasted content which had any and the configuration asks for them to
+    /// be stripped
+    pub stripped_characters: Option<StrippedCharacters>,
+    /// How long the update took to make, if the `timing` feature is
+    /// enabled and the update changed the content
+    pub timing: Option<UpdateTiming>,
 }
 
 impl<S> ComposerUpdate<S>
@@ -31,6 +49,12 @@ where
             menu_state: MenuState::Keep,
             menu_action: MenuAction::Keep,
             link_action: LinkActionUpdate::Keep,
+            offset_mapping: OffsetMapping::identity(),
+            deleted_mentions: Vec::new(),
+            changed_handles: ChangedHandles::default(),
+            paste_truncation: None,
+            stripped_characters: None,
+            timing: None,
         }
     }
 
@@ -43,6 +67,12 @@ where
             menu_state,
             menu_action,
             link_action: LinkActionUpdate::Keep,
+            offset_mapping: OffsetMapping::identity(),
+            deleted_mentions: Vec::new(),
+            changed_handles: ChangedHandles::default(),
+            paste_truncation: None,
+            stripped_characters: None,
+            timing: None,
         }
     }
 
@@ -58,6 +88,12 @@ where
             menu_state,
             menu_action,
             link_action,
+            offset_mapping: OffsetMapping::identity(),
+            deleted_mentions: Vec::new(),
+            changed_handles: ChangedHandles::default(),
+            paste_truncation: None,
+            stripped_characters: None,
+            timing: None,
         }
     }
 
@@ -68,6 +104,7 @@ where
         menu_state: MenuState,
         menu_action: MenuAction,
         link_action: LinkActionUpdate<S>,
+        offset_mapping: OffsetMapping,
     ) -> Self {
         Self {
             text_update: TextUpdate::ReplaceAll(ReplaceAll {
@@ -78,6 +115,61 @@ where
             menu_state,
             menu_action,
             link_action,
+            offset_mapping,
+            deleted_mentions: Vec::new(),
+            changed_handles: ChangedHandles::default(),
+            paste_truncation: None,
+            stripped_characters: None,
+            timing: None,
         }
     }
+
+    /// True if this update changed the content, so that it is worth saving,
+    /// rather than only e.g. moving the selection, updating the menu, or
+    /// toggling the formatting of text yet to be typed.
+    pub fn content_changed(&self) -> bool {
+        !self.changed_handles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::cm;
+    use crate::Location;
+
+    #[test]
+    fn only_edits_change_the_content() {
+        let mut model = cm("abc|");
+        assert!(model.replace_text(Utf16String::from("d")).content_changed());
+        // Only toggles the formatting of the next text typed
+        assert!(!model.bold().content_changed());
+        assert!(!model
+            .select(Location::from(0), Location::from(1))
+            .content_changed());
+        assert!(model.bold().content_changed());
+        assert!(model.undo().content_changed());
+        assert!(!model
+            .set_content_from_html(&Utf16String::from("abcd"))
+            .unwrap()
+            .content_changed());
+    }
+
+    #[test]
+    fn truncating_changes_the_content() {
+        let mut model = cm("hello world|");
+        let update = model.truncate_to(5);
+        assert!(update.content_changed());
+        assert!(!update.changed_handles.is_empty());
+    }
+
+    #[test]
+    fn filling_a_placeholder_changes_the_content() {
+        let mut model = cm("|");
+        model.insert_template(Utf16String::from("{{name}}: {{reason}}"));
+        let update = model.fill_placeholder(Utf16String::from("Alice"));
+        assert!(update.content_changed());
+        assert!(!update.changed_handles.is_empty());
+    }
 }
@@ -0,0 +1,92 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::{Location, OffsetMapping};
+
+/// What a [Decoration] is marking. Decorations are not part of the content:
+/// they are never included in the message HTML, and are only written out
+/// (as `data-decoration` spans) when explicitly asked for.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DecorationKind {
+    SpellingError,
+    GrammarSuggestion,
+    SearchHighlight,
+    /// A placeholder inserted by [crate::ComposerModel::insert_template]
+    Placeholder,
+}
+
+impl DecorationKind {
+    /// The value used for this kind in the `data-decoration` attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SpellingError => "spelling-error",
+            Self::GrammarSuggestion => "grammar-suggestion",
+            Self::SearchHighlight => "search-highlight",
+            Self::Placeholder => "placeholder",
+        }
+    }
+}
+
+/// A typed range of the content, attached by the client and moved along with
+/// the text it covers as the content is edited.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Decoration {
+    pub id: u64,
+    pub kind: DecorationKind,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Decoration {
+    pub(crate) fn overlaps(&self, start: usize, end: usize) -> bool {
+        usize::from(self.start) < end && start < usize::from(self.end)
+    }
+}
+
+/// All the decorations attached to a model.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Decorations {
+    pub(crate) items: Vec<Decoration>,
+    next_id: u64,
+}
+
+impl Decorations {
+    pub(crate) fn add(
+        &mut self,
+        kind: DecorationKind,
+        start: Location,
+        end: Location,
+    ) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.items.push(Decoration {
+            id,
+            kind,
+            start: start.min(end),
+            end: start.max(end),
+        });
+        id
+    }
+
+    /// Move every decoration to follow its text, dropping the ones whose
+    /// text was changed.
+    pub(crate) fn apply(&mut self, mapping: &OffsetMapping) {
+        if mapping.is_identity() {
+            return;
+        }
+        self.items.retain_mut(|decoration| {
+            match mapping
+                .map_range(decoration.start.into(), decoration.end.into())
+            {
+                Some((start, end)) if start < end => {
+                    decoration.start = Location::from(start);
+                    decoration.end = Location::from(end);
+                    true
+                }
+                _ => false,
+            }
+        });
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use matrix_mentions::{MentionKind, RoomIdentificationType};
+
+use crate::dom::nodes::{MentionNode, MentionNodeKind};
+use crate::{Location, OffsetMapping, UnicodeString};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DeletedMentionKind {
+    User,
+    RoomId,
+    RoomAlias,
+    AtRoom,
+}
+
+/// A mention pill which was removed from the content by an update, e.g.
+/// by backspace or delete.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeletedMention {
+    pub kind: DeletedMentionKind,
+    /// The matrix.to or matrix: URI of the mention, or None for @room
+    pub uri: Option<String>,
+    /// The user id, room id or room alias, or None for @room
+    pub mx_id: Option<String>,
+    pub display_text: String,
+    /// Where the mention was in the content before the update
+    pub location: Location,
+}
+
+impl DeletedMention {
+    pub(crate) fn from_node<S>(node: &MentionNode<S>, location: usize) -> Self
+    where
+        S: UnicodeString,
+    {
+        let display_text = node.display_text().to_string();
+        let location = Location::from(location);
+        match node.kind() {
+            MentionNodeKind::AtRoom => Self {
+                kind: DeletedMentionKind::AtRoom,
+                uri: None,
+                mx_id: None,
+                display_text,
+                location,
+            },
+            MentionNodeKind::MatrixUri { mention } => Self {
+                kind: match mention.kind() {
+                    MentionKind::User => DeletedMentionKind::User,
+                    MentionKind::Room(RoomIdentificationType::Id) => {
+                        DeletedMentionKind::RoomId
+                    }
+                    MentionKind::Room(RoomIdentificationType::Alias) => {
+                        DeletedMentionKind::RoomAlias
+                    }
+                },
+                uri: Some(mention.uri().to_string()),
+                mx_id: Some(mention.mx_id().to_string()),
+                display_text,
+                location,
+            },
+        }
+    }
+
+    fn is_same_mention(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.uri == other.uri
+            && self.display_text == other.display_text
+    }
+}
+
+/// The mentions in `old` which are inside the run replaced by `mapping`,
+/// and were not put back in its replacement in `new`.
+pub(crate) fn deleted_mentions(
+    old: &[DeletedMention],
+    new: &[DeletedMention],
+    mapping: &OffsetMapping,
+) -> Vec<DeletedMention> {
+    let mut inserted: Vec<&DeletedMention> = new
+        .iter()
+        .filter(|m| {
+            let location = usize::from(m.location);
+            mapping.start <= location && location < mapping.new_end
+        })
+        .collect();
+    old.iter()
+        .filter(|m| {
+            let location = usize::from(m.location);
+            mapping.start <= location && location < mapping.old_end
+        })
+        .filter(
+            |m| match inserted.iter().position(|n| n.is_same_mention(m)) {
+                Some(i) => {
+                    inserted.remove(i);
+                    false
+                }
+                None => true,
+            },
+        )
+        .cloned()
+        .collect()
+}
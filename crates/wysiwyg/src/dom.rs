@@ -5,12 +5,18 @@
 // Please see LICENSE in the repository root for full details.
 
 pub mod action_list;
+pub mod at_room_detection;
+pub mod attribute_policy;
+pub mod bidi_isolation;
+pub mod canonicalize;
+pub mod disallowed_formatting;
 pub mod dom_block_nodes;
 pub mod dom_creation_error;
 pub mod dom_handle;
 pub mod dom_invariants;
 pub mod dom_list_methods;
 pub mod dom_methods;
+pub mod dom_queries;
 pub mod dom_struct;
 pub mod find_extended_range;
 pub mod find_range;
@@ -20,16 +26,26 @@ pub mod insert_node_at_cursor;
 pub mod insert_parent;
 pub mod iter;
 pub mod join_nodes;
+pub mod message_validation;
 pub mod nodes;
 pub mod parser;
+pub mod placeholder_policy;
 pub mod range;
+pub mod redacted_tree;
+pub mod restructure;
+pub mod semantic_equality;
 pub mod to_html;
 pub mod to_markdown;
 pub mod to_plain_text;
 pub mod to_raw_text;
 pub mod to_tree;
+pub mod transaction;
 pub mod unicode_string;
+pub mod whitespace_policy;
 
+pub use at_room_detection::AtRoomDetection;
+pub use attribute_policy::AttributePolicy;
+pub use canonicalize::{RoundTripIssue, RoundTripIssueKind};
 pub use dom_creation_error::DomCreationError;
 pub use dom_creation_error::HtmlParseError;
 pub use dom_creation_error::MarkdownParseError;
@@ -37,10 +53,20 @@ pub use dom_handle::DomHandle;
 pub use dom_struct::Dom;
 pub use find_result::FindResult;
 pub use html_source::HtmlSource;
+pub use iter::LeafInRange;
+pub use message_validation::HtmlIssue;
+pub use placeholder_policy::PlaceholderPolicy;
 pub use range::DomLocation;
 pub use range::Range;
+pub use restructure::RestructureError;
+pub use semantic_equality::semantic_html_diff;
+pub use to_html::FormattingTags;
 pub use to_html::ToHtml;
-pub use to_markdown::{MarkdownError, ToMarkdown};
+pub use to_markdown::{
+    MarkdownError, MarkdownEscaping, MarkdownMentions, ToMarkdown,
+};
 pub use to_raw_text::ToRawText;
 pub use to_tree::ToTree;
+pub use transaction::TransactionGuard;
 pub use unicode_string::UnicodeString;
+pub use whitespace_policy::WhitespacePolicy;
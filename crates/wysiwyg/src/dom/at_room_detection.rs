@@ -0,0 +1,39 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::dom::nodes::dom_node::DomNodeKind;
+
+/// Where `@room` in the text of parsed HTML, and links written for @room
+/// mentions, become @room mentions.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AtRoomDetection {
+    /// Anywhere outside code and links
+    #[default]
+    Everywhere,
+    /// Anywhere outside code, links and quotes, so that quoting a message
+    /// which notified the room doesn't notify it again
+    OutsideQuotes,
+    /// Nowhere, so `@room` is kept as text and links as links
+    Off,
+}
+
+impl AtRoomDetection {
+    /// Whether `@room` may become an @room mention inside the nodes of
+    /// `path`. Code never has mentions, so `@room` in a code block or
+    /// inline code, or an @room link there, is always kept as it is.
+    /// Quotes have them unless this is [Self::OutsideQuotes].
+    ///
+    /// Both HTML parsers use this, so that they always agree.
+    pub(crate) fn allows_at_room_in(&self, path: &[DomNodeKind]) -> bool {
+        let in_code = path.iter().any(DomNodeKind::is_code_kind);
+        match self {
+            Self::Everywhere => !in_code,
+            Self::OutsideQuotes => {
+                !in_code && !path.contains(&DomNodeKind::Quote)
+            }
+            Self::Off => false,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+/// The tags which may keep a `class` attribute, as allowed by the Matrix
+/// spec.
+const TAGS_WITH_CLASSES: [&str; 3] = ["a", "code", "span"];
+
+/// Which attributes of supported tags are kept when parsing HTML, and
+/// written back out when the content is serialized. Attributes the editor
+/// relies on itself (e.g. `href` or `start`) are always kept.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AttributePolicy {
+    /// Keep `data-*` attributes, e.g. ones added by bridges to annotate
+    /// a message, so that they are not lost when the message is edited.
+    pub preserve_data_attributes: bool,
+    /// Classes kept on `<a>`, `<code>` and `<span>` tags. An entry ending
+    /// in `*` keeps every class starting with the rest of it, e.g.
+    /// `language-*` keeps the language of code blocks. Other classes are
+    /// removed.
+    pub allowed_classes: Vec<String>,
+}
+
+impl AttributePolicy {
+    /// The value to keep for an attribute of the given tag, if any. Classes
+    /// which are not allowed are removed from the value.
+    pub(crate) fn kept_value(
+        &self,
+        tag: &str,
+        name: &str,
+        value: &str,
+    ) -> Option<String> {
+        if name == "class" {
+            if !TAGS_WITH_CLASSES.contains(&tag) {
+                return None;
+            }
+            let classes: Vec<&str> = value
+                .split_whitespace()
+                .filter(|class| self.allows_class(class))
+                .collect();
+            (!classes.is_empty()).then(|| classes.join(" "))
+        } else if self.preserve_data_attributes && name.starts_with("data-") {
+            Some(value.to_owned())
+        } else {
+            None
+        }
+    }
+
+    fn allows_class(&self, class: &str) -> bool {
+        self.allowed_classes.iter().any(|allowed| {
+            match allowed.strip_suffix('*') {
+                Some(prefix) => class.starts_with(prefix),
+                None => class == allowed,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AttributePolicy;
+
+    fn policy(allowed_classes: &[&str]) -> AttributePolicy {
+        AttributePolicy {
+            preserve_data_attributes: false,
+            allowed_classes: allowed_classes
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn classes_are_dropped_by_default() {
+        let policy = AttributePolicy::default();
+        assert_eq!(policy.kept_value("code", "class", "language-rust"), None);
+    }
+
+    #[test]
+    fn classes_can_be_allowed_by_name_or_prefix() {
+        let policy = policy(&["language-*", "spoiler"]);
+        assert_eq!(
+            policy.kept_value("code", "class", "big language-rust"),
+            Some("language-rust".to_owned())
+        );
+        assert_eq!(
+            policy.kept_value("span", "class", "spoiler spoilers"),
+            Some("spoiler".to_owned())
+        );
+        assert_eq!(policy.kept_value("a", "class", "other"), None);
+    }
+
+    #[test]
+    fn classes_are_only_kept_on_some_tags() {
+        let policy = policy(&["*"]);
+        assert_eq!(policy.kept_value("a", "class", "x"), Some("x".to_owned()));
+        assert_eq!(policy.kept_value("p", "class", "x"), None);
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Unicode bidi isolates around the text of links and mentions in message
+//! HTML, so that a right-to-left display name doesn't visually reorder the
+//! left-to-right text around it, or the other way around. Parsing strips
+//! them again, so that editing a sent message doesn't keep adding more.
+
+use crate::dom::nodes::DomNode;
+use crate::dom::Dom;
+use crate::{DomHandle, UnicodeString};
+
+/// Starts an isolate whose direction is that of its first strong character.
+pub(crate) const FIRST_STRONG_ISOLATE: char = '\u{2068}';
+
+/// Ends the isolate started by [FIRST_STRONG_ISOLATE].
+pub(crate) const POP_DIRECTIONAL_ISOLATE: char = '\u{2069}';
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Remove the bidi isolates around the text of each link and mention,
+    /// as written in message HTML.
+    pub(crate) fn strip_bidi_isolates(&mut self) {
+        let mentions: Vec<DomHandle> = self
+            .iter_mentions()
+            .map(|mention| mention.handle())
+            .collect();
+        for handle in mentions {
+            if let DomNode::Mention(mention) = self.lookup_node_mut(&handle) {
+                let text = mention.display_text().to_string();
+                if let Some(stripped) = strip_isolates(&text) {
+                    mention.set_display_text(S::from(stripped));
+                }
+            }
+        }
+
+        let links: Vec<DomHandle> = self
+            .iter_containers()
+            .filter(|container| container.is_link())
+            .map(|container| container.handle())
+            .collect();
+        // Backwards, so that removing emptied text nodes doesn't move the
+        // links still to be stripped
+        for handle in links.into_iter().rev() {
+            self.strip_bidi_isolates_in_link(&handle);
+        }
+    }
+
+    fn strip_bidi_isolates_in_link(&mut self, handle: &DomHandle) {
+        let texts: Vec<DomHandle> = self
+            .lookup_node(handle)
+            .iter_text_in_subtree()
+            .map(|text| text.handle())
+            .collect();
+        let (Some(first), Some(last)) = (texts.first(), texts.last()) else {
+            return;
+        };
+        let starts_isolated =
+            self.text_data(first).starts_with(FIRST_STRONG_ISOLATE);
+        let ends_isolated =
+            self.text_data(last).ends_with(POP_DIRECTIONAL_ISOLATE);
+        if !starts_isolated || !ends_isolated {
+            return;
+        }
+        // The last text first, as removing the first could move it
+        for (handle, is_last) in [(last, true), (first, false)] {
+            let data = self.text_data(handle);
+            let stripped = if is_last {
+                data.strip_suffix(POP_DIRECTIONAL_ISOLATE)
+            } else {
+                data.strip_prefix(FIRST_STRONG_ISOLATE)
+            }
+            .unwrap_or(&data)
+            .to_owned();
+            if stripped.is_empty() {
+                self.remove(handle);
+            } else if let DomNode::Text(text) = self.lookup_node_mut(handle) {
+                text.set_data(S::from(stripped.as_str()));
+            }
+        }
+    }
+
+    fn text_data(&self, handle: &DomHandle) -> String {
+        match self.lookup_node(handle) {
+            DomNode::Text(text) => text.data().to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// `text` without the isolates around it, if it is isolated.
+fn strip_isolates(text: &str) -> Option<&str> {
+    text.strip_prefix(FIRST_STRONG_ISOLATE)?
+        .strip_suffix(POP_DIRECTIONAL_ISOLATE)
+}
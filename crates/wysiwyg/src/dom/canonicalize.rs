@@ -0,0 +1,252 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::dom::nodes::dom_node::DomNodeKind;
+use crate::dom::nodes::text_node::keep_spaces_visible;
+use crate::dom::nodes::{ContainerNode, DomNode};
+use crate::dom::parser::{convert_text, post_process_dom};
+use crate::dom::Dom;
+use crate::nbsp::is_placeholder;
+use crate::{DomHandle, InlineFormatType, UnicodeString, WhitespacePolicy};
+
+/// A construct in a [Dom] which is changed when its HTML is parsed again,
+/// so does not survive an edit cycle as it is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoundTripIssue {
+    /// The node concerned, in the Dom before it was canonicalized.
+    pub handle: DomHandle,
+    pub kind: RoundTripIssueKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoundTripIssueKind {
+    /// A link inside another link. HTML doesn't allow links to be nested,
+    /// so the inner link is removed, keeping its content.
+    NestedLink,
+    /// A mention inside a link, which is replaced by its display text.
+    MentionInLink,
+    /// A line break outside a code block, which ends the paragraph it is
+    /// in instead.
+    LineBreak,
+    /// Text containing `@room` outside code and links, which becomes an
+    /// `@room` mention.
+    AtRoomText,
+    /// Whitespace in text outside code blocks which does not render as it
+    /// is: newlines are collapsed into spaces, runs of spaces and spaces at
+    /// the edges of a line become non-breaking, and text made only of a
+    /// non-breaking space is removed, as that is how empty blocks are
+    /// written.
+    Whitespace,
+    /// A list, quote or code block right after another one of the same
+    /// kind, which is merged into it.
+    AdjacentBlock,
+}
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Rewrite this Dom into the form parsing its HTML gives, so that
+    /// `parse(dom.to_html())` is equal to it afterwards when both use the
+    /// default [WhitespacePolicy]. Returns the
+    /// constructs which had to be changed to get there, as listed by
+    /// [Self::round_trip_issues]. Other differences, such as adjacent text
+    /// nodes, are normalized without being reported as they don't change
+    /// the content.
+    pub fn canonicalize(&mut self) -> Vec<RoundTripIssue> {
+        let issues = self.round_trip_issues();
+        canonicalize_children(self.document_mut(), Context::default());
+        // Splitting blocks at line breaks can leave nodes which are joined
+        // when the result is parsed, so repeat until nothing changes
+        loop {
+            let processed = post_process_dom(self.clone());
+            if processed == *self {
+                break;
+            }
+            *self = processed;
+        }
+        self.keep_spaces_visible();
+        issues
+    }
+
+    /// The constructs in this Dom which would change if its HTML was
+    /// parsed again, in document order.
+    pub fn round_trip_issues(&self) -> Vec<RoundTripIssue> {
+        self.iter()
+            .filter_map(|node| {
+                self.round_trip_issue_kind(node).map(|kind| RoundTripIssue {
+                    handle: node.handle(),
+                    kind,
+                })
+            })
+            .collect()
+    }
+
+    fn round_trip_issue_kind(
+        &self,
+        node: &DomNode<S>,
+    ) -> Option<RoundTripIssueKind> {
+        let handle = node.handle();
+        let in_link = self.has_ancestor(&handle, |k| k.is_link_kind());
+        let in_code_block =
+            self.has_ancestor(&handle, |k| k == &DomNodeKind::CodeBlock);
+        match node {
+            DomNode::Container(c) if c.is_link() && in_link => {
+                Some(RoundTripIssueKind::NestedLink)
+            }
+            DomNode::Container(c) if c.is_block_node() => {
+                let follows_same_kind = handle.has_parent()
+                    && handle.index_in_parent() > 0
+                    && self.lookup_node(&handle.prev_sibling()).can_push(node);
+                follows_same_kind.then_some(RoundTripIssueKind::AdjacentBlock)
+            }
+            DomNode::Mention(_) if in_link => {
+                Some(RoundTripIssueKind::MentionInLink)
+            }
+            DomNode::LineBreak(_) if !in_code_block => {
+                Some(RoundTripIssueKind::LineBreak)
+            }
+            DomNode::Text(t) => {
+                let data = t.data().to_string();
+                let is_only_child = self.parent(&handle).children().len() == 1;
+                let is_nbsp = is_placeholder(&data);
+                let is_line_edge = |sibling: Option<&DomNode<S>>| {
+                    sibling.is_none_or(|n| n.is_line_break())
+                };
+                let has_hidden_spaces = data.contains("  ")
+                    || data.contains('\n')
+                    || (data.starts_with(' ')
+                        && is_line_edge(self.prev_sibling(&handle)))
+                    || (data.ends_with(' ')
+                        && is_line_edge(self.next_sibling(&handle)));
+                if (is_nbsp && (in_code_block || is_only_child))
+                    || (!in_code_block && has_hidden_spaces)
+                {
+                    Some(RoundTripIssueKind::Whitespace)
+                } else if data.contains("@room")
+                    && !in_link
+                    && !self.has_ancestor(&handle, |k| k.is_code_kind())
+                {
+                    Some(RoundTripIssueKind::AtRoomText)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Write the spaces in text as [ToHtml] does, so they render the same
+    /// after they are parsed.
+    fn keep_spaces_visible(&mut self) {
+        let handles: Vec<DomHandle> =
+            self.iter_text().map(|t| t.handle()).collect();
+        for handle in handles {
+            if self.has_ancestor(&handle, |k| k == &DomNodeKind::CodeBlock) {
+                continue;
+            }
+            let is_first = handle.index_in_parent() == 0;
+            let is_last = self.is_last_in_parent(&handle);
+            if let DomNode::Text(t) = self.lookup_node_mut(&handle) {
+                let data = keep_spaces_visible(
+                    &t.data().to_string(),
+                    is_first,
+                    is_last,
+                );
+                t.set_data(S::from(data));
+            }
+        }
+    }
+
+    fn has_ancestor(
+        &self,
+        handle: &DomHandle,
+        matches: impl Fn(&DomNodeKind) -> bool,
+    ) -> bool {
+        let mut handle = handle.clone();
+        while handle.has_parent() {
+            handle = handle.parent_handle();
+            if matches(&self.lookup_node(&handle).kind()) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Where the children being canonicalized are.
+#[derive(Clone, Copy, Default)]
+struct Context {
+    in_code_block: bool,
+    in_inline_code: bool,
+    in_link: bool,
+}
+
+impl Context {
+    fn inside<S: UnicodeString>(self, container: &ContainerNode<S>) -> Self {
+        let kind = DomNodeKind::from_container_kind(container.kind());
+        Self {
+            in_code_block: self.in_code_block || kind == DomNodeKind::CodeBlock,
+            in_inline_code: self.in_inline_code
+                || kind
+                    == DomNodeKind::Formatting(InlineFormatType::InlineCode),
+            in_link: self.in_link || kind == DomNodeKind::Link,
+        }
+    }
+}
+
+/// Rebuild the children of the container as the parser would create them
+/// from its HTML. Line breaks and adjacent blocks are left for
+/// [post_process_dom] to handle.
+fn canonicalize_children<S: UnicodeString>(
+    container: &mut ContainerNode<S>,
+    context: Context,
+) {
+    let children = container.remove_children();
+    let is_only_child = children.len() == 1;
+    for child in children {
+        match child {
+            DomNode::Container(mut c) => {
+                let inner_context = context.inside(&c);
+                canonicalize_children(&mut c, inner_context);
+                if c.is_link() && context.in_link {
+                    container.append_children(c.take_children());
+                } else {
+                    container.append_child(DomNode::Container(c));
+                }
+            }
+            DomNode::Mention(m) if context.in_link => {
+                container.append_child(DomNode::new_text(m.display_text()));
+            }
+            DomNode::Text(t) => convert_text(
+                &t.data().to_string(),
+                container,
+                context.in_code_block,
+                !context.in_inline_code && !context.in_link,
+                is_only_child,
+                WhitespacePolicy::Collapse,
+            ),
+            _ => {
+                container.append_child(child);
+            }
+        }
+    }
+    merge_adjacent_text(container);
+}
+
+/// Merge adjacent text nodes, which the parser never creates, before
+/// [post_process_dom] expects them to be the only ones.
+fn merge_adjacent_text<S: UnicodeString>(container: &mut ContainerNode<S>) {
+    for child in container.remove_children() {
+        match (container.last_child_mut(), child) {
+            (Some(DomNode::Text(last)), DomNode::Text(text)) => {
+                last.push(&text)
+            }
+            (_, child) => {
+                container.append_child(child);
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::collections::HashSet;
+
+use crate::dom::nodes::{ContainerNode, ContainerNodeKind, DomNode};
+use crate::dom::Dom;
+use crate::{ComposerAction, ListType, UnicodeString};
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Replace the formatting, links, lists, quotes and code blocks which
+    /// the `disallowed` actions would create by their content. A numbered
+    /// list becomes a bulleted one when only numbered lists are disallowed,
+    /// and the other way round.
+    pub(crate) fn remove_disallowed_formatting(
+        &mut self,
+        disallowed: &HashSet<ComposerAction>,
+    ) {
+        if disallowed.is_empty() {
+            return;
+        }
+        remove_disallowed_children(self.document_mut(), disallowed);
+        self.document_mut().merge_adjacent_text_nodes();
+    }
+}
+
+fn remove_disallowed_children<S: UnicodeString>(
+    container: &mut ContainerNode<S>,
+    disallowed: &HashSet<ComposerAction>,
+) {
+    for child in container.remove_children() {
+        let DomNode::Container(mut c) = child else {
+            container.append_child(child);
+            continue;
+        };
+        remove_disallowed_children(&mut c, disallowed);
+        let action = match c.kind() {
+            ContainerNodeKind::Formatting(format) => Some(format.action()),
+            ContainerNodeKind::Link(_) => Some(ComposerAction::Link),
+            ContainerNodeKind::List(ListType::Ordered) => {
+                Some(ComposerAction::OrderedList)
+            }
+            ContainerNodeKind::List(ListType::Unordered) => {
+                Some(ComposerAction::UnorderedList)
+            }
+            ContainerNodeKind::CodeBlock => Some(ComposerAction::CodeBlock),
+            ContainerNodeKind::Quote => Some(ComposerAction::Quote),
+            _ => None,
+        };
+        match action {
+            Some(action) if disallowed.contains(&action) => {
+                if let Some(list_type) = c.get_list_type().cloned() {
+                    let other = match list_type {
+                        ListType::Ordered => ListType::Unordered,
+                        ListType::Unordered => ListType::Ordered,
+                    };
+                    if !disallowed.contains(&other.action()) {
+                        c.set_list_type(other);
+                        container.append_child(DomNode::Container(c));
+                    } else {
+                        container.append_children(list_items_as_blocks(c));
+                    }
+                } else {
+                    container.append_children(c.take_children());
+                }
+            }
+            _ => {
+                container.append_child(DomNode::Container(c));
+            }
+        }
+    }
+}
+
+/// The content of each item of the list, with items containing only inline
+/// nodes turned into paragraphs.
+fn list_items_as_blocks<S: UnicodeString>(
+    list: ContainerNode<S>,
+) -> Vec<DomNode<S>> {
+    let mut blocks = Vec::new();
+    for item in list.take_children() {
+        let DomNode::Container(item) = item else {
+            blocks.push(item);
+            continue;
+        };
+        let children = item.take_children();
+        if children.iter().any(|c| c.is_block_node()) {
+            blocks.extend(children);
+        } else {
+            blocks.push(DomNode::new_paragraph(children));
+        }
+    }
+    blocks
+}
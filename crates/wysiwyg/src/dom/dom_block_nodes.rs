@@ -72,7 +72,7 @@ where
             let first_ancestor_to_split =
                 self.find_block_ancestor_to_split(&first.handle);
             let last_ancestor_to_split =
-                self.find_block_ancestor_to_split(&first.handle);
+                self.find_block_ancestor_to_split(&last.handle);
             let max_depth = min(
                 first_ancestor_to_split.depth(),
                 last_ancestor_to_split.depth(),
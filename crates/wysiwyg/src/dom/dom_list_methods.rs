@@ -203,9 +203,9 @@ mod test {
     use widestring::Utf16String;
 
     use crate::dom::Dom;
+    use crate::nbsp::nbsp_to_entity;
     use crate::tests::testutils_composer_model::{cm, tx};
 
-    use crate::char::CharExt;
     use crate::{DomHandle, ListType};
 
     #[test]
@@ -413,6 +413,6 @@ mod test {
 
     // TODO: move this to a more globally usable location if needed
     fn ds(dom: &Dom<Utf16String>) -> String {
-        dom.to_string().replace(char::nbsp(), "&nbsp;")
+        nbsp_to_entity(&dom.to_string())
     }
 }
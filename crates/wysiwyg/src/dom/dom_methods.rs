@@ -25,10 +25,8 @@ where
     /// Removes node at given handle from the dom, and if it has children
     /// moves them to its parent container children.
     pub fn remove_and_keep_children(&mut self, node_handle: &DomHandle) {
-        #[cfg(any(test, feature = "assert-invariants"))]
-        self.assert_invariants();
-
-        let parent = self.parent_mut(node_handle);
+        let mut transaction = self.transaction();
+        let parent = transaction.parent_mut(node_handle);
         let index = node_handle.index_in_parent();
         let node = parent.remove_child(index);
         let mut last_index = index;
@@ -47,9 +45,6 @@ where
         if index > 0 {
             merge_if_adjacent_text_nodes(parent, index - 1);
         }
-
-        #[cfg(any(test, feature = "assert-invariants"))]
-        self.assert_invariants();
     }
 
     pub fn replace_text_in(&mut self, new_text: S, start: usize, end: usize) {
@@ -1000,9 +995,15 @@ where
         let result: Vec<DomNode<S>>;
         let mut needs_to_remove_container = false;
         if let DomNode::Container(container) = self.lookup_node(&cur_handle) {
+            // Remove containers which were emptied by the split, and empty
+            // ones which are moved whole to the new sub tree, e.g. an empty
+            // paragraph between the two handles.
+            let is_moved_whole = child_count == 0
+                && cur_handle != *from_handle
+                && to_handle.as_ref() != Some(&cur_handle);
             if !container.handle().is_root()
                 && container.is_empty()
-                && child_count > 0
+                && (child_count > 0 || is_moved_whole)
             {
                 needs_to_remove_container = true;
             }
@@ -0,0 +1,109 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Methods on Dom which find the nodes matching a query, so that code
+//! transforming the content, e.g. rewriting links, doesn't have to walk
+//! the tree itself. Handles are returned in depth-first order, so a node
+//! comes before its descendants.
+
+use crate::dom::nodes::dom_node::DomNodeKind;
+use crate::{DomHandle, DomNode, UnicodeString};
+
+use super::Dom;
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// The handles of every node of the given kind.
+    pub fn find_all(&self, kind: DomNodeKind) -> Vec<DomHandle> {
+        self.find_where(|node| node.kind() == kind)
+    }
+
+    /// The handles of every node for which `predicate` returns true.
+    pub fn find_where(
+        &self,
+        predicate: impl Fn(&DomNode<S>) -> bool,
+    ) -> Vec<DomHandle> {
+        self.iter()
+            .filter(|node| predicate(node))
+            .map(DomNode::handle)
+            .collect()
+    }
+
+    /// The handles of every container with an attribute of the given name,
+    /// e.g. `style`.
+    pub fn find_with_attribute(&self, name: &str) -> Vec<DomHandle> {
+        self.find_where(|node| {
+            node.as_container()
+                .and_then(|container| container.attributes())
+                .is_some_and(|attrs| {
+                    attrs.iter().any(|(n, _)| n.to_string() == name)
+                })
+        })
+    }
+
+    /// The handles of every link.
+    pub fn links(&self) -> Vec<DomHandle> {
+        self.find_all(DomNodeKind::Link)
+    }
+
+    /// The handles of every mention, including @room mentions.
+    pub fn mentions(&self) -> Vec<DomHandle> {
+        self.find_all(DomNodeKind::Mention)
+    }
+
+    /// The handles of every code block.
+    pub fn code_blocks(&self) -> Vec<DomHandle> {
+        self.find_all(DomNodeKind::CodeBlock)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dom::nodes::dom_node::DomNodeKind;
+    use crate::tests::testutils_composer_model::cm;
+    use crate::{DomHandle, InlineFormatType};
+
+    fn handles(raw: &[&[usize]]) -> Vec<DomHandle> {
+        raw.iter()
+            .map(|r| DomHandle::from_raw(r.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn nodes_are_found_by_kind_in_document_order() {
+        let dom = cm("<p><b>a</b></p><ul><li><b>b</b>c|</li></ul>").state.dom;
+        assert_eq!(
+            dom.find_all(DomNodeKind::Formatting(InlineFormatType::Bold)),
+            handles(&[&[0, 0], &[1, 0, 0]])
+        );
+    }
+
+    #[test]
+    fn links_mentions_and_code_blocks_are_found() {
+        let dom = cm("<p><a href=\"https://x.org\">a</a> \
+             <a href=\"https://matrix.to/#/@b:x.org\">b</a></p>\
+             <pre><code>c|</code></pre>")
+        .state
+        .dom;
+        assert_eq!(dom.links(), handles(&[&[0, 0]]));
+        assert_eq!(dom.mentions(), handles(&[&[0, 2]]));
+        assert_eq!(dom.code_blocks(), handles(&[&[1]]));
+    }
+
+    #[test]
+    fn nodes_are_found_by_predicate_or_attribute() {
+        let dom = cm("<p>a</p><p><a style=\"color: red\" href=\"https://x.org\">bb|</a></p>")
+                .state
+                .dom;
+        assert_eq!(
+            dom.find_where(|node| node.text_len() == 2),
+            handles(&[&[1], &[1, 0], &[1, 0, 0]])
+        );
+        assert_eq!(dom.find_with_attribute("style"), handles(&[&[1, 0]]));
+        assert!(dom.find_with_attribute("class").is_empty());
+    }
+}
@@ -7,6 +7,7 @@
 use std::fmt::Display;
 
 use crate::composer_model::example_format::SelectionWriter;
+use crate::composer_model::generation::Generation;
 use crate::dom::nodes::{ContainerNode, DomNode};
 use crate::dom::to_html::ToHtmlState;
 use crate::dom::to_markdown::{MarkdownError, MarkdownOptions, ToMarkdown};
@@ -15,19 +16,21 @@ use crate::dom::DomLocation;
 use crate::dom::{
     find_range, to_raw_text::ToRawText, DomHandle, Range, ToTree, UnicodeString,
 };
+use crate::instrumentation::enter_span;
 use crate::ToHtml;
 
 use super::to_plain_text::ToPlainText;
 use super::FindResult;
 
-#[derive(Clone, Debug, PartialEq, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Dom<S>
 where
     S: UnicodeString,
 {
     document: DomNode<S>,
+    generation: Generation,
     #[cfg(any(test, feature = "assert-invariants"))]
-    is_transaction_in_progress: bool,
+    pub(super) is_transaction_in_progress: bool,
 }
 
 impl<S> Dom<S>
@@ -41,6 +44,7 @@ where
 
         Self {
             document: DomNode::Container(document),
+            generation: Generation::new(),
             #[cfg(any(test, feature = "assert-invariants"))]
             is_transaction_in_progress: false,
         }
@@ -59,6 +63,7 @@ where
 
         Self {
             document: root_node,
+            generation: Generation::new(),
             #[cfg(any(test, feature = "assert-invariants"))]
             is_transaction_in_progress: false,
         }
@@ -83,6 +88,7 @@ where
     }
 
     pub fn document_mut(&mut self) -> &mut ContainerNode<S> {
+        self.generation = Generation::new();
         // Would be nice if we could avoid this, but it is really convenient
         // in several places to be able to treat document as a DomNode.
         if let DomNode::Container(ret) = &mut self.document {
@@ -92,6 +98,13 @@ where
         }
     }
 
+    /// Identifies the content of this Dom, which is never the same after
+    /// it could have been changed, nor shared with a clone, so that handles
+    /// and work derived from the content can be kept until then.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.get()
+    }
+
     pub fn document_node(&self) -> &DomNode<S> {
         &self.document
     }
@@ -130,29 +143,6 @@ where
         }
     }
 
-    /// Asserts the DOM is in a good state and starts a transaction.
-    /// See [is_transaction_in_progress()].
-    #[cfg(any(test, feature = "assert-invariants"))]
-    pub fn start_transaction(&mut self) {
-        if self.is_transaction_in_progress() {
-            panic!("Cannot start transaction as one is already in progress");
-        }
-        self.assert_invariants();
-        self.is_transaction_in_progress = true;
-    }
-
-    /// Ends the current transaction and asserts the DOM is still in a good
-    /// state.
-    /// See [is_transaction_in_progress()].
-    #[cfg(any(test, feature = "assert-invariants"))]
-    pub fn end_transaction(&mut self) {
-        if !self.is_transaction_in_progress() {
-            panic!("Cannot end transaction as no transaction is in progress");
-        }
-        self.is_transaction_in_progress = false;
-        self.assert_invariants();
-    }
-
     /// Returns the last node handle of the Dom. It's useful for reverse iterators that should start
     /// at the end of the Dom.
     pub fn last_node_handle(&self) -> DomHandle {
@@ -190,7 +180,11 @@ where
         }
     }
 
-    #[cfg(all(feature = "js", target_arch = "wasm32"))]
+    #[cfg(all(
+        feature = "js",
+        target_arch = "wasm32",
+        not(feature = "headless")
+    ))]
     pub(crate) fn take_children(self) -> Vec<DomNode<S>> {
         if let DomNode::Container(container) = self.document {
             container.take_children()
@@ -330,29 +324,48 @@ where
         &self,
         child_handle: &DomHandle,
     ) -> Option<DomHandle> {
+        let nodes = self.nodes_on_path_to(child_handle);
         child_handle
             .with_ancestors()
-            .iter()
+            .into_iter()
+            .zip(nodes)
             .rev()
-            .find(|handle| {
-                if let DomNode::Container(n) = self.lookup_node(handle) {
-                    n.is_list_item()
-                } else {
-                    false
-                }
+            .find(|(_, node)| {
+                matches!(node, DomNode::Container(n) if n.is_list_item())
             })
-            .cloned()
+            .map(|(handle, _)| handle)
     }
 
     /// Determine if a node handle has any container ancestors with the attribute contenteditable=false
     pub fn has_immutable_ancestor(&self, child_handle: &DomHandle) -> bool {
-        child_handle.with_ancestors().iter().rev().any(|handle| {
-            if let DomNode::Container(n) = self.lookup_node(handle) {
-                n.is_immutable()
-            } else {
-                false
-            }
-        })
+        self.nodes_on_path_to(child_handle).into_iter().any(
+            |node| matches!(node, DomNode::Container(n) if n.is_immutable()),
+        )
+    }
+
+    /// The nodes from the root down to the one at `handle`, in the order of
+    /// [DomHandle::with_ancestors], found in one walk down the tree rather
+    /// than by looking each of them up from the root.
+    /// Panics if the handle is unset or invalid
+    pub(crate) fn nodes_on_path_to(
+        &self,
+        handle: &DomHandle,
+    ) -> Vec<&DomNode<S>> {
+        let mut node = self.document_node();
+        let mut nodes = vec![node];
+        for index in handle.raw() {
+            node = match node {
+                DomNode::Container(n) => n.get_child(*index).expect(
+                    "Handle is invalid: it refers to a child index which is \
+                    too large for the number of children in this node.",
+                ),
+                _ => panic!(
+                    "Handle is invalid: refers to the child of a leaf node."
+                ),
+            };
+            nodes.push(node);
+        }
+        nodes
     }
 
     /// Find the node based on its handle.
@@ -391,6 +404,7 @@ where
             )
         }
 
+        self.generation = Generation::new();
         let mut node = &mut self.document;
         if !node_handle.is_set() {
             panic!(
@@ -631,9 +645,18 @@ where
         state: &ToHtmlState,
         as_message: bool,
     ) {
+        let _span = enter_span!(
+            "to_html",
+            text_len = self.text_len(),
+            as_message = as_message,
+        );
         self.document
             .fmt_html(buf, selection_writer, state, as_message)
     }
+
+    fn html_len_hint(&self) -> usize {
+        self.document.html_len_hint()
+    }
 }
 
 impl<S> ToRawText<S> for Dom<S>
@@ -673,10 +696,29 @@ where
         options: &MarkdownOptions,
         as_message: bool,
     ) -> Result<(), MarkdownError<S>> {
+        let _span = enter_span!(
+            "to_markdown",
+            text_len = self.text_len(),
+            as_message = as_message,
+        );
         self.document.fmt_markdown(buffer, options, as_message)
     }
 }
 
+/// Doms are equal if their content is, whatever their generations.
+impl<S> PartialEq for Dom<S>
+where
+    S: UnicodeString,
+{
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(any(test, feature = "assert-invariants"))]
+        if self.is_transaction_in_progress != other.is_transaction_in_progress {
+            return false;
+        }
+        self.document == other.document
+    }
+}
+
 impl<S> Display for Dom<S>
 where
     S: UnicodeString,
@@ -987,6 +1029,23 @@ mod test {
         assert_eq!(range_by_node, actual_range);
     }
 
+    #[test]
+    fn generation_changes_whenever_the_dom_could_change() {
+        let mut d = cm("<b>ab|</b>cd").state.dom;
+        let generation = d.generation();
+        d.lookup_node(&DomHandle::from_raw(vec![0, 0]));
+        assert_eq!(d.generation(), generation);
+
+        let mut clone = d.clone();
+        assert_ne!(clone.generation(), generation);
+        clone.lookup_node_mut(&DomHandle::from_raw(vec![0, 0]));
+        assert_ne!(clone.generation(), generation);
+        d.document_mut();
+        assert_ne!(d.generation(), generation);
+        assert_ne!(d.generation(), clone.generation());
+        assert_eq!(d, clone);
+    }
+
     #[test]
     fn find_range_by_node_root() {
         let d = cm("<b><u>Hello, <i>world|</i></u></b>").state.dom;
@@ -1013,48 +1072,29 @@ mod test {
     }
 
     #[test]
-    fn transaction_succeeds() {
-        let mut d = cm("|").state.dom;
-        d.start_transaction();
-        d.end_transaction();
+    fn text_node_with_distant_immutable_ancestor() {
+        let d = cm("<ul><li><a contenteditable=\"false\" href=\"https://matrix.org\"><b><i>|first</i></b></a></li></ul>").state.dom;
+        assert!(d.has_immutable_ancestor(&DomHandle::from_raw(vec![
+            0, 0, 0, 0, 0, 0
+        ])));
+        assert!(!d.has_immutable_ancestor(&DomHandle::from_raw(vec![0, 0])));
     }
 
     #[test]
-    #[should_panic(expected = "More than one generic container node found")]
-    fn transaction_start_asserts() {
-        let mut bad_dom =
-            dom(vec![&DomNode::Container(ContainerNode::default())]);
-        bad_dom.start_transaction();
-    }
-
-    #[test]
-    #[should_panic(expected = "More than one generic container node found")]
-    fn transaction_end_asserts() {
-        let mut dom = dom(vec![]);
-        dom.start_transaction();
-        let container =
-            DomNode::<Utf16String>::Container(ContainerNode::default());
-        dom.insert(&DomHandle::root().child_handle(0), vec![container]);
-        dom.end_transaction();
-    }
-
-    #[test]
-    #[should_panic(
-        expected = "Cannot start transaction as one is already in progress"
-    )]
-    fn transaction_start_twice() {
-        let mut d = cm("|").state.dom;
-        d.start_transaction();
-        d.start_transaction();
-    }
-
-    #[test]
-    #[should_panic(
-        expected = "Cannot end transaction as no transaction is in progress"
-    )]
-    fn transaction_end_without_start() {
-        let mut d = cm("|").state.dom;
-        d.end_transaction();
+    fn finding_the_deepest_ancestor_list_item() {
+        let d = cm("<ul><li><p>a</p><ol><li><b>b|</b></li></ol></li></ul>")
+            .state
+            .dom;
+        assert_eq!(
+            d.find_ancestor_list_item_or_self(&DomHandle::from_raw(vec![
+                0, 0, 1, 0, 0, 0
+            ])),
+            Some(DomHandle::from_raw(vec![0, 0, 1, 0]))
+        );
+        assert_eq!(
+            d.find_ancestor_list_item_or_self(&DomHandle::from_raw(vec![0])),
+            None
+        );
     }
 
     const NO_CHILDREN: &Vec<DomNode<Utf16String>> = &Vec::new();
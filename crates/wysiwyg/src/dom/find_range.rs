@@ -9,6 +9,7 @@ use crate::dom::nodes::{ContainerNode, DomNode, LineBreakNode, TextNode};
 use crate::dom::range::DomLocation;
 use crate::dom::unicode_string::UnicodeStrExt;
 use crate::dom::{Dom, DomHandle, FindResult, Range};
+use crate::instrumentation::enter_span;
 use crate::UnicodeString;
 use std::cmp::{max, min};
 
@@ -18,6 +19,12 @@ pub fn find_range<S>(dom: &Dom<S>, start: usize, end: usize) -> Range
 where
     S: UnicodeString,
 {
+    let _span = enter_span!(
+        "find_range",
+        start = start,
+        end = end,
+        text_len = dom.text_len(),
+    );
     if dom.children().is_empty() {
         return Range::new(Vec::new());
     }
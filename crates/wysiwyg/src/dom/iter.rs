@@ -16,7 +16,7 @@ use std::collections::HashSet;
 
 use super::{
     nodes::{ContainerNode, MentionNode, TextNode},
-    Dom,
+    Dom, DomLocation, Range,
 };
 
 impl<S> Dom<S>
@@ -82,6 +82,24 @@ where
         self.iter_from(node).filter_map(DomNode::as_text)
     }
 
+    /// Return an iterator over the leaf nodes intersecting `range`, in
+    /// depth-first order, each with the part of it which is in the range.
+    /// Leaves which only touch the edge of a selection are left out, while
+    /// for a cursor every leaf it is in or next to is included.
+    pub fn iter_leaves_in<'a>(
+        &'a self,
+        range: &'a Range,
+    ) -> impl Iterator<Item = LeafInRange<'a, S>> {
+        let is_cursor = range.is_cursor();
+        range
+            .leaves()
+            .map(|location| LeafInRange {
+                node: self.lookup_node(&location.node_handle),
+                location,
+            })
+            .filter(move |leaf| is_cursor || !leaf.offsets().is_empty())
+    }
+
     /// Return the previous node in the DOM, if exists, in depth-first order.
     pub fn prev_node(&mut self, handle: &DomHandle) -> Option<&DomNode<S>> {
         let mut iter = self.iter_from_handle(handle);
@@ -164,6 +182,37 @@ where
     child_index: usize,
 }
 
+/// A leaf node intersecting a [Range], see [Dom::iter_leaves_in].
+#[derive(Clone, Copy, Debug)]
+pub struct LeafInRange<'a, S>
+where
+    S: UnicodeString,
+{
+    pub node: &'a DomNode<S>,
+    /// Where the leaf is in the Dom, and which part of it is in the range
+    pub location: &'a DomLocation,
+}
+
+impl<S> LeafInRange<'_, S>
+where
+    S: UnicodeString,
+{
+    pub fn handle(&self) -> &DomHandle {
+        &self.location.node_handle
+    }
+
+    /// The code units of this leaf which are in the range, counted from
+    /// its start, whichever way round the range was found.
+    pub fn offsets(&self) -> std::ops::Range<usize> {
+        let DomLocation {
+            start_offset,
+            end_offset,
+            ..
+        } = *self.location;
+        start_offset.min(end_offset)..start_offset.max(end_offset)
+    }
+}
+
 pub struct DomIterator<'a, S>
 where
     S: UnicodeString,
@@ -405,6 +454,8 @@ where
 
 #[cfg(test)]
 mod test {
+    use std::ops::Range;
+
     use widestring::Utf16String;
 
     use crate::tests::testutils_composer_model::cm;
@@ -693,6 +744,42 @@ mod test {
         );
     }
 
+    #[test]
+    fn can_walk_the_leaves_in_a_selection() {
+        let dom = cm("<b>ab</b>cd<i>ef</i>|").state.dom;
+        let leaves = |start, end| -> Vec<(String, Range<usize>)> {
+            let range = dom.find_range(start, end);
+            dom.iter_leaves_in(&range)
+                .map(|leaf| (node_txt(leaf.node), leaf.offsets()))
+                .collect()
+        };
+
+        assert_eq!(
+            leaves(1, 3),
+            vec![(String::from("'ab'"), 1..2), (String::from("'cd'"), 0..1)]
+        );
+        assert_eq!(leaves(2, 4), vec![(String::from("'cd'"), 0..2)]);
+        assert_eq!(leaves(6, 0), leaves(0, 6));
+    }
+
+    #[test]
+    fn can_walk_the_leaves_at_a_cursor() {
+        let dom = cm("<b>ab</b>cd|").state.dom;
+        let range = dom.find_range(2, 2);
+        let leaves: Vec<(DomHandle, Range<usize>)> = dom
+            .iter_leaves_in(&range)
+            .map(|leaf| (leaf.handle().clone(), leaf.offsets()))
+            .collect();
+
+        assert_eq!(
+            leaves,
+            vec![
+                (DomHandle::from_raw(vec![0, 0]), 2..2),
+                (DomHandle::from_raw(vec![1]), 0..0)
+            ]
+        );
+    }
+
     fn node_txt(node: &DomNode<Utf16String>) -> String {
         match node {
             DomNode::Container(c) => c.name().to_string(),
@@ -0,0 +1,179 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::fmt;
+
+use url::Url;
+
+/// The attributes which the Matrix spec allows on each tag of the HTML in
+/// a message. Other attributes should be removed by clients.
+const ALLOWED_ATTRIBUTES: [(&str, &[&str]); 7] = [
+    ("a", &["href", "target"]),
+    ("code", &["class"]),
+    ("div", &["data-mx-maths"]),
+    ("font", &["color", "data-mx-bg-color", "data-mx-color"]),
+    ("img", &["alt", "height", "src", "title", "width"]),
+    ("ol", &["start"]),
+    (
+        "span",
+        &[
+            "data-mx-bg-color",
+            "data-mx-color",
+            "data-mx-maths",
+            "data-mx-spoiler",
+        ],
+    ),
+];
+
+/// The schemes the Matrix spec allows for links, along with `matrix:` for
+/// mentions.
+const ALLOWED_LINK_SCHEMES: [&str; 6] =
+    ["ftp", "http", "https", "magnet", "mailto", "matrix"];
+
+/// A problem found by [crate::validate_message_html] in HTML meant for the
+/// formatted body of a Matrix message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HtmlIssue {
+    /// The HTML could not be parsed, for the given reason
+    Malformed(String),
+    /// An element which Matrix messages can't contain, e.g. `<table>`, or a
+    /// `<span>` without any attributes
+    UnknownTag { tag: String },
+    /// An element other than `<li>` directly inside a list
+    NotAListItem { tag: String },
+    /// An `<li>` outside a list
+    ListItemOutsideList,
+    /// An attribute which the Matrix spec doesn't allow on its tag
+    BadAttribute { tag: String, attribute: String },
+    /// A link or image whose URI doesn't have a scheme the Matrix spec
+    /// allows for it, e.g. `javascript:`
+    BadUriScheme {
+        tag: String,
+        attribute: String,
+        uri: String,
+    },
+}
+
+impl fmt::Display for HtmlIssue {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(reason) => write!(formatter, "{reason}"),
+            Self::UnknownTag { tag } => {
+                write!(formatter, "Node `{tag}` is not supported")
+            }
+            Self::NotAListItem { .. } => write!(
+                formatter,
+                "Invalid list item node: a list must only contain list items"
+            ),
+            Self::ListItemOutsideList => {
+                write!(formatter, "Parent node is not a list")
+            }
+            Self::BadAttribute { tag, attribute } => write!(
+                formatter,
+                "Attribute `{attribute}` is not allowed on `{tag}`"
+            ),
+            Self::BadUriScheme {
+                tag,
+                attribute,
+                uri,
+            } => write!(
+                formatter,
+                "The scheme of `{uri}` is not allowed in the `{attribute}` of `{tag}`"
+            ),
+        }
+    }
+}
+
+/// The issues with the given attributes of a tag, as the Matrix spec
+/// describes which attributes and URI schemes are allowed.
+pub(crate) fn attribute_issues<'a>(
+    tag: &str,
+    attributes: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Vec<HtmlIssue> {
+    let allowed = ALLOWED_ATTRIBUTES
+        .iter()
+        .find(|(t, _)| *t == tag)
+        .map_or(&[][..], |(_, allowed)| allowed);
+    attributes
+        .filter_map(|(name, value)| {
+            if !allowed.contains(&name)
+                || (name == "class" && !value.starts_with("language-"))
+            {
+                Some(HtmlIssue::BadAttribute {
+                    tag: tag.to_owned(),
+                    attribute: name.to_owned(),
+                })
+            } else if !uri_scheme_is_allowed(tag, name, value) {
+                Some(HtmlIssue::BadUriScheme {
+                    tag: tag.to_owned(),
+                    attribute: name.to_owned(),
+                    uri: value.to_owned(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether the value of an attribute holding a URI has an allowed scheme.
+/// Links may use the [ALLOWED_LINK_SCHEMES], and images only `mxc:`.
+fn uri_scheme_is_allowed(tag: &str, name: &str, value: &str) -> bool {
+    let schemes: &[&str] = match (tag, name) {
+        ("a", "href") => &ALLOWED_LINK_SCHEMES,
+        ("img", "src") => &["mxc"],
+        _ => return true,
+    };
+    Url::parse(value).is_ok_and(|url| schemes.contains(&url.scheme()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn issues(tag: &str, attributes: &[(&str, &str)]) -> Vec<HtmlIssue> {
+        attribute_issues(tag, attributes.iter().copied())
+    }
+
+    #[test]
+    fn allowed_attributes_have_no_issues() {
+        assert!(issues("a", &[("href", "https://matrix.org")]).is_empty());
+        assert!(issues("ol", &[("start", "3")]).is_empty());
+        assert!(issues("code", &[("class", "language-rust")]).is_empty());
+        assert!(issues("img", &[("src", "mxc://matrix.org/a")]).is_empty());
+    }
+
+    #[test]
+    fn other_attributes_are_bad() {
+        assert_eq!(
+            issues("p", &[("style", "color: red")]),
+            vec![HtmlIssue::BadAttribute {
+                tag: "p".into(),
+                attribute: "style".into(),
+            }]
+        );
+        assert_eq!(
+            issues("code", &[("class", "big")]),
+            vec![HtmlIssue::BadAttribute {
+                tag: "code".into(),
+                attribute: "class".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn uris_must_have_allowed_schemes() {
+        assert_eq!(
+            issues("a", &[("href", "javascript:alert(1)")]),
+            vec![HtmlIssue::BadUriScheme {
+                tag: "a".into(),
+                attribute: "href".into(),
+                uri: "javascript:alert(1)".into(),
+            }]
+        );
+        assert_eq!(issues("a", &[("href", "/relative")]).len(), 1);
+        assert_eq!(issues("img", &[("src", "https://x.org/a.png")]).len(), 1);
+    }
+}
@@ -4,13 +4,22 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
-use crate::char::CharExt;
 use crate::composer_model::example_format::SelectionWriter;
+use crate::dom::bidi_isolation::{
+    FIRST_STRONG_ISOLATE, POP_DIRECTIONAL_ISOLATE,
+};
 use crate::dom::dom_handle::DomHandle;
 use crate::dom::nodes::dom_node::{DomNode, DomNodeKind};
-use crate::dom::to_html::{ToHtml, ToHtmlExt, ToHtmlState};
+use crate::dom::placeholder_policy::EmptyBlock;
+use crate::dom::to_html::{
+    fmt_attribute, fmt_attributes, ToHtml, ToHtmlExt, ToHtmlState,
+};
 use crate::dom::to_markdown::{MarkdownError, MarkdownOptions, ToMarkdown};
 use crate::dom::to_plain_text::ToPlainText;
 use crate::dom::to_raw_text::ToRawText;
@@ -29,9 +38,95 @@ where
     attrs: Option<Vec<(S, S)>>,
     children: Vec<DomNode<S>>,
     handle: DomHandle,
+    content_cache: ContentCache,
+    /// Whether this has the attribute contenteditable=false, kept up to
+    /// date with its attributes as it is checked for each ancestor of many
+    /// nodes e.g. when deleting around mentions.
+    immutable: bool,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Memoized results of [ContainerNode::text_len] and
+/// [ContainerNode::content_hash].
+///
+/// Any mutable access to the children or attributes of a container resets
+/// it, so each is recomputed lazily the next time it is needed. Since a
+/// descendant can only be reached mutably through each of its ancestors,
+/// this also resets the cache of every container above a modified node.
+///
+/// The cache never affects comparisons: containers with the same content are
+/// equal whether or not their length has been computed yet.
+struct ContentCache {
+    text_len: AtomicUsize,
+    hash: AtomicU64,
+}
+
+impl ContentCache {
+    const UNKNOWN_LEN: usize = usize::MAX;
+    const UNKNOWN_HASH: u64 = u64::MAX;
+
+    fn text_len(&self, compute: impl FnOnce() -> usize) -> usize {
+        let cached = self.text_len.load(Ordering::Relaxed);
+        if cached != Self::UNKNOWN_LEN {
+            return cached;
+        }
+        let len = compute();
+        self.text_len.store(len, Ordering::Relaxed);
+        len
+    }
+
+    fn hash(&self) -> Option<u64> {
+        let cached = self.hash.load(Ordering::Relaxed);
+        (cached != Self::UNKNOWN_HASH).then_some(cached)
+    }
+
+    fn set_hash(&self, hash: u64) -> u64 {
+        // Give up the one hash which means unknown, rather than computing it
+        // again every time
+        let hash = hash.min(Self::UNKNOWN_HASH - 1);
+        self.hash.store(hash, Ordering::Relaxed);
+        hash
+    }
+
+    fn reset(&mut self) {
+        *self.text_len.get_mut() = Self::UNKNOWN_LEN;
+        *self.hash.get_mut() = Self::UNKNOWN_HASH;
+    }
+}
+
+impl Default for ContentCache {
+    fn default() -> Self {
+        Self {
+            text_len: AtomicUsize::new(Self::UNKNOWN_LEN),
+            hash: AtomicU64::new(Self::UNKNOWN_HASH),
+        }
+    }
+}
+
+impl Clone for ContentCache {
+    fn clone(&self) -> Self {
+        Self {
+            text_len: AtomicUsize::new(self.text_len.load(Ordering::Relaxed)),
+            hash: AtomicU64::new(self.hash.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl PartialEq for ContentCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for ContentCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.text_len.load(Ordering::Relaxed) {
+            Self::UNKNOWN_LEN => write!(f, "ContentCache(unknown)"),
+            len => write!(f, "ContentCache({len})"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ContainerNodeKind<S>
 where
     S: UnicodeString,
@@ -44,6 +139,8 @@ where
     CodeBlock,
     Quote,
     Paragraph,
+    /// A `<span>`, kept for the attributes on it
+    Span,
 }
 
 impl<S: dom::unicode_string::UnicodeString> Default for ContainerNode<S> {
@@ -61,6 +158,20 @@ impl<S: dom::unicode_string::UnicodeString> Default for ContainerNode<S> {
     }
 }
 
+/// Hashes the content of the node, but not its handle, so that the same
+/// content hashes the same wherever it is in the Dom.
+impl<S> Hash for ContainerNode<S>
+where
+    S: UnicodeString,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.kind.hash(state);
+        self.attrs.hash(state);
+        self.children.hash(state);
+    }
+}
+
 impl<S> ContainerNode<S>
 where
     S: UnicodeString,
@@ -78,9 +189,11 @@ where
         Self {
             name,
             kind,
+            immutable: has_contenteditable_false(attrs.iter().flatten()),
             attrs,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
         }
     }
 
@@ -91,6 +204,8 @@ where
             attrs: None,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
+            immutable: false,
         }
     }
 
@@ -105,6 +220,8 @@ where
             attrs: None,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
+            immutable: false,
         }
     }
 
@@ -118,6 +235,8 @@ where
             attrs: None,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
+            immutable: false,
         }
     }
 
@@ -129,9 +248,11 @@ where
         Self {
             name: list_type.tag().into(),
             kind: ContainerNodeKind::List(list_type),
+            immutable: has_contenteditable_false(attrs.iter().flatten()),
             attrs,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
         }
     }
 
@@ -142,6 +263,8 @@ where
             attrs: None,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
+            immutable: false,
         }
     }
 
@@ -152,6 +275,23 @@ where
             attrs: None,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
+            immutable: false,
+        }
+    }
+
+    pub fn new_span(
+        children: Vec<DomNode<S>>,
+        attrs: Option<Vec<(S, S)>>,
+    ) -> Self {
+        Self {
+            name: "span".into(),
+            kind: ContainerNodeKind::Span,
+            immutable: has_contenteditable_false(attrs.iter().flatten()),
+            attrs,
+            children,
+            handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
         }
     }
 
@@ -162,6 +302,8 @@ where
             attrs: None,
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
+            immutable: false,
         }
     }
 
@@ -172,6 +314,7 @@ where
         let child_handle = self.handle.child_handle(child_index);
         child.set_handle(child_handle.clone());
         self.children.push(child);
+        self.content_cache.reset();
         child_handle
     }
 
@@ -186,6 +329,7 @@ where
         assert!(index < self.children().len());
 
         let ret = self.children.remove(index);
+        self.content_cache.reset();
 
         for child_index in index..self.children.len() {
             let new_handle = self.handle.child_handle(child_index);
@@ -203,6 +347,7 @@ where
         nodes: Vec<DomNode<S>>,
     ) -> Vec<DomHandle> {
         let mut handles = Vec::new();
+        self.content_cache.reset();
 
         let mut current_index = index;
         for mut node in nodes {
@@ -231,10 +376,12 @@ where
         assert!(index < self.children().len());
 
         self.children.remove(index);
+        self.content_cache.reset();
         self.insert_children(index, nodes)
     }
 
     pub fn get_child_mut(&mut self, idx: usize) -> Option<&mut DomNode<S>> {
+        self.content_cache.reset();
         self.children.get_mut(idx)
     }
 
@@ -247,6 +394,7 @@ where
     }
 
     pub fn last_child_mut(&mut self) -> Option<&mut DomNode<S>> {
+        self.content_cache.reset();
         self.children.last_mut()
     }
 
@@ -259,6 +407,7 @@ where
         assert!(index <= self.children().len());
 
         self.children.insert(index, node);
+        self.content_cache.reset();
 
         for i in index..self.children.len() {
             let new_handle = self.handle.child_handle(i);
@@ -274,8 +423,62 @@ where
 
     pub fn set_handle(&mut self, handle: DomHandle) {
         self.handle = handle;
-        for (i, child) in self.children.iter_mut().enumerate() {
-            child.set_handle(self.handle.child_handle(i))
+        // Use an explicit stack rather than recursing, so that deeply nested
+        // content can't overflow the call stack.
+        let mut stack = vec![self];
+        while let Some(container) = stack.pop() {
+            let ContainerNode {
+                handle, children, ..
+            } = container;
+            for (i, child) in children.iter_mut().enumerate() {
+                let child_handle = handle.child_handle(i);
+                if let DomNode::Container(child) = child {
+                    child.handle = child_handle;
+                    stack.push(child);
+                } else {
+                    child.set_handle(child_handle);
+                }
+            }
+        }
+    }
+
+    /// Merge each run of adjacent text nodes in this container, and in all
+    /// the containers below it, into a single text node.
+    pub(crate) fn merge_adjacent_text_nodes(&mut self) {
+        // As in set_handle, use an explicit stack rather than recursing. The
+        // flag says whether the handles below the container need updating.
+        let mut stack = vec![(self, false)];
+        while let Some((container, moved)) = stack.pop() {
+            let ContainerNode {
+                handle,
+                children,
+                content_cache,
+                ..
+            } = container;
+            let len = children.len();
+            children.dedup_by(|next, prev| match (prev, next) {
+                (DomNode::Text(prev), DomNode::Text(next)) => {
+                    prev.push(next);
+                    true
+                }
+                _ => false,
+            });
+            let moved = moved || children.len() != len;
+            // The text nodes below an ancestor may have been merged, so its
+            // hash can't be kept either
+            content_cache.reset();
+            for (i, child) in children.iter_mut().enumerate() {
+                match child {
+                    DomNode::Container(child) => {
+                        if moved {
+                            child.handle = handle.child_handle(i);
+                        }
+                        stack.push((child, moved));
+                    }
+                    _ if moved => child.set_handle(handle.child_handle(i)),
+                    _ => {}
+                }
+            }
         }
     }
 
@@ -287,6 +490,21 @@ where
         self.attrs.as_ref()
     }
 
+    /// Add attributes to this node, skipping any it already has.
+    pub(crate) fn add_attributes(&mut self, attributes: Vec<(S, S)>) {
+        if attributes.is_empty() {
+            return;
+        }
+        self.content_cache.reset();
+        let attrs = self.attrs.get_or_insert_with(Vec::new);
+        for (name, value) in attributes {
+            if !attrs.iter().any(|(n, _)| *n == name) {
+                attrs.push((name, value));
+            }
+        }
+        self.immutable = has_contenteditable_false(attrs.iter());
+    }
+
     pub fn children(&self) -> &Vec<DomNode<S>> {
         &self.children
     }
@@ -297,6 +515,7 @@ where
 
     /// Removes all children from the container and returns them
     pub(crate) fn remove_children(&mut self) -> Vec<DomNode<S>> {
+        self.content_cache.reset();
         self.children.drain(..).collect()
     }
 
@@ -304,6 +523,7 @@ where
         &mut self,
         position: usize,
     ) -> Vec<DomNode<S>> {
+        self.content_cache.reset();
         self.children.drain(position..self.children.len()).collect()
     }
 
@@ -316,9 +536,7 @@ where
     }
 
     pub fn is_immutable(&self) -> bool {
-        self.attributes()
-            .unwrap_or(&vec![])
-            .contains(&("contenteditable".into(), "false".into()))
+        self.immutable
     }
 
     pub fn is_immutable_link(&self) -> bool {
@@ -337,6 +555,33 @@ where
         matches!(&self.kind, ContainerNodeKind::List(f) if f == list_type)
     }
 
+    /// The `start` attribute of an ordered list, if it has one.
+    fn list_start(&self) -> Option<usize> {
+        self.attrs
+            .iter()
+            .flatten()
+            .find(|(name, _)| *name == S::from("start"))
+            .and_then(|(_, value)| value.to_string().parse().ok())
+    }
+
+    /// Whether `next`, a list straight after this one, carries on with its
+    /// numbering, so that the two lists can be joined. Lists which don't
+    /// say where they start always do, while e.g. the separate list MS Word
+    /// writes for each item when copying does if each starts after the
+    /// last.
+    pub(crate) fn is_continued_by(&self, next: &ContainerNode<S>) -> bool {
+        if !self.is_list_of_type(&ListType::Ordered) {
+            return true;
+        }
+        match (self.list_start(), next.list_start()) {
+            (None, None) => true,
+            (start, next_start) => {
+                next_start.unwrap_or(1)
+                    == start.unwrap_or(1) + self.children.len()
+            }
+        }
+    }
+
     pub(crate) fn is_structure_node(&self) -> bool {
         use ContainerNodeKind::*;
 
@@ -358,20 +603,74 @@ where
         DomNodeKind::from_container_kind(&self.kind).is_block_kind()
     }
 
+    /// Returns the number of code units in the text of this container,
+    /// counting an extra one between each pair of block children.
+    ///
+    /// The result is memoized until the children are next modified.
     pub fn text_len(&self) -> usize {
-        let children_len: usize =
-            self.children.iter().map(|child| child.text_len()).sum();
-        let block_nodes_extra: usize = self
-            .children
-            .iter()
-            .filter(|child| child.is_block_node())
-            .count();
-        let block_nodes_extra = if block_nodes_extra > 0 {
-            block_nodes_extra - 1
-        } else {
-            block_nodes_extra
-        };
-        children_len + block_nodes_extra
+        self.content_cache.text_len(|| {
+            let children_len: usize =
+                self.children.iter().map(|child| child.text_len()).sum();
+            let block_nodes_extra: usize = self
+                .children
+                .iter()
+                .filter(|child| child.is_block_node())
+                .count();
+            let block_nodes_extra = if block_nodes_extra > 0 {
+                block_nodes_extra - 1
+            } else {
+                block_nodes_extra
+            };
+            children_len + block_nodes_extra
+        })
+    }
+
+    /// A hash of the name, kind and attributes of this container and of
+    /// everything below it, which is the same for equal containers.
+    ///
+    /// The result is memoized until the container is next modified, so
+    /// hashing a document again after an edit only rehashes the containers
+    /// above the edit. It is made with [DefaultHasher], whose algorithm std
+    /// may change between Rust releases, so it is only comparable with
+    /// hashes from the same process and must not be persisted.
+    pub(crate) fn content_hash(&self) -> u64 {
+        if let Some(hash) = self.content_cache.hash() {
+            return hash;
+        }
+        // Hash the containers below this one first, with an explicit stack
+        // rather than recursing, so that deeply nested content can't
+        // overflow the call stack. The flag says whether the children of
+        // the container have been hashed.
+        let mut stack = vec![(self, false)];
+        let mut hash = 0;
+        while let Some((container, children_hashed)) = stack.pop() {
+            if children_hashed {
+                hash = container
+                    .content_cache
+                    .set_hash(container.hash_with_hashed_children());
+                continue;
+            }
+            stack.push((container, true));
+            for child in &container.children {
+                if let DomNode::Container(child) = child {
+                    if child.content_cache.hash().is_none() {
+                        stack.push((child, false));
+                    }
+                }
+            }
+        }
+        hash
+    }
+
+    fn hash_with_hashed_children(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.name.hash(&mut hasher);
+        self.kind.hash(&mut hasher);
+        self.attrs.hash(&mut hasher);
+        for child in &self.children {
+            hasher.write_u64(child.content_hash());
+        }
+        hasher.finish()
     }
 
     pub fn new_link(
@@ -387,9 +686,11 @@ where
         Self {
             name: "a".into(),
             kind: ContainerNodeKind::Link(url),
+            immutable: has_contenteditable_false(&attributes),
             attrs: Some(attributes),
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
         }
     }
 
@@ -403,6 +704,7 @@ where
     pub(crate) fn set_list_type(&mut self, list_type: ListType) {
         match self.kind {
             ContainerNodeKind::List(_) => {
+                self.content_cache.reset();
                 self.name = list_type.tag().into();
                 self.kind = ContainerNodeKind::List(list_type);
             }
@@ -431,12 +733,15 @@ where
             attrs: self.attrs.clone(),
             children,
             handle: DomHandle::new_unset(),
+            content_cache: ContentCache::default(),
+            immutable: self.immutable,
         }
     }
 
     /// Remove leading Line break char from this container.
     /// Returns false if no updates were done.
     pub fn remove_leading_line_break(&mut self) -> bool {
+        self.content_cache.reset();
         let Some(first_child) = self.children.get_mut(0) else {
             return false;
         };
@@ -469,11 +774,13 @@ where
         if other_node.kind != self.kind {
             panic!("Trying to push a non-matching container kind");
         }
-        let last_child = self.children.last().unwrap();
-        let other_node_first_child = other_node.get_child(0).unwrap();
-        if last_child.can_push(other_node_first_child) {
-            let mut next_child = other_node.remove_child(0);
-            self.last_child_mut().unwrap().push(&mut next_child);
+        if let (Some(last_child), Some(other_node_first_child)) =
+            (self.children.last(), other_node.get_child(0))
+        {
+            if last_child.can_push(other_node_first_child) {
+                let mut next_child = other_node.remove_child(0);
+                self.last_child_mut().unwrap().push(&mut next_child);
+            }
         }
         while !other_node.children().is_empty() {
             let child = other_node.remove_child(0);
@@ -488,6 +795,7 @@ where
     pub fn slice_after(&mut self, position: usize) -> ContainerNode<S> {
         assert!(position <= self.text_len());
         let result = self.find_slice_location(position);
+        self.content_cache.reset();
 
         match result {
             ControlFlow::Continue(_) => self.clone_with_new_children(vec![]),
@@ -520,6 +828,7 @@ where
     pub fn slice_before(&mut self, position: usize) -> ContainerNode<S> {
         assert!(position <= self.text_len());
         let result = self.find_slice_location(position);
+        self.content_cache.reset();
 
         match result {
             ControlFlow::Continue(_) => self.clone_with_new_children(vec![]),
@@ -626,6 +935,12 @@ where
             ),
         };
     }
+
+    fn html_len_hint(&self) -> usize {
+        // Tags and attributes usually take up about as much space as the
+        // text they wrap
+        self.text_len() * 2
+    }
 }
 
 impl<S: UnicodeString> ContainerNode<S> {
@@ -637,12 +952,32 @@ impl<S: UnicodeString> ContainerNode<S> {
         state: &ToHtmlState,
         as_message: bool,
     ) {
-        let name = self.name();
+        let tag = match &self.kind {
+            ContainerNodeKind::Formatting(format) => {
+                state.formatting_tags.tag_for(format).map(S::from)
+            }
+            _ => None,
+        };
+        let name = tag.as_deref().unwrap_or(self.name());
         if !name.is_empty() {
-            self.fmt_tag_open(name, formatter, &self.attrs);
+            self.fmt_block_tag_open(
+                name,
+                formatter,
+                &self.attrs,
+                state.omits_style(as_message),
+                state,
+            );
         }
 
+        let isolate =
+            as_message && state.isolate_message_bidi && self.is_link();
+        if isolate {
+            formatter.push(FIRST_STRONG_ISOLATE);
+        }
         self.fmt_children_html(formatter, selection_writer, state, as_message);
+        if isolate {
+            formatter.push(POP_DIRECTIONAL_ISOLATE);
+        }
 
         if !name.is_empty() {
             self.fmt_tag_close(name, formatter);
@@ -685,9 +1020,14 @@ impl<S: UnicodeString> ContainerNode<S> {
         assert!(matches!(self.kind, ContainerNodeKind::Paragraph));
         let name = self.name();
 
-        self.fmt_tag_open(name, formatter, &self.attrs);
+        self.fmt_block_tag_open(name, formatter, &self.attrs, false, state);
         if self.is_empty() {
-            formatter.push(char::nbsp());
+            if let Some(placeholder) = state
+                .placeholder_policy
+                .placeholder_for(EmptyBlock::Paragraph, as_message)
+            {
+                formatter.push(placeholder);
+            }
         }
         self.fmt_children_html(formatter, selection_writer, state, as_message);
         self.fmt_tag_close(name, formatter);
@@ -733,10 +1073,16 @@ impl<S: UnicodeString> ContainerNode<S> {
         as_message: bool,
     ) {
         assert!(matches!(self.kind, ContainerNodeKind::Paragraph));
-        if self.is_empty()
-            && (state.next_sibling.is_none() || state.prev_sibling.is_none())
-        {
-            formatter.push(char::nbsp());
+        if self.is_empty() {
+            let line = EmptyBlock::CodeLine {
+                is_first: state.prev_sibling.is_none(),
+                is_last: state.next_sibling.is_none(),
+            };
+            if let Some(placeholder) =
+                state.placeholder_policy.placeholder_for(line, as_message)
+            {
+                formatter.push(placeholder);
+            }
         }
         self.fmt_children_html(formatter, selection_writer, state, as_message);
         if state.next_sibling.is_some() {
@@ -752,11 +1098,34 @@ impl<S: UnicodeString> ContainerNode<S> {
         as_message: bool,
     ) {
         assert!(matches!(self.kind, ContainerNodeKind::CodeBlock));
-        self.fmt_tag_open(&S::from("pre"), formatter, &self.attrs);
+        // The class (i.e. the language) belongs on the code tag
+        let attrs_where = |is_class: bool| {
+            let attrs: Vec<(S, S)> = self
+                .attrs
+                .iter()
+                .flatten()
+                .filter(|(name, _)| (*name == S::from("class")) == is_class)
+                .cloned()
+                .collect();
+            Some(attrs).filter(|attrs| !attrs.is_empty())
+        };
+        let omit_style = state.omits_style(as_message);
+        self.fmt_block_tag_open(
+            &S::from("pre"),
+            formatter,
+            &attrs_where(false),
+            omit_style,
+            state,
+        );
         let mut state = state.clone();
         state.is_inside_code_block = true;
 
-        self.fmt_tag_open(&S::from("code"), formatter, &None::<Vec<(S, S)>>);
+        self.fmt_tag_open(
+            &S::from("code"),
+            formatter,
+            &attrs_where(true),
+            omit_style,
+        );
 
         self.fmt_children_html(formatter, selection_writer, &state, as_message);
 
@@ -764,6 +1133,25 @@ impl<S: UnicodeString> ContainerNode<S> {
         self.fmt_tag_close(&S::from("pre"), formatter);
     }
 
+    /// Like [ToHtmlExt::fmt_tag_open], also writing the `data-block-id` of
+    /// the state, if any.
+    fn fmt_block_tag_open(
+        &self,
+        name: &S::Str,
+        formatter: &mut S,
+        attrs: &Option<Vec<(S, S)>>,
+        omit_style: bool,
+        state: &ToHtmlState,
+    ) {
+        formatter.push('<');
+        formatter.push(name);
+        fmt_attributes(formatter, attrs.iter().flatten(), omit_style);
+        if let Some(block_id) = state.block_id {
+            fmt_attribute(formatter, "data-block-id", &block_id.to_string());
+        }
+        formatter.push('>');
+    }
+
     fn fmt_children_html(
         &self,
         formatter: &mut S,
@@ -790,12 +1178,13 @@ impl<S: UnicodeString> ContainerNode<S> {
             }
         }
     }
-    fn updated_state(
+    pub(crate) fn updated_state(
         &self,
         initial_state: &ToHtmlState,
         child_index: usize,
     ) -> ToHtmlState {
         let mut state = initial_state.clone();
+        state.block_id = None;
         state.next_sibling =
             self.children().get(child_index + 1).map(|n| n.kind());
         state.prev_sibling = if child_index == 0 {
@@ -813,8 +1202,13 @@ where
 {
     fn to_raw_text(&self) -> S {
         let mut text = S::default();
-        for child in &self.children {
-            text.push(child.to_raw_text());
+        let mut stack: Vec<&DomNode<S>> = self.children.iter().rev().collect();
+        while let Some(node) = stack.pop() {
+            if let DomNode::Container(container) = node {
+                stack.extend(container.children.iter().rev());
+            } else {
+                text.push(node.to_raw_text());
+            }
         }
         text
     }
@@ -920,7 +1314,8 @@ where
         let mut options = *options;
 
         match self.kind() {
-            Generic => {
+            // Markdown has no spans, so only their content is kept.
+            Generic | Span => {
                 fmt_children(self, buffer, &options, as_message)?;
             }
 
@@ -1115,6 +1510,7 @@ where
             buffer.push("`` ");
 
             options.insert(MarkdownOptions::IGNORE_LINE_BREAK);
+            options.remove(MarkdownOptions::ESCAPE_TEXT);
             fmt_children(this, buffer, options, as_message)?;
 
             buffer.push(" ``");
@@ -1133,6 +1529,18 @@ where
         where
             S: UnicodeString,
         {
+            // An autolink can't contain spaces or angle brackets
+            let is_autolink = options
+                .contains(MarkdownOptions::AUTOLINK_BARE_URLS)
+                && this.to_raw_text() == *url
+                && !url.to_string().contains([' ', '<', '>']);
+            if is_autolink {
+                buffer.push('<');
+                buffer.push(url.clone());
+                buffer.push('>');
+                return Ok(());
+            }
+
             buffer.push('[');
 
             fmt_children(this, buffer, options, as_message)?;
@@ -1318,8 +1726,12 @@ where
         where
             S: UnicodeString,
         {
+            // Text in code is never read as Markdown, so needs no escaping
+            let mut options = *options;
+            options.remove(MarkdownOptions::ESCAPE_TEXT);
+
             buffer.push("```\n");
-            fmt_children(this, buffer, options, as_message)?;
+            fmt_children(this, buffer, &options, as_message)?;
             buffer.push("\n```\n");
 
             Ok(())
@@ -1359,6 +1771,17 @@ where
     }
 }
 
+fn has_contenteditable_false<'a, S>(
+    attrs: impl IntoIterator<Item = &'a (S, S)>,
+) -> bool
+where
+    S: UnicodeString + 'a,
+{
+    attrs.into_iter().any(|(name, value)| {
+        *name == S::from("contenteditable") && *value == S::from("false")
+    })
+}
+
 #[cfg(test)]
 mod test {
     use widestring::Utf16String;
@@ -1417,6 +1840,34 @@ mod test {
         assert_eq!(text_node3.handle().raw(), &[4, 5, 4, 1]);
     }
 
+    #[test]
+    fn merging_adjacent_text_nodes_sets_the_correct_handles_after() {
+        let mut node = container_with_handle(&[4, 5, 4]);
+        node.append_child(text_node("0"));
+        node.append_child(text_node("1"));
+        node.append_child(DomNode::Container(format_container_with_handle(
+            InlineFormatType::Bold,
+            &[],
+        )));
+        node.append_child(text_node("2"));
+        if let DomNode::Container(bold) = &mut node.children[2] {
+            bold.append_child(text_node("b0"));
+            bold.append_child(text_node("b1"));
+        }
+
+        node.merge_adjacent_text_nodes();
+
+        assert_eq!(
+            node.to_html(),
+            utf16("<div>01<strong>b0b1</strong>2</div>")
+        );
+        let bold = node.children[1].as_container().unwrap();
+        assert_eq!(bold.handle().raw(), &[4, 5, 4, 1]);
+        assert_eq!(bold.children().len(), 1);
+        assert_eq!(bold.children()[0].handle().raw(), &[4, 5, 4, 1, 0]);
+        assert_eq!(node.children[2].handle().raw(), &[4, 5, 4, 2]);
+    }
+
     #[test]
     fn inserting_children_updates_the_relevant_handles() {
         let mut node = container_with_handle(&[4, 5, 4]);
@@ -1576,6 +2027,76 @@ mod test {
         container.slice_after(42);
     }
 
+    #[test]
+    fn text_len_is_updated_when_a_descendant_changes() {
+        let mut container = create_container_with_nested_children();
+        assert_eq!(container.text_len(), 6);
+
+        let DomNode::Container(italic) = container.get_child_mut(0).unwrap()
+        else {
+            panic!("Expected a container");
+        };
+        let DomNode::Text(text) = italic.get_child_mut(0).unwrap() else {
+            panic!("Expected a text node");
+        };
+        text.set_data(utf16("abcd"));
+        assert_eq!(container.text_len(), 7);
+
+        container.remove_child(1);
+        assert_eq!(container.text_len(), 4);
+    }
+
+    #[test]
+    fn content_hash_is_updated_when_a_descendant_changes() {
+        let mut container = create_container_with_nested_children();
+        let hash = container.content_hash();
+
+        let DomNode::Container(italic) = container.get_child_mut(0).unwrap()
+        else {
+            panic!("Expected a container");
+        };
+        let DomNode::Text(text) = italic.get_child_mut(0).unwrap() else {
+            panic!("Expected a text node");
+        };
+        text.set_data(utf16("abcd"));
+        let changed = container.content_hash();
+        assert_ne!(changed, hash);
+        assert_eq!(
+            changed,
+            container
+                .clone_with_new_children(container.children().clone(),)
+                .content_hash()
+        );
+
+        container.add_attributes(vec![(utf16("id"), utf16("a"))]);
+        assert_ne!(container.content_hash(), changed);
+    }
+
+    #[test]
+    fn content_cache_is_ignored_when_comparing() {
+        let computed = create_container_with_nested_children();
+        computed.text_len();
+        let mut not_computed = create_container_with_nested_children();
+        not_computed.get_child_mut(0);
+        assert_eq!(computed, not_computed);
+    }
+
+    #[test]
+    fn immutable_follows_the_contenteditable_attribute() {
+        let mut span = ContainerNode::<Utf16String>::new_span(Vec::new(), None);
+        assert!(!span.is_immutable());
+        span.add_attributes(vec![("contenteditable".into(), "false".into())]);
+        assert!(span.is_immutable());
+        assert!(span.clone_with_new_children(Vec::new()).is_immutable());
+
+        let link = ContainerNode::<Utf16String>::new_link(
+            utf16("https://matrix.org"),
+            Vec::new(),
+            vec![("contenteditable".into(), "true".into())],
+        );
+        assert!(!link.is_immutable());
+    }
+
     #[test]
     fn paragraph_to_message_html() {
         let model =
@@ -4,6 +4,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 use crate::composer_model::example_format::SelectionWriter;
 use crate::dom::dom_handle::DomHandle;
 use crate::dom::nodes::{
@@ -16,12 +19,13 @@ use crate::dom::to_raw_text::ToRawText;
 use crate::dom::to_tree::ToTree;
 use crate::dom::unicode_string::UnicodeStrExt;
 use crate::dom::{self, UnicodeString};
+use crate::nbsp::is_placeholder;
 use crate::{InlineFormatType, ListType};
 
 use super::mention_node::UriParseError;
 use super::MentionNode;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Hash)]
 pub enum DomNode<S>
 where
     S: UnicodeString,
@@ -123,6 +127,19 @@ where
         }
     }
 
+    /// A hash of this node and everything below it, see
+    /// [ContainerNode::content_hash].
+    pub(crate) fn content_hash(&self) -> u64 {
+        match self {
+            DomNode::Container(n) => n.content_hash(),
+            _ => {
+                let mut hasher = DefaultHasher::new();
+                self.hash(&mut hasher);
+                hasher.finish()
+            }
+        }
+    }
+
     pub fn new_link(
         url: S,
         children: Vec<DomNode<S>>,
@@ -268,7 +285,7 @@ where
     /// Returns if this node is a placeholder, as used in empty paragraphs
     pub fn is_placeholder(&self) -> bool {
         match self {
-            DomNode::Text(n) => n.data() == "\u{A0}",
+            DomNode::Text(n) => is_placeholder(&n.data().to_string()),
             _ => false,
         }
     }
@@ -280,6 +297,11 @@ where
                 c1.kind() == c2.kind()
                     && !c1.is_list_item()
                     && !matches!(c1.kind(), ContainerNodeKind::Paragraph)
+                    // Ordered lists only when the numbering carries on
+                    && (!c1.is_list() || c1.is_continued_by(c2))
+                    // Spans are only there for their attributes
+                    && (!matches!(c1.kind(), ContainerNodeKind::Span)
+                        || c1.attributes() == c2.attributes())
             }
             (DomNode::Text(_), DomNode::Text(_)) => true,
             _ => false,
@@ -433,6 +455,13 @@ where
             }
         }
     }
+
+    fn html_len_hint(&self) -> usize {
+        match self {
+            DomNode::Container(s) => s.html_len_hint(),
+            _ => self.text_len(),
+        }
+    }
 }
 
 impl<S> ToRawText<S> for DomNode<S>
@@ -517,6 +546,7 @@ pub enum DomNodeKind {
     CodeBlock,
     Quote,
     Paragraph,
+    Span,
 }
 
 impl DomNodeKind {
@@ -534,6 +564,7 @@ impl DomNodeKind {
             ContainerNodeKind::CodeBlock => DomNodeKind::CodeBlock,
             ContainerNodeKind::Quote => DomNodeKind::Quote,
             ContainerNodeKind::Paragraph => DomNodeKind::Paragraph,
+            ContainerNodeKind::Span => DomNodeKind::Span,
         }
     }
 
@@ -563,7 +594,8 @@ impl DomNodeKind {
             | Self::List
             | Self::CodeBlock
             | Self::Quote
-            | Self::Paragraph => false,
+            | Self::Paragraph
+            | Self::Span => false,
         }
     }
 
@@ -13,6 +13,7 @@ use crate::dom::to_raw_text::ToRawText;
 use crate::dom::to_tree::ToTree;
 use crate::dom::unicode_string::{UnicodeStrExt, UnicodeStringExt};
 use crate::dom::UnicodeString;
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -24,6 +25,14 @@ where
     handle: DomHandle,
 }
 
+/// All line breaks hash the same, whatever their handle.
+impl<S> Hash for LineBreakNode<S>
+where
+    S: UnicodeString,
+{
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
 impl<S> Default for LineBreakNode<S>
 where
     S: UnicodeString,
@@ -3,11 +3,17 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
+
+use std::hash::{Hash, Hasher};
+
 use matrix_mentions::{Mention, MentionKind};
 
 use crate::composer_model::example_format::SelectionWriter;
+use crate::dom::bidi_isolation::{
+    FIRST_STRONG_ISOLATE, POP_DIRECTIONAL_ISOLATE,
+};
 use crate::dom::dom_handle::DomHandle;
-use crate::dom::to_html::{ToHtml, ToHtmlExt, ToHtmlState};
+use crate::dom::to_html::{fmt_attribute, fmt_attributes, ToHtml, ToHtmlState};
 use crate::dom::to_markdown::{MarkdownError, MarkdownOptions, ToMarkdown};
 use crate::dom::to_plain_text::ToPlainText;
 use crate::dom::to_raw_text::ToRawText;
@@ -37,12 +43,24 @@ where
     handle: DomHandle,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum MentionNodeKind {
     MatrixUri { mention: Mention },
     AtRoom,
 }
 
+/// Hashes the content of the mention, but not its handle.
+impl<S> Hash for MentionNode<S>
+where
+    S: UnicodeString,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.display_text.hash(state);
+        self.kind.hash(state);
+        self.attributes.hash(state);
+    }
+}
+
 impl<S> MentionNode<S>
 where
     S: UnicodeString,
@@ -101,6 +119,27 @@ where
         }
     }
 
+    /// Change the text shown for this mention, keeping its URI and
+    /// attributes. Returns false if nothing changed, which is always the
+    /// case for an at-room mention.
+    pub fn set_display_text(&mut self, display_text: S) -> bool {
+        let MentionNodeKind::MatrixUri { mention } = &self.kind else {
+            return false;
+        };
+        if display_text == self.display_text {
+            return false;
+        }
+        let Some(mention) = Mention::from_uri_with_display_text(
+            mention.uri(),
+            &display_text.to_string(),
+        ) else {
+            return false;
+        };
+        self.kind = MentionNodeKind::MatrixUri { mention };
+        self.display_text = display_text;
+        true
+    }
+
     pub fn set_handle(&mut self, handle: DomHandle) {
         self.handle = handle;
     }
@@ -118,6 +157,10 @@ where
     pub fn kind(&self) -> &MentionNodeKind {
         &self.kind
     }
+
+    pub fn attributes(&self) -> &[(S, S)] {
+        &self.attributes
+    }
 }
 
 // TODO implment From trait to convert from MentionNode to DomNode to allow MentionNode.into() usage
@@ -142,58 +185,60 @@ impl<S: UnicodeString> MentionNode<S> {
         &self,
         formatter: &mut S,
         selection_writer: Option<&mut SelectionWriter>,
-        _: &ToHtmlState,
+        state: &ToHtmlState,
         as_message: bool,
     ) {
-        let tag = &S::from("a");
-
         let cur_pos = formatter.len();
+        let isolate = as_message && state.isolate_message_bidi;
         match self.kind() {
             MentionNodeKind::MatrixUri { mention } => {
-                // if formatting as a message, only include the href attribute
-                let attributes = if as_message {
-                    vec![("href".into(), S::from(mention.uri()))]
+                formatter.push("<a");
+                if as_message {
+                    // if formatting as a message, only include the href attribute
+                    fmt_attribute(formatter, "href", mention.uri());
                 } else {
                     // this is now only required for us to attach a custom style attribute for web
-                    let mut attrs = self.attributes.clone();
+                    self.fmt_own_attributes(formatter);
                     let data_mention_type = match mention.kind() {
                         MentionKind::Room(_) => "room",
                         MentionKind::User => "user",
                     };
-                    attrs.push((
-                        "data-mention-type".into(),
-                        data_mention_type.into(),
-                    ));
-                    attrs.push(("href".into(), S::from(mention.uri())));
-                    attrs.push(("contenteditable".into(), "false".into()));
-                    attrs
-                };
-
-                let display_text = if as_message && mention.kind().is_room() {
-                    S::from(mention.mx_id())
+                    fmt_attribute(
+                        formatter,
+                        "data-mention-type",
+                        data_mention_type,
+                    );
+                    fmt_attribute(formatter, "href", mention.uri());
+                    fmt_attribute(formatter, "contenteditable", "false");
+                }
+                formatter.push('>');
+                if isolate {
+                    formatter.push(FIRST_STRONG_ISOLATE);
+                }
+                if as_message && mention.kind().is_room() {
+                    formatter.push(mention.mx_id());
                 } else {
-                    self.display_text()
-                };
-
-                self.fmt_tag_open(tag, formatter, &Some(attributes));
-                formatter.push(display_text);
-                self.fmt_tag_close(tag, formatter);
+                    formatter.push(&*self.display_text);
+                }
+                if isolate {
+                    formatter.push(POP_DIRECTIONAL_ISOLATE);
+                }
+                formatter.push("</a>");
             }
             MentionNodeKind::AtRoom => {
                 // if formatting as a message, simply use the display text (@room)
                 if as_message {
-                    formatter.push(self.display_text())
+                    formatter.push(get_at_room_display_text())
                 } else {
                     // this is now only required for us to attach a custom style attribute for web
-                    let mut attributes = self.attributes.clone();
-                    attributes
-                        .push(("data-mention-type".into(), "at-room".into()));
-                    attributes.push(("href".into(), "#".into())); // designates a placeholder link in html
-                    attributes.push(("contenteditable".into(), "false".into()));
-
-                    self.fmt_tag_open(tag, formatter, &Some(attributes));
-                    formatter.push(self.display_text());
-                    self.fmt_tag_close(tag, formatter);
+                    formatter.push("<a");
+                    self.fmt_own_attributes(formatter);
+                    fmt_attribute(formatter, "data-mention-type", "at-room");
+                    fmt_attribute(formatter, "href", "#"); // designates a placeholder link in html
+                    fmt_attribute(formatter, "contenteditable", "false");
+                    formatter.push('>');
+                    formatter.push(get_at_room_display_text());
+                    formatter.push("</a>");
                 };
             }
         }
@@ -202,6 +247,12 @@ impl<S: UnicodeString> MentionNode<S> {
             sel_writer.write_selection_mention_node(formatter, cur_pos, self);
         }
     }
+
+    /// Write the attributes this mention was created with, e.g. ones kept
+    /// from parsed HTML.
+    fn fmt_own_attributes(&self, formatter: &mut S) {
+        fmt_attributes(formatter, &self.attributes, false);
+    }
 }
 
 impl<S> ToRawText<S> for MentionNode<S>
@@ -258,37 +309,58 @@ where
     fn fmt_markdown(
         &self,
         buffer: &mut S,
-        _: &MarkdownOptions,
+        options: &MarkdownOptions,
         as_message: bool,
     ) -> Result<(), MarkdownError<S>> {
-        fmt_mention(self, buffer, as_message)?;
+        fmt_mention(self, buffer, options, as_message)?;
         return Ok(());
 
         #[inline(always)]
         fn fmt_mention<S>(
             this: &MentionNode<S>,
             buffer: &mut S,
+            options: &MarkdownOptions,
             as_message: bool,
         ) -> Result<(), MarkdownError<S>>
         where
             S: UnicodeString,
         {
             if as_message {
-                // For a mention in a message, display the `mx_id` for a room mention, `display_text` otherwise
-                let text = match this.kind() {
-                    MentionNodeKind::MatrixUri { mention }
-                        if mention.kind().is_room() =>
-                    {
-                        S::from(mention.mx_id())
-                    }
-                    _ => this.display_text(),
+                // @room mentions are written as plain text
+                let MentionNodeKind::MatrixUri { mention } = this.kind() else {
+                    buffer.push(this.display_text());
+                    return Ok(());
+                };
+                // Rooms are always shown by their `mx_id`, as their display
+                // text is only meaningful in the composer
+                let text = if mention.kind().is_room()
+                    || options.contains(MarkdownOptions::MENTIONS_AS_MX_ID)
+                {
+                    S::from(mention.mx_id())
+                } else {
+                    this.display_text()
                 };
 
-                buffer.push(text);
+                let is_link = !options
+                    .contains(MarkdownOptions::MENTIONS_AS_MX_ID)
+                    && !options
+                        .contains(MarkdownOptions::MENTIONS_AS_DISPLAY_TEXT);
+                if is_link {
+                    buffer.push('[');
+                    buffer.push(
+                        text.to_string()
+                            .replace('[', "\\[")
+                            .replace(']', "\\]")
+                            .as_str(),
+                    );
+                    buffer.push("](");
+                    buffer.push(mention.uri());
+                    buffer.push(')');
+                } else {
+                    buffer.push(text);
+                }
                 Ok(())
             } else {
-                // clone the attributes and set up variables to assign attributes to
-                let mut attrs = this.attributes.clone();
                 let data_mention_type;
                 let href;
 
@@ -307,25 +379,12 @@ where
                     }
                 };
 
-                // push the attributes into the vec for writing
-                attrs.push((
-                    "data-mention-type".into(),
-                    data_mention_type.into(),
-                ));
-                attrs.push(("href".into(), href.into()));
-                attrs.push(("contenteditable".into(), "false".into()));
-
                 // HTML is valid markdown. For a mention in a composer, output it as HTML.
                 buffer.push("<a");
-
-                for (attr, value) in attrs {
-                    buffer.push(' ');
-                    buffer.push(attr);
-                    buffer.push("=\"");
-                    buffer.push(value);
-                    buffer.push('"');
-                }
-
+                this.fmt_own_attributes(buffer);
+                fmt_attribute(buffer, "data-mention-type", data_mention_type);
+                fmt_attribute(buffer, "href", href);
+                fmt_attribute(buffer, "contenteditable", "false");
                 buffer.push('>');
 
                 buffer.push(this.display_text());
@@ -7,18 +7,22 @@
 use crate::composer_model::delete_text::Direction;
 use crate::composer_model::example_format::SelectionWriter;
 use crate::dom::dom_handle::DomHandle;
+use crate::dom::nodes::dom_node::DomNodeKind;
 use crate::dom::to_html::{ToHtml, ToHtmlState};
 use crate::dom::to_markdown::{MarkdownError, MarkdownOptions, ToMarkdown};
 use crate::dom::to_plain_text::ToPlainText;
 use crate::dom::to_raw_text::ToRawText;
 use crate::dom::to_tree::ToTree;
 use crate::dom::unicode_string::{UnicodeStr, UnicodeStrExt, UnicodeStringExt};
-use crate::dom::UnicodeString;
+use crate::dom::whitespace_policy::preserve_spaces;
+use crate::dom::{UnicodeString, WhitespacePolicy};
+use crate::nbsp::NBSP_STR;
 use html_escape;
+use std::hash::{Hash, Hasher};
 use std::ops::Range;
 
 // categories of character for backspace/delete word
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum CharType {
     Whitespace,
     Punctuation,
@@ -34,6 +38,16 @@ where
     handle: DomHandle,
 }
 
+/// Hashes the text, but not the handle of the node.
+impl<S> Hash for TextNode<S>
+where
+    S: UnicodeString,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
 impl<S> TextNode<S>
 where
     S: UnicodeString,
@@ -186,7 +200,7 @@ where
 }
 
 /// Given a character, determine its type
-fn get_char_type(c: char) -> CharType {
+pub(crate) fn get_char_type(c: char) -> CharType {
     // in order to determine where a ctrl/opt + delete type operation finishes
     // we need to distinguish between whitespace (nb no newline characters), punctuation
     // and then everything else is treated as the same type
@@ -211,28 +225,33 @@ where
         _as_message: bool,
     ) {
         let cur_pos = buf.len();
-        let string = self.data.to_string();
-
-        let mut escaped = html_escape::encode_text(&string).to_string();
-        // Replace all pairs of spaces with non-breaking ones. Transforms
-        // `a     b` to `a\u{A0}\u{A0}\u{A0}\u{A0} b`, which will render
-        // exactly as five spaces like in the input.
-        if !state.is_inside_code_block {
-            escaped = escaped.replace("  ", "\u{A0}\u{A0}");
-
-            if state.next_sibling.is_none() && (escaped.ends_with(' ')) {
-                // If this is the last node and it ends in a space, replace that
-                // space with a non-breaking one.
-                escaped.replace_range(escaped.len() - 1.., "\u{A0}");
-            }
+        let preserve_spaces_here = state.whitespace_policy
+            == WhitespacePolicy::Preserve
+            && !state.is_inside_code_block;
+        let (at_start, at_end) = if preserve_spaces_here {
+            (
+                is_line_edge(&state.prev_sibling),
+                is_line_edge(&state.next_sibling),
+            )
+        } else {
+            (state.prev_sibling.is_none(), state.next_sibling.is_none())
+        };
 
-            if state.prev_sibling.is_none() && (escaped.starts_with(' ')) {
-                // If this is the first node and it starts with a space, replace that
-                // space with a non-breaking one.
-                escaped.replace_range(..1, "\u{A0}");
+        if self.is_unchanged_in_html(state, at_start, at_end) {
+            buf.push(&*self.data);
+        } else {
+            let string = self.data.to_string();
+            let escaped = html_escape::encode_text(&string);
+            if preserve_spaces_here {
+                buf.push(preserve_spaces(&escaped, at_start, at_end).as_str());
+            } else if !state.is_inside_code_block {
+                buf.push(
+                    keep_spaces_visible(&escaped, at_start, at_end).as_str(),
+                );
+            } else {
+                buf.push(&*escaped);
             }
         }
-        buf.push(escaped.as_str());
 
         if let Some(selection_writer) = selection_writer {
             selection_writer.write_selection_text_node(buf, cur_pos, self);
@@ -240,6 +259,67 @@ where
     }
 }
 
+fn is_line_edge(sibling: &Option<DomNodeKind>) -> bool {
+    matches!(sibling, None | Some(DomNodeKind::LineBreak))
+}
+
+impl<S> TextNode<S>
+where
+    S: UnicodeString,
+{
+    /// Whether the text needs neither escaping nor any of its spaces made
+    /// non-breaking, so can be copied into the HTML without converting it
+    /// to UTF-8 and back, which is the common case.
+    fn is_unchanged_in_html(
+        &self,
+        state: &ToHtmlState,
+        at_start: bool,
+        at_end: bool,
+    ) -> bool {
+        let units = self.data.as_ref();
+        let unit = |c: u8| S::CodeUnit::from(c);
+        if units
+            .iter()
+            .any(|u| *u == unit(b'<') || *u == unit(b'>') || *u == unit(b'&'))
+        {
+            return false;
+        }
+        if state.is_inside_code_block {
+            return true;
+        }
+        let space = unit(b' ');
+        !(units.windows(2).any(|w| w[0] == space && w[1] == space)
+            || (at_start && units.first() == Some(&space))
+            || (at_end && units.last() == Some(&space)))
+    }
+}
+
+/// Replace the spaces in text which would otherwise collapse when it is
+/// rendered with non-breaking ones.
+pub(crate) fn keep_spaces_visible(
+    text: &str,
+    is_first_node: bool,
+    is_last_node: bool,
+) -> String {
+    // Replace all pairs of spaces with non-breaking ones. Transforms
+    // `a     b` to `a\u{A0}\u{A0}\u{A0}\u{A0} b`, which will render
+    // exactly as five spaces like in the input.
+    let mut text = text.replace("  ", &NBSP_STR.repeat(2));
+
+    if is_last_node && text.ends_with(' ') {
+        // If this is the last node and it ends in a space, replace that
+        // space with a non-breaking one.
+        text.replace_range(text.len() - 1.., NBSP_STR);
+    }
+
+    if is_first_node && text.starts_with(' ') {
+        // If this is the first node and it starts with a space, replace that
+        // space with a non-breaking one.
+        text.replace_range(..1, NBSP_STR);
+    }
+    text
+}
+
 impl<S> ToRawText<S> for TextNode<S>
 where
     S: UnicodeString,
@@ -281,20 +361,19 @@ where
     fn fmt_markdown(
         &self,
         buffer: &mut S,
-        _options: &MarkdownOptions,
+        options: &MarkdownOptions,
         _as_message: bool,
     ) -> Result<(), MarkdownError<S>> {
-        buffer.push(self.data.to_owned());
+        buffer.push(options.escape_text(&self.data.to_string()).as_str());
 
         Ok(())
     }
 }
 #[cfg(test)]
 mod test {
-    use crate::char::CharExt;
-
     use crate::composer_model::delete_text::Direction;
     use crate::dom::nodes::text_node::CharType;
+    use crate::nbsp::NBSP;
     use crate::tests::testutils_conversion::utf16;
 
     use super::{get_char_type, TextNode};
@@ -304,7 +383,7 @@ mod test {
         // space
         assert_eq!(get_char_type('\u{0020}'), CharType::Whitespace);
         // no break space
-        assert_eq!(get_char_type(char::nbsp()), CharType::Whitespace);
+        assert_eq!(get_char_type(NBSP), CharType::Whitespace);
     }
 
     #[test]
@@ -11,6 +11,11 @@
 //! [super::Dom]. All instances of classes within this module are thrown away
 //! when parsing finishes.
 
+mod code_editor;
+#[cfg(test)]
+mod conformance;
+mod google_doc;
+mod html_chunker;
 pub mod markdown;
 #[cfg(feature = "sys")]
 mod padom;
@@ -29,6 +34,8 @@ mod panode_text;
 #[cfg(feature = "sys")]
 mod paqual_name;
 mod parse;
+#[cfg(feature = "sys")]
+mod source_map;
 
 // Group all re-exports for `feature = "sys"`.
 #[cfg(feature = "sys")]
@@ -48,9 +55,26 @@ mod sys {
 #[cfg(feature = "sys")]
 use sys::*;
 
+pub(crate) use html_chunker::HtmlChunker;
 pub use parse::parse;
-pub use parse::parse_from_source;
+pub use parse::parse_from_source_with_options;
+pub use parse::parse_lenient;
+#[cfg(feature = "source-map")]
+pub use parse::parse_with_source_map;
+pub use parse::validate_message_html;
+pub use parse::DEFAULT_MAX_PARSE_DEPTH;
+pub(crate) use parse::{
+    convert_text, post_process_dom, post_process_joined_fragments,
+};
+#[cfg(feature = "source-map")]
+pub use source_map::{SourceChange, SourceMap, SourcePosition};
 
+#[cfg(test)]
+pub use parse::EMAIL_HTML_PASTEBOARD;
+#[cfg(test)]
+pub use parse::GOOGLE_DOC_CHECKLIST_HTML_PASTEBOARD;
+#[cfg(test)]
+pub use parse::GOOGLE_DOC_HEADINGS_HTML_PASTEBOARD;
 #[cfg(test)]
 pub use parse::GOOGLE_DOC_HTML_PASTEBOARD;
 #[cfg(test)]
@@ -0,0 +1,122 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Code copied from editors such as VS Code, which put it on the
+//! pasteboard as a `<div>` of preformatted monospace text with a `<div>`
+//! for each line and a coloured `<span>` for each token, rather than as a
+//! `<pre>`. Both parsers read it as a code block instead of a paragraph for
+//! each line, and drop the colours of its syntax highlighting.
+
+/// Fonts editors write code in when they don't end the list with the
+/// generic `monospace`.
+const MONOSPACE_FONTS: [&str; 9] = [
+    "cascadia",
+    "consolas",
+    "courier",
+    "fira code",
+    "jetbrains mono",
+    "menlo",
+    "monaco",
+    "sf mono",
+    "source code pro",
+];
+
+/// The value of the CSS property `name` in a `style` attribute, in lower
+/// case.
+fn style_value(style: &str, name: &str) -> Option<String> {
+    style.split(';').find_map(|declaration| {
+        let (property, value) = declaration.split_once(':')?;
+        property
+            .trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_ascii_lowercase())
+    })
+}
+
+/// Whether an element with the given `style` attribute holds code copied
+/// from an editor, i.e. preformatted text in a monospace font.
+pub(crate) fn is_code_editor_block(style: Option<&str>) -> bool {
+    let Some(style) = style else {
+        return false;
+    };
+    let is_preformatted = style_value(style, "white-space")
+        .is_some_and(|value| value.starts_with("pre"));
+    let is_monospace =
+        style_value(style, "font-family").is_some_and(|families| {
+            families.split(',').any(|family| {
+                let family = family.trim().trim_matches(['\'', '"']);
+                family == "monospace"
+                    || MONOSPACE_FONTS.iter().any(|f| family.starts_with(f))
+            })
+        });
+    is_preformatted && is_monospace
+}
+
+/// The `language-*` class for the language named by an element copied from
+/// an editor, from its `data-language` or `data-lang` attribute, or a
+/// `language-*` or `lang-*` class, given a way to get its attributes.
+pub(crate) fn language_class(
+    get_attr: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let language = get_attr("data-language")
+        .or_else(|| get_attr("data-lang"))
+        .or_else(|| {
+            get_attr("class")?.split_whitespace().find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+                    .map(str::to_owned)
+            })
+        })?;
+    let language = language.trim();
+    (!language.is_empty() && !language.contains(char::is_whitespace))
+        .then(|| format!("language-{}", language.to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VS_CODE_STYLE: &str = "color: #cccccc;background-color: #1f1f1f;\
+        font-family: Consolas, 'Courier New', monospace;\
+        font-weight: normal;font-size: 14px;line-height: 19px;\
+        white-space: pre;";
+
+    #[test]
+    fn preformatted_monospace_text_is_code() {
+        assert!(is_code_editor_block(Some(VS_CODE_STYLE)));
+        assert!(is_code_editor_block(Some(
+            "font-family:'JetBrains Mono';white-space:pre-wrap"
+        )));
+        assert!(!is_code_editor_block(Some("white-space: pre")));
+        assert!(!is_code_editor_block(Some(
+            "font-family: Arial, sans-serif; white-space: pre"
+        )));
+        assert!(!is_code_editor_block(Some("font-family: monospace")));
+        assert!(!is_code_editor_block(None));
+    }
+
+    #[test]
+    fn the_language_can_come_from_attributes_or_classes() {
+        let attrs = |attrs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            language_class(attrs(&[("data-language", "Rust")])),
+            Some("language-rust".to_owned())
+        );
+        assert_eq!(
+            language_class(attrs(&[("class", "code lang-ts")])),
+            Some("language-ts".to_owned())
+        );
+        assert_eq!(language_class(attrs(&[("data-lang", "a b")])), None);
+        assert_eq!(language_class(attrs(&[("class", "code")])), None);
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Cases which the sys and js parsers must agree on, so that they can't
+//! drift apart unnoticed. The tests of each parser run every case through
+//! [check_fixtures] and [check_at_room_detection].
+
+use widestring::Utf16String;
+
+use crate::dom::html_source::HtmlSource;
+use crate::dom::{AtRoomDetection, Dom};
+use crate::{ToHtml, ToTree};
+
+/// HTML and the trees it parses to. See the comment at its top for the
+/// format.
+const FIXTURES: &str = include_str!("../../../fixtures/parser_conformance.txt");
+
+use AtRoomDetection::{Everywhere, Off, OutsideQuotes};
+
+const AT_ROOM_LINK: &str = "<a data-mention-type=\"at-room\" href=\"#\" \
+    contenteditable=\"false\">@room</a>";
+
+/// The HTML to parse, the detection to parse it with, and how many @room
+/// mentions it has.
+const CASES: &[(&str, AtRoomDetection, usize)] = &[
+    ("<p>@room hi</p>", Everywhere, 1),
+    ("<p>@room hi</p>", OutsideQuotes, 1),
+    ("<p>@room hi</p>", Off, 0),
+    ("<blockquote><p>@room hi</p></blockquote>", Everywhere, 1),
+    ("<blockquote><p>@room hi</p></blockquote>", OutsideQuotes, 0),
+    ("<pre><code>@room hi</code></pre>", Everywhere, 0),
+    ("<p><code>@room</code> hi</p>", Everywhere, 0),
+    (
+        "<p><a href=\"https://x.org\">@room</a> hi</p>",
+        Everywhere,
+        0,
+    ),
+    ("<p><em>@room</em> hi</p>", Everywhere, 1),
+    ("<ul><li>@room hi</li></ul>", Everywhere, 1),
+];
+
+/// The same cases, with @room written as the composer writes the mention.
+const LINK_CASES: &[(&str, &str, AtRoomDetection, usize)] = &[
+    ("<p>", "</p>", Everywhere, 1),
+    ("<p>", "</p>", Off, 0),
+    ("<blockquote><p>", "</p></blockquote>", Everywhere, 1),
+    ("<blockquote><p>", "</p></blockquote>", OutsideQuotes, 0),
+    ("<pre><code>", "</code></pre>", Everywhere, 0),
+    ("<p><code>", "</code></p>", Everywhere, 0),
+];
+
+/// A case of [FIXTURES]: its description, where its HTML is from, the HTML
+/// and the tree it parses to.
+struct Fixture<'a> {
+    name: &'a str,
+    source: HtmlSource,
+    html: &'a str,
+    tree: String,
+}
+
+fn fixtures() -> Vec<Fixture<'static>> {
+    FIXTURES
+        .split("\n## ")
+        .skip(1)
+        .map(|case| {
+            let mut lines = case.trim_end().lines();
+            let header = lines.next().unwrap();
+            let (source, name) = header
+                .split_once(": ")
+                .unwrap_or_else(|| panic!("No source in `## {header}`"));
+            let source = match source {
+                "matrix" => HtmlSource::Matrix,
+                "external" => HtmlSource::UnknownExternal,
+                _ => panic!("Unknown source in `## {header}`"),
+            };
+            let html = lines
+                .next()
+                .unwrap_or_else(|| panic!("No HTML in `## {header}`"));
+            let tree: String = lines.map(|line| format!("\n{line}")).collect();
+            Fixture {
+                name,
+                source,
+                html,
+                tree: format!("{tree}\n"),
+            }
+        })
+        .collect()
+}
+
+/// Check that `parse` parses the HTML of every fixture to its tree.
+/// `parse` parses HTML from the given source.
+pub(super) fn check_fixtures(
+    parse: impl Fn(&str, HtmlSource) -> Dom<Utf16String>,
+) {
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty());
+    for fixture in fixtures {
+        let dom = parse(fixture.html, fixture.source);
+        assert_eq!(
+            dom.to_tree().to_string(),
+            fixture.tree,
+            "{} ({:?}): {}",
+            fixture.name,
+            fixture.source,
+            fixture.html
+        );
+    }
+}
+
+/// Check that `parse` finds @room mentions in every case, and nowhere
+/// else. `parse` parses HTML with the given detection.
+pub(super) fn check_at_room_detection(
+    parse: impl Fn(&str, AtRoomDetection) -> Dom<Utf16String>,
+) {
+    let link_cases = LINK_CASES.iter().map(|(open, close, detection, n)| {
+        (format!("{open}{AT_ROOM_LINK}{close}"), *detection, *n)
+    });
+    let cases = CASES
+        .iter()
+        .map(|(html, detection, n)| (html.to_string(), *detection, *n))
+        .chain(link_cases);
+    for (html, detection, mentions) in cases {
+        let dom = parse(&html, detection);
+        assert_eq!(
+            dom.iter_mentions().count(),
+            mentions,
+            "{html} parsed with {detection:?} to {}",
+            dom.to_html()
+        );
+        if mentions == 0 {
+            assert!(
+                dom.to_html().to_string().contains("@room"),
+                "{html} parsed with {detection:?} lost @room"
+            );
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! The parts of the HTML Google Docs puts on the pasteboard which are only
+//! said through styles and roles rather than tags, shared by both parsers.
+//!
+//! The composer has neither headings nor task lists, so headings become
+//! paragraphs of bold text, as `<h1>` to `<h6>` do, and checklist items
+//! become list items starting with a ballot box.
+
+use crate::dom::nodes::container_node::ContainerNodeKind;
+use crate::dom::nodes::{ContainerNode, DomNode};
+use crate::UnicodeString;
+
+/// Docs writes normal text at 11pt, and the title, subtitle and headings
+/// which aren't pasted as `<h1>` to `<h6>` at 14pt or more.
+const HEADING_FONT_SIZE_PT: f32 = 14.0;
+
+const UNCHECKED_MARKER: &str = "☐ ";
+const CHECKED_MARKER: &str = "☑ ";
+
+/// The size in points of the font in a `style` attribute, if it is given
+/// in points or pixels.
+pub(crate) fn font_size_pt(style: &str) -> Option<f32> {
+    let value = style.split(';').find_map(|declaration| {
+        let (name, value) = declaration.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("font-size")
+            .then(|| value.trim().to_ascii_lowercase())
+    })?;
+    if let Some(points) = value.strip_suffix("pt") {
+        points.trim().parse().ok()
+    } else if let Some(pixels) = value.strip_suffix("px") {
+        pixels.trim().parse::<f32>().ok().map(|px| px * 0.75)
+    } else {
+        None
+    }
+}
+
+/// Whether a paragraph with the given `role` attribute, whose text is in
+/// runs with the given font sizes, is styled as a heading. Text outside a
+/// styled run has no size, so keeps the paragraph as it is.
+pub(crate) fn is_heading_paragraph(
+    role: Option<&str>,
+    run_font_sizes: &[Option<f32>],
+) -> bool {
+    role == Some("heading")
+        || (!run_font_sizes.is_empty()
+            && run_font_sizes.iter().all(|size| {
+                size.is_some_and(|size| size >= HEADING_FONT_SIZE_PT)
+            }))
+}
+
+/// The text a list item with the given `role` and `aria-checked`
+/// attributes starts with, if it is a checklist item.
+pub(crate) fn checkbox_marker(
+    role: Option<&str>,
+    aria_checked: Option<&str>,
+) -> Option<&'static str> {
+    (role == Some("checkbox")).then(|| {
+        if aria_checked == Some("true") {
+            CHECKED_MARKER
+        } else {
+            UNCHECKED_MARKER
+        }
+    })
+}
+
+/// Start the text of `list_item`, or of the paragraph it starts with, with
+/// `marker`.
+pub(crate) fn prepend_marker<S: UnicodeString>(
+    list_item: &mut ContainerNode<S>,
+    marker: &str,
+) {
+    if let Some(DomNode::Container(paragraph)) = list_item.first_child_mut() {
+        if matches!(paragraph.kind(), ContainerNodeKind::Paragraph) {
+            return prepend_text(paragraph, marker);
+        }
+    }
+    prepend_text(list_item, marker);
+}
+
+fn prepend_text<S: UnicodeString>(
+    container: &mut ContainerNode<S>,
+    text: &str,
+) {
+    if let Some(DomNode::Text(first)) = container.first_child_mut() {
+        let mut data = first.data().to_owned();
+        data.insert(0, &S::from(text));
+        first.set_data(data);
+    } else {
+        let children = container.remove_children();
+        container.append_child(DomNode::new_text(S::from(text)));
+        container.append_children(children);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn font_sizes_are_read_in_points_or_pixels() {
+        assert_eq!(font_size_pt("font-size:26pt;color:#000"), Some(26.0));
+        assert_eq!(font_size_pt("color:red; Font-Size : 20px"), Some(15.0));
+        assert_eq!(font_size_pt("font-size:1.5em"), None);
+        assert_eq!(font_size_pt("font-weight:700"), None);
+    }
+
+    #[test]
+    fn paragraphs_of_large_text_are_headings() {
+        assert!(is_heading_paragraph(None, &[Some(26.0), Some(14.0)]));
+        assert!(is_heading_paragraph(Some("heading"), &[Some(11.0)]));
+        assert!(!is_heading_paragraph(None, &[Some(26.0), Some(11.0)]));
+        assert!(!is_heading_paragraph(None, &[Some(26.0), None]));
+        assert!(!is_heading_paragraph(None, &[]));
+    }
+
+    #[test]
+    fn only_checkboxes_have_markers() {
+        assert_eq!(checkbox_marker(Some("checkbox"), Some("true")), Some("☑ "));
+        assert_eq!(
+            checkbox_marker(Some("checkbox"), Some("false")),
+            Some("☐ ")
+        );
+        assert_eq!(checkbox_marker(Some("presentation"), Some("true")), None);
+        assert_eq!(checkbox_marker(None, None), None);
+    }
+}
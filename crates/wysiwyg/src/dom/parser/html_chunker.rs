@@ -0,0 +1,187 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use super::parse::BLOCK_TAGS;
+
+/// Elements which never have an end tag.
+const VOID_TAGS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "param", "source", "track", "wbr",
+];
+
+/// Elements whose content is not HTML, so may contain `<` anywhere.
+const RAW_TEXT_TAGS: [&str; 5] =
+    ["script", "style", "textarea", "title", "xmp"];
+
+/// Splits HTML which arrives in chunks, e.g. a large paste, into pieces
+/// which can each be parsed on their own as soon as they are complete, so
+/// that the work of parsing is spread across the chunks. Pieces end after a
+/// top-level block, e.g. `</p>`. HTML which can't safely be split, e.g.
+/// because an end tag was left out, stays in one piece.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HtmlChunker {
+    buffer: String,
+    /// How far into the buffer the tags have been followed
+    scanned: usize,
+    /// The end of the last top-level block in the buffer
+    cut: usize,
+    /// How many elements are open where the buffer has been scanned to
+    depth: usize,
+    /// True once the HTML turns out not to be safe to split
+    unsplittable: bool,
+}
+
+impl HtmlChunker {
+    /// A chunker which keeps all of the HTML in one piece.
+    pub(crate) fn unsplittable() -> Self {
+        Self {
+            unsplittable: true,
+            ..Self::default()
+        }
+    }
+
+    /// Add the next chunk of HTML, returning the HTML up to the end of the
+    /// last top-level block which is now complete, if any.
+    pub(crate) fn push(&mut self, chunk: &str) -> Option<String> {
+        self.buffer.push_str(chunk);
+        self.scan();
+        if self.unsplittable || self.cut == 0 {
+            return None;
+        }
+        let piece: String = self.buffer.drain(..self.cut).collect();
+        self.scanned -= self.cut;
+        self.cut = 0;
+        Some(piece)
+    }
+
+    /// The rest of the HTML, once every chunk has been pushed.
+    pub(crate) fn finish(self) -> String {
+        self.buffer
+    }
+
+    /// Follow each complete tag after the scanned part of the buffer,
+    /// noting where top-level blocks end.
+    fn scan(&mut self) {
+        while !self.unsplittable {
+            let rest = &self.buffer[self.scanned..];
+            let Some(start) = rest.find('<') else {
+                self.scanned = self.buffer.len();
+                return;
+            };
+            let candidate = &rest[start..];
+            let len = if candidate.starts_with("<!--") {
+                candidate.find("-->").map(|i| i + 3)
+            } else {
+                candidate.find('>').map(|i| i + 1)
+            };
+            let Some(len) = len else {
+                // Wait for the rest of the tag
+                self.scanned += start;
+                return;
+            };
+            let tag = candidate[..len].to_owned();
+            self.follow_tag(&tag);
+            self.scanned += start + len;
+            if self.depth == 0
+                && !self.unsplittable
+                && block_end(&tag)
+                    .is_some_and(|name| BLOCK_TAGS.contains(&name.as_str()))
+            {
+                self.cut = self.scanned;
+            }
+        }
+    }
+
+    /// Update the depth for a tag, e.g. `<p class="a">` or `</p>`.
+    fn follow_tag(&mut self, tag: &str) {
+        let Some(name) = tag_name(tag) else {
+            return;
+        };
+        if RAW_TEXT_TAGS.contains(&name.as_str()) {
+            self.unsplittable = true;
+        } else if tag.starts_with("</") {
+            match self.depth.checked_sub(1) {
+                Some(depth) => self.depth = depth,
+                None => self.unsplittable = true,
+            }
+        } else if !VOID_TAGS.contains(&name.as_str()) && !tag.ends_with("/>") {
+            self.depth += 1;
+        }
+    }
+}
+
+/// The lowercase name of a start or end tag, or None for comments and
+/// declarations.
+fn tag_name(tag: &str) -> Option<String> {
+    let name = tag.strip_prefix("</").or_else(|| tag.strip_prefix('<'))?;
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let len = name
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .unwrap_or(name.len());
+    Some(name[..len].to_ascii_lowercase())
+}
+
+/// The name of the element a tag ends: its own for an end tag or a void
+/// element, e.g. `<hr>`.
+fn block_end(tag: &str) -> Option<String> {
+    let name = tag_name(tag)?;
+    (tag.starts_with("</") || VOID_TAGS.contains(&name.as_str()))
+        .then_some(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::HtmlChunker;
+
+    fn pieces(chunks: &[&str]) -> Vec<String> {
+        let mut chunker = HtmlChunker::default();
+        let mut pieces: Vec<String> =
+            chunks.iter().filter_map(|c| chunker.push(c)).collect();
+        pieces.push(chunker.finish());
+        pieces
+    }
+
+    #[test]
+    fn html_is_split_after_top_level_blocks() {
+        assert_eq!(
+            pieces(&["<p>a</p><p>b", "</p><ul><li>c</li></ul>d", "<b>e</b>"]),
+            vec!["<p>a</p>", "<p>b</p><ul><li>c</li></ul>", "d<b>e</b>"]
+        );
+    }
+
+    #[test]
+    fn tags_split_between_chunks_are_followed() {
+        assert_eq!(
+            pieces(&["<p class=\"x\">a</", "p><hr", ">b"]),
+            vec!["<p class=\"x\">a</p>", "<hr>", "b"]
+        );
+    }
+
+    #[test]
+    fn nested_blocks_are_not_split() {
+        assert_eq!(
+            pieces(&["<blockquote><p>a</p>", "<p>b</p></blockquote>"]),
+            vec!["<blockquote><p>a</p><p>b</p></blockquote>", ""]
+        );
+    }
+
+    #[test]
+    fn html_with_missing_end_tags_is_not_split() {
+        assert_eq!(
+            pieces(&["<p>a<p>b</p>", "<p>c</p>"]),
+            vec!["<p>a<p>b</p><p>c</p>"]
+        );
+        assert_eq!(
+            pieces(&["</b><p>a</p>", "<p>c</p>"]),
+            vec!["</b><p>a</p><p>c</p>"]
+        );
+        assert_eq!(
+            pieces(&["<style>p{}</style><p>a</p>", "<p>c</p>"]),
+            vec!["<style>p{}</style><p>a</p><p>c</p>"]
+        );
+    }
+}
@@ -7,6 +7,8 @@
 use md_parser::Event;
 use pulldown_cmark as md_parser;
 
+use crate::instrumentation::enter_span;
+use crate::nbsp::NBSP_ENTITY;
 use crate::{dom::MarkdownParseError, UnicodeString};
 
 pub struct MarkdownHTMLParser {}
@@ -22,6 +24,8 @@ impl MarkdownHTMLParser {
         options.insert(Options::ENABLE_STRIKETHROUGH);
 
         let markdown = markdown.to_string();
+        let _span =
+            enter_span!("parse_markdown", markdown_len = markdown.len());
         let parser_events: Vec<_> = Parser::new_ext(&markdown, options)
             .map(|event| match event {
                 // this allows for line breaks to be parsed correctly from markdown
@@ -38,8 +42,11 @@ impl MarkdownHTMLParser {
         // correct way to handle a text block in Markdown. But it breaks our
         // assumption regarding the HTML markup. So let's remove it.
         let html = {
-            // only remove the external <p> if there is only one
-            if html.starts_with("<p>") && html.matches("<p>").count() == 1 {
+            // only remove the external <p> if it wraps all the content
+            if html.starts_with("<p>")
+                && html.ends_with("</p>\n")
+                && html.matches("<p>").count() == 1
+            {
                 let p = "<p>".len();
                 let ppnl = "</p>\n".len();
 
@@ -51,7 +58,7 @@ impl MarkdownHTMLParser {
 
         let html = html
             // Allow for having a newline between paragraphs
-            .replace("</p>\n<p>", "</p><p>&nbsp;</p><p>")
+            .replace("</p>\n<p>", &format!("</p><p>{NBSP_ENTITY}</p><p>"))
             // Remove any trailing newline characters from block tags
             .replace("<ul>\n", "<ul>")
             .replace("</ul>\n", "</ul>")
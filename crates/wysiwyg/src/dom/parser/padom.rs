@@ -4,14 +4,37 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use std::fmt::Display;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
 
 use super::{paqual_name, PaDomHandle, PaDomNode, PaNodeContainer, PaNodeText};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone)]
 pub(crate) struct PaDom {
     pub(crate) nodes: Vec<PaDomNode>,
     pub(crate) document_handle: PaDomHandle,
+    /// The parent of each node in the tree, kept up to date as children
+    /// are added and removed, so that moving nodes while fixing up
+    /// malformed HTML does not search the whole tree each time
+    parents: HashMap<PaDomHandle, PaDomHandle>,
+}
+
+// The parents are worked out from the children, so are left out when
+// comparing and printing trees
+impl PartialEq for PaDom {
+    fn eq(&self, other: &Self) -> bool {
+        self.nodes == other.nodes
+            && self.document_handle == other.document_handle
+    }
+}
+
+impl Debug for PaDom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PaDom")
+            .field("nodes", &self.nodes)
+            .field("document_handle", &self.document_handle)
+            .finish_non_exhaustive()
+    }
 }
 
 impl PaDom {
@@ -28,6 +51,7 @@ impl PaDom {
         Self {
             nodes: vec![document],
             document_handle: PaDomHandle(0),
+            parents: HashMap::new(),
         }
     }
 
@@ -62,6 +86,87 @@ impl PaDom {
         handle
     }
 
+    /// The node whose children include `handle`, if it is in the tree.
+    pub(crate) fn parent_of(
+        &self,
+        handle: &PaDomHandle,
+    ) -> Option<PaDomHandle> {
+        self.parents.get(handle).cloned()
+    }
+
+    /// The children of the node at `handle`, which are none for a text
+    /// node.
+    pub(crate) fn children(&self, handle: &PaDomHandle) -> &[PaDomHandle] {
+        match self.get_node(handle) {
+            PaDomNode::Container(p) | PaDomNode::Document(p) => &p.children,
+            PaDomNode::Text(_) => &[],
+        }
+    }
+
+    /// Add `child` as the last child of the node at `parent`.
+    pub(crate) fn append_child(
+        &mut self,
+        parent: &PaDomHandle,
+        child: PaDomHandle,
+    ) {
+        self.insert_child(parent, self.children(parent).len(), child);
+    }
+
+    /// Add `child` to the children of the node at `parent`, at `index`.
+    pub(crate) fn insert_child(
+        &mut self,
+        parent: &PaDomHandle,
+        index: usize,
+        child: PaDomHandle,
+    ) {
+        match self.get_mut_node(parent) {
+            PaDomNode::Container(p) | PaDomNode::Document(p) => {
+                p.children.insert(index, child.clone())
+            }
+            PaDomNode::Text(_) => {
+                panic!("Appending node to text! {:?}", parent)
+            }
+        }
+        self.parents.insert(child, parent.clone());
+    }
+
+    /// Take the node at `handle` out of the children of its parent, if it
+    /// has one.
+    pub(crate) fn remove_from_parent(&mut self, handle: &PaDomHandle) {
+        let Some(parent) = self.parents.remove(handle) else {
+            return;
+        };
+        if let PaDomNode::Container(p) | PaDomNode::Document(p) =
+            self.get_mut_node(&parent)
+        {
+            p.children.retain(|child| child != handle);
+        }
+    }
+
+    /// Move all the children of the node at `from` to the end of the
+    /// children of the node at `to`.
+    pub(crate) fn move_children(
+        &mut self,
+        from: &PaDomHandle,
+        to: &PaDomHandle,
+    ) {
+        let children = match self.get_mut_node(from) {
+            PaDomNode::Container(p) | PaDomNode::Document(p) => {
+                std::mem::take(&mut p.children)
+            }
+            PaDomNode::Text(_) => return,
+        };
+        if matches!(self.get_node(to), PaDomNode::Text(_)) {
+            for child in children {
+                self.parents.remove(&child);
+            }
+            return;
+        }
+        for child in children {
+            self.append_child(to, child);
+        }
+    }
+
     pub(crate) fn create_element(
         &mut self,
         name: html5ever::QualName,
@@ -12,11 +12,15 @@ use html5ever::tendril::{StrTendril, TendrilSink};
 use html5ever::tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink};
 use html5ever::{parse_fragment, Attribute, QualName};
 use std::cell::{Ref, RefCell};
+use std::collections::HashSet;
 
 pub(crate) type DomCreationResult = Result<PaDom, PaDomCreationError>;
 
 pub(crate) struct PaDomCreator {
     state: RefCell<PaDomCreationError>,
+    /// Nodes which are dropped instead of being added to the tree, e.g.
+    /// comments
+    dropped: RefCell<HashSet<PaDomHandle>>,
 }
 
 impl PaDomCreator {
@@ -37,6 +41,31 @@ impl Default for PaDomCreator {
     fn default() -> Self {
         Self {
             state: RefCell::new(PaDomCreationError::new()),
+            dropped: RefCell::new(HashSet::new()),
+        }
+    }
+}
+
+impl PaDomCreator {
+    /// Create a node which is never added to the tree.
+    fn create_dropped_node(&self) -> PaDomHandle {
+        let handle =
+            self.state
+                .borrow_mut()
+                .dom
+                .add_node(PaDomNode::Text(PaNodeText {
+                    content: String::new(),
+                }));
+        self.dropped.borrow_mut().insert(handle.clone());
+        handle
+    }
+
+    fn is_dropped(&self, child: &NodeOrText<PaDomHandle>) -> bool {
+        match child {
+            NodeOrText::AppendNode(handle) => {
+                self.dropped.borrow().contains(handle)
+            }
+            NodeOrText::AppendText(_) => false,
         }
     }
 }
@@ -82,7 +111,7 @@ impl TreeSink for PaDomCreator {
     }
 
     fn create_comment(&self, _text: StrTendril) -> Self::Handle {
-        todo!("Comments not yet supported")
+        self.create_dropped_node()
     }
 
     fn create_pi(
@@ -90,19 +119,16 @@ impl TreeSink for PaDomCreator {
         _target: StrTendril,
         _data: StrTendril,
     ) -> Self::Handle {
-        todo!("create_pi not yet supported")
+        self.create_dropped_node()
     }
 
     fn append(&self, parent: &Self::Handle, child: NodeOrText<Self::Handle>) {
+        if self.is_dropped(&child) {
+            return;
+        }
         let dom = &mut self.state.borrow_mut().dom;
         match child {
-            NodeOrText::AppendNode(child) => match dom.get_mut_node(parent) {
-                PaDomNode::Container(p) => p.children.push(child),
-                PaDomNode::Document(p) => p.children.push(child),
-                PaDomNode::Text(_) => {
-                    panic!("Appending node to text! {:?}", parent)
-                }
-            },
+            NodeOrText::AppendNode(child) => dom.append_child(parent, child),
             NodeOrText::AppendText(tendril) => {
                 let text_handle = match dom.get_node(parent) {
                     PaDomNode::Document(_) => None,
@@ -133,14 +159,7 @@ impl TreeSink for PaDomCreator {
                         dom.add_node(PaDomNode::Text(PaNodeText {
                             content: tendril.as_ref().to_owned(),
                         }));
-
-                    match dom.get_mut_node(parent) {
-                        PaDomNode::Container(p) => p.children.push(new_handle),
-                        PaDomNode::Document(p) => p.children.push(new_handle),
-                        PaDomNode::Text(_) => {
-                            panic!("parent changed from container to text!")
-                        }
-                    }
+                    dom.append_child(parent, new_handle);
                 }
             }
         };
@@ -148,11 +167,16 @@ impl TreeSink for PaDomCreator {
 
     fn append_based_on_parent_node(
         &self,
-        _element: &Self::Handle,
-        _prev_element: &Self::Handle,
-        _child: NodeOrText<Self::Handle>,
+        element: &Self::Handle,
+        prev_element: &Self::Handle,
+        child: NodeOrText<Self::Handle>,
     ) {
-        todo!("append_based_on_parent_node not yet supported")
+        let has_parent = self.state.borrow().dom.parent_of(element).is_some();
+        if has_parent {
+            self.append_before_sibling(element, child);
+        } else {
+            self.append(prev_element, child);
+        }
     }
 
     fn append_doctype_to_document(
@@ -161,11 +185,11 @@ impl TreeSink for PaDomCreator {
         _public_id: StrTendril,
         _system_id: StrTendril,
     ) {
-        todo!("append_doctype_to_document not yet supported")
+        // Doctypes are dropped
     }
 
     fn mark_script_already_started(&self, _node: &Self::Handle) {
-        todo!()
+        // Scripts are never run
     }
 
     fn pop(&self, _node: &Self::Handle) {
@@ -173,7 +197,14 @@ impl TreeSink for PaDomCreator {
     }
 
     fn get_template_contents(&self, _target: &Self::Handle) -> Self::Handle {
-        todo!("get_template_contents not yet supported")
+        // The contents of a template are never shown, so are dropped
+        self.state.borrow_mut().dom.add_node(PaDomNode::Container(
+            PaNodeContainer {
+                name: paqual_name("template"),
+                attrs: Vec::new(),
+                children: Vec::new(),
+            },
+        ))
     }
 
     fn same_node(&self, x: &Self::Handle, y: &Self::Handle) -> bool {
@@ -186,10 +217,38 @@ impl TreeSink for PaDomCreator {
 
     fn append_before_sibling(
         &self,
-        _sibling: &Self::Handle,
-        _new_node: NodeOrText<Self::Handle>,
+        sibling: &Self::Handle,
+        new_node: NodeOrText<Self::Handle>,
     ) {
-        todo!("append_before_sibling not yet supported")
+        if self.is_dropped(&new_node) {
+            return;
+        }
+        if let NodeOrText::AppendNode(node) = &new_node {
+            self.remove_from_parent(node);
+        }
+        let dom = &mut self.state.borrow_mut().dom;
+        let Some(parent) = dom.parent_of(sibling) else {
+            return;
+        };
+        let children = dom.children(&parent);
+        let index = children.iter().position(|c| c == sibling).unwrap();
+        let previous = index.checked_sub(1).map(|i| children[i].clone());
+        let new_handle = match new_node {
+            NodeOrText::AppendNode(node) => node,
+            NodeOrText::AppendText(tendril) => {
+                // Text is merged into text just before it, as in `append`
+                if let Some(PaDomNode::Text(text)) =
+                    previous.map(|previous| dom.get_mut_node(&previous))
+                {
+                    text.content += tendril.as_ref();
+                    return;
+                }
+                dom.add_node(PaDomNode::Text(PaNodeText {
+                    content: tendril.as_ref().to_owned(),
+                }))
+            }
+        };
+        dom.insert_child(&parent, index, new_handle);
     }
 
     fn add_attrs_if_missing(
@@ -226,26 +285,27 @@ impl TreeSink for PaDomCreator {
         _form: &Self::Handle,
         _nodes: (&Self::Handle, Option<&Self::Handle>),
     ) {
-        todo!()
+        // Forms are not supported, so there is nothing to associate
     }
 
-    fn remove_from_parent(&self, _target: &Self::Handle) {
-        todo!("remove_from_parent not yet supported")
+    fn remove_from_parent(&self, target: &Self::Handle) {
+        self.state.borrow_mut().dom.remove_from_parent(target);
     }
 
     fn reparent_children(
         &self,
-        _node: &Self::Handle,
-        _new_parent: &Self::Handle,
+        node: &Self::Handle,
+        new_parent: &Self::Handle,
     ) {
-        todo!("reparent_children not yet supported")
+        self.state.borrow_mut().dom.move_children(node, new_parent);
     }
 
     fn is_mathml_annotation_xml_integration_point(
         &self,
         _handle: &Self::Handle,
     ) -> bool {
-        todo!("is_mathml_annotation_xml_integration_point not yet supported")
+        // MathML is not supported, so its contents are never HTML
+        false
     }
 
     fn set_current_line(&self, _line_number: u64) {
@@ -256,7 +316,7 @@ impl TreeSink for PaDomCreator {
         &self,
         _intended_parent: &Self::Handle,
     ) -> bool {
-        todo!("allow_declarative_shadow_roots not yet supported")
+        false
     }
 
     fn attach_declarative_shadow(
@@ -265,7 +325,7 @@ impl TreeSink for PaDomCreator {
         _template: &Self::Handle,
         _attrs: &[Attribute],
     ) -> bool {
-        todo!("attach_declarative_shadow not yet supported")
+        false
     }
 }
 
@@ -457,6 +517,11 @@ mod test {
         PaDomCreator::parse(input).unwrap()
     }
 
+    /// Parse HTML which is not well formed, keeping what it parses to.
+    fn parse_malformed(input: &str) -> PaDom {
+        PaDomCreator::parse(input).unwrap_err().dom
+    }
+
     #[test]
     fn parsing_an_empty_string_creates_an_empty_dom() {
         assert_eq!(d(parse("")), d(doc(&[el("html", &[])])));
@@ -517,4 +582,53 @@ mod test {
     // Note: more complex tests are in parse, because it's more ergonomic to
     // work with a real Dom instead of PaDom, because it converts back to HTML
     // nicely.
+
+    #[test]
+    fn parsing_comments_drops_them() {
+        assert_eq!(
+            d(parse("a<!-- comment --><b>b</b>")),
+            d(doc(&[el("html", &[tx("a"), el("b", &[tx("b")])])]))
+        );
+    }
+
+    #[test]
+    fn parsing_misnested_formatting_reparents_it() {
+        assert_eq!(
+            d(parse_malformed("<b>a<i>b</b>c</i>")),
+            d(doc(&[el(
+                "html",
+                &[
+                    el("b", &[tx("a"), el("i", &[tx("b")])]),
+                    el("i", &[tx("c")])
+                ]
+            )]))
+        );
+    }
+
+    #[test]
+    fn parsing_text_in_a_table_moves_it_before_the_table() {
+        let dom = parse_malformed("<table>a</table>");
+        let PaDomNode::Document(document) = dom.get_document() else {
+            panic!("No document");
+        };
+        let PaDomNode::Container(html) = dom.get_node(&document.children[0])
+        else {
+            panic!("No html element");
+        };
+        let children: Vec<&PaDomNode> =
+            html.children.iter().map(|c| dom.get_node(c)).collect();
+        assert!(matches!(
+            children[..],
+            [PaDomNode::Text(PaNodeText { ref content }), PaDomNode::Container(ref table)]
+                if content == "a" && table.name.local.as_ref() == "table"
+        ));
+    }
+
+    #[test]
+    fn parsing_a_template_drops_its_contents() {
+        assert_eq!(
+            d(parse("<template><b>a</b></template>")),
+            d(doc(&[el("html", &[el("template", &[])])]))
+        );
+    }
 }
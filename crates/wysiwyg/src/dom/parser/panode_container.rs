@@ -26,8 +26,9 @@ impl PaNodeContainer {
     pub(crate) fn contains_style(&self, name: &str, value: &str) -> bool {
         self.get_attr("style")
             .map(|v| {
+                // The last declaration needn't end with a semicolon
                 Regex::new(&format!(
-                    r"(?i){}:\s*{};",
+                    r"(?i)(^|;)\s*{}\s*:\s*{}\s*(;|$)",
                     regex::escape(name),
                     regex::escape(value)
                 ))
@@ -48,3 +49,15 @@ fn test_contains_style() {
     assert!(node.contains_style("font-weight", "bold"));
     assert!(!node.contains_style("font-weight", "normal"));
 }
+
+#[test]
+fn test_contains_style_in_last_declaration() {
+    let node = PaNodeContainer {
+        name: QualName::new(None, "span".into(), "span".into()),
+        attrs: vec![("style".into(), "color: red; font-weight : bold".into())],
+        children: Vec::new(),
+    };
+    assert!(node.contains_style("font-weight", "bold"));
+    assert!(node.contains_style("color", "red"));
+    assert!(!node.contains_style("weight", "bold"));
+}
@@ -4,23 +4,59 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use regex::Regex;
-
+use crate::dom::at_room_detection::AtRoomDetection;
+use crate::dom::attribute_policy::AttributePolicy;
 use crate::dom::dom_creation_error::HtmlParseError;
 use crate::dom::html_source::HtmlSource;
+use crate::dom::message_validation::{attribute_issues, HtmlIssue};
 use crate::dom::nodes::dom_node::DomNodeKind::{self};
 use crate::dom::nodes::{ContainerNode, ContainerNodeKind};
+#[cfg(feature = "sys")]
+use crate::dom::parser::source_map::SourceChange;
+#[cfg(feature = "source-map")]
+use crate::dom::parser::source_map::{
+    start_tag_ranges, SourceMap, SourcePosition,
+};
+use crate::dom::whitespace_policy::WhitespacePolicy;
 use crate::dom::Dom;
+use crate::instrumentation::enter_span;
+use crate::nbsp::is_placeholder;
 use crate::{DomHandle, DomNode, UnicodeString};
 
+/// The default maximum number of nested elements kept when parsing HTML.
+pub const DEFAULT_MAX_PARSE_DEPTH: usize = 100;
+
+/// Tags which are parsed into block nodes, so that legacy `<div>` and
+/// `<center>` tags, and the cells of layout tables, around them can't
+/// become paragraphs.
+pub(super) const BLOCK_TAGS: [&str; 16] = [
+    "blockquote",
+    "center",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "ul",
+];
+
 pub fn parse<S>(html: &str) -> Result<Dom<S>, HtmlParseError>
 where
     S: UnicodeString,
 {
+    let _span = enter_span!("parse_html", html_len = html.len());
     cfg_if::cfg_if! {
         if #[cfg(feature = "sys")] {
             sys::HtmlParser::default().parse(html)
-        } else if #[cfg(all(feature = "js", target_arch = "wasm32"))] {
+        } else if #[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))] {
             js::HtmlParser::default().parse(html)
         } else {
             unreachable!("The `sys` or `js` are mutually exclusive, and one of them must be enabled.")
@@ -28,24 +64,131 @@ where
     }
 }
 
-pub fn parse_from_source<S>(
+/// Parse Matrix HTML like [parse], but skip unsupported elements, keeping
+/// their content, instead of failing, so that events which are slightly out
+/// of spec can still be edited. Returns a description of each element which
+/// was skipped along with the [Dom].
+pub fn parse_lenient<S>(
+    html: &str,
+) -> Result<(Dom<S>, Vec<String>), HtmlParseError>
+where
+    S: UnicodeString,
+{
+    let _span = enter_span!("parse_html", html_len = html.len());
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "sys")] {
+            let mut parser = sys::HtmlParser::default().with_lenient(true);
+            let dom = parser.parse(html)?;
+            Ok((dom, parser.take_warnings()))
+        } else if #[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))] {
+            let mut parser = js::HtmlParser::default().with_lenient(true);
+            let dom = parser.parse(html)?;
+            Ok((dom, parser.take_warnings()))
+        } else {
+            unreachable!("The `sys` or `js` are mutually exclusive, and one of them must be enabled.")
+        }
+    }
+}
+
+/// Check HTML meant for the formatted body of a Matrix message without
+/// creating a [crate::ComposerModel], e.g. in a bot or bridge before it
+/// sends the message. Elements are checked as the Matrix parser loads them,
+/// and attributes and URI schemes against the rules of the Matrix spec.
+/// Returns no issues if the HTML is valid.
+pub fn validate_message_html(html: &str) -> Vec<HtmlIssue> {
+    let _span = enter_span!("validate_html", html_len = html.len());
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "sys")] {
+            let mut parser = sys::HtmlParser::default()
+                .with_lenient(true)
+                .with_attribute_checks(true);
+            match parser.parse::<String>(html) {
+                Ok(_) => parser.take_issues(),
+                Err(err) => err
+                    .parse_errors
+                    .into_iter()
+                    .map(HtmlIssue::Malformed)
+                    .collect(),
+            }
+        } else if #[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))] {
+            let mut parser = js::HtmlParser::default()
+                .with_lenient(true)
+                .with_attribute_checks(true);
+            match parser.parse::<String>(html) {
+                Ok(_) => parser.take_issues(),
+                Err(err) => err
+                    .parse_errors
+                    .into_iter()
+                    .map(HtmlIssue::Malformed)
+                    .collect(),
+            }
+        } else {
+            unreachable!("The `sys` or `js` are mutually exclusive, and one of them must be enabled.")
+        }
+    }
+}
+
+/// Parse HTML from the given source, flattening any elements nested more
+/// than `max_depth` levels deep into plain text so that hostile input cannot
+/// produce a [Dom] deep enough to overflow the stack. Attributes of
+/// supported tags are kept if `attribute_policy` allows them, and
+/// whitespace in text is handled according to `whitespace_policy`, and
+/// `@room` becomes an @room mention where `at_room_detection` allows. If
+/// `lenient`, unsupported elements in Matrix HTML are skipped as with
+/// [parse_lenient], and described in the warnings returned with the [Dom].
+pub fn parse_from_source_with_options<S>(
     html: &str,
     source: HtmlSource,
-) -> Result<Dom<S>, HtmlParseError>
+    max_depth: usize,
+    attribute_policy: &AttributePolicy,
+    whitespace_policy: WhitespacePolicy,
+    at_room_detection: AtRoomDetection,
+    lenient: bool,
+) -> Result<(Dom<S>, Vec<String>), HtmlParseError>
 where
     S: UnicodeString,
 {
+    let _span = enter_span!("parse_html", html_len = html.len());
     cfg_if::cfg_if! {
         if #[cfg(feature = "sys")] {
-            sys::HtmlParser::default().parse_from_source(html, source)
-        } else if #[cfg(all(feature = "js", target_arch = "wasm32"))] {
-            js::HtmlParser::default().parse_from_source(html, source)
+            let mut parser = sys::HtmlParser::with_max_depth(max_depth)
+                .with_attribute_policy(attribute_policy.clone())
+                .with_whitespace_policy(whitespace_policy)
+                .with_at_room_detection(at_room_detection)
+                .with_lenient(lenient);
+            let dom = parser.parse_from_source(html, source)?;
+            Ok((dom, parser.take_warnings()))
+        } else if #[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))] {
+            let mut parser = js::HtmlParser::with_max_depth(max_depth)
+                .with_attribute_policy(attribute_policy.clone())
+                .with_whitespace_policy(whitespace_policy)
+                .with_at_room_detection(at_room_detection)
+                .with_lenient(lenient);
+            let dom = parser.parse_from_source(html, source)?;
+            Ok((dom, parser.take_warnings()))
         } else {
             unreachable!("The `sys` or `js` are mutually exclusive, and one of them must be enabled.")
         }
     }
 }
 
+/// Parse HTML from the given source with the default options, also
+/// returning where each of its elements was and what became of it, e.g. so
+/// that users can be shown which parts of pasted content were dropped.
+#[cfg(feature = "source-map")]
+pub fn parse_with_source_map<S>(
+    html: &str,
+    source: HtmlSource,
+) -> Result<(Dom<S>, SourceMap), HtmlParseError>
+where
+    S: UnicodeString,
+{
+    let _span = enter_span!("parse_html", html_len = html.len());
+    let mut parser = sys::HtmlParser::default().with_source_map();
+    let dom = parser.parse_from_source(html, source)?;
+    Ok((dom, parser.take_source_map()))
+}
+
 /* These html fragments were copied directly from google docs/ms docs(minus the cleanup/stripping we do in "replace_html" function) and represents the following content:
 └>ol
   ├>li
@@ -78,9 +221,37 @@ where
           └>"Nested"
 */
 #[cfg(test)]
-pub const GOOGLE_DOC_HTML_PASTEBOARD: &str = r#"<ol style="margin-top:0;margin-bottom:0;padding-inline-start:48px;"><li dir="ltr" style="list-style-type:decimal;font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:italic;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;" aria-level="1"><p dir="ltr" style="line-height:1.38;margin-top:0pt;margin-bottom:0pt;" role="presentation"><span style="font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:italic;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;white-space:pre-wrap;">Italic</span></p></li><li dir="ltr" style="list-style-type:decimal;font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:700;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;" aria-level="1"><p dir="ltr" style="line-height:1.38;margin-top:0pt;margin-bottom:0pt;" role="presentation"><span style="font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:700;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;white-space:pre-wrap;">Bold</span></p></li><li dir="ltr" style="list-style-type:decimal;font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;" aria-level="1"><p dir="ltr" style="line-height:1.38;margin-top:0pt;margin-bottom:0pt;" role="presentation"><span style="font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;white-space:pre-wrap;">Unformatted</span></p></li><li dir="ltr" style="list-style-type:decimal;font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:line-through;-webkit-text-decoration-skip:none;text-decoration-skip-ink:none;vertical-align:baseline;white-space:pre;" aria-level="1"><p dir="ltr" style="line-height:1.38;margin-top:0pt;margin-bottom:0pt;" role="presentation"><span style="font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:line-through;-webkit-text-decoration-skip:none;text-decoration-skip-ink:none;vertical-align:baseline;white-space:pre;white-space:pre-wrap;">Strikethrough</span></p></li><li dir="ltr" style="list-style-type:decimal;font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;" aria-level="1"><p dir="ltr" style="line-height:1.38;margin-top:0pt;margin-bottom:0pt;" role="presentation"><span style="font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:underline;-webkit-text-decoration-skip:none;text-decoration-skip-ink:none;vertical-align:baseline;white-space:pre;white-space:pre-wrap;">Underlined</span></p></li><li dir="ltr" style="list-style-type:decimal;font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;" aria-level="1"><p dir="ltr" style="line-height:1.38;margin-top:0pt;margin-bottom:0pt;" role="presentation"><a href="http://matrix.org" style="text-decoration:none;"><span style="font-size:11pt;font-family:Arial,sans-serif;color:#1155cc;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:underline;-webkit-text-decoration-skip:none;text-decoration-skip-ink:none;vertical-align:baseline;white-space:pre;white-space:pre-wrap;">Linked</span></a></p></li><ul style="margin-top:0;margin-bottom:0;padding-inline-start:48px;"><li dir="ltr" style="list-style-type:circle;font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;" aria-level="2"><p dir="ltr" style="line-height:1.38;margin-top:0pt;margin-bottom:0pt;" role="presentation"><span style="font-size:11pt;font-family:Arial,sans-serif;color:#000000;background-color:transparent;font-weight:400;font-style:normal;font-variant:normal;text-decoration:none;vertical-align:baseline;white-space:pre;white-space:pre-wrap;">Nested</span></p></li></ul></ol>"#;
+pub const GOOGLE_DOC_HTML_PASTEBOARD: &str =
+    include_str!("../../../fixtures/google_doc_pasteboard.html");
 #[cfg(test)]
-pub const MS_DOC_HTML_PASTEBOARD: &str = r#"<div class="ListContainerWrapper SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; position: relative; color: rgb(0, 0, 0); font-family: Aptos, Aptos_MSFontService, sans-serif; font-size: 16px; font-style: normal; font-variant-ligatures: normal; font-variant-caps: normal; font-weight: 400; letter-spacing: normal; orphans: 2; text-align: left; text-indent: 0px; text-transform: none; widows: 2; word-spacing: 0px; -webkit-text-stroke-width: 0px; white-space: normal; background-color: rgb(255, 255, 255); text-decoration-thickness: initial; text-decoration-style: initial; text-decoration-color: initial;"><ol class="NumberListStyle1 SCXW204127278 BCX0" role="list" start="1" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; list-style-type: decimal; overflow: visible;"><li aria-setsize="-1" data-leveltext="%1." data-font="" data-listid="3" data-list-defn-props="{&quot;335552541&quot;:0,&quot;335559685&quot;:720,&quot;335559991&quot;:360,&quot;469769242&quot;:[65533,0,46],&quot;469777803&quot;:&quot;left&quot;,&quot;469777804&quot;:&quot;%1.&quot;,&quot;469777815&quot;:&quot;hybridMultilevel&quot;}" data-aria-posinset="1" data-aria-level="1" role="listitem" class="OutlineElement Ltr SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px 0px 0px 24px; padding: 0px; user-select: text; clear: both; cursor: text; overflow: visible; position: relative; direction: ltr; display: block; font-size: 12pt; font-family: Aptos, Aptos_MSFontService, sans-serif; vertical-align: baseline;"><p class="Paragraph SCXW204127278 BCX0" paraid="81782558" paraeid="{dc225749-75de-40ef-a9ba-ce0ccc24981d}{48}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; overflow-wrap: break-word; white-space: pre-wrap; font-weight: normal; font-style: normal; vertical-align: baseline; font-kerning: none; background-color: transparent; color: windowtext; text-align: left; text-indent: 0px;"><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; font-size: 12pt; font-style: italic; text-decoration: none; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: normal;"><span class="NormalTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">Italic</span></span><span class="EOP SCXW204127278 BCX0" data-ccp-props="{}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-size: 12pt; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif;"> </span></p></li></ol></div><div class="ListContainerWrapper SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; position: relative; color: rgb(0, 0, 0); font-family: Aptos, Aptos_MSFontService, sans-serif; font-size: 16px; font-style: normal; font-variant-ligatures: normal; font-variant-caps: normal; font-weight: 400; letter-spacing: normal; orphans: 2; text-align: left; text-indent: 0px; text-transform: none; widows: 2; word-spacing: 0px; -webkit-text-stroke-width: 0px; white-space: normal; background-color: rgb(255, 255, 255); text-decoration-thickness: initial; text-decoration-style: initial; text-decoration-color: initial;"><ol class="NumberListStyle1 SCXW204127278 BCX0" role="list" start="2" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; list-style-type: decimal; overflow: visible;"><li aria-setsize="-1" data-leveltext="%1." data-font="" data-listid="3" data-list-defn-props="{&quot;335552541&quot;:0,&quot;335559685&quot;:720,&quot;335559991&quot;:360,&quot;469769242&quot;:[65533,0,46],&quot;469777803&quot;:&quot;left&quot;,&quot;469777804&quot;:&quot;%1.&quot;,&quot;469777815&quot;:&quot;hybridMultilevel&quot;}" data-aria-posinset="2" data-aria-level="1" role="listitem" class="OutlineElement Ltr SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px 0px 0px 24px; padding: 0px; user-select: text; clear: both; cursor: text; overflow: visible; position: relative; direction: ltr; display: block; font-size: 12pt; font-family: Aptos, Aptos_MSFontService, sans-serif; vertical-align: baseline;"><p class="Paragraph SCXW204127278 BCX0" paraid="1266616274" paraeid="{dc225749-75de-40ef-a9ba-ce0ccc24981d}{54}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; overflow-wrap: break-word; white-space: pre-wrap; font-weight: normal; font-style: normal; vertical-align: baseline; font-kerning: none; background-color: transparent; color: windowtext; text-align: left; text-indent: 0px;"><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun MacChromeBold SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; -webkit-font-smoothing: antialiased; font-variant-ligatures: none !important; font-size: 12pt; font-style: normal; text-decoration: none; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: bold;"><span class="NormalTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">Bold</span></span><span class="EOP SCXW204127278 BCX0" data-ccp-props="{}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-size: 12pt; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif;"> </span></p></li></ol></div><div class="ListContainerWrapper SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; position: relative; color: rgb(0, 0, 0); font-family: Aptos, Aptos_MSFontService, sans-serif; font-size: 16px; font-style: normal; font-variant-ligatures: normal; font-variant-caps: normal; font-weight: 400; letter-spacing: normal; orphans: 2; text-align: left; text-indent: 0px; text-transform: none; widows: 2; word-spacing: 0px; -webkit-text-stroke-width: 0px; white-space: normal; background-color: rgb(255, 255, 255); text-decoration-thickness: initial; text-decoration-style: initial; text-decoration-color: initial;"><ol class="NumberListStyle1 SCXW204127278 BCX0" role="list" start="3" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; list-style-type: decimal; overflow: visible;"><li aria-setsize="-1" data-leveltext="%1." data-font="" data-listid="3" data-list-defn-props="{&quot;335552541&quot;:0,&quot;335559685&quot;:720,&quot;335559991&quot;:360,&quot;469769242&quot;:[65533,0,46],&quot;469777803&quot;:&quot;left&quot;,&quot;469777804&quot;:&quot;%1.&quot;,&quot;469777815&quot;:&quot;hybridMultilevel&quot;}" data-aria-posinset="3" data-aria-level="1" role="listitem" class="OutlineElement Ltr SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px 0px 0px 24px; padding: 0px; user-select: text; clear: both; cursor: text; overflow: visible; position: relative; direction: ltr; display: block; font-size: 12pt; font-family: Aptos, Aptos_MSFontService, sans-serif; vertical-align: baseline;"><p class="Paragraph SCXW204127278 BCX0" paraid="2141762432" paraeid="{dc225749-75de-40ef-a9ba-ce0ccc24981d}{60}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; overflow-wrap: break-word; white-space: pre-wrap; font-weight: normal; font-style: normal; vertical-align: baseline; font-kerning: none; background-color: transparent; color: windowtext; text-align: left; text-indent: 0px;"><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; font-size: 12pt; font-style: normal; text-decoration: none; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: normal;"><span class="NormalTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">Unformatted</span></span><span class="EOP SCXW204127278 BCX0" data-ccp-props="{}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-size: 12pt; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif;"> </span></p></li></ol></div><div class="ListContainerWrapper SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; position: relative; color: rgb(0, 0, 0); font-family: Aptos, Aptos_MSFontService, sans-serif; font-size: 16px; font-style: normal; font-variant-ligatures: normal; font-variant-caps: normal; font-weight: 400; letter-spacing: normal; orphans: 2; text-align: left; text-indent: 0px; text-transform: none; widows: 2; word-spacing: 0px; -webkit-text-stroke-width: 0px; white-space: normal; background-color: rgb(255, 255, 255); text-decoration-thickness: initial; text-decoration-style: initial; text-decoration-color: initial;"><ol class="NumberListStyle1 SCXW204127278 BCX0" role="list" start="4" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; list-style-type: decimal; overflow: visible;"><li aria-setsize="-1" data-leveltext="%1." data-font="" data-listid="3" data-list-defn-props="{&quot;335552541&quot;:0,&quot;335559685&quot;:720,&quot;335559991&quot;:360,&quot;469769242&quot;:[65533,0,46],&quot;469777803&quot;:&quot;left&quot;,&quot;469777804&quot;:&quot;%1.&quot;,&quot;469777815&quot;:&quot;hybridMultilevel&quot;}" data-aria-posinset="4" data-aria-level="1" role="listitem" class="OutlineElement Ltr SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px 0px 0px 24px; padding: 0px; user-select: text; clear: both; cursor: text; overflow: visible; position: relative; direction: ltr; display: block; font-size: 12pt; font-family: Aptos, Aptos_MSFontService, sans-serif; vertical-align: baseline;"><p class="Paragraph SCXW204127278 BCX0" paraid="400977494" paraeid="{dc225749-75de-40ef-a9ba-ce0ccc24981d}{66}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; overflow-wrap: break-word; white-space: pre-wrap; font-weight: normal; font-style: normal; vertical-align: baseline; font-kerning: none; background-color: transparent; color: windowtext; text-align: left; text-indent: 0px;"><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun Strikethrough SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; font-size: 12pt; font-style: normal; text-decoration: line-through; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: normal;"><span class="NormalTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">Strikethrough</span></span><span class="EOP SCXW204127278 BCX0" data-ccp-props="{}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-size: 12pt; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif;"> </span></p></li></ol></div><div class="ListContainerWrapper SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; position: relative; color: rgb(0, 0, 0); font-family: Aptos, Aptos_MSFontService, sans-serif; font-size: 16px; font-style: normal; font-variant-ligatures: normal; font-variant-caps: normal; font-weight: 400; letter-spacing: normal; orphans: 2; text-align: left; text-indent: 0px; text-transform: none; widows: 2; word-spacing: 0px; -webkit-text-stroke-width: 0px; white-space: normal; background-color: rgb(255, 255, 255); text-decoration-thickness: initial; text-decoration-style: initial; text-decoration-color: initial;"><ol class="NumberListStyle1 SCXW204127278 BCX0" role="list" start="5" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; list-style-type: decimal; overflow: visible;"><li aria-setsize="-1" data-leveltext="%1." data-font="" data-listid="3" data-list-defn-props="{&quot;335552541&quot;:0,&quot;335559685&quot;:720,&quot;335559991&quot;:360,&quot;469769242&quot;:[65533,0,46],&quot;469777803&quot;:&quot;left&quot;,&quot;469777804&quot;:&quot;%1.&quot;,&quot;469777815&quot;:&quot;hybridMultilevel&quot;}" data-aria-posinset="5" data-aria-level="1" role="listitem" class="OutlineElement Ltr SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px 0px 0px 24px; padding: 0px; user-select: text; clear: both; cursor: text; overflow: visible; position: relative; direction: ltr; display: block; font-size: 12pt; font-family: Aptos, Aptos_MSFontService, sans-serif; vertical-align: baseline;"><p class="Paragraph SCXW204127278 BCX0" paraid="1929898719" paraeid="{dc225749-75de-40ef-a9ba-ce0ccc24981d}{72}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; overflow-wrap: break-word; white-space: pre-wrap; font-weight: normal; font-style: normal; vertical-align: baseline; font-kerning: none; background-color: transparent; color: windowtext; text-align: left; text-indent: 0px;"><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun Underlined SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; font-size: 12pt; font-style: normal; text-decoration: underline; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: normal;"><span class="NormalTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">Underlined</span></span><span class="EOP SCXW204127278 BCX0" data-ccp-props="{}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-size: 12pt; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif;"> </span></p></li></ol></div><div class="ListContainerWrapper SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; position: relative; color: rgb(0, 0, 0); font-family: Aptos, Aptos_MSFontService, sans-serif; font-size: 16px; font-style: normal; font-variant-ligatures: normal; font-variant-caps: normal; font-weight: 400; letter-spacing: normal; orphans: 2; text-align: left; text-indent: 0px; text-transform: none; widows: 2; word-spacing: 0px; -webkit-text-stroke-width: 0px; white-space: normal; background-color: rgb(255, 255, 255); text-decoration-thickness: initial; text-decoration-style: initial; text-decoration-color: initial;"><ol class="NumberListStyle1 SCXW204127278 BCX0" role="list" start="6" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; list-style-type: decimal; overflow: visible;"><li aria-setsize="-1" data-leveltext="%1." data-font="" data-listid="3" data-list-defn-props="{&quot;335552541&quot;:0,&quot;335559685&quot;:720,&quot;335559991&quot;:360,&quot;469769242&quot;:[65533,0,46],&quot;469777803&quot;:&quot;left&quot;,&quot;469777804&quot;:&quot;%1.&quot;,&quot;469777815&quot;:&quot;hybridMultilevel&quot;}" data-aria-posinset="6" data-aria-level="1" role="listitem" class="OutlineElement Ltr SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px 0px 0px 24px; padding: 0px; user-select: text; clear: both; cursor: text; overflow: visible; position: relative; direction: ltr; display: block; font-size: 12pt; font-family: Aptos, Aptos_MSFontService, sans-serif; vertical-align: baseline;"><p class="Paragraph SCXW204127278 BCX0" paraid="1763241731" paraeid="{dc225749-75de-40ef-a9ba-ce0ccc24981d}{78}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; overflow-wrap: break-word; white-space: pre-wrap; font-weight: normal; font-style: normal; vertical-align: baseline; font-kerning: none; background-color: transparent; color: windowtext; text-align: left; text-indent: 0px;"><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun EmptyTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; font-size: 12pt; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif;"></span><a class="Hyperlink SCXW204127278 BCX0" href="https://matrix.org/" target="_blank" rel="noreferrer noopener" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; text-decoration: none; color: inherit;"><span data-contrast="none" xml:lang="EN-GB" lang="EN-GB" class="TextRun Underlined SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; color: rgb(70, 120, 134); font-size: 12pt; font-style: normal; text-decoration: underline; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: normal;"><span class="NormalTextRun SCXW204127278 BCX0" data-ccp-charstyle="Hyperlink" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">Linked</span></span></a><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun EmptyTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; font-size: 12pt; font-style: normal; text-decoration: none; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: normal;"></span><span class="EOP SCXW204127278 BCX0" data-ccp-props="{}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-size: 12pt; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif;"> </span></p></li></ol></div><div class="ListContainerWrapper SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; position: relative; color: rgb(0, 0, 0); font-family: Aptos, Aptos_MSFontService, sans-serif; font-size: 16px; font-style: normal; font-variant-ligatures: normal; font-variant-caps: normal; font-weight: 400; letter-spacing: normal; orphans: 2; text-align: left; text-indent: 0px; text-transform: none; widows: 2; word-spacing: 0px; -webkit-text-stroke-width: 0px; white-space: normal; background-color: rgb(255, 255, 255); text-decoration-thickness: initial; text-decoration-style: initial; text-decoration-color: initial;"><ul class="BulletListStyle2 SCXW204127278 BCX0" role="list" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; cursor: text; font-family: verdana; overflow: visible; list-style-type: circle;"><li aria-setsize="-1" data-leveltext="o" data-font="Courier New" data-listid="3" data-list-defn-props="{&quot;335552541&quot;:1,&quot;335559685&quot;:1440,&quot;335559991&quot;:360,&quot;469769226&quot;:&quot;Courier New&quot;,&quot;469769242&quot;:[9675],&quot;469777803&quot;:&quot;left&quot;,&quot;469777804&quot;:&quot;o&quot;,&quot;469777815&quot;:&quot;hybridMultilevel&quot;}" data-aria-posinset="1" data-aria-level="2" role="listitem" class="OutlineElement Ltr SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px 0px 0px 72px; padding: 0px; user-select: text; clear: both; cursor: text; overflow: visible; position: relative; direction: ltr; display: block; font-size: 12pt; font-family: Aptos, Aptos_MSFontService, sans-serif; vertical-align: baseline;"><p class="Paragraph SCXW204127278 BCX0" paraid="274900590" paraeid="{dc225749-75de-40ef-a9ba-ce0ccc24981d}{85}" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; overflow-wrap: break-word; white-space: pre-wrap; font-weight: normal; font-style: normal; vertical-align: baseline; font-kerning: none; background-color: transparent; color: windowtext; text-align: left; text-indent: 0px;"><span data-contrast="auto" xml:lang="EN-GB" lang="EN-GB" class="TextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text; font-variant-ligatures: none !important; font-size: 12pt; font-style: normal; text-decoration: none; line-height: 22.0875px; font-family: Aptos, Aptos_EmbeddedFont, Aptos_MSFontService, sans-serif; font-weight: normal;"><span class="NormalTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">N</span><span class="NormalTextRun SCXW204127278 BCX0" style="-webkit-user-drag: none; -webkit-tap-highlight-color: transparent; margin: 0px; padding: 0px; user-select: text;">ested</span></span></p></li></ul></div>"#;
+pub const MS_DOC_HTML_PASTEBOARD: &str =
+    include_str!("../../../fixtures/ms_doc_pasteboard.html");
+
+/* A Google Docs checklist with a checked item, "Done", and an unchecked
+one, "To do". Each item has an image of its checkbox. */
+#[cfg(test)]
+pub const GOOGLE_DOC_CHECKLIST_HTML_PASTEBOARD: &str =
+    include_str!("../../../fixtures/google_doc_checklist_pasteboard.html");
+
+/* Google Docs paragraphs in the Title, Heading 1 and Heading 2 styles, and
+a normal one, "Body text", where only "text" is large. Only Heading 1 is
+written as a heading tag. */
+#[cfg(test)]
+pub const GOOGLE_DOC_HEADINGS_HTML_PASTEBOARD: &str =
+    include_str!("../../../fixtures/google_doc_headings_pasteboard.html");
+
+/* An Outlook email, laid out with nested tables, with a heading, two
+paragraphs around an empty one and a signature. Spacer cells hold only a
+non-breaking space or an image. */
+#[cfg(test)]
+pub const EMAIL_HTML_PASTEBOARD: &str =
+    include_str!("../../../fixtures/email_pasteboard.html");
+
+/* A Rust function copied from VS Code, with a line for each div and a
+span for each token, and an empty line between the comment and the body. */
+#[cfg(test)]
+pub const VS_CODE_HTML_PASTEBOARD: &str =
+    include_str!("../../../fixtures/vs_code_pasteboard.html");
 
 #[cfg(feature = "sys")]
 mod sys {
@@ -89,6 +260,8 @@ mod sys {
     use matrix_mentions::Mention;
 
     use super::super::padom_node::PaDomNode;
+    #[cfg(feature = "source-map")]
+    use super::super::PaDomHandle;
     use super::super::PaNodeContainer;
     use super::super::{PaDom, PaDomCreationError, PaDomCreator};
     use super::*;
@@ -96,18 +269,194 @@ mod sys {
     use crate::dom::nodes::dom_node::DomNodeKind::CodeBlock;
     use crate::dom::nodes::{ContainerNode, DomNode};
     use crate::dom::parser::sys::PaNodeText;
-    use crate::ListType;
+    use crate::dom::parser::{code_editor, google_doc};
+    use crate::{InlineFormatType, ListType};
 
     pub(super) struct HtmlParser {
         current_path: Vec<DomNodeKind>,
+        depth: usize,
+        max_depth: usize,
+        attribute_policy: AttributePolicy,
+        whitespace_policy: WhitespacePolicy,
+        at_room_detection: AtRoomDetection,
+        lenient: bool,
+        check_attributes: bool,
+        issues: Vec<HtmlIssue>,
+        /// The range of the start tag of each node of the [PaDom] being
+        /// converted, if a [SourceMap] is being recorded
+        #[cfg(feature = "source-map")]
+        source_ranges: Option<Vec<Option<std::ops::Range<usize>>>>,
+        /// The position of each element converted so far, along with the
+        /// index of its node in the [PaDom]
+        #[cfg(feature = "source-map")]
+        source_positions: Vec<(usize, SourcePosition)>,
     }
     impl HtmlParser {
         pub(super) fn default() -> Self {
+            Self::with_max_depth(DEFAULT_MAX_PARSE_DEPTH)
+        }
+
+        pub(super) fn with_max_depth(max_depth: usize) -> Self {
             Self {
                 current_path: Vec::new(),
+                depth: 0,
+                max_depth,
+                attribute_policy: AttributePolicy::default(),
+                whitespace_policy: WhitespacePolicy::default(),
+                at_room_detection: AtRoomDetection::default(),
+                lenient: false,
+                check_attributes: false,
+                issues: Vec::new(),
+                #[cfg(feature = "source-map")]
+                source_ranges: None,
+                #[cfg(feature = "source-map")]
+                source_positions: Vec::new(),
+            }
+        }
+
+        pub(super) fn with_attribute_policy(
+            mut self,
+            attribute_policy: AttributePolicy,
+        ) -> Self {
+            self.attribute_policy = attribute_policy;
+            self
+        }
+
+        pub(super) fn with_whitespace_policy(
+            mut self,
+            whitespace_policy: WhitespacePolicy,
+        ) -> Self {
+            self.whitespace_policy = whitespace_policy;
+            self
+        }
+
+        pub(super) fn with_at_room_detection(
+            mut self,
+            at_room_detection: AtRoomDetection,
+        ) -> Self {
+            self.at_room_detection = at_room_detection;
+            self
+        }
+
+        /// Skip unsupported elements in Matrix HTML, keeping their content,
+        /// instead of failing. What was skipped is reported by
+        /// [Self::take_warnings].
+        pub(super) fn with_lenient(mut self, lenient: bool) -> Self {
+            self.lenient = lenient;
+            self
+        }
+
+        /// Also check the attributes of elements in Matrix HTML against
+        /// the Matrix spec, reporting any problems by [Self::take_issues].
+        pub(super) fn with_attribute_checks(
+            mut self,
+            check_attributes: bool,
+        ) -> Self {
+            self.check_attributes = check_attributes;
+            self
+        }
+
+        /// The problems found in Matrix HTML parsed leniently since the last
+        /// call.
+        pub(super) fn take_warnings(&mut self) -> Vec<String> {
+            self.take_issues().iter().map(|i| i.to_string()).collect()
+        }
+
+        /// The problems found in Matrix HTML parsed leniently since the last
+        /// call, including the attributes checked if
+        /// [Self::with_attribute_checks] was set.
+        pub(super) fn take_issues(&mut self) -> Vec<HtmlIssue> {
+            std::mem::take(&mut self.issues)
+        }
+
+        /// Record where each element of the parsed HTML was and what
+        /// became of it, to be collected by [Self::take_source_map].
+        #[cfg(feature = "source-map")]
+        pub(super) fn with_source_map(mut self) -> Self {
+            self.source_ranges = Some(Vec::new());
+            self
+        }
+
+        /// The [SourceMap] of the HTML parsed since the last call.
+        #[cfg(feature = "source-map")]
+        pub(super) fn take_source_map(&mut self) -> SourceMap {
+            let mut positions = std::mem::take(&mut self.source_positions);
+            positions.sort_by_key(|(index, _)| *index);
+            SourceMap {
+                positions: positions.into_iter().map(|(_, p)| p).collect(),
+            }
+        }
+
+        /// Note what became of the element of the [PaDom] at `handle`,
+        /// if it was written in the HTML.
+        #[cfg(feature = "source-map")]
+        fn record_source_position(
+            &mut self,
+            handle: &PaDomHandle,
+            panode: &PaNodeContainer,
+            change: SourceChange,
+        ) {
+            let Some(ranges) = &self.source_ranges else {
+                return;
+            };
+            if let Some(Some(range)) = ranges.get(handle.0) {
+                self.source_positions.push((
+                    handle.0,
+                    SourcePosition {
+                        tag: panode.name.local.to_string(),
+                        range: range.clone(),
+                        change,
+                    },
+                ));
+            }
+        }
+
+        /// Note that the elements inside panode were flattened into text
+        /// because they were nested too deeply.
+        #[cfg(feature = "source-map")]
+        fn record_flattened_source_positions(
+            &mut self,
+            padom: &PaDom,
+            panode: &PaNodeContainer,
+        ) {
+            let mut stack: Vec<_> = panode.children.iter().collect();
+            while let Some(handle) = stack.pop() {
+                if let PaDomNode::Container(container) = padom.get_node(handle)
+                {
+                    self.record_source_position(
+                        handle,
+                        container,
+                        SourceChange::Unwrapped(
+                            "It was nested too deeply, so only its text was kept"
+                                .into(),
+                        ),
+                    );
+                    stack.extend(container.children.iter());
+                }
             }
         }
 
+        fn can_contain_mentions(&self) -> bool {
+            !self.current_path.iter().any(|kind| {
+                matches!(
+                    kind,
+                    DomNodeKind::Formatting(InlineFormatType::InlineCode)
+                        | DomNodeKind::Link
+                )
+            })
+        }
+
+        /// Whether `@room` here may become an @room mention, as allowed by
+        /// the [AtRoomDetection].
+        fn allows_at_room(&self) -> bool {
+            self.at_room_detection.allows_at_room_in(&self.current_path)
+        }
+
+        /// Whether `@room` in text here becomes an @room mention.
+        fn detects_at_room(&self) -> bool {
+            self.allows_at_room() && self.can_contain_mentions()
+        }
+
         pub(super) fn parse<S>(
             &mut self,
             html: &str,
@@ -137,9 +486,26 @@ mod sys {
         where
             S: UnicodeString,
         {
-            let pa_dom = PaDomCreator::parse(html).map_err(|err| {
-                self.padom_creation_error_to_html_parse_error(err)
-            })?;
+            // Pasted HTML, e.g. from emails, is often malformed, so only
+            // Matrix HTML has to parse cleanly
+            let pa_dom = match PaDomCreator::parse(html) {
+                Ok(pa_dom) => pa_dom,
+                Err(err) if html_source != HtmlSource::Matrix => err.dom,
+                Err(err) => {
+                    return Err(
+                        self.padom_creation_error_to_html_parse_error(err)
+                    )
+                }
+            };
+
+            #[cfg(feature = "source-map")]
+            if self.source_ranges.is_some() {
+                let names = pa_dom.nodes.iter().map(|node| match node {
+                    PaDomNode::Container(c) => Some(c.name.local.as_ref()),
+                    _ => None,
+                });
+                self.source_ranges = Some(start_tag_ranges(html, names));
+            }
 
             let dom =
                 self.padom_to_dom(pa_dom, html_source).map_err(|err| {
@@ -147,12 +513,7 @@ mod sys {
                         parse_errors: vec![err.to_string()],
                     }
                 })?;
-            let dom_blocks_done = post_process_blocks(dom);
-            let dom_inline_blocks_done =
-                post_process_for_block_and_inline_siblings(dom_blocks_done);
-            let dom_adjacted_text_done =
-                post_process_for_adjacent_text(dom_inline_blocks_done);
-            Ok(dom_adjacted_text_done)
+            Ok(post_process_dom(dom))
         }
 
         /// Convert a [PaDom] into a [Dom].
@@ -194,6 +555,36 @@ mod sys {
             node: &mut ContainerNode<S>,
             html_source: HtmlSource,
         ) -> Result<(), Error>
+        where
+            S: UnicodeString,
+        {
+            if self.depth >= self.max_depth {
+                #[cfg(feature = "source-map")]
+                self.record_flattened_source_positions(padom, panode);
+                let is_list = node.is_list();
+                append_flattened_text(
+                    &Self::flattened_text(padom, panode),
+                    node,
+                    is_list,
+                    self.current_path.contains(&CodeBlock),
+                    self.allows_at_room(),
+                );
+                return Ok(());
+            }
+            self.depth += 1;
+            let result =
+                self.convert_children_of(padom, panode, node, html_source);
+            self.depth -= 1;
+            result
+        }
+
+        fn convert_children_of<S>(
+            &mut self,
+            padom: &PaDom,
+            panode: &PaNodeContainer,
+            node: &mut ContainerNode<S>,
+            html_source: HtmlSource,
+        ) -> Result<(), Error>
         where
             S: UnicodeString,
         {
@@ -201,12 +592,18 @@ mod sys {
                 let child = padom.get_node(child_handle);
                 match child {
                     PaDomNode::Container(child) => {
-                        self.convert_container(
+                        let _change = self.convert_container(
                             padom,
                             child,
                             node,
                             html_source,
                         )?;
+                        #[cfg(feature = "source-map")]
+                        self.record_source_position(
+                            child_handle,
+                            child,
+                            _change,
+                        );
                     }
                     PaDomNode::Document(_) => {
                         panic!("Found a document inside a document!")
@@ -221,7 +618,9 @@ mod sys {
                             &text.content,
                             node,
                             is_inside_code_block,
+                            self.detects_at_room(),
                             is_only_child_in_parent,
+                            self.whitespace_policy,
                         );
                     }
                 }
@@ -229,14 +628,15 @@ mod sys {
             Ok(())
         }
 
-        /// Copy all panode's information into node (now we know it's a container).
+        /// Copy all panode's information into node (now we know it's a
+        /// container), returning what became of it.
         fn convert_container<S>(
             &mut self,
             padom: &PaDom,
             child: &PaNodeContainer,
-            node_in: &mut ContainerNode<S>,
+            node: &mut ContainerNode<S>,
             html_source: HtmlSource,
-        ) -> Result<(), Error>
+        ) -> Result<SourceChange, Error>
         where
             S: UnicodeString,
         {
@@ -244,7 +644,7 @@ mod sys {
             let tag = child.name.local.as_ref();
             let mut invalid_node_error: Option<Error> = None;
             let mut skip_children: bool = false;
-            let mut node = node_in.clone();
+            let mut change = SourceChange::Kept;
             if node.is_list()
                 && tag != "li"
                 && html_source != HtmlSource::GoogleDoc
@@ -256,66 +656,118 @@ mod sys {
 
             if invalid_node_error.is_none() {
                 match tag {
-                    "b" | "code" | "del" | "em" | "i" | "strong" | "u" => {
-                        let formatting_node = Self::new_formatting(tag);
+                    "b" | "code" | "del" | "em" | "i" | "s" | "strike"
+                    | "strong" | "u" => {
+                        // Legacy strikethrough tags are written as del
+                        let formatting_node = Self::new_formatting(match tag {
+                            "s" | "strike" => {
+                                change = SourceChange::Transformed(
+                                    "Strikethrough is written as <del>".into(),
+                                );
+                                "del"
+                            }
+                            tag => tag,
+                        });
                         if tag == "code"
                             && self.current_path.contains(&CodeBlock)
                         {
+                            // The language of a code block is on its code tag
+                            if matches!(
+                                node.kind(),
+                                ContainerNodeKind::CodeBlock
+                            ) {
+                                node.add_attributes(
+                                    self.kept_attributes(child),
+                                );
+                            }
                             self.convert_children(
                                 padom,
                                 child,
-                                Some(&mut node),
+                                Some(node),
                                 html_source,
                             )?;
                         } else {
                             self.current_path.push(formatting_node.kind());
-                            node.append_child(formatting_node);
+                            node.append_child(
+                                self.keep_attributes(child, formatting_node),
+                            );
                             self.convert_children(
                                 padom,
                                 child,
-                                last_container_mut_in(&mut node),
+                                last_container_mut_in(node),
                                 html_source,
                             )?;
                             self.current_path.remove(cur_path_idx);
                         }
                     }
                     "span" => 'span: {
+                        let attributes = self.kept_attributes(child);
                         if html_source == HtmlSource::Matrix {
-                            invalid_node_error =
-                                Some(Error::UnknownNode(tag.to_string()));
+                            if attributes.is_empty() {
+                                invalid_node_error =
+                                    Some(Error::UnknownNode(tag.to_string()));
+                            } else {
+                                self.append_span(
+                                    padom,
+                                    child,
+                                    node,
+                                    attributes,
+                                    html_source,
+                                )?;
+                            }
                             break 'span;
                         }
 
                         // For external sources, we check for common formatting styles for spans
                         // and convert them to appropriate formatting nodes.
-                        let mut formatting_tag = None;
-                        if child.contains_style("font-weight", "bold")
+                        let formatting_tag = if self
+                            .current_path
+                            .contains(&CodeBlock)
+                        {
+                            // Syntax highlighting in code isn't formatting
+                            None
+                        } else if child.contains_style("font-weight", "bold")
                             || child.contains_style("font-weight", "700")
                         {
-                            formatting_tag = Some("b");
+                            Some("b")
                         } else if child.contains_style("font-style", "italic") {
-                            formatting_tag = Some("i");
+                            Some("i")
                         } else if child
                             .contains_style("text-decoration", "underline")
                         {
-                            formatting_tag = Some("u");
+                            Some("u")
                         } else if child
                             .contains_style("text-decoration", "line-through")
                         {
-                            formatting_tag = Some("del");
-                        }
+                            Some("del")
+                        } else {
+                            None
+                        };
 
                         if let Some(tag) = formatting_tag {
+                            change = SourceChange::Transformed(format!(
+                                "Its style is written as <{tag}>"
+                            ));
                             let formatting_node = Self::new_formatting(tag);
                             self.current_path.push(formatting_node.kind());
-                            node.append_child(formatting_node);
+                            node.append_child(
+                                self.keep_attributes(child, formatting_node),
+                            );
                             self.convert_children(
                                 padom,
                                 child,
-                                last_container_mut_in(&mut node),
+                                last_container_mut_in(node),
                                 html_source,
                             )?;
                             self.current_path.remove(cur_path_idx);
+                        } else if !attributes.is_empty() {
+                            self.append_span(
+                                padom,
+                                child,
+                                node,
+                                attributes,
+                                html_source,
+                            )?;
                         } else {
                             // If no formatting tag was found, just skip and convert the children
                             invalid_node_error =
@@ -346,19 +798,22 @@ mod sys {
                                 .as_container_mut()
                                 .unwrap()
                         } else {
-                            &mut node
+                            &mut *node
                         };
                         self.current_path.push(DomNodeKind::List);
                         if tag == "ol" {
                             let custom_start = child
                                 .get_attr("start")
                                 .and_then(|start| start.parse::<usize>().ok());
-                            target_node.append_child(Self::new_ordered_list(
-                                custom_start,
+                            target_node.append_child(self.keep_attributes(
+                                child,
+                                Self::new_ordered_list(custom_start),
                             ));
                         } else {
-                            target_node
-                                .append_child(Self::new_unordered_list());
+                            target_node.append_child(self.keep_attributes(
+                                child,
+                                Self::new_unordered_list(),
+                            ));
                         }
                         self.convert_children(
                             padom,
@@ -374,14 +829,33 @@ mod sys {
                             break 'li;
                         }
                         self.current_path.push(DomNodeKind::ListItem);
-                        node.append_child(Self::new_list_item());
+                        node.append_child(
+                            self.keep_attributes(child, Self::new_list_item()),
+                        );
                         self.convert_children(
                             padom,
                             child,
-                            last_container_mut_in(&mut node),
+                            last_container_mut_in(node),
                             html_source,
                         )?;
                         self.current_path.remove(cur_path_idx);
+                        let marker = google_doc::checkbox_marker(
+                            child.get_attr("role"),
+                            child.get_attr("aria-checked"),
+                        );
+                        if let Some(marker) = marker
+                            .filter(|_| html_source == HtmlSource::GoogleDoc)
+                        {
+                            change = SourceChange::Transformed(
+                                "Checklist items start with a ballot box"
+                                    .into(),
+                            );
+                            google_doc::prepend_marker(
+                                last_container_mut_in(node)
+                                    .expect("List item was just added"),
+                                marker,
+                            );
+                        }
                     }
                     "a" => {
                         let is_mention = child.attrs.iter().any(|(k, v)| {
@@ -396,10 +870,31 @@ mod sys {
                             _ => None,
                         };
 
+                        // The composer writes @room mentions as links to `#`
+                        let is_at_room = self.allows_at_room()
+                            && (child.get_attr("data-mention-type")
+                                == Some("at-room")
+                                || (child.get_attr("href") == Some("#")
+                                    && text.is_some_and(|t| {
+                                        t.content == "@room"
+                                    })));
+
                         match (is_mention, text) {
+                            _ if is_at_room => {
+                                self.current_path.push(DomNodeKind::Mention);
+                                node.append_child(DomNode::Mention(
+                                    DomNode::new_at_room_mention(
+                                        self.mention_attributes(child),
+                                    ),
+                                ));
+                            }
                             (true, Some(text)) => {
                                 self.current_path.push(DomNodeKind::Mention);
-                                let mention = Self::new_mention(child, text);
+                                let mention = Self::new_mention(
+                                    child,
+                                    text,
+                                    self.mention_attributes(child),
+                                );
                                 node.append_child(mention);
                             }
                             _ => {
@@ -409,7 +904,7 @@ mod sys {
                                 self.convert_children(
                                     padom,
                                     child,
-                                    last_container_mut_in(&mut node),
+                                    last_container_mut_in(node),
                                     html_source,
                                 )?;
                             }
@@ -418,39 +913,235 @@ mod sys {
                     }
                     "pre" => {
                         self.current_path.push(DomNodeKind::CodeBlock);
-                        node.append_child(Self::new_code_block());
+                        node.append_child(
+                            self.keep_attributes(child, Self::new_code_block()),
+                        );
                         self.convert_children(
                             padom,
                             child,
-                            last_container_mut_in(&mut node),
+                            last_container_mut_in(node),
                             html_source,
                         )?;
                         self.current_path.remove(cur_path_idx);
                     }
                     "blockquote" => {
                         self.current_path.push(DomNodeKind::Quote);
-                        node.append_child(Self::new_quote());
+                        node.append_child(
+                            self.keep_attributes(child, Self::new_quote()),
+                        );
                         self.convert_children(
                             padom,
                             child,
-                            last_container_mut_in(&mut node),
+                            last_container_mut_in(node),
                             html_source,
                         )?;
 
                         self.current_path.remove(cur_path_idx);
                     }
+                    "font" => {
+                        // Legacy colours are written the way the Matrix
+                        // spec describes them now
+                        let color = child
+                            .get_attr("data-mx-color")
+                            .or(child.get_attr("color"));
+                        if let Some(color) = color {
+                            change = SourceChange::Transformed(
+                                "Its colour is written as a span".into(),
+                            );
+                            let mut attributes =
+                                vec![("data-mx-color".into(), color.into())];
+                            attributes.extend(
+                                self.kept_attributes(child).into_iter().filter(
+                                    |(name, _)| {
+                                        *name != S::from("data-mx-color")
+                                    },
+                                ),
+                            );
+                            self.append_span(
+                                padom,
+                                child,
+                                node,
+                                attributes,
+                                html_source,
+                            )?;
+                        } else {
+                            change = SourceChange::Unwrapped(
+                                "It has no colour".into(),
+                            );
+                            self.convert_children(
+                                padom,
+                                child,
+                                Some(node),
+                                html_source,
+                            )?;
+                        }
+                    }
+                    "div"
+                        if html_source != HtmlSource::Matrix
+                            && !self.current_path.contains(&CodeBlock)
+                            && code_editor::is_code_editor_block(
+                                child.get_attr("style"),
+                            ) =>
+                    {
+                        change = SourceChange::Transformed(
+                            "Code from an editor becomes a code block".into(),
+                        );
+                        self.current_path.push(DomNodeKind::CodeBlock);
+                        let mut code_block = Self::new_code_block();
+                        let language = code_editor::language_class(|name| {
+                            child.get_attr(name).map(str::to_owned)
+                        })
+                        .and_then(|class| {
+                            self.attribute_policy
+                                .kept_value("code", "class", &class)
+                        });
+                        if let (Some(language), DomNode::Container(c)) =
+                            (language, &mut code_block)
+                        {
+                            c.add_attributes(vec![(
+                                "class".into(),
+                                language.into(),
+                            )]);
+                        }
+                        node.append_child(code_block);
+                        self.convert_children(
+                            padom,
+                            child,
+                            last_container_mut_in(node),
+                            html_source,
+                        )?;
+                        self.current_path.remove(cur_path_idx);
+                    }
+                    "head" | "script" | "style" | "title" | "xml"
+                        if html_source != HtmlSource::Matrix =>
+                    {
+                        change = SourceChange::Dropped(
+                            "It isn't part of the content".into(),
+                        );
+                    }
+                    "table" | "thead" | "tbody" | "tfoot" | "tr"
+                        if html_source != HtmlSource::Matrix =>
+                    {
+                        // Emails lay out their content with tables, which
+                        // can't be composed, so only their cells are kept
+                        change = SourceChange::Unwrapped(
+                            "Only the content of its cells can be kept".into(),
+                        );
+                        self.convert_children(
+                            padom,
+                            child,
+                            Some(node),
+                            html_source,
+                        )?;
+                    }
+                    "td" | "th" | "caption"
+                        if html_source != HtmlSource::Matrix
+                            && Self::flattened_text(padom, child)
+                                .trim()
+                                .is_empty() =>
+                    {
+                        // Cells which only space out a layout
+                        change = SourceChange::Dropped("It has no text".into());
+                    }
+                    "div" | "center" | "td" | "th" | "caption"
+                        if matches!(tag, "div" | "center")
+                            || html_source != HtmlSource::Matrix =>
+                    {
+                        if Self::has_block_children(padom, child) {
+                            // Only the blocks inside can be kept
+                            change = SourceChange::Unwrapped(
+                                "Only the blocks inside it can be kept".into(),
+                            );
+                            self.convert_children(
+                                padom,
+                                child,
+                                Some(node),
+                                html_source,
+                            )?;
+                        } else {
+                            change = SourceChange::Transformed(
+                                "It became a paragraph".into(),
+                            );
+                            self.current_path.push(DomNodeKind::Paragraph);
+                            let mut paragraph =
+                                ContainerNode::new_paragraph(Vec::new());
+                            if tag == "center" {
+                                paragraph.add_attributes(vec![(
+                                    "align".into(),
+                                    "center".into(),
+                                )]);
+                            }
+                            node.append_child(self.keep_attributes(
+                                child,
+                                DomNode::Container(paragraph),
+                            ));
+                            // A line of code holding only a line break is
+                            // empty, the break only keeps it open
+                            let is_empty_code_line = self
+                                .current_path
+                                .contains(&CodeBlock)
+                                && Self::holds_only_line_break(padom, child);
+                            if !is_empty_code_line {
+                                self.convert_children(
+                                    padom,
+                                    child,
+                                    last_container_mut_in(node),
+                                    html_source,
+                                )?;
+                            }
+                            self.current_path.remove(cur_path_idx);
+                        }
+                    }
                     "html" => {
                         // Skip the html tag - add its children to the
-                        // current node directly.
-                        self.convert(padom, child, &mut node, html_source)?;
+                        // current node directly, without counting it
+                        // towards the nesting depth.
+                        change = SourceChange::Unwrapped(
+                            "Only its content can be kept".into(),
+                        );
+                        self.convert_children_of(
+                            padom,
+                            child,
+                            node,
+                            html_source,
+                        )?;
+                    }
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                        change = SourceChange::Transformed(
+                            "Headings become paragraphs of bold text".into(),
+                        );
+                        self.append_heading(padom, child, node, html_source)?;
+                    }
+                    "hr" => {
+                        // Rules can't be composed, so they are kept as an
+                        // empty paragraph separating what is around them
+                        change = SourceChange::Transformed(
+                            "Rules become empty paragraphs".into(),
+                        );
+                        node.append_child(Self::new_paragraph());
+                    }
+                    "p" if html_source == HtmlSource::GoogleDoc
+                        && google_doc::is_heading_paragraph(
+                            child.get_attr("role"),
+                            &Self::run_font_sizes(padom, child),
+                        ) =>
+                    {
+                        // Docs styles titles and some headings with large
+                        // text rather than heading tags
+                        change = SourceChange::Transformed(
+                            "Headings become paragraphs of bold text".into(),
+                        );
+                        self.append_heading(padom, child, node, html_source)?;
                     }
                     "p" => {
                         self.current_path.push(DomNodeKind::Paragraph);
-                        node.append_child(Self::new_paragraph());
+                        node.append_child(
+                            self.keep_attributes(child, Self::new_paragraph()),
+                        );
                         self.convert_children(
                             padom,
                             child,
-                            last_container_mut_in(&mut node),
+                            last_container_mut_in(node),
                             html_source,
                         )?;
                         self.current_path.remove(cur_path_idx);
@@ -462,16 +1153,118 @@ mod sys {
                 };
             }
 
+            if self.check_attributes
+                && html_source == HtmlSource::Matrix
+                && !matches!(invalid_node_error, Some(Error::UnknownNode(_)))
+            {
+                self.issues.extend(attribute_issues(
+                    tag,
+                    child.attrs.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+                ));
+            }
+
             if let Some(err) = invalid_node_error {
-                if html_source == HtmlSource::Matrix {
+                if html_source == HtmlSource::Matrix && !self.lenient {
                     return Err(err);
-                } else if !skip_children {
-                    // If the source is not Matrix and we haven't explicitly flagged to skip the children continue to parse them.
-                    self.convert(padom, child, &mut node, html_source)?;
+                }
+                if html_source == HtmlSource::Matrix {
+                    self.issues.push(err.to_issue(tag));
+                }
+                if !skip_children {
+                    // Unless we have explicitly flagged to skip the children, continue to parse them.
+                    self.convert(padom, child, node, html_source)?;
+                    change = SourceChange::Unwrapped(err.to_string());
+                } else {
+                    change = SourceChange::Dropped(err.to_string());
                 }
             }
-            *node_in = node;
-            Ok(())
+            Ok(change)
+        }
+
+        /// Add a paragraph of bold text to node, and convert panode's
+        /// children into it, as headings can't be composed.
+        fn append_heading<S>(
+            &mut self,
+            padom: &PaDom,
+            panode: &PaNodeContainer,
+            node: &mut ContainerNode<S>,
+            html_source: HtmlSource,
+        ) -> Result<(), Error>
+        where
+            S: UnicodeString,
+        {
+            let cur_path_idx = self.current_path.len();
+            self.current_path.push(DomNodeKind::Paragraph);
+            node.append_child(
+                self.keep_attributes(panode, Self::new_paragraph()),
+            );
+            let paragraph =
+                last_container_mut_in(node).expect("Paragraph was just added");
+            let bold = Self::new_formatting("strong");
+            self.current_path.push(bold.kind());
+            paragraph.append_child(bold);
+            let result = self.convert_children(
+                padom,
+                panode,
+                last_container_mut_in(paragraph),
+                html_source,
+            );
+            self.current_path.truncate(cur_path_idx);
+            result
+        }
+
+        /// The font sizes of the runs of text directly in panode, as Docs
+        /// writes them in spans. Other text has no size.
+        fn run_font_sizes(
+            padom: &PaDom,
+            panode: &PaNodeContainer,
+        ) -> Vec<Option<f32>> {
+            panode
+                .children
+                .iter()
+                .filter_map(|handle| match padom.get_node(handle) {
+                    PaDomNode::Container(c)
+                        if c.name.local.as_ref() == "span" =>
+                    {
+                        Some(
+                            c.get_attr("style")
+                                .and_then(google_doc::font_size_pt),
+                        )
+                    }
+                    PaDomNode::Text(t) if !t.content.trim().is_empty() => {
+                        Some(None)
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+
+        /// Add a span with the given attributes to node, and convert
+        /// panode's children into it.
+        fn append_span<S>(
+            &mut self,
+            padom: &PaDom,
+            panode: &PaNodeContainer,
+            node: &mut ContainerNode<S>,
+            attributes: Vec<(S, S)>,
+            html_source: HtmlSource,
+        ) -> Result<(), Error>
+        where
+            S: UnicodeString,
+        {
+            self.current_path.push(DomNodeKind::Span);
+            node.append_child(DomNode::Container(ContainerNode::new_span(
+                Vec::new(),
+                Some(attributes),
+            )));
+            let result = self.convert_children(
+                padom,
+                panode,
+                last_container_mut_in(node),
+                html_source,
+            );
+            self.current_path.pop();
+            result
         }
 
         /// Recurse into panode's children and convert them too
@@ -493,6 +1286,105 @@ mod sys {
             Ok(())
         }
 
+        /// True if panode has nothing in it but a `<br>`
+        fn holds_only_line_break(
+            padom: &PaDom,
+            panode: &PaNodeContainer,
+        ) -> bool {
+            matches!(
+                panode.children.as_slice(),
+                [handle] if matches!(
+                    padom.get_node(handle),
+                    PaDomNode::Container(child)
+                        if child.name.local.as_ref() == "br"
+                )
+            )
+        }
+
+        /// True if any of panode's children will become block nodes
+        fn has_block_children(padom: &PaDom, panode: &PaNodeContainer) -> bool {
+            panode.children.iter().any(|handle| {
+                matches!(
+                    padom.get_node(handle),
+                    PaDomNode::Container(child)
+                        if BLOCK_TAGS.contains(&child.name.local.as_ref())
+                )
+            })
+        }
+
+        /// Collect the text of all the descendants of panode, without
+        /// recursing, for content nested beyond the maximum depth.
+        fn flattened_text(padom: &PaDom, panode: &PaNodeContainer) -> String {
+            let mut text = String::new();
+            let mut stack: Vec<_> = panode.children.iter().rev().collect();
+            while let Some(handle) = stack.pop() {
+                match padom.get_node(handle) {
+                    PaDomNode::Container(container) => {
+                        stack.extend(container.children.iter().rev())
+                    }
+                    PaDomNode::Text(t) => text.push_str(&t.content),
+                    PaDomNode::Document(_) => {
+                        panic!("Found a document inside a document!")
+                    }
+                }
+            }
+            text
+        }
+
+        /// Copy the attributes of panode which the attribute policy keeps
+        /// onto the node created for it.
+        fn keep_attributes<S>(
+            &self,
+            panode: &PaNodeContainer,
+            mut node: DomNode<S>,
+        ) -> DomNode<S>
+        where
+            S: UnicodeString,
+        {
+            if let DomNode::Container(container) = &mut node {
+                container.add_attributes(self.kept_attributes(panode));
+            }
+            node
+        }
+
+        /// The attributes of panode which the attribute policy keeps
+        fn kept_attributes<S>(&self, panode: &PaNodeContainer) -> Vec<(S, S)>
+        where
+            S: UnicodeString,
+        {
+            let tag = panode.name.local.as_ref();
+            panode
+                .attrs
+                .iter()
+                .filter_map(|(k, v)| {
+                    self.attribute_policy
+                        .kept_value(tag, k, v)
+                        .map(|v| (k.as_str().into(), v.into()))
+                })
+                .collect()
+        }
+
+        /// The attributes kept on a mention: its `style`, as web clients
+        /// set on mentions they insert, and those the attribute policy
+        /// keeps, apart from the `data-mention-type` written for every
+        /// mention.
+        fn mention_attributes<S>(&self, link: &PaNodeContainer) -> Vec<(S, S)>
+        where
+            S: UnicodeString,
+        {
+            let style = link
+                .get_attr("style")
+                .map(|style| ("style".into(), style.into()));
+            style
+                .into_iter()
+                .chain(
+                    self.kept_attributes(link).into_iter().filter(
+                        |(name, _)| *name != S::from("data-mention-type"),
+                    ),
+                )
+                .collect()
+        }
+
         /// Create a formatting node
         fn new_formatting<S>(tag: &str) -> DomNode<S>
         where
@@ -533,6 +1425,7 @@ mod sys {
         fn new_mention<S>(
             link: &PaNodeContainer,
             text: &PaNodeText,
+            attributes: Vec<(S, S)>,
         ) -> DomNode<S>
         where
             S: UnicodeString,
@@ -543,8 +1436,7 @@ mod sys {
             let creation_result = DomNode::new_mention(
                 link.get_attr("href").unwrap_or("").into(),
                 text.as_str().into(),
-                // custom attributes are not required when cfg feature != "js"
-                vec![],
+                attributes,
             );
 
             match creation_result {
@@ -653,6 +1545,23 @@ mod sys {
         }
     }
 
+    impl Error {
+        /// The [HtmlIssue] reported for this error at an element with the
+        /// given tag.
+        fn to_issue(&self, tag: &str) -> HtmlIssue {
+            match self {
+                Self::NoBody => HtmlIssue::Malformed(self.to_string()),
+                Self::UnknownNode(tag) => HtmlIssue::UnknownTag {
+                    tag: tag.to_lowercase(),
+                },
+                Self::InvalidListItemNode => HtmlIssue::NotAListItem {
+                    tag: tag.to_lowercase(),
+                },
+                Self::ParentNotAList => HtmlIssue::ListItemOutsideList,
+            }
+        }
+    }
+
     #[cfg(test)]
     mod test {
         use crate::dom::parser::parse::sys::HtmlParser;
@@ -662,8 +1571,9 @@ mod sys {
         use widestring::Utf16String;
 
         use super::*;
+        use crate::dom::parser::conformance;
         use crate::tests::testutils_composer_model::restore_whitespace;
-        use crate::{ToHtml, ToMarkdown, ToTree};
+        use crate::{ToHtml, ToMarkdown, ToRawText, ToTree};
 
         trait Roundtrips<T> {
             fn roundtrips(&self);
@@ -1126,6 +2036,172 @@ mod sys {
             );
         }
 
+        #[test]
+        fn parse_lenient_keeps_content_of_unknown_elements() {
+            let mut parser = HtmlParser::default().with_lenient(true);
+            let dom: Dom<Utf16String> = parser
+                .parse(
+                    "<p>a <sup>b</sup></p><table><tr><td>c</td></tr></table>",
+                )
+                .unwrap();
+            assert_eq!(dom.to_html(), "<p>a b</p><p>c</p>");
+            assert_eq!(
+                parser.take_warnings(),
+                vec![
+                    "Node `sup` is not supported",
+                    "Node `table` is not supported",
+                    "Node `tbody` is not supported",
+                    "Node `tr` is not supported",
+                    "Node `td` is not supported",
+                ]
+            );
+            assert!(parser.take_warnings().is_empty());
+        }
+
+        #[test]
+        fn parse_fails_on_unknown_elements_unless_lenient() {
+            let result: Result<Dom<Utf16String>, _> =
+                HtmlParser::default().parse("<p><sup>b</sup></p>");
+            assert!(result.is_err());
+        }
+
+        #[cfg(feature = "source-map")]
+        #[test]
+        fn parse_with_source_map_records_what_became_of_elements() {
+            let html = "<h1>T</h1><p>a <s>b</s><b>c</b></p><div><p>d</p></div>";
+            let (dom, map) = parse_with_source_map::<Utf16String>(
+                html,
+                HtmlSource::UnknownExternal,
+            )
+            .unwrap();
+            assert_eq!(
+                dom.to_html(),
+                "<p><strong>T</strong></p><p>a <del>b</del><b>c</b></p><p>d</p>"
+            );
+            let positions: Vec<_> = map
+                .positions()
+                .iter()
+                .map(|p| (p.tag.as_str(), &html[p.range.clone()]))
+                .collect();
+            assert_eq!(
+                positions,
+                vec![
+                    ("h1", "<h1>"),
+                    ("p", "<p>"),
+                    ("s", "<s>"),
+                    ("b", "<b>"),
+                    ("div", "<div>"),
+                    ("p", "<p>"),
+                ]
+            );
+            let changes: Vec<_> = map
+                .changes()
+                .map(|p| (p.range.start, p.change.clone()))
+                .collect();
+            assert_eq!(
+                changes,
+                vec![
+                    (
+                        0,
+                        SourceChange::Transformed(
+                            "Headings become paragraphs of bold text".into()
+                        )
+                    ),
+                    (
+                        15,
+                        SourceChange::Transformed(
+                            "Strikethrough is written as <del>".into()
+                        )
+                    ),
+                    (
+                        35,
+                        SourceChange::Unwrapped(
+                            "Only the blocks inside it can be kept".into()
+                        )
+                    ),
+                ]
+            );
+        }
+
+        #[cfg(feature = "source-map")]
+        #[test]
+        fn parse_with_source_map_records_dropped_elements() {
+            let html = "<ul><li>a</li><p>b</p></ul><sup>c</sup>";
+            let (dom, map) = parse_with_source_map::<Utf16String>(
+                html,
+                HtmlSource::UnknownExternal,
+            )
+            .unwrap();
+            assert_eq!(dom.to_html(), "<ul><li>a</li></ul><p>c</p>");
+            let changes: Vec<_> = map
+                .changes()
+                .map(|p| (p.tag.as_str(), p.change.clone()))
+                .collect();
+            assert_eq!(
+                changes,
+                vec![
+                    (
+                        "p",
+                        SourceChange::Dropped(
+                            "Invalid list item node: a list must only contain list items"
+                                .into()
+                        )
+                    ),
+                    (
+                        "sup",
+                        SourceChange::Unwrapped(
+                            "Node `sup` is not supported".into()
+                        )
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn parse_headings_as_bold_paragraphs() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    "<h1>Title</h1><h3 id=\"x\">Sub <em>title</em></h3><p>a</p>",
+                    HtmlSource::Matrix,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_html(),
+                "<p><strong>Title</strong></p>\
+                <p><strong>Sub <em>title</em></strong></p><p>a</p>"
+            );
+        }
+
+        #[test]
+        fn parse_rule_as_empty_paragraph() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source("<p>a</p><hr><p>b</p>", HtmlSource::Matrix)
+                .unwrap();
+            assert_eq!(dom.to_html(), "<p>a</p><p>\u{a0}</p><p>b</p>");
+        }
+
+        #[test]
+        fn parse_headings_inside_divs_as_blocks() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    "<div><h2>a</h2><p>b</p></div>",
+                    HtmlSource::Matrix,
+                )
+                .unwrap();
+            assert_eq!(dom.to_html(), "<p><strong>a</strong></p><p>b</p>");
+        }
+
+        #[test]
+        fn parse_keeps_at_room_as_text_without_detection() {
+            let html = "<p>@room hello!</p>";
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .with_at_room_detection(AtRoomDetection::Off)
+                .parse(html)
+                .unwrap();
+            assert_eq!(dom.to_html(), html);
+            assert_eq!(dom.iter_mentions().count(), 0);
+        }
+
         #[test]
         fn parse_at_room_mentions() {
             let html = "\
@@ -1153,6 +2229,48 @@ mod sys {
             );
         }
 
+        #[test]
+        fn parser_conforms() {
+            conformance::check_fixtures(|html, source| {
+                HtmlParser::default()
+                    .parse_from_source(html, source)
+                    .unwrap()
+            });
+        }
+
+        #[test]
+        fn at_room_detection_conforms() {
+            conformance::check_at_room_detection(|html, detection| {
+                HtmlParser::default()
+                    .with_at_room_detection(detection)
+                    .parse(html)
+                    .unwrap()
+            });
+        }
+
+        #[test]
+        fn parse_at_room_mentions_written_by_the_composer() {
+            let html = "\
+                <p><a data-mention-type=\"at-room\" href=\"#\" contenteditable=\"false\">@room</a> \
+                <a href=\"https://element.io\">@room</a></p>";
+            let dom: Dom<Utf16String> =
+                HtmlParser::default().parse(html).unwrap();
+            assert_eq!(dom.to_html(), html);
+            let tree = dom.to_tree().to_string();
+            assert_eq!(
+                tree,
+                indoc! {
+                r#"
+                
+                └>p
+                  ├>mention "@room"
+                  ├>" "
+                  └>a "https://element.io"
+                    └>"@room"
+                "#}
+            );
+        }
+
         #[test]
         fn parse_mentions() {
             let html = r#"<p><a href="https://matrix.to/#/@test:example.org">test</a> hello!</p>"#;
@@ -1257,6 +2375,161 @@ mod sys {
             );
         }
 
+        #[test]
+        fn parse_google_doc_checklist_as_list_items_with_ballot_boxes() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    GOOGLE_DOC_CHECKLIST_HTML_PASTEBOARD,
+                    HtmlSource::GoogleDoc,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<ul><li><p>☑ <del>Done</del></p></li>\
+                <li><p>☐ To <b>do</b></p></li></ul>"
+            );
+
+            // Only Docs is trusted to mean a checklist by its roles
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    GOOGLE_DOC_CHECKLIST_HTML_PASTEBOARD,
+                    HtmlSource::UnknownExternal,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<ul><li><p><del>Done</del></p></li>\
+                <li><p>To <b>do</b></p></li></ul>"
+            );
+        }
+
+        #[test]
+        fn parse_google_doc_headings_as_bold_paragraphs() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    GOOGLE_DOC_HEADINGS_HTML_PASTEBOARD,
+                    HtmlSource::GoogleDoc,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<p><strong>Title</strong></p>\
+                <p><strong>Heading</strong></p>\
+                <p><strong>Subheading</strong></p>\
+                <p>Body text</p>"
+            );
+        }
+
+        #[test]
+        fn parse_code_from_an_editor_as_a_code_block() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    VS_CODE_HTML_PASTEBOARD,
+                    HtmlSource::UnknownExternal,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<pre><code>fn main() {\n    // Say hello\n\n    \
+                println!(\"&lt;hello&gt;\");\n}</code></pre>"
+            );
+        }
+
+        #[test]
+        fn parse_highlighted_code_without_formatting() {
+            // As JetBrains IDEs copy it
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    "<pre style=\"font-family:'JetBrains Mono',monospace;\">\
+                    <span style=\"color:#cc7832;font-weight:bold;\">fn \
+                    </span><span style=\"font-style:italic;\">main</span>\
+                    </pre>",
+                    HtmlSource::UnknownExternal,
+                )
+                .unwrap();
+            assert_eq!(dom.to_string(), "<pre><code>fn main</code></pre>");
+        }
+
+        #[test]
+        fn parse_the_language_of_code_from_an_editor_if_it_is_allowed() {
+            let html = "<div data-language=\"rust\" \
+                style=\"font-family: Menlo; white-space: pre\">\
+                <div>fn</div></div>";
+            let parse = |allowed_classes: Vec<String>| {
+                HtmlParser::default()
+                    .with_attribute_policy(AttributePolicy {
+                        allowed_classes,
+                        ..Default::default()
+                    })
+                    .parse_from_source::<Utf16String>(
+                        html,
+                        HtmlSource::UnknownExternal,
+                    )
+                    .unwrap()
+                    .to_string()
+            };
+            assert_eq!(
+                parse(vec!["language-*".into()]),
+                "<pre><code class=\"language-rust\">fn</code></pre>"
+            );
+            assert_eq!(parse(Vec::new()), "<pre><code>fn</code></pre>");
+        }
+
+        #[test]
+        fn parse_divs_from_matrix_as_paragraphs_whatever_their_style() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    "<div style=\"font-family: monospace; white-space: pre\">\
+                    <div>a</div></div>",
+                    HtmlSource::Matrix,
+                )
+                .unwrap();
+            assert_eq!(dom.to_string(), "<p>a</p>");
+        }
+
+        #[test]
+        fn parse_email_layout_tables_as_paragraphs() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(
+                    EMAIL_HTML_PASTEBOARD,
+                    HtmlSource::UnknownExternal,
+                )
+                .unwrap();
+            // The head and the spacer cells are dropped, and empty
+            // paragraphs are written with a placeholder
+            assert_eq!(
+                dom.to_string(),
+                "<p><b>Monthly update</b></p>\
+                <p>Hello all,</p>\
+                <p>\u{a0}</p>\
+                <p>Read the <a href=\"https://matrix.org/blog\">blog</a>.</p>\
+                <p>Sent from <i>Outlook</i></p>\
+                <p>\u{a0}</p>"
+            );
+        }
+
+        #[test]
+        fn parse_malformed_html_only_from_external_sources() {
+            let html = "<p>a</b></p>";
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(html, HtmlSource::UnknownExternal)
+                .unwrap();
+            assert_eq!(dom.to_string(), "<p>a</p>");
+            assert!(HtmlParser::default()
+                .parse_from_source::<Utf16String>(html, HtmlSource::Matrix)
+                .is_err());
+        }
+
+        #[test]
+        fn parse_tables_from_matrix_as_unknown() {
+            assert!(HtmlParser::default()
+                .parse_from_source::<Utf16String>(
+                    "<table><tr><td>a</td></tr></table>",
+                    HtmlSource::Matrix,
+                )
+                .is_err());
+        }
+
         #[test]
         fn parse_ms_doc_rich_text() {
             let dom: Dom<Utf16String> = HtmlParser::default()
@@ -1304,47 +2577,365 @@ mod sys {
                 }
             );
         }
-    }
-}
 
-fn post_process_for_adjacent_text<S: UnicodeString>(mut dom: Dom<S>) -> Dom<S> {
-    let text_handles = find_text_nodes(&dom);
-    for handle in text_handles.iter().rev() {
-        dom = post_process_adjacent_text(dom, handle);
-    }
-    dom
-}
+        #[test]
+        fn parse_joins_ordered_lists_which_carry_on_numbering() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse(
+                    "<ol start=\"3\"><li>a</li></ol>\
+                    <ol start=\"4\"><li>b</li><li>c</li></ol>\
+                    <ol start=\"6\"><li>d</li></ol>",
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<ol start=\"3\"><li>a</li><li>b</li><li>c</li><li>d</li></ol>"
+            );
+        }
 
-fn find_text_nodes<S: UnicodeString>(dom: &Dom<S>) -> Vec<DomHandle> {
-    dom.iter_text().map(|n| n.handle()).collect::<Vec<_>>()
-}
+        #[test]
+        fn parse_keeps_ordered_lists_which_restart_numbering_apart() {
+            let html = "<ol start=\"3\"><li>a</li></ol>\
+                <ol start=\"1\"><li>b</li></ol><ol><li>c</li></ol>";
+            let dom: Dom<Utf16String> =
+                HtmlParser::default().parse(html).unwrap();
+            assert_eq!(dom.to_string(), html);
+        }
 
-fn post_process_adjacent_text<S: UnicodeString>(
-    mut dom: Dom<S>,
-    handle: &DomHandle,
-) -> Dom<S> {
-    dom.merge_text_nodes_around(handle);
-    dom
-}
+        #[test]
+        fn parse_flattens_content_nested_beyond_max_depth() {
+            let html = "<b>a<em>b<u>c<del>d</del></u></em></b>";
+            let dom: Dom<Utf16String> = HtmlParser::with_max_depth(2)
+                .parse_from_source(html, HtmlSource::Matrix)
+                .unwrap();
+            assert_eq!(dom.to_html(), "<b>a<em>bcd</em></b>");
+        }
 
-fn post_process_for_block_and_inline_siblings<S: UnicodeString>(
-    mut dom: Dom<S>,
-) -> Dom<S> {
-    dom.wrap_inline_nodes_into_paragraphs_if_needed(&DomHandle::root());
-    dom
-}
+        #[test]
+        fn parse_flattens_list_items_nested_beyond_max_depth() {
+            let html = "<ul><li><b>a</b></li><li>b</li></ul>";
+            let dom: Dom<Utf16String> = HtmlParser::with_max_depth(1)
+                .parse_from_source(html, HtmlSource::Matrix)
+                .unwrap();
+            assert_eq!(dom.to_html(), "<ul><li>ab</li></ul>");
+        }
 
-fn post_process_blocks<S: UnicodeString>(mut dom: Dom<S>) -> Dom<S> {
-    let block_handles = find_blocks(&dom);
-    for handle in block_handles.iter().rev() {
-        dom = post_process_block_lines(dom, handle);
-        dom.join_nodes_in_container(handle);
-    }
-    dom
-}
+        #[test]
+        fn parse_very_deeply_nested_html_does_not_overflow() {
+            let depth = 5000;
+            let html = format!(
+                "{}deep{}",
+                "<blockquote>".repeat(depth),
+                "</blockquote>".repeat(depth)
+            );
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse_from_source(&html, HtmlSource::Matrix)
+                .unwrap();
+            assert_eq!(dom.document().to_raw_text(), "deep");
+            assert!(
+                dom.iter_containers().count() <= DEFAULT_MAX_PARSE_DEPTH + 2
+            );
+        }
 
-fn find_blocks<S: UnicodeString>(dom: &Dom<S>) -> Vec<DomHandle> {
-    dom.iter()
+        #[test]
+        fn parse_drops_data_attributes_by_default() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .parse("<p data-id=\"1\"><b data-x=\"y\">a</b></p>")
+                .unwrap();
+            assert_eq!(dom.to_string(), "<p><b>a</b></p>");
+        }
+
+        #[test]
+        fn parse_keeps_data_attributes_allowed_by_the_policy() {
+            let html = "<blockquote data-quote=\"q\"><p data-id=\"1\">\
+                <b data-x=\"y\">a</b></p></blockquote>\
+                <ol data-list=\"l\" start=\"2\"><li data-item=\"i\">b</li></ol>\
+                <pre data-lang=\"rust\"><code>c</code></pre>";
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .with_attribute_policy(AttributePolicy {
+                    preserve_data_attributes: true,
+                    ..Default::default()
+                })
+                .parse(html)
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<blockquote data-quote=\"q\"><p data-id=\"1\">\
+                <b data-x=\"y\">a</b></p></blockquote>\
+                <ol data-list=\"l\" start=\"2\"><li data-item=\"i\">b</li></ol>\
+                <pre data-lang=\"rust\"><code>c</code></pre>"
+            );
+        }
+
+        #[test]
+        fn parse_keeps_only_data_attributes() {
+            let dom: Dom<Utf16String> = HtmlParser::default()
+                .with_attribute_policy(AttributePolicy {
+                    preserve_data_attributes: true,
+                    ..Default::default()
+                })
+                .parse("<p id=\"x\" data-id=\"1\" style=\"color:red\">a</p>")
+                .unwrap();
+            assert_eq!(dom.to_string(), "<p data-id=\"1\">a</p>");
+        }
+
+        fn parse_with_classes(html: &str) -> Dom<Utf16String> {
+            HtmlParser::default()
+                .with_attribute_policy(AttributePolicy {
+                    allowed_classes: vec!["language-*".into(), "x".into()],
+                    ..Default::default()
+                })
+                .parse(html)
+                .unwrap()
+        }
+
+        #[test]
+        fn parse_keeps_allowed_classes_on_code_and_links() {
+            let dom = parse_with_classes(
+                "<pre class=\"x\"><code class=\"language-rust y\">a</code></pre>\
+                <p><code class=\"x\">b</code><a href=\"https://x.org\" class=\"x\">c</a></p>",
+            );
+            assert_eq!(
+                dom.to_string(),
+                "<pre><code class=\"language-rust\">a</code></pre>\
+                <p><code class=\"x\">b</code><a class=\"x\" href=\"https://x.org\">c</a></p>"
+            );
+        }
+
+        #[test]
+        fn parse_keeps_spans_with_allowed_classes() {
+            let dom = parse_with_classes("a<span class=\"x y\">b</span>c");
+            assert_eq!(dom.to_string(), "a<span class=\"x\">b</span>c");
+            assert_eq!(
+                dom.to_tree().to_string(),
+                indoc! {
+                r#"
+
+                ├>"a"
+                ├>span
+                │ └>"b"
+                └>"c"
+                "#}
+            );
+        }
+
+        #[test]
+        fn parse_rejects_spans_without_allowed_attributes() {
+            let result: Result<Dom<Utf16String>, HtmlParseError> =
+                HtmlParser::default()
+                    .with_attribute_policy(AttributePolicy {
+                        allowed_classes: vec!["x".into()],
+                        ..Default::default()
+                    })
+                    .parse("a<span class=\"y\">b</span>");
+            assert!(result.is_err());
+        }
+
+        fn parse_to_html(html: &str) -> String {
+            let dom: Dom<Utf16String> =
+                HtmlParser::default().parse(html).unwrap();
+            dom.to_string()
+        }
+
+        #[test]
+        fn parse_legacy_strikethrough_tags_as_del() {
+            assert_eq!(
+                parse_to_html("<s>a</s>b<strike>c</strike>"),
+                "<del>a</del>b<del>c</del>"
+            );
+        }
+
+        #[test]
+        fn parse_legacy_font_colours_as_spans() {
+            assert_eq!(
+                parse_to_html(
+                    "<font color=\"#ff0000\">red</font>\
+                    <font data-mx-color=\"#00ff00\" color=\"#ff0000\">green</font>\
+                    <font face=\"serif\">plain</font>"
+                ),
+                "<span data-mx-color=\"#ff0000\">red</span>\
+                <span data-mx-color=\"#00ff00\">green</span>plain"
+            );
+        }
+
+        #[test]
+        fn parse_legacy_divs_as_paragraphs() {
+            assert_eq!(
+                parse_to_html("<div>a</div><center>b</center>"),
+                "<p>a</p><p align=\"center\">b</p>"
+            );
+        }
+
+        #[test]
+        fn parse_legacy_divs_around_blocks_keeps_the_blocks() {
+            assert_eq!(
+                parse_to_html("<div><p>a</p><div>b</div></div>"),
+                "<p>a</p><p>b</p>"
+            );
+        }
+
+        fn parse_preserving_whitespace(html: &str) -> Dom<Utf16String> {
+            HtmlParser::default()
+                .with_whitespace_policy(WhitespacePolicy::Preserve)
+                .parse(html)
+                .unwrap()
+        }
+
+        #[test]
+        fn parse_preserving_whitespace_keeps_indentation_of_lines() {
+            let dom = parse_preserving_whitespace(
+                "<p>\n  /\\_/\\\n ( o.o )\n  &gt; ^ &lt;\n</p>",
+            );
+            assert_eq!(
+                dom.to_string(),
+                "<p>\u{A0}\u{A0}/\\_/\\</p>\
+                <p>\u{A0}( o.o )</p>\
+                <p>\u{A0}\u{A0}&gt; ^ &lt;</p>"
+            );
+        }
+
+        #[test]
+        fn parse_preserving_whitespace_ignores_layout_of_the_source() {
+            let dom = parse_preserving_whitespace(
+                "<ul>\n  <li>a   b</li>\n  <li>c</li>\n</ul>\n",
+            );
+            assert_eq!(
+                dom.to_string(),
+                "<ul><li>a\u{A0}\u{A0} b</li><li>c</li></ul>"
+            );
+        }
+
+        #[test]
+        fn parse_collapses_newlines_in_text_by_default() {
+            let dom: Dom<Utf16String> =
+                HtmlParser::default().parse("<p>a\n    b</p>").unwrap();
+            assert_eq!(dom.document().to_raw_text(), "a b");
+        }
+
+        #[test]
+        fn parse_post_processes_each_of_many_top_level_blocks() {
+            let html = "<p>a<br>b</p><ul><li>c</li></ul><ul><li>d</li></ul>\
+                <b>e</b> f<br><blockquote>g</blockquote>";
+            let dom: Dom<Utf16String> =
+                HtmlParser::default().parse(&html.repeat(70)).unwrap();
+            assert_eq!(
+                dom.to_html(),
+                "<p>a</p><p>b</p><ul><li>c</li><li>d</li></ul>\
+                <p><b>e</b> f</p><blockquote>g</blockquote>"
+                    .repeat(70)
+            );
+        }
+    }
+}
+
+/// Top-level blocks are post-processed in parallel, with the `rayon`
+/// feature, in documents with at least this many of them. Smaller documents
+/// aren't worth spreading across threads.
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+const PARALLEL_POST_PROCESSING_MIN_BLOCKS: usize = 64;
+
+/// Normalize a freshly converted [Dom] in the same way for every parser:
+/// line breaks outside code blocks end paragraphs, inline nodes next to
+/// blocks are wrapped in paragraphs and adjacent text nodes are merged.
+///
+/// Each top-level block only affects its own content, so is processed on
+/// its own, in parallel for large documents if the `rayon` feature is
+/// enabled. The rest of the document is processed once they are done.
+pub(crate) fn post_process_dom<S: UnicodeString>(mut dom: Dom<S>) -> Dom<S> {
+    let top_level_nodes =
+        post_process_top_level_nodes(dom.document_mut().remove_children());
+    let dom = Dom::new(top_level_nodes);
+    let blocks = find_blocks_outside_top_level_blocks(&dom);
+    let mut dom = post_process_blocks(dom, &blocks);
+    dom.wrap_inline_nodes_into_paragraphs_if_needed(&DomHandle::root());
+    dom.strip_bidi_isolates();
+    dom.document_mut().merge_adjacent_text_nodes();
+    dom
+}
+
+/// Normalize the top-level nodes of fragments of HTML which were each
+/// processed by [post_process_dom] on their own, once they are put
+/// together, so that they match HTML parsed in one piece: e.g. a list
+/// ending one fragment is joined with a list starting the next.
+pub(crate) fn post_process_joined_fragments<S: UnicodeString>(
+    nodes: Vec<DomNode<S>>,
+) -> Vec<DomNode<S>> {
+    let mut dom = Dom::new(nodes);
+    dom.join_nodes_in_container(&DomHandle::root());
+    dom.wrap_inline_nodes_into_paragraphs_if_needed(&DomHandle::root());
+    dom.document_mut().merge_adjacent_text_nodes();
+    dom.into_container().take_children()
+}
+
+fn post_process_top_level_nodes<S: UnicodeString>(
+    nodes: Vec<DomNode<S>>,
+) -> Vec<DomNode<S>> {
+    // Strings are `Send` with the `rayon` feature, see
+    // [crate::dom::unicode_string::SendIfParallel]
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    if nodes.len() >= PARALLEL_POST_PROCESSING_MIN_BLOCKS {
+        use rayon::prelude::*;
+        return nodes
+            .into_par_iter()
+            .flat_map_iter(post_process_top_level_node)
+            .collect();
+    }
+    nodes
+        .into_iter()
+        .flat_map(post_process_top_level_node)
+        .collect()
+}
+
+/// Process the blocks in a top-level node, if it is a block, which may turn
+/// it into several nodes.
+fn post_process_top_level_node<S: UnicodeString>(
+    node: DomNode<S>,
+) -> Vec<DomNode<S>> {
+    if !node.is_block_node() {
+        return vec![node];
+    }
+    let dom = Dom::new(vec![node]);
+    // The document holding the node has nothing else to process
+    let blocks: Vec<DomHandle> = find_blocks(&dom)
+        .into_iter()
+        .filter(|handle| !handle.is_root())
+        .collect();
+    post_process_blocks(dom, &blocks)
+        .document_mut()
+        .remove_children()
+}
+
+/// The document itself, and any blocks inside its top-level inline nodes.
+fn find_blocks_outside_top_level_blocks<S: UnicodeString>(
+    dom: &Dom<S>,
+) -> Vec<DomHandle> {
+    let mut blocks = vec![DomHandle::root()];
+    for node in dom.children().iter().filter(|n| !n.is_block_node()) {
+        blocks.extend(
+            node.iter_subtree()
+                .filter(|n| n.is_block_node())
+                .map(|n| n.handle()),
+        );
+    }
+    blocks
+}
+
+/// Process the given blocks, in reverse so that each block's content is
+/// done before the block itself and the handles of the blocks still to do
+/// remain valid.
+fn post_process_blocks<S: UnicodeString>(
+    mut dom: Dom<S>,
+    blocks: &[DomHandle],
+) -> Dom<S> {
+    for handle in blocks.iter().rev() {
+        dom = post_process_block_lines(dom, handle);
+        dom.join_nodes_in_container(handle);
+    }
+    dom
+}
+
+fn find_blocks<S: UnicodeString>(dom: &Dom<S>) -> Vec<DomHandle> {
+    dom.iter()
         .filter(|n| n.is_block_node())
         .map(|n| n.handle())
         .collect::<Vec<_>>()
@@ -1364,11 +2955,12 @@ fn post_process_block_lines<S: UnicodeString>(
         let mut line_breaks: Vec<Option<DomHandle>> = Vec::new();
         let mut next_lines: Vec<DomHandle> = Vec::new();
 
-        let nodes = dom
-            .iter_from_handle(&last_handle)
-            .filter(|n| n.is_leaf() && handle.is_ancestor_of(&n.handle()))
-            .rev()
+        let mut nodes = dom
+            .lookup_node(handle)
+            .iter_subtree()
+            .filter(|n| n.is_leaf())
             .collect::<Vec<_>>();
+        nodes.reverse();
         let mut next_handle = if nodes.is_empty() {
             last_handle.clone()
         } else {
@@ -1498,18 +3090,60 @@ fn last_container_mut_in<S: UnicodeString>(
     node.last_child_mut().and_then(|n| n.as_container_mut())
 }
 
-fn convert_text<S: UnicodeString>(
+/// Append text that was flattened out of elements nested beyond the maximum
+/// parse depth. Lists may only contain list items, so if the node is a list the
+/// text is wrapped in a new one.
+fn append_flattened_text<S: UnicodeString>(
     text: &str,
     node: &mut ContainerNode<S>,
+    is_list: bool,
     is_inside_code_block: bool,
+    detect_at_room: bool,
+) {
+    if is_list {
+        let handle = node.append_child(DomNode::Container(
+            ContainerNode::new_list_item(Vec::new()),
+        ));
+        let list_item = node
+            .last_child_mut()
+            .and_then(DomNode::as_container_mut)
+            .unwrap();
+        convert_text(
+            text,
+            list_item,
+            is_inside_code_block,
+            detect_at_room,
+            false,
+            WhitespacePolicy::Collapse,
+        );
+        if list_item.children().is_empty() {
+            node.remove_child(handle.index_in_parent());
+        }
+    } else {
+        convert_text(
+            text,
+            node,
+            is_inside_code_block,
+            detect_at_room,
+            false,
+            WhitespacePolicy::Collapse,
+        );
+    }
+}
+
+pub(crate) fn convert_text<S: UnicodeString>(
+    text: &str,
+    node: &mut ContainerNode<S>,
+    is_inside_code_block: bool,
+    detect_at_room: bool,
     is_only_child_in_parent: bool,
+    whitespace_policy: WhitespacePolicy,
 ) {
     if is_inside_code_block {
         let text_nodes: Vec<_> = text.split('\n').collect();
         let text_nodes_len = text_nodes.len();
         for (i, str) in text_nodes.into_iter().enumerate() {
-            let is_nbsp = str == "\u{A0}" || str == "&nbsp;";
-            if !str.is_empty() && !is_nbsp {
+            if !str.is_empty() && !is_placeholder(str) {
                 node.append_child(DomNode::new_text(str.into()));
             }
             if i + 1 < text_nodes_len {
@@ -1518,38 +3152,114 @@ fn convert_text<S: UnicodeString>(
         }
     } else {
         let contents = text;
-        let is_nbsp = contents == "\u{A0}" || contents == "&nbsp;";
-        if is_nbsp && is_only_child_in_parent {
+        if is_placeholder(contents) && is_only_child_in_parent {
             return;
         }
 
-        // Trim any surrounding indentation
-        let surrounding_indent =
-            Regex::new(r"^(\s*\n\s*)+|(\s*\n\s*)+$").unwrap();
-        let contents = &surrounding_indent.replace_all(contents, "");
-
-        // Replace any internal indentation with a single space
-        let internal_indent = Regex::new(r"s*\n\s*").unwrap();
-        let contents = &internal_indent.replace_all(contents, " ");
-
-        for (i, part) in contents.split("@room").enumerate() {
+        let lines = match whitespace_policy {
+            WhitespacePolicy::Collapse => vec![collapse_whitespace(contents)],
+            WhitespacePolicy::Preserve => preserve_whitespace(contents),
+        };
+        for (i, line) in lines.iter().enumerate() {
             if i > 0 {
-                node.append_child(DomNode::Mention(
-                    DomNode::new_at_room_mention(vec![]),
-                ));
+                node.append_child(DomNode::new_line_break());
             }
-            if !part.is_empty() {
-                node.append_child(DomNode::new_text(part.into()));
+            // Code is shown as it was written, and links can't contain
+            // other links, so neither can contain mentions
+            let parts: Vec<&str> = if detect_at_room {
+                line.split("@room").collect()
+            } else {
+                vec![line]
+            };
+            for (j, part) in parts.into_iter().enumerate() {
+                if j > 0 {
+                    node.append_child(DomNode::Mention(
+                        DomNode::new_at_room_mention(vec![]),
+                    ));
+                }
+                if !part.is_empty() {
+                    node.append_child(DomNode::new_text(part.into()));
+                }
             }
         }
     }
 }
 
-#[cfg(all(feature = "js", target_arch = "wasm32"))]
+/// Collapse newlines and the indentation around them into a single space,
+/// dropping them entirely at the start and end of the text.
+fn collapse_whitespace(text: &str) -> String {
+    // Trim any surrounding indentation
+    let text = trim_end_indent(trim_start_indent(text));
+
+    // Replace any internal indentation with a single space. This matches
+    // the `s*\n\s*` pattern used before, so it also drops any `s` just
+    // before a newline but keeps other whitespace there.
+    let mut collapsed = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(newline) = rest.find('\n') {
+        collapsed.push_str(rest[..newline].trim_end_matches('s'));
+        collapsed.push(' ');
+        rest = rest[newline..].trim_start();
+    }
+    collapsed.push_str(rest);
+    collapsed
+}
+
+/// Split text into lines at each newline, keeping the indentation at the
+/// start of each line. Newlines at the start and end of the text, and text
+/// made only of whitespace and newlines, only lay out the HTML source so
+/// are dropped.
+fn preserve_whitespace(text: &str) -> Vec<String> {
+    if text.contains('\n') && text.chars().all(|c| c.is_ascii_whitespace()) {
+        return Vec::new();
+    }
+    // Drop the leading newlines, but keep the indentation of the first line
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let text = match leading.rfind('\n') {
+        Some(newline) => &text[newline + 1..],
+        None => text,
+    };
+    let text = trim_end_indent(text);
+
+    let mut lines = text.split('\n').peekable();
+    let mut preserved = Vec::new();
+    while let Some(line) = lines.next() {
+        if lines.peek().is_some() {
+            preserved.push(line.trim_end_matches([' ', '\t', '\r']).to_owned());
+        } else {
+            preserved.push(line.to_owned());
+        }
+    }
+    preserved
+}
+
+/// Remove the whitespace at the start of the text if it contains a newline,
+/// as it is then indentation of the HTML source.
+fn trim_start_indent(text: &str) -> &str {
+    let trimmed = text.trim_start();
+    if text[..text.len() - trimmed.len()].contains('\n') {
+        trimmed
+    } else {
+        text
+    }
+}
+
+/// Remove the whitespace at the end of the text if it contains a newline.
+fn trim_end_indent(text: &str) -> &str {
+    let trimmed = text.trim_end();
+    if text[trimmed.len()..].contains('\n') {
+        trimmed
+    } else {
+        text
+    }
+}
+
+#[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))]
 mod js {
     use super::*;
     use crate::dom::nodes::dom_node::DomNodeKind;
     use crate::dom::nodes::dom_node::DomNodeKind::CodeBlock;
+    use crate::dom::parser::{code_editor, google_doc};
     use crate::{
         dom::nodes::{ContainerNode, DomNode},
         InlineFormatType, ListType,
@@ -1562,16 +3272,131 @@ mod js {
         Document, DomParser, Element, HtmlElement, NodeList, SupportedType,
     };
 
+    /// The formatting an external source means by the style of a span.
+    fn formatting_of_style(
+        style: &web_sys::CssStyleDeclaration,
+    ) -> Option<InlineFormatType> {
+        let value =
+            |name: &str| style.get_property_value(name).unwrap_or_default();
+        match (
+            value("font-weight").as_str(),
+            value("font-style").as_str(),
+            value("text-decoration").as_str(),
+        ) {
+            ("bold" | "700", _, _) => Some(InlineFormatType::Bold),
+            (_, "italic", _) => Some(InlineFormatType::Italic),
+            (_, _, "underline") => Some(InlineFormatType::Underline),
+            (_, _, "line-through") => Some(InlineFormatType::StrikeThrough),
+            _ => None,
+        }
+    }
+
     pub(super) struct HtmlParser {
         current_path: Vec<DomNodeKind>,
+        depth: usize,
+        max_depth: usize,
+        attribute_policy: AttributePolicy,
+        whitespace_policy: WhitespacePolicy,
+        at_room_detection: AtRoomDetection,
+        lenient: bool,
+        check_attributes: bool,
+        issues: Vec<HtmlIssue>,
     }
     impl HtmlParser {
         pub(super) fn default() -> Self {
+            Self::with_max_depth(DEFAULT_MAX_PARSE_DEPTH)
+        }
+
+        pub(super) fn with_max_depth(max_depth: usize) -> Self {
             Self {
                 current_path: Vec::new(),
+                depth: 0,
+                max_depth,
+                attribute_policy: AttributePolicy::default(),
+                whitespace_policy: WhitespacePolicy::default(),
+                at_room_detection: AtRoomDetection::default(),
+                lenient: false,
+                check_attributes: false,
+                issues: Vec::new(),
             }
         }
 
+        pub(super) fn with_attribute_policy(
+            mut self,
+            attribute_policy: AttributePolicy,
+        ) -> Self {
+            self.attribute_policy = attribute_policy;
+            self
+        }
+
+        pub(super) fn with_whitespace_policy(
+            mut self,
+            whitespace_policy: WhitespacePolicy,
+        ) -> Self {
+            self.whitespace_policy = whitespace_policy;
+            self
+        }
+
+        pub(super) fn with_at_room_detection(
+            mut self,
+            at_room_detection: AtRoomDetection,
+        ) -> Self {
+            self.at_room_detection = at_room_detection;
+            self
+        }
+
+        /// Skip unsupported elements in Matrix HTML, keeping their content,
+        /// instead of failing. What was skipped is reported by
+        /// [Self::take_warnings].
+        pub(super) fn with_lenient(mut self, lenient: bool) -> Self {
+            self.lenient = lenient;
+            self
+        }
+
+        /// Also check the attributes of elements in Matrix HTML against
+        /// the Matrix spec, reporting any problems by [Self::take_issues].
+        pub(super) fn with_attribute_checks(
+            mut self,
+            check_attributes: bool,
+        ) -> Self {
+            self.check_attributes = check_attributes;
+            self
+        }
+
+        /// The problems found in Matrix HTML parsed leniently since the last
+        /// call.
+        pub(super) fn take_warnings(&mut self) -> Vec<String> {
+            self.take_issues().iter().map(|i| i.to_string()).collect()
+        }
+
+        /// The problems found in Matrix HTML parsed leniently since the last
+        /// call, including the attributes checked if
+        /// [Self::with_attribute_checks] was set.
+        pub(super) fn take_issues(&mut self) -> Vec<HtmlIssue> {
+            std::mem::take(&mut self.issues)
+        }
+
+        fn can_contain_mentions(&self) -> bool {
+            !self.current_path.iter().any(|kind| {
+                matches!(
+                    kind,
+                    DomNodeKind::Formatting(InlineFormatType::InlineCode)
+                        | DomNodeKind::Link
+                )
+            })
+        }
+
+        /// Whether `@room` here may become an @room mention, as allowed by
+        /// the [AtRoomDetection].
+        fn allows_at_room(&self) -> bool {
+            self.at_room_detection.allows_at_room_in(&self.current_path)
+        }
+
+        /// Whether `@room` in text here becomes an @room mention.
+        fn detects_at_room(&self) -> bool {
+            self.allows_at_room() && self.can_contain_mentions()
+        }
+
         pub(super) fn parse<S>(
             &mut self,
             html: &str,
@@ -1617,9 +3442,7 @@ mod js {
 
             self.webdom_to_dom(document, html_source)
                 .map_err(to_dom_creation_error)
-                .map(post_process_blocks)
-                .map(post_process_for_block_and_inline_siblings)
-                .map(post_process_for_adjacent_text)
+                .map(post_process_dom)
         }
 
         fn webdom_to_dom<S>(
@@ -1647,12 +3470,30 @@ mod js {
             let mut dom = Dom::new(Vec::with_capacity(number_of_nodes));
             let dom_document = dom.document_mut();
 
-            self.convert_container(
+            if self.depth >= self.max_depth {
+                let text: String = (0..nodes.length())
+                    .filter_map(|nth| nodes.get(nth))
+                    .filter_map(|node| node.text_content())
+                    .collect();
+                append_flattened_text(
+                    &text,
+                    dom_document,
+                    parent_kind == DomNodeKind::List,
+                    self.current_path.contains(&CodeBlock),
+                    self.allows_at_room(),
+                );
+                return Ok(dom);
+            }
+
+            self.depth += 1;
+            let result = self.convert_container(
                 nodes,
                 dom_document,
                 parent_kind,
                 html_source,
-            )?;
+            );
+            self.depth -= 1;
+            result?;
 
             Ok(dom)
         }
@@ -1703,7 +3544,9 @@ mod js {
                                     value.as_str(),
                                     dom,
                                     is_inside_code_block,
+                                    self.detects_at_room(),
                                     is_only_child_in_parent,
+                                    self.whitespace_policy,
                                 );
                             }
                             _ => {}
@@ -1731,6 +3574,12 @@ mod js {
                                 }
                             }
 
+                            for (name, value) in self.kept_attributes(&node) {
+                                if !attributes.iter().any(|(n, _)| *n == name) {
+                                    attributes.push((name, value));
+                                }
+                            }
+
                             let url = node
                                 .unchecked_ref::<Element>()
                                 .get_attribute("href")
@@ -1745,7 +3594,26 @@ mod js {
                                 }
                                 None => false,
                             };
-                            if has_text && is_mention {
+                            // The composer writes @room mentions as links
+                            // to `#`
+                            let is_at_room = self.allows_at_room()
+                                && (node
+                                    .unchecked_ref::<Element>()
+                                    .get_attribute("data-mention-type")
+                                    .as_deref()
+                                    == Some("at-room")
+                                    || (url == "#"
+                                        && has_text
+                                        && text
+                                            .as_ref()
+                                            .and_then(|t| t.node_value())
+                                            .as_deref()
+                                            == Some("@room")));
+                            if is_at_room {
+                                dom.append_child(DomNode::Mention(
+                                    DomNode::new_at_room_mention(attributes),
+                                ));
+                            } else if has_text && is_mention {
                                 dom.append_child(
                                     DomNode::Mention(
                                         DomNode::new_mention(
@@ -1818,24 +3686,7 @@ mod js {
                                         .unwrap()
                                         .as_container_mut()
                                         .unwrap();
-                                    target.append_child(DomNode::Container(
-                                        ContainerNode::new_list(
-                                            list_type,
-                                            self.convert(
-                                                node.child_nodes(),
-                                                DomNodeKind::List,
-                                                html_source,
-                                            )?
-                                            .take_children(),
-                                            attributes,
-                                        ),
-                                    ));
-                                    self.current_path.pop();
-                                }
-                            } else {
-                                self.current_path.push(DomNodeKind::List);
-                                dom.append_child(DomNode::Container(
-                                    ContainerNode::new_list(
+                                    let list = ContainerNode::new_list(
                                         list_type,
                                         self.convert(
                                             node.child_nodes(),
@@ -1844,7 +3695,26 @@ mod js {
                                         )?
                                         .take_children(),
                                         attributes,
-                                    ),
+                                    );
+                                    target.append_child(DomNode::Container(
+                                        self.keep_attributes(&node, list),
+                                    ));
+                                    self.current_path.pop();
+                                }
+                            } else {
+                                self.current_path.push(DomNodeKind::List);
+                                let list = ContainerNode::new_list(
+                                    list_type,
+                                    self.convert(
+                                        node.child_nodes(),
+                                        DomNodeKind::List,
+                                        html_source,
+                                    )?
+                                    .take_children(),
+                                    attributes,
+                                );
+                                dom.append_child(DomNode::Container(
+                                    self.keep_attributes(&node, list),
                                 ));
                                 self.current_path.pop();
                             }
@@ -1856,7 +3726,7 @@ mod js {
                                     Some(Error::ParentNotAList);
                             } else {
                                 self.current_path.push(DomNodeKind::ListItem);
-                                dom.append_child(DomNode::Container(
+                                let mut list_item =
                                     ContainerNode::new_list_item(
                                         self.convert(
                                             node.child_nodes(),
@@ -1864,7 +3734,28 @@ mod js {
                                             html_source,
                                         )?
                                         .take_children(),
-                                    ),
+                                    );
+                                if html_source == HtmlSource::GoogleDoc {
+                                    let element =
+                                        node.unchecked_ref::<Element>();
+                                    if let Some(marker) =
+                                        google_doc::checkbox_marker(
+                                            element
+                                                .get_attribute("role")
+                                                .as_deref(),
+                                            element
+                                                .get_attribute("aria-checked")
+                                                .as_deref(),
+                                        )
+                                    {
+                                        google_doc::prepend_marker(
+                                            &mut list_item,
+                                            marker,
+                                        );
+                                    }
+                                }
+                                dom.append_child(DomNode::Container(
+                                    self.keep_attributes(&node, list_item),
                                 ));
                                 self.current_path.pop();
                             }
@@ -1873,54 +3764,249 @@ mod js {
                         "PRE" => {
                             self.current_path.push(DomNodeKind::CodeBlock);
                             let children = node.child_nodes();
-                            let children = if children.length() == 1
-                                && children.get(0).unwrap().node_name().as_str()
-                                    == "CODE"
-                            {
-                                let code_node = children.get(0).unwrap();
-                                code_node.child_nodes()
-                            } else {
-                                children
+                            let code_node = children.get(0).filter(|child| {
+                                children.length() == 1
+                                    && child.node_name().as_str() == "CODE"
+                            });
+                            let children = match &code_node {
+                                Some(code_node) => code_node.child_nodes(),
+                                None => children,
                             };
-                            dom.append_child(DomNode::Container(
-                                ContainerNode::new_code_block(
-                                    self.convert(
-                                        children,
-                                        DomNodeKind::CodeBlock,
-                                        html_source,
-                                    )?
-                                    .take_children(),
-                                ),
-                            ));
+                            let code_block = ContainerNode::new_code_block(
+                                self.convert(
+                                    children,
+                                    DomNodeKind::CodeBlock,
+                                    html_source,
+                                )?
+                                .take_children(),
+                            );
+                            let mut code_block =
+                                self.keep_attributes(&node, code_block);
+                            // The language of a code block is on its code tag
+                            if let Some(code_node) = &code_node {
+                                code_block =
+                                    self.keep_attributes(code_node, code_block);
+                            }
+                            dom.append_child(DomNode::Container(code_block));
                             self.current_path.pop();
                         }
 
                         "BLOCKQUOTE" => {
                             self.current_path.push(DomNodeKind::Quote);
+                            let quote = ContainerNode::new_quote(
+                                self.convert(
+                                    node.child_nodes(),
+                                    DomNodeKind::Quote,
+                                    html_source,
+                                )?
+                                .take_children(),
+                            );
                             dom.append_child(DomNode::Container(
-                                ContainerNode::new_quote(
-                                    self.convert(
+                                self.keep_attributes(&node, quote),
+                            ));
+                            self.current_path.pop();
+                        }
+
+                        "FONT" => {
+                            // Legacy colours are written the way the Matrix
+                            // spec describes them now
+                            let element = node.unchecked_ref::<Element>();
+                            let color = element
+                                .get_attribute("data-mx-color")
+                                .or_else(|| element.get_attribute("color"));
+                            if let Some(color) = color {
+                                let mut attributes: Vec<(S, S)> = vec![(
+                                    "data-mx-color".into(),
+                                    color.into(),
+                                )];
+                                attributes.extend(
+                                    self.kept_attributes(&node)
+                                        .into_iter()
+                                        .filter(|(name, _)| {
+                                            *name != S::from("data-mx-color")
+                                        }),
+                                );
+                                self.current_path.push(DomNodeKind::Span);
+                                let children = self
+                                    .convert(
                                         node.child_nodes(),
-                                        DomNodeKind::Quote,
+                                        DomNodeKind::Span,
                                         html_source,
                                     )?
-                                    .take_children(),
-                                ),
-                            ));
+                                    .take_children();
+                                self.current_path.pop();
+                                dom.append_child(DomNode::Container(
+                                    ContainerNode::new_span(
+                                        children,
+                                        Some(attributes),
+                                    ),
+                                ));
+                            } else {
+                                let children = self
+                                    .convert(
+                                        node.child_nodes(),
+                                        parent_kind.clone(),
+                                        html_source,
+                                    )?
+                                    .take_children();
+                                dom.append_children(children);
+                            }
+                        }
+
+                        "DIV"
+                            if html_source != HtmlSource::Matrix
+                                && !self.current_path.contains(&CodeBlock)
+                                && code_editor::is_code_editor_block(
+                                    node.unchecked_ref::<Element>()
+                                        .get_attribute("style")
+                                        .as_deref(),
+                                ) =>
+                        {
+                            self.current_path.push(DomNodeKind::CodeBlock);
+                            let mut code_block = ContainerNode::new_code_block(
+                                self.convert(
+                                    node.child_nodes(),
+                                    DomNodeKind::CodeBlock,
+                                    html_source,
+                                )?
+                                .take_children(),
+                            );
+                            let element = node.unchecked_ref::<Element>();
+                            let language =
+                                code_editor::language_class(|name| {
+                                    element.get_attribute(name)
+                                })
+                                .and_then(
+                                    |class| {
+                                        self.attribute_policy
+                                            .kept_value("code", "class", &class)
+                                    },
+                                );
+                            if let Some(language) = language {
+                                code_block.add_attributes(vec![(
+                                    "class".into(),
+                                    language.into(),
+                                )]);
+                            }
+                            dom.append_child(DomNode::Container(code_block));
                             self.current_path.pop();
                         }
 
-                        "P" => {
-                            self.current_path.push(DomNodeKind::Paragraph);
-                            dom.append_child(DomNode::Container(
-                                ContainerNode::new_paragraph(
-                                    self.convert(
+                        "HEAD" | "SCRIPT" | "STYLE" | "TITLE" | "XML"
+                            if html_source != HtmlSource::Matrix =>
+                        {
+                            // Not part of the content
+                        }
+
+                        "TABLE" | "THEAD" | "TBODY" | "TFOOT" | "TR"
+                            if html_source != HtmlSource::Matrix =>
+                        {
+                            // Emails lay out their content with tables,
+                            // which can't be composed, so only their cells
+                            // are kept
+                            let children = self
+                                .convert(
+                                    node.child_nodes(),
+                                    parent_kind.clone(),
+                                    html_source,
+                                )?
+                                .take_children();
+                            dom.append_children(children);
+                        }
+
+                        "TD" | "TH" | "CAPTION"
+                            if html_source != HtmlSource::Matrix
+                                && node
+                                    .text_content()
+                                    .unwrap_or_default()
+                                    .trim()
+                                    .is_empty() =>
+                        {
+                            // Cells which only space out a layout
+                        }
+
+                        "DIV" | "CENTER" | "TD" | "TH" | "CAPTION"
+                            if matches!(tag, "DIV" | "CENTER")
+                                || html_source != HtmlSource::Matrix =>
+                        {
+                            if has_block_children(&node) {
+                                // Only the blocks inside can be kept
+                                let children = self
+                                    .convert(
                                         node.child_nodes(),
+                                        parent_kind.clone(),
+                                        html_source,
+                                    )?
+                                    .take_children();
+                                dom.append_children(children);
+                            } else {
+                                self.current_path.push(DomNodeKind::Paragraph);
+                                // A line of code holding only a line break
+                                // is empty, the break only keeps it open
+                                let children = node.child_nodes();
+                                let is_empty_code_line =
+                                    self.current_path.contains(&CodeBlock)
+                                        && children.length() == 1
+                                        && children.get(0).is_some_and(|c| {
+                                            c.node_name() == "BR"
+                                        });
+                                let children = if is_empty_code_line {
+                                    Vec::new()
+                                } else {
+                                    self.convert(
+                                        children,
                                         DomNodeKind::Paragraph,
                                         html_source,
                                     )?
-                                    .take_children(),
-                                ),
+                                    .take_children()
+                                };
+                                let mut paragraph =
+                                    ContainerNode::new_paragraph(children);
+                                if tag == "CENTER" {
+                                    paragraph.add_attributes(vec![(
+                                        "align".into(),
+                                        "center".into(),
+                                    )]);
+                                }
+                                dom.append_child(DomNode::Container(
+                                    self.keep_attributes(&node, paragraph),
+                                ));
+                                self.current_path.pop();
+                            }
+                        }
+
+                        "H1" | "H2" | "H3" | "H4" | "H5" | "H6" => {
+                            self.append_heading(&node, dom, html_source)?;
+                        }
+
+                        "P" if html_source == HtmlSource::GoogleDoc
+                            && Self::is_google_doc_heading(&node) =>
+                        {
+                            // Docs styles titles and some headings with
+                            // large text rather than heading tags
+                            self.append_heading(&node, dom, html_source)?;
+                        }
+
+                        "HR" => {
+                            // Rules can't be composed, so they are kept as an
+                            // empty paragraph separating what is around them
+                            dom.append_child(DomNode::Container(
+                                ContainerNode::new_paragraph(Vec::new()),
+                            ));
+                        }
+
+                        "P" => {
+                            self.current_path.push(DomNodeKind::Paragraph);
+                            let paragraph = ContainerNode::new_paragraph(
+                                self.convert(
+                                    node.child_nodes(),
+                                    DomNodeKind::Paragraph,
+                                    html_source,
+                                )?
+                                .take_children(),
+                            );
+                            dom.append_child(DomNode::Container(
+                                self.keep_attributes(&node, paragraph),
                             ));
                             self.current_path.pop();
                         }
@@ -1928,65 +4014,55 @@ mod js {
                             let formatting_kind = match node_name {
                                 "STRONG" | "B" => Some(InlineFormatType::Bold),
                                 "EM" | "I" => Some(InlineFormatType::Italic),
-                                "DEL" => Some(InlineFormatType::StrikeThrough),
+                                // Legacy strikethrough tags are written as del
+                                "DEL" | "S" | "STRIKE" => {
+                                    Some(InlineFormatType::StrikeThrough)
+                                }
                                 "U" => Some(InlineFormatType::Underline),
                                 "CODE" => Some(InlineFormatType::InlineCode),
                                 "SPAN" => {
                                     if html_source == HtmlSource::Matrix {
-                                        invalid_node_error =
-                                            Some(Error::UnknownNode(
-                                                node_name.to_owned(),
-                                            ));
+                                        if !self.append_span_if_kept(
+                                            &node,
+                                            dom,
+                                            html_source,
+                                        )? {
+                                            invalid_node_error =
+                                                Some(Error::UnknownNode(
+                                                    node_name.to_owned(),
+                                                ));
+                                        }
                                         None
                                     } else {
                                         // For external sources, we check for common formatting styles for spans
                                         // and convert them to appropriate formatting nodes.
-                                        let style = node
-                                            .unchecked_ref::<HtmlElement>()
-                                            .style();
-                                        if style
-                                            .get_property_value("font-weight")
-                                            .unwrap_or_default()
-                                            == "bold"
-                                            || style
-                                                .get_property_value(
-                                                    "font-weight",
-                                                )
-                                                .unwrap_or_default()
-                                                == "700"
-                                        {
-                                            Some(InlineFormatType::Bold)
-                                        } else if style
-                                            .get_property_value("font-style")
-                                            .unwrap_or_default()
-                                            == "italic"
-                                        {
-                                            Some(InlineFormatType::Italic)
-                                        } else if style
-                                            .get_property_value(
-                                                "text-decoration",
-                                            )
-                                            .unwrap_or_default()
-                                            == "underline"
+                                        let formatting = if self
+                                            .current_path
+                                            .contains(&CodeBlock)
                                         {
-                                            Some(InlineFormatType::Underline)
-                                        } else if style
-                                            .get_property_value(
-                                                "text-decoration",
+                                            // Syntax highlighting in code
+                                            // isn't formatting
+                                            None
+                                        } else {
+                                            formatting_of_style(
+                                                &node
+                                                    .unchecked_ref::<HtmlElement>()
+                                                    .style(),
                                             )
-                                            .unwrap_or_default()
-                                            == "line-through"
+                                        };
+                                        if formatting.is_none()
+                                            && !self.append_span_if_kept(
+                                                &node,
+                                                dom,
+                                                html_source,
+                                            )?
                                         {
-                                            Some(
-                                                InlineFormatType::StrikeThrough,
-                                            )
-                                        } else {
                                             invalid_node_error =
                                                 Some(Error::UnknownNode(
                                                     node_name.to_owned(),
                                                 ));
-                                            None
                                         }
+                                        formatting
                                     }
                                 }
                                 _ => {
@@ -2030,11 +4106,13 @@ mod js {
                                         )?
                                         .take_children();
 
-                                    dom.append_child(DomNode::Container(
+                                    let formatting =
                                         ContainerNode::new_formatting(
                                             formatting_kind.clone(),
                                             children_nodes,
-                                        ),
+                                        );
+                                    dom.append_child(DomNode::Container(
+                                        self.keep_attributes(&node, formatting),
                                     ));
                                     self.current_path.pop();
                                 }
@@ -2043,12 +4121,40 @@ mod js {
                     }
                 }
 
+                if self.check_attributes
+                    && html_source == HtmlSource::Matrix
+                    && !matches!(
+                        invalid_node_error,
+                        Some(Error::UnknownNode(_))
+                    )
+                {
+                    if let Some(element) = node.dyn_ref::<Element>() {
+                        let tag = tag.to_lowercase();
+                        let attributes = element.attributes();
+                        let attributes: Vec<(String, String)> = (0..attributes
+                            .length())
+                            .filter_map(|i| attributes.item(i))
+                            .map(|attr| (attr.name(), attr.value()))
+                            .collect();
+                        self.issues.extend(attribute_issues(
+                            &tag,
+                            attributes
+                                .iter()
+                                .map(|(k, v)| (k.as_str(), v.as_str())),
+                        ));
+                    }
+                }
+
                 // Handle invalid node errors
                 if let Some(err) = invalid_node_error {
-                    if html_source == HtmlSource::Matrix {
+                    if html_source == HtmlSource::Matrix && !self.lenient {
                         return Err(err);
-                    } else if !skip_children {
-                        // If the source is not Matrix and we haven't explicitly flagged to skip the children continue to parse them.
+                    }
+                    if html_source == HtmlSource::Matrix {
+                        self.issues.push(err.to_issue(tag));
+                    }
+                    if !skip_children {
+                        // Unless we have explicitly flagged to skip the children, continue to parse them.
                         let children_nodes = self
                             .convert(
                                 node.child_nodes(),
@@ -2065,6 +4171,141 @@ mod js {
 
             Ok(())
         }
+
+        /// Copy the attributes of node which the attribute policy keeps
+        /// onto the container created for it.
+        fn keep_attributes<S>(
+            &self,
+            node: &web_sys::Node,
+            mut container: ContainerNode<S>,
+        ) -> ContainerNode<S>
+        where
+            S: UnicodeString,
+        {
+            container.add_attributes(self.kept_attributes(node));
+            container
+        }
+
+        /// Add a paragraph of bold text for node to dom, as headings can't
+        /// be composed.
+        fn append_heading<S>(
+            &mut self,
+            node: &web_sys::Node,
+            dom: &mut ContainerNode<S>,
+            html_source: HtmlSource,
+        ) -> Result<(), Error>
+        where
+            S: UnicodeString,
+        {
+            let bold = DomNodeKind::Formatting(InlineFormatType::Bold);
+            self.current_path.push(DomNodeKind::Paragraph);
+            self.current_path.push(bold.clone());
+            let children = self
+                .convert(node.child_nodes(), bold, html_source)?
+                .take_children();
+            self.current_path.truncate(self.current_path.len() - 2);
+            let paragraph =
+                ContainerNode::new_paragraph(vec![DomNode::Container(
+                    ContainerNode::new_formatting(
+                        InlineFormatType::Bold,
+                        children,
+                    ),
+                )]);
+            dom.append_child(DomNode::Container(
+                self.keep_attributes(node, paragraph),
+            ));
+            Ok(())
+        }
+
+        /// Whether node is a paragraph Docs styles as a heading, going by
+        /// its role or the font sizes of the spans of text directly in it.
+        fn is_google_doc_heading(node: &web_sys::Node) -> bool {
+            let children = node.child_nodes();
+            let run_font_sizes: Vec<Option<f32>> = (0..children.length())
+                .filter_map(|i| children.get(i))
+                .filter_map(|child| {
+                    if child.node_name() == "SPAN" {
+                        Some(
+                            child
+                                .unchecked_ref::<Element>()
+                                .get_attribute("style")
+                                .as_deref()
+                                .and_then(google_doc::font_size_pt),
+                        )
+                    } else if child.node_type() == web_sys::Node::TEXT_NODE
+                        && !child
+                            .node_value()
+                            .unwrap_or_default()
+                            .trim()
+                            .is_empty()
+                    {
+                        Some(None)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            google_doc::is_heading_paragraph(
+                node.unchecked_ref::<Element>()
+                    .get_attribute("role")
+                    .as_deref(),
+                &run_font_sizes,
+            )
+        }
+
+        /// Add a span for node to dom if the attribute policy keeps any of
+        /// its attributes, returning whether it did.
+        fn append_span_if_kept<S>(
+            &mut self,
+            node: &web_sys::Node,
+            dom: &mut ContainerNode<S>,
+            html_source: HtmlSource,
+        ) -> Result<bool, Error>
+        where
+            S: UnicodeString,
+        {
+            let attributes = self.kept_attributes(node);
+            if attributes.is_empty() {
+                return Ok(false);
+            }
+            self.current_path.push(DomNodeKind::Span);
+            let children = self
+                .convert(node.child_nodes(), DomNodeKind::Span, html_source)?
+                .take_children();
+            self.current_path.pop();
+            dom.append_child(DomNode::Container(ContainerNode::new_span(
+                children,
+                Some(attributes),
+            )));
+            Ok(true)
+        }
+
+        /// The attributes of node which the attribute policy keeps
+        fn kept_attributes<S>(&self, node: &web_sys::Node) -> Vec<(S, S)>
+        where
+            S: UnicodeString,
+        {
+            let tag = node.node_name().to_lowercase();
+            let attributes = node.unchecked_ref::<Element>().attributes();
+            (0..attributes.length())
+                .filter_map(|i| attributes.item(i))
+                .filter_map(|attr| {
+                    self.attribute_policy
+                        .kept_value(&tag, &attr.name(), &attr.value())
+                        .map(|value| (attr.name().into(), value.into()))
+                })
+                .collect()
+        }
+    }
+
+    /// True if any of node's children will become block nodes
+    fn has_block_children(node: &web_sys::Node) -> bool {
+        let children = node.child_nodes();
+        (0..children.length())
+            .filter_map(|i| children.get(i))
+            .any(|child| {
+                BLOCK_TAGS.contains(&child.node_name().to_lowercase().as_str())
+            })
     }
 
     fn to_dom_creation_error<E>(error: E) -> HtmlParseError
@@ -2109,9 +4350,27 @@ mod js {
         }
     }
 
+    impl Error {
+        /// The [HtmlIssue] reported for this error at an element with the
+        /// given tag.
+        fn to_issue(&self, tag: &str) -> HtmlIssue {
+            match self {
+                Self::NoBody => HtmlIssue::Malformed(self.to_string()),
+                Self::UnknownNode(tag) => HtmlIssue::UnknownTag {
+                    tag: tag.to_lowercase(),
+                },
+                Self::InvalidListItemNode => HtmlIssue::NotAListItem {
+                    tag: tag.to_lowercase(),
+                },
+                Self::ParentNotAList => HtmlIssue::ListItemOutsideList,
+            }
+        }
+    }
+
     #[cfg(all(test, target_arch = "wasm32"))]
     mod tests {
         use super::*;
+        use crate::dom::parser::conformance;
         use crate::{
             tests::testutils_composer_model::restore_whitespace, ToHtml,
             ToMarkdown, ToTree,
@@ -2136,6 +4395,25 @@ mod js {
             assert_eq!(html, html_again);
         }
 
+        #[wasm_bindgen_test]
+        fn parser_conforms() {
+            conformance::check_fixtures(|html, source| {
+                HtmlParser::default()
+                    .parse_from_source(html, source)
+                    .unwrap()
+            });
+        }
+
+        #[wasm_bindgen_test]
+        fn at_room_detection_conforms() {
+            conformance::check_at_room_detection(|html, detection| {
+                HtmlParser::default()
+                    .with_at_room_detection(detection)
+                    .parse(html)
+                    .unwrap()
+            });
+        }
+
         #[wasm_bindgen_test]
         fn formatting() {
             roundtrip("foo <strong>bar</strong> baz");
@@ -2177,6 +4455,72 @@ mod js {
             );
         }
 
+        #[wasm_bindgen_test]
+        fn google_doc_checklist() {
+            let dom = HtmlParser::default()
+                .parse_from_source::<Utf16String>(
+                    GOOGLE_DOC_CHECKLIST_HTML_PASTEBOARD,
+                    HtmlSource::GoogleDoc,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<ul><li><p>☑ <del>Done</del></p></li>\
+                <li><p>☐ To <strong>do</strong></p></li></ul>"
+            );
+        }
+
+        #[wasm_bindgen_test]
+        fn google_doc_headings() {
+            let dom = HtmlParser::default()
+                .parse_from_source::<Utf16String>(
+                    GOOGLE_DOC_HEADINGS_HTML_PASTEBOARD,
+                    HtmlSource::GoogleDoc,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<p><strong>Title</strong></p>\
+                <p><strong>Heading</strong></p>\
+                <p><strong>Subheading</strong></p>\
+                <p>Body text</p>"
+            );
+        }
+
+        #[wasm_bindgen_test]
+        fn code_from_an_editor() {
+            let dom = HtmlParser::default()
+                .parse_from_source::<Utf16String>(
+                    VS_CODE_HTML_PASTEBOARD,
+                    HtmlSource::UnknownExternal,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<pre><code>fn main() {\n    // Say hello\n\n    \
+                println!(\"&lt;hello&gt;\");\n}</code></pre>"
+            );
+        }
+
+        #[wasm_bindgen_test]
+        fn email_layout_tables() {
+            let dom = HtmlParser::default()
+                .parse_from_source::<Utf16String>(
+                    EMAIL_HTML_PASTEBOARD,
+                    HtmlSource::UnknownExternal,
+                )
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<p><strong>Monthly update</strong></p>\
+                <p>Hello all,</p>\
+                <p>\u{a0}</p>\
+                <p>Read the <a href=\"https://matrix.org/blog\">blog</a>.</p>\
+                <p>Sent from <em>Outlook</em></p>\
+                <p>\u{a0}</p>"
+            );
+        }
+
         #[wasm_bindgen_test]
         fn ms_rich_text() {
             let dom = HtmlParser::default()
@@ -2197,6 +4541,33 @@ mod js {
             assert_eq!(result.is_err(), true);
         }
 
+        #[wasm_bindgen_test]
+        fn legacy_tags() {
+            let html = "<s>a</s>b<strike>c</strike>\
+                <font color=\"#ff0000\">red</font>\
+                <div>d</div><center>e</center>";
+            let dom = HtmlParser::default().parse::<Utf16String>(html).unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<p><del>a</del>b<del>c</del>\
+                <span data-mx-color=\"#ff0000\">red</span></p>\
+                <p>d</p><p align=\"center\">e</p>"
+            );
+        }
+
+        #[wasm_bindgen_test]
+        fn preserved_whitespace() {
+            let html = "<p>\n  a\n   b  c\n</p>";
+            let dom = HtmlParser::default()
+                .with_whitespace_policy(WhitespacePolicy::Preserve)
+                .parse::<Utf16String>(html)
+                .unwrap();
+            assert_eq!(
+                dom.to_string(),
+                "<p>\u{A0}\u{A0}a</p><p>\u{A0}\u{A0} b\u{A0}\u{A0}c</p>"
+            );
+        }
+
         #[wasm_bindgen_test]
         fn br() {
             let html = "foo<br />bar";
@@ -0,0 +1,163 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Where the elements of parsed HTML were, and what became of them, so that
+//! tools can show users which parts of the content they pasted were dropped
+//! or transformed and why. Positions are only recorded with the
+//! `source-map` feature.
+
+#[cfg(feature = "source-map")]
+use std::ops::Range;
+
+/// What became of an element of the parsed HTML.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SourceChange {
+    /// It became the same kind of node
+    Kept,
+    /// It became a different kind of node, for the given reason
+    Transformed(String),
+    /// It was left out but its content was kept, for the given reason
+    Unwrapped(String),
+    /// It was left out along with its content, for the given reason
+    Dropped(String),
+}
+
+/// Where an element of the parsed HTML was, and what became of it.
+#[cfg(feature = "source-map")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SourcePosition {
+    /// The name of the element, e.g. `h1`
+    pub tag: String,
+    /// The byte range of the element's start tag in the HTML
+    pub range: Range<usize>,
+    pub change: SourceChange,
+}
+
+/// The [SourcePosition] of each element of some parsed HTML, in the order
+/// they appear in it. Elements which the parser implied, e.g. a `<tbody>`
+/// missing from a table, are not included.
+#[cfg(feature = "source-map")]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap {
+    pub(crate) positions: Vec<SourcePosition>,
+}
+
+#[cfg(feature = "source-map")]
+impl SourceMap {
+    pub fn positions(&self) -> &[SourcePosition] {
+        &self.positions
+    }
+
+    /// The elements which were not kept as they were.
+    pub fn changes(&self) -> impl Iterator<Item = &SourcePosition> {
+        self.positions
+            .iter()
+            .filter(|p| p.change != SourceChange::Kept)
+    }
+}
+
+/// The byte range of the start tag in `html` of each of the elements named
+/// by `names`, in the order the parser created them. Elements are matched
+/// to start tags in order, so an element which doesn't match the next start
+/// tag, e.g. because the parser implied it, has no range. Entries of
+/// `names` which are None, e.g. for text, are skipped.
+#[cfg(feature = "source-map")]
+pub(crate) fn start_tag_ranges<'a>(
+    html: &str,
+    names: impl Iterator<Item = Option<&'a str>>,
+) -> Vec<Option<Range<usize>>> {
+    let mut next = next_start_tag(html, 0);
+    names
+        .map(|name| {
+            let (range, tag) = next.clone()?;
+            if !name?.eq_ignore_ascii_case(tag) {
+                return None;
+            }
+            next = next_start_tag(html, range.end);
+            Some(range)
+        })
+        .collect()
+}
+
+/// The byte range and name of the first start tag in `html` at or after
+/// `from`, skipping end tags, comments and declarations.
+#[cfg(feature = "source-map")]
+fn next_start_tag(html: &str, from: usize) -> Option<(Range<usize>, &str)> {
+    let bytes = html.as_bytes();
+    let mut pos = from;
+    loop {
+        let start = pos + html[pos..].find('<')?;
+        let rest = &html[start + 1..];
+        if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let name_len = rest
+                .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+                .unwrap_or(rest.len());
+            let end = tag_end(bytes, start + 1 + name_len);
+            return Some((start..end, &rest[..name_len]));
+        }
+        pos = if rest.starts_with("!--") {
+            html[start..]
+                .find("-->")
+                .map_or(html.len(), |i| start + i + 3)
+        } else if rest.starts_with(['!', '/', '?']) {
+            tag_end(bytes, start + 1)
+        } else {
+            start + 1
+        };
+    }
+}
+
+/// The position just after the `>` closing the tag which continues at
+/// `pos`, ignoring any inside quoted attribute values.
+#[cfg(feature = "source-map")]
+fn tag_end(bytes: &[u8], mut pos: usize) -> usize {
+    let mut quote = None;
+    while pos < bytes.len() {
+        match (quote, bytes[pos]) {
+            (None, b'>') => return pos + 1,
+            (None, q @ (b'"' | b'\'')) => quote = Some(q),
+            (Some(q), b) if b == q => quote = None,
+            _ => {}
+        }
+        pos += 1;
+    }
+    bytes.len()
+}
+
+#[cfg(all(test, feature = "source-map"))]
+mod test {
+    use super::*;
+
+    fn ranges(html: &str, names: &[Option<&str>]) -> Vec<Option<Range<usize>>> {
+        start_tag_ranges(html, names.iter().copied())
+    }
+
+    #[test]
+    fn start_tags_are_found_in_order() {
+        let html = "<p>a <b class=\"x>y\">b</b></p><P>c</P>";
+        assert_eq!(
+            ranges(html, &[Some("p"), None, Some("b"), Some("p")]),
+            vec![Some(0..3), None, Some(5..20), Some(29..32)]
+        );
+    }
+
+    #[test]
+    fn implied_elements_have_no_range() {
+        let html = "<table><tr><td>a</td></tr></table>";
+        assert_eq!(
+            ranges(
+                html,
+                &[Some("html"), Some("table"), Some("tbody"), Some("tr")]
+            ),
+            vec![None, Some(0..7), None, Some(7..11)]
+        );
+    }
+
+    #[test]
+    fn comments_and_end_tags_are_skipped() {
+        let html = "<!-- <i> --></x>a < b<em>c";
+        assert_eq!(ranges(html, &[Some("em")]), vec![Some(21..25)]);
+    }
+}
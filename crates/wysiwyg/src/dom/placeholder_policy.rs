@@ -0,0 +1,80 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! The placeholders written into empty blocks in the HTML for the editor,
+//! so that browsers and platform text views show the block and can put the
+//! cursor in it. A placeholder is never part of the content: parsing drops
+//! it again, and the HTML for a message never has any, so that clients
+//! don't need to strip them and can't mistake a real non-breaking space
+//! for one.
+
+use crate::nbsp::NBSP;
+
+/// Which empty blocks get a placeholder in the HTML for the editor.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PlaceholderPolicy {
+    /// Empty paragraphs, and empty code lines which are the first or last
+    /// of their code block, get a `&nbsp;`, as they would otherwise
+    /// collapse when rendered.
+    #[default]
+    EmptyBlocks,
+    /// No placeholders are written, for platforms which show empty blocks
+    /// by themselves.
+    Never,
+}
+
+/// An empty block which may need a placeholder.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum EmptyBlock {
+    Paragraph,
+    CodeLine { is_first: bool, is_last: bool },
+}
+
+impl PlaceholderPolicy {
+    /// The placeholder to write into `block`, if any. HTML for a message
+    /// never has one.
+    pub(crate) fn placeholder_for(
+        &self,
+        block: EmptyBlock,
+        as_message: bool,
+    ) -> Option<char> {
+        if as_message {
+            return None;
+        }
+        let needs_placeholder = match self {
+            Self::Never => false,
+            Self::EmptyBlocks => match block {
+                EmptyBlock::Paragraph => true,
+                EmptyBlock::CodeLine { is_first, is_last } => {
+                    is_first || is_last
+                }
+            },
+        };
+        needs_placeholder.then_some(NBSP)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EmptyBlock, PlaceholderPolicy};
+
+    #[test]
+    fn only_edge_code_lines_get_a_placeholder() {
+        let policy = PlaceholderPolicy::EmptyBlocks;
+        let line =
+            |is_first, is_last| EmptyBlock::CodeLine { is_first, is_last };
+        assert!(policy.placeholder_for(line(true, false), false).is_some());
+        assert!(policy.placeholder_for(line(false, true), false).is_some());
+        assert!(policy.placeholder_for(line(false, false), false).is_none());
+    }
+
+    #[test]
+    fn messages_never_get_a_placeholder() {
+        let policy = PlaceholderPolicy::EmptyBlocks;
+        assert!(policy
+            .placeholder_for(EmptyBlock::Paragraph, true)
+            .is_none());
+    }
+}
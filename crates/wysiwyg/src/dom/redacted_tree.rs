@@ -0,0 +1,138 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! A tree of the Dom like [ToTree] outputs, but without any of the text the
+//! user wrote, so that it can be attached to bug reports.
+
+use matrix_mentions::MentionKind;
+
+use crate::dom::nodes::mention_node::MentionNodeKind;
+use crate::dom::nodes::ContainerNode;
+use crate::dom::unicode_string::{UnicodeStrExt, UnicodeStringExt};
+use crate::dom::Dom;
+use crate::{DomNode, ToTree, UnicodeString};
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// The tree of the Dom, with each text node replaced by its length and
+    /// only the names of the attributes of each container, so that nothing
+    /// the user wrote, including link and mention URLs, is in it.
+    pub fn to_tree_redacted(&self) -> S {
+        let mut tree = S::default();
+        redacted_tree(self.document_node(), vec![], &mut tree);
+        tree
+    }
+}
+
+fn redacted_tree<S: UnicodeString>(
+    node: &DomNode<S>,
+    continuous_positions: Vec<usize>,
+    tree: &mut S,
+) {
+    let description = match node {
+        DomNode::Container(container) => container_description(container),
+        DomNode::Text(text) => format!("text ({})", text.data().len()),
+        DomNode::LineBreak(line_break) => line_break.name().to_string(),
+        DomNode::Mention(mention) => match mention.kind() {
+            MentionNodeKind::MatrixUri { mention } => match mention.kind() {
+                MentionKind::Room(_) => "mention (room)".to_owned(),
+                MentionKind::User => "mention (user)".to_owned(),
+            },
+            MentionNodeKind::AtRoom => "mention (at-room)".to_owned(),
+        },
+    };
+    let depth = node.handle().raw().len();
+    tree.push(node.tree_line(
+        S::from(description.as_str()),
+        depth,
+        continuous_positions.clone(),
+    ));
+    if let DomNode::Container(container) = node {
+        let children = container.children();
+        for (i, child) in children.iter().enumerate() {
+            let mut new_positions = continuous_positions.clone();
+            if i < children.len() - 1 {
+                new_positions.push(depth);
+            }
+            redacted_tree(child, new_positions, tree);
+        }
+    }
+}
+
+/// The name of the container followed by the names of its attributes, if
+/// it has any.
+fn container_description<S: UnicodeString>(
+    container: &ContainerNode<S>,
+) -> String {
+    let mut description = container.name().to_string();
+    if let Some(attributes) = container.attributes() {
+        let names: Vec<String> = attributes
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if !names.is_empty() {
+            description.push_str(&format!(" [{}]", names.join(", ")));
+        }
+    }
+    description
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::dom::parser::parse;
+    use crate::dom::Dom;
+
+    fn dom(html: &str) -> Dom<Utf16String> {
+        parse(html).unwrap()
+    }
+
+    #[test]
+    fn text_is_replaced_by_its_length() {
+        assert_eq!(
+            dom("<p>hello <b>world</b></p><p>a</p>").to_tree_redacted(),
+            [
+                "",
+                "├>p",
+                "│ ├>text (6)",
+                "│ └>b",
+                "│   └>text (5)",
+                "└>p",
+                "  └>text (1)",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn urls_are_left_out() {
+        let tree = dom("<a href=\"https://secret.org\" title=\"t\">link</a> \
+            <a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a> \
+            <a href=\"https://matrix.to/#/#room:matrix.org\">Room</a> @room")
+        .to_tree_redacted()
+        .to_string();
+        assert_eq!(
+            tree,
+            [
+                "",
+                "├>a [title, href]",
+                "│ └>text (4)",
+                "├>text (1)",
+                "├>mention (user)",
+                "├>text (1)",
+                "├>mention (room)",
+                "├>text (1)",
+                "└>mention (at-room)",
+                "",
+            ]
+            .join("\n")
+        );
+        assert!(!tree.contains("secret"));
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Methods on Dom which split and join its blocks, for integrations which
+//! restructure the content themselves, e.g. to add their own kinds of
+//! block. Unlike the methods the editor uses internally, these check their
+//! arguments and return a [RestructureError] rather than panicking, and
+//! leave the Dom satisfying its invariants.
+
+use std::fmt;
+
+use crate::dom::nodes::dom_node::DomNodeKind;
+use crate::dom::{Dom, DomLocation};
+use crate::{DomHandle, DomNode, UnicodeString};
+
+/// Why a Dom could not be split or joined as asked.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RestructureError {
+    /// There is no node at the handle
+    InvalidHandle(DomHandle),
+    /// The node at the handle is not a container
+    NotAContainer(DomHandle),
+    /// The depth to split at is deeper than the node to split
+    InvalidDepth { handle: DomHandle, depth: usize },
+    /// The offset is past the end of the node, or of the Dom
+    InvalidOffset(usize),
+    /// There is no paragraph at the offset
+    NoParagraphAt(usize),
+    /// The paragraph at the offset is not followed by another paragraph
+    NoNextParagraph(usize),
+}
+
+impl fmt::Display for RestructureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidHandle(handle) => {
+                write!(f, "There is no node at {:?}", handle.raw())
+            }
+            Self::NotAContainer(handle) => {
+                write!(f, "The node at {:?} is not a container", handle.raw())
+            }
+            Self::InvalidDepth { handle, depth } => write!(
+                f,
+                "Can't split the node at {:?} at depth {depth}",
+                handle.raw()
+            ),
+            Self::InvalidOffset(offset) => {
+                write!(f, "Offset {offset} is out of range")
+            }
+            Self::NoParagraphAt(offset) => {
+                write!(f, "There is no paragraph at offset {offset}")
+            }
+            Self::NoNextParagraph(offset) => write!(
+                f,
+                "The paragraph at offset {offset} is not followed by another"
+            ),
+        }
+    }
+}
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Split the Dom at `offset` within the node at `from_handle`, leaving
+    /// everything before it in this Dom and returning the rest as a new Dom
+    /// rooted at the ancestor of the node at the given depth.
+    pub fn try_split_sub_tree_from(
+        &mut self,
+        from_handle: &DomHandle,
+        offset: usize,
+        depth: usize,
+    ) -> Result<Dom<S>, RestructureError> {
+        self.check_handle(from_handle)?;
+        if depth > from_handle.depth() {
+            return Err(RestructureError::InvalidDepth {
+                handle: from_handle.clone(),
+                depth,
+            });
+        }
+        if offset > self.lookup_node(from_handle).text_len() {
+            return Err(RestructureError::InvalidOffset(offset));
+        }
+        Ok(self.split_sub_tree_from(from_handle, offset, depth))
+    }
+
+    /// Join each pair of adjacent children of the container at the handle
+    /// which can be joined, e.g. two lists of the same type or two bold
+    /// nodes, along with any text nodes that end up next to each other.
+    pub fn try_join_nodes_in_container(
+        &mut self,
+        handle: &DomHandle,
+    ) -> Result<(), RestructureError> {
+        self.check_container(handle)?;
+        self.join_nodes_in_container(handle);
+        if let DomNode::Container(container) = self.lookup_node_mut(handle) {
+            container.merge_adjacent_text_nodes();
+        }
+        Ok(())
+    }
+
+    /// Split the paragraph containing `offset` in two, returning the handle
+    /// of the second paragraph, which holds the content after the offset.
+    pub fn split_paragraph_at(
+        &mut self,
+        offset: usize,
+    ) -> Result<DomHandle, RestructureError> {
+        let paragraph = self.paragraph_at(offset)?;
+        let handle = paragraph.node_handle.clone();
+        if paragraph.start_offset == 0 {
+            // Splitting before the first leaf would leave an empty paragraph
+            // behind, which the split removes, so add the new one instead.
+            self.insert_at(&handle, DomNode::new_paragraph(Vec::new()));
+            return Ok(handle.next_sibling());
+        }
+
+        let range = self.find_range(offset, offset);
+        let leaf = range
+            .leaves()
+            .filter(|leaf| handle.is_ancestor_of(&leaf.node_handle))
+            .find(|leaf| leaf.start_offset < leaf.length)
+            .cloned();
+        let children = match leaf {
+            Some(leaf) => self
+                .split_sub_tree_from(
+                    &leaf.node_handle,
+                    leaf.start_offset,
+                    handle.depth(),
+                )
+                .document_mut()
+                .remove_children(),
+            None => Vec::new(),
+        };
+        Ok(self.insert_at(
+            &handle.next_sibling(),
+            DomNode::new_paragraph(children),
+        ))
+    }
+
+    /// Join the paragraph containing `offset` with the paragraph which
+    /// follows it, returning the handle of the joined paragraph.
+    pub fn join_paragraphs_at(
+        &mut self,
+        offset: usize,
+    ) -> Result<DomHandle, RestructureError> {
+        let handle = self.paragraph_at(offset)?.node_handle;
+        let next_handle = handle.next_sibling();
+        if !self.contains(&next_handle)
+            || self.lookup_node(&next_handle).kind() != DomNodeKind::Paragraph
+        {
+            return Err(RestructureError::NoNextParagraph(offset));
+        }
+
+        let DomNode::Container(mut next) = self.remove(&next_handle) else {
+            unreachable!("Paragraphs are containers");
+        };
+        let DomNode::Container(paragraph) = self.lookup_node_mut(&handle)
+        else {
+            unreachable!("Paragraphs are containers");
+        };
+        paragraph.append_children(next.remove_children());
+        self.join_nodes_in_container(&handle);
+        if let DomNode::Container(paragraph) = self.lookup_node_mut(&handle) {
+            paragraph.merge_adjacent_text_nodes();
+        }
+        Ok(handle)
+    }
+
+    /// The location of the deepest paragraph containing the offset.
+    fn paragraph_at(
+        &self,
+        offset: usize,
+    ) -> Result<DomLocation, RestructureError> {
+        if offset > self.text_len() {
+            return Err(RestructureError::InvalidOffset(offset));
+        }
+        self.find_range(offset, offset)
+            .locations
+            .into_iter()
+            .filter(|loc| loc.kind == DomNodeKind::Paragraph)
+            .max_by_key(|loc| loc.node_handle.depth())
+            .ok_or(RestructureError::NoParagraphAt(offset))
+    }
+
+    fn check_handle(&self, handle: &DomHandle) -> Result<(), RestructureError> {
+        if self.contains(handle) {
+            Ok(())
+        } else {
+            Err(RestructureError::InvalidHandle(handle.clone()))
+        }
+    }
+
+    fn check_container(
+        &self,
+        handle: &DomHandle,
+    ) -> Result<(), RestructureError> {
+        self.check_handle(handle)?;
+        if self.lookup_node(handle).is_container_node() {
+            Ok(())
+        } else {
+            Err(RestructureError::NotAContainer(handle.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tests::testutils_composer_model::cm;
+    use crate::{DomHandle, ToHtml};
+
+    use super::RestructureError;
+
+    fn handle(raw: &[usize]) -> DomHandle {
+        DomHandle::from_raw(raw.to_vec())
+    }
+
+    #[test]
+    fn sub_trees_are_split_from_valid_handles() {
+        let mut dom = cm("<p>a<b>bcd|</b></p>").state.dom;
+        let right = dom.try_split_sub_tree_from(&handle(&[0, 1, 0]), 1, 1);
+        assert_eq!(right.unwrap().to_html(), "<p><b>cd</b></p>");
+        assert_eq!(dom.to_html(), "<p>a<b>b</b></p>");
+    }
+
+    #[test]
+    fn splitting_with_bad_arguments_is_an_error() {
+        let mut dom = cm("<p>abc|</p>").state.dom;
+        assert_eq!(
+            dom.try_split_sub_tree_from(&handle(&[1, 0]), 0, 0).err(),
+            Some(RestructureError::InvalidHandle(handle(&[1, 0])))
+        );
+        assert_eq!(
+            dom.try_split_sub_tree_from(&handle(&[0, 0]), 0, 3).err(),
+            Some(RestructureError::InvalidDepth {
+                handle: handle(&[0, 0]),
+                depth: 3
+            })
+        );
+        assert_eq!(
+            dom.try_split_sub_tree_from(&handle(&[0, 0]), 4, 0).err(),
+            Some(RestructureError::InvalidOffset(4))
+        );
+        assert_eq!(dom.to_html(), "<p>abc</p>");
+    }
+
+    #[test]
+    fn joining_nodes_merges_the_text_inside_them() {
+        let mut dom = cm("<p><b>a</b><b>b|</b></p>").state.dom;
+        dom.try_join_nodes_in_container(&handle(&[0])).unwrap();
+        assert_eq!(dom.to_html(), "<p><b>ab</b></p>");
+        assert_eq!(dom.lookup_container(&handle(&[0, 0])).children().len(), 1);
+        assert_eq!(
+            dom.try_join_nodes_in_container(&handle(&[0, 0, 0])),
+            Err(RestructureError::NotAContainer(handle(&[0, 0, 0])))
+        );
+    }
+
+    #[test]
+    fn paragraphs_are_split_at_an_offset() {
+        let mut dom = cm("<p>a<b>bc|</b></p><p>d</p>").state.dom;
+        assert_eq!(dom.split_paragraph_at(2), Ok(handle(&[1])));
+        assert_eq!(dom.to_html(), "<p>a<b>b</b></p><p><b>c</b></p><p>d</p>");
+    }
+
+    #[test]
+    fn paragraphs_are_split_at_their_edges() {
+        let mut dom = cm("<p>ab|</p>").state.dom;
+        assert_eq!(dom.split_paragraph_at(0), Ok(handle(&[1])));
+        assert_eq!(dom.split_paragraph_at(3), Ok(handle(&[2])));
+        assert_eq!(dom.to_html(), "<p>\u{a0}</p><p>ab</p><p>\u{a0}</p>");
+    }
+
+    #[test]
+    fn paragraphs_are_split_inside_lists() {
+        let mut dom = cm("<ul><li><p>ab|</p></li></ul><p>c</p>").state.dom;
+        assert_eq!(dom.split_paragraph_at(1), Ok(handle(&[0, 0, 1])));
+        assert_eq!(dom.to_html(), "<ul><li><p>a</p><p>b</p></li></ul><p>c</p>");
+    }
+
+    #[test]
+    fn paragraphs_are_joined_with_the_next() {
+        let mut dom = cm("<p>a<b>b</b></p><p><b>c|</b>d</p>").state.dom;
+        assert_eq!(dom.join_paragraphs_at(1), Ok(handle(&[0])));
+        assert_eq!(dom.to_html(), "<p>a<b>bc</b>d</p>");
+        assert_eq!(
+            dom.join_paragraphs_at(1),
+            Err(RestructureError::NoNextParagraph(1))
+        );
+    }
+
+    #[test]
+    fn there_must_be_a_paragraph_at_the_offset() {
+        let mut dom = cm("ab|").state.dom;
+        assert_eq!(
+            dom.split_paragraph_at(1),
+            Err(RestructureError::NoParagraphAt(1))
+        );
+        assert_eq!(
+            dom.join_paragraphs_at(5),
+            Err(RestructureError::InvalidOffset(5))
+        );
+    }
+}
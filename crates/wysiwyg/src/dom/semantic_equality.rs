@@ -0,0 +1,237 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Comparing Doms by what they mean rather than how they are written, so
+//! that tests don't break when e.g. the order of attributes changes or
+//! `<b>` is written as `<strong>`.
+
+use widestring::Utf16String;
+
+use crate::dom::nodes::container_node::ContainerNodeKind;
+use crate::dom::parser::parse;
+use crate::dom::{Dom, HtmlParseError};
+use crate::{DomNode, UnicodeString};
+
+/// Panics with a diff of the two trees unless both HTML strings parse to
+/// semantically equal Doms, as [Dom::semantically_equals] describes.
+///
+/// ```
+/// wysiwyg::assert_html_eq!(
+///     "<strong>a</strong><a title=\"t\" href=\"https://x.org\">b</a>",
+///     "<b>a</b><a href=\"https://x.org\" title=\"t\">b</a>",
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_html_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match $crate::semantic_html_diff(&$left, &$right) {
+            Ok(None) => {}
+            Ok(Some(diff)) => {
+                panic!("HTML is not semantically equal (-left +right):\n{diff}")
+            }
+            Err(e) => panic!("Failed to parse HTML: {:?}", e.parse_errors),
+        }
+    };
+}
+
+/// Parse both HTML strings, returning a diff of their trees if they are
+/// not semantically equal, or None if they are.
+pub fn semantic_html_diff(
+    left: &str,
+    right: &str,
+) -> Result<Option<String>, HtmlParseError> {
+    let left: Dom<Utf16String> = parse(left)?;
+    let right: Dom<Utf16String> = parse(right)?;
+    Ok(left.semantic_diff(&right))
+}
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// True if the two Doms have the same content, ignoring differences
+    /// that don't change its meaning: the order of attributes, which of the
+    /// equivalent tags was used for a node, e.g. `<em>` or `<i>`, and how
+    /// text is split between adjacent text nodes.
+    pub fn semantically_equals(&self, other: &Dom<S>) -> bool {
+        outline(self) == outline(other)
+    }
+
+    /// A readable diff of the two trees, with each line of this Dom's tree
+    /// which is not in the other's starting with `-` and each line of the
+    /// other's which is not in this one's with `+`, or None if the Doms are
+    /// semantically equal.
+    pub fn semantic_diff(&self, other: &Dom<S>) -> Option<String> {
+        let left = outline(self);
+        let right = outline(other);
+        (left != right).then(|| diff_lines(&left, &right))
+    }
+}
+
+/// One line describing each node of the Dom, indented by its depth, in a
+/// form which is the same for semantically equal Doms.
+fn outline<S: UnicodeString>(dom: &Dom<S>) -> Vec<String> {
+    let mut lines = Vec::new();
+    outline_node(dom.document_node(), 0, &mut lines);
+    lines
+}
+
+fn outline_node<S: UnicodeString>(
+    node: &DomNode<S>,
+    depth: usize,
+    lines: &mut Vec<String>,
+) {
+    let indent = "  ".repeat(depth);
+    match node {
+        DomNode::Container(container) => {
+            let description = match container.kind() {
+                ContainerNodeKind::Link(url) => format!("Link \"{url}\""),
+                kind => format!("{kind:?}"),
+            };
+            let attributes = container
+                .attributes()
+                .map_or(String::new(), |attrs| sorted_attributes(attrs));
+            lines.push(format!("{indent}{description}{attributes}"));
+            let mut text = String::new();
+            for child in container.children() {
+                if let DomNode::Text(t) = child {
+                    text.push_str(&t.data().to_string());
+                    continue;
+                }
+                push_text(&mut text, depth + 1, lines);
+                outline_node(child, depth + 1, lines);
+            }
+            push_text(&mut text, depth + 1, lines);
+        }
+        DomNode::Text(t) => {
+            let mut text = t.data().to_string();
+            push_text(&mut text, depth, lines);
+        }
+        DomNode::LineBreak(_) => lines.push(format!("{indent}LineBreak")),
+        DomNode::Mention(mention) => lines.push(format!(
+            "{indent}Mention {:?} \"{}\"{}",
+            mention.kind(),
+            mention.display_text(),
+            sorted_attributes(mention.attributes()),
+        )),
+    }
+}
+
+/// Add a line for the text, if there is any, emptying it.
+fn push_text(text: &mut String, depth: usize, lines: &mut Vec<String>) {
+    if !text.is_empty() {
+        lines.push(format!("{}{text:?}", "  ".repeat(depth)));
+        text.clear();
+    }
+}
+
+fn sorted_attributes<S: UnicodeString>(attributes: &[(S, S)]) -> String {
+    let mut attributes: Vec<String> = attributes
+        .iter()
+        .map(|(name, value)| format!(" {name}=\"{value}\""))
+        .collect();
+    attributes.sort();
+    attributes.concat()
+}
+
+/// A line by line diff, keeping the longest run of lines the two have in
+/// common.
+fn diff_lines(left: &[String], right: &[String]) -> String {
+    // common[i][j] is the length of the longest common subsequence of
+    // left[i..] and right[j..]
+    let mut common = vec![vec![0; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            common[i][j] = if left[i] == right[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        let (prefix, line) =
+            if i < left.len() && j < right.len() && left[i] == right[j] {
+                i += 1;
+                j += 1;
+                (' ', &left[i - 1])
+            } else if j == right.len()
+                || (i < left.len() && common[i + 1][j] >= common[i][j + 1])
+            {
+                i += 1;
+                ('-', &left[i - 1])
+            } else {
+                j += 1;
+                ('+', &right[j - 1])
+            };
+        diff.push_str(&format!("{prefix} {line}\n"));
+    }
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::dom::parser::parse;
+    use crate::dom::Dom;
+    use crate::semantic_html_diff;
+
+    fn dom(html: &str) -> Dom<Utf16String> {
+        parse(html).unwrap()
+    }
+
+    #[test]
+    fn tag_aliases_and_attribute_order_are_ignored() {
+        assert!(dom("<strong>a</strong><i>b</i><del>c</del>")
+            .semantically_equals(&dom("<b>a</b><em>b</em><s>c</s>")));
+        assert!(dom("<a style=\"s\" href=\"https://x.org\">a</a>")
+            .semantically_equals(&dom(
+                "<a href=\"https://x.org\" style=\"s\">a</a>"
+            )));
+    }
+
+    #[test]
+    fn different_content_is_not_equal() {
+        assert!(!dom("<b>a</b>").semantically_equals(&dom("<i>a</i>")));
+        assert!(!dom("<a href=\"https://x.org\">a</a>")
+            .semantically_equals(&dom("<a href=\"https://y.org\">a</a>")));
+        assert!(!dom("<p>a</p>").semantically_equals(&dom("<p>a</p><p></p>")));
+    }
+
+    #[test]
+    fn diff_shows_the_lines_that_differ() {
+        assert_eq!(
+            semantic_html_diff("<p>a<b>b</b></p>", "<p>a<i>b</i></p>"),
+            Ok(Some(
+                [
+                    "  Generic",
+                    "    Paragraph",
+                    "      \"a\"",
+                    "-     Formatting(Bold)",
+                    "+     Formatting(Italic)",
+                    "        \"b\"",
+                    "",
+                ]
+                .join("\n")
+            ))
+        );
+        assert_eq!(semantic_html_diff("<em>a</em>", "<i>a</i>"), Ok(None));
+    }
+
+    #[test]
+    fn the_macro_accepts_equivalent_html() {
+        assert_html_eq!("<ol><li>a</li></ol>", "<ol><li>a</li></ol>");
+    }
+
+    #[test]
+    #[should_panic(expected = "not semantically equal")]
+    fn the_macro_panics_with_a_diff() {
+        assert_html_eq!("<ol><li>a</li></ol>", "<ul><li>a</li></ul>");
+    }
+}
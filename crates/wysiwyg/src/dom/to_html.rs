@@ -4,12 +4,16 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
+use std::fmt;
+
 use crate::composer_model::example_format::SelectionWriter;
 
 use super::{
     nodes::dom_node::DomNodeKind, unicode_string::UnicodeStringExt,
     UnicodeString,
 };
+use super::{PlaceholderPolicy, WhitespacePolicy};
+use crate::InlineFormatType;
 
 pub trait ToHtml<S>
 where
@@ -27,20 +31,40 @@ where
         as_message: bool,
     );
 
+    /// Roughly how long the HTML will be, in code units, so that the buffer
+    /// it is written to can be allocated once up front.
+    fn html_len_hint(&self) -> usize {
+        0
+    }
+
     /// Convert to a clean HTML represention of the source object, suitable
     /// for sending as a message
     fn to_message_html(&self) -> S {
-        let mut buf = S::default();
-        self.fmt_html(&mut buf, None, &ToHtmlState::default(), true);
-        buf
+        self.to_html_with_state(&ToHtmlState::default(), true)
     }
 
     /// Convert to a literal HTML represention of the source object
     fn to_html(&self) -> S {
-        let mut buf = S::default();
-        self.fmt_html(&mut buf, None, &ToHtmlState::default(), false);
+        self.to_html_with_state(&ToHtmlState::default(), false)
+    }
+
+    /// Like [Self::to_html], or [Self::to_message_html] if `as_message` is
+    /// true, starting from the given state, e.g. to choose which tags bold
+    /// and italic text are written with.
+    fn to_html_with_state(&self, state: &ToHtmlState, as_message: bool) -> S {
+        let mut buf = S::with_capacity(self.html_len_hint());
+        self.fmt_html(&mut buf, None, state, as_message);
         buf
     }
+
+    /// Like [Self::to_html_with_state], but replaces the content of `buf`
+    /// instead of returning a new string, so that callers serializing
+    /// repeatedly can reuse its allocation.
+    fn write_html(&self, buf: &mut S, state: &ToHtmlState, as_message: bool) {
+        buf.clear();
+        buf.reserve(self.html_len_hint());
+        self.fmt_html(buf, None, state, as_message);
+    }
 }
 
 pub trait ToHtmlExt<S>: ToHtml<S>
@@ -52,6 +76,7 @@ where
         name: &S::Str,
         formatter: &mut S,
         attrs: &Option<Vec<(S, S)>>,
+        omit_style: bool,
     );
     fn fmt_tag_close(&self, name: &S::Str, formatter: &mut S);
 }
@@ -71,23 +96,67 @@ where
         name: &S::Str,
         formatter: &mut S,
         attrs: &Option<Vec<(S, S)>>,
+        omit_style: bool,
     ) {
         formatter.push('<');
         formatter.push(name);
-        if let Some(attrs) = attrs {
-            for attr in attrs {
-                let (attr_name, value) = attr;
-                formatter.push(' ');
-                formatter.push(&**attr_name);
-                formatter.push("=\"");
-                formatter.push(&**value);
-                formatter.push('"');
-            }
-        }
+        fmt_attributes(formatter, attrs.iter().flatten(), omit_style);
         formatter.push('>');
     }
 }
 
+/// Write ` name="value"`, escaping the characters in the value which could
+/// end the attribute or be taken for markup.
+pub(crate) fn fmt_attribute<S, T>(formatter: &mut S, name: T, value: T)
+where
+    S: UnicodeString + Extend<T>,
+    T: fmt::Display,
+{
+    formatter.push(' ');
+    formatter.push(name);
+    formatter.push("=\"");
+    let value = value.to_string();
+    formatter.push(&*html_escape::encode_double_quoted_attribute(&value));
+    formatter.push('"');
+}
+
+/// Attributes the editor writes for a node itself rather than keeping from
+/// parsed HTML, which come after any others, in this order.
+const GENERATED_ATTRIBUTES: [&str; 4] = [
+    "data-mention-type",
+    "href",
+    "contenteditable",
+    "data-block-id",
+];
+
+/// Write each of the attributes with [fmt_attribute], leaving out `style`
+/// if `omit_style` is true. They are written in a canonical order, sorted by
+/// name apart from the [GENERATED_ATTRIBUTES] which come last, so that the
+/// same content is always written the same way, whatever order its
+/// attributes were added in.
+pub(crate) fn fmt_attributes<'a, S>(
+    formatter: &mut S,
+    attrs: impl IntoIterator<Item = &'a (S, S)>,
+    omit_style: bool,
+) where
+    S: UnicodeString + 'a,
+{
+    let mut attrs: Vec<&(S, S)> = attrs
+        .into_iter()
+        .filter(|(name, _)| !(omit_style && **name == *"style"))
+        .collect();
+    let generated =
+        |name: &S| GENERATED_ATTRIBUTES.iter().position(|g| **name == **g);
+    attrs.sort_by(|(a, _), (b, _)| {
+        generated(a)
+            .cmp(&generated(b))
+            .then_with(|| (**a).as_ref().cmp((**b).as_ref()))
+    });
+    for (name, value) in attrs {
+        fmt_attribute(formatter, &**name, &**value);
+    }
+}
+
 /// State of the HTML generation at every `fmt_html` call, usually used to pass info from ancestor
 /// nodes to their descendants.
 #[derive(Clone, Default)]
@@ -95,4 +164,53 @@ pub struct ToHtmlState {
     pub is_inside_code_block: bool,
     pub prev_sibling: Option<DomNodeKind>,
     pub next_sibling: Option<DomNodeKind>,
+    pub formatting_tags: FormattingTags,
+    pub whitespace_policy: WhitespacePolicy,
+    pub placeholder_policy: PlaceholderPolicy,
+    /// Whether `style` attributes are left out of HTML for a message
+    pub strip_message_styles: bool,
+    /// Whether the text of links and mentions is wrapped in bidi isolates
+    /// in HTML for a message
+    pub isolate_message_bidi: bool,
+    /// The `data-block-id` to write on the top-level block being written,
+    /// which its descendants don't inherit
+    pub block_id: Option<u64>,
+}
+
+impl ToHtmlState {
+    /// Whether `style` attributes are left out of the HTML being written.
+    pub(crate) fn omits_style(&self, as_message: bool) -> bool {
+        as_message && self.strip_message_styles
+    }
+}
+
+/// Which tags bold and italic text are written with.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FormattingTags {
+    /// The tags each node was created with: `<strong>` and `<em>` for
+    /// formatting added in the composer, or whichever tags parsed HTML
+    /// used.
+    #[default]
+    AsCreated,
+    /// Always `<strong>` and `<em>`
+    Semantic,
+    /// Always `<b>` and `<i>`
+    Presentational,
+}
+
+impl FormattingTags {
+    /// The tag to write for the given format, or None to write the tag the
+    /// node was created with.
+    pub(crate) fn tag_for(
+        &self,
+        format: &InlineFormatType,
+    ) -> Option<&'static str> {
+        match (self, format) {
+            (Self::Semantic, InlineFormatType::Bold) => Some("strong"),
+            (Self::Semantic, InlineFormatType::Italic) => Some("em"),
+            (Self::Presentational, InlineFormatType::Bold) => Some("b"),
+            (Self::Presentational, InlineFormatType::Italic) => Some("i"),
+            _ => None,
+        }
+    }
 }
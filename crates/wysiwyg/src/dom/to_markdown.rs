@@ -53,6 +53,55 @@ where
 
         Ok(buffer)
     }
+
+    /// Like [Self::to_markdown], or [Self::to_message_markdown] if
+    /// `as_message` is true, with the given options, e.g. to choose how
+    /// text is escaped.
+    fn to_markdown_with_options(
+        &self,
+        options: &MarkdownOptions,
+        as_message: bool,
+    ) -> Result<S, MarkdownError<S>> {
+        let mut buffer = S::default();
+        self.fmt_markdown(&mut buffer, options, as_message)?;
+
+        Ok(buffer)
+    }
+}
+
+/// Which characters of text are escaped when writing Markdown, so that they
+/// are not read as Markdown syntax. Nothing is escaped by default, which
+/// suits bridges relaying the Markdown to systems which would show the
+/// escapes as they are.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MarkdownEscaping {
+    /// Escape `_`, so it is not read as emphasis
+    pub underscores: bool,
+    /// Escape `*`, so it is not read as emphasis or a list item
+    pub asterisks: bool,
+    /// Escape `#`, so it is not read as a heading
+    pub hashes: bool,
+    /// Escape `>`, so it is not read as a quote
+    pub angle_brackets: bool,
+    /// Write links whose text is their URL as `<url>` autolinks, instead
+    /// of `[url](<url>)`
+    pub autolink_bare_urls: bool,
+}
+
+/// How mentions are written in Markdown for a message. Markdown for the
+/// composer always keeps them as HTML, so that they become mentions again
+/// when it is parsed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MarkdownMentions {
+    /// A link to the mentioned user or room, as the Matrix spec recommends,
+    /// e.g. `[Alice](https://matrix.to/#/@alice:matrix.org)`
+    #[default]
+    Link,
+    /// The Matrix ID, e.g. `@alice:matrix.org`
+    MxId,
+    /// The text shown in the composer for users, e.g. `Alice`, or the ID
+    /// for rooms
+    DisplayText,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -62,6 +111,21 @@ pub struct MarkdownOptions {
 
 impl MarkdownOptions {
     pub const IGNORE_LINE_BREAK: Self = Self { bits: 0b0001 };
+    pub const ESCAPE_UNDERSCORES: Self = Self { bits: 0b0010 };
+    pub const ESCAPE_ASTERISKS: Self = Self { bits: 0b0100 };
+    pub const ESCAPE_HASHES: Self = Self { bits: 0b1000 };
+    pub const ESCAPE_ANGLE_BRACKETS: Self = Self { bits: 0b1_0000 };
+    pub const AUTOLINK_BARE_URLS: Self = Self { bits: 0b10_0000 };
+    pub const MENTIONS_AS_MX_ID: Self = Self { bits: 0b100_0000 };
+    pub const MENTIONS_AS_DISPLAY_TEXT: Self = Self { bits: 0b1000_0000 };
+
+    /// All the flags escaping characters of text.
+    pub const ESCAPE_TEXT: Self = Self {
+        bits: Self::ESCAPE_UNDERSCORES.bits
+            | Self::ESCAPE_ASTERISKS.bits
+            | Self::ESCAPE_HASHES.bits
+            | Self::ESCAPE_ANGLE_BRACKETS.bits,
+    };
 
     pub const fn empty() -> Self {
         Self { bits: 0 }
@@ -76,4 +140,56 @@ impl MarkdownOptions {
     pub fn insert(&mut self, other: Self) {
         self.bits |= other.bits;
     }
+
+    /// Removes the specified flags in-place.
+    pub fn remove(&mut self, other: Self) {
+        self.bits &= !other.bits;
+    }
+
+    /// Escape the characters of text which the flags ask for.
+    pub fn escape_text(&self, text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            let flag = match c {
+                '_' => Self::ESCAPE_UNDERSCORES,
+                '*' => Self::ESCAPE_ASTERISKS,
+                '#' => Self::ESCAPE_HASHES,
+                '>' => Self::ESCAPE_ANGLE_BRACKETS,
+                _ => Self::empty(),
+            };
+            if flag != Self::empty() && self.contains(flag) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+}
+
+impl From<&MarkdownEscaping> for MarkdownOptions {
+    fn from(escaping: &MarkdownEscaping) -> Self {
+        let mut options = Self::empty();
+        for (enabled, flag) in [
+            (escaping.underscores, Self::ESCAPE_UNDERSCORES),
+            (escaping.asterisks, Self::ESCAPE_ASTERISKS),
+            (escaping.hashes, Self::ESCAPE_HASHES),
+            (escaping.angle_brackets, Self::ESCAPE_ANGLE_BRACKETS),
+            (escaping.autolink_bare_urls, Self::AUTOLINK_BARE_URLS),
+        ] {
+            if enabled {
+                options.insert(flag);
+            }
+        }
+        options
+    }
+}
+
+impl From<MarkdownMentions> for MarkdownOptions {
+    fn from(mentions: MarkdownMentions) -> Self {
+        match mentions {
+            MarkdownMentions::Link => Self::empty(),
+            MarkdownMentions::MxId => Self::MENTIONS_AS_MX_ID,
+            MarkdownMentions::DisplayText => Self::MENTIONS_AS_DISPLAY_TEXT,
+        }
+    }
 }
@@ -0,0 +1,247 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::ops::Deref;
+
+use crate::dom::nodes::{ContainerNode, DomNode};
+use crate::dom::{Dom, DomHandle, UnicodeString};
+
+/// A transaction on a [Dom], in which it can be changed through several
+/// steps without being kept in a consistent state between them. Created by
+/// [Dom::transaction], and ended when dropped, which checks the invariants
+/// of the Dom again in builds which check them, so that a change which
+/// returns early still ends its transaction. The Dom can still be read
+/// through it.
+///
+/// It does not stop the Dom being changed outside a transaction, e.g.
+/// through [Dom::document_mut]: it is for changes which would otherwise
+/// start and end a transaction themselves.
+pub struct TransactionGuard<'a, S>
+where
+    S: UnicodeString,
+{
+    dom: &'a mut Dom<S>,
+}
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Start a transaction, checking the invariants of this Dom first in
+    /// builds which check them. See [TransactionGuard].
+    pub fn transaction(&mut self) -> TransactionGuard<'_, S> {
+        #[cfg(any(test, feature = "assert-invariants"))]
+        self.start_transaction();
+        TransactionGuard { dom: self }
+    }
+}
+
+#[cfg(any(test, feature = "assert-invariants"))]
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Asserts the DOM is in a good state and starts a transaction.
+    /// See [Dom::is_transaction_in_progress].
+    fn start_transaction(&mut self) {
+        if self.is_transaction_in_progress() {
+            panic!("Cannot start transaction as one is already in progress");
+        }
+        self.assert_invariants();
+        self.is_transaction_in_progress = true;
+    }
+
+    /// Ends the current transaction and asserts the DOM is still in a good
+    /// state.
+    fn end_transaction(&mut self) {
+        if !self.is_transaction_in_progress() {
+            panic!("Cannot end transaction as no transaction is in progress");
+        }
+        self.is_transaction_in_progress = false;
+        self.assert_invariants();
+    }
+
+    /// Ends the current transaction without checking the state of the DOM,
+    /// for a transaction which was interrupted by a panic.
+    fn abandon_transaction(&mut self) {
+        self.is_transaction_in_progress = false;
+    }
+}
+
+impl<S> TransactionGuard<'_, S>
+where
+    S: UnicodeString,
+{
+    /// See [Dom::parent_mut].
+    pub fn parent_mut(&mut self, handle: &DomHandle) -> &mut ContainerNode<S> {
+        self.dom.parent_mut(handle)
+    }
+
+    /// See [Dom::append].
+    pub fn append(
+        &mut self,
+        parent_handle: &DomHandle,
+        child: DomNode<S>,
+    ) -> DomHandle {
+        self.dom.append(parent_handle, child)
+    }
+
+    /// See [Dom::insert].
+    pub fn insert(
+        &mut self,
+        handle: &DomHandle,
+        nodes: Vec<DomNode<S>>,
+    ) -> Vec<DomHandle> {
+        self.dom.insert(handle, nodes)
+    }
+
+    /// See [Dom::replace].
+    pub fn replace(
+        &mut self,
+        handle: &DomHandle,
+        nodes: Vec<DomNode<S>>,
+    ) -> Vec<DomHandle> {
+        self.dom.replace(handle, nodes)
+    }
+
+    /// See [Dom::remove].
+    pub fn remove(&mut self, handle: &DomHandle) -> DomNode<S> {
+        self.dom.remove(handle)
+    }
+}
+
+impl<S> Deref for TransactionGuard<'_, S>
+where
+    S: UnicodeString,
+{
+    type Target = Dom<S>;
+
+    fn deref(&self) -> &Dom<S> {
+        self.dom
+    }
+}
+
+impl<S> Drop for TransactionGuard<'_, S>
+where
+    S: UnicodeString,
+{
+    fn drop(&mut self) {
+        // Checking the invariants while a panic in the transaction unwinds
+        // would abort instead of reporting it
+        #[cfg(any(test, feature = "assert-invariants"))]
+        if std::thread::panicking() {
+            self.dom.abandon_transaction();
+        } else {
+            self.dom.end_transaction();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::dom::nodes::{ContainerNode, DomNode, TextNode};
+    use crate::dom::Dom;
+    use crate::tests::testutils_composer_model::cm;
+    use crate::tests::testutils_conversion::utf16;
+    use crate::tests::testutils_dom::dom;
+    use crate::{DomHandle, ToHtml};
+
+    #[test]
+    fn changes_can_break_invariants_until_the_transaction_ends() {
+        let mut dom = cm("a|").state.dom;
+        {
+            let mut transaction = dom.transaction();
+            assert!(transaction.is_transaction_in_progress());
+            let handle = DomHandle::root().child_handle(1);
+            transaction.insert(&handle, vec![DomNode::new_text(utf16(""))]);
+            transaction.replace(&handle, vec![DomNode::new_text(utf16("b"))]);
+            transaction.remove(&handle);
+        }
+        assert!(!dom.is_transaction_in_progress());
+        assert_eq!(dom.to_html(), "a");
+    }
+
+    #[test]
+    #[should_panic(expected = "More than one generic container node found")]
+    fn invariants_are_checked_when_the_transaction_ends() {
+        let mut dom = cm("a|").state.dom;
+        let mut transaction = dom.transaction();
+        transaction.append(
+            &DomHandle::root(),
+            DomNode::<Utf16String>::Container(ContainerNode::default()),
+        );
+    }
+
+    #[test]
+    fn a_panic_in_a_transaction_is_reported_as_it_is() {
+        let mut dom = cm("a|").state.dom;
+        let result = std::panic::catch_unwind(move || {
+            let mut transaction = dom.transaction();
+            transaction
+                .append(&DomHandle::root(), DomNode::new_text(utf16("")));
+            panic!("In the transaction");
+        });
+        let message = result.unwrap_err();
+        assert_eq!(message.downcast_ref(), Some(&"In the transaction"));
+    }
+
+    #[test]
+    fn transaction_succeeds() {
+        let mut d = cm("|").state.dom;
+        d.start_transaction();
+        d.end_transaction();
+    }
+
+    #[test]
+    #[should_panic(expected = "More than one generic container node found")]
+    fn transaction_start_asserts() {
+        let mut bad_dom =
+            dom(vec![&DomNode::Container(ContainerNode::default())]);
+        bad_dom.start_transaction();
+    }
+
+    #[test]
+    #[should_panic(expected = "More than one generic container node found")]
+    fn transaction_end_asserts() {
+        let mut dom = dom(vec![]);
+        dom.start_transaction();
+        let container =
+            DomNode::<Utf16String>::Container(ContainerNode::default());
+        dom.insert(&DomHandle::root().child_handle(0), vec![container]);
+        dom.end_transaction();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot start transaction as one is already in progress"
+    )]
+    fn transaction_start_twice() {
+        let mut d = cm("|").state.dom;
+        d.start_transaction();
+        d.start_transaction();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot end transaction as no transaction is in progress"
+    )]
+    fn transaction_end_without_start() {
+        let mut d = cm("|").state.dom;
+        d.end_transaction();
+    }
+
+    #[test]
+    fn should_not_panic_if_transaction_in_progress() {
+        let mut dom = Dom::new(vec![]);
+        dom.start_transaction();
+        dom.insert(
+            &DomHandle::root().child_handle(0),
+            vec![DomNode::Text(TextNode::from(Utf16String::from("")))],
+        );
+        dom.assert_invariants();
+    }
+}
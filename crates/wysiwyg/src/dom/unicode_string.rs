@@ -5,6 +5,7 @@
 // Please see LICENSE in the repository root for full details.
 
 use std::fmt;
+use std::hash::Hash;
 use std::iter;
 use std::ops::{Deref, Index, Range, RangeFrom, RangeTo};
 use unicode_segmentation::UnicodeSegmentation;
@@ -21,6 +22,8 @@ pub trait UnicodeString:
     + fmt::Display
     + Default
     + PartialEq
+    + Hash
+    + SendIfParallel
     + AsRef<[Self::CodeUnit]>
     + for<'a> From<&'a str>
     + From<String>
@@ -34,15 +37,33 @@ pub trait UnicodeString:
     + Index<RangeFrom<usize>, Output = Self::Str>
     + Index<RangeTo<usize>, Output = Self::Str>
 {
-    type CodeUnit: Copy + From<u8> + PartialEq;
+    type CodeUnit: Copy + From<u8> + Ord;
     type Str: UnicodeStr<CodeUnit = Self::CodeUnit, Owned = Self> + ?Sized;
 
+    fn with_capacity(capacity: usize) -> Self;
+    fn reserve(&mut self, additional: usize);
+    fn clear(&mut self);
     fn insert(&mut self, idx: usize, s: &Self::Str);
     fn remove_at(&mut self, idx: usize) -> char;
     fn pop_first(&mut self) -> Option<char>;
     fn pop_last(&mut self) -> Option<char>;
 }
 
+/// `Send`, but only when the crate is built with the `rayon` feature, which
+/// post-processes parsed documents on several threads. Every type which
+/// meets it implements it, so strings never need to name it.
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+pub trait SendIfParallel: Send {}
+
+#[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+impl<T: Send + ?Sized> SendIfParallel for T {}
+
+#[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+pub trait SendIfParallel {}
+
+#[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+impl<T: ?Sized> SendIfParallel for T {}
+
 pub trait UnicodeStr:
     fmt::Display
     + fmt::Debug
@@ -54,7 +75,7 @@ pub trait UnicodeStr:
     + Index<RangeFrom<usize>, Output = Self>
     + Index<RangeTo<usize>, Output = Self>
 {
-    type CodeUnit: Copy + From<u8> + PartialEq;
+    type CodeUnit: Copy + From<u8> + Ord;
     type StringType: UnicodeString;
 
     // Should really be `-> Self::Chars<'a>`, but that requires GATs
@@ -71,6 +92,15 @@ impl UnicodeString for String {
     type CodeUnit = u8;
     type Str = str;
 
+    fn with_capacity(capacity: usize) -> Self {
+        String::with_capacity(capacity)
+    }
+    fn reserve(&mut self, additional: usize) {
+        String::reserve(self, additional)
+    }
+    fn clear(&mut self) {
+        String::clear(self)
+    }
     fn insert(&mut self, idx: usize, s: &Self::Str) {
         self.insert_str(idx, s);
     }
@@ -110,6 +140,15 @@ impl UnicodeString for Utf16String {
     type CodeUnit = u16;
     type Str = Utf16Str;
 
+    fn with_capacity(capacity: usize) -> Self {
+        Utf16String::with_capacity(capacity)
+    }
+    fn reserve(&mut self, additional: usize) {
+        Utf16String::reserve(self, additional)
+    }
+    fn clear(&mut self) {
+        Utf16String::clear(self)
+    }
     fn insert(&mut self, idx: usize, s: &Self::Str) {
         self.insert_utfstr(idx, s);
     }
@@ -149,6 +188,15 @@ impl UnicodeString for Utf32String {
     type CodeUnit = u32;
     type Str = Utf32Str;
 
+    fn with_capacity(capacity: usize) -> Self {
+        Utf32String::with_capacity(capacity)
+    }
+    fn reserve(&mut self, additional: usize) {
+        Utf32String::reserve(self, additional)
+    }
+    fn clear(&mut self) {
+        Utf32String::clear(self)
+    }
     fn insert(&mut self, idx: usize, s: &Self::Str) {
         self.insert_utfstr(idx, s);
     }
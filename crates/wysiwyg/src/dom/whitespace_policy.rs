@@ -0,0 +1,87 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::nbsp::NBSP;
+
+/// How whitespace in text outside code blocks is handled when parsing HTML
+/// and when writing it back out.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum WhitespacePolicy {
+    /// Newlines and the indentation around them are collapsed into a
+    /// single space when parsing, as browsers render them.
+    #[default]
+    Collapse,
+    /// Newlines become line breaks and indentation is kept when parsing,
+    /// so that pasted ASCII art or aligned text keeps its shape. Runs of
+    /// spaces are written as alternating `&nbsp;` and spaces, so that they
+    /// render at their full width but can still wrap.
+    Preserve,
+}
+
+/// Replace spaces in text with non-breaking ones where needed for them to
+/// render, alternating the two in runs of spaces. Spaces at the start or
+/// end of a line are always non-breaking.
+pub(crate) fn preserve_spaces(
+    text: &str,
+    at_line_start: bool,
+    at_line_end: bool,
+) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut preserved = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != ' ' {
+            preserved.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && chars[i] == ' ' {
+            i += 1;
+        }
+        let len = i - start;
+        let starts_line = start == 0 && at_line_start;
+        let ends_line = i == chars.len() && at_line_end;
+        if len == 1 && !starts_line && !ends_line {
+            preserved.push(' ');
+            continue;
+        }
+        for j in 0..len {
+            let is_last = j + 1 == len;
+            if j % 2 == 0 || (is_last && ends_line) {
+                preserved.push(NBSP);
+            } else {
+                preserved.push(' ');
+            }
+        }
+    }
+    preserved
+}
+
+#[cfg(test)]
+mod test {
+    use super::preserve_spaces;
+
+    #[test]
+    fn single_spaces_inside_a_line_are_kept() {
+        assert_eq!(preserve_spaces("a b c", true, true), "a b c");
+    }
+
+    #[test]
+    fn runs_of_spaces_alternate_with_non_breaking_spaces() {
+        assert_eq!(preserve_spaces("a  b", false, false), "a\u{A0} b");
+        assert_eq!(
+            preserve_spaces("a     b", false, false),
+            "a\u{A0} \u{A0} \u{A0}b"
+        );
+    }
+
+    #[test]
+    fn spaces_at_the_edges_of_lines_are_non_breaking() {
+        assert_eq!(preserve_spaces(" a", true, false), "\u{A0}a");
+        assert_eq!(preserve_spaces(" a", false, false), " a");
+        assert_eq!(preserve_spaces("a  ", false, true), "a\u{A0}\u{A0}");
+    }
+}
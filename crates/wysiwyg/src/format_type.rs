@@ -6,7 +6,12 @@
 
 use crate::{ComposerAction, UnicodeString};
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum InlineFormatType {
     Bold,
     Italic,
@@ -35,6 +40,20 @@ impl InlineFormatType {
             InlineFormatType::InlineCode => ComposerAction::InlineCode,
         }
     }
+
+    /// The format which the action toggles, or None if it isn't a format.
+    pub fn from_action(action: &ComposerAction) -> Option<Self> {
+        match action {
+            ComposerAction::Bold => Some(InlineFormatType::Bold),
+            ComposerAction::Italic => Some(InlineFormatType::Italic),
+            ComposerAction::StrikeThrough => {
+                Some(InlineFormatType::StrikeThrough)
+            }
+            ComposerAction::Underline => Some(InlineFormatType::Underline),
+            ComposerAction::InlineCode => Some(InlineFormatType::InlineCode),
+            _ => None,
+        }
+    }
 }
 
 impl<S: UnicodeString> From<S> for InlineFormatType {
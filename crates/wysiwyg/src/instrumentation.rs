@@ -0,0 +1,120 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Spans recorded with the `tracing` crate when the `tracing` feature is
+//! enabled, so that embedders can see how long parsing, serialization and
+//! each [crate::ComposerModel] operation take in production builds. Without
+//! the feature, entering a span does nothing.
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::span::EnteredSpan;
+
+/// Stands in for a span when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct EnteredSpan;
+
+/// Enter a debug level span with the given name and fields, which lasts
+/// until the returned guard is dropped. The fields are only evaluated if
+/// the `tracing` feature is enabled.
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        tracing::debug_span!($name $(, $field = $value)*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::instrumentation::EnteredSpan
+    };
+}
+
+pub(crate) use enter_span;
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_conversion::utf16;
+    use crate::ComposerModel;
+
+    /// Records the name of each span created, followed by the name of the
+    /// operation for operation spans.
+    #[derive(Clone, Default)]
+    struct SpanNames(Arc<Mutex<Vec<String>>>);
+
+    struct OperationName<'a>(&'a mut String);
+
+    impl Visit for OperationName<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "operation" {
+                self.0.push(':');
+                self.0.push_str(value);
+            }
+        }
+
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl Subscriber for SpanNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.0.lock().unwrap();
+            let mut name = span.metadata().name().to_owned();
+            span.record(&mut OperationName(&mut name));
+            names.push(name);
+            Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn span_names(f: impl FnOnce()) -> Vec<String> {
+        let subscriber = SpanNames::default();
+        tracing::subscriber::with_default(subscriber.clone(), f);
+        let names = subscriber.0.lock().unwrap().clone();
+        names
+    }
+
+    #[test]
+    fn operations_parsing_and_serialization_are_traced() {
+        let names = span_names(|| {
+            let mut model = ComposerModel::<Utf16String>::new();
+            model.set_content_from_html(&utf16("<b>a</b>")).unwrap();
+            model.replace_text(utf16("b"));
+            model.get_content_as_html();
+        });
+        assert!(names
+            .contains(&"composer_operation:set_content_from_html".to_owned()));
+        assert!(names.contains(&"parse_html".to_owned()));
+        assert!(names.contains(&"composer_operation:replace_text".to_owned()));
+        assert!(names.contains(&"find_range".to_owned()));
+        assert!(names.contains(&"to_html".to_owned()));
+    }
+
+    #[test]
+    fn markdown_is_traced() {
+        let names = span_names(|| {
+            let mut model = ComposerModel::<Utf16String>::new();
+            model.set_content_from_markdown(&utf16("**a**")).unwrap();
+            model.get_content_as_markdown();
+        });
+        assert!(names.contains(&"parse_markdown".to_owned()));
+        assert!(names.contains(&"to_markdown".to_owned()));
+    }
+}
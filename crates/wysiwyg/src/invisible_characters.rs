@@ -0,0 +1,181 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Characters which are invisible in pasted text, but which split words
+//! and mentions apart, so that e.g. moving by word stops in the middle of
+//! one and a pasted user ID is not recognised.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{DomNode, UnicodeString};
+
+const SOFT_HYPHEN: char = '\u{AD}';
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// How many invisible characters of each kind were removed from pasted
+/// content, so that clients can tell the user that what was pasted is not
+/// exactly what was copied.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StrippedCharacters {
+    /// Soft hyphens, which only show where a word is broken across lines
+    pub soft_hyphens: usize,
+    /// Zero-width spaces, word joiners and byte order marks, along with
+    /// zero-width joiners which don't join characters into one, as they do
+    /// in emoji such as 👩‍💻
+    pub zero_width: usize,
+    /// Left-to-right, right-to-left and Arabic letter marks
+    pub directional_marks: usize,
+}
+
+/// The text without the invisible characters in it, and which were removed,
+/// if any were.
+pub(crate) fn strip_text(text: &str) -> (String, Option<StrippedCharacters>) {
+    let mut stripped = StrippedCharacters::default();
+    let text = stripped.remove_from_text(text);
+    (text, stripped.any().then_some(stripped))
+}
+
+/// The nodes without the invisible characters in their text, leaving out
+/// any text which was nothing but them, and which were removed, if any were.
+pub(crate) fn strip_nodes<S: UnicodeString>(
+    nodes: Vec<DomNode<S>>,
+) -> (Vec<DomNode<S>>, Option<StrippedCharacters>) {
+    let mut stripped = StrippedCharacters::default();
+    let nodes = stripped.remove_from_nodes(nodes);
+    (nodes, stripped.any().then_some(stripped))
+}
+
+impl StrippedCharacters {
+    fn remove_from_text(&mut self, text: &str) -> String {
+        let mut stripped = String::with_capacity(text.len());
+        for grapheme in text.graphemes(true) {
+            let mut chars = grapheme.chars().peekable();
+            while let Some(c) = chars.next() {
+                let count = match c {
+                    SOFT_HYPHEN => &mut self.soft_hyphens,
+                    // A joiner which is followed by more of its grapheme
+                    // is joining characters into one
+                    ZERO_WIDTH_JOINER if chars.peek().is_some() => {
+                        stripped.push(c);
+                        continue;
+                    }
+                    ZERO_WIDTH_JOINER | '\u{200B}' | '\u{2060}'
+                    | '\u{FEFF}' => &mut self.zero_width,
+                    '\u{200E}' | '\u{200F}' | '\u{61C}' => {
+                        &mut self.directional_marks
+                    }
+                    _ => {
+                        stripped.push(c);
+                        continue;
+                    }
+                };
+                *count += 1;
+            }
+        }
+        stripped
+    }
+
+    fn remove_from_nodes<S: UnicodeString>(
+        &mut self,
+        nodes: Vec<DomNode<S>>,
+    ) -> Vec<DomNode<S>> {
+        nodes
+            .into_iter()
+            .filter_map(|node| self.remove_from_node(node))
+            .collect()
+    }
+
+    fn remove_from_node<S: UnicodeString>(
+        &mut self,
+        mut node: DomNode<S>,
+    ) -> Option<DomNode<S>> {
+        match &mut node {
+            DomNode::Container(container) => {
+                let children = container.remove_children();
+                container.append_children(self.remove_from_nodes(children));
+            }
+            DomNode::Text(text) => {
+                let data = text.data().to_string();
+                let stripped = self.remove_from_text(&data);
+                if stripped.is_empty() {
+                    return None;
+                }
+                if stripped.len() != data.len() {
+                    text.set_data(S::from(stripped.as_str()));
+                }
+            }
+            DomNode::LineBreak(_) | DomNode::Mention(_) => {}
+        }
+        Some(node)
+    }
+
+    fn any(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::cm;
+    use crate::ToHtml;
+
+    use super::{strip_nodes, strip_text, StrippedCharacters};
+
+    #[test]
+    fn invisible_characters_are_stripped_and_counted() {
+        assert_eq!(
+            strip_text("hy\u{AD}phen\u{200B} \u{200F}@alice\u{2060}:x.org"),
+            (
+                "hyphen @alice:x.org".to_owned(),
+                Some(StrippedCharacters {
+                    soft_hyphens: 1,
+                    zero_width: 2,
+                    directional_marks: 1,
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn joiners_in_emoji_are_kept() {
+        let text = "👩\u{200D}💻 and 🏳\u{FE0F}\u{200D}🌈";
+        assert_eq!(strip_text(text), (text.to_owned(), None));
+    }
+
+    #[test]
+    fn joiners_outside_emoji_are_stripped() {
+        let (text, stripped) = strip_text("a\u{200D}b\u{200D}");
+        assert_eq!(text, "ab");
+        assert_eq!(stripped.map(|s| s.zero_width), Some(2));
+    }
+
+    #[test]
+    fn visible_text_is_untouched() {
+        let text = "Grüße, مرحبا 👋🏽";
+        assert_eq!(strip_text(text), (text.to_owned(), None));
+    }
+
+    #[test]
+    fn text_of_nothing_but_invisible_characters_is_left_out() {
+        let nodes = cm("<p><b>a\u{AD}</b>\u{200B}</p><p>b|</p>")
+            .state
+            .dom
+            .into_container()
+            .take_children();
+        let (nodes, stripped) = strip_nodes::<Utf16String>(nodes);
+        let html: Vec<_> = nodes.iter().map(|n| n.to_html()).collect();
+        assert_eq!(html, ["<p><b>a</b></p>", "<p>b</p>"]);
+        assert_eq!(
+            stripped,
+            Some(StrippedCharacters {
+                soft_hyphens: 1,
+                zero_width: 1,
+                directional_marks: 0,
+            })
+        );
+    }
+}
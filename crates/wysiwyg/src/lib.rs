@@ -5,42 +5,105 @@
 // Please see LICENSE in the repository root for full details.
 
 mod action_state;
-mod char;
+mod block_ids;
+mod changed_handles;
 mod composer_action;
 mod composer_model;
+mod composer_model_config;
+mod composer_operation;
 mod composer_state;
 mod composer_update;
+mod decoration;
+mod deleted_mention;
 mod dom;
 mod format_type;
+mod instrumentation;
+mod invisible_characters;
 mod link_action;
 mod list_type;
 mod location;
 mod mentions_state;
 mod menu_action;
 mod menu_state;
+mod message_event_content;
+mod nbsp;
+mod offset_mapping;
+mod paste_truncation;
 mod pattern_key;
 mod suggestion_pattern;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 mod tests;
 mod text_update;
+mod tracked_content;
+mod update_timing;
 
 pub use crate::action_state::ActionState;
+pub use crate::changed_handles::ChangedHandles;
 pub use crate::composer_action::ComposerAction;
+pub use crate::composer_model::content_length::{ContentLength, TextLength};
+pub use crate::composer_model::delete_text::BlockBackspaceBehaviour;
+pub use crate::composer_model::delete_text::Direction;
+pub use crate::composer_model::extend_selection::Granularity;
+pub use crate::composer_model::generation::HandleLookupError;
+pub use crate::composer_model::range_query::{
+    RangeBlockKind, RangeLeafKind, RangeQuery,
+};
+pub use crate::composer_model::snapshots::SnapshotId;
+pub use crate::composer_model::undo_redo::HistoryEntry;
+pub use crate::composer_model::undo_redo::HistoryEntryKind;
+pub use crate::composer_model::words::Word;
 pub use crate::composer_model::ComposerModel;
+pub use crate::composer_model::Utf16ComposerModel;
+pub use crate::composer_model::Utf8ComposerModel;
+pub use crate::composer_model_config::ComposerModelConfig;
+pub use crate::composer_model_config::InlineCodeExitRule;
+pub use crate::composer_model_config::ListExitRule;
+pub use crate::composer_operation::ComposerOperation;
 pub use crate::composer_state::ComposerState;
 pub use crate::composer_update::ComposerUpdate;
+pub use crate::decoration::Decoration;
+pub use crate::decoration::DecorationKind;
+pub use crate::deleted_mention::DeletedMention;
+pub use crate::deleted_mention::DeletedMentionKind;
 pub use crate::dom::nodes::DomNode;
 pub use crate::dom::parser::parse;
+pub use crate::dom::parser::parse_from_source_with_options;
+pub use crate::dom::parser::parse_lenient;
+pub use crate::dom::parser::validate_message_html;
+pub use crate::dom::parser::DEFAULT_MAX_PARSE_DEPTH;
+#[cfg(feature = "source-map")]
+pub use crate::dom::parser::{
+    parse_with_source_map, SourceChange, SourceMap, SourcePosition,
+};
+pub use crate::dom::semantic_html_diff;
+pub use crate::dom::AtRoomDetection;
+pub use crate::dom::AttributePolicy;
 pub use crate::dom::DomCreationError;
 pub use crate::dom::DomHandle;
+pub use crate::dom::DomLocation;
+pub use crate::dom::FormattingTags;
+pub use crate::dom::HtmlIssue;
 pub use crate::dom::HtmlParseError;
 pub use crate::dom::HtmlSource;
+pub use crate::dom::LeafInRange;
 pub use crate::dom::MarkdownParseError;
+pub use crate::dom::PlaceholderPolicy;
+pub use crate::dom::Range;
+pub use crate::dom::RestructureError;
+pub use crate::dom::RoundTripIssue;
+pub use crate::dom::RoundTripIssueKind;
 pub use crate::dom::ToHtml;
 pub use crate::dom::ToRawText;
 pub use crate::dom::ToTree;
+pub use crate::dom::TransactionGuard;
 pub use crate::dom::UnicodeString;
-pub use crate::dom::{MarkdownError, ToMarkdown};
+pub use crate::dom::WhitespacePolicy;
+pub use crate::dom::{
+    MarkdownError, MarkdownEscaping, MarkdownMentions, ToMarkdown,
+};
 pub use crate::format_type::InlineFormatType;
+pub use crate::invisible_characters::StrippedCharacters;
 pub use crate::link_action::LinkAction;
 pub use crate::link_action::LinkActionUpdate;
 pub use crate::list_type::ListType;
@@ -50,8 +113,13 @@ pub use crate::menu_action::MenuAction;
 pub use crate::menu_action::MenuActionSuggestion;
 pub use crate::menu_state::MenuState;
 pub use crate::menu_state::MenuStateUpdate;
+pub use crate::message_event_content::MessageEventContent;
+pub use crate::offset_mapping::OffsetMapping;
+pub use crate::paste_truncation::PasteTruncation;
 pub use crate::pattern_key::PatternKey;
 pub use crate::suggestion_pattern::SuggestionPattern;
 pub use crate::text_update::ReplaceAll;
+pub use crate::text_update::ReplaceRange;
 pub use crate::text_update::Selection;
 pub use crate::text_update::TextUpdate;
+pub use crate::update_timing::UpdateTiming;
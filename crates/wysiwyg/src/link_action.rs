@@ -16,6 +16,9 @@ pub enum LinkActionUpdate<S: UnicodeString> {
 pub enum LinkAction<S: UnicodeString> {
     CreateWithText,
     Create,
-    Edit(S),
+    /// Edit the link with this URL and these attributes other than
+    /// `href`, which should be passed back when the link is updated so that
+    /// they are kept
+    Edit(S, Vec<(S, S)>),
     Disabled,
 }
@@ -4,9 +4,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use crate::UnicodeString;
+use crate::{ComposerAction, UnicodeString};
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum ListType {
     Ordered,
     Unordered,
@@ -19,6 +19,14 @@ impl ListType {
             ListType::Unordered => "ul",
         }
     }
+
+    /// The action which makes this kind of list.
+    pub(crate) fn action(&self) -> ComposerAction {
+        match self {
+            ListType::Ordered => ComposerAction::OrderedList,
+            ListType::Unordered => ComposerAction::UnorderedList,
+        }
+    }
 }
 
 impl<S: UnicodeString> From<S> for ListType {
@@ -6,6 +6,10 @@
 
 use std::ops;
 
+/// An offset into the text of a document, in code units of the string type
+/// the model uses: UTF-16 code units for a
+/// [Utf16ComposerModel](crate::Utf16ComposerModel) and bytes for a
+/// [Utf8ComposerModel](crate::Utf8ComposerModel).
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Default)]
 pub struct Location(usize);
 
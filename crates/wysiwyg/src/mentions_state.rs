@@ -12,4 +12,7 @@ pub struct MentionsState {
     pub room_ids: HashSet<String>,
     pub room_aliases: HashSet<String>,
     pub has_at_room_mention: bool,
+    /// Whether `@room` appears as plain text, e.g. in code or in pasted
+    /// text which was not turned into an @room mention
+    pub has_at_room_text: bool,
 }
@@ -11,6 +11,14 @@ pub enum MenuAction {
     Keep,
     None,
     Suggestion(SuggestionPattern),
+    /// A web address in the text at the cursor, e.g. so that the client can
+    /// offer a preview of the page it leads to. `start` and `end` are the
+    /// location of the address in the text.
+    UrlDetected {
+        url: String,
+        start: usize,
+        end: usize,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
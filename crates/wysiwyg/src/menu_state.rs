@@ -18,3 +18,50 @@ pub enum MenuState {
 pub struct MenuStateUpdate {
     pub action_states: HashMap<ComposerAction, ActionState>,
 }
+
+impl MenuStateUpdate {
+    /// The update from the action states `before` to those `after`: the
+    /// actions whose state changed, or which were not in `before`.
+    pub fn between(
+        before: &HashMap<ComposerAction, ActionState>,
+        after: &HashMap<ComposerAction, ActionState>,
+    ) -> Self {
+        let action_states = after
+            .iter()
+            .filter(|(action, state)| before.get(action) != Some(state))
+            .map(|(action, state)| (action.clone(), state.clone()))
+            .collect();
+        Self { action_states }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::MenuStateUpdate;
+    use crate::{ActionState, ComposerAction};
+
+    #[test]
+    fn only_changed_and_new_actions_are_in_the_update() {
+        let before = HashMap::from([
+            (ComposerAction::Bold, ActionState::Enabled),
+            (ComposerAction::Italic, ActionState::Enabled),
+        ]);
+        let after = HashMap::from([
+            (ComposerAction::Bold, ActionState::Reversed),
+            (ComposerAction::Italic, ActionState::Enabled),
+            (ComposerAction::Undo, ActionState::Disabled),
+        ]);
+        assert_eq!(
+            MenuStateUpdate::between(&before, &after).action_states,
+            HashMap::from([
+                (ComposerAction::Bold, ActionState::Reversed),
+                (ComposerAction::Undo, ActionState::Disabled),
+            ])
+        );
+        assert!(MenuStateUpdate::between(&after, &after)
+            .action_states
+            .is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+/// The format of `formatted_body` when it holds HTML.
+const HTML_FORMAT: &str = "org.matrix.custom.html";
+
+/// The fields of the content of an `m.room.message` event which the
+/// composer can load, passed to
+/// [crate::ComposerModel::set_content_from_event_content]. With the `serde`
+/// feature enabled, the content of an event can be deserialized into it
+/// directly, and any other fields are ignored.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageEventContent {
+    /// The plain text of the message
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub body: String,
+    /// The format of `formatted_body`, e.g. `org.matrix.custom.html`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub format: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub formatted_body: Option<String>,
+    /// The new content of the message, if the event is an edit
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "m.new_content",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub new_content: Option<Box<MessageEventContent>>,
+}
+
+impl MessageEventContent {
+    /// The HTML to load for this content: that of the new content if it is
+    /// an edit, its formatted body if that is HTML, or otherwise its body as
+    /// escaped text with a paragraph for each line.
+    pub fn to_html(&self) -> String {
+        if let Some(new_content) = &self.new_content {
+            return new_content.to_html();
+        }
+        match (&self.format, &self.formatted_body) {
+            (Some(format), Some(formatted_body)) if format == HTML_FORMAT => {
+                formatted_body.clone()
+            }
+            _ => plain_text_to_html(&self.body),
+        }
+    }
+}
+
+fn plain_text_to_html(text: &str) -> String {
+    if !text.contains('\n') {
+        return html_escape::encode_text(text).into_owned();
+    }
+    text.split('\n')
+        .map(|line| format!("<p>{}</p>", html_escape::encode_text(line)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn content(body: &str) -> MessageEventContent {
+        MessageEventContent {
+            body: body.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn html_formatted_bodies_are_used() {
+        let content = MessageEventContent {
+            format: Some(HTML_FORMAT.into()),
+            formatted_body: Some("<b>a</b>".into()),
+            ..content("**a**")
+        };
+        assert_eq!(content.to_html(), "<b>a</b>");
+    }
+
+    #[test]
+    fn bodies_are_used_for_other_formats() {
+        let content = MessageEventContent {
+            format: Some("org.example.other".into()),
+            formatted_body: Some("[b]a[/b]".into()),
+            ..content("a <b>")
+        };
+        assert_eq!(content.to_html(), "a &lt;b&gt;");
+    }
+
+    #[test]
+    fn each_line_of_a_body_is_a_paragraph() {
+        assert_eq!(content("a\n\nb").to_html(), "<p>a</p><p></p><p>b</p>");
+    }
+
+    #[test]
+    fn the_new_content_of_edits_is_used() {
+        let content = MessageEventContent {
+            new_content: Some(Box::new(content("new"))),
+            ..content("* new")
+        };
+        assert_eq!(content.to_html(), "new");
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Non-breaking spaces, which the editor writes in two cases: as the
+//! placeholder in empty blocks (see
+//! [PlaceholderPolicy](crate::PlaceholderPolicy)), and in place of spaces
+//! which would otherwise collapse when the HTML is rendered (see
+//! [WhitespacePolicy](crate::WhitespacePolicy)). Everything which writes or
+//! recognises one goes through here.
+
+/// A non-breaking space.
+pub(crate) const NBSP: char = '\u{A0}';
+
+/// [NBSP] as a string, for building text out of.
+pub(crate) const NBSP_STR: &str = "\u{A0}";
+
+/// The HTML entity for [NBSP].
+pub(crate) const NBSP_ENTITY: &str = "&nbsp;";
+
+/// Whether `text` is nothing but a placeholder, written either as the
+/// character or as its entity.
+pub(crate) fn is_placeholder(text: &str) -> bool {
+    text == NBSP_ENTITY || text.chars().eq([NBSP])
+}
+
+/// `text` with every non-breaking space written as its entity, so that it
+/// can be told apart from a space when read.
+pub(crate) fn nbsp_to_entity(text: &str) -> String {
+    text.replace(NBSP, NBSP_ENTITY)
+}
+
+/// `text` with every non-breaking space replaced by a space. Entities are
+/// left as they are, as this is for text rather than HTML.
+pub(crate) fn nbsp_to_space(text: &str) -> String {
+    text.replace(NBSP, " ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_placeholder, nbsp_to_entity, nbsp_to_space};
+
+    #[test]
+    fn placeholder_text_is_recognised() {
+        assert!(is_placeholder("\u{A0}"));
+        assert!(is_placeholder("&nbsp;"));
+        assert!(!is_placeholder("\u{A0}\u{A0}"));
+        assert!(!is_placeholder(" "));
+    }
+
+    #[test]
+    fn nbsps_are_written_as_entities() {
+        assert_eq!(nbsp_to_entity("a\u{A0} b\u{A0}"), "a&nbsp; b&nbsp;");
+    }
+
+    #[test]
+    fn nbsps_are_replaced_by_spaces() {
+        assert_eq!(nbsp_to_space("a\u{A0}&nbsp; b"), "a &nbsp; b");
+    }
+}
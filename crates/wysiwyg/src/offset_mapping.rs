@@ -0,0 +1,241 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+/// Describes how the offsets in a document moved during an update, so that
+/// ranges held outside the model (spelling errors, link previews, remote
+/// cursors) can be kept attached to the same text.
+///
+/// Every update replaces at most one contiguous run of the document: the
+/// code units from `start` to `old_end` were replaced with the ones from
+/// `start` to `new_end`. Offsets are in code units, like [crate::Location].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OffsetMapping {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl OffsetMapping {
+    /// A mapping for an update which didn't change the document.
+    pub fn identity() -> Self {
+        Self::default()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.old_end == self.start && self.new_end == self.start
+    }
+
+    /// Map an offset in the document before the update to the equivalent
+    /// offset after it. Offsets inside replaced text move to the start of
+    /// the replacement.
+    pub fn map_offset(&self, offset: usize) -> usize {
+        if offset <= self.start {
+            offset
+        } else if offset >= self.old_end {
+            offset - self.old_end + self.new_end
+        } else {
+            self.start
+        }
+    }
+
+    /// Map a range in the document before the update to the equivalent
+    /// range after it, or None if the text inside the range was changed,
+    /// meaning whatever was attached to it needs recomputing. Text inserted
+    /// exactly at either end of the range is not included in it.
+    pub fn map_range(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Option<(usize, usize)> {
+        let (start, end) = (start.min(end), start.max(end));
+        if start == end {
+            let offset = self.map_offset(start);
+            return Some((offset, offset));
+        }
+        let is_insertion = self.old_end == self.start;
+        let changed_inside = if is_insertion {
+            start < self.start && self.start < end
+        } else {
+            start < self.old_end && self.start < end
+        };
+        if changed_inside {
+            return None;
+        }
+        let new_start = if start >= self.old_end {
+            start - self.old_end + self.new_end
+        } else {
+            start
+        };
+        Some((new_start, self.map_offset(end)))
+    }
+
+    /// Find the single replaced run that turns `old` into `new`.
+    pub(crate) fn between<T: PartialEq>(old: &[T], new: &[T]) -> Self {
+        let prefix = old
+            .iter()
+            .zip(new.iter())
+            .take_while(|(o, n)| o == n)
+            .count();
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(max_suffix)
+            .take_while(|(o, n)| o == n)
+            .count();
+        Self {
+            start: prefix,
+            old_end: old.len() - suffix,
+            new_end: new.len() - suffix,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::cm;
+    use crate::ComposerModel;
+
+    use super::OffsetMapping;
+
+    #[test]
+    fn mapping_between_equal_texts_is_identity() {
+        let mapping = OffsetMapping::between(b"abc", b"abc");
+        assert!(mapping.is_identity());
+    }
+
+    #[test]
+    fn mapping_an_insertion() {
+        let mapping = OffsetMapping::between(b"abcd", b"abXYcd");
+        assert_eq!(
+            mapping,
+            OffsetMapping {
+                start: 2,
+                old_end: 2,
+                new_end: 4
+            }
+        );
+        assert_eq!(mapping.map_offset(1), 1);
+        assert_eq!(mapping.map_offset(2), 2);
+        assert_eq!(mapping.map_offset(3), 5);
+    }
+
+    #[test]
+    fn mapping_a_deletion() {
+        let mapping = OffsetMapping::between(b"abcdef", b"abf");
+        assert_eq!(mapping.map_offset(1), 1);
+        assert_eq!(mapping.map_offset(4), 2);
+        assert_eq!(mapping.map_offset(5), 2);
+        assert_eq!(mapping.map_offset(6), 3);
+    }
+
+    #[test]
+    fn mapping_repeated_characters_prefers_the_longest_prefix() {
+        let mapping = OffsetMapping::between(b"aa", b"aaa");
+        assert_eq!(
+            mapping,
+            OffsetMapping {
+                start: 2,
+                old_end: 2,
+                new_end: 3
+            }
+        );
+    }
+
+    #[test]
+    fn ranges_untouched_by_the_change_are_moved() {
+        let mapping = OffsetMapping::between(b"one two three", b"one 2 three");
+        assert_eq!(mapping.map_range(0, 3), Some((0, 3)));
+        assert_eq!(mapping.map_range(8, 13), Some((6, 11)));
+    }
+
+    #[test]
+    fn ranges_containing_the_change_are_dropped() {
+        let mapping = OffsetMapping::between(b"one two three", b"one 2 three");
+        assert_eq!(mapping.map_range(4, 7), None);
+        assert_eq!(mapping.map_range(0, 13), None);
+        let insertion = OffsetMapping::between(b"abcd", b"abXcd");
+        assert_eq!(insertion.map_range(0, 4), None);
+        assert_eq!(insertion.map_range(0, 2), Some((0, 2)));
+        assert_eq!(insertion.map_range(2, 4), Some((3, 5)));
+    }
+
+    #[test]
+    fn update_reports_mapping_for_typed_text() {
+        let mut model = cm("abc|def");
+        let update = model.replace_text(Utf16String::from("X"));
+        assert_eq!(update.offset_mapping.map_offset(5), 6);
+        assert_eq!(update.offset_mapping.map_range(2, 6), None);
+        assert_eq!(update.offset_mapping.map_range(3, 6), Some((4, 7)));
+        assert_eq!(update.offset_mapping.map_range(4, 6), Some((5, 7)));
+    }
+
+    #[test]
+    fn update_reports_mapping_for_undo() {
+        let mut model: ComposerModel<Utf16String> = cm("abc|");
+        model.replace_text(Utf16String::from("def"));
+        let update = model.undo();
+        assert_eq!(
+            update.offset_mapping,
+            OffsetMapping {
+                start: 3,
+                old_end: 6,
+                new_end: 3
+            }
+        );
+    }
+
+    #[test]
+    fn update_reports_mapping_for_the_whole_of_an_operation() {
+        let mut model = cm("{abc}| def");
+        let update = model.set_link_with_text(
+            Utf16String::from("https://matrix.org"),
+            Utf16String::from("Z"),
+            Vec::new(),
+        );
+        assert_eq!(update.offset_mapping.map_offset(7), 5);
+    }
+
+    #[test]
+    fn update_reports_mapping_for_truncation() {
+        let mut model = cm("hello world|");
+        let update = model.truncate_to(5);
+        assert_eq!(
+            update.offset_mapping,
+            OffsetMapping {
+                start: 5,
+                old_end: 11,
+                new_end: 5
+            }
+        );
+    }
+
+    #[test]
+    fn update_reports_mapping_for_filling_a_placeholder() {
+        let mut model = cm("|");
+        model.insert_template(Utf16String::from("{{name}}: {{reason}}"));
+        // "name" and "Alice" both end in "e"
+        let update = model.fill_placeholder(Utf16String::from("Alice"));
+        assert_eq!(
+            update.offset_mapping,
+            OffsetMapping {
+                start: 0,
+                old_end: 3,
+                new_end: 4
+            }
+        );
+    }
+
+    #[test]
+    fn selection_update_has_identity_mapping() {
+        let mut model = cm("abc|");
+        let update =
+            model.select(crate::Location::from(0), crate::Location::from(1));
+        assert!(update.offset_mapping.is_identity());
+    }
+}
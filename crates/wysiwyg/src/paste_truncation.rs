@@ -0,0 +1,147 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::dom::unicode_string::{UnicodeStr, UnicodeStrExt};
+use crate::{ComposerModelConfig, DomNode, UnicodeString};
+
+/// Which of the configured limits on pasted content a paste went over, so
+/// that clients can tell the user that only part of it was pasted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PasteTruncation {
+    /// The paste had more nodes than the maximum, so the nodes after them
+    /// were left out
+    pub too_many_nodes: bool,
+    /// The paste had more text than the maximum length, so the text after
+    /// it was left out
+    pub too_long: bool,
+    /// The paste had lists nested deeper than the maximum depth, which
+    /// were left out
+    pub lists_too_deep: bool,
+}
+
+/// Cuts pasted nodes down to the limits in the configuration, keeping
+/// everything up to the point where the node or length limit is reached.
+pub(crate) struct PasteLimiter {
+    nodes_left: Option<usize>,
+    length_left: Option<usize>,
+    max_list_depth: Option<usize>,
+    truncation: PasteTruncation,
+    /// True once the node or length limit is reached, after which no more
+    /// nodes are kept
+    is_full: bool,
+}
+
+impl PasteLimiter {
+    pub(crate) fn new(config: &ComposerModelConfig) -> Self {
+        Self {
+            nodes_left: config.max_paste_nodes,
+            length_left: config.max_paste_length,
+            max_list_depth: config.max_paste_list_depth,
+            truncation: PasteTruncation::default(),
+            is_full: false,
+        }
+    }
+
+    /// The nodes to paste, and which limits they went over, if any.
+    pub(crate) fn limit<S: UnicodeString>(
+        mut self,
+        nodes: Vec<DomNode<S>>,
+    ) -> (Vec<DomNode<S>>, Option<PasteTruncation>) {
+        let nodes = self.limit_nodes(nodes, 0);
+        let was_truncated = self.truncation != PasteTruncation::default();
+        (nodes, was_truncated.then_some(self.truncation))
+    }
+
+    fn limit_nodes<S: UnicodeString>(
+        &mut self,
+        nodes: Vec<DomNode<S>>,
+        list_depth: usize,
+    ) -> Vec<DomNode<S>> {
+        let mut kept = Vec::new();
+        for node in nodes {
+            if self.is_full {
+                break;
+            }
+            kept.extend(self.limit_node(node, list_depth));
+        }
+        kept
+    }
+
+    fn limit_node<S: UnicodeString>(
+        &mut self,
+        mut node: DomNode<S>,
+        list_depth: usize,
+    ) -> Option<DomNode<S>> {
+        let list_depth = list_depth + usize::from(node.is_list());
+        if self.max_list_depth.is_some_and(|max| list_depth > max) {
+            self.truncation.lists_too_deep = true;
+            return None;
+        }
+        match &mut self.nodes_left {
+            Some(0) => {
+                self.truncation.too_many_nodes = true;
+                self.is_full = true;
+                return None;
+            }
+            Some(nodes_left) => *nodes_left -= 1,
+            None => {}
+        }
+
+        let length = match &mut node {
+            DomNode::Container(container) => {
+                let children = container.remove_children();
+                if children.is_empty() {
+                    return Some(node);
+                }
+                let children = self.limit_nodes(children, list_depth);
+                if children.is_empty() {
+                    // Everything inside it was left out, so leave it out too
+                    return None;
+                }
+                container.append_children(children);
+                return Some(node);
+            }
+            DomNode::Text(text) => {
+                if let Some(length_left) = self.length_left {
+                    if text.data().len() > length_left {
+                        text.set_data(prefix_of_length::<S>(
+                            text.data(),
+                            length_left,
+                        ));
+                        self.truncation.too_long = true;
+                        self.is_full = true;
+                    }
+                }
+                text.data().len()
+            }
+            DomNode::LineBreak(_) | DomNode::Mention(_) => {
+                if self.length_left == Some(0) {
+                    self.truncation.too_long = true;
+                    self.is_full = true;
+                    return None;
+                }
+                node.text_len()
+            }
+        };
+        if let Some(length_left) = &mut self.length_left {
+            *length_left -= length;
+        }
+        (length > 0).then_some(node)
+    }
+}
+
+/// The longest start of the text, made of whole characters, which is no
+/// longer than the given length in code units.
+fn prefix_of_length<S: UnicodeString>(text: &S::Str, length: usize) -> S {
+    let mut end = 0;
+    for c in text.chars() {
+        let next = end + text.char_len(&c);
+        if next > length {
+            break;
+        }
+        end = next;
+    }
+    text[..end].to_owned()
+}
@@ -4,9 +4,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PatternKey {
     At,
     Hash,
@@ -16,24 +17,29 @@ pub enum PatternKey {
 }
 
 impl PatternKey {
-    pub(crate) fn is_static_pattern(&self) -> bool {
-        matches!(self, Self::At | Self::Hash | Self::Slash | Self::Colon)
+    /// The characters which start a suggestion unless the client changes
+    /// them, and the key each reports.
+    pub(crate) fn default_triggers() -> HashMap<char, Self> {
+        HashMap::from([
+            ('\u{0040}', Self::At),
+            ('\u{0023}', Self::Hash),
+            ('\u{002F}', Self::Slash),
+            ('\u{003A}', Self::Colon),
+        ])
     }
 
+    /// The key of the pattern the string is, and whether it starts with one
+    /// of the `triggers`, as opposed to being one of the
+    /// `custom_suggestion_patterns` as a whole.
     pub(crate) fn from_string_and_suggestions(
         string: String,
         custom_suggestion_patterns: &HashSet<String>,
-    ) -> Option<Self> {
+        triggers: &HashMap<char, Self>,
+    ) -> Option<(Self, bool)> {
         if custom_suggestion_patterns.contains(&string) {
-            return Some(Self::Custom(string));
+            return Some((Self::Custom(string), false));
         }
         let first_char = string.chars().nth(0)?;
-        match first_char {
-            '\u{0040}' => Some(Self::At),
-            '\u{0023}' => Some(Self::Hash),
-            '\u{002F}' => Some(Self::Slash),
-            '\u{003A}' => Some(Self::Colon),
-            _ => None,
-        }
+        triggers.get(&first_char).map(|key| (key.clone(), true))
     }
 }
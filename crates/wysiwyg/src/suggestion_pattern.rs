@@ -7,6 +7,7 @@
 use crate::PatternKey;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SuggestionPattern {
     pub key: PatternKey,
     pub text: String,
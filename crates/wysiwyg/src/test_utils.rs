@@ -0,0 +1,41 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Helpers for writing tests in the example format, where `|` marks the
+//! cursor and `{` and `}` the ends of the selection, e.g.
+//! `assert_eq!(tx(&cm("{a}|b")), "{a}|b")`. Available to other crates with
+//! the `test-utils` feature.
+
+use widestring::Utf16String;
+
+use crate::nbsp::{nbsp_to_space, NBSP_ENTITY};
+use crate::{ComposerModel, Location};
+
+/// Short wrapper around [ComposerModel::from_example_format].
+pub fn cm(text: &str) -> ComposerModel<Utf16String> {
+    ComposerModel::<Utf16String>::from_example_format(text)
+}
+
+/// Short wrapper around [ComposerModel::to_example_format].
+pub fn tx(model: &ComposerModel<Utf16String>) -> String {
+    model.to_example_format()
+}
+
+/// The selection from `start` to `end`, as returned by
+/// [ComposerModel::get_selection].
+pub fn sel(start: usize, end: usize) -> (Location, Location) {
+    (Location::from(start), Location::from(end))
+}
+
+/// Replace the non-breaking spaces written for whitespace the browser would
+/// otherwise collapse with plain spaces.
+pub fn restore_whitespace(text: &str) -> String {
+    nbsp_to_space(&text.replace(NBSP_ENTITY, " "))
+}
+
+/// [restore_whitespace] for a [Utf16String].
+pub fn restore_whitespace_u16(text: &Utf16String) -> Utf16String {
+    Utf16String::from(restore_whitespace(&text.to_string()))
+}
@@ -6,7 +6,10 @@
 
 #![cfg(test)]
 
+pub mod test_block_ids;
 pub mod test_characters;
+pub mod test_code_block;
+pub mod test_config;
 pub mod test_deleting;
 pub mod test_emoji_replacement;
 pub mod test_formatting;
@@ -19,6 +22,9 @@ pub mod test_menu_action;
 pub mod test_menu_state;
 pub mod test_paragraphs;
 pub mod test_remove_links;
+pub mod test_round_trip;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod test_round_trip_properties;
 pub mod test_selection;
 pub mod test_set_content;
 pub mod test_suggestions;
@@ -28,6 +34,8 @@ pub mod test_to_plain_text;
 pub mod test_to_raw_text;
 pub mod test_to_tree;
 pub mod test_undo_redo;
+pub mod test_utf8_model;
+pub mod test_validate_message_html;
 pub mod testutils_composer_model;
 pub mod testutils_conversion;
 pub mod testutils_dom;
@@ -0,0 +1,72 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use widestring::Utf16String;
+
+use crate::tests::testutils_composer_model::cm;
+use crate::tests::testutils_conversion::utf16;
+use crate::{ComposerModel, ComposerModelConfig, TextUpdate};
+
+fn with_block_ids(text: &str) -> ComposerModel<Utf16String> {
+    let mut model = cm(text);
+    model.set_config(ComposerModelConfig::default().with_block_ids(true));
+    model
+}
+
+fn update_html(model: &mut ComposerModel<Utf16String>, text: &str) -> String {
+    match model.replace_text(utf16(text)).text_update {
+        TextUpdate::ReplaceAll(replace_all) => {
+            replace_all.replacement_html.to_string()
+        }
+        _ => panic!("expected ReplaceAll"),
+    }
+}
+
+#[test]
+fn blocks_have_no_ids_by_default() {
+    let model = cm("<p>a</p><p>b|</p>");
+    assert_eq!(model.get_content_as_html(), "<p>a</p><p>b</p>");
+}
+
+#[test]
+fn top_level_blocks_have_ids() {
+    let model =
+        with_block_ids("<p>a</p><ul><li>b</li></ul><pre><code>c|</code></pre>");
+    assert_eq!(
+        model.get_content_as_html(),
+        "<p data-block-id=\"0\">a</p>\
+         <ul data-block-id=\"1\"><li>b</li></ul>\
+         <pre data-block-id=\"2\"><code>c</code></pre>"
+    );
+}
+
+#[test]
+fn edited_blocks_keep_their_ids() {
+    let mut model = with_block_ids("<p>a</p><p>b|</p><p>c</p>");
+    assert_eq!(
+        update_html(&mut model, "d"),
+        "<p data-block-id=\"0\">a</p>\
+         <p data-block-id=\"1\">bd</p>\
+         <p data-block-id=\"2\">c</p>"
+    );
+}
+
+#[test]
+fn new_blocks_get_new_ids() {
+    let mut model = with_block_ids("<p>a|</p><p>b</p>");
+    model.enter();
+    assert_eq!(
+        update_html(&mut model, "c"),
+        "<p data-block-id=\"0\">a</p>\
+         <p data-block-id=\"2\">c</p>\
+         <p data-block-id=\"1\">b</p>"
+    );
+}
+
+#[test]
+fn message_html_has_no_block_ids() {
+    let model = with_block_ids("<p>a|</p>");
+    assert_eq!(model.get_content_as_message_html(), "a");
+}
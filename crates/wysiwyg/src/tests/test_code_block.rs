@@ -0,0 +1,93 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::tests::testutils_composer_model::{cm, tx};
+
+/// Turn the selection into a code block and back, checking the html after
+/// each step.
+fn assert_round_trip(html: &str, in_code_block: &str, after: &str) {
+    let mut model = cm(html);
+    model.code_block();
+    assert_eq!(tx(&model), in_code_block);
+    model.code_block();
+    assert_eq!(tx(&model), after);
+}
+
+#[test]
+fn each_paragraph_becomes_a_line() {
+    assert_round_trip(
+        "<p>{a</p><p>b</p><p>c}|</p>",
+        "<pre><code>{a\nb\nc}|</code></pre>",
+        "<p>{a</p><p>b</p><p>c}|</p>",
+    );
+}
+
+#[test]
+fn empty_paragraphs_become_empty_lines() {
+    assert_round_trip(
+        "<p>{a</p><p></p><p>c}|</p>",
+        "<pre><code>{a\n\nc}|</code></pre>",
+        "<p>{a</p><p>&nbsp;</p><p>c}|</p>",
+    );
+}
+
+#[test]
+fn several_empty_paragraphs_are_kept() {
+    assert_round_trip(
+        "<p>{a</p><p></p><p></p><p>d}|</p>",
+        "<pre><code>{a\n\n\nd}|</code></pre>",
+        "<p>{a</p><p>&nbsp;</p><p>&nbsp;</p><p>d}|</p>",
+    );
+}
+
+#[test]
+fn partly_selected_paragraphs_are_included_whole() {
+    assert_round_trip(
+        "<p>x{a</p><p>b</p><p>c}|y</p>",
+        "<pre><code>x{a\nb\nc}|y</code></pre>",
+        "<p>x{a</p><p>b</p><p>c}|y</p>",
+    );
+}
+
+#[test]
+fn paragraphs_outside_the_selection_are_untouched() {
+    assert_round_trip(
+        "<p>a</p><p>{b</p><p></p><p>d}|</p><p>e</p>",
+        "<p>a</p><pre><code>{b\n\nd}|</code></pre><p>e</p>",
+        "<p>a</p><p>{b</p><p>&nbsp;</p><p>d}|</p><p>e</p>",
+    );
+}
+
+#[test]
+fn formatting_is_kept_on_each_line() {
+    assert_round_trip(
+        "<p>{a<b>b</b></p><p><i>c}|</i></p>",
+        "<pre><code>{a<b>b</b>\n<i>c}|</i></code></pre>",
+        "<p>{a<b>b</b></p><p><i>c}|</i></p>",
+    );
+}
+
+#[test]
+fn line_breaks_become_lines() {
+    assert_round_trip(
+        "<p>{a<br />b</p><p>c}|</p>",
+        "<pre><code>{a\nb\nc}|</code></pre>",
+        "<p>{a</p><p>b</p><p>c}|</p>",
+    );
+}
+
+#[test]
+fn quoted_paragraphs_and_list_items_become_lines() {
+    assert_round_trip(
+        "<p>{a</p><blockquote><p>b</p><p>c</p></blockquote><p>d}|</p>",
+        "<pre><code>{a\nb\nc\nd}|</code></pre>",
+        "<p>{a</p><p>b</p><p>c</p><p>d}|</p>",
+    );
+    assert_round_trip(
+        "<p>{a</p><ul><li>b</li><li>c</li></ul><p>d}|</p>",
+        "<pre><code>{a\nb\nc\nd}|</code></pre>",
+        "<p>{a</p><p>b</p><p>c</p><p>d}|</p>",
+    );
+}
@@ -0,0 +1,582 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::collections::HashSet;
+
+use widestring::Utf16String;
+
+use crate::tests::testutils_composer_model::{cm, tx};
+use crate::tests::testutils_conversion::utf16;
+use crate::{
+    ActionState, ComposerAction, ComposerModel, ComposerModelConfig,
+    FormattingTags, HtmlSource, ListExitRule, PatternKey, PlaceholderPolicy,
+    StrippedCharacters, ToRawText,
+};
+
+fn disallowing(actions: &[ComposerAction]) -> ComposerModelConfig {
+    ComposerModelConfig::default()
+        .with_disallowed_actions(HashSet::from_iter(actions.iter().cloned()))
+}
+
+fn with_config(
+    text: &str,
+    config: ComposerModelConfig,
+) -> ComposerModel<Utf16String> {
+    let mut model = cm(text);
+    model.set_config(config);
+    model
+}
+
+#[test]
+fn model_created_with_config_reports_it() {
+    let config = ComposerModelConfig::default().with_max_length(Some(10));
+    let model = ComposerModel::<Utf16String>::with_config(config.clone());
+    assert_eq!(model.config(), &config);
+}
+
+#[test]
+fn at_room_setters_update_the_config() {
+    let mut model = cm("|");
+    model.set_at_room_detection_in_quotes(false);
+    model.set_can_notify_room(false);
+    assert!(!model.config().at_room_detection_in_quotes());
+    assert!(!model.config().can_notify_room());
+    assert!(model.config().at_room_detection());
+}
+
+#[test]
+fn option_setters_update_the_config() {
+    let mut model = cm("|");
+    model.set_max_parse_depth(3);
+    model.set_formatting_tags(FormattingTags::Presentational);
+    model.set_strip_message_styles(true);
+    model.set_isolate_message_bidi(true);
+    model.set_placeholder_policy(PlaceholderPolicy::Never);
+    model.set_suggestion_trigger('!', Some(PatternKey::Custom("!".into())));
+    assert_eq!(model.config().max_parse_depth(), 3);
+    assert_eq!(
+        model.config().formatting_tags(),
+        FormattingTags::Presentational
+    );
+    assert!(model.config().strip_message_styles());
+    assert!(model.config().isolate_message_bidi());
+    assert_eq!(
+        model.config().placeholder_policy(),
+        PlaceholderPolicy::Never
+    );
+    assert_eq!(
+        model.config().suggestion_triggers().get(&'!'),
+        Some(&PatternKey::Custom("!".into()))
+    );
+}
+
+#[test]
+fn serialization_options_are_taken_from_the_config() {
+    let mut model = ComposerModel::<Utf16String>::with_config(
+        ComposerModelConfig::default()
+            .with_formatting_tags(FormattingTags::Presentational),
+    );
+    model
+        .set_content_from_html(&utf16("<strong>a</strong>"))
+        .unwrap();
+    assert_eq!(model.get_content_as_html(), "<b>a</b>");
+}
+
+#[test]
+fn placeholders_are_left_out_when_configured_never() {
+    let model = with_config(
+        "<p>a</p><p>|</p>",
+        ComposerModelConfig::default()
+            .with_placeholder_policy(PlaceholderPolicy::Never),
+    );
+    assert_eq!(model.get_content_as_html(), "<p>a</p><p></p>");
+}
+
+#[test]
+fn link_text_is_isolated_in_message_html_when_configured() {
+    let model = with_config(
+        "<a href=\"https://x.org\">שלום</a>|",
+        ComposerModelConfig::default().with_isolate_message_bidi(true),
+    );
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<a href=\"https://x.org\">\u{2068}שלום\u{2069}</a>"
+    );
+}
+
+#[test]
+fn at_room_is_kept_as_text_when_configured_off() {
+    let mut model = with_config(
+        "|",
+        ComposerModelConfig::default().with_at_room_detection(false),
+    );
+    model.set_content_from_html(&utf16("Hi @room")).unwrap();
+    assert_eq!(tx(&model), "Hi @room|");
+}
+
+#[test]
+fn enter_in_empty_list_item_leaves_the_list_by_default() {
+    let mut model = cm("<ul><li>a</li><li>|</li></ul>");
+    model.enter();
+    assert_eq!(tx(&model), "<ul><li>a</li></ul><p>&nbsp;|</p>");
+}
+
+#[test]
+fn enter_in_empty_list_item_adds_an_item_if_lists_are_never_left() {
+    let mut model = with_config(
+        "<ul><li>a</li><li>|</li></ul>",
+        ComposerModelConfig::default().with_list_exit_rule(ListExitRule::Never),
+    );
+    model.enter();
+    assert_eq!(tx(&model), "<ul><li>a</li><li></li><li>|</li></ul>");
+}
+
+#[test]
+fn typing_past_max_length_is_cut_short() {
+    let mut model = with_config(
+        "abc|",
+        ComposerModelConfig::default().with_max_length(Some(5)),
+    );
+    model.replace_text(utf16("defg"));
+    assert_eq!(tx(&model), "abcde|");
+    model.replace_text(utf16("h"));
+    assert_eq!(tx(&model), "abcde|");
+}
+
+#[test]
+fn replacing_a_selection_can_reach_max_length() {
+    let mut model = with_config(
+        "a{bc}|",
+        ComposerModelConfig::default().with_max_length(Some(3)),
+    );
+    model.replace_text(utf16("xyz"));
+    assert_eq!(tx(&model), "axy|");
+}
+
+#[test]
+fn max_length_does_not_split_surrogate_pairs() {
+    let mut model = with_config(
+        "a|",
+        ComposerModelConfig::default().with_max_length(Some(2)),
+    );
+    model.replace_text(utf16("😀"));
+    assert_eq!(tx(&model), "a|");
+}
+
+#[test]
+fn typing_a_space_after_a_url_links_it_with_auto_link() {
+    let mut model = with_config(
+        "See https://matrix.org|",
+        ComposerModelConfig::default().with_auto_link(true),
+    );
+    model.replace_text(utf16(" "));
+    assert_eq!(
+        tx(&model),
+        "See <a href=\"https://matrix.org\">https://matrix.org</a>&nbsp;|"
+    );
+}
+
+#[test]
+fn urls_are_not_linked_without_auto_link() {
+    let mut model = cm("See https://matrix.org|");
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "See https://matrix.org&nbsp;|");
+}
+
+#[test]
+fn auto_link_leaves_out_disallowed_schemes() {
+    let mut model = with_config(
+        "http://matrix.org|",
+        ComposerModelConfig::default()
+            .with_auto_link(true)
+            .with_allowed_link_schemes(Some(vec!["https".into()])),
+    );
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "http://matrix.org&nbsp;|");
+}
+
+fn linking_pasted_urls() -> ComposerModelConfig {
+    ComposerModelConfig::default().with_link_pasted_urls(true)
+}
+
+#[test]
+fn pasting_a_url_over_a_selection_links_it() {
+    let mut model = with_config("See {the docs}|", linking_pasted_urls());
+    model.paste_text(utf16("https://matrix.org/docs"));
+    assert_eq!(
+        tx(&model),
+        "See <a href=\"https://matrix.org/docs\">{the docs}|</a>"
+    );
+}
+
+#[test]
+fn pasting_a_url_at_the_cursor_inserts_a_link() {
+    let mut model = with_config("See |.", linking_pasted_urls());
+    model.paste_text(utf16(" https://matrix.org\n"));
+    assert_eq!(
+        tx(&model),
+        "See <a href=\"https://matrix.org\">https://matrix.org|</a>."
+    );
+    model.undo();
+    assert_eq!(tx(&model), "See |.");
+}
+
+#[test]
+fn pasting_text_which_is_not_only_a_url_inserts_it() {
+    let mut model = with_config("{a}|", linking_pasted_urls());
+    model.paste_text(utf16("see https://matrix.org"));
+    assert_eq!(tx(&model), "see https://matrix.org|");
+}
+
+#[test]
+fn pasted_urls_are_not_linked_by_default_or_in_code() {
+    let mut model = cm("{a}|");
+    model.paste_text(utf16("https://matrix.org"));
+    assert_eq!(tx(&model), "https://matrix.org|");
+
+    let mut model = with_config("<code>{a}|</code>", linking_pasted_urls());
+    model.paste_text(utf16("https://matrix.org"));
+    assert_eq!(tx(&model), "<code>https://matrix.org|</code>");
+}
+
+#[test]
+fn pasted_urls_with_disallowed_schemes_are_not_linked() {
+    let mut model = with_config(
+        "|",
+        linking_pasted_urls()
+            .with_allowed_link_schemes(Some(vec!["https".into()])),
+    );
+    model.paste_text(utf16("http://matrix.org"));
+    assert_eq!(tx(&model), "http://matrix.org|");
+}
+
+#[test]
+fn dash_and_space_make_a_bulleted_list_with_markdown_shortcuts() {
+    let mut model = with_config(
+        "-|",
+        ComposerModelConfig::default().with_markdown_shortcuts(true),
+    );
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "<ul><li>|</li></ul>");
+}
+
+#[test]
+fn number_and_space_make_a_numbered_list_with_markdown_shortcuts() {
+    let mut model = with_config(
+        "<p>a</p><p>1.|</p>",
+        ComposerModelConfig::default().with_markdown_shortcuts(true),
+    );
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "<p>a</p><ol><li>|</li></ol>");
+}
+
+#[test]
+fn chevron_and_space_make_a_quote_with_markdown_shortcuts() {
+    let mut model = with_config(
+        ">|",
+        ComposerModelConfig::default().with_markdown_shortcuts(true),
+    );
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "<blockquote><p>&nbsp;|</p></blockquote>");
+}
+
+#[test]
+fn shortcuts_are_typed_as_text_without_markdown_shortcuts() {
+    let mut model = cm("-|");
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "-&nbsp;|");
+}
+
+#[test]
+fn shortcuts_are_typed_as_text_inside_lists() {
+    let mut model = with_config(
+        "<ul><li>-|</li></ul>",
+        ComposerModelConfig::default().with_markdown_shortcuts(true),
+    );
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "<ul><li>-&nbsp;|</li></ul>");
+}
+
+#[test]
+fn pasted_plain_text_lists_become_lists_with_plain_text_lists() {
+    let mut model = with_config(
+        "|",
+        ComposerModelConfig::default().with_plain_text_lists(true),
+    );
+    model.replace_text(utf16("Shopping:\n- eggs\n* milk\n1. a\n2. b\n"));
+    assert_eq!(
+        tx(&model),
+        "<p>Shopping:</p><ul><li>eggs</li><li>milk</li></ul>\
+         <ol><li>a</li><li>b|</li></ol>"
+    );
+}
+
+#[test]
+fn pasted_plain_text_lists_are_inserted_at_the_selection() {
+    let mut model = with_config(
+        "<p>a</p><p>{b}|</p>",
+        ComposerModelConfig::default().with_plain_text_lists(true),
+    );
+    model.replace_text(utf16("- x\n- y"));
+    assert_eq!(tx(&model), "<p>a</p><ul><li>x</li><li>y|</li></ul>");
+}
+
+#[test]
+fn pasted_plain_text_lists_stay_text_without_plain_text_lists() {
+    let mut model = cm("|");
+    model.replace_text(utf16("- a\n- b"));
+    assert_eq!(tx(&model), "<p>- a</p><p>- b|</p>");
+}
+
+#[test]
+fn pasted_plain_text_without_list_items_is_inserted_as_text() {
+    let mut model = with_config(
+        "|",
+        ComposerModelConfig::default().with_plain_text_lists(true),
+    );
+    model.replace_text(utf16("-a\n1 b"));
+    assert_eq!(tx(&model), "<p>-a</p><p>1 b|</p>");
+}
+
+#[test]
+fn pasted_plain_text_lists_of_disallowed_kinds_stay_text() {
+    let mut model = with_config(
+        "|",
+        ComposerModelConfig::default()
+            .with_plain_text_lists(true)
+            .with_disallowed_actions(HashSet::from([
+                ComposerAction::OrderedList,
+            ])),
+    );
+    model.replace_text(utf16("1. a\n- b"));
+    assert_eq!(tx(&model), "<p>1. a</p><ul><li>b|</li></ul>");
+}
+
+#[test]
+fn links_with_disallowed_schemes_are_not_set() {
+    let mut model = with_config(
+        "{text}|",
+        ComposerModelConfig::default()
+            .with_allowed_link_schemes(Some(vec!["https".into()])),
+    );
+    model.set_link(utf16("javascript:alert(1)"), vec![]);
+    assert_eq!(tx(&model), "{text}|");
+    model.set_link(utf16("matrix.org"), vec![]);
+    assert_eq!(tx(&model), "<a href=\"https://matrix.org\">{text}|</a>");
+}
+
+#[test]
+fn disallowed_actions_are_reported_disabled() {
+    let mut model =
+        with_config("a|", disallowing(&[ComposerAction::Underline]));
+    model.select(0.into(), 1.into());
+    assert_eq!(
+        model.action_states().get(&ComposerAction::Underline),
+        Some(&ActionState::Disabled)
+    );
+    assert_eq!(
+        model.action_states().get(&ComposerAction::Bold),
+        Some(&ActionState::Enabled)
+    );
+}
+
+#[test]
+fn disallowed_actions_do_nothing() {
+    let mut model = with_config(
+        "{abc}|",
+        disallowing(&[
+            ComposerAction::Underline,
+            ComposerAction::Quote,
+            ComposerAction::Link,
+        ]),
+    );
+    model.underline();
+    model.quote();
+    model.set_link(utf16("https://matrix.org"), vec![]);
+    assert_eq!(tx(&model), "{abc}|");
+    model.bold();
+    assert_eq!(tx(&model), "<strong>{abc}|</strong>");
+}
+
+#[test]
+fn disallowed_formatting_is_removed_from_parsed_html() {
+    let mut model = with_config(
+        "|",
+        disallowing(&[ComposerAction::Underline, ComposerAction::Link]),
+    );
+    model
+        .set_content_from_html(&utf16(
+            "<u>a</u><b>b</b><a href=\"https://matrix.org\">c</a>",
+        ))
+        .unwrap();
+    assert_eq!(tx(&model), "a<b>b</b>c|");
+}
+
+#[test]
+fn disallowed_blocks_are_replaced_by_their_content() {
+    let mut model = with_config(
+        "|",
+        disallowing(&[ComposerAction::Quote, ComposerAction::CodeBlock]),
+    );
+    model
+        .set_content_from_html(&utf16(
+            "<blockquote><p>a</p></blockquote><pre><code>b</code></pre>",
+        ))
+        .unwrap();
+    assert_eq!(tx(&model), "<p>a</p><p>b|</p>");
+}
+
+#[test]
+fn disallowed_list_type_becomes_the_other_one() {
+    let mut model =
+        with_config("|", disallowing(&[ComposerAction::OrderedList]));
+    model
+        .set_content_from_html(&utf16("<ol><li>a</li></ol>"))
+        .unwrap();
+    assert_eq!(tx(&model), "<ul><li>a|</li></ul>");
+}
+
+#[test]
+fn lists_become_paragraphs_when_both_types_are_disallowed() {
+    let mut model = with_config(
+        "|",
+        disallowing(&[
+            ComposerAction::OrderedList,
+            ComposerAction::UnorderedList,
+        ]),
+    );
+    model
+        .set_content_from_html(&utf16("<ol><li>a</li><li>b</li></ol>"))
+        .unwrap();
+    assert_eq!(tx(&model), "<p>a</p><p>b|</p>");
+}
+
+#[test]
+fn pasted_html_has_disallowed_formatting_removed() {
+    let mut model = with_config("|", disallowing(&[ComposerAction::Italic]));
+    model.replace_html(
+        utf16("<em>a</em><strong>b</strong>"),
+        crate::HtmlSource::UnknownExternal,
+    );
+    assert_eq!(tx(&model), "a|<strong>b</strong>");
+}
+
+#[test]
+fn markdown_shortcuts_skip_disallowed_actions() {
+    let mut model = with_config(
+        "-|",
+        disallowing(&[ComposerAction::UnorderedList])
+            .with_markdown_shortcuts(true),
+    );
+    model.replace_text(utf16(" "));
+    assert_eq!(tx(&model), "-&nbsp;|");
+}
+
+#[test]
+fn typed_nbsps_are_kept_by_default() {
+    let mut model = cm("a|b");
+    model.replace_text(utf16("\u{A0}"));
+    assert_eq!(model.state.dom.to_raw_text(), utf16("a\u{A0}b"));
+}
+
+#[test]
+fn typed_nbsps_become_spaces_with_normalize_typed_nbsp() {
+    let mut model = with_config(
+        "a|b",
+        ComposerModelConfig::default().with_normalize_typed_nbsp(true),
+    );
+    model.replace_text(utf16("c\u{A0}d\u{A0}"));
+    assert_eq!(model.state.dom.to_raw_text(), utf16("ac d b"));
+}
+
+#[test]
+fn typed_nbsps_make_markdown_shortcuts_with_normalize_typed_nbsp() {
+    let mut model = with_config(
+        "-|",
+        ComposerModelConfig::default()
+            .with_normalize_typed_nbsp(true)
+            .with_markdown_shortcuts(true),
+    );
+    model.replace_text(utf16("\u{A0}"));
+    assert_eq!(tx(&model), "<ul><li>|</li></ul>");
+}
+
+#[test]
+fn typed_nbsps_are_kept_in_code_with_normalize_typed_nbsp() {
+    let config = ComposerModelConfig::default().with_normalize_typed_nbsp(true);
+    let mut model = with_config("<code>a|b</code>", config.clone());
+    model.replace_text(utf16("\u{A0}"));
+    assert_eq!(model.state.dom.to_raw_text(), utf16("a\u{A0}b"));
+
+    let mut model = with_config("<pre><code>a|b</code></pre>", config.clone());
+    model.replace_text(utf16("\u{A0}"));
+    assert_eq!(model.state.dom.to_raw_text(), utf16("a\u{A0}b"));
+
+    let mut model = with_config("a|", config);
+    model.inline_code();
+    model.replace_text(utf16("\u{A0}b"));
+    assert_eq!(model.state.dom.to_raw_text(), utf16("a\u{A0}b"));
+}
+
+#[test]
+fn pasted_invisible_characters_are_kept_by_default() {
+    let mut model = cm("|");
+    let update = model.paste_text(utf16("a\u{AD}b"));
+    assert_eq!(update.stripped_characters, None);
+    assert_eq!(model.state.dom.to_raw_text(), utf16("a\u{AD}b"));
+}
+
+#[test]
+fn pasted_text_has_invisible_characters_stripped() {
+    let mut model = with_config(
+        "|",
+        ComposerModelConfig::default().with_strip_invisible_characters(true),
+    );
+    let update = model.paste_text(utf16("\u{200E}hy\u{AD}phen 👩\u{200D}💻"));
+    assert_eq!(
+        update.stripped_characters,
+        Some(StrippedCharacters {
+            soft_hyphens: 1,
+            zero_width: 0,
+            directional_marks: 1,
+        })
+    );
+    assert_eq!(model.state.dom.to_raw_text(), utf16("hyphen 👩\u{200D}💻"));
+}
+
+#[test]
+fn pasted_urls_with_invisible_characters_become_links_once_stripped() {
+    let mut model = with_config(
+        "|",
+        ComposerModelConfig::default()
+            .with_strip_invisible_characters(true)
+            .with_link_pasted_urls(true),
+    );
+    model.paste_text(utf16("https://matrix\u{200B}.org"));
+    assert_eq!(
+        tx(&model),
+        "<a href=\"https://matrix.org\">https://matrix.org|</a>"
+    );
+}
+
+#[test]
+fn pasted_html_has_invisible_characters_stripped() {
+    let mut model = with_config(
+        "|",
+        ComposerModelConfig::default().with_strip_invisible_characters(true),
+    );
+    let update = model.replace_html(
+        utf16("<p><b>a\u{FEFF}b</b></p><p>\u{200B}</p>"),
+        HtmlSource::UnknownExternal,
+    );
+    assert_eq!(
+        update.stripped_characters,
+        Some(StrippedCharacters {
+            zero_width: 2,
+            ..Default::default()
+        })
+    );
+    assert_eq!(tx(&model), "<p><b>ab</b></p><p>&nbsp;|</p>");
+}
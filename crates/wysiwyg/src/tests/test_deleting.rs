@@ -6,7 +6,7 @@
 
 use crate::{
     tests::testutils_composer_model::{cm, restore_whitespace, tx},
-    ComposerModel, TextUpdate,
+    BlockBackspaceBehaviour, ComposerModel, TextUpdate,
 };
 
 #[test]
@@ -68,6 +68,7 @@ fn backspacing_a_line_break_deletes_it() {
     let replace_all = match update.text_update {
         TextUpdate::Keep => panic!("expected ReplaceAll"),
         TextUpdate::ReplaceAll(replace_all) => replace_all,
+        TextUpdate::ReplaceRange(_) => panic!("expected ReplaceAll"),
         TextUpdate::Select(_) => panic!("expected ReplaceAll"),
     };
 
@@ -810,6 +811,68 @@ fn backspace_between_nested_block_nodes() {
     assert_eq!(tx(&model), "<p>First|Second</p>");
 }
 
+#[test]
+fn backspace_at_start_of_code_block_merges_into_previous_paragraph_by_default()
+{
+    let mut model = cm("<p>First</p><pre><code>|Second\nThird</code></pre>");
+    model.backspace();
+    assert_eq!(
+        tx(&model),
+        "<p>First|Second</p><pre><code>Third</code></pre>"
+    );
+}
+
+#[test]
+fn backspace_at_start_of_code_block_can_exit_it() {
+    let mut model = cm("<p>First</p><pre><code>|Second\nThird</code></pre>");
+    model.set_block_backspace_behaviour(BlockBackspaceBehaviour::ExitBlock);
+    model.backspace();
+    assert_eq!(tx(&model), "<p>First</p><p>|Second</p><p>Third</p>");
+    model.undo();
+    assert_eq!(
+        tx(&model),
+        "<p>First</p><pre><code>|Second\nThird</code></pre>"
+    );
+}
+
+#[test]
+fn backspace_at_start_of_first_code_block_can_exit_it() {
+    let mut model = cm("<pre><code>|Second</code></pre>");
+    model.set_block_backspace_behaviour(BlockBackspaceBehaviour::ExitBlock);
+    model.backspace();
+    assert_eq!(tx(&model), "<p>|Second</p>");
+}
+
+#[test]
+fn backspace_at_start_of_quote_can_exit_it() {
+    let mut model =
+        cm("<p>First</p><blockquote><p>|Second</p><p>Third</p></blockquote>");
+    model.set_block_backspace_behaviour(BlockBackspaceBehaviour::ExitBlock);
+    model.backspace();
+    assert_eq!(tx(&model), "<p>First</p><p>|Second</p><p>Third</p>");
+}
+
+#[test]
+fn backspace_at_start_of_block_can_do_nothing() {
+    let mut model = cm("<p>First</p><blockquote><p>|Second</p></blockquote>");
+    model.set_block_backspace_behaviour(BlockBackspaceBehaviour::NoOp);
+    let update = model.backspace();
+    assert!(matches!(update.text_update, TextUpdate::Keep));
+    assert_eq!(
+        tx(&model),
+        "<p>First</p><blockquote><p>|Second</p></blockquote>"
+    );
+    assert!(model.history().is_empty());
+}
+
+#[test]
+fn backspace_inside_block_is_not_affected_by_block_behaviour() {
+    let mut model = cm("<blockquote><p>First</p><p>|Second</p></blockquote>");
+    model.set_block_backspace_behaviour(BlockBackspaceBehaviour::NoOp);
+    model.backspace();
+    assert_eq!(tx(&model), "<blockquote><p>First|Second</p></blockquote>");
+}
+
 #[test]
 // TODO: remove these tests when implementing list behaviour
 fn html_backspace_word_does_not_change_model() {
@@ -883,7 +946,7 @@ fn backspace_immutable_link_multiple() {
     model.backspace();
     assert_eq!(
         restore_whitespace(&tx(&model)),
-        "<a contenteditable=\"false\" href=\"https://matrix.org\">first|</a>"
+        "<a href=\"https://matrix.org\" contenteditable=\"false\">first|</a>"
     );
     model.backspace();
     assert_eq!(restore_whitespace(&tx(&model)), "|");
@@ -961,7 +1024,7 @@ fn delete_first_immutable_link_of_multiple() {
     model.delete();
     assert_eq!(
         restore_whitespace(&tx(&model)),
-        "<a contenteditable=\"false\" href=\"https://element.io\">|second</a>"
+        "<a href=\"https://element.io\" contenteditable=\"false\">|second</a>"
     );
     model.delete();
     assert_eq!(restore_whitespace(&tx(&model)), "|");
@@ -989,7 +1052,7 @@ fn delete_second_immutable_link_of_multiple() {
     model.backspace();
     assert_eq!(
         restore_whitespace(&tx(&model)),
-        "<a contenteditable=\"false\" href=\"https://matrix.org\">first|</a>"
+        "<a href=\"https://matrix.org\" contenteditable=\"false\">first|</a>"
     );
     model.backspace();
     assert_eq!(restore_whitespace(&tx(&model)), "|");
@@ -1031,3 +1094,53 @@ fn backspacing_paragraphs_with_nbsp_at_start() {
     model.backspace();
     assert_eq!(tx(&model), "<p>|test</p>")
 }
+
+#[test]
+fn truncating_cuts_the_end_of_the_content() {
+    let mut model = cm("ab<b>cd</b>ef|");
+    model.truncate_to(3);
+    assert_eq!(tx(&model), "ab<b>c|</b>");
+    model.undo();
+    assert_eq!(tx(&model), "ab<b>cd</b>ef|");
+}
+
+#[test]
+fn truncating_keeps_a_selection_which_fits() {
+    let mut model = cm("{ab}|cdef");
+    model.truncate_to(4);
+    assert_eq!(tx(&model), "{ab}|cd");
+}
+
+#[test]
+fn truncating_does_not_split_graphemes() {
+    let mut model = cm("ab👍🏽|");
+    model.truncate_to(5);
+    assert_eq!(tx(&model), "ab|");
+}
+
+#[test]
+fn truncating_leaves_out_a_mention_which_does_not_fit() {
+    let mut model =
+        cm("ab<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>|");
+    model.truncate_to(2);
+    assert_eq!(tx(&model), "ab|");
+}
+
+#[test]
+fn truncating_does_not_leave_an_empty_block_at_the_end() {
+    let mut model = cm("<p>ab</p><p>cd|</p>");
+    model.truncate_to(3);
+    assert_eq!(tx(&model), "<p>ab|</p>");
+    let mut model = cm("<p>ab</p><ul><li>cd|</li></ul>");
+    model.truncate_to(4);
+    assert_eq!(tx(&model), "<p>ab</p><ul><li>c|</li></ul>");
+}
+
+#[test]
+fn truncating_to_more_than_the_length_does_nothing() {
+    let mut model = cm("abc|");
+    model.truncate_to(3);
+    model.truncate_to(10);
+    assert_eq!(tx(&model), "abc|");
+    assert!(model.history().is_empty());
+}
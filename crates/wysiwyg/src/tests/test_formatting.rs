@@ -10,7 +10,10 @@ use widestring::Utf16String;
 
 use crate::InlineFormatType::Bold;
 use crate::Location;
-use crate::{ComposerModel, InlineFormatType};
+use crate::{
+    ComposerAction, ComposerModel, ComposerModelConfig, InlineCodeExitRule,
+    InlineFormatType,
+};
 
 #[test]
 fn selecting_and_bolding_multiple_times() {
@@ -356,3 +359,151 @@ fn formatting_in_an_empty_paragraph_applies_formatting() {
     model.replace_text("B".into());
     assert_eq!(tx(&model), "<p>A</p><p><strong>B|</strong></p>");
 }
+
+#[test]
+fn enter_at_the_end_of_inline_code_exits_it() {
+    let mut model = cm("<p>a<code>bc|</code></p>");
+    model.enter();
+    assert_eq!(tx(&model), "<p>a<code>bc</code></p><p>&nbsp;|</p>");
+    assert!(!model.action_is_reversed(ComposerAction::InlineCode));
+    assert!(model.action_is_enabled(ComposerAction::Bold));
+    model.replace_text(utf16("d"));
+    assert_eq!(tx(&model), "<p>a<code>bc</code></p><p>d|</p>");
+}
+
+#[test]
+fn enter_in_the_middle_of_inline_code_exits_it() {
+    let mut model = cm("<code>a|b</code>c");
+    model.enter();
+    assert_eq!(tx(&model), "<p><code>a</code></p><p><code>|b</code>c</p>");
+    assert!(!model.action_is_reversed(ComposerAction::InlineCode));
+    model.replace_text(utf16("d"));
+    assert_eq!(tx(&model), "<p><code>a</code></p><p>d|<code>b</code>c</p>");
+}
+
+#[test]
+fn enter_after_toggling_inline_code_exits_it() {
+    let mut model = cm("a|");
+    model.inline_code();
+    model.enter();
+    assert!(!model.action_is_reversed(ComposerAction::InlineCode));
+    model.replace_text(utf16("b"));
+    assert_eq!(tx(&model), "<p>a</p><p>b|</p>");
+}
+
+#[test]
+fn enter_keeps_other_formatting_when_exiting_inline_code() {
+    let mut model = cm("<b><code>a|</code></b>");
+    model.enter();
+    assert!(model.action_is_reversed(ComposerAction::Bold));
+    assert!(!model.action_is_reversed(ComposerAction::InlineCode));
+    model.replace_text(utf16("b"));
+    assert_eq!(tx(&model), "<p><b><code>a</code></b></p><p><b>b|</b></p>");
+}
+
+#[test]
+fn enter_stays_in_inline_code_if_configured_to() {
+    let mut model = cm("<code>a|</code>");
+    model.set_config(
+        ComposerModelConfig::default()
+            .with_inline_code_exit_rule(InlineCodeExitRule::Never),
+    );
+    model.enter();
+    assert!(model.action_is_reversed(ComposerAction::InlineCode));
+    model.replace_text(utf16("b"));
+    assert_eq!(tx(&model), "<p><code>a</code></p><p><code>b|</code></p>");
+}
+
+#[test]
+fn applying_several_formats_to_a_selection() {
+    let mut model = cm("a{bc}|d");
+    model.apply_formats(&[Bold, InlineFormatType::Italic], true);
+    assert_eq!(tx(&model), "a<strong><em>{bc}|</em></strong>d");
+    assert!(model.action_is_reversed(ComposerAction::Bold));
+    assert!(model.action_is_reversed(ComposerAction::Italic));
+}
+
+#[test]
+fn applying_formats_only_changes_those_not_yet_applied() {
+    let mut model = cm("<strong>{ab}|</strong>");
+    model.apply_formats(&[Bold, InlineFormatType::Underline], true);
+    assert_eq!(tx(&model), "<strong><u>{ab}|</u></strong>");
+}
+
+#[test]
+fn removing_several_formats_from_a_selection() {
+    let mut model = cm("<strong><em><u>{ab}|</u></em></strong>");
+    model.apply_formats(&[Bold, InlineFormatType::Underline], false);
+    assert_eq!(tx(&model), "<em>{ab}|</em>");
+}
+
+#[test]
+fn applying_several_formats_is_undone_in_one_step() {
+    let mut model = cm("{ab}|");
+    model.apply_formats(&[Bold, InlineFormatType::StrikeThrough], true);
+    model.undo();
+    assert_eq!(tx(&model), "{ab}|");
+}
+
+#[test]
+fn applying_several_formats_at_the_cursor_formats_typed_text() {
+    let mut model = cm("a|");
+    model.apply_formats(&[Bold, InlineFormatType::Italic], true);
+    assert!(model.action_is_reversed(ComposerAction::Bold));
+    assert!(model.action_is_reversed(ComposerAction::Italic));
+    model.replace_text(utf16("b"));
+    assert_eq!(tx(&model), "a<strong><em>b|</em></strong>");
+}
+
+#[test]
+fn applying_disallowed_formats_does_nothing() {
+    let mut model = cm("{ab}|");
+    model.set_config(
+        ComposerModelConfig::default()
+            .with_disallowed_actions([ComposerAction::Bold].into()),
+    );
+    model.apply_formats(&[Bold], true);
+    assert_eq!(tx(&model), "{ab}|");
+    assert!(model.history().is_empty());
+}
+
+fn with_warning_style(text: &str) -> ComposerModel<Utf16String> {
+    let mut model = cm(text);
+    model.set_config(
+        ComposerModelConfig::default()
+            .with_style("warning", vec![Bold, InlineFormatType::Underline]),
+    );
+    model
+}
+
+#[test]
+fn applying_a_style_applies_its_formats() {
+    let mut model = with_warning_style("a{bc}|d");
+    assert!(!model.style_is_applied("warning"));
+    model.apply_style("warning");
+    assert_eq!(tx(&model), "a<strong><u>{bc}|</u></strong>d");
+    assert!(model.style_is_applied("warning"));
+}
+
+#[test]
+fn applying_an_applied_style_removes_it() {
+    let mut model =
+        with_warning_style("<em><strong><u>{ab}|</u></strong></em>");
+    model.apply_style("warning");
+    assert_eq!(tx(&model), "<em>{ab}|</em>");
+}
+
+#[test]
+fn applying_a_partly_applied_style_completes_it() {
+    let mut model = with_warning_style("<strong>{ab}|</strong>");
+    model.apply_style("warning");
+    assert_eq!(tx(&model), "<strong><u>{ab}|</u></strong>");
+}
+
+#[test]
+fn applying_an_unknown_style_does_nothing() {
+    let mut model = with_warning_style("{ab}|");
+    model.apply_style("error");
+    assert_eq!(tx(&model), "{ab}|");
+    assert!(!model.style_is_applied("error"));
+}
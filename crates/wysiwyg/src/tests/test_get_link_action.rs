@@ -6,7 +6,7 @@
 
 use indoc::indoc;
 
-use crate::tests::testutils_composer_model::cm;
+use crate::tests::testutils_composer_model::{cm, tx};
 use crate::tests::testutils_conversion::utf16;
 
 use crate::{LinkAction, Location};
@@ -46,16 +46,43 @@ fn get_link_action_from_highlighted_link() {
     let model = cm("{<a href=\"https://element.io\">test</a>}|");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
+#[test]
+fn get_link_action_includes_the_attributes_of_the_link() {
+    let model =
+        cm("<a href=\"https://element.io\" style=\"color: red\">te|st</a>");
+    assert_eq!(
+        model.get_link_action(),
+        LinkAction::Edit(
+            utf16("https://element.io"),
+            vec![(utf16("style"), utf16("color: red"))]
+        )
+    )
+}
+
+#[test]
+fn attributes_from_get_link_action_are_kept_when_the_link_is_edited() {
+    let mut model =
+        cm("<a href=\"https://element.io\" style=\"color: red\">te|st</a>");
+    let LinkAction::Edit(_, attributes) = model.get_link_action() else {
+        panic!("Expected to edit the link");
+    };
+    model.set_link(utf16("https://matrix.org"), attributes);
+    assert_eq!(
+        tx(&model),
+        "<a style=\"color: red\" href=\"https://matrix.org\">te|st</a>"
+    );
+}
+
 #[test]
 fn get_link_action_from_cursor_at_the_end_of_a_link() {
     let model = cm("<a href=\"https://element.io\">test</a>|");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -64,7 +91,7 @@ fn get_link_action_from_cursor_inside_a_link() {
     let model = cm("<a href=\"https://element.io\">te|st</a>");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -73,7 +100,7 @@ fn get_link_action_from_cursor_at_the_start_of_a_link() {
     let model = cm("|<a href=\"https://element.io\">test</a>");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -82,7 +109,7 @@ fn get_link_action_from_selection_that_contains_a_link_and_non_links() {
     let model = cm("<b>{test_bold <a href=\"https://element.io\">test}|_link</a> test_bold</b>");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -91,7 +118,7 @@ fn get_link_action_from_selection_that_contains_multiple_links() {
     let model = cm("{<a href=\"https://element.io\">test_element</a> <a href=\"https://matrix.org\">test_matrix</a>}|");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -100,7 +127,7 @@ fn get_link_action_from_selection_that_contains_multiple_links_partially() {
     let model = cm("<a href=\"https://element.io\">test_{element</a> <a href=\"https://matrix.org\">test}|_matrix</a>");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -110,7 +137,7 @@ fn get_link_action_from_selection_that_contains_multiple_links_partially_in_diff
     let model = cm("<a href=\"https://element.io\"> <b>test_{element</b></a> <i><a href=\"https://matrix.org\">test}|_matrix</a></i>");
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -168,7 +195,7 @@ fn get_link_action_on_blank_selection_after_a_link() {
     // This is the correct behaviour because the end of a link should be considered part of the link itself
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit(utf16("https://element.io"))
+        LinkAction::Edit(utf16("https://element.io"), vec![])
     )
 }
 
@@ -216,7 +243,7 @@ fn get_link_action_on_multiple_link_with_first_immutable() {
     model.select(Location::from(20), Location::from(20));
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit("https://rust-lang.org".into()),
+        LinkAction::Edit("https://rust-lang.org".into(), vec![]),
     );
 }
 
@@ -232,7 +259,7 @@ fn get_link_action_on_multiple_link_with_last_immutable() {
     model.select(Location::from(0), Location::from(0));
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit("https://rust-lang.org".into()),
+        LinkAction::Edit("https://rust-lang.org".into(), vec![]),
     );
 }
 
@@ -273,13 +300,13 @@ fn get_link_action_on_multiple_link_with_first_is_mention() {
     "#});
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit("https://rust-lang.org".into()),
+        LinkAction::Edit("https://rust-lang.org".into(), vec![]),
     );
     // Selecting the link afterwards works
     model.select(Location::from(10), Location::from(10));
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit("https://rust-lang.org".into()),
+        LinkAction::Edit("https://rust-lang.org".into(), vec![]),
     );
 }
 
@@ -292,12 +319,12 @@ fn get_link_action_on_multiple_link_with_last_is_mention() {
     "#});
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit("https://rust-lang.org".into()),
+        LinkAction::Edit("https://rust-lang.org".into(), vec![]),
     );
     // Selecting the mutable link afterwards works
     model.select(Location::from(0), Location::from(0));
     assert_eq!(
         model.get_link_action(),
-        LinkAction::Edit("https://rust-lang.org".into()),
+        LinkAction::Edit("https://rust-lang.org".into(), vec![]),
     );
 }
@@ -878,6 +878,25 @@ fn set_link_with_text_and_custom_attributes() {
     )
 }
 
+#[test]
+fn link_attributes_are_written_in_the_same_order_however_they_were_given() {
+    let mut model = cm("{hello}| world");
+    model.set_link(
+        "https://matrix.org".into(),
+        vec![("title".into(), "t".into()), ("rel".into(), "r".into())],
+    );
+    let mut other = cm("{hello}| world");
+    other.set_link(
+        "https://matrix.org".into(),
+        vec![("rel".into(), "r".into()), ("title".into(), "t".into())],
+    );
+    assert_eq!(
+        tx(&model),
+        "<a rel=\"r\" title=\"t\" href=\"https://matrix.org\">{hello}|</a> world"
+    );
+    assert_eq!(tx(&model), tx(&other));
+}
+
 #[test]
 fn set_link_in_list_then_exit_list() {
     // start with empty model
@@ -595,6 +595,62 @@ fn backspacing_an_indented_list_item_with_siblings_doesnt_remove_parent_list_ite
     );
 }
 
+#[test]
+fn splitting_a_list_at_the_cursor_starts_a_new_list() {
+    let mut model = cm("<ol><li>a</li><li>b|</li><li>c</li></ol>");
+    model.split_list_at_cursor();
+    assert_eq!(
+        tx(&model),
+        "<ol><li>a</li></ol><ol><li>b|</li><li>c</li></ol>"
+    );
+}
+
+#[test]
+fn splitting_a_nested_list_keeps_it_in_its_item() {
+    let mut model =
+        cm("<ul><li><p>a</p><ol><li>b</li><li>c|</li></ol></li></ul>");
+    model.split_list_at_cursor();
+    assert_eq!(
+        tx(&model),
+        "<ul><li><p>a</p><ol><li>b</li></ol><ol><li>c|</li></ol></li></ul>"
+    );
+}
+
+#[test]
+fn splitting_a_list_in_its_first_item_does_nothing() {
+    let mut model = cm("<ol><li>a|</li><li>b</li></ol>");
+    model.split_list_at_cursor();
+    assert_eq!(tx(&model), "<ol><li>a|</li><li>b</li></ol>");
+    let mut model = cm("<p>a|</p>");
+    model.split_list_at_cursor();
+    assert_eq!(tx(&model), "<p>a|</p>");
+}
+
+#[test]
+fn joining_with_the_previous_list_moves_the_items_into_it() {
+    let mut model = cm("<ol><li>a</li></ol><ul><li>b|</li><li>c</li></ul>");
+    model.join_with_previous_list();
+    assert_eq!(tx(&model), "<ol><li>a</li><li>b|</li><li>c</li></ol>");
+}
+
+#[test]
+fn joining_undoes_splitting() {
+    let mut model = cm("<ol><li>a</li><li>b|</li></ol>");
+    model.split_list_at_cursor();
+    model.join_with_previous_list();
+    assert_eq!(tx(&model), "<ol><li>a</li><li>b|</li></ol>");
+}
+
+#[test]
+fn joining_a_list_without_a_previous_list_does_nothing() {
+    let mut model = cm("<p>a</p><ol><li>b|</li></ol>");
+    model.join_with_previous_list();
+    assert_eq!(tx(&model), "<p>a</p><ol><li>b|</li></ol>");
+    let mut model = cm("<ol><li>a|</li></ol>");
+    model.join_with_previous_list();
+    assert_eq!(tx(&model), "<ol><li>a|</li></ol>");
+}
+
 fn replace_text(model: &mut ComposerModel<Utf16String>, new_text: &str) {
     model.replace_text(utf16(new_text));
 }
@@ -8,7 +8,8 @@ use widestring::Utf16String;
 
 use crate::{
     tests::testutils_composer_model::{cm, tx},
-    ComposerModel, MentionsState, MenuAction,
+    ComposerModel, ComposerUpdate, DeletedMention, DeletedMentionKind,
+    HtmlSource, Location, MentionsState, MenuAction,
 };
 /**
  * INSERTING WITH PARSING
@@ -107,6 +108,28 @@ fn mention_with_attributes() {
     );
 }
 
+#[test]
+fn mention_attributes_are_written_in_name_order_before_the_generated_ones() {
+    let mut model = cm("|");
+    let update = model.replace_text("@alic".into());
+    let MenuAction::Suggestion(suggestion) = update.menu_action else {
+        panic!("No suggestion pattern found")
+    };
+    model.insert_mention_at_suggestion(
+        "https://matrix.to/#/@alice:matrix.org".into(),
+        "Alice".into(),
+        suggestion,
+        vec![
+            ("style".into(), "{some: CSS}".into()),
+            ("class".into(), "pill".into()),
+        ],
+    );
+    assert_eq!(
+        tx(&model),
+        "<a class=\"pill\" style=\"{some: CSS}\" data-mention-type=\"user\" href=\"https://matrix.to/#/@alice:matrix.org\" contenteditable=\"false\">Alice</a>&nbsp;|",
+    );
+}
+
 /**
  * INSERT AT CURSOR
  */
@@ -617,6 +640,7 @@ fn get_mentions_state_for_at_room_mention() {
         room_ids: Default::default(),
         room_aliases: Default::default(),
         has_at_room_mention: true,
+        has_at_room_text: false,
     };
     assert_eq!(model.get_mentions_state(), state)
 }
@@ -629,6 +653,7 @@ fn get_mentions_state_for_at_room_plain_mention() {
         room_ids: Default::default(),
         room_aliases: Default::default(),
         has_at_room_mention: true,
+        has_at_room_text: false,
     };
     assert_eq!(model.get_mentions_state(), state)
 }
@@ -652,9 +677,14 @@ fn get_mentions_state_for_user_mention_with_custom_link() {
 }
 
 #[test]
-fn get_mentions_state_empty_for_non_intentional_at_room_mention() {
+fn get_mentions_state_reports_at_room_text_in_code_without_a_mention() {
     let model = cm("<pre>hello @room!|</pre>");
-    assert_eq!(model.get_mentions_state(), MentionsState::default())
+    let state = MentionsState {
+        has_at_room_text: true,
+        ..Default::default()
+    };
+    assert!(!model.get_mentions_state().has_at_room_mention);
+    assert_eq!(model.get_mentions_state(), state)
 }
 
 #[test]
@@ -703,6 +733,338 @@ fn get_mentions_state_for_multiple_mentions() {
     assert_eq!(model.get_mentions_state(), state)
 }
 
+#[test]
+fn get_mentions_state_distinguishes_at_room_text_from_mentions() {
+    let model = cm("<p>hello <code>@room</code>|</p>");
+    let state = model.get_mentions_state();
+    assert!(state.has_at_room_text);
+    assert!(!state.has_at_room_mention);
+}
+
+/**
+ * DETECTING @ROOM
+ */
+#[test]
+fn at_room_is_not_detected_without_permission() {
+    let mut model = cm("|");
+    model.set_can_notify_room(false);
+    model.set_content_from_html(&"hello @room".into()).unwrap();
+    let state = model.get_mentions_state();
+    assert!(!state.has_at_room_mention);
+    assert!(state.has_at_room_text);
+}
+
+#[test]
+fn at_room_in_quotes_is_not_detected_if_asked() {
+    let mut model = cm("|");
+    model.set_at_room_detection_in_quotes(false);
+    model
+        .set_content_from_html(&"<blockquote>@room</blockquote>".into())
+        .unwrap();
+    assert!(!model.get_mentions_state().has_at_room_mention);
+    model
+        .set_content_from_html(
+            &"<blockquote><a href=\"#\">@room</a></blockquote>".into(),
+        )
+        .unwrap();
+    assert!(!model.get_mentions_state().has_at_room_mention);
+    model.set_content_from_html(&"<p>@room</p>".into()).unwrap();
+    assert!(model.get_mentions_state().has_at_room_mention);
+}
+
+/**
+ * REPORTING DELETED MENTIONS
+ */
+#[test]
+fn backspacing_a_mention_reports_it_as_deleted() {
+    let mut model = cm("|");
+    insert_mention_at_cursor(&mut model);
+    model.backspace();
+    let update = model.backspace();
+    assert_eq!(
+        update.deleted_mentions,
+        vec![DeletedMention {
+            kind: DeletedMentionKind::User,
+            uri: Some("https://matrix.to/#/@alice:matrix.org".into()),
+            mx_id: Some("@alice:matrix.org".into()),
+            display_text: "Alice".into(),
+            location: Location::from(0),
+        }]
+    );
+}
+
+#[test]
+fn deleting_an_at_room_mention_reports_it_as_deleted() {
+    let mut model = cm("a|");
+    model.insert_at_room_mention(vec![]);
+    model.select(Location::from(1), Location::from(1));
+    let update = model.delete();
+    assert_eq!(update.deleted_mentions.len(), 1);
+    assert_eq!(update.deleted_mentions[0].kind, DeletedMentionKind::AtRoom);
+    assert_eq!(update.deleted_mentions[0].uri, None);
+    assert_eq!(update.deleted_mentions[0].location, 1);
+}
+
+#[test]
+fn deleting_a_selection_reports_every_mention_in_it() {
+    let mut model = cm("|");
+    insert_mention_at_cursor(&mut model);
+    model.replace_text("and ".into());
+    model.insert_at_room_mention(vec![]);
+    model.select(Location::from(0), Location::from(7));
+    let update = model.delete();
+    let kinds: Vec<DeletedMentionKind> =
+        update.deleted_mentions.iter().map(|m| m.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![DeletedMentionKind::User, DeletedMentionKind::AtRoom]
+    );
+}
+
+#[test]
+fn editing_text_next_to_a_mention_reports_nothing_deleted() {
+    let mut model = cm("|");
+    insert_mention_at_cursor(&mut model);
+    let update = model.replace_text("x".into());
+    assert!(update.deleted_mentions.is_empty());
+    let update = model.backspace();
+    assert!(update.deleted_mentions.is_empty());
+}
+
+#[test]
+fn undoing_a_mention_insertion_reports_it_as_deleted() {
+    let mut model = cm("|");
+    insert_mention_at_selection(&mut model);
+    let update = model.undo();
+    assert_eq!(update.deleted_mentions.len(), 1);
+    let update = model.redo();
+    assert!(update.deleted_mentions.is_empty());
+}
+
+#[test]
+fn truncating_away_a_mention_reports_it_as_deleted() {
+    let mut model = cm("hi |");
+    insert_mention_at_selection(&mut model);
+    model.replace_text("there".into());
+    let update = model.truncate_to(3);
+    assert_eq!(tx(&model), "hi&nbsp;|");
+    assert_eq!(update.deleted_mentions.len(), 1);
+    assert_eq!(update.deleted_mentions[0].location, 3);
+}
+
+#[test]
+fn linking_over_a_selected_mention_reports_it_as_deleted() {
+    let mut model = cm("|");
+    insert_mention_at_selection(&mut model);
+    model.select(Location::from(0), Location::from(1));
+    let update = model.set_link_with_text(
+        "https://matrix.org".into(),
+        "link".into(),
+        vec![],
+    );
+    assert_eq!(update.deleted_mentions.len(), 1);
+    assert_eq!(update.deleted_mentions[0].display_text, "Alice");
+}
+
+#[test]
+fn pasting_a_mention_over_a_selected_mention_reports_it_as_deleted() {
+    let mut model = cm("|");
+    insert_mention_at_selection(&mut model);
+    model.select(Location::from(0), Location::from(1));
+    let update = model.replace_html(
+        "<a href=\"https://matrix.to/#/@bob:matrix.org\">Bob</a>".into(),
+        HtmlSource::Matrix,
+    );
+    assert_eq!(update.deleted_mentions.len(), 1);
+    assert_eq!(update.deleted_mentions[0].display_text, "Alice");
+}
+
+#[test]
+fn inserting_a_mention_over_a_selected_mention_reports_it_as_deleted() {
+    let mut model = cm("|");
+    insert_mention_at_selection(&mut model);
+    model.select(Location::from(0), Location::from(1));
+    let update = model.insert_mention(
+        "https://matrix.to/#/@bob:matrix.org".into(),
+        "Bob".into(),
+        vec![],
+    );
+    assert_eq!(update.deleted_mentions.len(), 1);
+    assert_eq!(update.deleted_mentions[0].display_text, "Alice");
+}
+
+/**
+ * UPDATING TEXT
+ */
+#[test]
+fn updating_mention_text_keeps_its_uri_and_attributes() {
+    let mut model = cm("|");
+    model.insert_mention(
+        "https://matrix.to/#/@alice:matrix.org".into(),
+        "Alice".into(),
+        vec![("style".into(), "color: red".into())],
+    );
+    let update = model.update_mention_text(
+        Location::from(0),
+        Location::from(1),
+        "Alice Smith".into(),
+    );
+    assert_eq!(update.changed_handles.modified.len(), 1);
+    assert!(update.deleted_mentions.is_empty());
+    assert_eq!(
+        tx(&model),
+        "<a style=\"color: red\" data-mention-type=\"user\" href=\"https://matrix.to/#/@alice:matrix.org\" contenteditable=\"false\">Alice Smith</a>&nbsp;|"
+    );
+    assert_eq!(
+        model.get_mentions_state().user_ids,
+        ["@alice:matrix.org".to_string()].into()
+    );
+}
+
+#[test]
+fn deleting_a_mention_after_updating_its_text_reports_the_new_text() {
+    let mut model = cm("|");
+    insert_mention_at_selection(&mut model);
+    model.update_mention_text(
+        Location::from(0),
+        Location::from(1),
+        "Alice Smith".into(),
+    );
+    model.select(Location::from(0), Location::from(1));
+    let update = model.delete();
+    assert_eq!(update.deleted_mentions.len(), 1);
+    assert_eq!(update.deleted_mentions[0].display_text, "Alice Smith");
+}
+
+#[test]
+fn updating_mention_text_leaves_mentions_outside_the_range() {
+    let mut model = cm("|");
+    insert_mention_at_selection(&mut model);
+    model.replace_text("and ".into());
+    insert_mention_at_selection(&mut model);
+    model.update_mention_text(
+        Location::from(6),
+        Location::from(7),
+        "Alice Smith".into(),
+    );
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a> and <a href=\"https://matrix.to/#/@alice:matrix.org\">Alice Smith</a>\u{a0}"
+    );
+}
+
+#[test]
+fn updating_mention_text_with_the_same_text_keeps_the_content() {
+    let mut model = cm("|");
+    insert_mention_at_selection(&mut model);
+    let update = model.update_mention_text(
+        Location::from(0),
+        Location::from(1),
+        "Alice".into(),
+    );
+    assert_eq!(update, ComposerUpdate::keep());
+}
+
+#[test]
+fn updating_text_of_at_room_mention_does_nothing() {
+    let mut model = cm("|");
+    model.insert_at_room_mention(vec![]);
+    let update = model.update_mention_text(
+        Location::from(0),
+        Location::from(1),
+        "everyone".into(),
+    );
+    assert_eq!(update, ComposerUpdate::keep());
+    assert_eq!(model.get_content_as_message_html(), "@room\u{a0}");
+}
+
+/**
+ * CONVERTING PLAIN TEXT
+ */
+#[test]
+fn converting_plain_mentions_replaces_each_text_with_a_mention() {
+    let mut model = cm("Hi @alice and @bob:matrix.org|");
+    model.convert_plain_mentions(vec![
+        (
+            "@alice".into(),
+            "https://matrix.to/#/@alice:matrix.org".into(),
+        ),
+        (
+            "@bob:matrix.org".into(),
+            "https://matrix.to/#/@bob:matrix.org".into(),
+        ),
+    ]);
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "Hi <a href=\"https://matrix.to/#/@alice:matrix.org\">@alice</a> and <a href=\"https://matrix.to/#/@bob:matrix.org\">@bob:matrix.org</a>"
+    );
+    assert_eq!(model.state.start, Location::from(10));
+    assert_eq!(model.state.end, Location::from(10));
+}
+
+#[test]
+fn converting_plain_mentions_prefers_the_longest_text() {
+    let mut model = cm("@alice:matrix.org|");
+    model.convert_plain_mentions(vec![
+        (
+            "@alice".into(),
+            "https://matrix.to/#/@alice:example.org".into(),
+        ),
+        (
+            "@alice:matrix.org".into(),
+            "https://matrix.to/#/@alice:matrix.org".into(),
+        ),
+    ]);
+    assert_eq!(
+        model.get_mentions_state().user_ids,
+        ["@alice:matrix.org".to_string()].into()
+    );
+}
+
+#[test]
+fn converting_plain_mentions_only_matches_whole_words() {
+    let mut model = cm("@alicia and me@alice|");
+    let update = model.convert_plain_mentions(vec![(
+        "@alice".into(),
+        "https://matrix.to/#/@alice:matrix.org".into(),
+    )]);
+    assert_eq!(update, ComposerUpdate::keep());
+    assert_eq!(tx(&model), "@alicia and me@alice|");
+}
+
+#[test]
+fn converting_plain_mentions_leaves_links_and_code() {
+    let mut model =
+        cm("<code>@alice</code> <a href=\"https://x.org\">@alice</a> @alice|");
+    model.convert_plain_mentions(vec![(
+        "@alice".into(),
+        "https://matrix.to/#/@alice:matrix.org".into(),
+    )]);
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<code>@alice</code> <a href=\"https://x.org\">@alice</a> <a href=\"https://matrix.to/#/@alice:matrix.org\">@alice</a>"
+    );
+}
+
+#[test]
+fn converting_plain_mentions_is_undone_in_one_step() {
+    let mut model = cm("@alice @alice|");
+    model.convert_plain_mentions(vec![(
+        "@alice".into(),
+        "https://matrix.to/#/@alice:matrix.org".into(),
+    )]);
+    assert_eq!(
+        model
+            .get_content_as_message_html()
+            .to_string()
+            .matches("<a")
+            .count(),
+        2
+    );
+    model.undo();
+    assert_eq!(tx(&model), "@alice @alice|");
+}
+
 /**
  * HELPER FUNCTIONS
  */
@@ -4,10 +4,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use crate::PatternKey::{At, Colon, Hash, Slash};
+use crate::PatternKey::{At, Colon, Custom, Hash, Slash};
 use crate::{
-    tests::testutils_composer_model::cm, Location, MenuAction, PatternKey,
-    SuggestionPattern,
+    tests::testutils_composer_model::cm, ComposerModelConfig, Location,
+    MenuAction, PatternKey, SuggestionPattern,
 };
 
 // MenuAction computation tests.
@@ -205,6 +205,57 @@ fn emoji_pattern_is_not_detected_after_immediate_preceeding_text() {
     assert_eq!(model.compute_menu_action(), MenuAction::None);
 }
 
+#[test]
+fn removed_trigger_does_not_start_a_suggestion() {
+    let mut model = cm("/|");
+    model.set_suggestion_trigger('/', None);
+    assert_eq!(model.compute_menu_action(), MenuAction::None);
+}
+
+#[test]
+fn added_trigger_reports_its_key() {
+    let mut model = cm("Hey !roo|");
+    model.set_suggestion_trigger('!', Some(Custom("!".into())));
+    assert_eq!(
+        model.compute_menu_action(),
+        sp(Custom("!".into()), "roo", 4, 8)
+    );
+}
+
+#[test]
+fn trigger_can_report_a_different_key() {
+    let mut model = cm("+alic|");
+    model.set_suggestion_trigger('+', Some(At));
+    assert_eq!(model.compute_menu_action(), sp(At, "alic", 0, 5));
+}
+
+#[test]
+fn triggers_can_be_set_in_the_config() {
+    let mut model = cm("Hey !roo|");
+    model.set_config(
+        ComposerModelConfig::default()
+            .with_suggestion_trigger('!', Some(Custom("!".into())))
+            .with_suggestion_trigger('@', None),
+    );
+    assert_eq!(
+        model.compute_menu_action(),
+        sp(Custom("!".into()), "roo", 4, 8)
+    );
+    model.replace_text(" @alic".into());
+    assert_eq!(model.compute_menu_action(), MenuAction::None);
+}
+
+#[test]
+fn changed_triggers_leave_custom_patterns_whole() {
+    let mut model = cm(":)|");
+    model.set_custom_suggestion_patterns(vec![":)".into()]);
+    model.set_suggestion_trigger(':', None);
+    assert_eq!(
+        model.compute_menu_action(),
+        sp(Custom(":)".into()), ":)", 0, 2)
+    );
+}
+
 #[test]
 fn menu_action_retuns_keep_after_format_with_cursor() {
     let mut model = cm("@alic|");
@@ -215,6 +266,80 @@ fn menu_action_retuns_keep_after_format_with_cursor() {
     assert_eq!(update.menu_action, MenuAction::Keep);
 }
 
+#[test]
+fn typed_url_is_detected() {
+    let mut model = cm("See |");
+    let update = model.replace_text("https://element.io".into());
+    assert_eq!(update.menu_action, url("https://element.io", 4, 22));
+}
+
+#[test]
+fn url_is_detected_with_the_cursor_inside_it() {
+    let model = cm("See https://elem|ent.io/about now");
+    assert_eq!(
+        model.compute_menu_action(),
+        url("https://element.io/about", 4, 28)
+    );
+}
+
+#[test]
+fn punctuation_after_url_is_left_out() {
+    let model = cm("See https://element.io.|");
+    assert_eq!(
+        model.compute_menu_action(),
+        url("https://element.io", 4, 22)
+    );
+}
+
+#[test]
+fn pasted_url_is_detected() {
+    let mut model = cm("|");
+    let update = model.replace_html(
+        "http://matrix.org/blog".into(),
+        crate::HtmlSource::UnknownExternal,
+    );
+    assert_eq!(update.menu_action, url("http://matrix.org/blog", 0, 22));
+}
+
+#[test]
+fn url_is_not_detected_after_whitespace() {
+    let model = cm("https://element.io |");
+    assert_eq!(model.compute_menu_action(), MenuAction::None);
+}
+
+#[test]
+fn urls_without_a_web_scheme_are_not_detected() {
+    for text in ["mailto:alice@matrix.org|", "element.io|", "https:|"] {
+        let model = cm(text);
+        assert_eq!(model.compute_menu_action(), MenuAction::None, "{text}");
+    }
+}
+
+#[test]
+fn url_is_not_detected_in_links_or_code() {
+    let model = cm("<a href=\"https://x.org\">https://x.org|</a>");
+    assert_eq!(model.compute_menu_action(), MenuAction::None);
+    let model = cm("<code>https://x.org|</code>");
+    assert_eq!(model.compute_menu_action(), MenuAction::None);
+}
+
+#[test]
+fn no_suggestion_is_reported_for_text_being_composed() {
+    let mut model = cm("Hey @あい|");
+    let update =
+        model.set_composing_region(Location::from(5), Location::from(7));
+    assert_eq!(update.menu_action, MenuAction::None);
+    let update = model.clear_composing_region();
+    assert_eq!(update.menu_action, sp(At, "あい", 4, 7));
+}
+
+#[test]
+fn suggestions_outside_the_composing_region_are_reported() {
+    let mut model = cm("@alic| あ");
+    model.set_composing_region(Location::from(6), Location::from(7));
+    assert_eq!(model.compute_menu_action(), sp(At, "alic", 0, 5));
+}
+
 /// Short wrapper around [MenuAction::Suggestion(SuggestionPattern)].
 fn sp(k: PatternKey, t: &str, s: usize, e: usize) -> MenuAction {
     MenuAction::Suggestion(SuggestionPattern {
@@ -224,3 +349,12 @@ fn sp(k: PatternKey, t: &str, s: usize, e: usize) -> MenuAction {
         end: e,
     })
 }
+
+/// Short wrapper around [MenuAction::UrlDetected].
+fn url(url: &str, start: usize, end: usize) -> MenuAction {
+    MenuAction::UrlDetected {
+        url: url.into(),
+        start,
+        end,
+    }
+}
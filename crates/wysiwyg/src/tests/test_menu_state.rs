@@ -4,12 +4,18 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
+use std::collections::HashMap;
+
+use strum::IntoEnumIterator;
 use widestring::Utf16String;
 
 use crate::tests::testutils_composer_model::cm;
 use crate::tests::testutils_conversion::utf16;
 
-use crate::{ComposerAction, ComposerModel, Location};
+use crate::{
+    ActionState, ComposerAction, ComposerModel, ComposerModelConfig, Location,
+    MenuState,
+};
 
 #[test]
 fn creating_and_deleting_lists_updates_reversed_actions() {
@@ -281,6 +287,59 @@ fn empty_list_item_with_formatting_computes_expected_menu_state() {
     assert!(model.action_is_reversed(ComposerAction::Italic));
 }
 
+#[test]
+fn moving_back_to_a_node_after_changing_it_updates_reversed_actions() {
+    let mut model = cm("<ul><li><b><i>a|b</i></b></li></ul><p>cd</p>");
+    assert!(model.action_is_reversed(ComposerAction::Bold));
+    model.select(Location::from(4), Location::from(4));
+    assert!(model.action_is_enabled(ComposerAction::Bold));
+    model.select(Location::from(0), Location::from(2));
+    model.bold();
+    model.select(Location::from(1), Location::from(1));
+    assert!(model.action_is_enabled(ComposerAction::Bold));
+    assert!(model.action_is_reversed(ComposerAction::Italic));
+    assert!(model.action_is_reversed(ComposerAction::UnorderedList));
+}
+
+#[test]
+fn undoing_a_change_updates_reversed_actions() {
+    let mut model = cm("<ol><li>ab|</li></ol>");
+    model.unordered_list();
+    assert!(model.action_is_reversed(ComposerAction::UnorderedList));
+    model.undo();
+    assert!(model.action_is_reversed(ComposerAction::OrderedList));
+    assert!(model.action_is_enabled(ComposerAction::UnorderedList));
+}
+
+#[test]
+fn menu_state_deltas_only_hold_changed_actions() {
+    let mut model = cm("abc|");
+    model.set_config(
+        ComposerModelConfig::default().with_menu_state_deltas(true),
+    );
+    let MenuState::Update(update) = model.bold().menu_state else {
+        panic!("Expected the menu state to be updated");
+    };
+    assert_eq!(
+        update.action_states,
+        HashMap::from([
+            (ComposerAction::Bold, ActionState::Reversed),
+            (ComposerAction::Undo, ActionState::Enabled),
+        ])
+    );
+    assert!(model.action_is_reversed(ComposerAction::Bold));
+    assert_eq!(model.action_states().len(), ComposerAction::iter().count());
+}
+
+#[test]
+fn menu_state_updates_hold_every_action_by_default() {
+    let mut model = cm("abc|");
+    let MenuState::Update(update) = model.bold().menu_state else {
+        panic!("Expected the menu state to be updated");
+    };
+    assert_eq!(&update.action_states, model.action_states());
+}
+
 fn assert_formatting_actions_and_links_are_disabled(
     model: &ComposerModel<Utf16String>,
 ) {
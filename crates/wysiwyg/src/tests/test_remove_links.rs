@@ -5,6 +5,7 @@
 // Please see LICENSE in the repository root for full details.
 
 use crate::tests::testutils_composer_model::{cm, tx};
+use crate::Location;
 
 #[test]
 fn remove_link_on_a_non_link_node() {
@@ -108,3 +109,37 @@ fn remove_link_between_text_nodes_joins() {
     assert_eq!(tx(&model), "abc{def}|ghi");
     model.state.dom.explicitly_assert_invariants();
 }
+
+#[test]
+fn remove_links_in_a_range_keeps_the_selection() {
+    let mut model = cm(
+        "a|b <a href=\"https://matrix.org\">cd</a> <a href=\"https://element.io\">ef</a>",
+    );
+    model.remove_links_in(Location::from(3), Location::from(4));
+    assert_eq!(tx(&model), "a|b cd <a href=\"https://element.io\">ef</a>");
+}
+
+#[test]
+fn remove_links_in_a_reversed_range_past_the_end() {
+    let mut model =
+        cm("|<a href=\"https://matrix.org\">ab</a> <a href=\"https://element.io\">cd</a>");
+    model.remove_links_in(Location::from(10), Location::from(1));
+    assert_eq!(tx(&model), "|ab cd");
+}
+
+#[test]
+fn remove_link_at_an_offset_keeps_the_selection() {
+    let mut model = cm(
+        "<a href=\"https://matrix.org\">ab</a> <a href=\"https://element.io\">c{d</a> e}|",
+    );
+    model.remove_link_at(Location::from(1));
+    assert_eq!(tx(&model), "ab <a href=\"https://element.io\">c{d</a> e}|");
+}
+
+#[test]
+fn remove_link_at_an_offset_without_a_link_does_nothing() {
+    let mut model = cm("ab <a href=\"https://matrix.org\">cd|</a>");
+    model.remove_link_at(Location::from(1));
+    assert_eq!(tx(&model), "ab <a href=\"https://matrix.org\">cd|</a>");
+    assert!(model.history().is_empty());
+}
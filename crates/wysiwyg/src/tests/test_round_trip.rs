@@ -0,0 +1,217 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use widestring::Utf16String;
+
+use crate::dom::nodes::DomNode;
+use crate::dom::Dom;
+use crate::tests::testutils_conversion::utf16;
+use crate::tests::testutils_dom::{a, b, dom, tn};
+use crate::{parse, ListType, RoundTripIssueKind, ToHtml};
+
+const TEXTS: [&str; 9] = [
+    "a", "bc", " d", "e ", "f  g", "<&>", "@room", "\u{A0}h", "i\nj",
+];
+const FORMATS: [&str; 5] = ["b", "i", "u", "del", "code"];
+
+/// A small xorshift generator, so that the documents tested are the same
+/// on every run.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    fn text(&mut self) -> DomNode<Utf16String> {
+        DomNode::new_text(utf16(TEXTS[self.below(TEXTS.len())]))
+    }
+
+    fn inline_nodes(&mut self, depth: usize) -> Vec<DomNode<Utf16String>> {
+        let kinds = if depth == 0 { 3 } else { 6 };
+        (0..=self.below(3))
+            .map(|_| match self.below(kinds) {
+                0 | 1 => self.text(),
+                2 => DomNode::new_line_break(),
+                3 => DomNode::new_formatting_from_tag(
+                    utf16(FORMATS[self.below(FORMATS.len())]),
+                    self.inline_nodes(depth - 1),
+                ),
+                4 => DomNode::new_link(
+                    utf16("https://element.io"),
+                    self.inline_nodes(depth - 1),
+                    vec![],
+                ),
+                _ => DomNode::Mention(
+                    DomNode::new_mention(
+                        utf16("https://matrix.to/#/@alice:matrix.org"),
+                        utf16("Alice"),
+                        vec![],
+                    )
+                    .unwrap(),
+                ),
+            })
+            .collect()
+    }
+
+    fn block(&mut self, depth: usize) -> DomNode<Utf16String> {
+        match self.below(if depth == 0 { 1 } else { 4 }) {
+            0 => DomNode::new_paragraph(self.inline_nodes(2)),
+            1 => DomNode::new_list(
+                if self.below(2) == 0 {
+                    ListType::Ordered
+                } else {
+                    ListType::Unordered
+                },
+                (0..=self.below(2))
+                    .map(|_| DomNode::new_list_item(self.inline_nodes(2)))
+                    .collect(),
+            ),
+            2 => DomNode::new_quote(
+                (0..=self.below(2)).map(|_| self.block(depth - 1)).collect(),
+            ),
+            _ => DomNode::new_code_block(vec![DomNode::new_paragraph(vec![
+                self.text(),
+            ])]),
+        }
+    }
+
+    fn dom(&mut self) -> Dom<Utf16String> {
+        Dom::new((0..=self.below(3)).map(|_| self.block(2)).collect())
+    }
+}
+
+fn issue_kinds(dom: &Dom<Utf16String>) -> Vec<RoundTripIssueKind> {
+    dom.round_trip_issues()
+        .into_iter()
+        .map(|i| i.kind)
+        .collect()
+}
+
+#[test]
+fn canonical_documents_survive_a_round_trip() {
+    let mut random = Random(0x2545_F491_4F6C_DD1D);
+    for _ in 0..200 {
+        let mut dom = random.dom();
+        dom.canonicalize();
+        let html = dom.to_html().to_string();
+        assert_eq!(parse(&html).as_ref(), Ok(&dom), "{html}");
+        assert_eq!(issue_kinds(&dom), vec![], "{html}");
+
+        let mut again = dom.clone();
+        assert_eq!(again.canonicalize(), vec![]);
+        assert_eq!(again, dom, "{html}");
+    }
+}
+
+#[test]
+fn parsed_documents_are_already_canonical() {
+    let mut parsed = parse::<Utf16String>(
+        "<ol><li>a<b>b</b></li></ol><blockquote><p>c @room d</p></blockquote>",
+    )
+    .unwrap();
+    let before = parsed.clone();
+    assert_eq!(parsed.canonicalize(), vec![]);
+    assert_eq!(parsed, before);
+}
+
+#[test]
+fn nested_links_are_reported_and_unwrapped() {
+    let inner = a(&[tn("b")]);
+    let mut dom = dom(&[DomNode::new_paragraph(vec![a(&[tn("a"), inner])])]);
+    assert_eq!(issue_kinds(&dom), vec![RoundTripIssueKind::NestedLink]);
+    dom.canonicalize();
+    assert_eq!(
+        dom.to_html(),
+        "<p><a href=\"https://element.io\">ab</a></p>"
+    );
+}
+
+#[test]
+fn mentions_in_links_become_their_display_text() {
+    let mention = DomNode::Mention(
+        DomNode::new_mention(
+            utf16("https://matrix.to/#/@alice:matrix.org"),
+            utf16("Alice"),
+            vec![],
+        )
+        .unwrap(),
+    );
+    let mut dom = dom(&[DomNode::new_paragraph(vec![a(&[mention])])]);
+    assert_eq!(issue_kinds(&dom), vec![RoundTripIssueKind::MentionInLink]);
+    dom.canonicalize();
+    assert_eq!(
+        dom.to_html(),
+        "<p><a href=\"https://element.io\">Alice</a></p>"
+    );
+}
+
+#[test]
+fn line_breaks_become_paragraphs() {
+    let mut dom = dom(&[DomNode::new_paragraph(vec![
+        b(&[tn("a")]),
+        DomNode::new_line_break(),
+        tn("b"),
+    ])]);
+    assert_eq!(issue_kinds(&dom), vec![RoundTripIssueKind::LineBreak]);
+    dom.canonicalize();
+    assert_eq!(dom.to_html(), "<p><b>a</b></p><p>b</p>");
+}
+
+#[test]
+fn at_room_text_becomes_a_mention_outside_code_and_links() {
+    let mut dom = dom(&[DomNode::new_paragraph(vec![
+        tn("hi @room"),
+        a(&[tn("@room")]),
+    ])]);
+    assert_eq!(issue_kinds(&dom), vec![RoundTripIssueKind::AtRoomText]);
+    dom.canonicalize();
+    assert_eq!(
+        dom.to_html(),
+        "<p>hi <a data-mention-type=\"at-room\" href=\"#\" contenteditable=\"false\">@room</a>\
+        <a href=\"https://element.io\">@room</a></p>"
+    );
+}
+
+#[test]
+fn hidden_whitespace_is_made_visible() {
+    let mut dom = dom(&[DomNode::new_paragraph(vec![tn(" a  b\nc")])]);
+    assert_eq!(issue_kinds(&dom), vec![RoundTripIssueKind::Whitespace]);
+    dom.canonicalize();
+    assert_eq!(dom.to_html(), "<p>\u{A0}a\u{A0}\u{A0}b c</p>");
+    assert_eq!(dom.round_trip_issues(), vec![]);
+}
+
+#[test]
+fn adjacent_lists_of_the_same_type_are_merged() {
+    let list = |text| {
+        DomNode::new_list(
+            ListType::Ordered,
+            vec![DomNode::new_list_item(vec![tn(text)])],
+        )
+    };
+    let mut dom = dom(&[list("a"), list("b")]);
+    let issues = dom.round_trip_issues();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, RoundTripIssueKind::AdjacentBlock);
+    assert_eq!(issues[0].handle.raw(), &vec![1]);
+    dom.canonicalize();
+    assert_eq!(dom.to_html(), "<ol><li>a</li><li>b</li></ol>");
+}
+
+#[test]
+fn attribute_values_survive_a_round_trip() {
+    let link = DomNode::new_link(
+        utf16("https://x.org/?q=\"a\"&b=<c>'d'"),
+        vec![tn("x")],
+        vec![],
+    );
+    let dom = dom(&[DomNode::new_paragraph(vec![link])]);
+    let html = dom.to_html().to_string();
+    assert_eq!(parse(&html).as_ref(), Ok(&dom), "{html}");
+}
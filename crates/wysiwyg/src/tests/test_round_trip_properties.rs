@@ -0,0 +1,260 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! Round trips of random documents through HTML, Markdown and the example
+//! format. When one fails, proptest shrinks the document to a minimal one
+//! which still fails.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+use widestring::Utf16String;
+
+use crate::dom::nodes::container_node::ContainerNodeKind;
+use crate::dom::nodes::dom_node::DomNodeKind;
+use crate::dom::nodes::DomNode;
+use crate::dom::to_plain_text::ToPlainText;
+use crate::dom::Dom;
+use crate::nbsp::is_placeholder;
+use crate::tests::testutils_composer_model::{cm, tx};
+use crate::tests::testutils_conversion::utf16;
+use crate::{
+    parse, ComposerModel, InlineFormatType, ListType, Location,
+    MarkdownEscaping, ToHtml,
+};
+
+const FORMATS: [&str; 5] = ["b", "i", "u", "del", "code"];
+
+fn text() -> impl Strategy<Value = DomNode<Utf16String>> {
+    "[ab <&>@\u{A0}\n]{1,5}".prop_map(|text| DomNode::new_text(utf16(&text)))
+}
+
+fn mention() -> DomNode<Utf16String> {
+    DomNode::Mention(
+        DomNode::new_mention(
+            utf16("https://matrix.to/#/@alice:matrix.org"),
+            utf16("Alice"),
+            vec![],
+        )
+        .unwrap(),
+    )
+}
+
+fn inline_nodes() -> impl Strategy<Value = Vec<DomNode<Utf16String>>> {
+    let leaf = prop_oneof![
+        4 => text(),
+        1 => Just(DomNode::new_line_break()),
+        1 => Just(mention()),
+    ];
+    let inline = leaf.prop_recursive(2, 12, 3, |inner| {
+        let children = vec(inner, 1..=3);
+        prop_oneof![
+            (select(&FORMATS[..]), children.clone()).prop_map(
+                |(format, children)| {
+                    DomNode::new_formatting_from_tag(utf16(format), children)
+                }
+            ),
+            children.prop_map(|children| DomNode::new_link(
+                utf16("https://element.io"),
+                children,
+                vec![],
+            )),
+        ]
+    });
+    vec(inline, 1..=3)
+}
+
+fn block() -> impl Strategy<Value = DomNode<Utf16String>> {
+    let list_type =
+        prop_oneof![Just(ListType::Ordered), Just(ListType::Unordered)];
+    let leaf = prop_oneof![
+        3 => inline_nodes().prop_map(DomNode::new_paragraph),
+        1 => (list_type, vec(inline_nodes(), 1..=2)).prop_map(
+            |(list_type, items)| {
+                DomNode::new_list(
+                    list_type,
+                    items.into_iter().map(DomNode::new_list_item).collect(),
+                )
+            }
+        ),
+    ];
+    // Known issue: code blocks gain a line each time they are parsed, so
+    // they are not generated
+    leaf.prop_recursive(2, 6, 2, |inner| {
+        vec(inner, 1..=2).prop_map(DomNode::new_quote)
+    })
+}
+
+/// Random documents, canonicalized so that they can survive a round trip
+/// through HTML.
+fn documents() -> impl Strategy<Value = Dom<Utf16String>> {
+    vec(block(), 1..=4).prop_map(|blocks| {
+        let mut dom = Dom::new(blocks);
+        dom.canonicalize();
+        dom
+    })
+}
+
+fn model_of(dom: &Dom<Utf16String>) -> ComposerModel<Utf16String> {
+    let mut model = ComposerModel::new();
+    model.set_content_from_html(&dom.to_html()).unwrap();
+    model
+}
+
+fn has_empty_blocks(dom: &Dom<Utf16String>) -> bool {
+    dom.iter_containers()
+        .any(|c| c.is_block_node() && c.text_len() == 0)
+}
+
+fn has_touching_formatting(dom: &Dom<Utf16String>) -> bool {
+    dom.iter_containers()
+        .filter(|c| c.is_formatting_node())
+        .any(|c| {
+            let handle = c.handle();
+            let kind = DomNodeKind::from_container_kind(c.kind());
+            let previous = (handle.index_in_parent() > 0)
+                .then(|| dom.lookup_node(&handle.prev_sibling()));
+            dom.lookup_node(&handle.parent_handle()).kind() == kind
+                || previous.is_some_and(|p| p.kind() == kind)
+        })
+}
+
+/// Whether any text is a lone non-breaking space, which is how empty blocks
+/// are written.
+fn has_placeholder_text(dom: &Dom<Utf16String>) -> bool {
+    dom.iter_text()
+        .any(|t| is_placeholder(&t.data().to_string()))
+}
+
+/// The document with the characters which are written as entities, e.g.
+/// `&amp;`, replaced by letters.
+fn without_escaped_text(mut dom: Dom<Utf16String>) -> Dom<Utf16String> {
+    let handles: Vec<_> = dom.iter_text().map(|t| t.handle()).collect();
+    for handle in handles {
+        let DomNode::Text(text) = dom.lookup_node_mut(&handle) else {
+            continue;
+        };
+        let data = text.data().to_string().replace(['&', '<', '>'], "b");
+        text.set_data(utf16(&data));
+    }
+    dom
+}
+
+fn has_formatting_with_spaces_at_its_edges(dom: &Dom<Utf16String>) -> bool {
+    dom.iter_containers()
+        .filter(|c| c.is_formatting_node())
+        .any(|c| {
+            let text = c.to_plain_text().to_string();
+            text.is_empty()
+                || text.starts_with(char::is_whitespace)
+                || text.ends_with(char::is_whitespace)
+        })
+}
+
+/// The text of the model, without the whitespace which formats differ in,
+/// or the delimiters of emphasis Markdown couldn't say, e.g. within a word.
+/// The generated text has none of those.
+fn visible_text(model: &ComposerModel<Utf16String>) -> String {
+    model
+        .get_content_as_plain_text()
+        .to_string()
+        .chars()
+        .filter(|c| !c.is_whitespace() && !"*_~".contains(*c))
+        .collect()
+}
+
+proptest! {
+    // Markdown and the example format can't be checked for documents with
+    // empty blocks, which are common
+    #![proptest_config(ProptestConfig {
+        cases: 64,
+        max_global_rejects: 1 << 14,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn canonical_documents_survive_a_round_trip(dom in documents()) {
+        // Known issue: a lone non-breaking space is read as an empty
+        // block's placeholder wherever it is
+        prop_assume!(!has_placeholder_text(&dom));
+        let html = dom.to_html().to_string();
+        let parsed = parse(&html);
+        prop_assert_eq!(parsed.as_ref(), Ok(&dom), "{}", html);
+        prop_assert_eq!(dom.round_trip_issues(), vec![], "{}", html);
+
+        let mut again = dom.clone();
+        prop_assert_eq!(again.canonicalize(), vec![]);
+        prop_assert_eq!(again, dom, "{}", html);
+    }
+
+    #[test]
+    fn markdown_keeps_the_text(dom in documents()) {
+        // Markdown can't say everything HTML can, e.g. empty blocks or
+        // emphasis within words, so only check that it loads again and
+        // keeps the text
+        prop_assume!(!has_empty_blocks(&dom));
+        // Underlines are written as HTML tags, which emphasis that Markdown
+        // reads differently can straddle, and inline code can't hold the
+        // HTML mentions are written as
+        prop_assume!(!dom.iter_containers().any(|c| {
+            [InlineFormatType::Underline, InlineFormatType::InlineCode]
+                .into_iter()
+                .any(|format| c.kind() == &ContainerNodeKind::Formatting(format))
+        }));
+        // The delimiters of the same formatting run together when it is
+        // nested or adjacent
+        prop_assume!(!has_touching_formatting(&dom));
+        // Known issue: emphasis around nothing, or with spaces just inside
+        // it, is written with delimiters which are not read back
+        prop_assume!(!has_formatting_with_spaces_at_its_edges(&dom));
+        let mut model = model_of(&dom);
+        model.set_markdown_escaping(MarkdownEscaping {
+            underscores: true,
+            asterisks: true,
+            hashes: true,
+            angle_brackets: true,
+            autolink_bare_urls: false,
+        });
+        let markdown = model.get_content_as_markdown();
+        let mut again = ComposerModel::new();
+        let loaded = again.set_content_from_markdown(&markdown);
+        prop_assert!(loaded.is_ok(), "{:?}: {}", loaded, markdown);
+        prop_assert_eq!(
+            visible_text(&again),
+            visible_text(&model),
+            "{}",
+            markdown
+        );
+    }
+
+    #[test]
+    fn example_format_survives_a_round_trip(
+        dom in documents(),
+        cursor in 0..64usize,
+    ) {
+        // A block without text is written with a placeholder, which reads
+        // back as text when the cursor is next to it, and can't tell apart
+        // positions in consecutive ones
+        prop_assume!(!has_empty_blocks(&dom));
+        // Known issue: markers are placed by the code units of the text,
+        // not of its HTML, so they land inside entities
+        let dom = without_escaped_text(dom);
+        // Known issue: a lone non-breaking space is read as an empty
+        // block's placeholder wherever it is
+        prop_assume!(!has_placeholder_text(&dom));
+        // Known issue: a selection which starts at the end of a line can't
+        // be written, so only cursors are checked
+        let mut model = model_of(&dom);
+        let cursor = Location::from(cursor.min(model.state.dom.text_len()));
+        model.select(cursor, cursor);
+        let example = tx(&model);
+        // A position can often be written in several places, e.g. either
+        // side of an empty link, so compare what was written rather than
+        // how
+        let again = cm(&example);
+        prop_assert_eq!(&again.state.dom, &model.state.dom, "{}", example);
+        prop_assert_eq!(again.get_selection(), model.get_selection(), "{}", example);
+    }
+}
@@ -10,7 +10,8 @@ use widestring::Utf16String;
 use crate::{
     dom::DomCreationError,
     tests::{testutils_composer_model::tx, testutils_conversion::utf16},
-    HtmlParseError,
+    AttributePolicy, ComposerModelConfig, HtmlParseError, HtmlSource, Location,
+    MessageEventContent, TextUpdate, WhitespacePolicy,
 };
 
 use super::testutils_composer_model::cm;
@@ -141,6 +142,176 @@ fn set_content_from_html_moves_cursor_to_the_end() {
     assert_eq!(tx(&model), "content|");
 }
 
+#[test]
+fn set_content_from_html_flattens_content_beyond_max_parse_depth() {
+    let mut model = cm("|");
+    model.set_max_parse_depth(2);
+    model
+        .set_content_from_html(&utf16("<b>a<i>b<u>c</u></i></b>"))
+        .unwrap();
+    assert_eq!(tx(&model), "<b>a<i>bc|</i></b>");
+}
+
+#[test]
+fn set_content_from_html_keeps_data_attributes_through_edits() {
+    let mut model = cm("|");
+    model.set_attribute_policy(AttributePolicy {
+        preserve_data_attributes: true,
+        ..Default::default()
+    });
+    model
+        .set_content_from_html(&utf16(
+            "<p data-bridge-id=\"42\">hello <em data-x=\"y\">world</em></p>",
+        ))
+        .unwrap();
+    model.replace_text(utf16("!"));
+    model.select(Location::from(0), Location::from(5));
+    model.bold();
+    assert_eq!(
+        model.get_content_as_html(),
+        "<p data-bridge-id=\"42\"><strong>hello</strong> \
+        <em data-x=\"y\">world!</em></p>"
+    );
+}
+
+#[test]
+fn set_content_from_html_drops_data_attributes_by_default() {
+    let mut model = cm("|");
+    model
+        .set_content_from_html(&utf16("<p data-bridge-id=\"42\">hello</p>"))
+        .unwrap();
+    assert_eq!(model.get_content_as_html(), "<p>hello</p>");
+}
+
+#[test]
+fn set_content_from_html_keeps_allowed_classes_through_edits() {
+    let mut model = cm("|");
+    model.set_attribute_policy(AttributePolicy {
+        allowed_classes: vec!["language-*".into(), "spoiler".into()],
+        ..Default::default()
+    });
+    model
+        .set_content_from_html(&utf16(
+            "<p>a <span class=\"spoiler\">secret</span></p>\
+            <pre><code class=\"language-rust\">fn</code></pre>",
+        ))
+        .unwrap();
+    model.replace_text(utf16(" main"));
+    model.select(Location::from(4), Location::from(4));
+    model.replace_text(utf16("!"));
+    assert_eq!(
+        model.get_content_as_html(),
+        "<p>a <span class=\"spoiler\">se!cret</span></p>\
+        <pre><code class=\"language-rust\">fn main</code></pre>"
+    );
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "a <span class=\"spoiler\">se!cret</span>\
+        <pre><code class=\"language-rust\">fn main</code></pre>"
+    );
+}
+
+#[test]
+fn set_content_from_html_accepts_legacy_tags() {
+    let mut model = cm("|");
+    model
+        .set_content_from_html(&utf16(
+            "<div><strike>old</strike> <font color=\"red\">news</font></div>",
+        ))
+        .unwrap();
+    model.replace_text(utf16("!"));
+    assert_eq!(
+        tx(&model),
+        "<p><del>old</del> <span data-mx-color=\"red\">news!|</span></p>"
+    );
+}
+
+#[test]
+fn set_content_from_html_keeps_at_room_as_text_if_asked() {
+    let mut model = cm("|");
+    model.set_at_room_detection(false);
+    model
+        .set_content_from_html(&utf16(
+            "Hi @room and <a href=\"#\" data-mention-type=\"at-room\">@room</a>",
+        ))
+        .unwrap();
+    assert!(!model.get_mentions_state().has_at_room_mention);
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "Hi @room and <a data-mention-type=\"at-room\" href=\"#\">@room</a>"
+    );
+}
+
+#[test]
+fn set_content_from_markdown_keeps_at_room_as_text_if_asked() {
+    let mut model = cm("|");
+    model.set_at_room_detection(false);
+    model
+        .set_content_from_markdown(&utf16("**@room**"))
+        .unwrap();
+    assert!(!model.get_mentions_state().has_at_room_mention);
+    model.set_at_room_detection(true);
+    model
+        .set_content_from_markdown(&utf16("**@room**"))
+        .unwrap();
+    assert!(model.get_mentions_state().has_at_room_mention);
+}
+
+#[test]
+fn replace_html_keeps_at_room_as_text_if_asked() {
+    let mut model = cm("|");
+    model.set_at_room_detection(false);
+    model.replace_html(utf16("<p>@room</p>"), HtmlSource::Matrix);
+    assert!(!model.get_mentions_state().has_at_room_mention);
+    assert_eq!(tx(&model), "<p>@room|</p>");
+}
+
+#[test]
+fn set_content_from_html_preserves_whitespace_if_asked() {
+    let mut model = cm("|");
+    model.set_whitespace_policy(WhitespacePolicy::Preserve);
+    model
+        .set_content_from_html(&utf16("<p>\n  a    b\n c\n</p>"))
+        .unwrap();
+    assert_eq!(
+        model.get_content_as_html(),
+        "<p>\u{A0} a\u{A0} \u{A0} b</p><p>\u{A0}c</p>"
+    );
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "\u{A0} a\u{A0} \u{A0} b<br />\u{A0}c"
+    );
+}
+
+#[test]
+fn update_html_preserves_whitespace_if_asked() {
+    let mut model = cm("|");
+    model.set_whitespace_policy(WhitespacePolicy::Preserve);
+    let update = model
+        .set_content_from_html(&utf16("<p>\n  a    b\n c\n</p>"))
+        .unwrap();
+    let TextUpdate::ReplaceAll(replace_all) = update.text_update else {
+        panic!("expected ReplaceAll");
+    };
+    assert_eq!(
+        replace_all.replacement_html,
+        "<p>\u{A0} a\u{A0} \u{A0} b</p><p>\u{A0}c</p>"
+    );
+    assert_eq!(replace_all.replacement_html, model.get_content_as_html());
+}
+
+#[test]
+fn set_content_from_html_collapses_whitespace_by_default() {
+    let mut model = cm("|");
+    model
+        .set_content_from_html(&utf16("<p>\n  a    b\n c\n</p>"))
+        .unwrap();
+    assert_eq!(
+        model.get_content_as_html(),
+        "<p>a\u{A0}\u{A0}\u{A0}\u{A0}b c</p>"
+    );
+}
+
 #[test]
 fn set_content_from_html_single_br() {
     let mut model = cm("|");
@@ -212,6 +383,65 @@ fn set_content_from_markdown_codeblock_with_newlines_in_the_middle() {
     assert_eq!(tx(&model), "<pre><code>I am\na code block|</code></pre>");
 }
 
+#[test]
+fn set_content_from_markdown_codeblock_keeps_indentation() {
+    let mut model = cm("|");
+    model
+        .set_content_from_markdown(&utf16(
+            "```\nfn a() {\n    b();\n\tc();\n  \n}\n```",
+        ))
+        .unwrap();
+    assert_eq!(
+        tx(&model),
+        "<pre><code>fn a() {\n    b();\n\tc();\n  \n}|</code></pre>"
+    );
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<pre><code>fn a() {\n    b();\n\tc();\n  \n}</code></pre>"
+    );
+    assert_eq!(
+        model.get_content_as_markdown(),
+        "```\nfn a() {\n    b();\n\tc();\n  \n}\n```\n"
+    );
+}
+
+#[test]
+fn set_content_from_markdown_codeblock_keeps_leading_blank_lines() {
+    let mut model = cm("|");
+    model
+        .set_content_from_markdown(&utf16("```\n\n  x\n```"))
+        .unwrap();
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<pre><code>\n  x</code></pre>"
+    );
+    assert_eq!(model.get_content_as_markdown(), "```\n\n  x\n```\n");
+}
+
+#[test]
+fn set_content_from_markdown_codeblock_after_paragraph() {
+    let mut model = cm("|");
+    model
+        .set_content_from_markdown(&utf16("a\n\n```\n  x  \n```"))
+        .unwrap();
+    assert_eq!(tx(&model), "<p>a</p><pre><code>  x  |</code></pre>");
+}
+
+#[test]
+fn reediting_indented_code_keeps_it() {
+    let mut model = cm("|");
+    model
+        .set_content_from_markdown(&utf16("```\n\n  x\n\n    y\n```"))
+        .unwrap();
+    let html = model.get_content_as_message_html();
+    model.set_content_from_html(&html).unwrap();
+    assert_eq!(model.get_content_as_message_html(), html);
+    assert_eq!(
+        model.get_content_as_markdown(),
+        "```\n\n  x\n\n    y\n```\n"
+    );
+}
+
 #[test]
 fn set_content_from_markdown_multiple_new_lines() {
     let mut model = cm("|");
@@ -236,3 +466,129 @@ fn set_content_from_markdown_ordered_list_with_start() {
     model.set_content_from_markdown(&utf16("3. First")).unwrap();
     assert_eq!(tx(&model), "<ol start=\"3\"><li>First|</li></ol>");
 }
+
+#[test]
+fn set_content_from_markdown_creates_mentions() {
+    let mut model = cm("|");
+    model
+        .set_content_from_markdown(&utf16(
+            "hi [Alice](https://matrix.to/#/@alice:matrix.org), \
+            [Room](<https://matrix.to/#/#room:matrix.org>) and @room",
+        ))
+        .unwrap();
+    assert_eq!(
+        tx(&model),
+        "hi <a data-mention-type=\"user\" href=\"https://matrix.to/#/@alice:matrix.org\" contenteditable=\"false\">Alice</a>, \
+        <a data-mention-type=\"room\" href=\"https://matrix.to/#/#room:matrix.org\" contenteditable=\"false\">Room</a> and \
+        <a data-mention-type=\"at-room\" href=\"#\" contenteditable=\"false\">@room</a>|"
+    );
+}
+
+#[test]
+fn set_content_from_markdown_keeps_other_links_and_code() {
+    let mut model = cm("|");
+    model
+        .set_content_from_markdown(&utf16(
+            "[x](https://example.org) `@room` and ```@alice:matrix.org```",
+        ))
+        .unwrap();
+    assert_eq!(
+        tx(&model),
+        "<a href=\"https://example.org\">x</a> <code>@room</code> and \
+        <code>@alice:matrix.org|</code>"
+    );
+}
+
+#[test]
+fn message_markdown_mentions_are_restored() {
+    let mut model = cm("|");
+    model
+        .set_content_from_html(&utf16(
+            "<a href=\"https://matrix.to/#/@alice:matrix.org\">[Alice]</a> @room",
+        ))
+        .unwrap();
+    let markdown = model.get_content_as_message_markdown();
+    let html = model.get_content_as_html();
+    model.set_content_from_markdown(&markdown).unwrap();
+    assert_eq!(model.get_content_as_html(), html);
+}
+
+#[test]
+fn set_content_from_html_with_headings_and_rules() {
+    let mut model = cm("|");
+    model
+        .set_content_from_html(&utf16("<h1>Title</h1><hr><p>Body</p>"))
+        .unwrap();
+    assert_eq!(
+        tx(&model),
+        "<p><strong>Title</strong></p><p>&nbsp;</p><p>Body|</p>"
+    );
+}
+
+#[test]
+fn set_content_from_html_leniently_skips_unknown_elements() {
+    let mut model = cm("|");
+    model.set_config(ComposerModelConfig::default().with_lenient_parsing(true));
+    model
+        .set_content_from_html(&utf16("<p>x<sup>2</sup></p>"))
+        .unwrap();
+    assert_eq!(tx(&model), "<p>x2|</p>");
+    assert_eq!(model.parse_warnings(), ["Node `sup` is not supported"]);
+
+    model.set_content_from_html(&utf16("<p>x</p>")).unwrap();
+    assert!(model.parse_warnings().is_empty());
+}
+
+#[test]
+fn set_content_from_html_fails_on_unknown_elements_by_default() {
+    let mut model = cm("|");
+    assert!(model
+        .set_content_from_html(&utf16("<p>x<sup>2</sup></p>"))
+        .is_err());
+    assert!(model.parse_warnings().is_empty());
+}
+
+#[test]
+fn set_content_from_event_content_uses_the_html_body() {
+    let mut model = cm("|");
+    model
+        .set_content_from_event_content(&MessageEventContent {
+            body: "a **b**".into(),
+            format: Some("org.matrix.custom.html".into()),
+            formatted_body: Some("a <strong>b</strong>".into()),
+            new_content: None,
+        })
+        .unwrap();
+    assert_eq!(tx(&model), "a <strong>b|</strong>");
+}
+
+#[test]
+fn set_content_from_event_content_falls_back_to_the_plain_body() {
+    let mut model = cm("|");
+    model
+        .set_content_from_event_content(&MessageEventContent {
+            body: "<b>\nc".into(),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(tx(&model), "<p>&lt;b&gt;</p><p>c|</p>");
+}
+
+#[test]
+fn set_content_from_event_content_uses_the_new_content_of_edits() {
+    let mut model = cm("|");
+    let new_content = MessageEventContent {
+        body: "new".into(),
+        format: Some("org.matrix.custom.html".into()),
+        formatted_body: Some("<em>new</em>".into()),
+        new_content: None,
+    };
+    model
+        .set_content_from_event_content(&MessageEventContent {
+            body: "* new".into(),
+            new_content: Some(Box::new(new_content)),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(tx(&model), "<em>new|</em>");
+}
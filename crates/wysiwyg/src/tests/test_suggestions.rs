@@ -4,7 +4,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
-use crate::MenuAction;
+use widestring::Utf16String;
+
+use crate::{ComposerModel, MenuAction, PatternKey, SuggestionPattern};
 
 use super::testutils_composer_model::{cm, tx};
 
@@ -18,3 +20,66 @@ fn test_replace_text_suggestion() {
     model.replace_text_suggestion("/invite".into(), suggestion, true);
     assert_eq!(tx(&model), "/invite&nbsp;|");
 }
+
+#[test]
+fn a_new_suggestion_starts_right_after_accepting_a_mention() {
+    let mut model = cm("Hey |");
+    accept_mention(&mut model);
+    let update = model.replace_text("@".into());
+    assert_eq!(update.menu_action, at("", 6, 7));
+    let update = model.replace_text("b".into());
+    assert_eq!(update.menu_action, at("b", 6, 8));
+}
+
+#[test]
+fn a_new_suggestion_starts_after_a_mention_accepted_mid_sentence() {
+    let mut model = cm("Hey |, bye");
+    accept_mention(&mut model);
+    assert_eq!(
+        tx(&model),
+        "Hey <a data-mention-type=\"user\" href=\"https://matrix.to/#/@alice:matrix.org\" contenteditable=\"false\">Alice</a>|, bye"
+    );
+    let update = model.replace_text(" @".into());
+    assert_eq!(update.menu_action, at("", 6, 7));
+}
+
+#[test]
+fn punctuation_after_the_cursor_is_not_part_of_a_suggestion() {
+    let mut model = cm("Hey |, bye");
+    let update = model.replace_text("@al".into());
+    assert_eq!(update.menu_action, at("al", 4, 7));
+}
+
+#[test]
+fn suggestions_follow_the_content_through_undo() {
+    let mut model = cm("Hey |");
+    accept_mention(&mut model);
+    model.replace_text("@b".into());
+    let update = model.undo();
+    assert_eq!(update.menu_action, MenuAction::None);
+    let update = model.undo();
+    assert_eq!(update.menu_action, at("al", 4, 7));
+}
+
+/// Type `@al` and accept the suggestion as a mention of Alice.
+fn accept_mention(model: &mut ComposerModel<Utf16String>) {
+    let update = model.replace_text("@al".into());
+    let MenuAction::Suggestion(suggestion) = update.menu_action else {
+        panic!("No suggestion pattern found")
+    };
+    model.insert_mention_at_suggestion(
+        "https://matrix.to/#/@alice:matrix.org".into(),
+        "Alice".into(),
+        suggestion,
+        vec![],
+    );
+}
+
+fn at(text: &str, start: usize, end: usize) -> MenuAction {
+    MenuAction::Suggestion(SuggestionPattern {
+        key: PatternKey::At,
+        text: text.into(),
+        start,
+        end,
+    })
+}
@@ -6,7 +6,8 @@
 
 use crate::{
     dom::parser::markdown::MarkdownHTMLParser,
-    tests::testutils_composer_model::tx, ComposerModel, ToMarkdown,
+    tests::testutils_composer_model::tx, ComposerModel, MarkdownEscaping,
+    MarkdownMentions, ToMarkdown,
 };
 use widestring::Utf16String;
 
@@ -24,6 +25,7 @@ fn text() {
 // Athough the plain text content of a Matrix message _may_ contain markdown,
 // the format is not specified. It is more important for the message to be
 // human readable than valid or machine readable markdown.
+// Escaping can be turned on with `MarkdownEscaping`, see below.
 #[test]
 fn text_with_ascii_punctuation() {
     assert_to_md_no_roundtrip(r"<em>**b**</em>", r"***b***");
@@ -226,10 +228,29 @@ fn list_ordered_and_unordered() {
 fn user_mention_for_message() {
     assert_to_md_no_roundtrip(
         r#"<a href="https://matrix.to/#/@alice:matrix.org">test</a>"#,
-        r#"test"#,
+        r#"[test](https://matrix.to/#/@alice:matrix.org)"#,
     );
 }
 
+#[test]
+fn user_mention_for_message_as_mx_id_or_display_text() {
+    let mut model =
+        cm("<a href=\"https://matrix.to/#/@alice:matrix.org\">[Alice]</a>|");
+    assert_eq!(
+        model.get_content_as_message_markdown(),
+        "[\\[Alice\\]](https://matrix.to/#/@alice:matrix.org)"
+    );
+    model.set_markdown_mentions(MarkdownMentions::MxId);
+    assert_eq!(model.get_content_as_message_markdown(), "@alice:matrix.org");
+    model.set_markdown_mentions(MarkdownMentions::DisplayText);
+    assert_eq!(model.get_content_as_message_markdown(), "[Alice]");
+    // The composer keeps mentions as HTML whatever the option
+    assert!(model
+        .get_content_as_markdown()
+        .to_string()
+        .starts_with("<a data-mention-type=\"user\""));
+}
+
 #[test]
 fn user_mention_for_composer() {
     assert_to_composer_md(
@@ -242,7 +263,7 @@ fn user_mention_for_composer() {
 fn room_mention_for_message() {
     assert_to_md_no_roundtrip(
         r#"<a href="https://matrix.to/#/#alice:matrix.org">test</a>"#,
-        r#"#alice:matrix.org"#,
+        r#"[#alice:matrix.org](https://matrix.to/#/#alice:matrix.org)"#,
     );
 }
 
@@ -269,6 +290,61 @@ fn at_room_mention_for_composer() {
     assert_eq!(model.get_content_as_message_markdown(), "@room hello!");
 }
 
+#[test]
+fn text_is_escaped_if_asked() {
+    let mut model = cm("<p># a_b *c*</p><blockquote>&gt; d</blockquote>|");
+    model.set_markdown_escaping(MarkdownEscaping {
+        underscores: true,
+        asterisks: true,
+        hashes: true,
+        angle_brackets: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        model.get_content_as_message_markdown(),
+        "\\# a\\_b \\*c\\*\n> \\> d\n\n"
+    );
+}
+
+#[test]
+fn text_is_escaped_only_for_the_characters_asked_for() {
+    let mut model = cm("# a_b *c*|");
+    model.set_markdown_escaping(MarkdownEscaping {
+        underscores: true,
+        ..Default::default()
+    });
+    assert_eq!(model.get_content_as_markdown(), "# a\\_b *c*");
+}
+
+#[test]
+fn code_is_never_escaped() {
+    let mut model = cm("<code>a_b</code><pre><code>*c*</code></pre>|");
+    model.set_markdown_escaping(MarkdownEscaping {
+        underscores: true,
+        asterisks: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        model.get_content_as_message_markdown(),
+        "`` a_b ``\n```\n*c*\n```\n\n"
+    );
+}
+
+#[test]
+fn bare_urls_are_written_as_autolinks_if_asked() {
+    let mut model = cm("<a href=\"https://x.org/a_b\">https://x.org/a_b</a> \
+        <a href=\"https://y.org\">y</a>|");
+    model.set_markdown_escaping(MarkdownEscaping {
+        underscores: true,
+        autolink_bare_urls: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        model.get_content_as_message_markdown(),
+        "<https://x.org/a_b> [y](<https://y.org>)"
+    );
+}
+
 fn assert_to_md_no_roundtrip(html: &str, expected_markdown: &str) {
     let markdown = to_message_markdown(html);
     assert_eq!(markdown, expected_markdown);
@@ -4,7 +4,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
 // Please see LICENSE in the repository root for full details.
 
+use widestring::Utf16String;
+
 use crate::tests::testutils_composer_model::{cm, tx};
+use crate::{ComposerUpdate, FormattingTags, PlaceholderPolicy, TextUpdate};
 
 #[test]
 fn outputs_paragraphs_as_line_breaks() {
@@ -75,3 +78,153 @@ fn only_outputs_href_inner_text_for_at_room_mention() {
     let message_output = model.get_content_as_message_html();
     assert_eq!(message_output, "@room\u{a0}");
 }
+
+#[test]
+fn outputs_bold_and_italic_with_the_tags_they_were_created_with() {
+    let model = cm("<b>a</b> <strong>b</strong> <i>c</i> <em>d|</em>");
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<b>a</b> <strong>b</strong> <i>c</i> <em>d</em>"
+    );
+}
+
+#[test]
+fn outputs_bold_and_italic_as_semantic_tags() {
+    let mut model = cm("<b>a</b> <strong>b</strong> <i>c</i> <em>d|</em>");
+    model.set_formatting_tags(FormattingTags::Semantic);
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<strong>a</strong> <strong>b</strong> <em>c</em> <em>d</em>"
+    );
+    assert_eq!(
+        model.get_content_as_html(),
+        "<strong>a</strong> <strong>b</strong> <em>c</em> <em>d</em>"
+    );
+}
+
+#[test]
+fn updates_write_bold_and_italic_with_the_configured_tags() {
+    let mut model = cm("<b>a|</b>");
+    model.set_formatting_tags(FormattingTags::Semantic);
+    let snapshot = model.create_snapshot();
+    let html_of = |update: ComposerUpdate<Utf16String>| match update.text_update
+    {
+        TextUpdate::ReplaceAll(replace_all) => replace_all.replacement_html,
+        _ => panic!("expected ReplaceAll"),
+    };
+
+    let html = html_of(model.replace_text("b".into()));
+    assert_eq!(html, "<strong>ab</strong>");
+    assert_eq!(html, model.get_content_as_html());
+
+    let html = html_of(model.restore_snapshot(snapshot));
+    assert_eq!(html, "<strong>a</strong>");
+    assert_eq!(html, model.get_content_as_html());
+}
+
+#[test]
+fn outputs_bold_and_italic_as_presentational_tags() {
+    let mut model = cm("|");
+    model.set_formatting_tags(FormattingTags::Presentational);
+    model.bold();
+    model.italic();
+    model.replace_text("a".into());
+    assert_eq!(tx(&model), "<strong><em>a|</em></strong>");
+    assert_eq!(model.get_content_as_message_html(), "<b><i>a</i></b>");
+}
+
+#[test]
+fn escapes_quotes_and_angle_brackets_in_link_urls() {
+    let mut model = cm("|");
+    model.set_link_with_text(
+        "https://x.org/?q=\"a\"&b=<c>".into(),
+        "x".into(),
+        vec![],
+    );
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<a href=\"https://x.org/?q=&quot;a&quot;&amp;b=&lt;c&gt;\">x</a>"
+    );
+}
+
+#[test]
+fn escapes_quotes_in_style_values() {
+    let model = cm("<a style=\"font-family:&quot;Arial&quot;\" \
+        href=\"https://x.org\">x|</a>");
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<a style=\"font-family:&quot;Arial&quot;\" href=\"https://x.org\">x</a>"
+    );
+}
+
+#[test]
+fn style_attributes_can_be_left_out_of_messages() {
+    let mut model = cm("<a style=\"color:red\" href=\"https://x.org\">x|</a>");
+    model.set_strip_message_styles(true);
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<a href=\"https://x.org\">x</a>"
+    );
+    assert_eq!(
+        model.get_content_as_html(),
+        "<a style=\"color:red\" href=\"https://x.org\">x</a>"
+    );
+}
+
+#[test]
+fn empty_code_blocks_have_no_placeholder() {
+    let mut model = cm("|");
+    model.code_block();
+    assert_eq!(tx(&model), "<pre><code>&nbsp;|</code></pre>");
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<pre><code></code></pre>"
+    );
+}
+
+#[test]
+fn non_breaking_spaces_in_text_are_kept() {
+    let mut model = cm("|");
+    model.replace_text("\u{A0}".into());
+    model.enter();
+    model.replace_text("a\u{A0}".into());
+    assert_eq!(model.get_content_as_message_html(), "\u{A0}<br />a\u{A0}");
+}
+
+#[test]
+fn placeholders_can_be_left_out_of_editor_html() {
+    let mut model = cm("<p>a</p><p></p><pre><code>|</code></pre>");
+    model.set_placeholder_policy(PlaceholderPolicy::Never);
+    assert_eq!(
+        model.get_content_as_html(),
+        "<p>a</p><p></p><pre><code></code></pre>"
+    );
+}
+
+#[test]
+fn link_and_mention_text_can_be_isolated() {
+    let mut model = cm("<a href=\"https://x.org\">שלום</a> and \
+        <a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>|");
+    model.set_isolate_message_bidi(true);
+    assert_eq!(
+        model.get_content_as_message_html(),
+        "<a href=\"https://x.org\">\u{2068}שלום\u{2069}</a> and \
+        <a href=\"https://matrix.to/#/@alice:matrix.org\">\u{2068}Alice\u{2069}</a>"
+    );
+    assert_eq!(
+        model.get_content_as_html(),
+        "<a href=\"https://x.org\">שלום</a> and \
+        <a data-mention-type=\"user\" href=\"https://matrix.to/#/@alice:matrix.org\" contenteditable=\"false\">Alice</a>"
+    );
+}
+
+#[test]
+fn isolates_are_removed_when_parsing() {
+    let mut model = cm("|");
+    model.set_isolate_message_bidi(true);
+    let html = "<a href=\"https://x.org\">\u{2068}a <b>b</b>\u{2069}</a> \
+        <a href=\"https://matrix.to/#/@alice:matrix.org\">\u{2068}Alice\u{2069}</a>";
+    model.set_content_from_html(&html.into()).unwrap();
+    assert_eq!(model.get_content_as_message_html(), html);
+    assert_eq!(model.get_content_as_plain_text(), "a b Alice");
+}
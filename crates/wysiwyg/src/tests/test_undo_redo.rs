@@ -7,9 +7,13 @@
 use crate::tests::testutils_composer_model::{cm, tx};
 
 use crate::dom::nodes::{DomNode, TextNode};
-use crate::{InlineFormatType, Location};
+use crate::{
+    ComposerModelConfig, FormattingTags, InlineFormatType, Location,
+    ReplaceRange, TextUpdate,
+};
 
 use crate::tests::testutils_conversion::utf16;
+use widestring::Utf16String;
 
 #[test]
 fn undoing_action_restores_previous_state() {
@@ -181,3 +185,88 @@ fn replacing_text_with_newlines_only_adds_one_to_undo_stack() {
     model.undo();
     assert_eq!(tx(&model), "abc|");
 }
+
+fn with_range_updates(text: &str) -> crate::ComposerModel<Utf16String> {
+    let mut model = cm(text);
+    model.set_config(ComposerModelConfig::default().with_range_updates(true));
+    model
+}
+
+fn replace_range(update: TextUpdate<Utf16String>) -> ReplaceRange<Utf16String> {
+    match update {
+        TextUpdate::ReplaceRange(replace_range) => replace_range,
+        _ => panic!("expected ReplaceRange"),
+    }
+}
+
+#[test]
+fn undo_replaces_all_of_the_content_by_default() {
+    let mut model = cm("<p>a</p><p>b|</p>");
+    model.replace_text(utf16("c"));
+    let update = model.undo();
+    assert!(matches!(update.text_update, TextUpdate::ReplaceAll(_)));
+}
+
+#[test]
+fn undo_replaces_only_the_changed_nodes_and_their_neighbours() {
+    let mut model =
+        with_range_updates("<p>a</p><p>b</p><p>c|</p><p>d</p><p>e</p>");
+    model.replace_text(utf16("x"));
+    let range = replace_range(model.undo().text_update);
+    assert_eq!(range.first_node, 1);
+    assert_eq!(range.removed_nodes, 3);
+    assert_eq!(range.replacement_html, "<p>b</p><p>c</p><p>d</p>");
+    assert_eq!(
+        (range.start, range.end),
+        (Location::from(5), Location::from(5))
+    );
+    assert_eq!(tx(&model), "<p>a</p><p>b</p><p>c|</p><p>d</p><p>e</p>");
+}
+
+#[test]
+fn redo_replaces_only_the_changed_nodes_and_their_neighbours() {
+    let mut model = with_range_updates("<p>a</p><p>b|</p><p>c</p>");
+    model.enter();
+    model.undo();
+    let range = replace_range(model.redo().text_update);
+    assert_eq!(range.first_node, 1);
+    assert_eq!(range.removed_nodes, 2);
+    assert_eq!(range.replacement_html, "<p>b</p><p>\u{a0}</p><p>c</p>");
+}
+
+#[test]
+fn replacing_the_range_gives_the_content_after_undo() {
+    let mut model = with_range_updates("<p>a</p><ul><li>b|</li></ul><p>c</p>");
+    model.replace_text(utf16("d"));
+    model.enter();
+    model.enter();
+    assert_eq!(
+        model.get_content_as_html(),
+        "<p>a</p><ul><li>bd</li></ul><p>\u{a0}</p><p>c</p>"
+    );
+    let mut nodes = [
+        "<p>a</p>",
+        "<ul><li>bd</li></ul>",
+        "<p>\u{a0}</p>",
+        "<p>c</p>",
+    ]
+    .map(String::from)
+    .to_vec();
+    let range = replace_range(model.undo().text_update);
+    nodes.splice(
+        range.first_node..range.first_node + range.removed_nodes,
+        [range.replacement_html.to_string()],
+    );
+    assert_eq!(nodes.concat(), model.get_content_as_html().to_string());
+}
+
+#[test]
+fn replacing_the_range_writes_the_configured_formatting_tags() {
+    let mut model = with_range_updates("<p><b>a</b></p><p>b|</p>");
+    model.set_formatting_tags(FormattingTags::Semantic);
+    model.replace_text(utf16("c"));
+    let range = replace_range(model.undo().text_update);
+    assert_eq!(range.first_node, 0);
+    assert_eq!(range.replacement_html, "<p><strong>a</strong></p><p>b</p>");
+    assert_eq!(range.replacement_html, model.get_content_as_html());
+}
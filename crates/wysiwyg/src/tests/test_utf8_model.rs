@@ -0,0 +1,70 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use crate::tests::testutils_composer_model::sel;
+use crate::{
+    Direction, Granularity, Location, TextUpdate, ToHtml, Utf8ComposerModel,
+};
+
+fn model(text: &str) -> Utf8ComposerModel {
+    let mut model = Utf8ComposerModel::new();
+    model.replace_text(text.to_owned());
+    model
+}
+
+#[test]
+fn offsets_are_counted_in_bytes() {
+    let model = model("é👍");
+    assert_eq!(model.get_selection(), sel(6, 6));
+    assert_eq!(model.state.dom.text_len(), 6);
+}
+
+#[test]
+fn formatting_a_byte_range() {
+    let mut model = model("aé👍b");
+    model.select(Location::from(1), Location::from(7));
+    model.bold();
+    assert_eq!(model.get_content_as_html(), "a<strong>é👍</strong>b");
+}
+
+#[test]
+fn replacing_a_multibyte_character() {
+    let mut model = model("aé👍b");
+    model.select(Location::from(3), Location::from(7));
+    model.replace_text("c".to_owned());
+    assert_eq!(model.get_content_as_html(), "aécb");
+    assert_eq!(model.get_selection(), sel(4, 4));
+}
+
+#[test]
+fn deleting_removes_whole_characters() {
+    let mut model = model("aé👍");
+    model.backspace();
+    assert_eq!(model.get_content_as_html(), "aé");
+    assert_eq!(model.get_selection(), sel(3, 3));
+    model.select(Location::from(0), Location::from(0));
+    model.delete();
+    assert_eq!(model.get_content_as_html(), "é");
+}
+
+#[test]
+fn moving_by_character_skips_whole_graphemes() {
+    let mut model = model("a👍🏽b");
+    model.select(Location::from(1), Location::from(1));
+    let update =
+        model.extend_selection(Direction::Forwards, Granularity::Character);
+    assert!(matches!(update.text_update, TextUpdate::Select(_)));
+    assert_eq!(model.get_selection(), sel(1, 9));
+}
+
+#[test]
+fn html_can_be_set_and_read_back() {
+    let mut model = Utf8ComposerModel::new();
+    model
+        .set_content_from_html(&"<p>😮‍💨 <em>ok</em></p>".to_owned())
+        .unwrap();
+    assert_eq!(model.get_selection(), sel(14, 14));
+    assert_eq!(model.state.dom.to_html(), "<p>😮‍💨 <em>ok</em></p>");
+}
@@ -0,0 +1,66 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use widestring::Utf16String;
+
+use crate::tests::testutils_composer_model::cm;
+use crate::{validate_message_html, HtmlIssue};
+
+#[test]
+fn message_html_written_by_the_composer_is_valid() {
+    let model = cm("<p><b>a</b> <a href=\"https://matrix.org\">b</a></p>\
+        <ol start=\"3\"><li>c</li></ol><pre><code>d</code></pre>|");
+    let html: Utf16String = model.get_content_as_message_html();
+    assert_eq!(validate_message_html(&html.to_string()), vec![]);
+}
+
+#[test]
+fn unknown_tags_are_reported_and_their_content_checked() {
+    assert_eq!(
+        validate_message_html(
+            "<sup><a href=\"javascript:alert(1)\">a</a></sup><span>b</span>"
+        ),
+        vec![
+            HtmlIssue::UnknownTag { tag: "sup".into() },
+            HtmlIssue::BadUriScheme {
+                tag: "a".into(),
+                attribute: "href".into(),
+                uri: "javascript:alert(1)".into(),
+            },
+            HtmlIssue::UnknownTag { tag: "span".into() },
+        ]
+    );
+}
+
+#[test]
+fn attributes_not_allowed_by_the_spec_are_reported() {
+    assert_eq!(
+        validate_message_html(
+            "<p style=\"color: red\">a</p><font color=\"red\">b</font>"
+        ),
+        vec![HtmlIssue::BadAttribute {
+            tag: "p".into(),
+            attribute: "style".into(),
+        }]
+    );
+}
+
+#[test]
+fn badly_nested_lists_are_reported() {
+    assert_eq!(
+        validate_message_html("<ul><p>a</p></ul><li>b</li>"),
+        vec![
+            HtmlIssue::NotAListItem { tag: "p".into() },
+            HtmlIssue::ListItemOutsideList,
+        ]
+    );
+}
+
+#[test]
+fn html_which_cant_be_parsed_is_malformed() {
+    let issues = validate_message_html("<p>a</b>");
+    assert!(!issues.is_empty());
+    assert!(issues.iter().all(|i| matches!(i, HtmlIssue::Malformed(_))));
+}
@@ -13,6 +13,7 @@ where
 {
     Keep,
     ReplaceAll(ReplaceAll<S>),
+    ReplaceRange(ReplaceRange<S>),
     Select(Selection),
 }
 
@@ -26,6 +27,25 @@ where
     pub end: Location,
 }
 
+/// An update which changed only some of the top-level nodes of the content,
+/// see [crate::ComposerModelConfig::with_range_updates]. Each top-level node
+/// is one node of the HTML, so clients can replace the nodes from
+/// `first_node` with the HTML of the new ones and leave the rest alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceRange<S>
+where
+    S: UnicodeString,
+{
+    /// The index of the first top-level node replaced
+    pub first_node: usize,
+    /// How many top-level nodes of the previous content are replaced
+    pub removed_nodes: usize,
+    /// The HTML of the top-level nodes which replace them
+    pub replacement_html: S,
+    pub start: Location,
+    pub end: Location,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Selection {
     pub start: Location,
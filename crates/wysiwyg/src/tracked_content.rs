@@ -0,0 +1,325 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use std::sync::Arc;
+
+use crate::changed_handles::common_ends;
+use crate::deleted_mention::{deleted_mentions, DeletedMention};
+use crate::dom::Dom;
+use crate::{ChangedHandles, DomNode, Location, OffsetMapping, UnicodeString};
+
+/// The content as of an update, which the next one is compared with to
+/// report how offsets moved, which mentions were removed and which
+/// top-level nodes changed.
+///
+/// It is kept per top-level node, and the nodes which an edit left alone
+/// are shared with the previous content rather than worked out again, so
+/// tracking costs little more than the size of the edited blocks.
+#[derive(Clone)]
+pub(crate) struct TrackedContent<S: UnicodeString> {
+    nodes: Vec<Arc<TrackedNode<S>>>,
+}
+
+struct TrackedNode<S: UnicodeString> {
+    /// See [DomNode::content_hash]
+    hash: u64,
+    is_block: bool,
+    /// The code units of the node laid out so that their indices match
+    /// [Location]s: line breaks, mentions and the gaps between block nodes
+    /// each take up a single code unit
+    code_units: Vec<S::CodeUnit>,
+    /// The mentions in the node, located from the start of the node
+    mentions: Vec<DeletedMention>,
+}
+
+/// How the content changed between two [TrackedContent]s.
+pub(crate) struct ContentChanges {
+    pub offset_mapping: OffsetMapping,
+    pub deleted_mentions: Vec<DeletedMention>,
+    pub changed_handles: ChangedHandles,
+}
+
+impl<S> Default for TrackedContent<S>
+where
+    S: UnicodeString,
+{
+    fn default() -> Self {
+        Self { nodes: Vec::new() }
+    }
+}
+
+impl<S> TrackedContent<S>
+where
+    S: UnicodeString,
+{
+    pub(crate) fn of(dom: &Dom<S>) -> Self {
+        Self::default().updated_for(dom)
+    }
+
+    /// The content of `dom`, reusing the nodes of this content which are
+    /// the same at its start and end.
+    pub(crate) fn updated_for(&self, dom: &Dom<S>) -> Self {
+        let children = dom.children();
+        let hashes: Vec<u64> =
+            children.iter().map(DomNode::content_hash).collect();
+        let (prefix, suffix) = common_ends(&self.hashes(), &hashes);
+        let changed_end = children.len() - suffix;
+        let nodes = self.nodes[..prefix]
+            .iter()
+            .cloned()
+            .chain(
+                children[prefix..changed_end]
+                    .iter()
+                    .zip(&hashes[prefix..changed_end])
+                    .map(|(node, hash)| Arc::new(TrackedNode::of(node, *hash))),
+            )
+            .chain(self.nodes[self.nodes.len() - suffix..].iter().cloned())
+            .collect();
+        Self { nodes }
+    }
+
+    /// The hash of each top-level node.
+    pub(crate) fn hashes(&self) -> Vec<u64> {
+        self.nodes.iter().map(|node| node.hash).collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Work out how the content changed from `old` to this.
+    pub(crate) fn changes_since(&self, old: &Self) -> ContentChanges {
+        let old_hashes = old.hashes();
+        let new_hashes = self.hashes();
+        let (prefix, suffix) = common_ends(&old_hashes, &new_hashes);
+        let changed_handles = ChangedHandles::between(&old_hashes, &new_hashes);
+        // The nodes at the end are laid out the same in both, but the last
+        // node of the start may be followed by a line break in only one of
+        // them if it is the last node of the other, so it is compared too
+        let first = prefix.saturating_sub(1);
+        let offset: usize =
+            (0..first).map(|i| self.node_len_with_separator(i)).sum();
+        let old_changed = old.units(first..old.len() - suffix);
+        let new_changed = self.units(first..self.len() - suffix);
+        let changed = OffsetMapping::between(&old_changed, &new_changed);
+        let offset_mapping = OffsetMapping {
+            start: offset + changed.start,
+            old_end: offset + changed.old_end,
+            new_end: offset + changed.new_end,
+        };
+        let deleted_mentions = if offset_mapping.is_identity() {
+            Vec::new()
+        } else {
+            deleted_mentions(
+                &old.mentions(first..old.len() - suffix, offset),
+                &self.mentions(first..self.len() - suffix, offset),
+                &offset_mapping,
+            )
+        };
+        ContentChanges {
+            offset_mapping,
+            deleted_mentions,
+            changed_handles,
+        }
+    }
+
+    fn has_separator_after(&self, index: usize) -> bool {
+        // A block is followed by a line break unless it is the last node
+        self.nodes[index].is_block && index + 1 < self.nodes.len()
+    }
+
+    fn node_len_with_separator(&self, index: usize) -> usize {
+        self.nodes[index].code_units.len()
+            + usize::from(self.has_separator_after(index))
+    }
+
+    /// The units of the nodes in `range`, laid out like [Location]s.
+    fn units(&self, range: std::ops::Range<usize>) -> Vec<Unit<'_, S>> {
+        let mut units = Vec::new();
+        for i in range {
+            let node = &self.nodes[i];
+            let start = units.len();
+            units.extend(node.code_units.iter().copied().map(Unit::Code));
+            for mention in &node.mentions {
+                units[start + usize::from(mention.location)] =
+                    Unit::Mention(mention);
+            }
+            if self.has_separator_after(i) {
+                units.push(Unit::Code(b'\n'.into()));
+            }
+        }
+        units
+    }
+
+    /// The mentions in the nodes in `range`, which start at `offset`.
+    fn mentions(
+        &self,
+        range: std::ops::Range<usize>,
+        mut offset: usize,
+    ) -> Vec<DeletedMention> {
+        let mut mentions = Vec::new();
+        for i in range {
+            mentions.extend(self.nodes[i].mentions.iter().map(|mention| {
+                DeletedMention {
+                    location: Location::from(
+                        offset + usize::from(mention.location),
+                    ),
+                    ..mention.clone()
+                }
+            }));
+            offset += self.node_len_with_separator(i);
+        }
+        mentions
+    }
+}
+
+/// A code unit of the content, which tells mentions apart although each
+/// is laid out as a single code unit, so that replacing a mention with one
+/// of someone else is reported as a change. Changing only the text of a
+/// mention is not.
+enum Unit<'a, S: UnicodeString> {
+    Code(S::CodeUnit),
+    Mention(&'a DeletedMention),
+}
+
+impl<S> PartialEq for Unit<'_, S>
+where
+    S: UnicodeString,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Unit::Code(a), Unit::Code(b)) => a == b,
+            (Unit::Mention(a), Unit::Mention(b)) => {
+                a.kind == b.kind && a.uri == b.uri
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<S> TrackedNode<S>
+where
+    S: UnicodeString,
+{
+    fn of(node: &DomNode<S>, hash: u64) -> Self {
+        let mut code_units = Vec::with_capacity(node.text_len());
+        let mut mentions = Vec::new();
+        // The nodes being walked, with the index of the next one, so that
+        // deep documents don't overflow the call stack
+        let mut stack = vec![(std::slice::from_ref(node), 0)];
+        while let Some((nodes, index)) = stack.last_mut() {
+            let nodes: &[DomNode<S>] = nodes;
+            let Some(child) = nodes.get(*index) else {
+                stack.pop();
+                // A block is followed by a line break unless it is the last
+                // child of its parent
+                if let Some((parent_children, next)) = stack.last() {
+                    let block = &parent_children[*next - 1];
+                    if block.is_block_node() && *next < parent_children.len() {
+                        code_units.push(b'\n'.into());
+                    }
+                }
+                continue;
+            };
+            *index += 1;
+            match child {
+                DomNode::Container(c) => stack.push((c.children(), 0)),
+                DomNode::Text(t) => {
+                    code_units.extend_from_slice(t.data().as_ref())
+                }
+                DomNode::LineBreak(_) => code_units.push(b'\n'.into()),
+                DomNode::Mention(m) => {
+                    mentions
+                        .push(DeletedMention::from_node(m, code_units.len()));
+                    code_units.push(0.into());
+                }
+            }
+        }
+        Self {
+            hash,
+            is_block: node.is_block_node(),
+            code_units,
+            mentions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_composer_model::cm;
+    use crate::{ComposerModel, Location, OffsetMapping};
+
+    use super::{TrackedContent, Unit};
+
+    fn location_text(model: &ComposerModel<Utf16String>) -> String {
+        let content = TrackedContent::of(&model.state.dom);
+        let units: Vec<u16> = content
+            .units(0..content.len())
+            .into_iter()
+            .map(|unit| match unit {
+                Unit::Code(unit) => unit,
+                Unit::Mention(_) => 0,
+            })
+            .collect();
+        assert_eq!(units.len(), model.state.dom.text_len());
+        String::from_utf16(&units).unwrap()
+    }
+
+    #[test]
+    fn code_units_match_locations() {
+        let model = cm("<p>ab</p><p>c<br />d|</p>");
+        assert_eq!(location_text(&model), "ab\nc\nd");
+    }
+
+    #[test]
+    fn code_units_of_nested_blocks_match_locations() {
+        let model = cm("<blockquote><p>a</p><ul><li>b</li><li>c</li></ul>\
+            </blockquote><p>d|</p>");
+        assert_eq!(location_text(&model), "a\nb\nc\nd");
+    }
+
+    #[test]
+    fn mentions_are_located_in_the_whole_content() {
+        let model = cm("<p>a</p><p>b<a href=\"https://matrix.to/#/@alice:matrix.org\">Alice</a>|</p>");
+        let content = TrackedContent::of(&model.state.dom);
+        let mentions = content.mentions(0..content.len(), 0);
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0].location, Location::from(3));
+    }
+
+    #[test]
+    fn unchanged_nodes_are_shared_with_the_previous_content() {
+        let mut model = cm("<p>a</p><p>b|</p><p>c</p>");
+        let before = TrackedContent::of(&model.state.dom);
+        model.replace_text(Utf16String::from("X"));
+        let after = before.updated_for(&model.state.dom);
+        assert!(std::sync::Arc::ptr_eq(&before.nodes[0], &after.nodes[0]));
+        assert!(!std::sync::Arc::ptr_eq(&before.nodes[1], &after.nodes[1]));
+        assert!(std::sync::Arc::ptr_eq(&before.nodes[2], &after.nodes[2]));
+    }
+
+    #[test]
+    fn changes_match_comparing_all_the_code_units() {
+        let examples = [
+            ("<p>a|</p>", "<p>a</p><p>|</p>"),
+            ("<p>a</p><p>|</p>", "<p>a|</p>"),
+            ("<p>ab|</p><p>c</p>", "<p>a|</p><p>c</p>"),
+            ("<p>a</p><p>b|</p>", "<p>a</p><p>b</p><p>|</p>"),
+            ("a|", "<p>a</p><p>|</p>"),
+            ("<p>a</p><p>a|</p>", "<p>a|</p>"),
+        ];
+        for (old, new) in examples {
+            let old = TrackedContent::of(&cm(old).state.dom);
+            let new = TrackedContent::of(&cm(new).state.dom);
+            let expected = OffsetMapping::between(
+                &old.units(0..old.len()),
+                &new.units(0..new.len()),
+            );
+            assert_eq!(new.changes_since(&old).offset_mapping, expected);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+//! How long the model took to make each update, reported in
+//! [crate::ComposerUpdate::timing] when the `timing` feature is enabled, so
+//! that clients can tell whether slow updates are spent in Rust or in
+//! rendering. Without the feature, nothing is timed.
+
+/// The time spent making an update, in microseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpdateTiming {
+    /// From the start of the operation until its HTML was written
+    pub operation_micros: u64,
+    /// Writing the HTML of the content for the update
+    pub serialization_micros: u64,
+}
+
+/// Measures the time since it was started.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Stopwatch {
+    #[cfg(feature = "timing")]
+    started: f64,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "timing")]
+            started: now_micros(),
+        }
+    }
+
+    /// The whole microseconds since the stopwatch was started, or None if
+    /// the `timing` feature is disabled.
+    pub(crate) fn elapsed_micros(&self) -> Option<u64> {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "timing")] {
+                Some((now_micros() - self.started).max(0.0) as u64)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl UpdateTiming {
+    /// The timing of an update whose operation was started with
+    /// `operation` and whose HTML was written while `serialization` ran,
+    /// stopping both now.
+    pub(crate) fn measure(
+        operation: &Stopwatch,
+        serialization: &Stopwatch,
+    ) -> Option<Self> {
+        let operation_micros = operation.elapsed_micros()?;
+        let serialization_micros = serialization.elapsed_micros()?;
+        Some(Self {
+            operation_micros: operation_micros
+                .saturating_sub(serialization_micros),
+            serialization_micros,
+        })
+    }
+}
+
+/// Microseconds from an arbitrary point which stays the same for the life
+/// of the process.
+#[cfg(feature = "timing")]
+fn now_micros() -> f64 {
+    cfg_if::cfg_if! {
+        if #[cfg(all(feature = "js", target_arch = "wasm32", not(feature = "headless")))] {
+            js::now() * 1000.0
+        } else {
+            use std::sync::OnceLock;
+            use std::time::Instant;
+
+            static START: OnceLock<Instant> = OnceLock::new();
+            START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1e6
+        }
+    }
+}
+
+#[cfg(all(
+    feature = "timing",
+    feature = "js",
+    target_arch = "wasm32",
+    not(feature = "headless")
+))]
+mod js {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        // std::time is not available in the browser
+        #[wasm_bindgen(js_namespace = performance)]
+        pub fn now() -> f64;
+    }
+}
+
+#[cfg(all(test, feature = "timing"))]
+mod test {
+    use widestring::Utf16String;
+
+    use crate::tests::testutils_conversion::utf16;
+    use crate::ComposerModel;
+
+    #[test]
+    fn updates_which_change_the_content_are_timed() {
+        let mut model = ComposerModel::<Utf16String>::new();
+        let update = model.replace_text(utf16("a"));
+        assert!(update.timing.is_some());
+        let update = model.select(0.into(), 1.into());
+        assert!(update.timing.is_none());
+    }
+}
@@ -0,0 +1,13 @@
+// Copyright 2025 New Vector Ltd.
+//
+// SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-Element-Commercial
+// Please see LICENSE in the repository root for full details.
+
+use wysiwyg::test_utils::{cm, tx};
+
+#[test]
+fn can_write_tests_in_the_example_format() {
+    let mut model = cm("f{o}|o");
+    model.bold();
+    assert_eq!(tx(&model), "f<strong>{o}|</strong>o");
+}
@@ -168,7 +168,7 @@ class RichTextEditor : LinearLayout {
             MenuAction.Keep -> {
                 // Do nothing
             }
-            MenuAction.None -> {
+            MenuAction.None, is MenuAction.UrlDetected -> {
                 suggestionAdapter.clear()
             }
             is MenuAction.Suggestion -> {
@@ -178,6 +178,9 @@ internal class EditorViewModel(
                 val selection = textUpdate.startUtf16Codeunit.toInt()..textUpdate.endUtf16Codeunit.toInt()
                 ComposerResult.SelectionUpdated(selection = selection)
             }
+            // Only sent when range updates are enabled in the config, which
+            // this view model doesn't do.
+            is TextUpdate.ReplaceRange,
             is TextUpdate.Keep,
             null -> null
         }
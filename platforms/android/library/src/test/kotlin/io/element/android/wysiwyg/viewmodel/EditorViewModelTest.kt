@@ -223,7 +223,7 @@ internal class EditorViewModelTest {
 
     @Test
     fun `given internal edit link action, when get, it returns the right action`() {
-        composer.givenLinkAction(ComposerLinkAction.Edit(linkUrl))
+        composer.givenLinkAction(ComposerLinkAction.Edit(linkUrl, emptyList()))
 
         assertThat(
             viewModel.getLinkAction(), equalTo(
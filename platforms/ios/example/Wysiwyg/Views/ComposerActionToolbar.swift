@@ -62,7 +62,7 @@ struct ComposerActionToolbar: View {
             }
             actions.append(createWithTextAction(doubleTextAction: doubleTextAction))
             return AlertConfig(title: createLinkTitle, actions: actions)
-        case let .edit(url):
+        case let .edit(url, _):
             let editLinktitle = "Edit Link URL"
             actions.append(editTextAction(singleTextAction: singleTextAction, url: url))
             let removeAction = {
@@ -145,7 +145,7 @@ final class ComposerModelWrapper: ComposerModelWrapperProtocol {
     }
 
     func toTree() -> String {
-        model.toTree()
+        model.toTree(maxDepth: nil)
     }
 
     func getCurrentDomState() -> ComposerState {
@@ -238,7 +238,7 @@ public extension WysiwygComposerViewModel {
 
     /// Returns a textual representation of the composer model as a tree.
     func treeRepresentation() -> String {
-        model.toTree()
+        model.toTree(maxDepth: nil)
     }
 
     /// Set a mention with given pattern. Usually used
@@ -566,6 +566,10 @@ private extension WysiwygComposerViewModel {
         case let .select(startUtf16Codeunit: start,
                          endUtf16Codeunit: end):
             applySelect(start: start, end: end)
+        case .replaceRange:
+            // Only sent when range updates are enabled in the config, which
+            // this view model doesn't do.
+            break
         case .keep:
             break
         }
@@ -580,7 +584,7 @@ private extension WysiwygComposerViewModel {
         switch update.menuAction() {
         case .keep:
             break
-        case .none:
+        case .none, .urlDetected:
             suggestionPattern = nil
         case let .suggestion(suggestionPattern: pattern):
             suggestionPattern = pattern
@@ -26,7 +26,7 @@ extension WysiwygComposerTests {
         let url = "test_url"
         ComposerModelWrapper()
             .action { $0.setLinkWithText(url: url, text: "test", attributes: []) }
-            .assertLinkAction(.edit(url: "https://\(url)"))
+            .assertLinkAction(.edit(url: "https://\(url)", attributes: []))
     }
 
     @Test func setLinkWithText() {